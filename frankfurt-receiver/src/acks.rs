@@ -0,0 +1,68 @@
+// Acknowledgements of every Nth event back to the forwarder (--ack-every)
+//
+// Each goes out as one UDP datagram to the forwarder host's --ack-port, from
+// the processing loop. The socket is non-blocking: an acknowledgement that
+// cannot be sent straight away is dropped rather than delaying processing.
+
+use crate::ingest::epoch_nanos;
+use shared::EventAck;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use tracing::{debug, info};
+
+pub struct Acker {
+    socket: UdpSocket,
+    every: u64,
+    port: u16,
+    sent: u64,
+    failed: u64,
+}
+
+impl Acker {
+    pub fn new(every: u64, port: u16) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        info!(every, port, "acknowledging events to the forwarder");
+        Ok(Self {
+            socket,
+            every,
+            port,
+            sent: 0,
+            failed: 0,
+        })
+    }
+
+    /// Acknowledge event `sequence_id` from `peer` if it is one of every Nth.
+    /// `sent` is the forwarder's send time, `received` when it arrived here.
+    pub fn event(&mut self, sequence_id: u64, peer: Option<IpAddr>, sent: i64, received: i64) {
+        let Some(peer) = peer.filter(|_| sequence_id.is_multiple_of(self.every)) else {
+            return;
+        };
+        let ack = EventAck {
+            ack: sequence_id,
+            sent,
+            received,
+            acked: epoch_nanos(),
+        };
+        match self
+            .socket
+            .send_to(ack.to_json().as_bytes(), SocketAddr::new(peer, self.port))
+        {
+            Ok(_) => self.sent += 1,
+            Err(e) => {
+                if self.failed == 0 {
+                    debug!(%peer, error = %e, "failed to send acknowledgement");
+                }
+                self.failed += 1;
+            }
+        }
+    }
+
+    /// Log how many acknowledgements went out
+    pub fn finish(self) {
+        info!(
+            sent = self.sent,
+            failed = self.failed,
+            "acknowledgements sent"
+        );
+    }
+}
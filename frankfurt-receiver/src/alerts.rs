@@ -0,0 +1,112 @@
+// Alarm notifications (--alert-p99-ms, --alert-loss-pct, --alert-webhook)
+//
+// The collector decides when an alarm fires or resolves; here each one is
+// logged and, with a webhook, posted as Slack-compatible JSON ({"text": ...}).
+// Posting happens on a background task so a slow webhook never delays
+// measuring; messages that cannot be queued are dropped with a warning.
+
+use crate::metadata::RunContext;
+use latency_core::{Alert, Collector};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+const QUEUE_MESSAGES: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5); // For alarms of the last second
+
+pub struct Alerts {
+    region: String,
+    context: RunContext, // For the run ID, which a backbone run may only learn later
+    webhook: Option<(mpsc::Sender<String>, JoinHandle<()>)>,
+}
+
+impl Alerts {
+    /// Start posting to `webhook`, if given
+    pub fn start(region: &str, webhook: Option<&str>, context: RunContext) -> Self {
+        let webhook = webhook.map(|url| {
+            info!("posting alerts to the webhook");
+            let (tx, rx) = mpsc::channel(QUEUE_MESSAGES);
+            (tx, tokio::spawn(post_messages(url.to_string(), rx)))
+        });
+        Self {
+            region: region.to_string(),
+            context,
+            webhook,
+        }
+    }
+
+    /// Log and post the alarms the collector raised since the previous call
+    pub fn notify(&mut self, collector: &mut Collector) {
+        for alert in collector.new_alerts() {
+            if alert.firing {
+                warn!(elapsed_secs = alert.elapsed_secs, "alert: {}", alert);
+            } else {
+                info!(
+                    elapsed_secs = alert.elapsed_secs,
+                    "alert resolved: {}", alert
+                );
+            }
+            if let Some((tx, _)) = &self.webhook {
+                match tx.try_send(self.message(alert)) {
+                    Ok(()) | Err(TrySendError::Closed(_)) => {}
+                    Err(TrySendError::Full(_)) => warn!("alert webhook is falling behind"),
+                }
+            }
+        }
+    }
+
+    /// Give alarms still being posted a moment to go out
+    pub async fn finish(self) {
+        if let Some((tx, poster)) = self.webhook {
+            drop(tx);
+            if tokio::time::timeout(FINISH_TIMEOUT, poster).await.is_err() {
+                warn!("alert webhook did not finish in time");
+            }
+        }
+    }
+
+    fn message(&self, alert: &Alert) -> String {
+        let mut receiver = format!("receiver in {}", self.region);
+        if let Some(run_id) = self.context.run_id() {
+            receiver += &format!(", run {}", run_id);
+        }
+        let text = format!(
+            "{} ({}, {} s into the run): {}",
+            if alert.firing {
+                ":warning: Latency alert"
+            } else {
+                ":white_check_mark: Resolved"
+            },
+            receiver,
+            alert.elapsed_secs,
+            alert
+        );
+        json!({ "text": text }).to_string()
+    }
+}
+
+async fn post_messages(url: String, mut rx: mpsc::Receiver<String>) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(error = %e, "alert webhook unavailable");
+            return;
+        }
+    };
+    while let Some(body) = rx.recv().await {
+        let response = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = response {
+            // Without the URL, which is a secret for Slack
+            warn!(error = %e.without_url(), "failed to post alert");
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Continuous (daemon) mode: rotated raw CSV files and periodic rolling results
+
+use chrono::Utc;
+use latency_core::{CsvWriter, ExperimentResults, CSV_HEADER};
+use shared::{LatencyMeasurement, RotatingFile, RotationPolicy};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Output state for a run that never stops on its own
+pub struct Continuous {
+    results_dir: PathBuf,
+    emit_interval: Duration,
+    last_emit: Instant,
+    csv: CsvWriter<RotatingFile>,
+}
+
+impl Continuous {
+    /// Create the results directory and open the first CSV file
+    pub fn start(
+        results_dir: &str,
+        emit_interval: Duration,
+        rotation: RotationPolicy,
+    ) -> Result<Self, std::io::Error> {
+        let results_dir = PathBuf::from(results_dir);
+        std::fs::create_dir_all(&results_dir)?;
+        let file = RotatingFile::create(
+            results_dir.join("measurements.csv"),
+            rotation,
+            CSV_HEADER.as_bytes(),
+        )?;
+        info!(path = %file.path().display(), "writing raw measurements");
+
+        Ok(Self {
+            results_dir,
+            emit_interval,
+            last_emit: Instant::now(),
+            csv: CsvWriter::from_writer(file),
+        })
+    }
+
+    /// Append a measurement to the current CSV file, rotating it when due
+    pub fn write(&mut self, measurement: &LatencyMeasurement) {
+        if let Err(e) = self.csv.get_mut().rotate_if_due() {
+            warn!(error = %e, "failed to rotate CSV file");
+        }
+        if let Err(e) = self.csv.write(measurement) {
+            warn!(error = %e, "failed to write CSV row");
+        }
+    }
+
+    /// Close the current CSV file and start a new one, returning its path
+    pub fn rotate(&mut self) -> Result<&Path, std::io::Error> {
+        self.csv.get_mut().rotate()
+    }
+
+    /// Flush buffered CSV rows to disk
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.csv.flush()
+    }
+
+    /// Time left until the next rolling results file is due
+    pub fn until_emit(&self) -> Duration {
+        self.emit_interval.saturating_sub(self.last_emit.elapsed())
+    }
+
+    /// Write rolling results if the emit interval has passed. Each snapshot is
+    /// kept as a timestamped file and also copied to `latest.json`. Returns
+    /// whether results were due.
+    pub fn emit_if_due(&mut self, results: impl FnOnce() -> ExperimentResults) -> bool {
+        if self.last_emit.elapsed() < self.emit_interval {
+            return false;
+        }
+        self.last_emit = Instant::now();
+
+        let results = results();
+        let written = serde_json::to_string_pretty(&results)
+            .map_err(std::io::Error::from)
+            .and_then(|json| {
+                let path = self
+                    .results_dir
+                    .join(format!("results-{}.json", timestamp()));
+                std::fs::write(&path, &json)?;
+                std::fs::write(self.results_dir.join("latest.json"), &json)?;
+                self.csv.flush()?;
+                Ok(path)
+            });
+
+        match written {
+            Ok(path) => info!(
+                samples = results.sample_count,
+                median_ms = results.median_latency_ms,
+                path = %path.display(),
+                "rolling results written"
+            ),
+            Err(e) => warn!(error = %e, "failed to write rolling results"),
+        }
+        true
+    }
+
+    /// Flush (and, if configured, compress) the current CSV file
+    pub fn finish(self) -> Result<(), std::io::Error> {
+        self.csv.into_inner().finish()
+    }
+}
+
+/// UTC timestamp used in output file names
+fn timestamp() -> String {
+    Utc::now().format("%Y%m%dT%H%M%SZ").to_string()
+}
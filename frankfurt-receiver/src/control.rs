@@ -0,0 +1,177 @@
+// Control channel for runtime commands (--control-addr)
+//
+// Accepts one command per line (`echo status | nc 127.0.0.1 7070`) or a plain
+// HTTP request whose path is the command (`curl 127.0.0.1:7070/status`).
+// Arguments follow the command after a space or, in a path, a slash
+// (`subscribe ethusdt@aggTrade`, `/subscribe/ethusdt@aggTrade`). Every
+// reply is one JSON object: {"ok": true, "result": ...} or {"ok": false, "error": ...}.
+// Commands are answered by the collection loop, between events.
+
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, info_span, warn, Instrument};
+
+/// Something an operator can ask a running receiver to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Status,                   // Progress and summary statistics so far
+    Flush,                    // Flush buffered output files to disk
+    RotateCsv,                // Start a new raw CSV file (continuous mode)
+    StopAndReport,            // End collection now and write results
+    Subscribe(Vec<String>),   // Add exchange streams (baseline mode with --subscribe)
+    Unsubscribe(Vec<String>), // Drop exchange streams (baseline mode with --subscribe)
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, argument) = match s.split_once([' ', '/']) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (s, ""),
+        };
+        // Streams, comma-separated
+        let streams = || -> Vec<String> {
+            argument
+                .split(',')
+                .map(str::trim)
+                .filter(|stream| !stream.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        match (name, argument) {
+            ("status", "") => Ok(Command::Status),
+            ("flush", "") => Ok(Command::Flush),
+            ("rotate-csv", "") => Ok(Command::RotateCsv),
+            ("stop-and-report", "") => Ok(Command::StopAndReport),
+            ("subscribe", _) => Ok(Command::Subscribe(streams())),
+            ("unsubscribe", _) => Ok(Command::Unsubscribe(streams())),
+            _ => Err(format!(
+                "unknown command: {} (expected status, flush, rotate-csv, stop-and-report, subscribe STREAMS or unsubscribe STREAMS)",
+                s
+            )),
+        }
+    }
+}
+
+/// A command waiting for the collection loop to answer it
+pub struct Request {
+    pub command: Command,
+    reply: oneshot::Sender<Result<Value, String>>,
+}
+
+impl Request {
+    pub fn reply(self, result: Result<Value, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Receiving end of the control channel, polled by the collection loop
+pub struct Control {
+    rx: mpsc::Receiver<Request>,
+}
+
+impl Control {
+    /// Listen for control connections on `addr`
+    pub async fn start(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        info!(addr = %listener.local_addr()?, "control channel listening");
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(accept(listener, tx));
+        Ok(Self { rx })
+    }
+}
+
+/// Next request, or never if the control channel is disabled
+pub async fn next(control: &mut Option<Control>) -> Request {
+    match control {
+        Some(control) => match control.rx.recv().await {
+            Some(request) => request,
+            None => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+async fn accept(listener: TcpListener, tx: mpsc::Sender<Request>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let span = info_span!("control", %peer);
+                let tx = tx.clone();
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = serve(stream, tx).await {
+                            debug!(error = %e, "control connection failed");
+                        }
+                    }
+                    .instrument(span),
+                );
+            }
+            Err(e) => warn!(error = %e, "failed to accept control connection"),
+        }
+    }
+}
+
+async fn serve(stream: TcpStream, tx: mpsc::Sender<Request>) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // "GET /status HTTP/1.1": answer once, after skipping the headers
+        if let Some(path) = http_path(line) {
+            let command = path.trim_start_matches('/').to_string();
+            while let Some(header) = lines.next_line().await? {
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+            let (status, body) = match dispatch(&tx, &command).await {
+                Ok(result) => ("200 OK", json!({ "ok": true, "result": result })),
+                Err(e) => ("400 Bad Request", json!({ "ok": false, "error": e })),
+            };
+            let body = body.to_string();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            return write.write_all(response.as_bytes()).await;
+        }
+
+        let reply = match dispatch(&tx, line).await {
+            Ok(result) => json!({ "ok": true, "result": result }),
+            Err(e) => json!({ "ok": false, "error": e }),
+        };
+        write.write_all(format!("{}\n", reply).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Path of an HTTP request line, e.g. "/status" from "GET /status HTTP/1.1"
+fn http_path(line: &str) -> Option<&str> {
+    let mut parts = line.split_whitespace();
+    let (_method, path, version) = (parts.next()?, parts.next()?, parts.next()?);
+    (version.starts_with("HTTP/") && path.starts_with('/')).then_some(path)
+}
+
+async fn dispatch(tx: &mpsc::Sender<Request>, line: &str) -> Result<Value, String> {
+    let command: Command = line.parse()?;
+    info!(?command, "control command");
+    let (reply, response) = oneshot::channel();
+    tx.send(Request { command, reply })
+        .await
+        .map_err(|_| "the run is finishing".to_string())?;
+    response
+        .await
+        .map_err(|_| "the run is finishing".to_string())?
+}
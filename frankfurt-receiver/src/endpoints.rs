@@ -0,0 +1,310 @@
+// Baseline mode against several exchange endpoints at once
+//
+// Every endpoint streams the same symbol over its own connection. Frames are
+// timestamped by the connection's task, queued, and measured in one collector,
+// tagged with the index of the endpoint they came from.
+//
+// With --ws-connections the endpoints are N connections to the same stream.
+// Every event then arrives once per connection, so copies are matched by
+// their update ID and only the first is measured, tagged with the connection
+// that won.
+
+use crate::alerts::Alerts;
+use crate::control;
+use crate::ingest::{self, epoch_nanos, QueueSender};
+use crate::metadata::RunContext;
+use crate::progress::Progress;
+use crate::server_time::ServerTimeMonitor;
+use crate::{
+    emit_continuous, finish_timeseries, handle_control, log_spikes, new_collector, open_timeseries,
+    print_collecting, record_quote, start_continuous, start_control, start_sinks, stream_latest,
+    write_report, write_second, ws_url, Args,
+};
+use futures_util::{SinkExt, StreamExt};
+use latency_core::{rank_endpoints, Arrival, Market, PathRace, ServerTimeCorrection, StreamNames};
+use shared::{
+    tcp_peer, Backoff, ExchangeAdapter, ExchangeAddresses, ExperimentError, LatencyMeasurement,
+    ReconnectPolicy, Shutdown,
+};
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{debug, info, info_span, warn, Instrument};
+
+/// Something that happened on one endpoint connection
+enum EndpointEvent {
+    Frame {
+        endpoint: u16,
+        receive_time: i64, // Epoch nanos
+        text: String,
+    },
+    Disconnected,
+}
+
+pub async fn run(
+    args: &Args,
+    context: &RunContext,
+    adapter: &dyn ExchangeAdapter,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    let redundant = args.ws_connections > 1;
+    let urls = if redundant {
+        vec![ws_url(args, adapter); args.ws_connections]
+    } else {
+        args.endpoints.clone()
+    };
+    let labels: Vec<String> = (0..urls.len()).map(|i| format!("ws{}", i)).collect();
+    let mut race = redundant.then(|| PathRace::new(&labels));
+    let mut events_without_id = 0u64;
+
+    let (tx, mut rx) = ingest::queue(args.queue_capacity);
+    let addresses = ExchangeAddresses::default();
+    for (index, url) in urls.iter().enumerate() {
+        let span = info_span!("endpoint", endpoint = index, %url);
+        tokio::spawn(
+            stream_endpoint(
+                index as u16,
+                url.clone(),
+                adapter.subscribe_message(&args.symbol),
+                args.reconnect_policy(),
+                addresses.clone(),
+                tx.clone(),
+            )
+            .instrument(span),
+        );
+    }
+    drop(tx);
+
+    let mut collector = new_collector(args);
+    let mut exchange_clock = ServerTimeCorrection::new(args.correct_server_time);
+    let mut timeseries = open_timeseries(args)?;
+    let mut continuous = start_continuous(args)?;
+    let mut sinks = start_sinks(args, "baseline")?;
+    let mut alerts = Alerts::start(
+        &args.region_name,
+        args.alert_webhook.as_deref(),
+        context.clone(),
+    );
+    // Spot and futures URLs can be compared in the same run
+    let markets: Vec<Option<Market>> = urls.iter().map(|url| Market::from_url(url)).collect();
+    let connections = if redundant {
+        format!("{} connections", urls.len())
+    } else {
+        format!("{} endpoints", urls.len())
+    };
+    let mut streams = StreamNames::default();
+    let mut symbols = StreamNames::default(); // With --split-by-symbol
+    let mut sequence_id = 0u64;
+    let mut parse_failures = 0u64;
+    let mut reconnects = 0usize;
+    let mut control = start_control(args).await?;
+    let duration = args.run_duration();
+
+    print_collecting(args);
+    let mut progress = Progress::start(args, "baseline")?;
+    progress.status(connections.clone(), &collector);
+
+    loop {
+        let elapsed = collector.elapsed();
+        if elapsed >= duration {
+            info!("duration reached, stopping collection");
+            break;
+        }
+        progress.tick(&collector);
+        let wait = emit_continuous(&mut continuous, &mut collector, args, context, "baseline")
+            .min(progress.refresh_in())
+            .min(duration - elapsed);
+
+        let next = tokio::select! {
+            next = timeout(wait, rx.recv()) => next,
+            request = control::next(&mut control) => {
+                if handle_control(request, args, context, "baseline", &collector, &mut timeseries, &mut continuous) {
+                    break;
+                }
+                continue;
+            }
+            _ = shutdown.wait() => {
+                info!("stopping collection early, writing partial results");
+                break;
+            }
+        };
+
+        let (endpoint, receive_time, text) = match next {
+            Ok(Some(EndpointEvent::Frame {
+                endpoint,
+                receive_time,
+                text,
+            })) => (endpoint, receive_time, text),
+            Ok(Some(EndpointEvent::Disconnected)) => {
+                reconnects += 1;
+                progress.status(
+                    format!("{}, {} reconnects", connections, reconnects),
+                    &collector,
+                );
+                continue;
+            }
+            Ok(None) => break,
+            Err(_) => continue, // Rolling results due; the loop checks the duration
+        };
+
+        let event = match adapter.parse(&text) {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(e) => {
+                if parse_failures < 5 {
+                    warn!(endpoint, error = %e, "failed to parse message");
+                } else {
+                    debug!(endpoint, error = %e, "failed to parse message");
+                }
+                parse_failures += 1;
+                continue;
+            }
+        };
+
+        // Only the first copy of each event is measured
+        if let Some(race) = &mut race {
+            let Some(update_id) = event.update_id else {
+                if events_without_id == 0 {
+                    warn!("skipping events without an update ID");
+                }
+                events_without_id += 1;
+                continue;
+            };
+            let label = &labels[usize::from(endpoint)];
+            if race.record_arrival(update_id as u64, label, receive_time) == Arrival::Duplicate {
+                continue;
+            }
+        }
+
+        if args.market_stats {
+            record_quote(&mut collector, &event);
+        }
+
+        let Some(event_time) = event.event_time else {
+            continue;
+        };
+
+        let mut measurement =
+            LatencyMeasurement::new_baseline(sequence_id, event_time, receive_time)
+                .with_endpoint(endpoint)
+                .with_frame_bytes(text.len() as u32);
+        if let Some(transaction_time) = event.transaction_time {
+            measurement = measurement.with_transaction_time(transaction_time);
+        }
+        if let Some(market) = markets[usize::from(endpoint)] {
+            measurement = measurement.with_market(market);
+        }
+        if let Some(stream) = event.stream {
+            measurement = measurement.with_stream(streams.intern(stream));
+        }
+        if args.split_by_symbol {
+            measurement = measurement.with_symbol(symbols.intern(&event.symbol));
+        }
+        exchange_clock.record(
+            &mut measurement,
+            context.server_time().and_then(ServerTimeMonitor::latest),
+        );
+        sequence_id += 1;
+
+        let second = collector.record(measurement);
+        stream_latest(&collector, &mut continuous, &mut sinks);
+        log_spikes(&mut collector);
+        alerts.notify(&mut collector);
+        if let Some(second) = second {
+            write_second(&mut timeseries, &second);
+            progress.second(&second, &collector);
+        }
+    }
+
+    drop(progress);
+    info!(measurements = collector.len(), "collection complete");
+    finish_timeseries(args, timeseries, &mut collector)?;
+    if let Some(continuous) = continuous {
+        continuous.finish()?;
+    }
+    alerts.notify(&mut collector);
+    alerts.finish().await;
+
+    let mut report = collector.finish("baseline");
+    report.results.region = Some(args.region_name.clone());
+    report.results.exchange = Some(adapter.name().to_string());
+    report.results.reconnects = reconnects;
+    match race {
+        Some(race) => report.results.path_race = Some(race.results()),
+        None => report.results.endpoints = Some(rank_endpoints(&urls, &report.measurements)),
+    }
+    report.results.receive_queue = Some(rx.stats());
+    report.results.exchange_addresses =
+        Some(addresses.snapshot()).filter(|addresses| !addresses.is_empty());
+    report.results.server_time = context
+        .server_time()
+        .and_then(|server_time| server_time.results(&exchange_clock));
+    write_report(args, context, &mut report, sinks).await?;
+
+    Ok(())
+}
+
+/// Stream one endpoint into `tx`, reconnecting with backoff until the run ends
+/// or the policy's attempt limit is reached
+async fn stream_endpoint(
+    endpoint: u16,
+    url: String,
+    subscribe: Option<String>,
+    reconnect: ReconnectPolicy,
+    addresses: ExchangeAddresses,
+    tx: QueueSender<EndpointEvent>,
+) {
+    let mut backoff = Backoff::new(reconnect);
+    loop {
+        match connect_async(url.as_str()).await {
+            Ok((mut ws_stream, _)) => {
+                info!("connected");
+                if let Some(peer) = tcp_peer(ws_stream.get_ref()) {
+                    addresses.record_peer(peer, Some(endpoint));
+                }
+                backoff.succeeded();
+                if let Some(subscribe) = &subscribe {
+                    if let Err(e) = ws_stream.send(Message::Text(subscribe.clone())).await {
+                        warn!(error = %e, "subscribe failed");
+                    }
+                }
+
+                while let Some(message) = ws_stream.next().await {
+                    // Timestamp before handing the frame to the measuring loop
+                    let receive_time = epoch_nanos();
+                    match message {
+                        Ok(Message::Text(text)) => {
+                            let frame = EndpointEvent::Frame {
+                                endpoint,
+                                receive_time,
+                                text,
+                            };
+                            if tx.send(frame).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => {}
+                        Err(e) => {
+                            warn!(error = %e, "WebSocket error");
+                            break;
+                        }
+                    }
+                }
+                warn!("disconnected, reconnecting");
+                if tx.send(EndpointEvent::Disconnected).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => warn!(error = %e, "connection failed"),
+        }
+
+        let Some(delay) = backoff.next_delay() else {
+            warn!(reconnects = %backoff.stats(), "reconnect attempts exhausted, giving up");
+            return;
+        };
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = tx.closed() => return,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// Hot receive path
+//
+// Receive tasks do nothing but read, timestamp and push onto a bounded queue;
+// parsing, statistics and file writing happen in the processing loop at the
+// other end. Slow processing then only shows up as queue depth (and, once the
+// queue is full, as time the receive task spends waiting) instead of silently
+// delaying the next read.
+
+use crate::{kernel_ts, ExchangeStream};
+use futures_util::stream::SplitStream;
+use futures_util::StreamExt;
+use latency_core::{QueueMonitor, ReceiveQueueStats};
+use shared::{BufferPool, ForwardedEventView, PooledBuffer};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{self, error::SendError, error::TrySendError};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::error;
+
+/// Create a bounded queue whose senders record backpressure
+pub fn queue<T>(capacity: usize) -> (QueueSender<T>, QueueReceiver<T>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let monitor = Arc::new(QueueMonitor::new());
+    let sender = QueueSender {
+        tx,
+        monitor: monitor.clone(),
+    };
+    (sender, QueueReceiver { rx, monitor })
+}
+
+pub struct QueueSender<T> {
+    tx: mpsc::Sender<T>,
+    monitor: Arc<QueueMonitor>,
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            monitor: self.monitor.clone(),
+        }
+    }
+}
+
+impl<T> QueueSender<T> {
+    /// Push `item`, waiting for space if processing has fallen behind.
+    /// Fails once processing has stopped.
+    pub async fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let blocked = match self.tx.try_send(item) {
+            Ok(()) => None,
+            Err(TrySendError::Full(item)) => {
+                let start = Instant::now();
+                self.tx.send(item).await?;
+                Some(start.elapsed())
+            }
+            Err(TrySendError::Closed(item)) => return Err(SendError(item)),
+        };
+        let depth = self.tx.max_capacity() - self.tx.capacity();
+        self.monitor.record(depth, blocked);
+        Ok(())
+    }
+
+    /// Resolves once processing has stopped
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+}
+
+pub struct QueueReceiver<T> {
+    rx: mpsc::Receiver<T>,
+    monitor: Arc<QueueMonitor>,
+}
+
+impl<T> QueueReceiver<T> {
+    pub async fn recv(&mut self) -> Option<T> {
+        self.rx.recv().await
+    }
+
+    pub fn stats(&self) -> ReceiveQueueStats {
+        self.monitor.results(self.rx.max_capacity())
+    }
+}
+
+/// Where receive tasks push forwarded frames
+#[derive(Clone)]
+pub enum FrameSender {
+    Queue(QueueSender<Forwarded>), // One queue, e.g. a UDP socket's own shard
+    Shards(Arc<[QueueSender<Forwarded>]>), // The decode shard of each frame's stream (--shards)
+}
+
+impl FrameSender {
+    /// Push `frame` like `QueueSender::send`
+    pub async fn send(&self, frame: Forwarded) -> Result<(), SendError<Forwarded>> {
+        match self {
+            Self::Queue(tx) => tx.send(frame).await,
+            Self::Shards(shards) => {
+                let shard = ForwardedEventView::stream_shard(&frame.data, shards.len());
+                shards[shard].send(frame).await
+            }
+        }
+    }
+}
+
+/// One forwarded event as received on a backbone path
+#[derive(Debug)]
+pub struct Forwarded {
+    pub path: &'static str,
+    pub frankfurt_receive_time: i64,      // Epoch nanos
+    pub kernel_receive_time: Option<i64>, // Epoch nanos (UDP with SO_TIMESTAMPING)
+    pub peer: Option<IpAddr>,             // The forwarder's address, for --multi-forwarder
+    pub data: PooledBuffer,
+}
+
+/// Something that happened on the exchange WebSocket in baseline mode
+pub enum ExchangeFrame {
+    Text {
+        frankfurt_receive_time: i64, // Epoch nanos
+        text: String,
+    },
+    Pong {
+        frankfurt_receive_time: i64, // Epoch nanos
+        payload: Vec<u8>,
+    },
+    Closed(Option<tungstenite::Error>), // The connection ended, with the error if any
+}
+
+/// Receive datagrams until the socket fails or processing stops
+pub async fn udp(socket: UdpSocket, kernel_timestamps: bool, tx: FrameSender, pool: BufferPool) {
+    let mut buf = vec![0u8; 65536]; // Max UDP packet size
+    loop {
+        let received = if kernel_timestamps {
+            kernel_ts::recv(&socket, &mut buf).await
+        } else {
+            socket
+                .recv_from(&mut buf)
+                .await
+                .map(|(len, addr)| (len, None, Some(addr.ip())))
+        };
+        let (len, kernel_receive_time, peer) = match received {
+            Ok(received) => received,
+            Err(e) => {
+                error!(error = %e, "UDP recv error");
+                return;
+            }
+        };
+        // Record arrival timestamp immediately
+        let frankfurt_receive_time = epoch_nanos();
+
+        let mut data = pool.get();
+        data.extend_from_slice(&buf[..len]);
+        let forwarded = Forwarded {
+            path: "udp",
+            frankfurt_receive_time,
+            kernel_receive_time,
+            peer,
+            data,
+        };
+        if tx.send(forwarded).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Read the exchange WebSocket until it closes or processing stops. The end
+/// of the connection is reported as `ExchangeFrame::Closed`. Frames are
+/// tagged with `conn`, so those of a standby connection can be told apart.
+pub async fn exchange(
+    mut read: SplitStream<ExchangeStream>,
+    conn: u64,
+    tx: QueueSender<(u64, ExchangeFrame)>,
+) {
+    let closed = loop {
+        match read.next().await {
+            Some(Ok(message)) => {
+                // Record timestamp immediately upon receiving message
+                let frankfurt_receive_time = epoch_nanos();
+                let frame = match message {
+                    Message::Text(text) => ExchangeFrame::Text {
+                        frankfurt_receive_time,
+                        text,
+                    },
+                    Message::Pong(payload) => ExchangeFrame::Pong {
+                        frankfurt_receive_time,
+                        payload,
+                    },
+                    _ => continue,
+                };
+                if tx.send((conn, frame)).await.is_err() {
+                    return;
+                }
+            }
+            Some(Err(e)) => break Some(e),
+            None => break None,
+        }
+    };
+    let _ = tx.send((conn, ExchangeFrame::Closed(closed))).await;
+}
+
+pub fn epoch_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64
+}
@@ -0,0 +1,141 @@
+// Kernel receive timestamps for UDP via SO_TIMESTAMPING (Linux only)
+//
+// Only software (kernel) timestamps are requested: they are taken from
+// CLOCK_REALTIME and so are directly comparable with SystemTime::now(),
+// unlike raw NIC hardware timestamps.
+
+use std::net::IpAddr;
+use tokio::net::UdpSocket;
+
+/// Ask the kernel to timestamp every datagram received on `socket`
+#[cfg(target_os = "linux")]
+pub fn enable(socket: &UdpSocket) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let flags: libc::c_uint = libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_SOFTWARE;
+
+    // SAFETY: the fd is a valid socket owned by `socket`, and `flags` outlives the call
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_TIMESTAMPING,
+            &flags as *const libc::c_uint as *const libc::c_void,
+            std::mem::size_of_val(&flags) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enable(_socket: &UdpSocket) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "kernel timestamps require Linux SO_TIMESTAMPING",
+    ))
+}
+
+/// Receive one datagram along with its kernel receive timestamp (epoch nanos)
+/// and the sender's address
+#[cfg(target_os = "linux")]
+pub async fn recv(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+) -> std::io::Result<(usize, Option<i64>, Option<IpAddr>)> {
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+
+    let fd = socket.as_raw_fd();
+    socket
+        .async_io(Interest::READABLE, || recvmsg_with_timestamp(fd, buf))
+        .await
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn recv(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+) -> std::io::Result<(usize, Option<i64>, Option<IpAddr>)> {
+    socket
+        .recv_from(buf)
+        .await
+        .map(|(len, addr)| (len, None, Some(addr.ip())))
+}
+
+#[cfg(target_os = "linux")]
+fn recvmsg_with_timestamp(
+    fd: std::os::fd::RawFd,
+    buf: &mut [u8],
+) -> std::io::Result<(usize, Option<i64>, Option<IpAddr>)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // Room for an SCM_TIMESTAMPING message (three timespecs), aligned for cmsghdr
+    let mut control = [0u64; 16];
+    // SAFETY: sockaddr_storage is plain old data
+    let mut name: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+
+    // SAFETY: msghdr is plain old data; all pointers set below outlive recvmsg
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_name = &mut name as *mut libc::sockaddr_storage as *mut libc::c_void;
+    msg.msg_namelen = std::mem::size_of_val(&name) as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+    // SAFETY: fd is a valid non-blocking socket and msg points at live buffers
+    let len = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+    if len < 0 {
+        // WouldBlock is handled by async_io, which waits for readiness again
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut kernel_time = None;
+    // SAFETY: the CMSG_* macros walk the control buffer filled in by recvmsg
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_TIMESTAMPING
+            {
+                // [software, deprecated, raw hardware]; only software is requested
+                let stamps =
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]);
+                let software = stamps[0];
+                if software.tv_sec != 0 || software.tv_nsec != 0 {
+                    kernel_time =
+                        Some(software.tv_sec as i64 * 1_000_000_000 + software.tv_nsec as i64);
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((len as usize, kernel_time, sender_ip(&name)))
+}
+
+/// The IP address recvmsg wrote into `name`
+#[cfg(target_os = "linux")]
+fn sender_ip(name: &libc::sockaddr_storage) -> Option<IpAddr> {
+    // SAFETY: ss_family tells which sockaddr type the storage holds, and
+    // sockaddr_storage is large and aligned enough for either
+    unsafe {
+        match name.ss_family as libc::c_int {
+            libc::AF_INET => {
+                let addr = &*(name as *const libc::sockaddr_storage as *const libc::sockaddr_in);
+                Some(IpAddr::from(
+                    u32::from_be(addr.sin_addr.s_addr).to_be_bytes(),
+                ))
+            }
+            libc::AF_INET6 => {
+                let addr = &*(name as *const libc::sockaddr_storage as *const libc::sockaddr_in6);
+                Some(IpAddr::from(addr.sin6_addr.s6_addr))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,2806 @@
+mod acks;
+mod alerts;
+mod continuous;
+mod control;
+mod endpoints;
+mod ingest;
+mod kernel_ts;
+mod metadata;
+mod probe;
+mod progress;
+mod selftest;
+mod server_time;
+mod shards;
+mod tcp;
+mod tui;
+mod udp_drops;
+
+use alerts::Alerts;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use continuous::Continuous;
+use control::{Command, Control};
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use ingest::{epoch_nanos, ExchangeFrame, FrameSender};
+use latency_core::{
+    merge_arrivals, percentile_label, read_arrivals, split_by_symbol, validate_bucket_bounds,
+    AlertThresholds, Arrival, ArrivalLog, ClockSource, Collector, DeliveryClass, ExperimentResults,
+    Heatmap, Market, OneWayDelayTracker, OverheadTracker, PathRace, PingTracker, Report,
+    SampleRate, SecondStats, ServerTimeCorrection, SessionSplit, StageBudget, StreamNames,
+    TimeSeriesWriter, UpdateArrival, HEATMAP_INTERVAL_SECS, OPENMETRICS_BUCKET_BOUNDS_MS,
+};
+use metadata::RunContext;
+use probe::{PathProber, ProbeTarget};
+use progress::Progress;
+use serde_json::json;
+use server_time::ServerTimeMonitor;
+use shards::{Content, Decoded};
+use shared::{
+    check_aws_cli, connect_exchange, default_rollover, exchange_adapter, expand_template, files_in,
+    init_logging, new_run_id, output_files, parse_interval, parse_rollover, parse_size,
+    split_stream_url, tls_acceptor, validate_run_id, Answer, Backoff, BufferPool, CaptureWriter,
+    CurrentFrame, ExchangeAdapter, ExchangeAddresses, ExchangeStream, ExperimentError,
+    ForwardedEventView, ForwarderHello, InfluxConfig, LatencyMeasurement, PayloadCheck,
+    PayloadQuotes, PayloadVerifier, ReconnectPolicy, Rollover, RotationPolicy, S3Destination,
+    Shutdown, SinkSpec, Sinks, Subscriptions, SymbolCsvSink, TickerEvent, WsCompression, EXCHANGES,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use udp_drops::DropMonitor;
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tracing::{debug, error, info, warn};
+
+#[derive(Parser, Debug, Clone)]
+#[command(name = "frankfurt-receiver")]
+#[command(about = "Receiver for Binance latency experiment (any region)")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Cmd>,
+
+    /// Region label recorded in the results (e.g. frankfurt, london)
+    #[arg(long, default_value = "frankfurt")]
+    region_name: String,
+
+    /// Mode: baseline, aws-backbone, or continuous (runs until stopped)
+    #[arg(long, default_value = "baseline")]
+    mode: String,
+
+    /// Data source for continuous mode: baseline or aws-backbone
+    #[arg(long, default_value = "aws-backbone")]
+    source: String,
+
+    /// Duration to collect data in seconds (ignored in continuous mode)
+    #[arg(long, default_value = "60")]
+    duration: u64,
+
+    /// Sliding window for rolling statistics in seconds (continuous mode only)
+    #[arg(long, default_value = "300")]
+    window_secs: u64,
+
+    /// Interval between rolling results files in seconds (continuous mode only)
+    #[arg(long, default_value = "60")]
+    emit_interval: u64,
+
+    /// Start a new raw CSV (continuous mode, default 1h) or capture file after this long, e.g. 30m or 3600 (seconds)
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval)]
+    rotate_interval: Option<Duration>,
+
+    /// Start a new raw CSV (continuous mode) or capture file at this size, e.g. 500MB
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    rotate_size: Option<u64>,
+
+    /// Gzip rotated CSV and capture files once they are closed
+    #[arg(long)]
+    rotate_compress: bool,
+
+    /// Relative accuracy of the whole-run percentiles kept beyond the window or sample (continuous mode or --sample-rate)
+    #[arg(long, value_name = "FRACTION", default_value = "0.01")]
+    digest_accuracy: f64,
+
+    /// Latency per UTC session in the results: hourly (runs of an hour or more), markets (Asia, Europe and US hours), or windows like asia=0-9,europe=7-16
+    #[arg(long, value_name = "SPLIT", default_value = "hourly")]
+    sessions: SessionSplit,
+
+    /// Directory for rolling results and rotated CSV files (continuous mode only)
+    #[arg(long, default_value = "results")]
+    results_dir: String,
+
+    /// Warm-up period in seconds: measurements are kept in the CSV but excluded from statistics
+    #[arg(long, default_value = "0")]
+    warmup_secs: u64,
+
+    /// End-to-end latency percentiles to report, comma-separated
+    #[arg(long, value_delimiter = ',', default_value = "50,90,95,99,99.9")]
+    percentiles: Vec<f64>,
+
+    /// Flag latency spikes more than K median absolute deviations above the rolling median
+    #[arg(long, value_name = "K")]
+    spike_mad_k: Option<f64>,
+
+    /// Also report latency corrected for the exchange's millisecond event times, with its ± bound
+    #[arg(long)]
+    quantization: bool,
+
+    /// Count events older than this many ms on arrival (against the exchange event time) as stale and leave them out of the latency statistics
+    #[arg(long, value_name = "MS", value_parser = parse_staleness)]
+    max_staleness_ms: Option<Duration>,
+
+    /// Keep only this fraction of events (e.g. 0.1 or 1/10) for the CSV and the exact statistics; counters and whole-run percentiles still see every event
+    #[arg(long, value_name = "RATE")]
+    sample_rate: Option<SampleRate>,
+
+    /// Attach 95% confidence intervals to the median, p95 and p99 from this many bootstrap resamples (1000 without a value)
+    #[arg(long, value_name = "ITERATIONS", num_args = 0..=1, default_missing_value = "1000")]
+    bootstrap: Option<usize>,
+
+    /// Alert when the p99 end-to-end latency over the alert window exceeds this many ms
+    #[arg(long, value_name = "MS")]
+    alert_p99_ms: Option<f64>,
+
+    /// Alert when more than this percentage of events over the alert window was lost
+    #[arg(long, value_name = "PCT")]
+    alert_loss_pct: Option<f64>,
+
+    /// Rolling window the alert thresholds are checked over, in seconds
+    #[arg(long, value_name = "SECS", default_value = "60")]
+    alert_window_secs: u64,
+
+    /// Post alerts to this webhook as Slack-compatible JSON, e.g. a Slack incoming webhook URL
+    #[arg(long, value_name = "URL")]
+    alert_webhook: Option<String>,
+
+    /// Frames the receive tasks may queue ahead of processing before they have to wait
+    #[arg(long, default_value = "10000")]
+    queue_capacity: usize,
+
+    /// Decode forwarded frames on this many threads, each with its own UDP socket (SO_REUSEPORT) and, on the other transports, its share of the streams (aws-backbone mode)
+    #[arg(long, value_name = "N", default_value = "1")]
+    shards: usize,
+
+    /// Stream every measurement to this InfluxDB (or Timestream for InfluxDB) server, e.g. http://localhost:8086 (same as --sink influx:URL)
+    #[arg(long, value_name = "URL")]
+    influx_url: Option<String>,
+
+    /// API token for InfluxDB sinks
+    #[arg(long)]
+    influx_token: Option<String>,
+
+    /// Organization to write to (InfluxDB sinks)
+    #[arg(long)]
+    influx_org: Option<String>,
+
+    /// Bucket to write to (InfluxDB sinks)
+    #[arg(long, default_value = "latency")]
+    influx_bucket: String,
+
+    /// Output file path for results (JSON)
+    #[arg(long, default_value = "results.json")]
+    output: String,
+
+    /// Name the results JSON from a template instead, e.g. results_{mode}_{symbol}_{start_ts}.json; {mode}, {symbol}, {exchange}, {region}, {run_id} and {start_ts} also work in the CSV, capture and other output paths
+    #[arg(long, value_name = "TEMPLATE")]
+    output_template: Option<String>,
+
+    /// Also write to this sink; repeat for several: csv:PATH, json:PATH, parquet:PATH, stdout (raw CSV) or influx:URL
+    #[arg(long, value_name = "KIND[:TARGET]")]
+    sink: Vec<SinkSpec>,
+
+    /// Accept runtime commands (status, flush, rotate-csv, stop-and-report) on this address, e.g. 127.0.0.1:7070
+    #[arg(long, value_name = "ADDR")]
+    control_addr: Option<String>,
+
+    /// CSV output file path for raw measurements (same as --sink csv:PATH)
+    #[arg(long)]
+    csv_output: Option<String>,
+
+    /// CSV output file path for per-second time-series stats (optional)
+    #[arg(long)]
+    timeseries_output: Option<String>,
+
+    /// Add the mid-price, spread in bps and top-of-book changes of each second to the time-series output (book ticker streams)
+    #[arg(long)]
+    market_stats: bool,
+
+    /// Measure each symbol of a multi-symbol connection on its own: latency per symbol in the results, and a CSV file per symbol next to each CSV output (raw-BTCUSDT.csv)
+    #[arg(long)]
+    split_by_symbol: bool,
+
+    /// Latency heatmap (time interval × latency bucket counts); JSON if the path ends in .json, otherwise CSV
+    #[arg(long, value_name = "PATH")]
+    heatmap_output: Option<String>,
+
+    /// Heatmap interval length in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = HEATMAP_INTERVAL_SECS)]
+    heatmap_interval: u64,
+
+    /// Final latency distribution as OpenMetrics (Prometheus) histograms in the text format
+    #[arg(long, value_name = "PATH")]
+    openmetrics_output: Option<String>,
+
+    /// Upper bounds of the OpenMetrics histogram buckets in ms, comma-separated; +Inf is added
+    #[arg(long, value_name = "MS", value_delimiter = ',', default_values_t = OPENMETRICS_BUCKET_BOUNDS_MS.to_vec())]
+    openmetrics_buckets: Vec<f64>,
+
+    /// Upload results, CSV, time-series, heatmap, OpenMetrics and capture files to s3://bucket/prefix/<run id>/ when the run ends (needs the AWS CLI)
+    #[arg(long, value_name = "URL")]
+    s3_upload: Option<S3Destination>,
+
+    /// Only measure events from the forwarder run with this ID (default: the first run ID received, or a random UUID in baseline mode)
+    #[arg(long, value_name = "ID")]
+    run_id: Option<String>,
+
+    /// Exchange to measure: binance, okx, or bybit (baseline mode, and the forwarder's exchange for --verify-payload)
+    #[arg(long, default_value = "binance")]
+    exchange: String,
+
+    /// Trading pair as BASE-QUOTE (baseline mode, and the forwarder's symbol for --verify-payload)
+    #[arg(long, default_value = "BTC-USDT")]
+    symbol: String,
+
+    /// Override the exchange WebSocket URL (baseline mode only)
+    #[arg(long, visible_alias = "ws-url")]
+    binance_url: Option<String>,
+
+    /// Record raw exchange frames as JSON lines, gzipped if the path ends in .gz (baseline mode only)
+    #[arg(long, value_name = "FILE")]
+    capture: Option<String>,
+
+    /// Record each event's update ID and receive time, to merge with the forwarder's --arrival-log (baseline mode only)
+    #[arg(long, value_name = "FILE")]
+    arrival_log: Option<String>,
+
+    /// Compare several WebSocket URLs for the same stream side by side (baseline mode, comma-separated)
+    #[arg(long, value_name = "URLS", value_delimiter = ',')]
+    endpoints: Vec<String>,
+
+    /// Open this many connections to the same stream; each event is measured on the one that delivered it first (baseline mode, Binance)
+    #[arg(long, value_name = "N", default_value = "1")]
+    ws_connections: usize,
+
+    /// Send a WebSocket ping to the exchange every this many seconds and report the round trip; 0 disables (baseline mode only)
+    #[arg(long, value_name = "SECONDS", default_value = "5")]
+    ping_interval: u64,
+
+    /// Offer permessage-deflate to the exchange: on or off (baseline mode only)
+    #[arg(long, value_name = "on|off", default_value = "off")]
+    ws_compression: WsCompression,
+
+    /// Replace the exchange connection after this long with a standby opened next to it, e.g. 1435m, or off (baseline mode only; default: 1435m for Binance, off otherwise)
+    #[arg(long, value_name = "INTERVAL|off", value_parser = check_rollover)]
+    exchange_rollover: Option<String>,
+
+    /// Request the stream with a SUBSCRIBE message on the bare /stream endpoint, so streams can be added and dropped over --control-addr (baseline mode, Binance)
+    #[arg(long)]
+    subscribe: bool,
+
+    /// Sample the network RTT to the forwarder host alongside the run: udp://HOST:PORT (the forwarder's --echo-port) or tcp://HOST:PORT (aws-backbone mode only)
+    #[arg(long, value_name = "TARGET")]
+    path_probe: Option<ProbeTarget>,
+
+    /// Milliseconds between path probes
+    #[arg(long, value_name = "MS", default_value = "1000")]
+    path_probe_interval: u64,
+
+    /// Acknowledge every Nth event to the forwarder over UDP, so it can measure the round trip and clock offset (aws-backbone mode only)
+    #[arg(long, value_name = "N")]
+    ack_every: Option<u64>,
+
+    /// Port of the forwarder's --ack-port, on the host each event came from
+    #[arg(long, value_name = "PORT", default_value = "9300")]
+    ack_port: u16,
+
+    /// Read this host's clock error from chrony or ptp during the run; with the forwarder doing the same, backbone latency is also reported corrected for both clocks
+    #[arg(long, value_name = "chrony|ptp")]
+    clock_sync: Option<ClockSource>,
+
+    /// Seconds between clock error readings (--clock-sync)
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    clock_sync_interval: u64,
+
+    /// Read the exchange's clock from its REST time endpoint during the run (default: Binance spot's /api/v3/time) and report its offset from ours against the end-to-end latencies
+    #[arg(long, value_name = "URL", num_args = 0..=1, default_missing_value = server_time::BINANCE_TIME_URL)]
+    server_time: Option<String>,
+
+    /// Seconds between server time requests (--server-time)
+    #[arg(long, value_name = "SECONDS", default_value = "10")]
+    server_time_interval: u64,
+
+    /// Correct end-to-end latencies by the latest --server-time offset, so they are measured on the exchange's clock
+    #[arg(long, requires = "server_time")]
+    correct_server_time: bool,
+
+    /// Max reconnection delay in seconds (baseline mode only)
+    #[arg(long, default_value = "30")]
+    reconnect_max_delay: u64,
+
+    /// Fraction of each reconnect delay that is randomized, 0-1 (baseline mode only)
+    #[arg(long, default_value = "0.5")]
+    reconnect_jitter: f64,
+
+    /// Stop collecting after this many consecutive failed reconnects (baseline mode only)
+    #[arg(long, value_name = "N")]
+    max_reconnect_attempts: Option<u32>,
+
+    /// Consecutive reconnect failures before pausing for the cooldown; 0 disables (baseline mode only)
+    #[arg(long, value_name = "N", default_value = "10")]
+    breaker_after: u32,
+
+    /// Seconds to pause while the reconnect circuit breaker is open (baseline mode only)
+    #[arg(long, value_name = "SECONDS", default_value = "300")]
+    breaker_cooldown: u64,
+
+    /// Listen port (aws-backbone mode only)
+    #[arg(long, default_value = "8080")]
+    port: u16,
+
+    /// Parse each forwarded payload again and count events whose event time or symbol disagree with the envelope (aws-backbone mode)
+    #[arg(long)]
+    verify_payload: bool,
+
+    /// SO_RCVBUF for the UDP socket in bytes; the kernel caps it at net.core.rmem_max (Linux, aws-backbone mode)
+    #[arg(long, value_name = "BYTES")]
+    recv_buffer_bytes: Option<usize>,
+
+    /// Capture kernel receive timestamps (SO_TIMESTAMPING, Linux UDP only)
+    #[arg(long)]
+    kernel_timestamps: bool,
+
+    /// Accept events from several forwarders at once: sequence IDs, loss and latency are tracked per forwarder (its --forwarder-id, else its IP address) as well as combined (aws-backbone mode)
+    #[arg(long)]
+    multi_forwarder: bool,
+
+    /// Backbone transport: udp, tcp, dual (both, deduplicated by sequence ID), ws, wss, uds (a forwarder on the same host), or grpc
+    #[arg(long, default_value = "udp")]
+    transport: String,
+
+    /// Socket to listen on with --transport uds
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "/tmp/frankfurt-receiver.sock"
+    )]
+    uds_path: String,
+
+    /// PEM certificate chain; enables TLS on the TCP listener (required for wss)
+    #[arg(long, value_name = "PEM", requires = "tls_key")]
+    tls_cert: Option<String>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PEM", requires = "tls_cert")]
+    tls_key: Option<String>,
+
+    /// Log level (error, warn, info, debug, trace) or a tracing filter directive; RUST_LOG overrides it
+    #[arg(long, value_name = "FILTER", default_value = "info")]
+    log_level: String,
+
+    /// Write logs to stderr as JSON lines
+    #[arg(long)]
+    log_json: bool,
+
+    /// Show a live dashboard instead of the per-second table (logs move into a panel)
+    #[arg(long)]
+    tui: bool,
+
+    /// Stream synthetic events from an in-process mock forwarder over localhost, check the results and exit
+    #[arg(long)]
+    self_test: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Cmd {
+    /// Render results.json (and optionally the raw CSV) as a standalone HTML report
+    Report {
+        /// Results JSON written by a previous run
+        results: String,
+
+        /// Raw measurements CSV from the same run, for the distribution and time-series charts
+        #[arg(long)]
+        csv: Option<String>,
+
+        /// HTML file to write
+        #[arg(long, default_value = "report.html")]
+        output: String,
+    },
+    /// Join the forwarder's and a baseline receiver's --arrival-log by update ID
+    Merge {
+        /// Arrival log written by the forwarder
+        tokyo: String,
+
+        /// Arrival log written by the receiver (baseline mode)
+        frankfurt: String,
+
+        /// CSV file for the matched events and their arrival deltas
+        #[arg(long)]
+        output: Option<String>,
+
+        /// JSON file for the summary
+        #[arg(long)]
+        json: Option<String>,
+
+        /// Arrival delta percentiles to report, comma-separated
+        #[arg(long, value_delimiter = ',', default_value = "50,90,95,99,99.9")]
+        percentiles: Vec<f64>,
+    },
+    /// Recompute results from the raw measurements of an earlier run with the current statistics
+    Analyze {
+        /// Raw measurements CSV or .parquet files; every rotated file of a run, in any order
+        #[arg(required = true)]
+        measurements: Vec<String>,
+
+        /// Results JSON of the same run, for what the raw files do not hold (setup type, region, run ID, metadata)
+        #[arg(long)]
+        results: Option<String>,
+
+        /// JSON file to write the recomputed results to
+        #[arg(long, default_value = "analysis.json")]
+        output: String,
+
+        /// End-to-end latency percentiles to report, comma-separated
+        #[arg(long, value_delimiter = ',', default_value = "50,90,95,99,99.9")]
+        percentiles: Vec<f64>,
+
+        /// Flag the first SECS of the run as warm-up instead of keeping the recorded flags
+        #[arg(long, value_name = "SECS")]
+        warmup_secs: Option<u64>,
+
+        /// Flag latency spikes more than K median absolute deviations above the rolling median
+        #[arg(long, value_name = "K")]
+        spike_mad_k: Option<f64>,
+
+        /// Latency per UTC session: hourly, markets, or windows like asia=0-9,europe=7-16
+        #[arg(long, value_name = "SPLIT", default_value = "hourly")]
+        sessions: SessionSplit,
+
+        /// Also report latency corrected for the exchange's millisecond event times, with its ± bound
+        #[arg(long)]
+        quantization: bool,
+
+        /// Forwarder and receiver --arrival-log of the run, for update-ID matched arrival deltas
+        #[arg(long, num_args = 2, value_names = ["TOKYO", "FRANKFURT"], requires = "quantization")]
+        arrival_logs: Option<Vec<String>>,
+
+        /// Count events older than this many ms on arrival as stale and leave them out of the latency statistics
+        #[arg(long, value_name = "MS", value_parser = parse_staleness)]
+        max_staleness_ms: Option<Duration>,
+
+        /// Attach 95% confidence intervals to the median, p95 and p99 from this many bootstrap resamples (1000 without a value)
+        #[arg(long, value_name = "ITERATIONS", num_args = 0..=1, default_missing_value = "1000")]
+        bootstrap: Option<usize>,
+
+        /// Also write the latency distribution as OpenMetrics (Prometheus) histograms
+        #[arg(long, value_name = "PATH")]
+        openmetrics_output: Option<String>,
+
+        /// Upper bounds of the OpenMetrics histogram buckets in ms, comma-separated; +Inf is added
+        #[arg(long, value_name = "MS", value_delimiter = ',', default_values_t = OPENMETRICS_BUCKET_BOUNDS_MS.to_vec())]
+        openmetrics_buckets: Vec<f64>,
+    },
+}
+
+/// Collector for `analyze`, configured from its flags
+fn analysis_collector(
+    percentiles: &[f64],
+    warmup_secs: Option<u64>,
+    spike_mad_k: Option<f64>,
+    sessions: &SessionSplit,
+    quantization: bool,
+    max_staleness: Option<Duration>,
+    bootstrap: Option<usize>,
+) -> Collector {
+    let mut collector = Collector::new()
+        .with_percentiles(percentiles.to_vec())
+        .with_sessions(sessions.clone());
+    if let Some(secs) = warmup_secs {
+        collector = collector.with_warmup(Duration::from_secs(secs));
+    }
+    if let Some(k) = spike_mad_k {
+        collector = collector.with_spike_detection(k);
+    }
+    if quantization {
+        collector = collector.with_quantization();
+    }
+    if let Some(max_staleness) = max_staleness {
+        collector = collector.with_max_staleness(max_staleness);
+    }
+    if let Some(iterations) = bootstrap {
+        collector = collector.with_bootstrap(iterations);
+    }
+    collector
+}
+
+/// `analyze` subcommand
+fn analyze(
+    paths: &[String],
+    results_path: Option<&str>,
+    output: &str,
+    percentiles: &[f64],
+    collector: Collector,
+    arrival_logs: Option<&[String]>,
+    openmetrics: Option<(&str, &[f64])>,
+) -> Result<(), ExperimentError> {
+    if let Some(p) = percentiles.iter().find(|p| !(0.0..=100.0).contains(*p)) {
+        return Err(ExperimentError::other(format!(
+            "Invalid percentile: {}. Must be between 0 and 100",
+            p
+        )));
+    }
+    let mut measurements = Vec::new();
+    for path in paths {
+        let read = if path.ends_with(".parquet") {
+            shared::read_parquet(path).map_err(|e| e.to_string())
+        } else {
+            LatencyMeasurement::read_from_csv(path).map_err(|e| e.to_string())
+        };
+        measurements.extend(read.map_err(|e| read_failed(path, e))?);
+    }
+    let previous = results_path
+        .map(|path| ExperimentResults::load(path).map_err(|e| read_failed(path, e)))
+        .transpose()?;
+
+    let setup_type = match &previous {
+        Some(previous) => previous.setup_type.clone(),
+        None if measurements.iter().any(|m| m.tokyo_receive_time.is_some()) => {
+            "aws-backbone".to_string()
+        }
+        None => "baseline".to_string(),
+    };
+    let mut report = collector.replay(&setup_type, measurements);
+    if let (Some(quantized), Some([tokyo, frankfurt])) =
+        (&mut report.results.quantization, arrival_logs)
+    {
+        let read = |path: &str| read_arrivals(path).map_err(|e| read_failed(path, e));
+        let merge = merge_arrivals(&read(tokyo)?, &read(frankfurt)?);
+        quantized.arrival_delta = Some(merge.stats(percentiles));
+    }
+    if let Some(previous) = previous {
+        let results = &mut report.results;
+        results.region = previous.region;
+        results.exchange = previous.exchange;
+        results.run_id = previous.run_id;
+        results.reconnects = previous.reconnects;
+        results.outage_ms = previous.outage_ms;
+        results.metadata = previous.metadata;
+    }
+
+    report.print_summary();
+    if let Err(e) = report.write_json(output) {
+        return Err(ExperimentError::other(format!(
+            "failed to write {}: {}",
+            output, e
+        )));
+    }
+    println!("Results written to {}", output);
+    if let Some((path, buckets)) = openmetrics {
+        if let Err(e) = report.write_openmetrics(path, buckets) {
+            return Err(ExperimentError::other(format!(
+                "failed to write {}: {}",
+                path, e
+            )));
+        }
+        println!("OpenMetrics histograms written to {}", path);
+    }
+    Ok(())
+}
+
+/// `report` subcommand
+fn write_html_report(
+    results_path: &str,
+    csv_path: Option<&str>,
+    output: &str,
+) -> Result<(), ExperimentError> {
+    let results =
+        ExperimentResults::load(results_path).map_err(|e| read_failed(results_path, e))?;
+    let measurements = match csv_path {
+        Some(csv_path) => {
+            LatencyMeasurement::read_from_csv(csv_path).map_err(|e| read_failed(csv_path, e))?
+        }
+        None => Vec::new(),
+    };
+    if let Err(e) = Report::new(results, measurements).write_html(output) {
+        return Err(ExperimentError::other(format!(
+            "failed to write {}: {}",
+            output, e
+        )));
+    }
+    println!("Report written to {}", output);
+    Ok(())
+}
+
+/// `merge` subcommand
+fn merge_arrival_logs(
+    tokyo_path: &str,
+    frankfurt_path: &str,
+    output: Option<&str>,
+    json: Option<&str>,
+    percentiles: &[f64],
+) -> Result<(), ExperimentError> {
+    if let Some(p) = percentiles.iter().find(|p| !(0.0..=100.0).contains(*p)) {
+        return Err(ExperimentError::other(format!(
+            "Invalid percentile: {}. Must be between 0 and 100",
+            p
+        )));
+    }
+    let read = |path: &str| read_arrivals(path).map_err(|e| read_failed(path, e));
+    let merge = merge_arrivals(&read(tokyo_path)?, &read(frankfurt_path)?);
+    let stats = merge.stats(percentiles);
+
+    println!("\n=== Arrival Deltas (receiver − forwarder, same update ID) ===");
+    println!(
+        "Matched: {} | Forwarder only: {} | Receiver only: {} | Duplicate IDs: {}",
+        stats.matched, stats.tokyo_only, stats.frankfurt_only, stats.duplicates
+    );
+    if stats.matched > 0 {
+        println!(
+            "Average: {:.3} ms | Min: {:.3} ms | Max: {:.3} ms | Jitter (stddev): {:.3} ms",
+            stats.delta.avg_ms, stats.delta.min_ms, stats.delta.max_ms, stats.delta.stddev_ms
+        );
+        for &level in percentiles {
+            let label = percentile_label(level);
+            println!(
+                "{}: {:.3} ms",
+                label.to_uppercase(),
+                stats.percentiles[&label]
+            );
+        }
+    }
+
+    if let Some(path) = output {
+        if let Err(e) = merge.write_csv(path) {
+            return Err(ExperimentError::other(format!(
+                "failed to write {}: {}",
+                path, e
+            )));
+        }
+        println!("Matched events written to {}", path);
+    }
+    if let Some(path) = json {
+        let written = serde_json::to_string_pretty(&stats)
+            .map_err(std::io::Error::from)
+            .and_then(|text| std::fs::write(path, text));
+        if let Err(e) = written {
+            return Err(ExperimentError::other(format!(
+                "failed to write {}: {}",
+                path, e
+            )));
+        }
+        println!("Summary written to {}", path);
+    }
+    Ok(())
+}
+
+fn read_failed(path: &str, error: impl std::fmt::Display) -> ExperimentError {
+    ExperimentError::other(format!("failed to read {}: {}", path, error))
+}
+
+/// Run the receiver on command-line arguments, the program name first.
+/// Invalid arguments and failed runs are returned; only `main` exits.
+pub async fn run<I, T>(args: I) -> Result<(), ExperimentError>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let command_line: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let mut args = match Args::try_parse_from(&command_line) {
+        Ok(args) => args,
+        // --help and --version
+        Err(e) if !e.use_stderr() => {
+            print!("{}", e);
+            return Ok(());
+        }
+        Err(e) => return Err(ExperimentError::other(e.to_string().trim_end())),
+    };
+    match &args.command {
+        Some(Cmd::Report {
+            results,
+            csv,
+            output,
+        }) => {
+            return write_html_report(results, csv.as_deref(), output);
+        }
+        Some(Cmd::Merge {
+            tokyo,
+            frankfurt,
+            output,
+            json,
+            percentiles,
+        }) => {
+            return merge_arrival_logs(
+                tokyo,
+                frankfurt,
+                output.as_deref(),
+                json.as_deref(),
+                percentiles,
+            );
+        }
+        Some(Cmd::Analyze {
+            measurements,
+            results,
+            output,
+            percentiles,
+            warmup_secs,
+            spike_mad_k,
+            sessions,
+            quantization,
+            arrival_logs,
+            max_staleness_ms,
+            bootstrap,
+            openmetrics_output,
+            openmetrics_buckets,
+        }) => {
+            if bootstrap.is_some_and(|iterations| iterations < MIN_BOOTSTRAP_ITERATIONS) {
+                return Err(ExperimentError::other(format!(
+                    "--bootstrap must be at least {}",
+                    MIN_BOOTSTRAP_ITERATIONS
+                )));
+            }
+            if let Err(e) = validate_bucket_bounds(openmetrics_buckets) {
+                return Err(ExperimentError::other(format!(
+                    "--openmetrics-buckets: {}",
+                    e
+                )));
+            }
+            let collector = analysis_collector(
+                percentiles,
+                *warmup_secs,
+                *spike_mad_k,
+                sessions,
+                *quantization,
+                *max_staleness_ms,
+                *bootstrap,
+            );
+            return analyze(
+                measurements,
+                results.as_deref(),
+                output,
+                percentiles,
+                collector,
+                arrival_logs.as_deref(),
+                openmetrics_output
+                    .as_deref()
+                    .map(|path| (path, openmetrics_buckets.as_slice())),
+            );
+        }
+        None => {}
+    }
+    if args.tui && !std::io::stdout().is_terminal() {
+        return Err(ExperimentError::other(
+            "--tui requires stdout to be a terminal",
+        ));
+    }
+    match args
+        .sink
+        .iter()
+        .filter(|&sink| *sink == SinkSpec::Stdout)
+        .count()
+    {
+        0 => {}
+        1 if !args.tui => {}
+        1 => {
+            return Err(ExperimentError::other(
+                "--sink stdout cannot be combined with --tui",
+            ));
+        }
+        _ => {
+            return Err(ExperimentError::other(
+                "--sink stdout can only be given once",
+            ));
+        }
+    }
+    if (args.influx_token.is_some() || args.influx_org.is_some())
+        && !args
+            .sinks()
+            .iter()
+            .any(|sink| matches!(sink, SinkSpec::Influx(_)))
+    {
+        return Err(ExperimentError::other(
+            "--influx-token and --influx-org require an InfluxDB sink",
+        ));
+    }
+    if args.alert_webhook.is_some() && args.alert_thresholds().is_none() {
+        return Err(ExperimentError::other(
+            "--alert-webhook requires --alert-p99-ms or --alert-loss-pct",
+        ));
+    }
+    if args.alert_window_secs == 0 {
+        return Err(ExperimentError::other(
+            "--alert-window-secs must be at least 1",
+        ));
+    }
+    let logging = if args.tui {
+        tui::init_logging(&args.log_level, args.log_json)
+    } else {
+        init_logging(&args.log_level, args.log_json)
+    };
+    logging.map_err(ExperimentError::other)?;
+    if args.s3_upload.is_some() {
+        check_aws_cli().await.map_err(ExperimentError::other)?;
+    }
+    let shutdown = Shutdown::install();
+    let context = RunContext::default();
+    context.capture(
+        command_line
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+    );
+    if let Some(source) = args.clock_sync {
+        context.start_clock_sync(source, Duration::from_secs(args.clock_sync_interval));
+    }
+
+    if args.self_test {
+        let passed = selftest::run(&args, &context, shutdown).await;
+        return if passed {
+            Ok(())
+        } else {
+            Err(ExperimentError::other("self-test failed"))
+        };
+    }
+
+    info!(
+        region = %args.region_name,
+        mode = %args.mode,
+        "receiver starting"
+    );
+    if args.continuous() {
+        info!(
+            source = %args.source,
+            window_secs = args.window_secs,
+            "continuous mode"
+        );
+    } else {
+        info!(duration_secs = args.duration, "fixed-duration run");
+    }
+    if !cfg!(target_os = "linux") {
+        warn!(
+            platform = %metadata::platform(),
+            "kernel timestamps, UDP shards and kernel drop counters need Linux; \
+             compare these latencies only with runs on the same platform"
+        );
+        let linux_only = [
+            (args.kernel_timestamps, "--kernel-timestamps"),
+            (args.recv_buffer_bytes.is_some(), "--recv-buffer-bytes"),
+            (
+                args.shards > 1 && matches!(args.transport.as_str(), "udp" | "dual"),
+                "--shards with the udp or dual transport",
+            ),
+        ];
+        if let Some((_, flag)) = linux_only.iter().find(|(used, _)| *used) {
+            return Err(ExperimentError::other(format!("{} requires Linux", flag)));
+        }
+    }
+
+    if !matches!(
+        args.transport.as_str(),
+        "udp" | "tcp" | "dual" | "ws" | "wss" | "uds" | "grpc"
+    ) {
+        return Err(ExperimentError::other(format!(
+            "Invalid transport: {}. Must be 'udp', 'tcp', 'dual', 'ws', 'wss', 'uds' or 'grpc'",
+            args.transport
+        )));
+    }
+    if args.transport == "wss" && args.tls_cert.is_none() {
+        return Err(ExperimentError::other(
+            "The wss transport requires --tls-cert and --tls-key",
+        ));
+    }
+    if args.transport == "ws" && args.tls_cert.is_some() {
+        return Err(ExperimentError::other(
+            "The ws transport runs without TLS; use --transport wss with --tls-cert",
+        ));
+    }
+    if args.transport == "uds" && args.tls_cert.is_some() {
+        return Err(ExperimentError::other("The uds transport runs without TLS"));
+    }
+    if args.transport == "grpc" && args.tls_cert.is_some() {
+        return Err(ExperimentError::other(
+            "The grpc transport runs without TLS",
+        ));
+    }
+    if !args.endpoints.is_empty() && args.capture.is_some() {
+        return Err(ExperimentError::other(
+            "--capture is not supported together with --endpoints",
+        ));
+    }
+    if args.endpoints.len() > usize::from(u16::MAX) {
+        return Err(ExperimentError::other("Too many endpoints"));
+    }
+    if args.ws_connections == 0 || args.ws_connections > usize::from(u16::MAX) {
+        return Err(ExperimentError::other(format!(
+            "--ws-connections must be between 1 and {}",
+            u16::MAX
+        )));
+    }
+    if args.arrival_log.is_some()
+        && (args.source_mode() != "baseline"
+            || !args.endpoints.is_empty()
+            || args.ws_connections > 1)
+    {
+        return Err(ExperimentError::other(
+            "--arrival-log requires baseline mode with a single connection",
+        ));
+    }
+    if args.ws_connections > 1 {
+        if !args.endpoints.is_empty() || args.capture.is_some() {
+            return Err(ExperimentError::other(
+                "--ws-connections cannot be combined with --endpoints or --capture",
+            ));
+        }
+        // Copies are matched by the update ID, which only the Binance adapter reads
+        if !args.exchange.eq_ignore_ascii_case("binance") {
+            return Err(ExperimentError::other(
+                "--ws-connections is only supported for Binance",
+            ));
+        }
+    }
+    if args.subscribe {
+        if args.source_mode() != "baseline" || !args.endpoints.is_empty() || args.ws_connections > 1
+        {
+            return Err(ExperimentError::other(
+                "--subscribe requires baseline mode with a single connection",
+            ));
+        }
+        if !args.exchange.eq_ignore_ascii_case("binance") {
+            return Err(ExperimentError::other(
+                "--subscribe is only supported for Binance",
+            ));
+        }
+    }
+    if (args.rotate_size.is_some() || args.rotate_interval.is_some() || args.rotate_compress)
+        && !args.continuous()
+        && args.capture.is_none()
+    {
+        return Err(ExperimentError::other(
+            "File rotation applies to continuous mode and --capture only",
+        ));
+    }
+    if args.path_probe.is_some() {
+        if args.source_mode() != "aws-backbone" {
+            return Err(ExperimentError::other(
+                "--path-probe requires aws-backbone mode",
+            ));
+        }
+        if args.path_probe_interval == 0 {
+            return Err(ExperimentError::other(
+                "--path-probe-interval must be at least 1",
+            ));
+        }
+    }
+    if args.clock_sync.is_some() && args.clock_sync_interval == 0 {
+        return Err(ExperimentError::other(
+            "--clock-sync-interval must be at least 1",
+        ));
+    }
+    if args.server_time.is_some() && args.server_time_interval == 0 {
+        return Err(ExperimentError::other(
+            "--server-time-interval must be at least 1",
+        ));
+    }
+    if args.recv_buffer_bytes.is_some()
+        && (args.source_mode() != "aws-backbone"
+            || !matches!(args.transport.as_str(), "udp" | "dual"))
+    {
+        return Err(ExperimentError::other(
+            "--recv-buffer-bytes requires aws-backbone mode with the udp or dual transport",
+        ));
+    }
+    if let Some(every) = args.ack_every {
+        if args.source_mode() != "aws-backbone" {
+            return Err(ExperimentError::other(
+                "--ack-every requires aws-backbone mode",
+            ));
+        }
+        if every == 0 {
+            return Err(ExperimentError::other("--ack-every must be at least 1"));
+        }
+        // Acknowledgements go to the sender's address, which a Unix socket does not have
+        if args.transport == "uds" {
+            return Err(ExperimentError::other(
+                "--ack-every cannot be combined with the uds transport",
+            ));
+        }
+    }
+    if args.market_stats && args.timeseries_output.is_none() {
+        return Err(ExperimentError::other(
+            "--market-stats requires --timeseries-output",
+        ));
+    }
+    if args.verify_payload && args.source_mode() != "aws-backbone" {
+        return Err(ExperimentError::other(
+            "--verify-payload requires aws-backbone mode",
+        ));
+    }
+    if args.multi_forwarder {
+        if args.source_mode() != "aws-backbone" {
+            return Err(ExperimentError::other(
+                "--multi-forwarder requires aws-backbone mode",
+            ));
+        }
+        // Copies over the two paths are matched by sequence ID alone
+        if args.transport == "dual" {
+            return Err(ExperimentError::other(
+                "--multi-forwarder cannot be combined with the dual transport",
+            ));
+        }
+    }
+    if let Some(Err(e)) = args.run_id.as_deref().map(validate_run_id) {
+        return Err(ExperimentError::other(format!("Invalid --run-id: {}", e)));
+    }
+    if let Err(e) = validate_bucket_bounds(&args.openmetrics_buckets) {
+        return Err(ExperimentError::other(format!(
+            "--openmetrics-buckets: {}",
+            e
+        )));
+    }
+    if args.heatmap_interval == 0 {
+        return Err(ExperimentError::other(
+            "--heatmap-interval must be at least 1",
+        ));
+    }
+    if !(args.digest_accuracy > 0.0 && args.digest_accuracy < 1.0) {
+        return Err(ExperimentError::other(
+            "--digest-accuracy must be greater than 0 and less than 1",
+        ));
+    }
+    if let Some(iterations) = args.bootstrap {
+        if args.continuous() {
+            return Err(ExperimentError::other("--bootstrap applies to the final results of a run, not continuous mode; run analyze --bootstrap over its CSV files"));
+        }
+        if iterations < MIN_BOOTSTRAP_ITERATIONS {
+            return Err(ExperimentError::other(format!(
+                "--bootstrap must be at least {}",
+                MIN_BOOTSTRAP_ITERATIONS
+            )));
+        }
+    }
+    if args.queue_capacity == 0 {
+        return Err(ExperimentError::other(
+            "--queue-capacity must be at least 1",
+        ));
+    }
+    if args.shards == 0 {
+        return Err(ExperimentError::other("--shards must be at least 1"));
+    }
+    if args.shards > 1 && args.source_mode() != "aws-backbone" {
+        return Err(ExperimentError::other(
+            "--shards requires aws-backbone mode",
+        ));
+    }
+    if !(0.0..=1.0).contains(&args.reconnect_jitter) {
+        return Err(ExperimentError::other(
+            "--reconnect-jitter must be between 0 and 1",
+        ));
+    }
+    if let Some(p) = args
+        .percentiles
+        .iter()
+        .find(|p| !(0.0..=100.0).contains(*p))
+    {
+        return Err(ExperimentError::other(format!(
+            "Invalid percentile: {}. Must be between 0 and 100",
+            p
+        )));
+    }
+
+    let Some(adapter) = exchange_adapter(&args.exchange) else {
+        return Err(ExperimentError::other(format!(
+            "Invalid exchange: {}. Must be one of {}",
+            args.exchange,
+            EXCHANGES.join(", ")
+        )));
+    };
+
+    // Backbone runs take the forwarder's run ID from the first event
+    match args.run_id.clone() {
+        Some(run_id) => context.set_run_id(run_id),
+        None if args.source_mode() == "baseline" => context.set_run_id(new_run_id()),
+        None => {}
+    }
+    if let Err(e) = args.expand_output_names(Utc::now(), context.run_id()) {
+        return Err(ExperimentError::other(format!(
+            "Invalid output name: {}",
+            e
+        )));
+    }
+    info!(output = %args.output, "results file");
+    if let Some(url) = &args.server_time {
+        let period = Duration::from_secs(args.server_time_interval);
+        if let Some(monitor) = ServerTimeMonitor::start(url.clone(), period).await {
+            context.set_server_time(monitor);
+        }
+    }
+
+    // Continuous mode runs one of the regular modes without a time limit
+    let mode = args.source_mode();
+    let started = SystemTime::now();
+    let (name, result) = match mode {
+        "baseline" if !args.endpoints.is_empty() || args.ws_connections > 1 => (
+            "multi-endpoint baseline mode",
+            endpoints::run(&args, &context, adapter.as_ref(), shutdown).await,
+        ),
+        "baseline" => (
+            "baseline mode",
+            run_baseline_mode(&args, &context, adapter.as_ref(), shutdown).await,
+        ),
+        "aws-backbone" => (
+            "AWS backbone mode",
+            run_aws_backbone_mode(&args, &context, shutdown).await,
+        ),
+        _ => {
+            return Err(ExperimentError::other(format!(
+                "Invalid mode: {}. Must be 'baseline', 'aws-backbone' or 'continuous'",
+                mode
+            )));
+        }
+    };
+    if let Err(e) = &result {
+        error!(error = %e, "{} failed", name);
+    } else if !args.stdout_sink() {
+        print_output_files(&args.output_files(started));
+    }
+
+    // Whatever was written is uploaded, also after a failure
+    if let Some(s3) = &args.s3_upload {
+        let run_id = context.run_id().map_or_else(new_run_id, str::to_string);
+        let files = args.output_files(started);
+        let uploaded = s3.upload(&run_id, &files, started).await;
+        info!(uploaded, destination = %s3.url(&run_id, Path::new("")), "S3 upload finished");
+    }
+    result
+}
+
+impl Args {
+    /// What to alert on, if any threshold was given
+    fn alert_thresholds(&self) -> Option<AlertThresholds> {
+        (self.alert_p99_ms.is_some() || self.alert_loss_pct.is_some()).then_some(AlertThresholds {
+            p99_ms: self.alert_p99_ms,
+            loss_pct: self.alert_loss_pct,
+            window_secs: self.alert_window_secs,
+        })
+    }
+
+    fn continuous(&self) -> bool {
+        self.mode == "continuous"
+    }
+
+    /// The mode events are measured in: `--source` in continuous mode
+    fn source_mode(&self) -> &str {
+        if self.continuous() {
+            &self.source
+        } else {
+            &self.mode
+        }
+    }
+
+    /// Fill in the placeholders of --output-template and the other output
+    /// paths, once the run ID is known
+    fn expand_output_names(
+        &mut self,
+        start: DateTime<Utc>,
+        run_id: Option<&str>,
+    ) -> Result<(), String> {
+        let start_ts = start.format("%Y%m%dT%H%M%SZ").to_string();
+        let mode = self.source_mode().to_string();
+        let mut values = vec![
+            ("mode", mode.as_str()),
+            ("symbol", self.symbol.as_str()),
+            ("exchange", self.exchange.as_str()),
+            ("region", self.region_name.as_str()),
+            ("start_ts", start_ts.as_str()),
+        ];
+        // Backbone runs without --run-id only learn it from the first event
+        if let Some(run_id) = run_id {
+            values.push(("run_id", run_id));
+        }
+
+        let expand = |path: &str| expand_template(path, &values);
+        let output = match &self.output_template {
+            Some(template) => expand(template)?,
+            None => expand(&self.output)?,
+        };
+        let paths = [
+            &mut self.csv_output,
+            &mut self.timeseries_output,
+            &mut self.heatmap_output,
+            &mut self.openmetrics_output,
+            &mut self.capture,
+            &mut self.arrival_log,
+        ];
+        for path in paths.into_iter().flatten() {
+            *path = expand(path)?;
+        }
+        for sink in &mut self.sink {
+            if let SinkSpec::Csv(path) | SinkSpec::Json(path) | SinkSpec::Parquet(path) = sink {
+                *path = expand(path)?;
+            }
+        }
+        self.output = output;
+        Ok(())
+    }
+
+    /// Every sink of the run: the results JSON, the shorthands for CSV and
+    /// InfluxDB, then the --sink arguments
+    fn sinks(&self) -> Vec<SinkSpec> {
+        let mut sinks = vec![SinkSpec::Json(self.output.clone())];
+        sinks.extend(self.csv_output.clone().map(SinkSpec::Csv));
+        sinks.extend(self.influx_url.clone().map(SinkSpec::Influx));
+        sinks.extend(self.sink.iter().cloned());
+        sinks
+    }
+
+    /// Whether raw measurements go to stdout, which then carries nothing else
+    fn stdout_sink(&self) -> bool {
+        self.sink.contains(&SinkSpec::Stdout)
+    }
+
+    /// Files this run wrote, for --s3-upload
+    fn output_files(&self, since: SystemTime) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.sinks().iter().filter_map(SinkSpec::path).collect();
+        // Per-symbol CSV files are named like rotated ones
+        if self.split_by_symbol {
+            for sink in self.sinks() {
+                if let SinkSpec::Csv(path) = sink {
+                    files.extend(output_files(&path, since));
+                }
+            }
+            files.sort();
+            files.dedup();
+        }
+        files.extend(
+            [
+                &self.timeseries_output,
+                &self.heatmap_output,
+                &self.openmetrics_output,
+                &self.arrival_log,
+            ]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::from),
+        );
+        if let Some(capture) = &self.capture {
+            files.extend(output_files(capture, since));
+        }
+        if self.continuous() {
+            files.extend(files_in(&self.results_dir, since));
+        }
+        files
+    }
+
+    /// How long to collect for; unbounded in continuous mode
+    fn run_duration(&self) -> Duration {
+        if self.continuous() {
+            Duration::MAX
+        } else {
+            Duration::from_secs(self.duration)
+        }
+    }
+
+    /// File rotation for raw CSV and capture files
+    fn rotation(&self, default_interval: Option<Duration>) -> RotationPolicy {
+        RotationPolicy {
+            max_bytes: self.rotate_size,
+            interval: self.rotate_interval.or(default_interval),
+            compress: self.rotate_compress,
+        }
+    }
+
+    /// Connection age at which a standby exchange connection takes over
+    fn exchange_rollover(&self) -> Option<Duration> {
+        match &self.exchange_rollover {
+            Some(text) => parse_rollover(text).expect("validated by clap"),
+            None => default_rollover(&self.exchange),
+        }
+    }
+
+    fn reconnect_policy(&self) -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_delay: Duration::from_secs(self.reconnect_max_delay),
+            jitter: self.reconnect_jitter,
+            max_attempts: self.max_reconnect_attempts,
+            breaker_after: self.breaker_after,
+            breaker_cooldown: Duration::from_secs(self.breaker_cooldown),
+            ..ReconnectPolicy::default()
+        }
+    }
+}
+
+async fn run_baseline_mode(
+    args: &Args,
+    context: &RunContext,
+    adapter: &dyn ExchangeAdapter,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    info!(
+        exchange = adapter.name(),
+        url = %ws_url(args, adapter),
+        "connecting to exchange WebSocket"
+    );
+
+    // Connect to the exchange WebSocket
+    let addresses = ExchangeAddresses::default();
+    let ws_stream = tokio::select! {
+        connected = connect_to_exchange(args, adapter, &addresses) => connected?,
+        _ = shutdown.wait() => {
+            info!("shutdown requested before connecting, nothing collected");
+            return Ok(());
+        }
+    };
+    let ws_compressed = ws_stream.get_ref().compressed();
+    info!(
+        exchange = adapter.name(),
+        ws_compressed, "connected to exchange WebSocket"
+    );
+
+    // The exchange is read by its own task so processing never delays a read
+    let (mut write, read) = ws_stream.split();
+    let (tx, mut frames) = ingest::queue(args.queue_capacity);
+    let mut conn = 0u64; // Frames of other connections are from a standby or a replaced one
+    let mut last_conn = conn;
+    tokio::spawn(ingest::exchange(read, conn, tx.clone()));
+
+    // Shortly before the exchange's connection limit a standby is opened and
+    // takes over once it overlaps the current connection
+    let mut rollover = Rollover::new(&args.exchange, args.exchange_rollover());
+    let mut opening: Option<JoinHandle<Result<ExchangeStream, WsError>>> = None;
+    let mut standby: Option<(u64, SplitSink<ExchangeStream, Message>)> = None;
+    let mut held = VecDeque::new(); // Delivered by the standby ahead of the connection it replaced
+
+    // With --subscribe the streams are requested in-band and can change during the run
+    let mut subscriptions = initial_subscriptions(args, adapter);
+    let mut awaiting = Vec::new(); // Control requests waiting for the exchange's answer
+    if let Some(message) = subscriptions
+        .as_mut()
+        .and_then(|subscriptions| subscriptions.resubscribe(epoch_nanos()))
+    {
+        send_subscription(message, &mut write, &mut standby).await;
+    }
+
+    let mut collector = new_collector(args);
+    let mut timeseries = open_timeseries(args)?;
+    let mut continuous = start_continuous(args)?;
+    let mut sinks = start_sinks(args, "baseline")?;
+    let mut exchange_clock = ServerTimeCorrection::new(args.correct_server_time);
+    let mut alerts = Alerts::start(
+        &args.region_name,
+        args.alert_webhook.as_deref(),
+        context.clone(),
+    );
+    let mut capture = args
+        .capture
+        .as_deref()
+        .map(|path| CaptureWriter::create_rotating(path, args.rotation(None)))
+        .transpose()?;
+    let mut arrivals = args
+        .arrival_log
+        .as_deref()
+        .map(ArrivalLog::create)
+        .transpose()?;
+    let market = Market::from_url(&ws_url(args, adapter));
+    let mut streams = StreamNames::default();
+    let mut symbols = StreamNames::default(); // With --split-by-symbol
+    let mut sequence_id = 0u64;
+    let mut events_without_time = 0u64;
+    let mut backoff = Backoff::new(args.reconnect_policy());
+    let mut outage = Duration::ZERO;
+    let mut control = start_control(args).await?;
+    let duration = args.run_duration();
+    let ping_interval = (args.ping_interval > 0).then(|| Duration::from_secs(args.ping_interval));
+    let mut next_ping = ping_interval.map(|period| Instant::now() + period);
+    let mut ping = PingTracker::new();
+
+    print_collecting(args);
+    let mut progress = Progress::start(args, "baseline")?;
+    progress.status(format!("connected to {}", adapter.name()), &collector);
+
+    // Receive messages with timeout
+    loop {
+        let elapsed = collector.elapsed();
+        if elapsed >= duration {
+            info!("duration reached, stopping collection");
+            break;
+        }
+        progress.tick(&collector);
+        if let (Some(due), Some(period)) = (next_ping, ping_interval) {
+            if Instant::now() >= due {
+                let payload = ping.payload(epoch_nanos());
+                if let Err(e) = write.send(Message::Ping(payload)).await {
+                    debug!(error = %e, "failed to send ping");
+                }
+                next_ping = Some(Instant::now() + period);
+            }
+        }
+        let standby_due = rollover.standby_due().filter(|_| opening.is_none());
+        if standby_due.is_some_and(|due| due <= tokio::time::Instant::now()) {
+            info!("opening standby exchange connection");
+            let args = args.clone();
+            let addresses = addresses.clone();
+            opening = Some(tokio::spawn(async move {
+                let adapter = exchange_adapter(&args.exchange).expect("exchange validated in main");
+                connect_to_exchange(&args, adapter.as_ref(), &addresses).await
+            }));
+        }
+        let wait = emit_continuous(&mut continuous, &mut collector, args, context, "baseline")
+            .min(progress.refresh_in())
+            .min(duration - elapsed)
+            .min(next_ping.map_or(Duration::MAX, |due| {
+                due.saturating_duration_since(Instant::now())
+            }))
+            .min(standby_due.map_or(Duration::MAX, |due| {
+                due.saturating_duration_since(tokio::time::Instant::now())
+            }));
+
+        // Frames held from the standby come first, as frames of the new connection
+        let next = if let Some((text, frankfurt_receive_time)) = held.pop_front() {
+            Ok(Some((
+                conn,
+                ExchangeFrame::Text {
+                    frankfurt_receive_time,
+                    text,
+                },
+            )))
+        } else {
+            tokio::select! {
+                next = timeout(wait, frames.recv()) => next,
+                opened = async { opening.as_mut().unwrap().await }, if opening.is_some() => {
+                    opening = None;
+                    match opened {
+                        Ok(Ok(ws_stream)) => {
+                            info!("standby exchange connection open");
+                            last_conn += 1;
+                            let (mut standby_write, read) = ws_stream.split();
+                            tokio::spawn(ingest::exchange(read, last_conn, tx.clone()));
+                            if let Some(message) = subscriptions
+                                .as_mut()
+                                .and_then(|subscriptions| subscriptions.resubscribe(epoch_nanos()))
+                            {
+                                if let Err(e) = standby_write.send(Message::Text(message)).await {
+                                    warn!(error = %e, "failed to subscribe on the standby connection");
+                                }
+                            }
+                            standby = Some((last_conn, standby_write));
+                            rollover.standby_opened();
+                        }
+                        Ok(Err(e)) => {
+                            warn!(error = %e, "standby exchange connection failed");
+                            rollover.standby_failed();
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "standby exchange connection task failed");
+                            rollover.standby_failed();
+                        }
+                    }
+                    continue;
+                }
+                request = control::next(&mut control) => {
+                    let subscription = matches!(
+                        request.command,
+                        Command::Subscribe(_) | Command::Unsubscribe(_)
+                    );
+                    match &mut subscriptions {
+                        Some(subscriptions) if subscription => {
+                            request_subscription(request, subscriptions, &mut write, &mut standby, &mut awaiting).await;
+                        }
+                        _ => {
+                            if handle_control(request, args, context, "baseline", &collector, &mut timeseries, &mut continuous) {
+                                break;
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ = shutdown.wait() => {
+                    info!("stopping collection early, writing partial results");
+                    break;
+                }
+            }
+        };
+
+        match next {
+            Ok(Some((from, frame))) if from != conn => {
+                // Frames of a replaced connection still in the queue are dropped
+                if standby.as_ref().is_none_or(|(id, _)| *id != from) {
+                    continue;
+                }
+                match frame {
+                    ExchangeFrame::Text {
+                        frankfurt_receive_time,
+                        text,
+                    } => {
+                        if let Some(answer) = subscriptions.as_mut().and_then(|subscriptions| {
+                            subscriptions.reply(&text, frankfurt_receive_time)
+                        }) {
+                            answer_subscription(answer, &mut awaiting);
+                            continue;
+                        }
+                        if let Some(ahead) = rollover.standby_frame(text, frankfurt_receive_time) {
+                            let (id, standby_write) = standby.take().expect("standby is open");
+                            conn = id;
+                            close_exchange(std::mem::replace(&mut write, standby_write));
+                            held.extend(ahead);
+                            info!("standby exchange connection took over");
+                        }
+                    }
+                    ExchangeFrame::Pong { .. } => {}
+                    ExchangeFrame::Closed(_) => {
+                        warn!("standby exchange connection closed before taking over");
+                        standby = None;
+                        rollover.standby_failed();
+                    }
+                }
+            }
+            Ok(Some((
+                _,
+                ExchangeFrame::Text {
+                    frankfurt_receive_time,
+                    text,
+                },
+            ))) => {
+                if let Some(answer) = subscriptions
+                    .as_mut()
+                    .and_then(|subscriptions| subscriptions.reply(&text, frankfurt_receive_time))
+                {
+                    answer_subscription(answer, &mut awaiting);
+                    continue;
+                }
+                match rollover.current_frame(&text, frankfurt_receive_time) {
+                    CurrentFrame::Pass => {}
+                    CurrentFrame::Skip => continue,
+                    CurrentFrame::PassThenSwitch(ahead) => {
+                        let (id, standby_write) = standby.take().expect("standby is open");
+                        conn = id;
+                        close_exchange(std::mem::replace(&mut write, standby_write));
+                        held.extend(ahead);
+                        info!("standby exchange connection took over");
+                    }
+                }
+                if let Some(capture) = &mut capture {
+                    if let Err(e) = capture.write(frankfurt_receive_time, &text) {
+                        warn!(error = %e, "failed to capture frame");
+                    }
+                }
+
+                if sequence_id == 0 {
+                    debug!(frame = %text, "first message received");
+                }
+
+                // Parse JSON to get the exchange event with timestamp
+                match adapter.parse(&text) {
+                    Ok(None) => {
+                        // Subscription acknowledgements and other control frames
+                    }
+                    Ok(Some(event)) => {
+                        // Logged even without an event time, since matching uses the update ID
+                        if let (Some(log), Some(update_id)) = (&mut arrivals, event.update_id) {
+                            let arrival = UpdateArrival {
+                                update_id,
+                                event_time: event.event_time,
+                                receive_time: frankfurt_receive_time,
+                            };
+                            if let Err(e) = log.write(&arrival) {
+                                warn!(error = %e, "failed to write arrival log");
+                            }
+                        }
+                        if args.market_stats {
+                            record_quote(&mut collector, &event);
+                        }
+
+                        // Spot bookTicker frames carry no event time and cannot be measured
+                        let Some(binance_event_time) = event.event_time else {
+                            if events_without_time == 0 {
+                                warn!(
+                                    exchange = event.exchange,
+                                    "stream has no event time; use a trade stream (Binance: aggTrade, trade or futures bookTicker)"
+                                );
+                            }
+                            events_without_time += 1;
+                            continue;
+                        };
+
+                        // Calculate latency using the exchange's event time (Binance E field)
+                        // event_time is in milliseconds, frankfurt_receive_time is in nanoseconds
+                        let mut measurement = LatencyMeasurement::new_baseline(
+                            sequence_id,
+                            binance_event_time, // Exchange event time (ms, or µs with timeUnit=MICROSECOND)
+                            frankfurt_receive_time,
+                        )
+                        .with_frame_bytes(text.len() as u32);
+                        if let Some(transaction_time) = event.transaction_time {
+                            measurement = measurement.with_transaction_time(transaction_time);
+                        }
+                        if let Some(market) = market {
+                            measurement = measurement.with_market(market);
+                        }
+                        if let Some(stream) = event.stream {
+                            measurement = measurement.with_stream(streams.intern(stream));
+                        }
+                        if args.split_by_symbol {
+                            measurement = measurement.with_symbol(symbols.intern(&event.symbol));
+                        }
+                        exchange_clock.record(
+                            &mut measurement,
+                            context.server_time().and_then(ServerTimeMonitor::latest),
+                        );
+                        sequence_id += 1;
+
+                        // Report stats every second
+                        let second = collector.record(measurement);
+                        stream_latest(&collector, &mut continuous, &mut sinks);
+                        log_spikes(&mut collector);
+                        alerts.notify(&mut collector);
+                        if let Some(second) = second {
+                            write_second(&mut timeseries, &second);
+                            progress.second(&second, &collector);
+                        }
+                    }
+                    Err(e) => {
+                        // Early failures usually mean a wrong stream; later ones are noise
+                        if sequence_id < 5 {
+                            warn!(error = %e, frame = %text, "failed to parse message");
+                        } else {
+                            debug!(error = %e, frame = %text, "failed to parse message");
+                        }
+                    }
+                }
+            }
+            Ok(Some((
+                _,
+                ExchangeFrame::Pong {
+                    frankfurt_receive_time,
+                    payload,
+                },
+            ))) => {
+                if let Some(rtt_ms) = ping.pong(&payload, frankfurt_receive_time) {
+                    debug!(rtt_ms, "exchange pong");
+                }
+            }
+            Ok(Some((_, ExchangeFrame::Closed(error)))) => {
+                if rollover.closed() {
+                    warn!("WebSocket closed by the exchange at its 24-hour limit");
+                }
+                if let Some((_, standby_write)) = standby.take() {
+                    close_exchange(standby_write);
+                }
+                match error {
+                    Some(e) => {
+                        warn!(conn_id = backoff.stats().recoveries, error = %e, "WebSocket error, reconnecting")
+                    }
+                    _ => warn!(
+                        conn_id = backoff.stats().recoveries,
+                        "WebSocket connection closed, reconnecting"
+                    ),
+                }
+
+                // Keep collecting into the same run once the connection is back
+                progress.status("reconnecting", &collector);
+                let outage_start = Instant::now();
+                let remaining = duration.saturating_sub(collector.elapsed());
+                let Some(ws_stream) = reconnect_to_exchange(
+                    args,
+                    adapter,
+                    &addresses,
+                    &mut backoff,
+                    remaining,
+                    &mut shutdown,
+                )
+                .await
+                else {
+                    outage += outage_start.elapsed();
+                    break;
+                };
+                let read;
+                (write, read) = ws_stream.split();
+                last_conn += 1;
+                conn = last_conn;
+                tokio::spawn(ingest::exchange(read, conn, tx.clone()));
+                rollover.reconnected();
+                if let Some(message) = subscriptions
+                    .as_mut()
+                    .and_then(|subscriptions| subscriptions.resubscribe(epoch_nanos()))
+                {
+                    send_subscription(message, &mut write, &mut standby).await;
+                }
+                outage += outage_start.elapsed();
+                progress.status(
+                    format!(
+                        "connected to {} ({} reconnects)",
+                        adapter.name(),
+                        backoff.stats().recoveries
+                    ),
+                    &collector,
+                );
+            }
+            Ok(None) | Err(_) => {
+                // Woken up to emit rolling results; the loop checks the duration
+            }
+        }
+    }
+
+    drop(progress);
+    info!(measurements = collector.len(), "collection complete");
+    finish_timeseries(args, timeseries, &mut collector)?;
+    if let Some(continuous) = continuous {
+        continuous.finish()?;
+    }
+    alerts.notify(&mut collector);
+    alerts.finish().await;
+    if let (Some(capture), Some(path)) = (capture, &args.capture) {
+        let frames = capture.frames();
+        capture.finish()?;
+        info!(frames, path = %path, "capture written");
+    }
+    if let (Some(arrivals), Some(path)) = (arrivals, &args.arrival_log) {
+        let rows = arrivals.rows();
+        arrivals.finish()?;
+        info!(rows, path = %path, "arrival log written");
+    }
+
+    let mut report = collector.finish("baseline");
+    report.results.region = Some(args.region_name.clone());
+    report.results.exchange = Some(adapter.name().to_string());
+    let reconnects = backoff.stats();
+    if reconnects.failures > 0 {
+        info!(reconnects = %reconnects, "exchange reconnects");
+    }
+    report.results.reconnects = reconnects.recoveries as usize;
+    report.results.outage_ms = outage.as_secs_f64() * 1000.0;
+    report.results.receive_queue = Some(frames.stats());
+    report.results.exchange_ping_rtt = ping.stats(true);
+    report.results.ws_compression = Some(ws_compressed);
+    report.results.exchange_handovers =
+        Some(rollover.handovers().to_vec()).filter(|handovers| !handovers.is_empty());
+    report.results.exchange_addresses =
+        Some(addresses.snapshot()).filter(|addresses| !addresses.is_empty());
+    report.results.subscriptions =
+        subscriptions.map(|subscriptions| subscriptions.changes().to_vec());
+    report.results.server_time = context
+        .server_time()
+        .and_then(|server_time| server_time.results(&exchange_clock));
+    write_report(args, context, &mut report, sinks).await?;
+
+    Ok(())
+}
+
+async fn run_aws_backbone_mode(
+    args: &Args,
+    context: &RunContext,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    let uses_udp = matches!(args.transport.as_str(), "udp" | "dual");
+    let uses_tcp = args.transport != "udp";
+
+    info!(transport = %args.transport, port = args.port, "starting AWS backbone mode");
+    let listening = if args.transport == "uds" {
+        format!("listening on {} (uds)", args.uds_path)
+    } else {
+        format!("listening on port {} ({})", args.port, args.transport)
+    };
+
+    // Every path is received by its own tasks, which feed one queue, or with
+    // --shards one queue per decode shard. Frames are received into pooled
+    // buffers that return once processed.
+    let (queues, mut input) = shards::start(args.shards, args.queue_capacity, context);
+    let tx = match queues.as_slice() {
+        [queue] => FrameSender::Queue(queue.clone()),
+        _ => FrameSender::Shards(queues.clone().into()),
+    };
+    let pool = BufferPool::new(
+        FRAME_BUFFER_BYTES,
+        args.queue_capacity * queues.len() + POOL_SPARE_BUFFERS,
+    );
+
+    // Bind UDP socket(s) and/or TCP listener to configured port
+    let mut drops = Vec::new();
+    let mut grpc_framing = None;
+    if uses_udp {
+        let sockets = if args.shards > 1 {
+            (0..args.shards)
+                .map(|_| shards::bind_udp(args.port))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![UdpSocket::bind(format!("0.0.0.0:{}", args.port)).await?]
+        };
+        info!(
+            port = args.port,
+            sockets = sockets.len(),
+            "UDP socket bound"
+        );
+        // Each shard reads a socket of its own
+        for (socket, queue) in sockets.into_iter().zip(&queues) {
+            if let Some(bytes) = args.recv_buffer_bytes {
+                let effective = udp_drops::set_recv_buffer(&socket, bytes)?;
+                info!(requested = bytes, effective, "UDP receive buffer set");
+                if effective < bytes as u64 {
+                    warn!(
+                        requested = bytes,
+                        effective, "UDP receive buffer capped, raise net.core.rmem_max"
+                    );
+                }
+            }
+            drops.push(DropMonitor::start(&socket));
+            if args.kernel_timestamps {
+                kernel_ts::enable(&socket)?;
+                info!("kernel receive timestamps enabled (SO_TIMESTAMPING)");
+            }
+            tokio::spawn(ingest::udp(
+                socket,
+                args.kernel_timestamps,
+                FrameSender::Queue(queue.clone()),
+                pool.clone(),
+            ));
+        }
+    }
+    drop(queues);
+    // Timestamps on TCP paths are taken when bytes arrive, before TLS decryption
+    let tls = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(tls_acceptor(cert, key)?),
+        _ => None,
+    };
+    match tls {
+        _ if args.transport == "uds" => tcp::listen_unix(&args.uds_path, tx, pool, context).await?,
+        _ if args.transport == "ws" => tcp::listen_ws(args.port, None, tx, pool).await?,
+        _ if args.transport == "grpc" => {
+            grpc_framing = Some(tcp::listen_grpc(args.port, tx, pool).await?)
+        }
+        Some(tls) if args.transport == "wss" => {
+            tcp::listen_ws(args.port, Some(tls), tx, pool).await?
+        }
+        tls if uses_tcp => tcp::listen(args.port, tls, tx, pool, context).await?,
+        _ => drop(tx),
+    }
+    info!("waiting for data from Tokyo forwarder");
+
+    let mut run = BackboneRun {
+        collector: new_collector(args),
+        timeseries: open_timeseries(args)?,
+        // Deduplicate only when events arrive over both paths
+        race: (uses_udp && uses_tcp).then(|| PathRace::new(&["udp", "tcp"])),
+        continuous: start_continuous(args)?,
+        sinks: start_sinks(args, "aws-backbone")?,
+        alerts: Alerts::start(
+            &args.region_name,
+            args.alert_webhook.as_deref(),
+            context.clone(),
+        ),
+        context: context.clone(),
+        stages: StageBudget::new(),
+        overhead: OverheadTracker::new(),
+        one_way: OneWayDelayTracker::new(),
+        exchange_clock: ServerTimeCorrection::new(args.correct_server_time),
+        buffered: 0,
+        foreign_run: 0,
+        incompatible: HashSet::new(),
+        incompatible_events: 0,
+        dscp: None,
+        ws_compressed: None,
+        verifier: exchange_adapter(&args.exchange)
+            .filter(|_| args.verify_payload)
+            .map(|adapter| PayloadVerifier::new(adapter, &args.symbol)),
+        quotes: exchange_adapter(&args.exchange)
+            .filter(|_| args.market_stats)
+            .map(PayloadQuotes::new),
+        forwarders: args.multi_forwarder.then(ForwarderNames::default),
+        acker: args
+            .ack_every
+            .map(|every| acks::Acker::new(every, args.ack_port))
+            .transpose()?,
+        split_by_symbol: args.split_by_symbol,
+        progress: Progress::start(args, "aws-backbone")?,
+    };
+    let mut control = start_control(args).await?;
+    let duration = args.run_duration();
+    let prober = args
+        .path_probe
+        .clone()
+        .map(|target| PathProber::start(target, Duration::from_millis(args.path_probe_interval)));
+
+    print_collecting(args);
+    run.progress.status(listening, &run.collector);
+
+    // Receive events until the duration elapses
+    loop {
+        let elapsed = run.collector.elapsed();
+        if elapsed >= duration {
+            info!("duration reached, stopping collection");
+            break;
+        }
+        run.progress.tick(&run.collector);
+        let wait = emit_continuous(
+            &mut run.continuous,
+            &mut run.collector,
+            args,
+            context,
+            "aws-backbone",
+        )
+        .min(run.progress.refresh_in())
+        .min(duration - elapsed);
+
+        let next = tokio::select! {
+            next = input.recv() => next,
+            // Duration reached or rolling results due; the loop handles both
+            _ = sleep(wait) => continue,
+            request = control::next(&mut control) => {
+                if handle_control(
+                    request,
+                    args,
+                    context,
+                    "aws-backbone",
+                    &run.collector,
+                    &mut run.timeseries,
+                    &mut run.continuous,
+                ) {
+                    break;
+                }
+                continue;
+            }
+            _ = shutdown.wait() => {
+                info!("stopping collection early, writing partial results");
+                break;
+            }
+        };
+
+        let Some(decoded) = next else {
+            error!("all receive tasks stopped");
+            break;
+        };
+        run.handle(decoded);
+    }
+    let input = input.finish().await;
+
+    let BackboneRun {
+        mut collector,
+        timeseries,
+        race,
+        continuous,
+        sinks,
+        mut alerts,
+        stages,
+        overhead,
+        one_way,
+        exchange_clock,
+        buffered,
+        foreign_run,
+        incompatible_events,
+        dscp,
+        ws_compressed,
+        verifier,
+        acker,
+        progress,
+        ..
+    } = run;
+
+    drop(progress);
+    if let Some(acker) = acker {
+        acker.finish();
+    }
+    info!(measurements = collector.len(), "collection complete");
+    let kernel_udp_drops = udp_drops::finish_all(&drops);
+    let path_rtt = match prober {
+        Some(prober) => prober.finish().await,
+        None => None,
+    };
+    finish_timeseries(args, timeseries, &mut collector)?;
+    if let Some(continuous) = continuous {
+        continuous.finish()?;
+    }
+    alerts.notify(&mut collector);
+    alerts.finish().await;
+
+    // Detect packet loss by checking for gaps in sequence IDs
+    let events_lost = collector.events_lost();
+    if events_lost > 0 {
+        warn!(events_lost, "events lost (gaps in sequence IDs)");
+    }
+
+    let mut report = collector.finish("aws-backbone");
+    report.results.region = Some(args.region_name.clone());
+    report.results.path_race = race.map(|race| race.results());
+    report.results.stage_budget = stages.results();
+    report.results.forwarding_overhead = overhead.results();
+    report.results.backbone_one_way = one_way.results();
+    report.results.receive_queue = Some(input.receive_queue);
+    report.results.shards = input.shards;
+    report.results.udp_fragments = Some(input.fragments).filter(|stats| stats.frames > 0);
+    report.results.kernel_udp_drops = kernel_udp_drops;
+    report.results.grpc_framing = grpc_framing
+        .map(|framing| *framing.lock().unwrap())
+        .filter(|framing| framing.messages > 0);
+    report.results.buffered_events = Some(buffered).filter(|&buffered| buffered > 0);
+    report.results.foreign_run_events = Some(foreign_run).filter(|&events| events > 0);
+    report.results.incompatible_events = Some(incompatible_events).filter(|&events| events > 0);
+    report.results.payload_check = verifier.map(|verifier| verifier.stats());
+    report.results.dscp = dscp;
+    report.results.ws_compression = ws_compressed;
+    report.results.path_rtt = path_rtt;
+    report.results.server_time = context
+        .server_time()
+        .and_then(|server_time| server_time.results(&exchange_clock));
+    write_report(args, context, &mut report, sinks).await?;
+
+    Ok(())
+}
+
+/// WebSocket URL for baseline mode: the override if given, else the adapter's
+/// (with --subscribe, its bare /stream endpoint)
+fn ws_url(args: &Args, adapter: &dyn ExchangeAdapter) -> String {
+    let url = args
+        .binance_url
+        .clone()
+        .unwrap_or_else(|| adapter.stream_url(&args.symbol));
+    match split_stream_url(&url) {
+        Some((endpoint, _)) if args.subscribe => endpoint,
+        _ => url,
+    }
+}
+
+/// Streams to request in-band with --subscribe: the one in the URL, or the
+/// symbol's default stream when --binance-url is a bare endpoint
+fn initial_subscriptions(args: &Args, adapter: &dyn ExchangeAdapter) -> Option<Subscriptions> {
+    if !args.subscribe {
+        return None;
+    }
+    let default = adapter.stream_url(&args.symbol);
+    let stream = split_stream_url(args.binance_url.as_deref().unwrap_or(&default))
+        .or_else(|| split_stream_url(&default))
+        .map(|(_, stream)| stream.to_string());
+    Some(Subscriptions::new(stream.into_iter().collect()))
+}
+
+/// Pass a subscribe or unsubscribe command on to the exchange. The control
+/// client is answered once the exchange acknowledges the request.
+async fn request_subscription(
+    request: control::Request,
+    subscriptions: &mut Subscriptions,
+    write: &mut SplitSink<ExchangeStream, Message>,
+    standby: &mut Option<(u64, SplitSink<ExchangeStream, Message>)>,
+    awaiting: &mut Vec<(u64, control::Request)>,
+) {
+    let requested = match &request.command {
+        Command::Subscribe(streams) => subscriptions.subscribe(streams.clone(), epoch_nanos()),
+        Command::Unsubscribe(streams) => subscriptions.unsubscribe(streams.clone(), epoch_nanos()),
+        _ => unreachable!("only subscription commands are passed on"),
+    };
+    match requested {
+        Ok((id, message)) => {
+            send_subscription(message, write, standby).await;
+            awaiting.push((id, request));
+        }
+        Err(e) => request.reply(Err(e)),
+    }
+}
+
+/// Log the exchange's answer to a subscription request and pass it on to
+/// the control client that asked for it
+fn answer_subscription(answer: Answer, awaiting: &mut Vec<(u64, control::Request)>) {
+    // Requests sent to both connections are answered twice
+    let Some(change) = answer.change else {
+        return;
+    };
+    match &change.error {
+        Some(error) => warn!(
+            method = %change.method,
+            streams = ?change.streams,
+            error = %error,
+            "subscription request rejected"
+        ),
+        None => info!(
+            method = %change.method,
+            streams = ?change.streams,
+            ack_ms = change.ack_ms,
+            "subscription acknowledged"
+        ),
+    }
+    if let Some(index) = awaiting.iter().position(|(id, _)| *id == answer.id) {
+        let (_, request) = awaiting.remove(index);
+        request.reply(match change.error {
+            Some(error) => Err(error),
+            None => Ok(json!({
+                "method": change.method,
+                "streams": change.streams,
+                "ack_ms": change.ack_ms,
+            })),
+        });
+    }
+}
+
+/// Send a subscription request on the exchange connection and, if one is
+/// open, the standby
+async fn send_subscription(
+    message: String,
+    write: &mut SplitSink<ExchangeStream, Message>,
+    standby: &mut Option<(u64, SplitSink<ExchangeStream, Message>)>,
+) {
+    if let Some((_, standby_write)) = standby {
+        if let Err(e) = standby_write.send(Message::Text(message.clone())).await {
+            debug!(error = %e, "failed to send subscription request to the standby connection");
+        }
+    }
+    if let Err(e) = write.send(Message::Text(message)).await {
+        warn!(error = %e, "failed to send subscription request");
+    }
+}
+
+/// Validate --exchange-rollover, keeping the text for `Args::exchange_rollover`
+fn check_rollover(text: &str) -> Result<String, String> {
+    parse_rollover(text).map(|_| text.to_string())
+}
+
+/// `--max-staleness-ms`: a positive number of milliseconds
+fn parse_staleness(text: &str) -> Result<Duration, String> {
+    match text.parse::<f64>() {
+        Ok(ms) if ms > 0.0 && ms.is_finite() => Ok(Duration::from_secs_f64(ms / 1_000.0)),
+        _ => Err(format!(
+            "expected a number of milliseconds above 0, got {}",
+            text
+        )),
+    }
+}
+
+/// Close a replaced exchange connection in the background
+fn close_exchange(mut write: SplitSink<ExchangeStream, Message>) {
+    tokio::spawn(async move {
+        if let Err(e) = write.close().await {
+            debug!(error = %e, "failed to close replaced exchange connection");
+        }
+    });
+}
+
+/// Connect and, for venues that need it, send the subscription request.
+/// Where the connection landed is added to `addresses`.
+async fn connect_to_exchange(
+    args: &Args,
+    adapter: &dyn ExchangeAdapter,
+    addresses: &ExchangeAddresses,
+) -> Result<ExchangeStream, tokio_tungstenite::tungstenite::Error> {
+    let mut ws_stream = connect_exchange(&ws_url(args, adapter), args.ws_compression).await?;
+    addresses.record(&ws_stream);
+    if let Some(subscribe) = adapter.subscribe_message(&args.symbol) {
+        ws_stream.send(Message::Text(subscribe)).await?;
+    }
+    Ok(ws_stream)
+}
+
+/// Reconnect to the exchange following the backoff policy, mirroring the
+/// forwarder. Gives up (returning `None`) when `give_up_after` elapses, the
+/// policy's attempt limit is reached, or on shutdown.
+async fn reconnect_to_exchange(
+    args: &Args,
+    adapter: &dyn ExchangeAdapter,
+    addresses: &ExchangeAddresses,
+    backoff: &mut Backoff,
+    give_up_after: Duration,
+    shutdown: &mut Shutdown,
+) -> Option<ExchangeStream> {
+    let reconnecting = async {
+        loop {
+            let Some(delay) = backoff.next_delay() else {
+                error!(
+                    attempts = backoff.consecutive_failures() - 1,
+                    "reconnect attempts exhausted, stopping collection"
+                );
+                return None;
+            };
+            info!(
+                exchange = adapter.name(),
+                delay_ms = delay.as_millis() as u64,
+                breaker_open = backoff.breaker_open(),
+                "attempting to reconnect to exchange WebSocket"
+            );
+            sleep(delay).await;
+
+            match connect_to_exchange(args, adapter, addresses).await {
+                Ok(ws_stream) => {
+                    info!(
+                        exchange = adapter.name(),
+                        "reconnected to exchange WebSocket"
+                    );
+                    backoff.succeeded();
+                    return Some(ws_stream);
+                }
+                Err(e) => warn!(error = %e, "reconnection failed"),
+            }
+        }
+    };
+
+    tokio::select! {
+        ws_stream = reconnecting => ws_stream,
+        _ = sleep(give_up_after) => {
+            info!("duration reached while reconnecting");
+            None
+        }
+        _ = shutdown.wait() => None,
+    }
+}
+
+/// State of an aws-backbone collection run
+struct BackboneRun {
+    collector: Collector,
+    timeseries: Option<TimeSeriesWriter>,
+    race: Option<PathRace>,
+    continuous: Option<Continuous>,
+    sinks: Sinks,
+    alerts: Alerts,
+    context: RunContext,
+    stages: StageBudget,
+    overhead: OverheadTracker,
+    one_way: OneWayDelayTracker, // Backbone latency corrected by both clocks' --clock-sync estimates
+    exchange_clock: ServerTimeCorrection, // End-to-end latency against the exchange's --server-time
+    buffered: usize,             // Measured events the forwarder sent from its retry buffer
+    foreign_run: usize,          // Events from a forwarder of another run, not measured
+    incompatible: HashSet<Option<IpAddr>>, // Forwarders whose handshake announced an unreadable wire format
+    incompatible_events: usize,            // Their events, refused
+    dscp: Option<u8>,                      // Marking the forwarder reported on its latest event
+    ws_compressed: Option<bool>, // Whether the forwarder's exchange connection was compressed, per its latest event
+    verifier: Option<PayloadVerifier>,
+    quotes: Option<PayloadQuotes>,      // --market-stats
+    forwarders: Option<ForwarderNames>, // With --multi-forwarder
+    acker: Option<acks::Acker>,         // --ack-every
+    split_by_symbol: bool,
+    progress: Progress,
+}
+
+/// Names every event's forwarder for --multi-forwarder: its --forwarder-id,
+/// else the address it sends from
+#[derive(Default)]
+struct ForwarderNames {
+    ids: StreamNames,
+    peers: HashMap<IpAddr, Arc<str>>,
+}
+
+impl ForwarderNames {
+    fn name(&mut self, forwarder_id: Option<&str>, peer: Option<IpAddr>) -> Option<Arc<str>> {
+        match (forwarder_id, peer) {
+            (Some(id), _) => Some(self.ids.intern(id)),
+            (None, Some(peer)) => Some(
+                self.peers
+                    .entry(peer)
+                    .or_insert_with(|| Arc::from(peer.to_string()))
+                    .clone(),
+            ),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Initial capacity of a pooled frame buffer; most forwarded events fit
+const FRAME_BUFFER_BYTES: usize = 2048;
+/// Pooled buffers beyond one per queue slot, for frames being received or processed
+const POOL_SPARE_BUFFERS: usize = 16;
+/// Fewest --bootstrap resamples: fewer put the ends of an interval on a handful of them
+const MIN_BOOTSTRAP_ITERATIONS: usize = 100;
+
+impl BackboneRun {
+    /// Record one decoded frame: a handshake, or an event's latency
+    fn handle(&mut self, decoded: Decoded) {
+        let Decoded {
+            path,
+            frankfurt_receive_time,
+            kernel_receive_time,
+            peer,
+            content,
+        } = decoded;
+        let (event, data) = match content {
+            Content::Hello(hello) => {
+                self.handshake(hello, peer, path);
+                return;
+            }
+            _ if self.incompatible.contains(&peer) => {
+                self.incompatible_events += 1;
+                return;
+            }
+            Content::Invalid(e) => {
+                debug!(path, error = %e, "failed to parse ForwardedEvent");
+                return;
+            }
+            Content::Event(event, data) => (event, data),
+        };
+
+        // A stale forwarder still sending to this port; its sequence IDs
+        // would otherwise be mixed into this run's gaps and duplicates
+        if let Some(run_id) = &event.foreign_run_id {
+            if self.foreign_run == 0 {
+                warn!(
+                    run_id,
+                    expected = self.context.run_id().unwrap_or_default(),
+                    "ignoring events from another forwarder run"
+                );
+            }
+            self.foreign_run += 1;
+            return;
+        }
+
+        if let Some(dscp) = event.dscp {
+            if self.dscp.is_some_and(|previous| previous != dscp) {
+                warn!(
+                    previous = self.dscp,
+                    dscp, "forwarder DSCP marking changed during the run"
+                );
+            }
+            self.dscp = Some(dscp);
+        }
+        self.ws_compressed = Some(event.ws_compressed);
+
+        // With redundant paths only the first copy of each event is measured
+        if let Some(race) = &mut self.race {
+            if race.record_arrival(event.sequence_id, path, frankfurt_receive_time)
+                == Arrival::Duplicate
+            {
+                return;
+            }
+        }
+
+        // Each forwarder numbers its own events
+        let source = match &mut self.forwarders {
+            Some(forwarders) => forwarders.name(event.forwarder_id.as_deref(), peer),
+            None => None,
+        };
+
+        // A network duplicate of an event already measured
+        if self
+            .collector
+            .check_duplicate(source.as_deref(), event.sequence_id)
+        {
+            return;
+        }
+        if event.buffered {
+            self.buffered += 1;
+        }
+        // The verifier compares the payload with the envelope as parsed from it
+        let view = self
+            .verifier
+            .as_ref()
+            .and_then(|_| ForwardedEventView::parse(&data).ok());
+        if let (Some(verifier), Some(view)) = (&mut self.verifier, view) {
+            let check = verifier.check(&data, &view);
+            if check != PayloadCheck::Match {
+                // Usually every event fails the same way, so only the first is a warning
+                if verifier.stats().failed() == 1 {
+                    warn!(
+                        sequence_id = event.sequence_id,
+                        binance_event_time = event.binance_event_time,
+                        ?check,
+                        "forwarded payload does not match its envelope"
+                    );
+                } else {
+                    debug!(sequence_id = event.sequence_id, ?check, "payload mismatch");
+                }
+            }
+        }
+
+        if let Some((bid, ask)) = self.quotes.as_ref().and_then(|quotes| quotes.read(&data)) {
+            self.collector.record_quote(bid, ask);
+        }
+
+        // Stage timestamps refer to the previous event, which another
+        // forwarder's events would interleave with
+        if let Some(stages) = event.stages.filter(|_| self.forwarders.is_none()) {
+            self.stages.record(
+                event.sequence_id,
+                event.tokyo_receive_timestamp,
+                stages.parsed,
+                stages.previous(),
+                frankfurt_receive_time,
+            );
+        }
+
+        // Events held back by the forwarder would skew the backbone average
+        if let Some(overhead_ns) = event.forwarding_overhead_ns {
+            if !event.buffered && !event.retransmitted {
+                self.overhead.record(
+                    overhead_ns,
+                    frankfurt_receive_time - event.tokyo_receive_timestamp,
+                );
+            }
+        }
+        if let (Some(forwarder), Some(receiver)) = (event.clock, self.context.clock_estimate()) {
+            if !event.buffered && !event.retransmitted {
+                self.one_way.record(
+                    event.tokyo_receive_timestamp,
+                    frankfurt_receive_time,
+                    forwarder,
+                    receiver,
+                );
+            }
+        }
+        // Held-back events left the forwarder later than their stamps say
+        if let Some(acker) = self.acker.as_mut() {
+            if !event.buffered && !event.retransmitted {
+                let sent = event.tokyo_receive_timestamp
+                    + event.forwarding_overhead_ns.unwrap_or(0)
+                    + event.send_queue_delay_ns.unwrap_or(0);
+                acker.event(event.sequence_id, peer, sent, frankfurt_receive_time);
+            }
+        }
+
+        // Calculate latencies
+        let mut measurement = LatencyMeasurement::new_aws_backbone(
+            event.sequence_id,
+            event.binance_event_time,
+            event.tokyo_receive_timestamp,
+            frankfurt_receive_time,
+        );
+        if let Some(kernel_receive_time) = kernel_receive_time {
+            measurement = measurement.with_kernel_receive_time(kernel_receive_time);
+        }
+        if let Some(transaction_time) = event.binance_transaction_time {
+            measurement = measurement.with_transaction_time(transaction_time);
+        }
+        if let Some(stream) = event.stream {
+            measurement = measurement.with_stream(stream);
+        }
+        if let Some(symbol) = event.symbol.filter(|_| self.split_by_symbol) {
+            measurement = measurement.with_symbol(symbol);
+        }
+        if let Some(frame_bytes) = event.frame_bytes {
+            measurement = measurement.with_frame_bytes(frame_bytes);
+        }
+        measurement = measurement.with_packet_bytes(data.len() as u32);
+        if let Some(overhead_ns) = event.forwarding_overhead_ns {
+            measurement = measurement.with_forwarding_overhead(overhead_ns);
+        }
+        if let Some(delay_ns) = event.send_queue_delay_ns {
+            measurement = measurement.with_send_queue_delay(delay_ns);
+        }
+        if let Some(source) = source {
+            measurement = measurement.with_source(source);
+        }
+        if event.buffered {
+            measurement = measurement.with_delivery_class(DeliveryClass::Buffered);
+        } else if event.retransmitted {
+            measurement = measurement.with_delivery_class(DeliveryClass::Retransmitted);
+        }
+
+        let server_time = self
+            .context
+            .server_time()
+            .and_then(ServerTimeMonitor::latest);
+        self.exchange_clock.record(&mut measurement, server_time);
+
+        // Report stats every second
+        let second = self.collector.record(measurement);
+        stream_latest(&self.collector, &mut self.continuous, &mut self.sinks);
+        log_spikes(&mut self.collector);
+        self.alerts.notify(&mut self.collector);
+        if let Some(second) = second {
+            write_second(&mut self.timeseries, &second);
+            self.progress.second(&second, &self.collector);
+        }
+    }
+}
+
+impl BackboneRun {
+    /// Register a forwarder, refusing its events if it is incompatible
+    fn handshake(
+        &mut self,
+        hello: Result<ForwarderHello, serde_json::Error>,
+        peer: Option<IpAddr>,
+        path: &'static str,
+    ) {
+        let hello = match hello {
+            Ok(hello) => hello,
+            Err(e) => {
+                warn!(path, ?peer, error = %e, "malformed forwarder handshake");
+                return;
+            }
+        };
+        let compatible = hello.check_compatible();
+        self.context
+            .register(&hello, peer, path, compatible.is_ok());
+        match compatible {
+            Ok(()) => {
+                self.incompatible.remove(&peer);
+            }
+            Err(e) => {
+                if self.incompatible.insert(peer) {
+                    error!(
+                        forwarder_id = hello.forwarder_id.as_deref(),
+                        ?peer,
+                        version = %hello.version,
+                        error = %e,
+                        "refusing events from an incompatible forwarder"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Create a collector configured from the command line
+fn new_collector(args: &Args) -> Collector {
+    let mut collector = Collector::new()
+        .with_warmup(Duration::from_secs(args.warmup_secs))
+        .with_percentiles(args.percentiles.clone())
+        .with_sessions(args.sessions.clone());
+    if args.continuous() {
+        collector = collector.with_window(Duration::from_secs(args.window_secs));
+    }
+    if args.continuous() || args.sample_rate.is_some() {
+        collector = collector.with_streaming_stats(args.digest_accuracy);
+    }
+    if let Some(rate) = args.sample_rate {
+        collector = collector.with_sample_rate(rate);
+    }
+    if let Some(k) = args.spike_mad_k {
+        collector = collector.with_spike_detection(k);
+    }
+    if args.quantization {
+        collector = collector.with_quantization();
+    }
+    if let Some(max_staleness) = args.max_staleness_ms {
+        collector = collector.with_max_staleness(max_staleness);
+    }
+    if let Some(iterations) = args.bootstrap {
+        collector = collector.with_bootstrap(iterations);
+    }
+    if let Some(thresholds) = args.alert_thresholds() {
+        collector = collector.with_alerts(thresholds);
+    }
+    collector
+}
+
+fn print_collecting(args: &Args) {
+    if args.continuous() {
+        info!(
+            emit_interval_secs = args.emit_interval,
+            results_dir = %args.results_dir,
+            "collecting continuously (Ctrl+C to stop)"
+        );
+    } else {
+        info!(duration_secs = args.duration, "collecting data");
+    }
+}
+
+/// Set up continuous-mode outputs when running as a daemon
+fn start_continuous(args: &Args) -> Result<Option<Continuous>, std::io::Error> {
+    if !args.continuous() {
+        return Ok(None);
+    }
+    Continuous::start(
+        &args.results_dir,
+        Duration::from_secs(args.emit_interval),
+        args.rotation(Some(Duration::from_secs(3600))),
+    )
+    .map(Some)
+}
+
+/// Open the control channel if one is configured
+async fn start_control(args: &Args) -> Result<Option<Control>, std::io::Error> {
+    match &args.control_addr {
+        Some(addr) => Control::start(addr).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Answer a control-channel request; returns true when collection should stop
+fn handle_control(
+    request: control::Request,
+    args: &Args,
+    context: &RunContext,
+    setup_type: &str,
+    collector: &Collector,
+    timeseries: &mut Option<TimeSeriesWriter>,
+    continuous: &mut Option<Continuous>,
+) -> bool {
+    let result = match &request.command {
+        Command::Status => {
+            let results = collector.snapshot(setup_type);
+            Ok(json!({
+                "mode": setup_type,
+                "run_id": context.run_id(),
+                "elapsed_secs": collector.elapsed().as_secs(),
+                "in_warmup": collector.in_warmup(),
+                "sample_count": results.sample_count,
+                "events_lost": results.events_lost,
+                "avg_latency_ms": results.avg_latency_ms,
+                "median_latency_ms": results.median_latency_ms,
+                "p99_latency_ms": results.p99_latency_ms,
+                "output": args.output,
+            }))
+        }
+        Command::Flush => {
+            let flushed = timeseries
+                .as_mut()
+                .map_or(Ok(()), TimeSeriesWriter::flush)
+                .and_then(|()| continuous.as_mut().map_or(Ok(()), Continuous::flush));
+            flushed
+                .map(|()| json!({ "flushed": true }))
+                .map_err(|e| e.to_string())
+        }
+        Command::RotateCsv => match continuous {
+            Some(continuous) => continuous
+                .rotate()
+                .map(|path| json!({ "csv": path.display().to_string() }))
+                .map_err(|e| e.to_string()),
+            None => Err("rotate-csv needs --mode continuous".to_string()),
+        },
+        Command::StopAndReport => Ok(json!({ "stopping": true, "output": args.output })),
+        Command::Subscribe(_) | Command::Unsubscribe(_) => {
+            Err("subscribe and unsubscribe need baseline mode with --subscribe".to_string())
+        }
+    };
+
+    let stop = request.command == Command::StopAndReport;
+    request.reply(result);
+    if stop {
+        info!("stop requested over the control channel, writing results");
+    }
+    stop
+}
+
+/// Open every output sink of the run
+fn start_sinks(args: &Args, setup_type: &str) -> Result<Sinks, ExperimentError> {
+    let influx = InfluxConfig {
+        url: String::new(), // Taken from each sink
+        token: args.influx_token.clone(),
+        org: args.influx_org.clone(),
+        bucket: args.influx_bucket.clone(),
+    };
+    let tags = [
+        ("mode", setup_type),
+        ("symbol", args.symbol.as_str()),
+        ("region", args.region_name.as_str()),
+    ];
+    let mut sinks: Vec<_> = args
+        .sinks()
+        .iter()
+        .map(|sink| sink.open(&influx, &tags))
+        .collect::<Result<_, _>>()?;
+    if args.split_by_symbol {
+        for sink in args.sinks() {
+            if let SinkSpec::Csv(path) = sink {
+                sinks.push(Box::new(SymbolCsvSink::new(&path)));
+            }
+        }
+    }
+    Ok(Sinks::new(sinks))
+}
+
+/// Hand the latest measurement to the outputs that stream every event
+fn stream_latest(collector: &Collector, continuous: &mut Option<Continuous>, sinks: &mut Sinks) {
+    let Some(m) = collector.last_measurement() else {
+        return;
+    };
+    if let Some(continuous) = continuous {
+        continuous.write(m);
+    }
+    sinks.write(m);
+}
+
+/// Write rolling results if due and return how long until the next ones
+fn emit_continuous(
+    continuous: &mut Option<Continuous>,
+    collector: &mut Collector,
+    args: &Args,
+    context: &RunContext,
+    setup_type: &str,
+) -> Duration {
+    let Some(continuous) = continuous else {
+        return Duration::MAX;
+    };
+    let emitted = continuous.emit_if_due(|| {
+        let mut results = collector.snapshot(setup_type);
+        results.region = Some(args.region_name.clone());
+        results.run_id = context.run_id().map(str::to_string);
+        results.metadata = context.current();
+        results
+    });
+    if emitted {
+        collector.start_streaming_interval();
+    }
+    continuous.until_emit()
+}
+
+/// Feed the best bid and ask of a book update to the per-second market stats
+fn record_quote(collector: &mut Collector, event: &TickerEvent) {
+    if let (Some(bid), Some(ask)) = (event.best_bid(), event.best_ask()) {
+        collector.record_quote(bid, ask);
+    }
+}
+
+/// List the files the run wrote, so templated names can be found
+fn print_output_files(files: &[PathBuf]) {
+    let files: Vec<&PathBuf> = files.iter().filter(|file| file.is_file()).collect();
+    if files.is_empty() {
+        return;
+    }
+    println!("\n=== Output Files ===");
+    for file in files {
+        println!("{}", file.display());
+    }
+}
+
+/// Log spikes as soon as they are detected
+fn log_spikes(collector: &mut Collector) {
+    for spike in collector.new_spikes() {
+        warn!(
+            elapsed_secs = spike.elapsed_secs,
+            sequence_id = spike.sequence_id,
+            latency_ms = spike.latency_ms,
+            threshold_ms = spike.threshold_ms,
+            magnitude = spike.magnitude,
+            events_per_sec = spike.events_per_sec,
+            seq_gap = spike.seq_gap,
+            send_queue_delay_ms = spike.send_queue_delay_ms,
+            "latency spike"
+        );
+    }
+}
+
+/// Finish the sinks (writing the results JSON), the heatmap and the
+/// OpenMetrics histograms, then print the summary
+async fn write_report(
+    args: &Args,
+    context: &RunContext,
+    report: &mut Report,
+    sinks: Sinks,
+) -> Result<(), ExperimentError> {
+    report.results.run_id = context.run_id().map(str::to_string);
+    report.results.metadata = context.current();
+    if args.split_by_symbol {
+        let symbols = split_by_symbol(&report.measurements);
+        if symbols.is_empty() {
+            warn!(
+                "no event named its symbol; forwarders older than --split-by-symbol do not send it"
+            );
+        }
+        report.results.symbols = Some(symbols);
+    }
+    if let Some(heatmap_path) = &args.heatmap_output {
+        Heatmap::from_measurements(&report.measurements, args.heatmap_interval)
+            .write(heatmap_path)?;
+        info!(path = %heatmap_path, "latency heatmap written");
+    }
+    if let Some(path) = &args.openmetrics_output {
+        report.write_openmetrics(path, &args.openmetrics_buckets)?;
+        info!(path = %path, "OpenMetrics histograms written");
+    }
+    sinks.finish(report).await?;
+
+    if !args.stdout_sink() {
+        report.print_summary();
+    }
+
+    Ok(())
+}
+
+/// Open the per-second time-series file if one was requested
+fn open_timeseries(args: &Args) -> Result<Option<TimeSeriesWriter>, std::io::Error> {
+    args.timeseries_output
+        .as_deref()
+        .map(TimeSeriesWriter::create)
+        .transpose()
+}
+
+/// Append a finished per-second window to the time-series file, if enabled
+fn write_second(timeseries: &mut Option<TimeSeriesWriter>, second: &SecondStats) {
+    if let Some(writer) = timeseries {
+        if let Err(e) = writer.write(second) {
+            warn!(error = %e, "failed to write time-series row");
+        }
+    }
+}
+
+/// Write the final partial window and flush the time-series file
+fn finish_timeseries(
+    args: &Args,
+    mut timeseries: Option<TimeSeriesWriter>,
+    collector: &mut Collector,
+) -> Result<(), std::io::Error> {
+    if let (Some(writer), Some(path)) = (timeseries.as_mut(), &args.timeseries_output) {
+        if let Some(second) = collector.flush_second() {
+            writer.write(&second)?;
+        }
+        writer.flush()?;
+        info!(path = %path, "time-series written");
+    }
+    Ok(())
+}
@@ -0,0 +1,343 @@
+// Run environment recorded in the results
+//
+// Captured once at startup in the background: IMDS, chronyc and, off Linux,
+// the hostname and kernel version commands may take a moment (or time out off
+// EC2) and must not delay collection. The run ID is set from --run-id, or
+// taken from the first forwarded event that carries one.
+// With --clock-sync the clock error is also read throughout the run, and
+// every forwarder that announces itself is added to the registry, along with
+// the restart history its heartbeats report.
+
+use crate::server_time::ServerTimeMonitor;
+use chrono::Utc;
+use latency_core::{
+    ClockEstimate, ClockSource, ForwarderRegistration, ForwarderSupervision, RunMetadata,
+};
+use shared::{chrony_tracking, ClockMonitor, ForwarderHello};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const IMDS: &str = "http://169.254.169.254/latest";
+const IMDS_TIMEOUT: Duration = Duration::from_secs(1);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// State of one receiver run: its environment, run ID, clock readings and
+/// forwarders. `run()` builds one and hands it to everything that reads or
+/// records them, so runs in one process (as in the integration tests) do not
+/// see each other's. Clones share the same run.
+#[derive(Clone, Default)]
+pub struct RunContext {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    metadata: OnceLock<RunMetadata>,
+    run_id: OnceLock<String>,
+    clock: OnceLock<ClockMonitor>,
+    server_time: OnceLock<ServerTimeMonitor>,
+    forwarders: Mutex<Vec<ForwarderRegistration>>,
+}
+
+impl Drop for Inner {
+    /// The run is over: nothing reads the exchange's clock any more
+    fn drop(&mut self) {
+        if let Some(server_time) = self.server_time.get() {
+            server_time.stop();
+        }
+    }
+}
+
+impl RunContext {
+    /// Start capturing the run environment; the start time is taken
+    /// immediately. `command_line` is the program name and arguments given
+    /// to `run()`.
+    pub fn capture(&self, command_line: Vec<String>) {
+        let started_at = Utc::now().to_rfc3339();
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let (instance, clock_sync, hostname, kernel_version) = tokio::join!(
+                ec2_instance(),
+                chrony_tracking(),
+                hostname(),
+                kernel_version()
+            );
+            let (instance_type, availability_zone, aws_region) = instance.unwrap_or_default();
+            let metadata = RunMetadata {
+                hostname,
+                instance_type,
+                availability_zone,
+                aws_region,
+                kernel_version,
+                platform: Some(platform()),
+                crate_version: env!("CARGO_PKG_VERSION").to_string(),
+                command_line: redacted_args(command_line.into_iter()),
+                started_at,
+                ended_at: None,
+                clock_sync,
+                clock_error: Vec::new(),
+                forwarders: Vec::new(),
+            };
+            let _ = inner.metadata.set(metadata);
+        });
+    }
+
+    /// Metadata for results written now, if capture has finished
+    pub fn current(&self) -> Option<RunMetadata> {
+        let mut metadata = self.inner.metadata.get()?.clone();
+        metadata.ended_at = Some(Utc::now().to_rfc3339());
+        metadata.clock_error = self
+            .inner
+            .clock
+            .get()
+            .map(ClockMonitor::samples)
+            .unwrap_or_default();
+        metadata.forwarders = self.forwarders();
+        Some(metadata)
+    }
+
+    /// Every forwarder registered so far
+    pub fn forwarders(&self) -> Vec<ForwarderRegistration> {
+        self.inner.forwarders.lock().unwrap().clone()
+    }
+
+    /// Read the clock error from `source` every `period` (--clock-sync)
+    pub fn start_clock_sync(&self, source: ClockSource, period: Duration) {
+        let _ = self.inner.clock.set(ClockMonitor::start(source, period));
+    }
+
+    /// The receiver's latest clock error estimate, with --clock-sync
+    pub fn clock_estimate(&self) -> Option<ClockEstimate> {
+        self.inner.clock.get()?.latest()
+    }
+
+    /// Keep the exchange server time poller (--server-time)
+    pub fn set_server_time(&self, monitor: ServerTimeMonitor) {
+        let _ = self.inner.server_time.set(monitor);
+    }
+
+    /// The exchange server time poller, with --server-time
+    pub fn server_time(&self) -> Option<&ServerTimeMonitor> {
+        self.inner.server_time.get()
+    }
+
+    /// Record a forwarder's handshake. A forwarder reconnecting from the same
+    /// address with the same announcement is counted, not listed again.
+    pub fn register(
+        &self,
+        hello: &ForwarderHello,
+        peer: Option<IpAddr>,
+        path: &str,
+        compatible: bool,
+    ) {
+        let peer = peer.map(|peer| peer.to_string());
+        let mut forwarders = self.inner.forwarders.lock().unwrap();
+        let known = forwarders
+            .iter_mut()
+            .find(|known| announced(known, hello, &peer, path));
+        match known {
+            Some(known) => known.handshakes += 1,
+            None => {
+                info!(
+                    forwarder_id = hello.forwarder_id.as_deref(),
+                    region = %hello.region,
+                    peer = peer.as_deref(),
+                    path,
+                    version = %hello.version,
+                    wire_format = hello.wire_format,
+                    compatible,
+                    "forwarder registered"
+                );
+                forwarders.push(ForwarderRegistration {
+                    forwarder_id: hello.forwarder_id.clone(),
+                    region: hello.region.clone(),
+                    streams: hello.streams.clone(),
+                    wire_format: hello.wire_format,
+                    version: hello.version.clone(),
+                    peer,
+                    path: path.to_string(),
+                    registered_at: Utc::now().to_rfc3339(),
+                    handshakes: 1,
+                    compatible,
+                    supervision: None,
+                    restarts_during_run: 0,
+                });
+            }
+        }
+    }
+
+    /// Keep the restart history from a heartbeat of the forwarder that sent
+    /// `hello` on the same connection. A forwarder that restarted since its
+    /// previous heartbeat is counted and warned about.
+    pub fn record_supervision(
+        &self,
+        hello: &ForwarderHello,
+        peer: Option<IpAddr>,
+        path: &str,
+        supervision: ForwarderSupervision,
+    ) {
+        let peer = peer.map(|peer| peer.to_string());
+        let mut forwarders = self.inner.forwarders.lock().unwrap();
+        let Some(known) = forwarders
+            .iter_mut()
+            .find(|known| announced(known, hello, &peer, path))
+        else {
+            return;
+        };
+        if let Some(earlier) = &known.supervision {
+            let restarts = supervision.restarts_since(earlier);
+            if restarts > 0 {
+                warn!(
+                    forwarder_id = known.forwarder_id.as_deref(),
+                    peer = peer.as_deref(),
+                    restarts,
+                    last_error = supervision.last_error.as_deref(),
+                    "forwarder restarted"
+                );
+                known.restarts_during_run += restarts;
+            }
+        }
+        known.supervision = Some(supervision);
+    }
+
+    /// Fix the run ID before any event arrives
+    pub fn set_run_id(&self, id: String) {
+        let _ = self.inner.run_id.set(id);
+    }
+
+    /// The run ID, once set or adopted
+    pub fn run_id(&self) -> Option<&str> {
+        self.inner.run_id.get().map(String::as_str)
+    }
+
+    /// Whether an event of run `id` belongs to this run. Without a run ID
+    /// yet, the first one seen is adopted.
+    pub fn matches_run_id(&self, id: &str) -> bool {
+        self.inner.run_id.get_or_init(|| {
+            info!(run_id = id, "adopted the forwarder's run ID");
+            id.to_string()
+        }) == id
+    }
+}
+
+/// Whether `known` is the registration of `hello` from `peer` over `path`
+fn announced(
+    known: &ForwarderRegistration,
+    hello: &ForwarderHello,
+    peer: &Option<String>,
+    path: &str,
+) -> bool {
+    known.forwarder_id == hello.forwarder_id
+        && known.peer == *peer
+        && known.path == path
+        && known.region == hello.region
+        && known.streams == hello.streams
+        && known.wire_format == hello.wire_format
+        && known.version == hello.version
+}
+
+/// OS and architecture the receiver was built for, e.g. macos-aarch64
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// From /proc on Linux; elsewhere from the environment, else `hostname`
+async fn hostname() -> Option<String> {
+    let known = read_trimmed("/proc/sys/kernel/hostname")
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok());
+    match known {
+        Some(hostname) => Some(hostname),
+        None => command_output("hostname", &[]).await,
+    }
+}
+
+/// The kernel release, or on Windows the version `ver` prints
+async fn kernel_version() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        read_trimmed("/proc/sys/kernel/osrelease")
+    } else if cfg!(windows) {
+        command_output("cmd", &["/C", "ver"]).await
+    } else {
+        command_output("uname", &["-r"]).await
+    }
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    let value = std::fs::read_to_string(path).ok()?;
+    Some(value.trim().to_string())
+}
+
+/// Trimmed standard output of a command that succeeded within COMMAND_TIMEOUT
+async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = tokio::time::timeout(
+        COMMAND_TIMEOUT,
+        tokio::process::Command::new(program).args(args).output(),
+    )
+    .await
+    .ok()?
+    .map_err(|e| debug!(program, error = %e, "command not available"))
+    .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+/// Command line with the values of secret flags replaced
+fn redacted_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut redact_next = false;
+    args.map(|arg| {
+        if std::mem::take(&mut redact_next) {
+            return "<redacted>".to_string();
+        }
+        match arg.split_once('=') {
+            Some((flag, _)) if is_secret(flag) => format!("{}=<redacted>", flag),
+            None if arg.starts_with("--") && is_secret(&arg) => {
+                redact_next = true;
+                arg
+            }
+            _ => arg,
+        }
+    })
+    .collect()
+}
+
+/// Flags whose value grants access: API tokens, and webhook URLs (Slack's embed a secret)
+fn is_secret(flag: &str) -> bool {
+    flag.contains("token") || flag.contains("webhook")
+}
+
+/// Instance type, availability zone and region from EC2 instance metadata (IMDSv2)
+async fn ec2_instance() -> Option<(Option<String>, Option<String>, Option<String>)> {
+    let client = reqwest::Client::builder()
+        .timeout(IMDS_TIMEOUT)
+        .build()
+        .ok()?;
+    let token = client
+        .put(format!("{}/api/token", IMDS))
+        .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| debug!(error = %e, "EC2 instance metadata not available"))
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+
+    let get = |path: &'static str| {
+        let request = client
+            .get(format!("{}/meta-data/{}", IMDS, path))
+            .header("X-aws-ec2-metadata-token", &token);
+        async move {
+            let response = request.send().await.ok()?.error_for_status().ok()?;
+            response.text().await.ok()
+        }
+    };
+    Some(tokio::join!(
+        get("instance-type"),
+        get("placement/availability-zone"),
+        get("placement/region")
+    ))
+}
@@ -0,0 +1,182 @@
+// Path RTT prober (--path-probe)
+//
+// Samples the round trip between this host and the forwarder host while the
+// experiment runs, so application latency can be read against what the network
+// path itself costs at the same moment. UDP probes are echoed by the
+// forwarder's --echo-port responder; TCP probes time the handshake with any
+// port on the forwarder host.
+
+use crate::ingest::epoch_nanos;
+use latency_core::{PingRttStats, PingTracker};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// How long a TCP probe may take before it counts as lost
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where to probe, from `udp://HOST:PORT` or `tcp://HOST:PORT`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeTarget {
+    Udp(String),
+    Tcp(String),
+}
+
+impl FromStr for ProbeTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let target = match s.split_once("://") {
+            Some(("udp", addr)) => ProbeTarget::Udp(addr.to_string()),
+            Some(("tcp", addr)) => ProbeTarget::Tcp(addr.to_string()),
+            _ => {
+                return Err(format!(
+                    "expected udp://HOST:PORT or tcp://HOST:PORT, got {}",
+                    s
+                ))
+            }
+        };
+        match &target {
+            ProbeTarget::Udp(addr) | ProbeTarget::Tcp(addr) if addr.rsplit_once(':').is_some() => {
+                Ok(target)
+            }
+            _ => Err(format!("missing port in {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ProbeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeTarget::Udp(addr) => write!(f, "udp://{}", addr),
+            ProbeTarget::Tcp(addr) => write!(f, "tcp://{}", addr),
+        }
+    }
+}
+
+/// A running prober; `finish` stops it and returns what it measured
+pub struct PathProber {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<PingTracker>,
+}
+
+impl PathProber {
+    pub fn start(target: ProbeTarget, period: Duration) -> Self {
+        info!(target = %target, interval_ms = period.as_millis() as u64, "probing path RTT");
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            match target {
+                ProbeTarget::Udp(addr) => probe_udp(&addr, period, stopped).await,
+                ProbeTarget::Tcp(addr) => probe_tcp(&addr, period, stopped).await,
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// Stop probing; `None` when no probe was answered
+    pub async fn finish(self) -> Option<PingRttStats> {
+        let _ = self.stop.send(());
+        let stats = self.task.await.ok()?.stats(true);
+        if stats.is_none() {
+            warn!("no path probe was answered");
+        }
+        stats
+    }
+}
+
+/// Send a timestamped datagram every `period` and time the echoes
+async fn probe_udp(
+    addr: &str,
+    period: Duration,
+    mut stopped: oneshot::Receiver<()>,
+) -> PingTracker {
+    let mut rtt = PingTracker::new();
+    let socket = match connect_udp(addr).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(addr, error = %e, "path probe socket failed");
+            return rtt;
+        }
+    };
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut buf = [0u8; 64];
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let payload = rtt.payload(epoch_nanos());
+                if let Err(e) = socket.send(&payload).await {
+                    debug!(error = %e, "path probe send failed");
+                }
+            }
+            received = socket.recv(&mut buf) => match received {
+                Ok(len) => {
+                    if let Some(rtt_ms) = rtt.pong(&buf[..len], epoch_nanos()) {
+                        debug!(rtt_ms, "path probe");
+                    }
+                }
+                // ICMP port unreachable from an earlier probe; the next one retries
+                Err(e) => debug!(error = %e, "path probe receive failed"),
+            },
+            _ = &mut stopped => return rtt,
+        }
+    }
+}
+
+async fn connect_udp(addr: &str) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+/// Time a TCP handshake every `period`. A refused connection is answered by
+/// the host's kernel just like an accepted one, so it counts as a sample too.
+async fn probe_tcp(
+    addr: &str,
+    period: Duration,
+    mut stopped: oneshot::Receiver<()>,
+) -> PingTracker {
+    let mut rtt = PingTracker::new();
+    // Resolve once so lookups are not timed
+    let addr = match lookup_host(addr).await.map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => addr,
+        Ok(None) => {
+            warn!(addr, "path probe address did not resolve");
+            return rtt;
+        }
+        Err(e) => {
+            warn!(addr, error = %e, "path probe address did not resolve");
+            return rtt;
+        }
+    };
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = &mut stopped => return rtt,
+        }
+        let sent_at = epoch_nanos();
+        rtt.payload(sent_at);
+        match timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {}
+            Ok(Err(e)) => {
+                debug!(error = %e, "path probe connect failed");
+                continue;
+            }
+            Err(_) => {
+                debug!("path probe timed out");
+                continue;
+            }
+        }
+        if let Some(rtt_ms) = rtt.record(sent_at, epoch_nanos()) {
+            debug!(rtt_ms, "path probe");
+        }
+    }
+}
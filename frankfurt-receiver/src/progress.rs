@@ -0,0 +1,84 @@
+// Live output while collecting: the per-second table, or the --tui dashboard
+
+use crate::tui::{self, Dashboard};
+use crate::Args;
+use latency_core::{Collector, SecondStats};
+use std::time::Duration;
+
+pub enum Progress {
+    Table { backbone: bool }, // Backbone runs add a backbone latency column
+    Dashboard(Box<Dashboard>),
+    Hidden, // Stdout carries the raw measurements (--sink stdout)
+}
+
+impl Progress {
+    pub fn start(args: &Args, setup_type: &str) -> std::io::Result<Self> {
+        let backbone = setup_type == "aws-backbone";
+        if args.tui {
+            let title = format!(
+                " frankfurt-receiver · {} · {} {} · {} ",
+                setup_type, args.exchange, args.symbol, args.region_name
+            );
+            return Ok(Progress::Dashboard(Box::new(Dashboard::start(title)?)));
+        }
+        if args.stdout_sink() {
+            return Ok(Progress::Hidden);
+        }
+
+        if backbone {
+            println!("Time | Events/s | E2E Latency | Backbone | Min E2E | Max E2E");
+            println!("-----|----------|-------------|----------|---------|--------");
+        } else {
+            println!("Time | Events/s | Avg Latency | Min | Max");
+            println!("-----|----------|-------------|-----|-----");
+        }
+        Ok(Progress::Table { backbone })
+    }
+
+    /// Show a closed one-second window
+    pub fn second(&mut self, second: &SecondStats, collector: &Collector) {
+        match self {
+            Progress::Table { backbone: true } => println!(
+                "{:>4}s | {:>8} | {:>9.2} ms | {:>6.2} ms | {:>7.0} | {:>7.0}",
+                second.elapsed_secs,
+                second.events,
+                second.avg_latency_ms,
+                second.avg_backbone_latency_ms.unwrap_or(0.0),
+                second.min_latency_ms,
+                second.max_latency_ms
+            ),
+            Progress::Table { backbone: false } => println!(
+                "{:>4}s | {:>8} | {:>9.2} ms | {:>3.0} | {:>3.0}",
+                second.elapsed_secs,
+                second.events,
+                second.avg_latency_ms,
+                second.min_latency_ms,
+                second.max_latency_ms
+            ),
+            Progress::Dashboard(dashboard) => dashboard.second(second, collector),
+            Progress::Hidden => {}
+        }
+    }
+
+    /// Connection status shown by the dashboard (the table relies on the logs)
+    pub fn status(&mut self, status: impl Into<String>, collector: &Collector) {
+        if let Progress::Dashboard(dashboard) = self {
+            dashboard.set_status(status.into(), collector);
+        }
+    }
+
+    /// Keep the dashboard current between events
+    pub fn tick(&mut self, collector: &Collector) {
+        if let Progress::Dashboard(dashboard) = self {
+            dashboard.tick(collector);
+        }
+    }
+
+    /// Longest the collection loop may wait before calling `tick`
+    pub fn refresh_in(&self) -> Duration {
+        match self {
+            Progress::Table { .. } | Progress::Hidden => Duration::MAX,
+            Progress::Dashboard(_) => tui::REDRAW_INTERVAL,
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Loopback self-test (--self-test)
+//
+// Runs the aws-backbone receive path against a mock forwarder in the same
+// process. The mock streams synthetic Binance aggTrade events over UDP on
+// localhost with known timestamps and a known pattern of gaps, a duplicate, a
+// reordered pair, one event large enough to be fragmented and one from another
+// forwarder run, then the written results are checked against what was sent.
+
+use crate::ingest::epoch_nanos;
+use crate::metadata::RunContext;
+use crate::{run_aws_backbone_mode, Args};
+use latency_core::ExperimentResults;
+use shared::{fragment, ForwardedEvent, Shutdown};
+use std::borrow::Cow;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::sleep;
+
+const EVENTS: u64 = 200;
+const LOST: [u64; 2] = [50, 51];
+const DUPLICATE: u64 = 100;
+const REORDERED: u64 = 150; // Sent after REORDERED + 1
+const FRAGMENTED: u64 = 120;
+const STALE: u64 = 10; // Also sent with another run ID, as a stale forwarder would
+const RUN_ID: &str = "self-test";
+const SYMBOL: &str = "BTC-USDT";
+const MAX_DATAGRAM: usize = 1400;
+
+/// Synthetic delays: exchange → Tokyo → Frankfurt
+const END_TO_END_MS: f64 = 150.0;
+const BACKBONE_MS: f64 = 100.0;
+/// Slack above the synthetic delays for loopback and scheduling on a busy host
+const TOLERANCE_MS: f64 = 50.0;
+
+/// Run the self-test; returns whether every check passed
+pub async fn run(args: &Args, context: &RunContext, shutdown: Shutdown) -> bool {
+    let port = match free_udp_port() {
+        Ok(port) => port,
+        Err(e) => {
+            eprintln!("Self-test failed: no free UDP port: {}", e);
+            return false;
+        }
+    };
+    let output = std::env::temp_dir()
+        .join(format!(
+            "frankfurt-receiver-self-test-{}.json",
+            std::process::id()
+        ))
+        .to_string_lossy()
+        .into_owned();
+    let test_args = Args {
+        mode: "aws-backbone".to_string(),
+        transport: "udp".to_string(),
+        port,
+        duration: 2,
+        warmup_secs: 0,
+        output: output.clone(),
+        csv_output: None,
+        timeseries_output: None,
+        heatmap_output: None,
+        influx_url: None,
+        sink: Vec::new(),
+        control_addr: None,
+        path_probe: None,
+        s3_upload: None,
+        run_id: None,
+        exchange: "binance".to_string(),
+        symbol: SYMBOL.to_string(),
+        verify_payload: true,
+        recv_buffer_bytes: None,
+        kernel_timestamps: false,
+        tls_cert: None,
+        tls_key: None,
+        tui: false,
+        ..args.clone()
+    };
+
+    println!(
+        "Self-test: streaming {} synthetic events over UDP port {}",
+        EVENTS, port
+    );
+    let forwarder = tokio::spawn(mock_forwarder(port));
+    if let Err(e) = run_aws_backbone_mode(&test_args, context, shutdown).await {
+        eprintln!("Self-test failed: receiver error: {}", e);
+        return false;
+    }
+    match forwarder.await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            eprintln!("Self-test failed: mock forwarder error: {}", e);
+            return false;
+        }
+        Err(e) => {
+            eprintln!("Self-test failed: mock forwarder panicked: {}", e);
+            return false;
+        }
+    }
+
+    let results = ExperimentResults::load(&output);
+    let _ = std::fs::remove_file(&output);
+    match results {
+        Ok(results) => check(&results),
+        Err(e) => {
+            eprintln!("Self-test failed: could not read results: {}", e);
+            false
+        }
+    }
+}
+
+/// Let the OS pick a port that is free right now
+fn free_udp_port() -> std::io::Result<u16> {
+    Ok(std::net::UdpSocket::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+/// Send the synthetic stream once the receiver has had time to bind
+async fn mock_forwarder(port: u16) -> std::io::Result<()> {
+    sleep(Duration::from_millis(300)).await;
+    let socket = UdpSocket::bind("127.0.0.1:0").await?;
+    socket.connect(("127.0.0.1", port)).await?;
+
+    let mut order: Vec<u64> = (0..EVENTS).filter(|id| !LOST.contains(id)).collect();
+    let at = order.iter().position(|&id| id == REORDERED).unwrap();
+    order.swap(at, at + 1);
+    let at = order.iter().position(|&id| id == DUPLICATE).unwrap();
+    order.insert(at + 1, DUPLICATE);
+
+    let at = order.iter().position(|&id| id == STALE).unwrap();
+    order.insert(at + 1, STALE);
+
+    let mut stale_sent = false;
+    for id in order {
+        let mut event = synthetic_event(id);
+        if id == STALE && std::mem::replace(&mut stale_sent, true) {
+            event.run_id = Some(Cow::Borrowed("stale-run"));
+        }
+        let json = serde_json::to_vec(&event)?;
+        match fragment(id, &json, MAX_DATAGRAM) {
+            Some(fragments) => {
+                for datagram in fragments {
+                    socket.send(&datagram).await?;
+                }
+            }
+            None => {
+                socket.send(&json).await?;
+            }
+        }
+        sleep(Duration::from_millis(2)).await;
+    }
+    Ok(())
+}
+
+/// A forwarded aggTrade as the forwarder would send it right now
+fn synthetic_event(id: u64) -> ForwardedEvent {
+    let now = epoch_nanos();
+    let event_time = (now - (END_TO_END_MS * 1e6) as i64) / 1_000_000;
+    let trade_time = event_time - 1;
+    // Padding an otherwise ordinary event pushes it past one datagram
+    let padding = if id == FRAGMENTED {
+        format!(r#","pad":"{}""#, "x".repeat(3 * MAX_DATAGRAM))
+    } else {
+        String::new()
+    };
+    let event_data = format!(
+        r#"{{"e":"aggTrade","E":{},"s":"BTCUSDT","a":{},"p":"64250.10","q":"0.012","f":{},"l":{},"T":{},"m":false,"M":true{}}}"#,
+        event_time, id, id, id, trade_time, padding
+    );
+    ForwardedEvent {
+        binance_transaction_time: Some(trade_time),
+        transport: Some(Cow::Borrowed("udp")),
+        run_id: Some(Cow::Borrowed(RUN_ID)),
+        frame_bytes: Some(event_data.len() as u32),
+        ..ForwardedEvent::new(id, now - (BACKBONE_MS * 1e6) as i64, event_time, event_data)
+    }
+}
+
+/// Compare the results with what was sent, printing one line per check
+fn check(results: &ExperimentResults) -> bool {
+    let sent = EVENTS as usize - LOST.len();
+    let reassembled = results.udp_fragments.map_or(0, |f| f.reassembled);
+    let backbone = results.backbone_avg_latency_ms.unwrap_or(f64::NAN);
+    let foreign = results.foreign_run_events.unwrap_or(0);
+    let payload = results.payload_check.unwrap_or_default();
+    let checks = [
+        (
+            "events received",
+            results.sample_count == sent,
+            format!("{} (expected {})", results.sample_count, sent),
+        ),
+        (
+            "sequence gaps",
+            results.events_lost == LOST.len(),
+            format!("{} lost (expected {})", results.events_lost, LOST.len()),
+        ),
+        (
+            "duplicates",
+            results.duplicates == 1,
+            format!("{} (expected 1)", results.duplicates),
+        ),
+        (
+            "reordering",
+            results.reordered >= 1,
+            format!("{} reordered (expected at least 1)", results.reordered),
+        ),
+        (
+            "other forwarder runs",
+            foreign == 1 && results.run_id.as_deref() == Some(RUN_ID),
+            format!(
+                "{} ignored, run ID {} (expected 1, {})",
+                foreign,
+                results.run_id.as_deref().unwrap_or("none"),
+                RUN_ID
+            ),
+        ),
+        (
+            "payload check",
+            payload.checked == sent as u64 && payload.failed() == 0,
+            format!(
+                "{} of {} failed (expected 0 of {})",
+                payload.failed(),
+                payload.checked,
+                sent
+            ),
+        ),
+        (
+            "fragment reassembly",
+            reassembled == 1,
+            format!("{} frames reassembled (expected 1)", reassembled),
+        ),
+        (
+            "end-to-end latency",
+            (END_TO_END_MS..=END_TO_END_MS + TOLERANCE_MS).contains(&results.avg_latency_ms),
+            format!(
+                "avg {:.2}ms (expected {}-{}ms)",
+                results.avg_latency_ms,
+                END_TO_END_MS,
+                END_TO_END_MS + TOLERANCE_MS
+            ),
+        ),
+        (
+            "backbone latency",
+            (BACKBONE_MS..=BACKBONE_MS + TOLERANCE_MS).contains(&backbone),
+            format!(
+                "avg {:.2}ms (expected {}-{}ms)",
+                backbone,
+                BACKBONE_MS,
+                BACKBONE_MS + TOLERANCE_MS
+            ),
+        ),
+    ];
+
+    println!("\n=== Self-Test ===");
+    for (name, passed, detail) in &checks {
+        println!(
+            "{} {}: {}",
+            if *passed { "PASS" } else { "FAIL" },
+            name,
+            detail
+        );
+    }
+    let passed = checks.iter().all(|(_, passed, _)| *passed);
+    println!("Self-test {}", if passed { "passed" } else { "failed" });
+    passed
+}
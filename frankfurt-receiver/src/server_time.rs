@@ -0,0 +1,151 @@
+// Exchange server time poller (--server-time)
+//
+// Asks the exchange's REST time endpoint for its clock at startup and every
+// --server-time-interval after that. The first answer is awaited so events
+// can be corrected from the start; a failed request clears the latest sample
+// rather than leaving a stale one, like the --clock-sync readings.
+
+use crate::ingest::epoch_nanos;
+use latency_core::{ServerTimeCorrection, ServerTimeSample, ServerTimeStats};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// Binance spot's time endpoint, for --server-time without a URL
+pub const BINANCE_TIME_URL: &str = "https://api.binance.com/api/v3/time";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Default)]
+struct ServerTimeState {
+    latest: Option<ServerTimeSample>,
+    samples: Vec<ServerTimeSample>,
+    failures: u64,
+}
+
+/// The exchange's clock as read during one run
+#[derive(Debug, Clone)]
+pub struct ServerTimeMonitor {
+    url: String,
+    client: reqwest::Client,
+    state: Arc<Mutex<ServerTimeState>>,
+    poller: Option<AbortHandle>,
+}
+
+impl ServerTimeMonitor {
+    /// Read the exchange's clock now and every `period` after that, until
+    /// stopped
+    pub async fn start(url: String, period: Duration) -> Option<Self> {
+        let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!(error = %e, "exchange server time unavailable");
+                return None;
+            }
+        };
+        let mut monitor = ServerTimeMonitor {
+            url,
+            client,
+            state: Arc::default(),
+            poller: None,
+        };
+        monitor.read().await;
+        if let Some(sample) = monitor.latest() {
+            info!(
+                url = %monitor.url,
+                offset_ms = sample.offset_ms,
+                rtt_ms = sample.rtt_ms,
+                "exchange server time offset"
+            );
+        }
+        let task = monitor.clone();
+        let poller = tokio::spawn(async move {
+            let mut ticker = interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // The first tick completes immediately, and that reading is done
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                task.read().await;
+            }
+        });
+        monitor.poller = Some(poller.abort_handle());
+        Some(monitor)
+    }
+
+    /// Stop reading; the samples so far are kept
+    pub fn stop(&self) {
+        if let Some(poller) = &self.poller {
+            poller.abort();
+        }
+    }
+
+    /// The latest answer, if the last request succeeded
+    pub fn latest(&self) -> Option<ServerTimeSample> {
+        self.state.lock().unwrap().latest
+    }
+
+    /// The offset series and what it says about the run's latencies
+    pub fn results(&self, correction: &ServerTimeCorrection) -> Option<ServerTimeStats> {
+        let state = self.state.lock().unwrap();
+        let stats = correction.results(&self.url, state.samples.clone(), state.failures);
+        match &stats {
+            None => warn!(url = %self.url, "the exchange server time endpoint never answered"),
+            Some(stats) if stats.skewed() && !stats.applied => warn!(
+                offset_ms = stats.avg_offset_ms,
+                error_bound_ms = stats.avg_error_bound_ms,
+                "the exchange's clock disagrees with ours beyond the error bound; end-to-end latencies are skewed (see --correct-server-time)"
+            ),
+            Some(_) => {}
+        }
+        stats
+    }
+
+    async fn read(&self) {
+        let sample = self.request().await;
+        let mut state = self.state.lock().unwrap();
+        match sample {
+            Some(sample) => {
+                debug!(
+                    offset_ms = sample.offset_ms,
+                    rtt_ms = sample.rtt_ms,
+                    "exchange server time"
+                );
+                state.samples.push(sample);
+            }
+            // Warned once per outage
+            None if state.failures == 0 || state.latest.is_some() => warn!(
+                url = %self.url,
+                "cannot read the exchange server time, events are not corrected until it is back"
+            ),
+            None => {}
+        }
+        if sample.is_none() {
+            state.failures += 1;
+        }
+        state.latest = sample;
+    }
+
+    /// One request, timed around the whole exchange
+    async fn request(&self) -> Option<ServerTimeSample> {
+        let sent = epoch_nanos();
+        let body = async {
+            let response = self.client.get(&self.url).send().await?;
+            response.error_for_status()?.bytes().await
+        }
+        .await
+        .map_err(|e| debug!(url = %self.url, error = %e, "server time request failed"))
+        .ok()?;
+        let received = epoch_nanos();
+        let server_time = serde_json::from_slice::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|body| body.get("serverTime")?.as_i64());
+        let Some(server_time) = server_time else {
+            debug!(url = %self.url, body = %String::from_utf8_lossy(&body), "no serverTime in the answer");
+            return None;
+        };
+        Some(ServerTimeSample::new(sent, server_time, received))
+    }
+}
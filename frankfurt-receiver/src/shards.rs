@@ -0,0 +1,509 @@
+// Decoding forwarded frames, on the processing loop or on shard threads (--shards)
+//
+// Turning a received frame into an event (reassembling UDP fragments,
+// recognizing handshakes, parsing the envelope) is the part of processing that
+// grows with the message rate and needs no run-wide state. With one shard it
+// happens on the processing loop as each frame is taken from the queue. With
+// more, every shard has its own queue and thread, fed by a UDP socket of its
+// own (bound with SO_REUSEPORT, so the kernel spreads senders across them) and
+// by the TCP-based paths' receive tasks by stream name.
+//
+// Shards decode in parallel and may overtake each other, which sequence
+// tracking would count as reordering. The processing loop therefore holds what
+// they send for a few milliseconds and records events in the order they
+// arrived.
+
+use crate::ingest::{self, epoch_nanos, Forwarded, QueueReceiver, QueueSender};
+use crate::metadata::RunContext;
+use latency_core::{FragmentStats, ReceiveQueueStats, ShardLoad, StreamNames};
+use shared::{
+    ClockEstimate, Datagram, ForwardedEventView, ForwarderHello, ForwarderStages, PooledBuffer,
+    Reassembler,
+};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::net::IpAddr;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::sleep;
+use tracing::{debug, info};
+
+/// How long a fragmented UDP event may wait for its missing fragments
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long decoded events wait for slower shards before being recorded
+const MERGE_HOLD: Duration = Duration::from_millis(5);
+
+/// One received frame, decoded
+#[derive(Debug)]
+pub struct Decoded {
+    pub path: &'static str,
+    pub frankfurt_receive_time: i64,      // Epoch nanos
+    pub kernel_receive_time: Option<i64>, // Epoch nanos (UDP with SO_TIMESTAMPING)
+    pub peer: Option<IpAddr>,
+    pub content: Content,
+}
+
+#[derive(Debug)]
+pub enum Content {
+    Hello(Result<ForwarderHello, serde_json::Error>), // The handshake a forwarder opens every connection with
+    Event(Box<EventFields>, Frame),
+    Invalid(serde_json::Error), // Not a forwarded event
+}
+
+/// The envelope of a forwarded event, owned so it can leave its shard
+#[derive(Debug)]
+pub struct EventFields {
+    pub sequence_id: u64,
+    pub tokyo_receive_timestamp: i64,
+    pub binance_event_time: i64,
+    pub binance_transaction_time: Option<i64>,
+    pub stages: Option<ForwarderStages>,
+    pub buffered: bool,
+    pub retransmitted: bool,
+    pub foreign_run_id: Option<String>, // The event's run ID, if it is not this run's
+    pub dscp: Option<u8>,
+    pub ws_compressed: bool,
+    pub forwarding_overhead_ns: Option<i64>,
+    pub stream: Option<Arc<str>>,
+    pub symbol: Option<Arc<str>>,
+    pub clock: Option<ClockEstimate>,
+    pub frame_bytes: Option<u32>,
+    pub send_queue_delay_ns: Option<i64>,
+    pub forwarder_id: Option<Arc<str>>,
+}
+
+/// The bytes of a decoded event: as received, or reassembled from fragments
+#[derive(Debug)]
+pub enum Frame {
+    Received(PooledBuffer),
+    Reassembled(Vec<u8>),
+}
+
+impl Deref for Frame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Received(data) => data,
+            Self::Reassembled(data) => data,
+        }
+    }
+}
+
+/// Decodes the frames of one queue
+pub struct Decoder {
+    context: RunContext, // Whose run ID events are checked against
+    fragments: HashMap<Option<IpAddr>, Reassembler>, // Per sender address; each forwarder numbers its own frames
+    streams: StreamNames,
+    symbols: StreamNames,
+    forwarder_ids: StreamNames,
+}
+
+impl Decoder {
+    pub fn new(context: RunContext) -> Self {
+        Self {
+            context,
+            fragments: HashMap::new(),
+            streams: StreamNames::default(),
+            symbols: StreamNames::default(),
+            forwarder_ids: StreamNames::default(),
+        }
+    }
+
+    /// Decode one frame; `None` for a fragment of an incomplete event
+    pub fn decode(&mut self, forwarded: Forwarded) -> Option<Decoded> {
+        let Forwarded {
+            path,
+            frankfurt_receive_time,
+            kernel_receive_time,
+            peer,
+            data,
+        } = forwarded;
+        // Events too large for one datagram arrive as fragments
+        let frame = match path {
+            "udp" => match self
+                .fragments
+                .entry(peer)
+                .or_insert_with(|| Reassembler::new(REASSEMBLY_TIMEOUT))
+                .push(&data, frankfurt_receive_time)
+            {
+                Datagram::Whole(_) => Frame::Received(data),
+                Datagram::Reassembled(frame) => Frame::Reassembled(frame),
+                Datagram::Pending => return None,
+            },
+            _ => Frame::Received(data),
+        };
+
+        let content = if let Some(hello) = ForwarderHello::parse(&frame) {
+            Content::Hello(hello)
+        } else {
+            // Deserialize ForwardedEvent without copying the exchange payload
+            match ForwardedEventView::parse(&frame) {
+                Ok(event) => {
+                    let fields = self.fields(&event);
+                    Content::Event(Box::new(fields), frame)
+                }
+                Err(e) => Content::Invalid(e),
+            }
+        };
+        Some(Decoded {
+            path,
+            frankfurt_receive_time,
+            kernel_receive_time,
+            peer,
+            content,
+        })
+    }
+
+    fn fields(&mut self, event: &ForwardedEventView) -> EventFields {
+        EventFields {
+            sequence_id: event.sequence_id,
+            tokyo_receive_timestamp: event.tokyo_receive_timestamp,
+            binance_event_time: event.binance_event_time,
+            binance_transaction_time: event.binance_transaction_time,
+            stages: event.stages,
+            buffered: event.buffered,
+            retransmitted: event.retransmitted,
+            foreign_run_id: event
+                .run_id
+                .filter(|run_id| !self.context.matches_run_id(run_id))
+                .map(str::to_string),
+            dscp: event.dscp,
+            ws_compressed: event.ws_compressed,
+            forwarding_overhead_ns: event.forwarding_overhead_ns,
+            stream: event.stream.map(|stream| self.streams.intern(stream)),
+            symbol: event.symbol.map(|symbol| self.symbols.intern(symbol)),
+            clock: event.clock,
+            frame_bytes: event.frame_bytes,
+            send_queue_delay_ns: event.send_queue_delay_ns,
+            forwarder_id: event.forwarder_id.map(|id| self.forwarder_ids.intern(id)),
+        }
+    }
+
+    /// Reassembly counts over every sender
+    pub fn finish(self) -> FragmentStats {
+        let mut stats = FragmentStats::default();
+        for reassembler in self.fragments.into_values() {
+            stats.add(&reassembler.finish());
+        }
+        stats
+    }
+}
+
+/// Where the processing loop takes decoded events from
+pub enum Input {
+    Inline {
+        queue: QueueReceiver<Forwarded>,
+        decoder: Decoder,
+    },
+    Sharded(Shards),
+}
+
+/// What the input saw over the run
+pub struct InputStats {
+    pub receive_queue: ReceiveQueueStats,
+    pub fragments: FragmentStats,
+    pub shards: Option<Vec<ShardLoad>>,
+}
+
+/// Start `count` decode shards, or with one, decode on the processing loop.
+/// Returns the queue of each shard for the receive tasks to push onto.
+pub fn start(
+    count: usize,
+    capacity: usize,
+    context: &RunContext,
+) -> (Vec<QueueSender<Forwarded>>, Input) {
+    if count <= 1 {
+        let (tx, queue) = ingest::queue(capacity);
+        let decoder = Decoder::new(context.clone());
+        return (vec![tx], Input::Inline { queue, decoder });
+    }
+    let (decoded_tx, decoded) = ingest::queue(capacity);
+    let mut queues = Vec::with_capacity(count);
+    let mut threads = Vec::with_capacity(count);
+    for shard in 0..count {
+        let (tx, frames) = ingest::queue(capacity);
+        let decoded_tx = decoded_tx.clone();
+        let decoder = Decoder::new(context.clone());
+        let thread = std::thread::Builder::new()
+            .name(format!("shard-{}", shard))
+            .spawn(move || run_shard(shard, decoder, frames, decoded_tx))
+            .expect("failed to start a shard thread");
+        queues.push(tx);
+        threads.push(thread);
+    }
+    info!(shards = count, "decoding on shard threads");
+    let shards = Shards {
+        decoded,
+        held: BinaryHeap::new(),
+        arrivals: 0,
+        threads,
+    };
+    (queues, Input::Sharded(shards))
+}
+
+impl Input {
+    /// The next decoded event, or `None` once every receive task has stopped
+    pub async fn recv(&mut self) -> Option<Decoded> {
+        match self {
+            Self::Inline { queue, decoder } => loop {
+                if let Some(decoded) = decoder.decode(queue.recv().await?) {
+                    return Some(decoded);
+                }
+            },
+            Self::Sharded(shards) => shards.recv().await,
+        }
+    }
+
+    /// Stop decoding and collect the statistics
+    pub async fn finish(self) -> InputStats {
+        match self {
+            Self::Inline { queue, decoder } => InputStats {
+                receive_queue: queue.stats(),
+                fragments: decoder.finish(),
+                shards: None,
+            },
+            Self::Sharded(shards) => shards.finish().await,
+        }
+    }
+}
+
+/// Decode shard threads and the events they sent, held for reordering
+pub struct Shards {
+    decoded: QueueReceiver<Decoded>,
+    held: BinaryHeap<Reverse<Held>>,
+    arrivals: u64, // Taken from `decoded` so far; orders events received at the same time
+    threads: Vec<JoinHandle<(ShardLoad, FragmentStats)>>,
+}
+
+impl Shards {
+    async fn recv(&mut self) -> Option<Decoded> {
+        loop {
+            let release_at = self.held.peek().map(|Reverse(held)| {
+                held.decoded.frankfurt_receive_time + MERGE_HOLD.as_nanos() as i64
+            });
+            let wait = match release_at {
+                Some(at) => {
+                    let now = epoch_nanos();
+                    if now >= at {
+                        return self.held.pop().map(|Reverse(held)| held.decoded);
+                    }
+                    Some(Duration::from_nanos((at - now) as u64))
+                }
+                None => None,
+            };
+            tokio::select! {
+                decoded = self.decoded.recv() => match decoded {
+                    Some(decoded) => {
+                        self.held.push(Reverse(Held {
+                            arrival: self.arrivals,
+                            decoded,
+                        }));
+                        self.arrivals += 1;
+                    }
+                    // Every shard stopped; release what is left
+                    None => return self.held.pop().map(|Reverse(held)| held.decoded),
+                },
+                _ = sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+            }
+        }
+    }
+
+    async fn finish(self) -> InputStats {
+        let Shards {
+            decoded, threads, ..
+        } = self;
+        let receive_queue = decoded.stats();
+        // Shards stop once nothing takes their events any more
+        drop(decoded);
+        let joined = tokio::task::spawn_blocking(move || {
+            threads
+                .into_iter()
+                .filter_map(|thread| thread.join().ok())
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        let mut fragments = FragmentStats::default();
+        let mut loads = Vec::with_capacity(joined.len());
+        for (load, shard_fragments) in joined {
+            fragments.add(&shard_fragments);
+            loads.push(load);
+        }
+        InputStats {
+            receive_queue,
+            fragments,
+            shards: Some(loads),
+        }
+    }
+}
+
+/// A decoded event waiting for slower shards, earliest arrival first
+#[derive(Debug)]
+struct Held {
+    arrival: u64,
+    decoded: Decoded,
+}
+
+impl Held {
+    fn key(&self) -> (i64, u64) {
+        (self.decoded.frankfurt_receive_time, self.arrival)
+    }
+}
+
+impl PartialEq for Held {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Held {}
+
+impl PartialOrd for Held {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Held {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Decode one shard's frames until processing stops
+fn run_shard(
+    shard: usize,
+    mut decoder: Decoder,
+    mut frames: QueueReceiver<Forwarded>,
+    decoded: QueueSender<Decoded>,
+) -> (ShardLoad, FragmentStats) {
+    let started = Instant::now();
+    let cpu_start = thread_cpu_time();
+    let mut count = 0;
+    let mut bytes = 0;
+    let mut busy = Duration::ZERO;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("failed to start a shard runtime");
+    runtime.block_on(async {
+        loop {
+            let frame = tokio::select! {
+                frame = frames.recv() => frame,
+                _ = decoded.closed() => None,
+            };
+            let Some(frame) = frame else {
+                break;
+            };
+            let decode_start = Instant::now();
+            count += 1;
+            bytes += frame.data.len() as u64;
+            let event = decoder.decode(frame);
+            busy += decode_start.elapsed();
+            if let Some(event) = event {
+                if decoded.send(event).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let cpu = thread_cpu_time()
+        .zip(cpu_start)
+        .map(|(end, start)| end.saturating_sub(start));
+    let load = ShardLoad {
+        shard,
+        frames: count,
+        bytes,
+        busy_ms: busy.as_secs_f64() * 1000.0,
+        cpu_ms: cpu.map(|cpu| cpu.as_secs_f64() * 1000.0),
+        wall_ms: started.elapsed().as_secs_f64() * 1000.0,
+        queue: frames.stats(),
+    };
+    debug!(shard, frames = count, "shard stopped");
+    (load, decoder.finish())
+}
+
+/// CPU time the calling thread has used
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Option<Duration> {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `now` is valid for writes
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut now) };
+    (ret == 0).then(|| Duration::new(now.tv_sec as u64, now.tv_nsec as u32))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Bind one of several UDP sockets sharing `port` with SO_REUSEPORT
+#[cfg(target_os = "linux")]
+pub fn bind_udp(port: u16) -> std::io::Result<UdpSocket> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    // SAFETY: creates a socket whose descriptor is owned by `socket` from here on
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_INET,
+            libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            0,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a valid socket that nothing else owns
+    let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+    let one: libc::c_int = 1;
+    // SAFETY: the fd is a valid socket owned by `socket`, and `one` outlives the call
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_REUSEPORT,
+            &one as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&one) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr {
+            s_addr: libc::INADDR_ANY,
+        },
+        sin_zero: [0; 8],
+    };
+    // SAFETY: `addr` is a valid sockaddr_in for the duration of the call
+    let ret = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    UdpSocket::from_std(socket)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bind_udp(_port: u16) -> std::io::Result<UdpSocket> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--shards with UDP requires Linux (SO_REUSEPORT)",
+    ))
+}
@@ -0,0 +1,520 @@
+// TCP listeners for forwarded events: newline-delimited JSON and WebSocket,
+// each optionally over TLS, and a gRPC server. A forwarder on the same host
+// can send the same lines over a Unix domain socket instead.
+
+use crate::ingest::{Forwarded, FrameSender};
+use crate::metadata::RunContext;
+use futures_util::{Stream, StreamExt};
+use latency_core::FramingStats;
+use shared::{grpc, heartbeat_ack_line, parse_heartbeat, BufferPool, ForwarderHello};
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::Message;
+use tonic::transport::server::Connected;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, info_span, warn, Instrument};
+
+// Identifies forwarder connections in logs across both listeners
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Bind a TCP listener and push every received line onto `tx`. Each
+/// forwarder connection is served by its own task. With `tls`, every
+/// connection must complete a TLS handshake first.
+pub async fn listen(
+    port: u16,
+    tls: Option<TlsAcceptor>,
+    tx: FrameSender,
+    pool: BufferPool,
+    context: &RunContext,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let context = context.clone();
+    info!(port, tls = tls.is_some(), "TCP listener bound");
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let span = connection_span(peer, "tcp");
+                    span.in_scope(|| info!("forwarder connected"));
+                    let (stream, clock) = ArrivalClock::wrap(stream);
+                    let tx = tx.clone();
+                    let pool = pool.clone();
+                    let context = context.clone();
+                    match tls.clone() {
+                        Some(acceptor) => {
+                            tokio::spawn(
+                                async move {
+                                    match acceptor.accept(stream).await {
+                                        Ok(stream) => {
+                                            let peer = Some(peer.ip());
+                                            read_lines(
+                                                stream, "tcp", peer, clock, tx, pool, context,
+                                            )
+                                            .await
+                                        }
+                                        Err(e) => warn!(error = %e, "TLS handshake failed"),
+                                    }
+                                }
+                                .instrument(span),
+                            );
+                        }
+                        None => {
+                            let peer = Some(peer.ip());
+                            let lines = read_lines(stream, "tcp", peer, clock, tx, pool, context);
+                            tokio::spawn(lines.instrument(span));
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(error = %e, "TCP accept error");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Listen on the Unix domain socket at `path` and push every received line
+/// onto `tx`, like `listen`. A socket file left behind by an earlier run is
+/// replaced.
+#[cfg(unix)]
+pub async fn listen_unix(
+    path: &str,
+    tx: FrameSender,
+    pool: BufferPool,
+    context: &RunContext,
+) -> Result<(), std::io::Error> {
+    match std::fs::remove_file(path) {
+        Ok(()) => info!(path, "removed stale socket file"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    info!(path, "Unix socket listener bound");
+    let context = context.clone();
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let conn_id = NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed);
+                    let span = info_span!("forwarder_connection", conn_id, transport = "uds");
+                    span.in_scope(|| info!("forwarder connected"));
+                    let (stream, clock) = ArrivalClock::wrap(stream);
+                    let lines = read_lines(
+                        stream,
+                        "uds",
+                        None,
+                        clock,
+                        tx.clone(),
+                        pool.clone(),
+                        context.clone(),
+                    );
+                    tokio::spawn(lines.instrument(span));
+                }
+                Err(e) => {
+                    warn!(error = %e, "Unix socket accept error");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn listen_unix(
+    _path: &str,
+    _tx: FrameSender,
+    _pool: BufferPool,
+    _context: &RunContext,
+) -> Result<(), std::io::Error> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the uds transport requires Unix domain sockets",
+    ))
+}
+
+/// Bind a WebSocket listener; each text message is one forwarded event. With
+/// `tls` (the wss transport), every connection must complete a TLS handshake
+/// before the WebSocket one.
+pub async fn listen_ws(
+    port: u16,
+    tls: Option<TlsAcceptor>,
+    tx: FrameSender,
+    pool: BufferPool,
+) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let transport = if tls.is_some() { "wss" } else { "ws" };
+    info!(port, transport, "WebSocket listener bound");
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let span = connection_span(peer, transport);
+                    span.in_scope(|| info!("forwarder connected"));
+                    let acceptor = tls.clone();
+                    let tx = tx.clone();
+                    let pool = pool.clone();
+                    tokio::spawn(
+                        async move {
+                            let (stream, clock) = ArrivalClock::wrap(stream);
+                            match acceptor {
+                                Some(acceptor) => match acceptor.accept(stream).await {
+                                    Ok(stream) => {
+                                        accept_ws(stream, transport, peer.ip(), clock, tx, pool)
+                                            .await
+                                    }
+                                    Err(e) => warn!(error = %e, "TLS handshake failed"),
+                                },
+                                None => {
+                                    accept_ws(stream, transport, peer.ip(), clock, tx, pool).await
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    );
+                }
+                Err(e) => {
+                    warn!(error = %e, "TCP accept error");
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Serve the gRPC backbone; each message of a forwarder's stream is one
+/// forwarded event. The framing around every message is added to the
+/// returned stats.
+pub async fn listen_grpc(
+    port: u16,
+    tx: FrameSender,
+    pool: BufferPool,
+) -> Result<Arc<Mutex<FramingStats>>, std::io::Error> {
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    info!(port, "gRPC listener bound");
+
+    let framing = Arc::new(Mutex::new(FramingStats::default()));
+    let service = GrpcBackbone {
+        tx,
+        pool,
+        framing: framing.clone(),
+    };
+    // Connections are wrapped before tonic reads them, so messages are
+    // timestamped when their bytes arrive rather than once HTTP/2 is decoded
+    let incoming = futures_util::stream::unfold(listener, |listener| async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nodelay(true);
+                    let (stream, _) = ArrivalClock::wrap(stream);
+                    return Some((Ok::<_, std::io::Error>(stream), listener));
+                }
+                Err(e) => warn!(error = %e, "TCP accept error"),
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let served = tonic::transport::Server::builder()
+            .add_service(grpc::BackboneServer::new(service))
+            .serve_with_incoming(incoming)
+            .await;
+        if let Err(e) = served {
+            warn!(error = %e, "gRPC server stopped");
+        }
+    });
+
+    Ok(framing)
+}
+
+/// The receiver's side of the gRPC backbone
+struct GrpcBackbone {
+    tx: FrameSender,
+    pool: BufferPool,
+    framing: Arc<Mutex<FramingStats>>,
+}
+
+type SummaryStream = Pin<Box<dyn Stream<Item = Result<grpc::StreamSummary, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl grpc::Backbone for GrpcBackbone {
+    type ForwardStream = SummaryStream;
+
+    async fn forward(
+        &self,
+        request: Request<Streaming<grpc::ForwardedEvent>>,
+    ) -> Result<Response<Self::ForwardStream>, Status> {
+        let arrival = request.extensions().get::<Arrival>().cloned();
+        let Some(Arrival { peer, clock }) = arrival else {
+            return Err(Status::internal("connection without arrival clock"));
+        };
+        let span = connection_span(peer, "grpc");
+        span.in_scope(|| info!("forwarder connected"));
+        let messages = read_grpc(
+            request.into_inner(),
+            peer.ip(),
+            clock,
+            self.tx.clone(),
+            self.pool.clone(),
+            self.framing.clone(),
+        )
+        .instrument(span);
+
+        // One summary once the forwarder closes its side
+        let summary = futures_util::stream::once(async move {
+            Ok(grpc::StreamSummary {
+                events: messages.await,
+            })
+        });
+        Ok(Response::new(Box::pin(summary)))
+    }
+}
+
+/// Push every message of one stream onto `tx`, returning how many arrived
+async fn read_grpc(
+    mut messages: Streaming<grpc::ForwardedEvent>,
+    peer: IpAddr,
+    clock: Arc<AtomicI64>,
+    tx: FrameSender,
+    pool: BufferPool,
+    framing: Arc<Mutex<FramingStats>>,
+) -> u64 {
+    let mut received = 0;
+    loop {
+        match messages.message().await {
+            Ok(Some(message)) => {
+                received += 1;
+                framing
+                    .lock()
+                    .unwrap()
+                    .record(message.json.len() as u64, message.framing_bytes() as u64);
+                let forwarded = Forwarded {
+                    path: "grpc",
+                    frankfurt_receive_time: clock.load(Ordering::Relaxed),
+                    kernel_receive_time: None,
+                    peer: Some(peer),
+                    // Messages arrive allocated by the protobuf decoder
+                    data: pool.adopt(message.json),
+                };
+                if tx.send(forwarded).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => {
+                info!("forwarder connection closed");
+                break;
+            }
+            Err(e) => {
+                warn!(error = %e, "gRPC read error");
+                break;
+            }
+        }
+    }
+    received
+}
+
+/// What tonic hands the gRPC service about the connection a stream runs on
+#[derive(Clone)]
+struct Arrival {
+    peer: SocketAddr,
+    clock: Arc<AtomicI64>,
+}
+
+impl Connected for ArrivalClock<TcpStream> {
+    type ConnectInfo = Arrival;
+
+    fn connect_info(&self) -> Arrival {
+        Arrival {
+            // A connection whose peer address is unknown is reported as 0.0.0.0:0
+            peer: self
+                .inner
+                .peer_addr()
+                .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0))),
+            clock: self.last_arrival.clone(),
+        }
+    }
+}
+
+/// Span carrying the connection id and peer for everything logged about one connection
+fn connection_span(peer: std::net::SocketAddr, transport: &'static str) -> tracing::Span {
+    let conn_id = NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed);
+    info_span!("forwarder_connection", conn_id, %peer, transport)
+}
+
+/// Push every line of one connection onto `tx`. Forwarder heartbeats are
+/// answered on the same connection instead.
+async fn read_lines<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    path: &'static str,
+    peer: Option<IpAddr>,
+    clock: Arc<AtomicI64>,
+    tx: FrameSender,
+    pool: BufferPool,
+    context: RunContext,
+) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut hello = None; // The connection's first line, which heartbeats are recorded against
+    let mut first = true;
+
+    loop {
+        let mut line = pool.get();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => {
+                info!("forwarder connection closed");
+                return;
+            }
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                }
+                if first {
+                    hello = ForwarderHello::parse(&line).and_then(Result::ok);
+                    first = false;
+                }
+                if let Some(heartbeat) = parse_heartbeat(&line) {
+                    if let (Some(hello), Some(supervision)) = (&hello, heartbeat.supervision) {
+                        context.record_supervision(hello, peer, path, supervision);
+                    }
+                    let ack = heartbeat_ack_line(heartbeat.id);
+                    if let Err(e) = writer.write_all(ack.as_bytes()).await {
+                        warn!(path, error = %e, "failed to acknowledge heartbeat");
+                        return;
+                    }
+                    continue;
+                }
+                let received = Forwarded {
+                    path,
+                    frankfurt_receive_time: clock.load(Ordering::Relaxed),
+                    kernel_receive_time: None,
+                    peer,
+                    data: line,
+                };
+                if tx.send(received).await.is_err() {
+                    // Collection has finished
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!(path, error = %e, "read error");
+                return;
+            }
+        }
+    }
+}
+
+/// Complete the WebSocket handshake, then read its messages
+async fn accept_ws<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    path: &'static str,
+    peer: IpAddr,
+    clock: Arc<AtomicI64>,
+    tx: FrameSender,
+    pool: BufferPool,
+) {
+    match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => read_messages(ws, path, peer, clock, tx, pool).await,
+        Err(e) => warn!(error = %e, "WebSocket handshake failed"),
+    }
+}
+
+async fn read_messages<S: AsyncRead + AsyncWrite + Unpin>(
+    mut ws: tokio_tungstenite::WebSocketStream<S>,
+    path: &'static str,
+    peer: IpAddr,
+    clock: Arc<AtomicI64>,
+    tx: FrameSender,
+    pool: BufferPool,
+) {
+    while let Some(message) = ws.next().await {
+        match message {
+            Ok(Message::Text(line)) => {
+                let received = Forwarded {
+                    path,
+                    frankfurt_receive_time: clock.load(Ordering::Relaxed),
+                    kernel_receive_time: None,
+                    peer: Some(peer),
+                    // Messages arrive allocated by the WebSocket library
+                    data: pool.adopt(line.into_bytes()),
+                };
+                if tx.send(received).await.is_err() {
+                    return;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!(error = %e, "WebSocket read error");
+                return;
+            }
+        }
+    }
+    info!("forwarder connection closed");
+}
+
+/// Wraps the raw socket and records when bytes last arrived, so receive
+/// timestamps are taken before TLS decryption and message framing
+struct ArrivalClock<S> {
+    inner: S,
+    last_arrival: Arc<AtomicI64>, // Epoch nanos
+}
+
+impl<S> ArrivalClock<S> {
+    fn wrap(inner: S) -> (Self, Arc<AtomicI64>) {
+        let last_arrival = Arc::new(AtomicI64::new(0));
+        let clock = Self {
+            inner,
+            last_arrival: last_arrival.clone(),
+        };
+        (clock, last_arrival)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ArrivalClock<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if matches!(poll, Poll::Ready(Ok(()))) && buf.filled().len() > before {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as i64;
+            self.last_arrival.store(now, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ArrivalClock<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
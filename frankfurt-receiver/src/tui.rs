@@ -0,0 +1,255 @@
+// Full-screen dashboard (--tui)
+//
+// Replaces the per-second table while collecting. The dashboard owns the
+// terminal, so log lines are captured into a panel instead of going to stderr;
+// once it closes, logging goes back to stderr. Ctrl+C still stops the run.
+
+use latency_core::{percentile, Collector, SecondStats};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::cursor::{Hide, Show};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, LineGauge, Paragraph, Sparkline};
+use ratatui::Terminal;
+use std::collections::VecDeque;
+use std::io::{self, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+const HISTORY_SECS: usize = 600;
+const LOG_LINES: usize = 200;
+const PERCENTILE_WINDOW: Duration = Duration::from_secs(60);
+const GAUGE_LEVELS: [f64; 4] = [50.0, 90.0, 99.0, 99.9];
+pub const REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+
+// The log writer belongs to the global subscriber, which is installed once
+// per process; each dashboard starts it afresh.
+static LOGS: OnceLock<LogBuffer> = OnceLock::new();
+
+/// Log lines kept for the dashboard while it is open
+#[derive(Clone, Default)]
+struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capturing: Arc<AtomicBool>,
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.capturing.load(Ordering::Relaxed) {
+            return io::stderr().write(buf);
+        }
+        let mut lines = self.lines.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if lines.len() == LOG_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Install logging that the dashboard can capture; until it opens (and after
+/// it closes) lines still go to stderr
+pub fn init_logging(filter: &str, json: bool) -> Result<(), String> {
+    let logs = LOGS.get_or_init(LogBuffer::default).clone();
+    shared::init_logging_to(filter, json, move || logs.clone())
+}
+
+pub struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    title: String,
+    status: String,
+    latency_history: VecDeque<u64>, // Average latency per second (µs), oldest first
+    last_second: Option<SecondStats>,
+    events: u64,
+    lost: u64,
+    percentiles: Vec<(f64, f64)>, // (level, ms) over PERCENTILE_WINDOW
+    window_max_ms: f64,
+    drawn_at: Instant,
+}
+
+impl Dashboard {
+    /// Take over the terminal
+    pub fn start(title: String) -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        if let Some(logs) = LOGS.get() {
+            logs.lines.lock().unwrap().clear();
+            logs.capturing.store(true, Ordering::Relaxed);
+        }
+
+        Ok(Self {
+            terminal,
+            title,
+            status: "starting".to_string(),
+            latency_history: VecDeque::with_capacity(HISTORY_SECS),
+            last_second: None,
+            events: 0,
+            lost: 0,
+            percentiles: Vec::new(),
+            window_max_ms: 0.0,
+            drawn_at: Instant::now(),
+        })
+    }
+
+    pub fn set_status(&mut self, status: String, collector: &Collector) {
+        self.status = status;
+        self.draw(collector);
+    }
+
+    /// Add a closed one-second window and refresh the percentiles
+    pub fn second(&mut self, second: &SecondStats, collector: &Collector) {
+        if self.latency_history.len() == HISTORY_SECS {
+            self.latency_history.pop_front();
+        }
+        self.latency_history
+            .push_back((second.avg_latency_ms.max(0.0) * 1000.0) as u64);
+        self.last_second = Some(*second);
+        self.events += second.events;
+        self.lost += second.events_lost;
+
+        let mut latencies: Vec<f64> = collector
+            .recent(PERCENTILE_WINDOW)
+            .iter()
+            .filter(|m| !m.warmup)
+            .map(|m| m.end_to_end_latency_ms())
+            .collect();
+        latencies.sort_by(f64::total_cmp);
+        self.window_max_ms = latencies.last().copied().unwrap_or(0.0);
+        self.percentiles = GAUGE_LEVELS
+            .iter()
+            .filter(|_| !latencies.is_empty())
+            .map(|&level| (level, percentile(&latencies, level / 100.0)))
+            .collect();
+
+        self.draw(collector);
+    }
+
+    /// Redraw if the screen is older than `REDRAW_INTERVAL`, so the clock
+    /// and log panel keep moving when no events arrive
+    pub fn tick(&mut self, collector: &Collector) {
+        if self.drawn_at.elapsed() >= REDRAW_INTERVAL {
+            self.draw(collector);
+        }
+    }
+
+    fn draw(&mut self, collector: &Collector) {
+        self.drawn_at = Instant::now();
+        let elapsed = collector.elapsed().as_secs();
+        let logs: Vec<String> = LOGS
+            .get()
+            .map(|logs| logs.lines.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default();
+
+        let summary = match &self.last_second {
+            Some(second) => format!(
+                "Events/s {}   Avg {:.2} ms   Min {:.2} ms   Max {:.2} ms",
+                second.events, second.avg_latency_ms, second.min_latency_ms, second.max_latency_ms
+            ),
+            None => "Waiting for events".to_string(),
+        };
+        let backbone = self
+            .last_second
+            .and_then(|second| second.avg_backbone_latency_ms)
+            .map(|ms| format!("   Backbone {:.2} ms", ms))
+            .unwrap_or_default();
+        let header = vec![
+            Line::from(format!(
+                "Elapsed {:02}:{:02}:{:02}   Status: {}",
+                elapsed / 3600,
+                elapsed / 60 % 60,
+                elapsed % 60,
+                self.status
+            )),
+            Line::from(format!("{}{}", summary, backbone)),
+            Line::from(format!(
+                "Events {}   Lost {} ({} gaps)",
+                self.events,
+                self.lost,
+                collector.gaps().len()
+            )),
+        ];
+
+        let result = self.terminal.draw(|frame| {
+            let [top, chart, gauges, log] = Layout::vertical([
+                Constraint::Length(5),
+                Constraint::Min(6),
+                Constraint::Length(GAUGE_LEVELS.len() as u16 + 2),
+                Constraint::Percentage(35),
+            ])
+            .areas(frame.area());
+
+            frame.render_widget(
+                Paragraph::new(header).block(Block::bordered().title(self.title.as_str())),
+                top,
+            );
+
+            // Newest seconds on the right, as many as fit
+            let width = usize::from(chart.width.saturating_sub(2));
+            let skip = self.latency_history.len().saturating_sub(width);
+            let history: Vec<u64> = self.latency_history.iter().skip(skip).copied().collect();
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::bordered().title("Average latency per second"))
+                    .data(&history)
+                    .style(Style::default().fg(Color::Cyan)),
+                chart,
+            );
+
+            let block = Block::bordered().title("Percentiles (last 60 s, bar relative to max)");
+            let inner = block.inner(gauges);
+            frame.render_widget(block, gauges);
+            let rows =
+                Layout::vertical(vec![Constraint::Length(1); GAUGE_LEVELS.len()]).split(inner);
+            for (row, &(level, ms)) in rows.iter().zip(&self.percentiles) {
+                let ratio = if self.window_max_ms > 0.0 {
+                    (ms / self.window_max_ms).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                frame.render_widget(
+                    LineGauge::default()
+                        .ratio(ratio)
+                        .label(format!("p{:<5} {:>9.2} ms", level, ms))
+                        .filled_style(Style::default().fg(Color::Green)),
+                    *row,
+                );
+            }
+
+            let visible = usize::from(log.height.saturating_sub(2));
+            let lines: Vec<Line> = logs
+                .iter()
+                .skip(logs.len().saturating_sub(visible))
+                .map(|line| Line::from(line.as_str()))
+                .collect();
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::bordered().title("Log")),
+                log,
+            );
+        });
+        if let Err(e) = result {
+            debug!(error = %e, "failed to draw dashboard");
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    /// Give the terminal back, also when a run ends with an error
+    fn drop(&mut self) {
+        if let Some(logs) = LOGS.get() {
+            logs.capturing.store(false, Ordering::Relaxed);
+        }
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen, Show);
+    }
+}
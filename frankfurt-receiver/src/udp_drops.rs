@@ -0,0 +1,141 @@
+// Receive buffer size and kernel drop accounting for the UDP path (Linux only)
+//
+// The drop counters are read when the socket is bound and again when the run
+// ends, so drops in the receiver's own socket buffer can be reported apart
+// from losses on the network.
+
+use latency_core::{KernelDropStats, UdpCounters};
+use tokio::net::UdpSocket;
+
+/// Set SO_RCVBUF on `socket`; returns the effective size, which the kernel
+/// doubles for bookkeeping overhead and caps at net.core.rmem_max
+#[cfg(target_os = "linux")]
+pub fn set_recv_buffer(socket: &UdpSocket, bytes: usize) -> std::io::Result<u64> {
+    use std::os::fd::AsRawFd;
+
+    let requested = libc::c_int::try_from(bytes).map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "receive buffer too large")
+    })?;
+    // SAFETY: the fd is a valid socket owned by `socket`, and `requested` outlives the call
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUF,
+            &requested as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&requested) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    recv_buffer(socket)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_recv_buffer(_socket: &UdpSocket, _bytes: usize) -> std::io::Result<u64> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--recv-buffer-bytes requires Linux",
+    ))
+}
+
+/// The socket's effective SO_RCVBUF
+#[cfg(target_os = "linux")]
+fn recv_buffer(socket: &UdpSocket) -> std::io::Result<u64> {
+    use std::os::fd::AsRawFd;
+
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    // SAFETY: the fd is a valid socket, and `value`/`len` are valid for writes
+    let ret = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_RCVBUF,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(value as u64)
+}
+
+/// Kernel UDP counters at the start of a run
+#[derive(Debug)]
+pub struct DropMonitor {
+    inode: Option<u64>,
+    recv_buffer: Option<u64>,
+    before: UdpCounters,
+}
+
+impl DropMonitor {
+    /// Take the starting counters for `socket`
+    pub fn start(socket: &UdpSocket) -> Self {
+        let inode = socket_inode(socket);
+        Self {
+            inode,
+            recv_buffer: effective_recv_buffer(socket),
+            before: read_counters(inode),
+        }
+    }
+
+    /// Drops since `start`; `None` where /proc is not available
+    pub fn finish(&self) -> Option<KernelDropStats> {
+        let stats = read_counters(self.inode).since(&self.before, self.recv_buffer);
+        let read_any = stats.socket_drops.is_some() || stats.host_in_errors.is_some();
+        read_any.then_some(stats)
+    }
+}
+
+/// Drops over the sockets sharing the port, one per shard: their socket drops
+/// added up, and the host-wide counters as read for the first
+pub fn finish_all(monitors: &[DropMonitor]) -> Option<KernelDropStats> {
+    let mut readings = monitors.iter().filter_map(DropMonitor::finish);
+    let mut total = readings.next()?;
+    for reading in readings {
+        total.socket_drops = total
+            .socket_drops
+            .zip(reading.socket_drops)
+            .map(|(total, drops)| total + drops);
+    }
+    Some(total)
+}
+
+#[cfg(target_os = "linux")]
+fn effective_recv_buffer(socket: &UdpSocket) -> Option<u64> {
+    recv_buffer(socket).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn effective_recv_buffer(_socket: &UdpSocket) -> Option<u64> {
+    None
+}
+
+/// Inode of the socket, which identifies its row in /proc/net/udp
+#[cfg(target_os = "linux")]
+fn socket_inode(socket: &UdpSocket) -> Option<u64> {
+    use std::os::fd::AsRawFd;
+
+    // The fd links to "socket:[<inode>]"
+    let link = std::fs::read_link(format!("/proc/self/fd/{}", socket.as_raw_fd())).ok()?;
+    let link = link.to_str()?;
+    link.strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn socket_inode(_socket: &UdpSocket) -> Option<u64> {
+    None
+}
+
+fn read_counters(inode: Option<u64>) -> UdpCounters {
+    let udp = std::fs::read_to_string("/proc/net/udp").unwrap_or_default();
+    let snmp = std::fs::read_to_string("/proc/net/snmp").unwrap_or_default();
+    // Inode 0 matches no socket
+    UdpCounters::parse(&udp, &snmp, inode.unwrap_or(0))
+}
@@ -0,0 +1,575 @@
+// The mock exchange, forwarder and receiver in one process over loopback: a
+// short aws-backbone run must produce results that account for every event.
+
+use latency_core::{ExperimentResults, LatencyMeasurement};
+use shared::{CaptureWriter, ErrorKind};
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// A loopback port nothing is listening on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Run the mock exchange, receiver and forwarder for a three-second
+/// aws-backbone run over `transport` and return the receiver's results.
+/// `mock_args`, `receiver_args` and `forwarder_args` are added to their
+/// command lines. Every run has a run ID of its own, which the receiver must
+/// adopt from the forwarder.
+async fn run_backbone(
+    transport: &str,
+    mock_args: &[&str],
+    receiver_args: &[&str],
+    forwarder_args: &[&str],
+) -> ExperimentResults {
+    let exchange_port = free_port();
+    let receiver_port = free_port();
+    // Tests run in parallel, so each writes results of its own
+    let output = std::env::temp_dir().join(format!(
+        "itest-results-{}-{}-{}.json",
+        transport,
+        std::process::id(),
+        receiver_port
+    ));
+    let output = output.to_str().unwrap();
+    let exchange = format!("127.0.0.1:{}", exchange_port);
+    let ws_url = format!("ws://{}/ws/btcusdt@bookTicker", exchange);
+    let receiver_port = receiver_port.to_string();
+    let run_id = format!("itest-{}-{}", transport, receiver_port);
+
+    let mut mock_command = args(&[
+        "mock-binance",
+        "--listen",
+        &exchange,
+        "--rate",
+        "200",
+        "--log-level",
+        "warn",
+    ]);
+    mock_command.extend(args(mock_args));
+    let mock = mock_binance::run(mock_command);
+    let mut receiver_command = args(&[
+        "frankfurt-receiver",
+        "--mode",
+        "aws-backbone",
+        "--transport",
+        transport,
+        "--port",
+        &receiver_port,
+        "--duration",
+        "3",
+        "--output",
+        output,
+        "--log-level",
+        "warn",
+    ]);
+    receiver_command.extend(args(receiver_args));
+    let receiver = frankfurt_receiver::run(receiver_command);
+    let forwarder = async {
+        // Both servers listen before the forwarder dials them
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut command = args(&[
+            "tokyo-forwarder",
+            "--ws-url",
+            &ws_url,
+            "--frankfurt-ip",
+            "127.0.0.1",
+            "--frankfurt-port",
+            &receiver_port,
+            "--transport",
+            transport,
+            "--run-id",
+            &run_id,
+            "--log-level",
+            "warn",
+        ]);
+        command.extend(args(forwarder_args));
+        tokyo_forwarder::run(command).await
+    };
+
+    // The mock and the forwarder run until stopped; the receiver stops itself
+    tokio::select! {
+        result = receiver => result.unwrap(),
+        result = mock => panic!("mock exchange stopped: {:?}", result),
+        result = forwarder => panic!("forwarder stopped: {:?}", result),
+    }
+
+    let results = ExperimentResults::load(output).unwrap();
+    std::fs::remove_file(output).unwrap();
+    assert_eq!(results.run_id.as_deref(), Some(run_id.as_str()));
+    results
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn forwarder_and_receiver_interoperate() {
+    let results = run_backbone("tcp", &[], &[], &[]).await;
+    assert_eq!(results.setup_type, "aws-backbone");
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+    assert_eq!(results.duplicates, 0);
+    let backbone = results.backbone_avg_latency_ms.unwrap();
+    assert!(backbone > 0.0 && backbone < results.avg_latency_ms);
+}
+
+/// A self-signed certificate for 127.0.0.1 and its key as PEM files. The
+/// receiver serves the certificate and the forwarder trusts it as its CA.
+fn self_signed_certificate(name: &str) -> (String, String) {
+    let certified = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let path = |kind: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "itest-{}-{}-{}.pem",
+            name,
+            std::process::id(),
+            kind
+        ));
+        path.to_str().unwrap().to_string()
+    };
+    let (cert, key) = (path("cert"), path("key"));
+    std::fs::write(&cert, certified.cert.pem()).unwrap();
+    std::fs::write(&key, certified.key_pair.serialize_pem()).unwrap();
+    (cert, key)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tls_backbone_timestamps_arrivals() {
+    let (cert, key) = self_signed_certificate("tls");
+    let csv = std::env::temp_dir().join(format!("itest-tls-{}.csv", std::process::id()));
+    let csv_path = csv.to_str().unwrap();
+    let results = run_backbone(
+        "tcp",
+        &[],
+        &[
+            "--tls-cert",
+            &cert,
+            "--tls-key",
+            &key,
+            "--csv-output",
+            csv_path,
+        ],
+        &["--tls", "--tls-ca", &cert],
+    )
+    .await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+
+    // Receive times are taken as the ciphertext arrives: after the forwarder
+    // stamped the event, and well within a loopback round trip of it
+    let csv = std::fs::read_to_string(csv_path).unwrap();
+    let rows: Vec<Vec<&str>> = csv
+        .lines()
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|line| line.split(',').collect())
+        .collect();
+    assert_eq!(rows.len(), results.sample_count);
+    for row in rows {
+        let tokyo: i64 = row[2].parse().unwrap();
+        let frankfurt: i64 = row[3].parse().unwrap();
+        assert!(
+            tokyo <= frankfurt && frankfurt - tokyo < 100_000_000,
+            "received {} ns after the forwarder stamped the event",
+            frankfurt - tokyo
+        );
+    }
+    for path in [csv_path, &cert, &key] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replayed_captures_keep_their_spacing() {
+    const FRAMES: i64 = 50;
+    const GAP_MS: i64 = 20;
+    const EXCHANGE_DELAY_MS: i64 = 5;
+    let temp = |name: &str| {
+        let path =
+            std::env::temp_dir().join(format!("itest-replay-{}-{}", std::process::id(), name));
+        path.to_str().unwrap().to_string()
+    };
+    let (capture, csv, output) = (temp("capture.jsonl"), temp("raw.csv"), temp("results.json"));
+
+    // Frames received EXCHANGE_DELAY_MS after their event time, GAP_MS apart
+    let mut writer = CaptureWriter::create(&capture).unwrap();
+    for i in 0..FRAMES {
+        let event_time = 1_700_000_000_000 + i * GAP_MS;
+        let text = format!(
+            r#"{{"e":"bookTicker","u":{},"E":{},"T":{},"s":"BTCUSDT","b":"64250.10","B":"1.5","a":"64250.20","A":"2.0"}}"#,
+            i + 1,
+            event_time,
+            event_time - 1
+        );
+        writer
+            .write((event_time + EXCHANGE_DELAY_MS) * 1_000_000, &text)
+            .unwrap();
+    }
+    writer.finish().unwrap();
+
+    let receiver_port = free_port().to_string();
+    let receiver = frankfurt_receiver::run(args(&[
+        "frankfurt-receiver",
+        "--mode",
+        "aws-backbone",
+        "--transport",
+        "tcp",
+        "--port",
+        &receiver_port,
+        "--duration",
+        "3",
+        "--output",
+        &output,
+        "--csv-output",
+        &csv,
+        "--log-level",
+        "warn",
+    ]));
+    // Unlike a live forwarder, a replay stops once the capture is sent
+    let forwarder = async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        tokyo_forwarder::run(args(&[
+            "tokyo-forwarder",
+            "--replay",
+            &capture,
+            "--frankfurt-ip",
+            "127.0.0.1",
+            "--frankfurt-port",
+            &receiver_port,
+            "--transport",
+            "tcp",
+            "--log-level",
+            "warn",
+        ]))
+        .await
+    };
+    let (received, replayed) = tokio::join!(receiver, forwarder);
+    received.unwrap();
+    replayed.unwrap();
+
+    let results = ExperimentResults::load(&output).unwrap();
+    assert_eq!(results.sample_count, FRAMES as usize);
+    assert_eq!(results.events_lost, 0);
+    assert_eq!(results.duplicates, 0);
+
+    let measurements = LatencyMeasurement::read_from_csv(&csv).unwrap();
+    let first = &measurements[0];
+    for (i, m) in measurements.iter().enumerate() {
+        // Sequence IDs are assigned in capture order
+        assert_eq!(m.sequence_id, i as u64);
+        // Sent at the original offsets, never early
+        let offset_ms = (m.frankfurt_receive_time - first.frankfurt_receive_time) / 1_000_000;
+        let expected_ms = i as i64 * GAP_MS;
+        assert!(
+            (expected_ms - 2..expected_ms + 30).contains(&offset_ms),
+            "event {} received {} ms after the first",
+            i,
+            offset_ms
+        );
+        // Event times are shifted, keeping the exchange → forwarder delay
+        let tokyo = m.tokyo_receive_time.unwrap();
+        let exchange_delay_ms = tokyo / 1_000_000 - m.binance_event_time;
+        assert!(
+            (EXCHANGE_DELAY_MS - 1..=EXCHANGE_DELAY_MS + 1).contains(&exchange_delay_ms),
+            "event {} stamped {} ms after its event time",
+            i,
+            exchange_delay_ms
+        );
+    }
+    for path in [capture, csv, output] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn synthetic_events_arrive_at_the_configured_rate() {
+    const RATE: f64 = 500.0;
+    let csv = std::env::temp_dir().join(format!("itest-synthetic-{}.csv", std::process::id()));
+    let csv_path = csv.to_str().unwrap();
+    // The forwarder ignores the mock exchange and generates its own events
+    let results = run_backbone(
+        "tcp",
+        &[],
+        &["--csv-output", csv_path],
+        &["--synthetic-rate", "500"],
+    )
+    .await;
+    assert_eq!(results.events_lost, 0);
+
+    // Events over the time between the first and the last arrival
+    let measurements = LatencyMeasurement::read_from_csv(csv_path).unwrap();
+    let first = measurements.first().unwrap().frankfurt_receive_time;
+    let last = measurements.last().unwrap().frankfurt_receive_time;
+    let secs = (last - first) as f64 / 1e9;
+    let expected = RATE * secs + 1.0;
+    let received = measurements.len() as f64;
+    assert!(secs > 1.5, "events arrived over {} s only", secs);
+    assert!(
+        (received - expected).abs() < expected * 0.05,
+        "{} events in {:.3} s, expected {:.0}",
+        received,
+        secs,
+        expected
+    );
+    std::fs::remove_file(csv_path).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn unix_socket_transport_interoperates() {
+    let socket = std::env::temp_dir().join(format!("itest-{}.sock", std::process::id()));
+    let socket = socket.to_str().unwrap();
+    let results = run_backbone("uds", &[], &["--uds-path", socket], &["--uds-path", socket]).await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+    assert_eq!(results.duplicates, 0);
+    let backbone = results.backbone_avg_latency_ms.unwrap();
+    assert!(backbone > 0.0 && backbone < results.avg_latency_ms);
+    let _ = std::fs::remove_file(socket);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn grpc_transport_reports_framing() {
+    let results = run_backbone("grpc", &[], &[], &[]).await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+    let framing = results.grpc_framing.unwrap();
+    // Every event plus the handshake
+    assert!(framing.messages as usize > results.sample_count);
+    // At least the gRPC prefix and one HTTP/2 frame header
+    assert!(framing.min_framing_bytes > 5 + 9);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn acknowledged_heartbeats_keep_the_connection() {
+    let results = run_backbone(
+        "tcp",
+        &[],
+        &[],
+        &[
+            "--forwarder-id",
+            "heartbeats",
+            "--heartbeat-ms",
+            "50",
+            "--write-timeout-ms",
+            "1000",
+        ],
+    )
+    .await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+    // A missed acknowledgement would have reconnected, with another handshake
+    let forwarders = results.metadata.unwrap().forwarders;
+    let [forwarder] = forwarders.as_slice() else {
+        panic!("expected one forwarder: {:?}", forwarders);
+    };
+    assert_eq!(forwarder.forwarder_id.as_deref(), Some("heartbeats"));
+    assert_eq!(forwarder.handshakes, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn receiver_acknowledgements_reach_the_forwarder_status() {
+    let ack_port = free_port().to_string();
+    let status_file =
+        std::env::temp_dir().join(format!("itest-status-acks-{}.json", std::process::id()));
+    let status_path = status_file.to_str().unwrap();
+    let results = run_backbone(
+        "tcp",
+        &[],
+        &["--ack-every", "10", "--ack-port", &ack_port],
+        &["--ack-port", &ack_port, "--status-file", status_path],
+    )
+    .await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+
+    let status: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&status_file).unwrap()).unwrap();
+    std::fs::remove_file(&status_file).unwrap();
+    let acks = &status["receiver_ack"];
+    assert!(acks["acks"].as_u64().unwrap() > 10, "{}", acks);
+    assert!(acks["rtt"]["min_ms"].as_f64().unwrap() >= 0.0);
+    // Both ends read the same clock over loopback
+    assert!(acks["offset"]["median_ms"].as_f64().unwrap().abs() < 50.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sharded_receiver_merges_statistics() {
+    let results = run_backbone("udp", &[], &["--shards", "3"], &[]).await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    assert_eq!(results.events_lost, 0);
+    assert_eq!(results.duplicates, 0);
+    let shards = results.shards.unwrap();
+    assert_eq!(results.reordered, 0);
+    assert_eq!(shards.len(), 3);
+    // Every event, plus the handshake, was decoded by some shard
+    let frames: u64 = shards.iter().map(|shard| shard.frames).sum();
+    assert!(frames as usize > results.sample_count);
+    assert!(shards.iter().all(|shard| shard.wall_ms > 0.0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn symbols_are_split_into_their_own_sets() {
+    let csv = std::env::temp_dir().join(format!("itest-symbols-{}.csv", std::process::id()));
+    let csv_path = csv.to_str().unwrap();
+    let results = run_backbone(
+        "tcp",
+        &["--symbols", "BTCUSDT,ETHUSDT"],
+        &["--split-by-symbol", "--csv-output", csv_path],
+        &[],
+    )
+    .await;
+    let symbols = results.symbols.unwrap();
+    let names: Vec<&str> = symbols.iter().map(|s| s.symbol.as_str()).collect();
+    assert_eq!(names.len(), 2, "{:?}", names);
+    assert!(names.contains(&"BTCUSDT") && names.contains(&"ETHUSDT"));
+    let split: usize = symbols.iter().map(|s| s.sample_count).sum();
+    assert_eq!(split, results.sample_count);
+    assert!(symbols.iter().all(|s| s.backbone_median_ms.is_some()));
+
+    // Every row of the combined CSV is in exactly one symbol's file
+    let rows = |path: &str| {
+        let csv = std::fs::read_to_string(path).unwrap();
+        // Past the comment and the column names
+        csv.lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+            .count()
+    };
+    let per_symbol: usize = ["BTCUSDT", "ETHUSDT"]
+        .iter()
+        .map(|symbol| {
+            let path = shared::symbol_path(csv_path, symbol);
+            let count = rows(&path);
+            std::fs::remove_file(path).unwrap();
+            count
+        })
+        .sum();
+    assert_eq!(per_symbol, rows(csv_path));
+    std::fs::remove_file(csv_path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn heartbeats_report_the_restart_history() {
+    // A previous process that restarted once and forwarded 500 events
+    let state_file = std::env::temp_dir().join(format!("itest-state-{}.json", std::process::id()));
+    let state_path = state_file.to_str().unwrap();
+    std::fs::write(
+        &state_file,
+        r#"{"process_starts":1,"restarts":1,"panics":0,"last_error":"exchange connection closed","events_forwarded":500,"sessions":[]}"#,
+    )
+    .unwrap();
+    let results = run_backbone(
+        "tcp",
+        &[],
+        &[],
+        &[
+            "--forwarder-id",
+            "supervised",
+            "--heartbeat-ms",
+            "50",
+            "--state-file",
+            state_path,
+        ],
+    )
+    .await;
+    let forwarders = results.metadata.unwrap().forwarders;
+    let [forwarder] = forwarders.as_slice() else {
+        panic!("expected one forwarder: {:?}", forwarders);
+    };
+    assert_eq!(forwarder.forwarder_id.as_deref(), Some("supervised"));
+    let supervision = forwarder.supervision.as_ref().unwrap();
+    assert_eq!(supervision.process_starts, 2);
+    assert_eq!(supervision.restarts, 1);
+    assert_eq!(
+        supervision.last_error.as_deref(),
+        Some("exchange connection closed")
+    );
+    assert!(supervision.session_uptime_secs > 1.0);
+    assert!(supervision.events_forwarded > 600);
+    assert_eq!(forwarder.restarts_during_run, 0);
+
+    // The forwarder keeps the state file up to date while it runs
+    let state: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&state_file).unwrap()).unwrap();
+    assert_eq!(state["process_starts"], 2);
+    assert_eq!(state["sessions"].as_array().unwrap().len(), 1);
+    std::fs::remove_file(&state_file).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn consecutive_runs_keep_their_own_state() {
+    let first = run_backbone("tcp", &[], &[], &["--forwarder-id", "first"]).await;
+    let second = run_backbone("tcp", &[], &[], &["--forwarder-id", "second"]).await;
+    assert_ne!(first.run_id, second.run_id);
+    for (results, forwarder_id) in [(first, "first"), (second, "second")] {
+        let metadata = results.metadata.unwrap();
+        let forwarders: Vec<Option<&str>> = metadata
+            .forwarders
+            .iter()
+            .map(|forwarder| forwarder.forwarder_id.as_deref())
+            .collect();
+        assert_eq!(forwarders, [Some(forwarder_id)]);
+        // The arguments given to run(), not the test binary's
+        assert_eq!(
+            metadata.command_line[..3],
+            ["frankfurt-receiver", "--mode", "aws-backbone"]
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_arguments_are_returned_as_errors() {
+    let error = frankfurt_receiver::run(args(&["frankfurt-receiver", "--alert-window-secs", "0"]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Other);
+    assert_eq!(error.exit_code(), 1);
+    assert!(
+        frankfurt_receiver::run(args(&["frankfurt-receiver", "--duration", "soon"]))
+            .await
+            .is_err()
+    );
+    let error = tokyo_forwarder::run(args(&["tokyo-forwarder", "--port"]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "--port requires a value");
+
+    // A port already taken fails to bind
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let listen = taken.local_addr().unwrap().to_string();
+    let error = mock_binance::run(args(&["mock-binance", "--listen", &listen]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Io);
+}
@@ -0,0 +1,185 @@
+// The mock exchange and the receiver in one process over loopback: a short
+// baseline run measuring the mock directly.
+
+use latency_core::ExperimentResults;
+use std::net::TcpListener;
+
+/// A loopback port nothing is listening on
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Run the mock exchange and the receiver for a three-second baseline run and
+/// return the receiver's results. `mock_args` and `receiver_args` are added to
+/// their command lines; `{exchange}` in `receiver_args` is the mock's address.
+async fn run_baseline(mock_args: &[&str], receiver_args: &[&str]) -> ExperimentResults {
+    let exchange_port = free_port();
+    let exchange = format!("127.0.0.1:{}", exchange_port);
+    // Tests run in parallel, so each writes results of its own
+    let output = std::env::temp_dir().join(format!(
+        "itest-results-baseline-{}-{}.json",
+        std::process::id(),
+        exchange_port
+    ));
+    let output = output.to_str().unwrap();
+    let ws_url = format!("ws://{}/ws/btcusdt@bookTicker", exchange);
+
+    let mut mock_command = args(&[
+        "mock-binance",
+        "--listen",
+        &exchange,
+        "--rate",
+        "200",
+        "--log-level",
+        "warn",
+    ]);
+    mock_command.extend(args(mock_args));
+    let mock = mock_binance::run(mock_command);
+    let mut receiver_command = args(&[
+        "frankfurt-receiver",
+        "--mode",
+        "baseline",
+        "--ws-url",
+        &ws_url,
+        "--duration",
+        "3",
+        "--output",
+        output,
+        "--log-level",
+        "warn",
+    ]);
+    receiver_command.extend(
+        receiver_args
+            .iter()
+            .map(|arg| arg.replace("{exchange}", &exchange)),
+    );
+    let receiver = async {
+        // The mock listens before the receiver dials it
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        frankfurt_receiver::run(receiver_command).await
+    };
+
+    // The mock runs until stopped; the receiver stops itself
+    tokio::select! {
+        result = receiver => result.unwrap(),
+        result = mock => panic!("mock exchange stopped: {:?}", result),
+    }
+
+    let results = ExperimentResults::load(output).unwrap();
+    std::fs::remove_file(output).unwrap();
+    results
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_time_corrects_a_skewed_exchange_clock() {
+    // An exchange clock 50 ms ahead makes every measured latency negative
+    let results = run_baseline(
+        &["--clock-offset-ms", "50"],
+        &[
+            "--server-time",
+            "http://{exchange}/api/v3/time",
+            "--server-time-interval",
+            "1",
+            "--correct-server-time",
+        ],
+    )
+    .await;
+    assert!(
+        results.sample_count > 100,
+        "only {} samples",
+        results.sample_count
+    );
+    let server_time = results.server_time.unwrap();
+    assert!(server_time.applied);
+    assert!(server_time.samples.len() >= 3);
+    assert!(
+        (45.0..55.0).contains(&server_time.avg_offset_ms),
+        "offset {} ms",
+        server_time.avg_offset_ms
+    );
+    assert!(server_time.skewed());
+    assert_eq!(server_time.uncorrected_events, 0);
+    assert!(server_time.negative_latencies as usize > results.sample_count / 2);
+    assert!(
+        results.avg_latency_ms > -5.0 && results.avg_latency_ms < 10.0,
+        "corrected latency {} ms",
+        results.avg_latency_ms
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sampling_thins_the_csv_but_not_the_counters() {
+    let csv = std::env::temp_dir().join(format!("itest-sampling-{}.csv", std::process::id()));
+    let csv_path = csv.to_str().unwrap();
+    let results = run_baseline(&[], &["--sample-rate", "1/4", "--csv-output", csv_path]).await;
+    let sampling = results.sampling.unwrap();
+    assert!(sampling.events > 400, "only {} events", sampling.events);
+    assert_eq!(sampling.retained as usize, results.sample_count);
+    assert!(
+        (15.0..35.0).contains(&sampling.retained_pct()),
+        "{}% kept",
+        sampling.retained_pct()
+    );
+    let whole_run = results.streaming.unwrap().since_start;
+    assert_eq!(whole_run.count, sampling.events);
+
+    // Only the kept events are written, each once
+    let csv = std::fs::read_to_string(csv_path).unwrap();
+    let rows = csv
+        .lines()
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_digit()))
+        .count();
+    assert_eq!(rows, results.sample_count);
+    std::fs::remove_file(csv_path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn results_record_the_platform() {
+    let results = run_baseline(&[], &[]).await;
+    let metadata = results.metadata.unwrap();
+    assert_eq!(
+        metadata.platform.unwrap(),
+        format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+    );
+    assert!(metadata.hostname.is_some());
+    assert!(metadata.kernel_version.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn openmetrics_histogram_counts_every_sample() {
+    let path = std::env::temp_dir().join(format!("itest-openmetrics-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let results = run_baseline(
+        &[],
+        &[
+            "--openmetrics-output",
+            path,
+            "--openmetrics-buckets",
+            "1,10,100",
+        ],
+    )
+    .await;
+    let text = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let count = text
+        .lines()
+        .find(|line| line.starts_with("latency_seconds_count{"))
+        .and_then(|line| line.rsplit(' ').next())
+        .unwrap();
+    assert_eq!(count, results.sample_count.to_string());
+    assert_eq!(
+        text.lines()
+            .filter(|line| line.starts_with("latency_seconds_bucket{"))
+            .count(),
+        4
+    );
+    assert!(text.ends_with("# EOF\n"));
+}
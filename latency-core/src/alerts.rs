@@ -0,0 +1,194 @@
+// Latency and loss alarms for unattended runs
+//
+// Latencies and sequence gaps are kept for a rolling window of whole seconds.
+// At the end of every second the window's p99 latency and loss rate are checked
+// against their thresholds. An alarm fires when a value crosses its threshold
+// and resolves when it is back at or below it, so a long excursion is reported
+// twice rather than every second.
+
+use crate::collector::SecondStats;
+use crate::measurement::LatencyMeasurement;
+use crate::stats::percentile;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Fewest latency samples in the window before the p99 is checked
+pub const MIN_ALERT_SAMPLES: usize = 100;
+
+/// What is watched, from the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertThresholds {
+    pub p99_ms: Option<f64>,
+    pub loss_pct: Option<f64>, // Events lost per 100 sent
+    pub window_secs: u64,
+}
+
+/// The value an alarm watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    P99Latency,
+    Loss,
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AlertKind::P99Latency => "p99 latency",
+            AlertKind::Loss => "loss rate",
+        })
+    }
+}
+
+/// An alarm firing or resolving
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub firing: bool,      // False when it resolves
+    pub elapsed_secs: u64, // Of the collector, at the end of the second checked
+    pub value: f64,        // ms for p99 latency, % for loss
+    pub threshold: f64,
+    pub window_secs: u64,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = match self.kind {
+            AlertKind::P99Latency => " ms",
+            AlertKind::Loss => "%",
+        };
+        write!(
+            f,
+            "{} {:.2}{} over the last {} s {} the threshold of {}{}",
+            self.kind,
+            self.value,
+            unit,
+            self.window_secs,
+            if self.firing {
+                "exceeds"
+            } else {
+                "is back within"
+            },
+            self.threshold,
+            unit
+        )
+    }
+}
+
+/// One closed second of the window
+#[derive(Debug)]
+struct Second {
+    latencies: Vec<f64>, // ms
+    events: u64,
+    lost: u64,
+}
+
+/// Checks the rolling window against the thresholds
+#[derive(Debug)]
+pub struct AlertMonitor {
+    thresholds: AlertThresholds,
+    current: Vec<f64>, // Latencies of the second still open
+    window: VecDeque<Second>,
+    p99_firing: bool,
+    loss_firing: bool,
+    history: Vec<Alert>,
+}
+
+impl AlertMonitor {
+    pub fn new(thresholds: AlertThresholds) -> Self {
+        Self {
+            thresholds,
+            current: Vec::new(),
+            window: VecDeque::new(),
+            p99_firing: false,
+            loss_firing: false,
+            history: Vec::new(),
+        }
+    }
+
+    /// Count one measurement; warm-up ones are left out
+    pub fn record(&mut self, measurement: &LatencyMeasurement) {
+        if !measurement.warmup {
+            self.current.push(measurement.end_to_end_latency_ms());
+        }
+    }
+
+    /// Close a second and return the alarms that fired or resolved with it
+    pub fn second(&mut self, second: &SecondStats) -> Vec<Alert> {
+        self.window.push_back(Second {
+            latencies: std::mem::take(&mut self.current),
+            events: second.events,
+            lost: second.events_lost,
+        });
+        while self.window.len() as u64 > self.thresholds.window_secs.max(1) {
+            self.window.pop_front();
+        }
+
+        let mut alerts = Vec::new();
+        if let (Some(threshold), Some(p99)) = (self.thresholds.p99_ms, self.p99()) {
+            if let Some(alert) = self.check(AlertKind::P99Latency, p99, threshold, second) {
+                alerts.push(alert);
+            }
+        }
+        if let (Some(threshold), Some(loss)) = (self.thresholds.loss_pct, self.loss_pct()) {
+            if let Some(alert) = self.check(AlertKind::Loss, loss, threshold, second) {
+                alerts.push(alert);
+            }
+        }
+        self.history.extend(alerts.iter().cloned());
+        alerts
+    }
+
+    /// Every alarm fired or resolved so far
+    pub fn history(&self) -> &[Alert] {
+        &self.history
+    }
+
+    fn p99(&self) -> Option<f64> {
+        let mut latencies: Vec<f64> = self
+            .window
+            .iter()
+            .flat_map(|second| second.latencies.iter().copied())
+            .collect();
+        if latencies.len() < MIN_ALERT_SAMPLES {
+            return None;
+        }
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Some(percentile(&latencies, 0.99))
+    }
+
+    fn loss_pct(&self) -> Option<f64> {
+        let (events, lost) = self.window.iter().fold((0, 0), |(events, lost), second| {
+            (events + second.events, lost + second.lost)
+        });
+        let sent = events + lost;
+        (sent > 0).then(|| lost as f64 * 100.0 / sent as f64)
+    }
+
+    fn check(
+        &mut self,
+        kind: AlertKind,
+        value: f64,
+        threshold: f64,
+        second: &SecondStats,
+    ) -> Option<Alert> {
+        let firing = match kind {
+            AlertKind::P99Latency => &mut self.p99_firing,
+            AlertKind::Loss => &mut self.loss_firing,
+        };
+        let exceeded = value > threshold;
+        if exceeded == *firing {
+            return None;
+        }
+        *firing = exceeded;
+        Some(Alert {
+            kind,
+            firing: exceeded,
+            elapsed_secs: second.elapsed_secs,
+            value,
+            threshold,
+            window_secs: self.thresholds.window_secs,
+        })
+    }
+}
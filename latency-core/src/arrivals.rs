@@ -0,0 +1,216 @@
+// Arrival logs keyed by exchange update ID (--arrival-log and `merge`)
+//
+// The forwarder and a baseline receiver connected to the same stream each log
+// when every exchange event arrived, keyed by the venue's update ID (Binance
+// `a`, `t` or `u`). Joining the two logs on that ID gives the difference in
+// arrival time of the same event at both hosts, without going through the
+// exchange event time and its millisecond resolution.
+
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// First line of every arrival log
+pub const ARRIVAL_LOG_HEADER: &str = "update_id,event_time,receive_time\n";
+
+/// When one host received an exchange event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateArrival {
+    pub update_id: i64,
+    pub event_time: Option<i64>, // Exchange event time as published, if the feed has one
+    pub receive_time: i64,       // Local receive time (epoch nanos)
+}
+
+/// Writes arrivals as CSV rows
+#[derive(Debug)]
+pub struct ArrivalLog {
+    writer: BufWriter<File>,
+    rows: u64,
+}
+
+impl ArrivalLog {
+    /// Create the file and write the header
+    pub fn create(filepath: &str) -> Result<Self, std::io::Error> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writer.write_all(ARRIVAL_LOG_HEADER.as_bytes())?;
+        Ok(Self { writer, rows: 0 })
+    }
+
+    pub fn write(&mut self, arrival: &UpdateArrival) -> Result<(), std::io::Error> {
+        match arrival.event_time {
+            Some(event_time) => writeln!(
+                self.writer,
+                "{},{},{}",
+                arrival.update_id, event_time, arrival.receive_time
+            )?,
+            None => writeln!(
+                self.writer,
+                "{},,{}",
+                arrival.update_id, arrival.receive_time
+            )?,
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Rows written so far
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Flush buffered rows to disk
+    pub fn finish(mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+}
+
+/// Read an arrival log written by `ArrivalLog`
+pub fn read_arrivals(filepath: &str) -> Result<Vec<UpdateArrival>, std::io::Error> {
+    let invalid = |line: usize, what: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", filepath, line, what),
+        )
+    };
+
+    let mut lines = BufReader::new(File::open(filepath)?).lines();
+    let header = lines.next().ok_or_else(|| invalid(1, "empty file"))??;
+    if header.trim() != ARRIVAL_LOG_HEADER.trim() {
+        return Err(invalid(1, "not an arrival log"));
+    }
+
+    let mut arrivals = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = i + 2;
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let [update_id, event_time, receive_time] = fields[..] else {
+            return Err(invalid(line_number, "expected 3 fields"));
+        };
+        let parse = |value: &str| value.parse().map_err(|_| invalid(line_number, value));
+        arrivals.push(UpdateArrival {
+            update_id: parse(update_id)?,
+            event_time: (!event_time.is_empty())
+                .then(|| parse(event_time))
+                .transpose()?,
+            receive_time: parse(receive_time)?,
+        });
+    }
+    Ok(arrivals)
+}
+
+/// An event found in both logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchedArrival {
+    pub update_id: i64,
+    pub event_time: Option<i64>,
+    pub tokyo_time: i64,     // Forwarder receive time (epoch nanos)
+    pub frankfurt_time: i64, // Receiver receive time (epoch nanos)
+}
+
+impl MatchedArrival {
+    /// How much later the receiver got the event than the forwarder
+    pub fn delta_ns(&self) -> i64 {
+        self.frankfurt_time - self.tokyo_time
+    }
+}
+
+/// Two arrival logs joined by update ID
+#[derive(Debug, Clone, Default)]
+pub struct ArrivalMerge {
+    pub matched: Vec<MatchedArrival>, // In forwarder arrival order
+    pub tokyo_only: usize,            // Received by the forwarder only
+    pub frankfurt_only: usize,        // Received by the receiver only
+    pub duplicates: usize,            // Repeated IDs within one log; the first arrival is kept
+}
+
+/// Summary of a merge, written as JSON by `merge`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArrivalDeltaStats {
+    pub matched: usize,
+    pub tokyo_only: usize,
+    pub frankfurt_only: usize,
+    pub duplicates: usize,
+    pub delta: LatencySummary, // Receiver − forwarder arrival (ms)
+    pub percentiles: BTreeMap<String, f64>, // Same, keyed "p50", "p99.9", ...
+}
+
+/// Join the forwarder's (`tokyo`) and receiver's (`frankfurt`) arrival logs
+pub fn merge_arrivals(tokyo: &[UpdateArrival], frankfurt: &[UpdateArrival]) -> ArrivalMerge {
+    let mut merge = ArrivalMerge::default();
+
+    let mut received: HashMap<i64, &UpdateArrival> = HashMap::with_capacity(frankfurt.len());
+    for arrival in frankfurt {
+        match received.entry(arrival.update_id) {
+            Entry::Occupied(_) => merge.duplicates += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(arrival);
+            }
+        }
+    }
+
+    let mut forwarded = HashSet::with_capacity(tokyo.len());
+    for arrival in tokyo {
+        if !forwarded.insert(arrival.update_id) {
+            merge.duplicates += 1;
+            continue;
+        }
+        match received.remove(&arrival.update_id) {
+            Some(frankfurt) => merge.matched.push(MatchedArrival {
+                update_id: arrival.update_id,
+                event_time: arrival.event_time.or(frankfurt.event_time),
+                tokyo_time: arrival.receive_time,
+                frankfurt_time: frankfurt.receive_time,
+            }),
+            None => merge.tokyo_only += 1,
+        }
+    }
+    merge.frankfurt_only = received.len();
+    merge
+}
+
+impl ArrivalMerge {
+    /// Arrival deltas of the matched events, with the given percentiles (in percent)
+    pub fn stats(&self, percentiles: &[f64]) -> ArrivalDeltaStats {
+        let deltas: StatsAggregator = self
+            .matched
+            .iter()
+            .map(|m| m.delta_ns() as f64 / 1_000_000.0)
+            .collect();
+        ArrivalDeltaStats {
+            matched: self.matched.len(),
+            tokyo_only: self.tokyo_only,
+            frankfurt_only: self.frankfurt_only,
+            duplicates: self.duplicates,
+            delta: deltas.summary(),
+            percentiles: deltas.percentiles(percentiles),
+        }
+    }
+
+    /// Write the matched events as CSV
+    pub fn write_csv(&self, filepath: &str) -> Result<(), std::io::Error> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writeln!(
+            writer,
+            "update_id,event_time,tokyo_time,frankfurt_time,delta_ms"
+        )?;
+        for m in &self.matched {
+            writeln!(
+                writer,
+                "{},{},{},{},{:.3}",
+                m.update_id,
+                m.event_time.map_or(String::new(), |t| t.to_string()),
+                m.tokyo_time,
+                m.frankfurt_time,
+                m.delta_ns() as f64 / 1_000_000.0
+            )?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,164 @@
+// Bootstrap confidence intervals for the headline percentiles (--bootstrap)
+//
+// A run's median or p99 is a point estimate: the same path measured again
+// gives a somewhat different one, the tail most of all, as it rests on a
+// small share of the events. Resampling the run's latencies with replacement
+// and taking the percentiles of every resample shows how far they move; the
+// middle 95% of them is the confidence interval. A baseline and a backbone
+// median whose intervals do not overlap differ by more than chance.
+//
+// Resamples are drawn from a fixed seed, so the same measurements always give
+// the same intervals. Each one costs a pass over the samples, so the time
+// taken grows with samples × iterations / cores.
+
+use crate::measurement::LatencyMeasurement;
+use crate::sampling::mix;
+use crate::stats::percentile;
+use serde::{Deserialize, Serialize};
+
+/// Share of the resampled percentiles an interval covers
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+const SEED: u64 = 0x6c61_7465_6e63_7921;
+
+/// A percentile of the run with its bootstrap confidence interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub estimate_ms: f64, // From the run's own samples
+    pub lower_ms: f64,
+    pub upper_ms: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn width_ms(&self) -> f64 {
+        self.upper_ms - self.lower_ms
+    }
+
+    /// Whether the two intervals share any value. Percentiles whose intervals
+    /// do not overlap differ significantly; overlapping ones still may.
+    pub fn overlaps(&self, other: &ConfidenceInterval) -> bool {
+        self.lower_ms <= other.upper_ms && other.lower_ms <= self.upper_ms
+    }
+}
+
+/// Confidence intervals of the end-to-end median, p95 and p99
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BootstrapIntervals {
+    pub iterations: usize,
+    pub confidence: f64, // CONFIDENCE_LEVEL
+    pub median: ConfidenceInterval,
+    pub p95: ConfidenceInterval,
+    pub p99: ConfidenceInterval,
+}
+
+/// Resample end-to-end latency `iterations` times. Warm-up is left out;
+/// returns nothing without samples or iterations.
+pub fn bootstrap_intervals(
+    measurements: &[LatencyMeasurement],
+    iterations: usize,
+) -> Option<BootstrapIntervals> {
+    let mut sorted: Vec<f64> = measurements
+        .iter()
+        .filter(|m| !m.warmup)
+        .map(|m| m.end_to_end_latency_ms())
+        .collect();
+    if sorted.is_empty() || iterations == 0 {
+        return None;
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    // Resamples are spread over the cores. Resample i always takes the same
+    // draws, so the intervals do not depend on how many there are.
+    const LEVELS: [f64; 3] = [0.5, 0.95, 0.99];
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let per_thread = iterations.div_ceil(threads);
+    let chunks: Vec<Vec<[f64; 3]>> = std::thread::scope(|scope| {
+        let sorted = &sorted;
+        let handles: Vec<_> = (0..iterations)
+            .step_by(per_thread)
+            .map(|first| {
+                let last = (first + per_thread).min(iterations);
+                scope.spawn(move || {
+                    let mut counts = vec![0u32; sorted.len()];
+                    let mut draws = Draws(SEED.wrapping_add((first * sorted.len()) as u64));
+                    (first..last)
+                        .map(|_| resample_percentiles(sorted, &LEVELS, &mut counts, &mut draws))
+                        .collect()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    let mut resampled: [Vec<f64>; 3] = Default::default();
+    for values in chunks.into_iter().flatten() {
+        for (resampled, value) in resampled.iter_mut().zip(values) {
+            resampled.push(value);
+        }
+    }
+
+    let [median, p95, p99] = resampled;
+    let interval = |mut values: Vec<f64>, level: f64| {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let tail = (1.0 - CONFIDENCE_LEVEL) / 2.0;
+        ConfidenceInterval {
+            estimate_ms: percentile(&sorted, level),
+            lower_ms: percentile(&values, tail),
+            upper_ms: percentile(&values, 1.0 - tail),
+        }
+    };
+    Some(BootstrapIntervals {
+        iterations,
+        confidence: CONFIDENCE_LEVEL,
+        median: interval(median, LEVELS[0]),
+        p95: interval(p95, LEVELS[1]),
+        p99: interval(p99, LEVELS[2]),
+    })
+}
+
+/// Percentiles at ascending `levels` of one resample of `sorted`. The
+/// resample is kept as how often each sample was drawn, so it is never
+/// sorted: walking the counts visits its values in order.
+fn resample_percentiles<const N: usize>(
+    sorted: &[f64],
+    levels: &[f64; N],
+    counts: &mut [u32],
+    draws: &mut Draws,
+) -> [f64; N] {
+    counts.fill(0);
+    for _ in 0..sorted.len() {
+        counts[draws.below(sorted.len())] += 1;
+    }
+
+    // Resample value at each 0-based position, as `percentile` interpolates
+    let value_at = |position: usize, start: &mut (usize, usize)| {
+        let (index, seen) = start;
+        while *seen + counts[*index] as usize <= position {
+            *seen += counts[*index] as usize;
+            *index += 1;
+        }
+        sorted[*index]
+    };
+    let mut walk = (0, 0); // Sample index, resample values before it
+    levels.map(|level| {
+        let position = level * (sorted.len() - 1) as f64;
+        let lower = value_at(position.floor() as usize, &mut walk);
+        let upper = value_at(position.ceil() as usize, &mut walk);
+        let weight = position - position.floor();
+        lower * (1.0 - weight) + upper * weight
+    })
+}
+
+/// Random draws: SplitMix64's finalizer over a counter, enough for resampling
+struct Draws(u64);
+
+impl Draws {
+    /// Uniform in 0..n
+    fn below(&mut self, n: usize) -> usize {
+        let x = mix(self.0);
+        self.0 = self.0.wrapping_add(1);
+        ((x as u128 * n as u128) >> 64) as usize
+    }
+}
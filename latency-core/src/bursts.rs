@@ -0,0 +1,86 @@
+// Latency bursts: are slow events isolated or do they come in stretches?
+//
+// Autocorrelation of consecutive latencies (in arrival order) near zero means
+// each event's latency is independent of the last; values near one mean a
+// slow event is followed by more slow events. Runs of consecutive latencies
+// above the median give the same answer in events: with independent latencies
+// they average two events, sustained bursts stretch them far beyond that.
+
+use crate::stats::percentile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Event distances at which autocorrelation is reported
+pub const AUTOCORRELATION_LAGS: &[usize] = &[1, 2, 5, 10, 100];
+
+/// Autocorrelation and above-median run lengths of a run's latencies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurstStats {
+    // Correlation of each latency with the one `lag` events later, keyed
+    // "lag1", "lag10", ...; lags as long as the run are left out
+    pub autocorrelation: BTreeMap<String, f64>,
+    pub above_median_runs: usize, // Stretches of consecutive latencies above the median
+    pub avg_run_length: f64,      // Events per stretch
+    pub p99_run_length: f64,
+    pub max_run_length: usize,
+}
+
+/// Burst statistics of `latencies_ms` in arrival order. Returns nothing for
+/// fewer than two samples or latencies that never vary.
+pub fn burst_stats(latencies_ms: &[f64]) -> Option<BurstStats> {
+    let n = latencies_ms.len();
+    if n < 2 {
+        return None;
+    }
+
+    let mean = latencies_ms.iter().sum::<f64>() / n as f64;
+    let deviations: Vec<f64> = latencies_ms.iter().map(|ms| ms - mean).collect();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+    if variance == 0.0 {
+        return None;
+    }
+    let autocorrelation = AUTOCORRELATION_LAGS
+        .iter()
+        .filter(|&&lag| lag < n)
+        .map(|&lag| {
+            let covariance: f64 = deviations
+                .iter()
+                .zip(&deviations[lag..])
+                .map(|(a, b)| a * b)
+                .sum();
+            (format!("lag{}", lag), covariance / variance)
+        })
+        .collect();
+
+    let mut sorted = latencies_ms.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = percentile(&sorted, 0.5);
+
+    let mut runs = Vec::new();
+    let mut current = 0;
+    for &ms in latencies_ms {
+        if ms > median {
+            current += 1;
+        } else if current > 0 {
+            runs.push(current);
+            current = 0;
+        }
+    }
+    if current > 0 {
+        runs.push(current);
+    }
+    runs.sort_unstable();
+    let lengths: Vec<f64> = runs.iter().map(|&len| len as f64).collect();
+
+    Some(BurstStats {
+        autocorrelation,
+        above_median_runs: runs.len(),
+        avg_run_length: if runs.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<f64>() / runs.len() as f64
+        },
+        p99_run_length: percentile(&lengths, 0.99),
+        max_run_length: runs.last().copied().unwrap_or(0),
+    })
+}
@@ -0,0 +1,199 @@
+// Clock error estimates and the one-way delay they correct (--clock-sync)
+//
+// The backbone latency subtracts the forwarder's timestamp from the
+// receiver's, so it is off by the difference between the two clocks' offsets
+// from true time. With --clock-sync each side reads its own offset, and the
+// bound on that estimate, from chrony or the PTP daemon, and the forwarder
+// sends its latest estimate with every event. Events with an estimate from
+// both sides get a corrected one-way delay, whose error bar is the two bounds
+// added together.
+
+use crate::metadata::ChronyTracking;
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a host's clock error estimate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSource {
+    Chrony, // `chronyc tracking`
+    Ptp,    // ptp4l's current data set, through `pmc`
+}
+
+impl FromStr for ClockSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chrony" => Ok(ClockSource::Chrony),
+            "ptp" => Ok(ClockSource::Ptp),
+            _ => Err(format!(
+                "unknown clock source {}: expected chrony or ptp",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClockSource::Chrony => "chrony",
+            ClockSource::Ptp => "ptp",
+        })
+    }
+}
+
+/// How far a host's clock is from true time, as its synchronization daemon
+/// estimates it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockEstimate {
+    pub offset_ns: i64,      // Positive when the clock is ahead
+    pub error_bound_ns: i64, // The true offset is within this of `offset_ns`
+}
+
+impl ClockEstimate {
+    /// `timestamp` (epoch nanos) taken on this clock, in true time
+    pub fn correct(&self, timestamp: i64) -> i64 {
+        timestamp - self.offset_ns
+    }
+}
+
+impl ChronyTracking {
+    /// chrony's bound on the clock error is |offset| + root dispersion +
+    /// root delay / 2; once corrected by the offset, the rest remains
+    pub fn estimate(&self) -> ClockEstimate {
+        ClockEstimate {
+            offset_ns: ms_to_nanos(self.system_time_offset_ms),
+            error_bound_ns: ms_to_nanos(self.root_dispersion_ms + self.root_delay_ms / 2.0),
+        }
+    }
+}
+
+/// Offset from the grandmaster as reported by
+/// `pmc -u -b 0 'GET CURRENT_DATA_SET'`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PtpDataSet {
+    pub offset_from_master_ns: f64,
+    pub mean_path_delay_ns: f64,
+}
+
+impl PtpDataSet {
+    /// Parse the output of `pmc`; `None` if a field is missing
+    pub fn parse(output: &str) -> Option<Self> {
+        let field = |name: &str| -> Option<f64> {
+            output.lines().find_map(|line| {
+                let mut words = line.split_whitespace();
+                (words.next()? == name).then(|| words.next()?.parse().ok())?
+            })
+        };
+        Some(Self {
+            offset_from_master_ns: field("offsetFromMaster")?,
+            mean_path_delay_ns: field("meanPathDelay")?,
+        })
+    }
+
+    /// PTP assumes a symmetric path, so an asymmetric one moves the offset by
+    /// up to half the path delay, like chrony's root delay
+    pub fn estimate(&self) -> ClockEstimate {
+        ClockEstimate {
+            offset_ns: self.offset_from_master_ns.round() as i64,
+            error_bound_ns: (self.mean_path_delay_ns / 2.0).round() as i64,
+        }
+    }
+}
+
+/// One reading of the local clock error, kept in the run metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockSample {
+    pub time: i64, // Epoch nanos
+    pub source: ClockSource,
+    pub offset_ms: f64,
+    pub error_bound_ms: f64,
+}
+
+impl ClockSample {
+    pub fn new(time: i64, source: ClockSource, estimate: ClockEstimate) -> Self {
+        Self {
+            time,
+            source,
+            offset_ms: nanos_to_ms(estimate.offset_ns),
+            error_bound_ms: nanos_to_ms(estimate.error_bound_ns),
+        }
+    }
+}
+
+/// Backbone latency corrected for both clocks' offsets (milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneWayDelay {
+    pub delay: LatencySummary,         // Corrected Tokyo → receiver delay
+    pub avg_correction_ms: f64,        // Subtracted from the measured backbone latency, on average
+    pub avg_error_bound_ms: f64,       // ± on each event's delay, on average
+    pub max_error_bound_ms: f64,       // Widest error bar of any event
+    pub forwarder_error_bound_ms: f64, // Largest bound the forwarder reported
+    pub receiver_error_bound_ms: f64,  // Largest bound of the receiver's own clock
+}
+
+/// Collects the corrected one-way delay of events that carry the forwarder's
+/// clock estimate, received while the receiver's own was known
+#[derive(Debug, Default)]
+pub struct OneWayDelayTracker {
+    delay: StatsAggregator,
+    correction_ns: i128,
+    error_bound_ns: i128,
+    max_error_bound_ns: i64,
+    forwarder_bound_ns: i64,
+    receiver_bound_ns: i64,
+}
+
+impl OneWayDelayTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one event from its Tokyo and receiver timestamps (epoch nanos)
+    pub fn record(
+        &mut self,
+        tokyo_receive_time: i64,
+        frankfurt_receive_time: i64,
+        forwarder: ClockEstimate,
+        receiver: ClockEstimate,
+    ) {
+        let measured = frankfurt_receive_time - tokyo_receive_time;
+        let corrected =
+            receiver.correct(frankfurt_receive_time) - forwarder.correct(tokyo_receive_time);
+        let error_bound = forwarder.error_bound_ns + receiver.error_bound_ns;
+        self.delay.push(nanos_to_ms(corrected));
+        self.correction_ns += i128::from(measured - corrected);
+        self.error_bound_ns += i128::from(error_bound);
+        self.max_error_bound_ns = self.max_error_bound_ns.max(error_bound);
+        self.forwarder_bound_ns = self.forwarder_bound_ns.max(forwarder.error_bound_ns);
+        self.receiver_bound_ns = self.receiver_bound_ns.max(receiver.error_bound_ns);
+    }
+
+    /// `None` if no event could be corrected
+    pub fn results(&self) -> Option<OneWayDelay> {
+        if self.delay.is_empty() {
+            return None;
+        }
+        let count = self.delay.len() as f64;
+        Some(OneWayDelay {
+            delay: self.delay.summary(),
+            avg_correction_ms: self.correction_ns as f64 / count / 1_000_000.0,
+            avg_error_bound_ms: self.error_bound_ns as f64 / count / 1_000_000.0,
+            max_error_bound_ms: nanos_to_ms(self.max_error_bound_ns),
+            forwarder_error_bound_ms: nanos_to_ms(self.forwarder_bound_ns),
+            receiver_error_bound_ms: nanos_to_ms(self.receiver_bound_ns),
+        })
+    }
+}
+
+fn ms_to_nanos(ms: f64) -> i64 {
+    (ms * 1_000_000.0).round() as i64
+}
+
+fn nanos_to_ms(nanos: i64) -> f64 {
+    nanos as f64 / 1_000_000.0
+}
@@ -0,0 +1,740 @@
+// Measurement collection with per-second windows and sequence tracking
+
+use crate::alerts::{Alert, AlertMonitor, AlertThresholds};
+use crate::bootstrap::bootstrap_intervals;
+use crate::digest::{StreamingPercentiles, StreamingStats};
+use crate::gaps::SequenceGap;
+use crate::measurement::LatencyMeasurement;
+use crate::quantization::{quantized_latency, QuantizedLatency};
+use crate::quotes::{QuoteStats, QuoteTracker};
+use crate::report::Report;
+use crate::results::ExperimentResults;
+use crate::sampling::{SampleRate, SamplingStats};
+use crate::session::{session_stats, SessionSplit, SessionStats};
+use crate::sources::{source_stats, SourceStats};
+use crate::spikes::{Spike, SpikeContext, SpikeDetector, MAX_SPIKES};
+use crate::stale::{stale_stats, StaleStats};
+use crate::stats::{StatsAggregator, DEFAULT_PERCENTILES};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Statistics for one reporting window (roughly one second)
+#[derive(Debug, Clone, Copy)]
+pub struct SecondStats {
+    pub elapsed_secs: u64, // Seconds since the collector was created
+    pub events: u64,
+    pub avg_latency_ms: f64,
+    pub min_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub avg_backbone_latency_ms: Option<f64>,
+    pub events_lost: u64,           // New sequence gaps observed in this window
+    pub quotes: Option<QuoteStats>, // Top of book, once quotes are recorded
+}
+
+/// Sequence tracking for the IDs of one forwarder. Only the ranges still
+/// missing below the highest ID are stored, so memory grows with the gaps
+/// rather than with the events.
+#[derive(Debug, Default)]
+pub(crate) struct Sequences {
+    first: Option<u64>, // Lowest ID accounted for
+    max: Option<u64>,
+    missing: BTreeMap<u64, u64>, // Gaps between `first` and `max`: first missing ID → one past the last
+    lost: u64,                   // IDs in `missing`
+    pruned: bool,                // `first` was raised as the window moved on
+    pub duplicates: usize,
+    pub reordered: usize,          // Arrived after a higher sequence ID
+    pub max_reorder_distance: u64, // Largest amount by which an arrival trailed the highest ID
+}
+
+impl Sequences {
+    /// Number of sequence IDs missing between the lowest and highest received
+    pub fn events_lost(&self) -> usize {
+        self.lost as usize
+    }
+
+    /// Whether `id` was received (or has fallen below the window)
+    fn contains(&self, id: u64) -> bool {
+        let (Some(first), Some(max)) = (self.first, self.max) else {
+            return false;
+        };
+        (first..=max).contains(&id) && self.missing_range(id).is_none()
+    }
+
+    /// The missing range that holds `id`, if any
+    fn missing_range(&self, id: u64) -> Option<(u64, u64)> {
+        self.missing
+            .range(..=id)
+            .next_back()
+            .filter(|&(_, &end)| id < end)
+            .map(|(&start, &end)| (start, end))
+    }
+
+    fn add_missing(&mut self, start: u64, end: u64) {
+        if start < end {
+            self.missing.insert(start, end);
+            self.lost += end - start;
+        }
+    }
+
+    fn insert(&mut self, id: u64) {
+        let (Some(first), Some(max)) = (self.first, self.max) else {
+            self.first = Some(id);
+            self.max = Some(id);
+            return;
+        };
+        if id > max {
+            self.add_missing(max + 1, id);
+            self.max = Some(id);
+        } else if id < first {
+            // Below a pruned window there is nothing left to compare against
+            if !self.pruned {
+                self.add_missing(id + 1, first);
+                self.first = Some(id);
+            }
+        } else if let Some((start, end)) = self.missing_range(id) {
+            self.missing.remove(&start);
+            self.lost -= end - start;
+            self.add_missing(start, id);
+            self.add_missing(id + 1, end);
+        }
+    }
+
+    /// Forget IDs below `floor`, the lowest one still in the window, or all of
+    /// them if the window holds none
+    fn prune(&mut self, floor: Option<u64>) {
+        let Some(max) = self.max else {
+            return;
+        };
+        let floor = floor.unwrap_or(max + 1);
+        if self.first.is_some_and(|first| floor <= first) {
+            return;
+        }
+        let straddling = self.missing_range(floor);
+        self.missing = self.missing.split_off(&floor);
+        if let Some((_, end)) = straddling {
+            self.missing.insert(floor, end);
+        }
+        self.lost = self.missing.iter().map(|(start, end)| end - start).sum();
+        self.first = Some(floor);
+        self.pruned = true;
+    }
+}
+
+/// Accumulates measurements for one experiment run
+#[derive(Debug)]
+pub struct Collector {
+    measurements: Vec<LatencyMeasurement>,
+    stale: Vec<LatencyMeasurement>, // Older than `max_staleness` on arrival; not in statistics
+    // By measurement source; several forwarders number their events independently
+    sequences: Vec<(Option<Arc<str>>, Sequences)>,
+    gaps: Vec<SequenceGap>,
+    start_time: Instant,
+    warmup: Duration,
+    window: Option<Duration>, // Keep only this much history (continuous mode)
+    percentiles: Vec<f64>,    // Reported in results (percent)
+    sessions: Option<SessionSplit>, // Latency per UTC session in results
+    quantization: bool,       // Latency corrected for event time truncation in results
+    max_staleness: Option<Duration>, // Events older than this go to `stale`
+    sample_rate: Option<SampleRate>, // Keep only this fraction in `measurements`
+    bootstrap_iterations: Option<usize>, // Confidence intervals in final results
+    events: u64,              // Recorded and not stale, whether kept or not
+    events_retained: u64,     // Of those, kept in `measurements`
+    last_kept: bool,          // The last event recorded is the last in `measurements`
+
+    // Approximate end-to-end percentiles beyond the window (continuous mode)
+    streaming_run: Option<StreamingStats>, // Up to the current interval
+    streaming_interval: Option<StreamingStats>, // Since `start_streaming_interval`
+
+    // Per-second tracking
+    last_second_report: Instant,
+    events_this_second: u64,
+    e2e_latencies_this_second: StatsAggregator,
+    backbone_latencies_this_second: StatsAggregator,
+    lost_this_second: u64,
+    events_last_second: u64,
+    quotes: QuoteTracker,
+
+    // Spike detection
+    spike_detector: Option<SpikeDetector>,
+    spikes: VecDeque<Spike>, // The most recent MAX_SPIKES
+    spike_count: u64,        // Every spike detected
+    spikes_reported: u64,
+
+    // Latency and loss alarms
+    alerts: Option<AlertMonitor>,
+    alerts_reported: usize,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            measurements: Vec::new(),
+            stale: Vec::new(),
+            sequences: Vec::new(),
+            gaps: Vec::new(),
+            start_time: now,
+            warmup: Duration::ZERO,
+            window: None,
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+            sessions: None,
+            quantization: false,
+            max_staleness: None,
+            sample_rate: None,
+            bootstrap_iterations: None,
+            events: 0,
+            events_retained: 0,
+            last_kept: false,
+            streaming_run: None,
+            streaming_interval: None,
+            last_second_report: now,
+            events_this_second: 0,
+            e2e_latencies_this_second: StatsAggregator::new(),
+            backbone_latencies_this_second: StatsAggregator::new(),
+            lost_this_second: 0,
+            events_last_second: 0,
+            quotes: QuoteTracker::default(),
+            spike_detector: None,
+            spikes: VecDeque::new(),
+            spike_count: 0,
+            spikes_reported: 0,
+            alerts: None,
+            alerts_reported: 0,
+        }
+    }
+
+    /// Flag measurements recorded during the first `warmup` of the run so
+    /// they are kept in the raw output but excluded from statistics
+    pub fn with_warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Keep only measurements received within the last `window`, so memory
+    /// stays bounded when the collector runs indefinitely
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Report end-to-end latency at these percentiles (in percent, e.g. 99.9)
+    pub fn with_percentiles(mut self, percentiles: Vec<f64>) -> Self {
+        self.percentiles = percentiles;
+        self
+    }
+
+    /// Report latency per session, split by UTC receive time. Hourly buckets
+    /// are left out of runs shorter than an hour.
+    pub fn with_sessions(mut self, split: SessionSplit) -> Self {
+        self.sessions = Some(split);
+        self
+    }
+
+    /// Also track end-to-end latency in sketches with the given relative
+    /// accuracy, so percentiles cover the whole run even with a window
+    pub fn with_streaming_stats(mut self, relative_accuracy: f64) -> Self {
+        self.streaming_run = Some(StreamingStats::new(relative_accuracy));
+        self.streaming_interval = Some(StreamingStats::new(relative_accuracy));
+        self
+    }
+
+    /// Report latency corrected for the resolution of the exchange event time
+    pub fn with_quantization(mut self) -> Self {
+        self.quantization = true;
+        self
+    }
+
+    /// Keep events older than `max_staleness` on arrival, measured against
+    /// the exchange event time, out of the latency statistics. They are still
+    /// tracked by sequence ID and summarized separately in results.
+    pub fn with_max_staleness(mut self, max_staleness: Duration) -> Self {
+        self.max_staleness = Some(max_staleness);
+        self
+    }
+
+    /// Keep only a sample of the events, chosen by sequence ID, for
+    /// `measurements` and the statistics calculated from them. Sequence
+    /// tracking, per-second stats, spikes, alerts and streaming stats still
+    /// see every event.
+    pub fn with_sample_rate(mut self, rate: SampleRate) -> Self {
+        self.sample_rate = Some(rate);
+        self
+    }
+
+    /// Attach confidence intervals of the median, p95 and p99 to the final
+    /// results, from this many bootstrap resamples of the measurements
+    pub fn with_bootstrap(mut self, iterations: usize) -> Self {
+        self.bootstrap_iterations = Some(iterations);
+        self
+    }
+
+    /// Flag end-to-end latency spikes more than `k` MADs above the rolling median
+    pub fn with_spike_detection(mut self, k: f64) -> Self {
+        self.spike_detector = Some(SpikeDetector::new(k));
+        self
+    }
+
+    /// Check the rolling p99 latency and loss rate against `thresholds` at
+    /// the end of every second
+    pub fn with_alerts(mut self, thresholds: AlertThresholds) -> Self {
+        self.alerts = Some(AlertMonitor::new(thresholds));
+        self
+    }
+
+    /// Whether the run is still within its warm-up period
+    pub fn in_warmup(&self) -> bool {
+        self.start_time.elapsed() < self.warmup
+    }
+
+    /// Whether `sequence_id` was already recorded from `source`, counting it
+    /// as a duplicate if so. Duplicates should be dropped rather than recorded
+    /// again.
+    pub fn check_duplicate(&mut self, source: Option<&str>, sequence_id: u64) -> bool {
+        let sequences = self.sequences(source);
+        let duplicate = sequences.contains(sequence_id);
+        if duplicate {
+            sequences.duplicates += 1;
+        }
+        duplicate
+    }
+
+    fn sequences(&mut self, source: Option<&str>) -> &mut Sequences {
+        sequences_of(&mut self.sequences, source)
+    }
+
+    /// Record a measurement. Returns the stats of the current window once
+    /// at least one second has passed since the previous window was closed.
+    pub fn record(&mut self, mut measurement: LatencyMeasurement) -> Option<SecondStats> {
+        measurement.warmup = self.in_warmup();
+        self.track(measurement, self.start_time.elapsed().as_secs_f64());
+
+        if self.last_second_report.elapsed() >= Duration::from_secs(1) {
+            Some(self.close_second())
+        } else {
+            None
+        }
+    }
+
+    /// Record the best bid and ask of a book update, reported with the
+    /// current window
+    pub fn record_quote(&mut self, bid_price: f64, ask_price: f64) {
+        self.quotes.record(bid_price, ask_price);
+    }
+
+    /// Calculate results for measurements recorded earlier, e.g. read back
+    /// from a raw CSV, as if they were arriving now. Time is taken from their
+    /// receive timestamps, counted from the earliest. The recorded warm-up
+    /// flags are kept unless this collector has a warm-up period of its own.
+    pub fn replay(mut self, setup_type: &str, mut measurements: Vec<LatencyMeasurement>) -> Report {
+        // Rows of one file are in arrival order; files may come in any order
+        measurements.sort_by_key(|m| m.frankfurt_receive_time);
+        let start = measurements.first().map_or(0, |m| m.frankfurt_receive_time);
+        let mut second_start = start;
+        for mut measurement in measurements {
+            if self.check_duplicate(measurement.source.as_deref(), measurement.sequence_id) {
+                continue;
+            }
+            let elapsed = Duration::from_nanos((measurement.frankfurt_receive_time - start) as u64);
+            if !self.warmup.is_zero() {
+                measurement.warmup = elapsed < self.warmup;
+            }
+            if measurement.frankfurt_receive_time - second_start >= 1_000_000_000 {
+                self.close_second();
+                second_start = measurement.frankfurt_receive_time;
+            }
+            self.track(measurement, elapsed.as_secs_f64());
+        }
+        self.finish(setup_type)
+    }
+
+    /// Account for one measurement received `elapsed_secs` into the run
+    fn track(&mut self, measurement: LatencyMeasurement, elapsed_secs: f64) {
+        let sequence_id = measurement.sequence_id;
+        let sequences = self.sequences(measurement.source.as_deref());
+
+        // Count gaps as they open; late arrivals that fill a gap are not subtracted
+        let mut gap = None;
+        match sequences.max {
+            Some(max) if sequence_id > max + 1 => {
+                gap = Some(SequenceGap {
+                    first_missing: max + 1,
+                    size: sequence_id - max - 1,
+                    receive_time: measurement.frankfurt_receive_time,
+                    elapsed_secs,
+                    source: measurement.source.as_deref().map(str::to_string),
+                });
+            }
+            Some(max) if sequence_id <= max => {
+                sequences.reordered += 1;
+                sequences.max_reorder_distance =
+                    sequences.max_reorder_distance.max(max - sequence_id);
+            }
+            _ => {}
+        }
+        sequences.insert(sequence_id);
+        let seq_gap = gap.is_some();
+        if let Some(gap) = gap {
+            self.lost_this_second += gap.size;
+            self.gaps.push(gap);
+        }
+        self.last_kept = false;
+        if self.is_stale(&measurement) {
+            self.stale.push(measurement);
+            return;
+        }
+        self.events += 1;
+
+        if let Some(alerts) = &mut self.alerts {
+            alerts.record(&measurement);
+        }
+        if !measurement.warmup {
+            if let Some(interval) = &mut self.streaming_interval {
+                interval.push(measurement.end_to_end_latency_ms());
+            }
+            if let Some(detector) = &mut self.spike_detector {
+                let context = SpikeContext {
+                    elapsed_secs,
+                    events_per_sec: self.events_last_second.max(self.events_this_second + 1),
+                    seq_gap,
+                    send_queue_delay_ms: measurement.send_queue_delay_ms(),
+                };
+                if let Some(spike) = detector.check(
+                    measurement.sequence_id,
+                    measurement.frankfurt_receive_time,
+                    measurement.end_to_end_latency_ms(),
+                    context,
+                ) {
+                    if self.spikes.len() == MAX_SPIKES {
+                        self.spikes.pop_front();
+                    }
+                    self.spikes.push_back(spike);
+                    self.spike_count += 1;
+                }
+            }
+        }
+
+        // Track for per-second stats
+        self.events_this_second += 1;
+        self.e2e_latencies_this_second
+            .push(measurement.end_to_end_latency_ms());
+        if let Some(backbone) = measurement.backbone_latency_ms() {
+            self.backbone_latencies_this_second.push(backbone);
+        }
+
+        if self
+            .sample_rate
+            .is_none_or(|rate| rate.keeps(measurement.sequence_id))
+        {
+            self.measurements.push(measurement);
+            self.events_retained += 1;
+            self.last_kept = true;
+        }
+    }
+
+    fn is_stale(&self, measurement: &LatencyMeasurement) -> bool {
+        self.max_staleness.is_some_and(|max| {
+            !measurement.warmup && measurement.end_to_end_latency_ns > max.as_nanos() as i64
+        })
+    }
+
+    /// Close the current window early (e.g. at the end of a run).
+    /// Returns `None` if no events were recorded since the last window.
+    pub fn flush_second(&mut self) -> Option<SecondStats> {
+        if self.events_this_second == 0 {
+            return None;
+        }
+        Some(self.close_second())
+    }
+
+    /// Close the current per-second window and reset its counters
+    fn close_second(&mut self) -> SecondStats {
+        let summary = self.e2e_latencies_this_second.summary();
+        let avg_backbone_latency_ms = if !self.backbone_latencies_this_second.is_empty() {
+            Some(self.backbone_latencies_this_second.mean())
+        } else {
+            None
+        };
+
+        let stats = SecondStats {
+            elapsed_secs: self.start_time.elapsed().as_secs(),
+            events: self.events_this_second,
+            avg_latency_ms: summary.avg_ms,
+            min_latency_ms: summary.min_ms,
+            max_latency_ms: summary.max_ms,
+            p95_latency_ms: summary.p95_ms,
+            avg_backbone_latency_ms,
+            events_lost: self.lost_this_second,
+            quotes: self.quotes.close(),
+        };
+
+        if let Some(alerts) = &mut self.alerts {
+            alerts.second(&stats);
+        }
+
+        // Reset counters
+        self.events_last_second = self.events_this_second;
+        self.events_this_second = 0;
+        self.e2e_latencies_this_second.clear();
+        self.backbone_latencies_this_second.clear();
+        self.lost_this_second = 0;
+        self.last_second_report = Instant::now();
+
+        self.prune_window();
+
+        stats
+    }
+
+    /// Drop measurements that have fallen out of the sliding window
+    fn prune_window(&mut self) {
+        let Some(window) = self.window else {
+            return;
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as i64;
+        let cutoff = now - window.as_nanos() as i64;
+
+        // Measurements are appended in arrival order
+        let expired = self
+            .measurements
+            .partition_point(|m| m.frankfurt_receive_time < cutoff);
+        let expired_stale = self
+            .stale
+            .partition_point(|m| m.frankfurt_receive_time < cutoff);
+        if expired > 0 || expired_stale > 0 {
+            self.measurements.drain(..expired);
+            self.stale.drain(..expired_stale);
+            // Sampled runs keep only some measurements, so the lowest kept ID
+            // marks where the window starts; IDs above it stay as tracked
+            for (source, sequences) in &mut self.sequences {
+                let floor = self
+                    .measurements
+                    .iter()
+                    .chain(&self.stale)
+                    .filter(|m| m.source.as_deref() == source.as_deref())
+                    .map(|m| m.sequence_id)
+                    .min();
+                sequences.prune(floor);
+            }
+        }
+        self.gaps.retain(|gap| gap.receive_time >= cutoff);
+    }
+
+    /// Fold the current streaming interval into the whole-run sketch and
+    /// start a new one (e.g. after writing rolling results)
+    pub fn start_streaming_interval(&mut self) {
+        if let (Some(run), Some(interval)) = (&mut self.streaming_run, &mut self.streaming_interval)
+        {
+            run.merge(interval);
+            interval.clear();
+        }
+    }
+
+    /// Approximate percentiles since the start of the run and since the last
+    /// `start_streaming_interval`, when streaming stats are enabled
+    pub fn streaming_percentiles(&self) -> Option<StreamingPercentiles> {
+        let (Some(run), Some(interval)) = (&self.streaming_run, &self.streaming_interval) else {
+            return None;
+        };
+        let mut since_start = run.clone();
+        since_start.merge(interval);
+        Some(StreamingPercentiles {
+            relative_accuracy: run.relative_accuracy(),
+            since_start: since_start.summary(&self.percentiles),
+            since_last_report: interval.summary(&self.percentiles),
+        })
+    }
+
+    /// Spikes detected since the previous call, for real-time logging
+    pub fn new_spikes(&mut self) -> impl Iterator<Item = &Spike> {
+        let new = (self.spike_count - self.spikes_reported).min(self.spikes.len() as u64);
+        self.spikes_reported = self.spike_count;
+        self.spikes.range(self.spikes.len() - new as usize..)
+    }
+
+    /// Alarms fired or resolved since the previous call, for notification
+    pub fn new_alerts(&mut self) -> &[Alert] {
+        let Some(alerts) = &self.alerts else {
+            return &[];
+        };
+        let new = &alerts.history()[self.alerts_reported..];
+        self.alerts_reported = alerts.history().len();
+        new
+    }
+
+    /// Time since the collector was created
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    pub fn measurements(&self) -> &[LatencyMeasurement] {
+        &self.measurements
+    }
+
+    /// Measurements received within the last `window`, oldest first
+    pub fn recent(&self, window: Duration) -> &[LatencyMeasurement] {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as i64;
+        let cutoff = now - window.as_nanos() as i64;
+        let start = self
+            .measurements
+            .partition_point(|m| m.frankfurt_receive_time < cutoff);
+        &self.measurements[start..]
+    }
+
+    /// Most recently recorded measurement, unless it was stale or sampled out
+    pub fn last_measurement(&self) -> Option<&LatencyMeasurement> {
+        self.measurements.last().filter(|_| self.last_kept)
+    }
+
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.measurements.is_empty()
+    }
+
+    /// Number of sequence IDs missing between the lowest and highest
+    /// received, summed over sources
+    pub fn events_lost(&self) -> usize {
+        self.sequences
+            .iter()
+            .map(|(_, sequences)| sequences.events_lost())
+            .sum()
+    }
+
+    /// Calculate results over the measurements held so far without ending the run
+    pub fn snapshot(&self, setup_type: &str) -> ExperimentResults {
+        let mut results = ExperimentResults::with_percentiles(
+            setup_type.to_string(),
+            &self.measurements,
+            self.events_lost(),
+            &self.percentiles,
+        );
+        results.gaps = self.gaps.clone();
+        results.streaming = self.streaming_percentiles();
+        results.sessions = self.session_stats();
+        results.quantization = self.quantized_latency();
+        results.stale = self.stale_stats();
+        results.sampling = self.sampling_stats();
+        results.sources = self.source_stats();
+        self.add_ordering(&mut results);
+        results
+    }
+
+    fn add_ordering(&self, results: &mut ExperimentResults) {
+        for (_, sequences) in &self.sequences {
+            results.duplicates += sequences.duplicates;
+            results.reordered += sequences.reordered;
+            results.max_reorder_distance = results
+                .max_reorder_distance
+                .max(sequences.max_reorder_distance);
+        }
+    }
+
+    /// Per-source statistics, once any measurement was tagged with its source
+    fn source_stats(&self) -> Option<Vec<SourceStats>> {
+        self.sequences
+            .iter()
+            .any(|(source, _)| source.is_some())
+            .then(|| source_stats(&self.measurements, &self.sequences, &self.percentiles))
+    }
+
+    fn session_stats(&self) -> Option<Vec<SessionStats>> {
+        self.sessions
+            .as_ref()
+            .map(|split| session_stats(&self.measurements, split, &self.percentiles))
+            .filter(|sessions| !sessions.is_empty())
+    }
+
+    fn quantized_latency(&self) -> Option<QuantizedLatency> {
+        if !self.quantization {
+            return None;
+        }
+        quantized_latency(&self.measurements, &self.percentiles)
+    }
+
+    fn sampling_stats(&self) -> Option<SamplingStats> {
+        let rate = self.sample_rate?;
+        Some(SamplingStats {
+            sample_rate: rate.rate(),
+            events: self.events,
+            retained: self.events_retained,
+        })
+    }
+
+    fn stale_stats(&self) -> Option<StaleStats> {
+        let max_staleness = self.max_staleness?;
+        Some(stale_stats(
+            max_staleness.as_secs_f64() * 1_000.0,
+            &self.stale,
+            &self.measurements,
+            &self.percentiles,
+        ))
+    }
+
+    /// Sequence gaps detected so far, in detection order
+    pub fn gaps(&self) -> &[SequenceGap] {
+        &self.gaps
+    }
+
+    /// Calculate final results for this run
+    pub fn finish(self, setup_type: &str) -> Report {
+        let mut results = ExperimentResults::with_percentiles(
+            setup_type.to_string(),
+            &self.measurements,
+            self.events_lost(),
+            &self.percentiles,
+        );
+        self.add_ordering(&mut results);
+        results.streaming = self.streaming_percentiles();
+        results.sessions = self.session_stats();
+        results.quantization = self.quantized_latency();
+        results.stale = self.stale_stats();
+        results.sampling = self.sampling_stats();
+        results.sources = self.source_stats();
+        results.confidence_intervals = self
+            .bootstrap_iterations
+            .and_then(|iterations| bootstrap_intervals(&self.measurements, iterations));
+        results.gaps = self.gaps;
+        if self.spike_detector.is_some() {
+            results.spikes = Some(self.spikes.into());
+            results.spike_count = Some(self.spike_count);
+        }
+        results.alerts = self.alerts.map(|alerts| alerts.history().to_vec());
+
+        Report::new(results, self.measurements)
+    }
+}
+
+/// Sequence tracking for `source`, started the first time it is seen. A run
+/// has a handful of sources at most.
+fn sequences_of<'a>(
+    sequences: &'a mut Vec<(Option<Arc<str>>, Sequences)>,
+    source: Option<&str>,
+) -> &'a mut Sequences {
+    let index = match sequences
+        .iter()
+        .position(|(known, _)| known.as_deref() == source)
+    {
+        Some(index) => index,
+        None => {
+            sequences.push((source.map(Arc::from), Sequences::default()));
+            sequences.len() - 1
+        }
+    };
+    &mut sequences[index].1
+}
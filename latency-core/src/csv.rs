@@ -0,0 +1,208 @@
+// Streaming CSV output for raw measurements
+
+use crate::delivery::DeliveryClass;
+use crate::market::Market;
+use crate::measurement::{LatencyMeasurement, StreamNames};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// First lines of every raw measurements CSV file: a `#` comment describing
+/// the columns, then the column names
+pub const CSV_HEADER: &str = "\
+# Raw latency measurements. *_time columns are epoch nanoseconds except binance_time and transaction_time (exchange unit: ms or µs); *_ms columns are milliseconds and *_ns nanoseconds. \
+latency_ms = frankfurt_time - binance_time; backbone_latency_ms = frankfurt_time - tokyo_time (Tokyo → Frankfurt); binance_tokyo_ms = tokyo_time - binance_time; \
+tokyo_processing_ns = forwarder frame received → event handed to the socket. Tokyo columns are empty in baseline mode.
+sequence_id,binance_time,tokyo_time,frankfurt_time,latency_ms,backbone_latency_ms,kernel_time,warmup,transaction_time,endpoint,market,delivery_class,stream,frame_bytes,packet_bytes,send_queue_delay_ms,source,binance_tokyo_ms,tokyo_processing_ns\n";
+
+/// Writes measurements as CSV rows, one at a time
+#[derive(Debug)]
+pub struct CsvWriter<W: Write = BufWriter<File>> {
+    writer: W,
+}
+
+impl CsvWriter {
+    /// Create the file and write the CSV header
+    pub fn create(filepath: &str) -> Result<Self, std::io::Error> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writer.write_all(CSV_HEADER.as_bytes())?;
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Write rows to `writer`, which is responsible for the header (e.g. a
+    /// rotating file that starts every file with `CSV_HEADER`)
+    pub fn from_writer(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// The underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Append one measurement. Fields are formatted straight into the
+    /// writer, so rows are written without allocating.
+    pub fn write(&mut self, m: &LatencyMeasurement) -> Result<(), std::io::Error> {
+        writeln!(
+            self.writer,
+            "{},{},{},{},{:.3},{:.3},{},{},{},{},{},{},{},{},{},{:.3},{},{:.3},{}",
+            m.sequence_id,
+            m.binance_event_time,
+            Field(m.tokyo_receive_time),
+            m.frankfurt_receive_time,
+            m.end_to_end_latency_ms(),
+            Field(m.backbone_latency_ms()),
+            Field(m.kernel_receive_time),
+            m.warmup as u8,
+            Field(m.transaction_time),
+            Field(m.endpoint),
+            m.market.map_or("", Market::as_str),
+            m.delivery_class,
+            m.stream.as_deref().unwrap_or_default(),
+            Field(m.frame_bytes),
+            Field(m.packet_bytes),
+            Field(m.send_queue_delay_ms()),
+            m.source.as_deref().unwrap_or_default(),
+            Field(m.tokyo_latency_ms()),
+            Field(m.forwarding_overhead_ns)
+        )
+    }
+
+    /// Flush buffered rows to disk
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+}
+
+/// An optional CSV field, empty when absent; the format spec applies to the value
+struct Field<T>(Option<T>);
+
+impl<T: fmt::Display> fmt::Display for Field<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Read measurements back from a raw measurements CSV file. Columns are found
+/// by name, so files from before a column was added still load; `#` comment
+/// lines are skipped. Derived columns are recomputed from the timestamps.
+pub(crate) fn read_csv(filepath: &str) -> Result<Vec<LatencyMeasurement>, std::io::Error> {
+    let invalid = |line: usize, what: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", filepath, line, what),
+        )
+    };
+
+    let mut lines = BufReader::new(File::open(filepath)?)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.starts_with('#')));
+    let (_, header) = lines.next().ok_or_else(|| invalid(1, "empty file"))?;
+    let header = header?;
+    let columns: Vec<&str> = header.trim().split(',').collect();
+    let column = |names: &[&str]| columns.iter().position(|c| names.contains(c));
+    let (Some(sequence_id), Some(frankfurt_time), Some(latency)) = (
+        column(&["sequence_id"]),
+        column(&["frankfurt_time"]),
+        column(&["latency_ms", "end_to_end_latency_ms"]),
+    ) else {
+        return Err(invalid(1, "not a raw measurements CSV"));
+    };
+    let binance_time = column(&["binance_time"]);
+    let tokyo_time = column(&["tokyo_time"]);
+    let backbone = column(&["backbone_latency_ms"]);
+    let kernel_time = column(&["kernel_time"]);
+    let warmup = column(&["warmup"]);
+    let transaction_time = column(&["transaction_time"]);
+    let endpoint = column(&["endpoint"]);
+    let market = column(&["market"]);
+    let delivery_class = column(&["delivery_class"]);
+    let stream = column(&["stream"]);
+    let frame_bytes = column(&["frame_bytes"]);
+    let packet_bytes = column(&["packet_bytes"]);
+    let send_queue_delay = column(&["send_queue_delay_ms"]);
+    let source = column(&["source"]);
+    let forwarding_overhead = column(&["tokyo_processing_ns"]);
+    let mut streams = StreamNames::default();
+    let mut sources = StreamNames::default();
+
+    let mut measurements = Vec::new();
+    for (i, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let line_number = i + 1;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| fields.get(index))
+                .filter(|value| !value.is_empty())
+        };
+        let parse = |index: Option<usize>| -> Result<Option<i64>, std::io::Error> {
+            field(index)
+                .map(|value| value.parse().map_err(|_| invalid(line_number, value)))
+                .transpose()
+        };
+        let parse_ms = |index: Option<usize>| -> Result<Option<i64>, std::io::Error> {
+            field(index)
+                .map(|value| {
+                    value
+                        .parse::<f64>()
+                        .map(|ms| (ms * 1_000_000.0).round() as i64)
+                        .map_err(|_| invalid(line_number, value))
+                })
+                .transpose()
+        };
+
+        let frankfurt_receive_time = parse(Some(frankfurt_time))?
+            .ok_or_else(|| invalid(line_number, "missing frankfurt_time"))?;
+        let tokyo_receive_time = parse(tokyo_time)?;
+        measurements.push(LatencyMeasurement {
+            sequence_id: parse(Some(sequence_id))?
+                .ok_or_else(|| invalid(line_number, "missing sequence_id"))?
+                as u64,
+            binance_event_time: parse(binance_time)?.unwrap_or_default(),
+            tokyo_receive_time,
+            frankfurt_receive_time,
+            end_to_end_latency_ns: parse_ms(Some(latency))?
+                .ok_or_else(|| invalid(line_number, "missing latency"))?,
+            backbone_latency_ns: match tokyo_receive_time {
+                Some(tokyo) => Some(frankfurt_receive_time - tokyo),
+                None => parse_ms(backbone)?,
+            },
+            kernel_receive_time: parse(kernel_time)?,
+            warmup: matches!(field(warmup), Some(&"1" | &"true")),
+            transaction_time: parse(transaction_time)?,
+            endpoint: parse(endpoint)?.map(|e| e as u16),
+            market: match field(market) {
+                Some(&"spot") => Some(Market::Spot),
+                Some(&"futures") => Some(Market::Futures),
+                _ => None,
+            },
+            delivery_class: match field(delivery_class) {
+                Some(&"buffered") => DeliveryClass::Buffered,
+                Some(&"retransmitted") => DeliveryClass::Retransmitted,
+                _ => DeliveryClass::Live,
+            },
+            stream: field(stream).map(|name| streams.intern(name)),
+            symbol: None, // Split into a file per symbol rather than a column
+            frame_bytes: parse(frame_bytes)?.map(|bytes| bytes as u32),
+            packet_bytes: parse(packet_bytes)?.map(|bytes| bytes as u32),
+            send_queue_delay_ns: parse_ms(send_queue_delay)?,
+            source: field(source).map(|name| sources.intern(name)),
+            forwarding_overhead_ns: parse(forwarding_overhead)?,
+        });
+    }
+    Ok(measurements)
+}
@@ -0,0 +1,83 @@
+// Live vs replayed events: keeping stale deliveries out of the live latency
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How an event reached the receiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryClass {
+    Live,
+    Buffered,      // Held in the forwarder's retry buffer during a receiver outage
+    Retransmitted, // Write failed on a broken connection, sent again after reconnecting
+}
+
+impl DeliveryClass {
+    pub const ALL: [DeliveryClass; 3] = [
+        DeliveryClass::Live,
+        DeliveryClass::Buffered,
+        DeliveryClass::Retransmitted,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryClass::Live => "live",
+            DeliveryClass::Buffered => "buffered",
+            DeliveryClass::Retransmitted => "retransmitted",
+        }
+    }
+}
+
+impl fmt::Display for DeliveryClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Latency of the measurements of one delivery class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryClassStats {
+    pub class: DeliveryClass,
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>,
+    pub backbone_avg_latency_ms: Option<f64>,
+}
+
+/// Summarize end-to-end latency per delivery class at `percentiles`. Returns
+/// one entry per class with samples, live first; warm-up is left out.
+pub fn delivery_class_stats(
+    measurements: &[LatencyMeasurement],
+    percentiles: &[f64],
+) -> Vec<DeliveryClassStats> {
+    DeliveryClass::ALL
+        .iter()
+        .filter_map(|&class| {
+            let samples = || {
+                measurements
+                    .iter()
+                    .filter(move |m| !m.warmup && m.delivery_class == class)
+            };
+            let latency: StatsAggregator = samples().map(|m| m.end_to_end_latency_ms()).collect();
+            if latency.is_empty() {
+                return None;
+            }
+            let backbone: StatsAggregator =
+                samples().filter_map(|m| m.backbone_latency_ms()).collect();
+
+            let summary = latency.summary();
+            Some(DeliveryClassStats {
+                class,
+                sample_count: summary.count,
+                avg_latency_ms: summary.avg_ms,
+                max_latency_ms: summary.max_ms,
+                percentiles: latency.percentiles(percentiles),
+                backbone_avg_latency_ms: (!backbone.is_empty()).then(|| backbone.mean()),
+            })
+        })
+        .collect()
+}
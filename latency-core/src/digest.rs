@@ -0,0 +1,239 @@
+// Approximate percentiles with bounded memory (DDSketch)
+//
+// Continuous mode only keeps the last `--window-secs` of measurements, so
+// percentiles over the whole run cannot be computed from them. A DDSketch
+// counts samples in logarithmic buckets instead: every percentile it reports
+// is within a fixed relative error of the true value, memory is bounded by the
+// bucket count rather than the sample count, and two sketches with the same
+// accuracy merge exactly (the counts are added).
+
+use crate::stats::percentile_label;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Relative accuracy used when none is configured (1%)
+pub const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Buckets kept per sketch. At 1% accuracy this covers about eight orders of
+/// magnitude; beyond that the lowest buckets are merged, losing accuracy only
+/// at the fast end of the distribution.
+const MAX_BUCKETS: usize = 2048;
+
+/// Magnitudes below this count as zero (milliseconds, i.e. 1 ps)
+const MIN_MAGNITUDE: f64 = 1e-9;
+
+/// Approximate latency distribution (milliseconds) in a DDSketch
+#[derive(Debug, Clone)]
+pub struct StreamingStats {
+    relative_accuracy: f64,
+    ln_gamma: f64,
+    positive: BTreeMap<i32, u64>, // Bucket index → count
+    negative: BTreeMap<i32, u64>, // Same, by magnitude (negative latencies from clock skew)
+    zero: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+/// Statistics read from a `StreamingStats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamingSummary {
+    pub count: u64,
+    pub avg_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub percentiles: BTreeMap<String, f64>, // Keyed "p50", "p99.9", ...
+}
+
+/// Results section for approximate percentiles over a continuous run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamingPercentiles {
+    pub relative_accuracy: f64, // Reported percentiles are within this fraction of the true value
+    pub since_start: StreamingSummary, // Every non-warm-up event of the run
+    pub since_last_report: StreamingSummary, // Events since the previous rolling results
+}
+
+impl Default for StreamingStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELATIVE_ACCURACY)
+    }
+}
+
+impl StreamingStats {
+    /// An empty sketch whose percentiles are within `relative_accuracy` (a
+    /// fraction, e.g. 0.01) of the true values
+    pub fn new(relative_accuracy: f64) -> Self {
+        assert!(
+            relative_accuracy > 0.0 && relative_accuracy < 1.0,
+            "relative accuracy must be between 0 and 1"
+        );
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        Self {
+            relative_accuracy,
+            ln_gamma: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zero: 0,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn relative_accuracy(&self) -> f64 {
+        self.relative_accuracy
+    }
+
+    /// Add a sample (milliseconds)
+    pub fn push(&mut self, latency_ms: f64) {
+        if latency_ms.is_nan() {
+            return;
+        }
+        if latency_ms.abs() < MIN_MAGNITUDE {
+            self.zero += 1;
+        } else {
+            let index = self.index(latency_ms.abs());
+            let store = if latency_ms > 0.0 {
+                &mut self.positive
+            } else {
+                &mut self.negative
+            };
+            *store.entry(index).or_default() += 1;
+            self.collapse();
+        }
+        self.count += 1;
+        self.sum += latency_ms;
+        self.min = self.min.min(latency_ms);
+        self.max = self.max.max(latency_ms);
+    }
+
+    /// Add the samples of `other`. Both sketches must have the same accuracy.
+    pub fn merge(&mut self, other: &StreamingStats) {
+        assert_eq!(
+            self.relative_accuracy, other.relative_accuracy,
+            "only sketches with the same accuracy can be merged"
+        );
+        for (index, count) in &other.positive {
+            *self.positive.entry(*index).or_default() += count;
+        }
+        for (index, count) in &other.negative {
+            *self.negative.entry(*index).or_default() += count;
+        }
+        self.collapse();
+        self.zero += other.zero;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Drop all samples, keeping the accuracy
+    pub fn clear(&mut self) {
+        *self = Self::new(self.relative_accuracy);
+    }
+
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Buckets currently held, a measure of the sketch's memory use
+    pub fn buckets(&self) -> usize {
+        self.positive.len() + self.negative.len()
+    }
+
+    /// Mean of the samples (exact), 0.0 when empty
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum / self.count as f64
+    }
+
+    /// Approximate value at `quantile` (a fraction, 0.99 = p99), 0.0 when empty.
+    /// The minimum and maximum are exact.
+    pub fn quantile(&self, quantile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        if quantile <= 0.0 {
+            return self.min;
+        }
+        if quantile >= 1.0 {
+            return self.max;
+        }
+
+        let rank = (quantile * (self.count - 1) as f64).round() as u64;
+        let mut seen = 0;
+        // Most negative first, i.e. the negative store by descending magnitude
+        for (index, count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return (-self.value(*index)).clamp(self.min, self.max);
+            }
+        }
+        seen += self.zero;
+        if seen > rank {
+            return 0.0;
+        }
+        for (index, count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return self.value(*index).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Summary at each of `levels` (in percent, e.g. 99.9)
+    pub fn summary(&self, levels: &[f64]) -> StreamingSummary {
+        if self.count == 0 {
+            return StreamingSummary::default();
+        }
+        StreamingSummary {
+            count: self.count,
+            avg_ms: self.mean(),
+            min_ms: self.min,
+            max_ms: self.max,
+            percentiles: levels
+                .iter()
+                .map(|&level| (percentile_label(level), self.quantile(level / 100.0)))
+                .collect(),
+        }
+    }
+
+    /// Bucket holding magnitude `value`: values in (γ^(i-1), γ^i] map to i
+    fn index(&self, value: f64) -> i32 {
+        (value.ln() / self.ln_gamma).ceil() as i32
+    }
+
+    /// Representative value of bucket `index`, within the relative accuracy of
+    /// every value in the bucket
+    fn value(&self, index: i32) -> f64 {
+        let gamma = self.ln_gamma.exp();
+        2.0 * (index as f64 * self.ln_gamma).exp() / (gamma + 1.0)
+    }
+
+    /// Merge the lowest buckets until the bucket limit holds again
+    fn collapse(&mut self) {
+        while self.buckets() > MAX_BUCKETS {
+            // Small magnitudes matter least: fold the smallest negative bucket
+            // into zero first, then the two lowest positive buckets together
+            if let Some((_, count)) = self.negative.pop_first() {
+                self.zero += count;
+                continue;
+            }
+            let Some((_, count)) = self.positive.pop_first() else {
+                break;
+            };
+            if let Some(mut next) = self.positive.first_entry() {
+                *next.get_mut() += count;
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Ranking of exchange endpoints measured side by side in one run
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+
+/// Latency of one exchange endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointStats {
+    pub rank: usize, // 1 = lowest median latency
+    pub endpoint: String,
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub median_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub jitter_stddev_ms: f64,
+}
+
+/// Summarize measurements tagged with an index into `endpoints` and rank the
+/// endpoints by median latency. Endpoints without samples are ranked last.
+pub fn rank_endpoints(
+    endpoints: &[String],
+    measurements: &[LatencyMeasurement],
+) -> Vec<EndpointStats> {
+    let mut latencies = vec![StatsAggregator::new(); endpoints.len()];
+    for m in measurements.iter().filter(|m| !m.warmup) {
+        if let Some(stats) = m.endpoint.and_then(|e| latencies.get_mut(e as usize)) {
+            stats.push(m.end_to_end_latency_ms());
+        }
+    }
+
+    let mut ranked: Vec<EndpointStats> = endpoints
+        .iter()
+        .zip(&latencies)
+        .map(|(endpoint, stats)| {
+            let summary = stats.summary();
+            EndpointStats {
+                rank: 0,
+                endpoint: endpoint.clone(),
+                sample_count: summary.count,
+                avg_latency_ms: summary.avg_ms,
+                median_latency_ms: summary.median_ms,
+                p99_latency_ms: summary.p99_ms,
+                jitter_stddev_ms: summary.stddev_ms,
+            }
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| {
+        (a.sample_count == 0)
+            .cmp(&(b.sample_count == 0))
+            .then(a.median_latency_ms.total_cmp(&b.median_latency_ms))
+    });
+    for (i, endpoint) in ranked.iter_mut().enumerate() {
+        endpoint.rank = i + 1;
+    }
+    ranked
+}
@@ -0,0 +1,54 @@
+// Where exchange connections landed
+//
+// An exchange hostname resolves to several addresses (DNS rotation, anycast),
+// so a reconnect can land on another machine, possibly in another region, and
+// shift the latency for the rest of the run. Every connection's peer address is
+// kept with the results, with its reverse DNS name and the AWS region that name
+// suggests.
+
+use serde::{Deserialize, Serialize};
+
+/// The address one exchange connection was made to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeAddress {
+    pub connected_at: i64, // Epoch nanos
+    pub ip: String,
+    pub port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverse_dns: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws_region: Option<String>, // Guessed from the reverse DNS name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<u16>, // Index into --endpoints when several are measured
+}
+
+/// The AWS region named in a reverse DNS name such as
+/// `ec2-52-68-1-2.ap-northeast-1.compute.amazonaws.com`. EC2 names in
+/// us-east-1 carry no region (`compute-1.amazonaws.com`).
+pub fn guess_aws_region(hostname: &str) -> Option<String> {
+    let name = hostname.trim_end_matches('.').to_ascii_lowercase();
+    let rest = name.strip_suffix(".amazonaws.com")?;
+    if rest.ends_with(".compute-1") {
+        return Some("us-east-1".to_string());
+    }
+    rest.split('.')
+        .rev()
+        .find(|label| is_region(label))
+        .map(str::to_string)
+}
+
+/// A label shaped like an AWS region: `ap-northeast-1`, `us-gov-west-1`
+fn is_region(label: &str) -> bool {
+    let parts: Vec<&str> = label.split('-').collect();
+    let [area, .., number] = parts.as_slice() else {
+        return false;
+    };
+    parts.len() >= 3
+        && area.len() == 2
+        && area.bytes().all(|b| b.is_ascii_lowercase())
+        && parts[1..parts.len() - 1]
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_lowercase()))
+        && !number.is_empty()
+        && number.bytes().all(|b| b.is_ascii_digit())
+}
@@ -0,0 +1,31 @@
+// Loss accounting for events split over several UDP datagrams
+//
+// A datagram larger than the path MTU is fragmented by IP, and losing any
+// fragment silently loses the whole datagram. The forwarder can instead split
+// large events itself; the receiver reassembles them and counts what went
+// missing, which lands here.
+
+use serde::{Deserialize, Serialize};
+
+/// Fragmented events over a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FragmentStats {
+    pub frames: u64,              // Events that arrived as fragments (at least one)
+    pub reassembled: u64,         // Events with every fragment received
+    pub incomplete: u64,          // Events dropped with fragments still missing
+    pub fragments: u64,           // Fragments received
+    pub lost_fragments: u64,      // Fragments missing from incomplete events
+    pub duplicate_fragments: u64, // Fragments received more than once
+}
+
+impl FragmentStats {
+    /// Add the counts of another reassembler, e.g. one per forwarder
+    pub fn add(&mut self, other: &FragmentStats) {
+        self.frames += other.frames;
+        self.reassembled += other.reassembled;
+        self.incomplete += other.incomplete;
+        self.fragments += other.fragments;
+        self.lost_fragments += other.lost_fragments;
+        self.duplicate_fragments += other.duplicate_fragments;
+    }
+}
@@ -0,0 +1,47 @@
+// Bytes the gRPC transport adds around each forwarded event
+//
+// The tcp transport sends an event's JSON followed by a newline. Over grpc the
+// same JSON travels inside a protobuf message behind gRPC's length prefix, in
+// HTTP/2 DATA frames. Counting both parts of every message shows what the
+// framing costs next to a tcp run with the same events.
+
+use serde::{Deserialize, Serialize};
+
+/// Payload and framing bytes of the messages received over gRPC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FramingStats {
+    pub messages: u64,          // Messages received, handshakes included
+    pub payload_bytes: u64,     // JSON carried by those messages
+    pub framing_bytes: u64,     // Protobuf fields, gRPC prefixes and HTTP/2 frame headers
+    pub min_framing_bytes: u64, // Smallest framing of one message
+    pub max_framing_bytes: u64, // Largest; events over one HTTP/2 frame need several headers
+}
+
+impl FramingStats {
+    /// Count one message
+    pub fn record(&mut self, payload_bytes: u64, framing_bytes: u64) {
+        if self.messages == 0 || framing_bytes < self.min_framing_bytes {
+            self.min_framing_bytes = framing_bytes;
+        }
+        self.max_framing_bytes = self.max_framing_bytes.max(framing_bytes);
+        self.messages += 1;
+        self.payload_bytes += payload_bytes;
+        self.framing_bytes += framing_bytes;
+    }
+
+    /// Average framing bytes per message
+    pub fn avg_framing_bytes(&self) -> f64 {
+        if self.messages == 0 {
+            return 0.0;
+        }
+        self.framing_bytes as f64 / self.messages as f64
+    }
+
+    /// Framing as a percentage of the payload it carries
+    pub fn overhead_pct(&self) -> f64 {
+        if self.payload_bytes == 0 {
+            return 0.0;
+        }
+        self.framing_bytes as f64 / self.payload_bytes as f64 * 100.0
+    }
+}
@@ -0,0 +1,39 @@
+// Sequence gaps (lost events) with when they happened
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A run of missing sequence IDs, detected when a later event arrived
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceGap {
+    pub first_missing: u64, // First missing sequence ID
+    pub size: u64,          // Number of consecutive missing IDs
+    pub receive_time: i64,  // Receive time of the event that revealed the gap (epoch nanos)
+    pub elapsed_secs: f64,  // Seconds since the start of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>, // Forwarder whose IDs are missing, with several forwarders
+}
+
+/// Events lost in one minute of the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinuteLosses {
+    pub minute: u64, // Minutes since the start of the run
+    pub events_lost: u64,
+    pub gaps: usize,
+}
+
+/// Bucket gaps by the minute they were detected in; minutes without losses are omitted
+pub fn losses_per_minute(gaps: &[SequenceGap]) -> Vec<MinuteLosses> {
+    let mut minutes: BTreeMap<u64, MinuteLosses> = BTreeMap::new();
+    for gap in gaps {
+        let minute = (gap.elapsed_secs / 60.0) as u64;
+        let entry = minutes.entry(minute).or_insert(MinuteLosses {
+            minute,
+            events_lost: 0,
+            gaps: 0,
+        });
+        entry.events_lost += gap.size;
+        entry.gaps += 1;
+    }
+    minutes.into_values().collect()
+}
@@ -0,0 +1,36 @@
+// Exchange connection handovers
+//
+// Binance ends every stream connection after 24 hours. Long runs replace the
+// connection shortly before that with one opened in advance, or reconnect when
+// the exchange resets it first. Each handover is kept with the results so a
+// long run shows where the connection changed and whether events went missing.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why the exchange connection was replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoverReason {
+    Rollover,      // Replaced before the exchange's connection limit by a standby connection
+    ExchangeReset, // Closed by the exchange at its connection limit, then reconnected
+}
+
+impl fmt::Display for HandoverReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HandoverReason::Rollover => "rollover",
+            HandoverReason::ExchangeReset => "exchange reset",
+        })
+    }
+}
+
+/// One replacement of the exchange connection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionHandover {
+    pub time: i64, // Epoch nanos of the first frame on the new connection
+    pub reason: HandoverReason,
+    pub connection_age_secs: f64, // Of the connection replaced
+    pub gap_ms: f64,              // Last frame on the old connection → first on the new
+    pub duplicates_skipped: u64,  // Frames that arrived on both connections, forwarded once
+}
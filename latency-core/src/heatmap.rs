@@ -0,0 +1,117 @@
+// Latency heatmap: how the distribution moves over the course of a run
+//
+// Measurements are counted into (time interval × latency bucket) cells. The
+// grid is dense, with empty intervals and buckets kept as zeros, so it can be
+// handed to a plotting tool as is.
+
+use crate::measurement::LatencyMeasurement;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Default length of a heatmap row in seconds
+pub const HEATMAP_INTERVAL_SECS: u64 = 10;
+
+/// Lower bounds of the latency buckets (ms); the last bucket is open-ended
+pub const HEATMAP_BUCKET_BOUNDS_MS: &[f64] = &[
+    0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 150.0, 200.0, 250.0, 300.0, 400.0, 500.0, 1000.0,
+    2000.0, 5000.0,
+];
+
+/// End-to-end latency counts per time interval and latency bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub interval_secs: u64,
+    pub bucket_bounds_ms: Vec<f64>, // Lower bound of each bucket; the last is open-ended
+    pub interval_starts: Vec<i64>,  // Epoch seconds, one per row
+    pub counts: Vec<Vec<u64>>,      // counts[interval][bucket]
+}
+
+impl Heatmap {
+    /// Bin measurements by receive time into intervals aligned to multiples of
+    /// `interval_secs` since the epoch. Warm-up measurements are left out, and
+    /// negative latencies (receiver clock behind the exchange) count in the
+    /// first bucket.
+    ///
+    /// # Panics
+    /// If `interval_secs` is zero.
+    pub fn from_measurements(measurements: &[LatencyMeasurement], interval_secs: u64) -> Self {
+        assert!(interval_secs > 0, "heatmap interval must be positive");
+        let interval_ns = interval_secs as i64 * 1_000_000_000;
+        let measured = measurements.iter().filter(|m| !m.warmup);
+        let rows = |m: &LatencyMeasurement| m.frankfurt_receive_time.div_euclid(interval_ns);
+
+        let rows_seen = measured.clone().map(rows);
+        let (Some(first), Some(last)) = (rows_seen.clone().min(), rows_seen.max()) else {
+            return Self {
+                interval_secs,
+                bucket_bounds_ms: HEATMAP_BUCKET_BOUNDS_MS.to_vec(),
+                interval_starts: Vec::new(),
+                counts: Vec::new(),
+            };
+        };
+
+        let mut counts =
+            vec![vec![0u64; HEATMAP_BUCKET_BOUNDS_MS.len()]; (last - first + 1) as usize];
+        for m in measured {
+            let latency = m.end_to_end_latency_ms();
+            let bucket = HEATMAP_BUCKET_BOUNDS_MS
+                .partition_point(|&bound| bound <= latency)
+                .saturating_sub(1);
+            counts[(rows(m) - first) as usize][bucket] += 1;
+        }
+
+        Self {
+            interval_secs,
+            bucket_bounds_ms: HEATMAP_BUCKET_BOUNDS_MS.to_vec(),
+            interval_starts: (first..=last)
+                .map(|row| row * interval_secs as i64)
+                .collect(),
+            counts,
+        }
+    }
+
+    /// Write as JSON when the path ends in `.json`, otherwise as CSV
+    pub fn write(&self, filepath: &str) -> Result<(), std::io::Error> {
+        if filepath.ends_with(".json") {
+            self.write_json(filepath)
+        } else {
+            self.write_csv(filepath)
+        }
+    }
+
+    /// The grid as JSON, ready for e.g. a Plotly heatmap (`z` = `counts`)
+    pub fn write_json(&self, filepath: &str) -> Result<(), std::io::Error> {
+        std::fs::write(filepath, serde_json::to_string_pretty(self)?)
+    }
+
+    /// One row per cell in long format:
+    /// `interval_start,elapsed_secs,bucket_min_ms,bucket_max_ms,count`. The
+    /// top bucket has an empty `bucket_max_ms`.
+    pub fn write_csv(&self, filepath: &str) -> Result<(), std::io::Error> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(filepath)?);
+        writeln!(
+            file,
+            "interval_start,elapsed_secs,bucket_min_ms,bucket_max_ms,count"
+        )?;
+        let origin = self.interval_starts.first().copied().unwrap_or(0);
+        for (start, row) in self.interval_starts.iter().zip(&self.counts) {
+            for (i, count) in row.iter().enumerate() {
+                let max = self
+                    .bucket_bounds_ms
+                    .get(i + 1)
+                    .map(|max| max.to_string())
+                    .unwrap_or_default();
+                writeln!(
+                    file,
+                    "{},{},{},{},{}",
+                    start,
+                    start - origin,
+                    self.bucket_bounds_ms[i],
+                    max,
+                    count
+                )?;
+            }
+        }
+        file.flush()
+    }
+}
@@ -0,0 +1,458 @@
+// Standalone HTML report with inline SVG charts
+//
+// Everything is in one file (no scripts, no external assets) so it can be
+// mailed around or attached to a ticket. Charts that need the raw
+// measurements are left out when only results.json is available.
+
+use crate::gaps::losses_per_minute;
+use crate::measurement::LatencyMeasurement;
+use crate::report::label_value;
+use crate::results::ExperimentResults;
+use crate::stats::percentile;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const WIDTH: f64 = 760.0;
+const HEIGHT: f64 = 280.0;
+const MARGIN_LEFT: f64 = 64.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 16.0;
+const MARGIN_BOTTOM: f64 = 44.0;
+const HISTOGRAM_BINS: usize = 60;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:820px;margin:2em auto;color:#222}\
+h1{font-size:1.5em}h2{font-size:1.15em;margin-top:2em}\
+table{border-collapse:collapse}td,th{padding:3px 12px;text-align:right;border-bottom:1px solid #ddd}\
+td:first-child,th:first-child{text-align:left}.note{color:#777;font-size:.9em}\
+svg text{font-size:11px;fill:#444}";
+
+/// Render `results` (and, if given, the raw measurements) as an HTML page
+pub fn render_html(results: &ExperimentResults, measurements: &[LatencyMeasurement]) -> String {
+    let mut html = String::new();
+    let title = format!(
+        "Latency report: {}{}",
+        results.setup_type,
+        results
+            .region
+            .as_deref()
+            .map(|region| format!(" ({})", region))
+            .unwrap_or_default()
+    );
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head><body>\n<h1>{}</h1>\n",
+        escape(&title),
+        STYLE,
+        escape(&title)
+    );
+
+    run_details(&mut html, results);
+    summary_table(&mut html, results);
+    percentile_table(&mut html, results);
+
+    let samples: Vec<&LatencyMeasurement> = measurements.iter().filter(|m| !m.warmup).collect();
+    if samples.is_empty() {
+        html.push_str("<p class=\"note\">Pass the raw measurements CSV for the latency distribution and time-series charts.</p>\n");
+    } else {
+        distribution_chart(&mut html, &samples);
+        time_series_chart(&mut html, &samples);
+    }
+    loss_timeline(&mut html, results);
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+fn run_details(html: &mut String, results: &ExperimentResults) {
+    let mut rows: Vec<(&str, String)> = Vec::new();
+    if let Some(exchange) = &results.exchange {
+        rows.push(("Exchange", exchange.clone()));
+    }
+    if let Some(metadata) = &results.metadata {
+        rows.push(("Started", metadata.started_at.clone()));
+        if let Some(ended_at) = &metadata.ended_at {
+            rows.push(("Ended", ended_at.clone()));
+        }
+        if let Some(hostname) = &metadata.hostname {
+            rows.push(("Host", hostname.clone()));
+        }
+        if let Some(platform) = &metadata.platform {
+            rows.push(("Platform", platform.clone()));
+        }
+        if let Some(instance_type) = &metadata.instance_type {
+            rows.push(("Instance", instance_type.clone()));
+        }
+        if let Some(clock) = &metadata.clock_sync {
+            rows.push((
+                "Clock offset",
+                format!(
+                    "{:.3} ms ({}, {})",
+                    clock.system_time_offset_ms, clock.reference, clock.leap_status
+                ),
+            ));
+        }
+        rows.push(("Command", metadata.command_line.join(" ")));
+    }
+    if rows.is_empty() {
+        return;
+    }
+    html.push_str("<table>\n");
+    for (label, value) in rows {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td style=\"text-align:left\">{}</td></tr>",
+            label,
+            escape(&value)
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn summary_table(html: &mut String, results: &ExperimentResults) {
+    html.push_str("<h2>Summary</h2>\n<table>\n");
+    let mut rows = vec![
+        ("Samples", results.sample_count.to_string()),
+        ("Events lost", results.events_lost.to_string()),
+        ("Duplicates", results.duplicates.to_string()),
+        ("Reordered", results.reordered.to_string()),
+        ("Reconnects", results.reconnects.to_string()),
+        ("Outage", format!("{:.0} ms", results.outage_ms)),
+        ("Average", ms(results.avg_latency_ms)),
+        ("Median", ms(results.median_latency_ms)),
+        ("P95", ms(results.p95_latency_ms)),
+        ("P99", ms(results.p99_latency_ms)),
+        ("Min", ms(results.min_latency_ms)),
+        ("Max", ms(results.max_latency_ms)),
+        ("Jitter (stddev)", ms(results.jitter_stddev_ms)),
+    ];
+    if let Some(backbone) = results.backbone_avg_latency_ms {
+        rows.push(("Backbone average", ms(backbone)));
+    }
+    for (label, value) in rows {
+        let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", label, value);
+    }
+    html.push_str("</table>\n");
+}
+
+fn percentile_table(html: &mut String, results: &ExperimentResults) {
+    if results.percentiles.is_empty() {
+        return;
+    }
+    let backbone = results.backbone_percentiles_us.as_ref();
+    html.push_str("<h2>Percentiles</h2>\n<table>\n<tr><th></th><th>End-to-end</th>");
+    if backbone.is_some() {
+        html.push_str("<th>Backbone</th>");
+    }
+    html.push_str("</tr>\n");
+    // Labels sort as strings ("p10" < "p5"), so order them numerically
+    let mut percentiles: Vec<(&String, &f64)> = results.percentiles.iter().collect();
+    percentiles.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+    for (label, &value) in percentiles {
+        let _ = write!(html, "<tr><td>{}</td><td>{}</td>", label, ms(value));
+        if let Some(us) = backbone.and_then(|backbone| backbone.get(label)) {
+            let _ = write!(html, "<td>{:.1} µs</td>", us);
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+/// Histogram of end-to-end latency up to p99.9, so a few outliers do not
+/// squeeze the rest of the distribution into one bar
+fn distribution_chart(html: &mut String, samples: &[&LatencyMeasurement]) {
+    let mut latencies: Vec<f64> = samples.iter().map(|m| m.end_to_end_latency_ms()).collect();
+    latencies.sort_by(f64::total_cmp);
+    let low = latencies[0];
+    let high = percentile(&latencies, 0.999).max(low + 0.001);
+    let width = (high - low) / HISTOGRAM_BINS as f64;
+
+    let mut counts = vec![0u64; HISTOGRAM_BINS];
+    let mut above = 0;
+    for &latency in &latencies {
+        if latency > high {
+            above += 1;
+            continue;
+        }
+        counts[(((latency - low) / width) as usize).min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let bars: Vec<(f64, f64, f64)> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let start = low + i as f64 * width;
+            (start, start + width, count as f64)
+        })
+        .collect();
+
+    html.push_str("<h2>Latency distribution</h2>\n");
+    html.push_str(&bar_chart(
+        &bars,
+        "End-to-end latency (ms)",
+        "Events",
+        "#4a7bd0",
+    ));
+    if above > 0 {
+        let _ = writeln!(
+            html,
+            "<p class=\"note\">{} events above p99.9 ({:.2} ms, max {:.2} ms) are not shown.</p>",
+            above,
+            high,
+            latencies[latencies.len() - 1]
+        );
+    }
+}
+
+/// Average and maximum latency per second of the run
+fn time_series_chart(html: &mut String, samples: &[&LatencyMeasurement]) {
+    let start = samples
+        .iter()
+        .map(|m| m.frankfurt_receive_time)
+        .min()
+        .unwrap_or_default();
+    let mut seconds: BTreeMap<i64, (f64, f64, u64)> = BTreeMap::new();
+    for m in samples {
+        let latency = m.end_to_end_latency_ms();
+        let entry = seconds
+            .entry((m.frankfurt_receive_time - start) / 1_000_000_000)
+            .or_insert((0.0, f64::MIN, 0));
+        entry.0 += latency;
+        entry.1 = entry.1.max(latency);
+        entry.2 += 1;
+    }
+    let average: Vec<(f64, f64)> = seconds
+        .iter()
+        .map(|(&second, &(sum, _, count))| (second as f64, sum / count as f64))
+        .collect();
+    let max: Vec<(f64, f64)> = seconds
+        .iter()
+        .map(|(&second, &(_, max, _))| (second as f64, max))
+        .collect();
+
+    html.push_str("<h2>Latency over time</h2>\n");
+    html.push_str(&line_chart(
+        &[
+            Series {
+                legend: "max",
+                color: "#e39a9a",
+                points: &max,
+            },
+            Series {
+                legend: "average",
+                color: "#4a7bd0",
+                points: &average,
+            },
+        ],
+        "Seconds since start",
+        "Latency (ms)",
+    ));
+}
+
+fn loss_timeline(html: &mut String, results: &ExperimentResults) {
+    html.push_str("<h2>Loss timeline</h2>\n");
+    let minutes = losses_per_minute(&results.gaps);
+    if minutes.is_empty() {
+        html.push_str("<p>No sequence gaps.</p>\n");
+        return;
+    }
+    let bars: Vec<(f64, f64, f64)> = minutes
+        .iter()
+        .map(|m| (m.minute as f64, m.minute as f64 + 1.0, m.events_lost as f64))
+        .collect();
+    html.push_str(&bar_chart(
+        &bars,
+        "Minutes since start",
+        "Events lost",
+        "#d05a4a",
+    ));
+    let _ = writeln!(
+        html,
+        "<p class=\"note\">{} events lost in {} gaps.</p>",
+        results.events_lost,
+        results.gaps.len()
+    );
+}
+
+/// Maps data coordinates onto the plot area of a chart
+struct Axes {
+    x: (f64, f64),
+    y: (f64, f64),
+}
+
+impl Axes {
+    fn new(x: (f64, f64), y_max: f64) -> Self {
+        let x = if x.1 > x.0 { x } else { (x.0, x.0 + 1.0) };
+        let y_max = if y_max > 0.0 { y_max * 1.05 } else { 1.0 };
+        Self { x, y: (0.0, y_max) }
+    }
+
+    fn px(&self, x: f64) -> f64 {
+        MARGIN_LEFT + (x - self.x.0) / (self.x.1 - self.x.0) * (WIDTH - MARGIN_LEFT - MARGIN_RIGHT)
+    }
+
+    fn py(&self, y: f64) -> f64 {
+        HEIGHT
+            - MARGIN_BOTTOM
+            - (y - self.y.0) / (self.y.1 - self.y.0) * (HEIGHT - MARGIN_TOP - MARGIN_BOTTOM)
+    }
+
+    /// Frame, tick labels and axis titles
+    fn draw(&self, svg: &mut String, x_label: &str, y_label: &str) {
+        let (left, right) = (MARGIN_LEFT, WIDTH - MARGIN_RIGHT);
+        let (top, bottom) = (MARGIN_TOP, HEIGHT - MARGIN_BOTTOM);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{left}\" y=\"{top}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#999\"/>",
+            right - left,
+            bottom - top
+        );
+        for tick in ticks(self.x.0, self.x.1) {
+            let x = self.px(tick);
+            let _ = writeln!(
+                svg,
+                "<text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                bottom + 14.0,
+                label(tick)
+            );
+        }
+        for tick in ticks(self.y.0, self.y.1) {
+            let y = self.py(tick);
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{left}\" x2=\"{right}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"#eee\"/>\
+                 <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+                left - 6.0,
+                y + 4.0,
+                label(tick)
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            (left + right) / 2.0,
+            HEIGHT - 8.0,
+            escape(x_label)
+        );
+        let _ = writeln!(
+            svg,
+            "<text transform=\"translate(14 {:.1}) rotate(-90)\" text-anchor=\"middle\">{}</text>",
+            (top + bottom) / 2.0,
+            escape(y_label)
+        );
+    }
+}
+
+/// Bars given as (x start, x end, height)
+fn bar_chart(bars: &[(f64, f64, f64)], x_label: &str, y_label: &str, color: &str) -> String {
+    let x_min = bars.iter().map(|b| b.0).fold(f64::INFINITY, f64::min);
+    let x_max = bars.iter().map(|b| b.1).fold(f64::NEG_INFINITY, f64::max);
+    let y_max = bars.iter().map(|b| b.2).fold(0.0, f64::max);
+    let axes = Axes::new((x_min, x_max), y_max);
+
+    let mut svg = svg_open();
+    axes.draw(&mut svg, x_label, y_label);
+    for &(start, end, height) in bars.iter().filter(|b| b.2 > 0.0) {
+        let (x0, x1) = (axes.px(start), axes.px(end));
+        let y = axes.py(height);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{x0:.1}\" y=\"{y:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{color}\"/>",
+            (x1 - x0 - 1.0).max(1.0),
+            axes.py(0.0) - y
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// One line of a line chart
+struct Series<'a> {
+    legend: &'a str,
+    color: &'a str,
+    points: &'a [(f64, f64)],
+}
+
+/// Lines drawn in order, so later series are on top
+fn line_chart(series: &[Series], x_label: &str, y_label: &str) -> String {
+    let points = || series.iter().flat_map(|s| s.points.iter());
+    let x_min = points().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let x_max = points().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let y_max = points().map(|p| p.1).fold(0.0, f64::max);
+    let axes = Axes::new((x_min, x_max), y_max);
+
+    let mut svg = svg_open();
+    axes.draw(&mut svg, x_label, y_label);
+    for (
+        i,
+        Series {
+            legend,
+            color,
+            points,
+        },
+    ) in series.iter().enumerate()
+    {
+        let path: Vec<String> = points
+            .iter()
+            .map(|&(x, y)| format!("{:.1},{:.1}", axes.px(x), axes.py(y)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\"/>",
+            path.join(" ")
+        );
+        let y = MARGIN_TOP + 14.0 + i as f64 * 14.0;
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{y:.1}\" text-anchor=\"end\" style=\"fill:{color}\">{}</text>",
+            WIDTH - MARGIN_RIGHT - 8.0,
+            escape(legend)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_open() -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\">\n"
+    )
+}
+
+/// About five round tick values between `low` and `high`
+fn ticks(low: f64, high: f64) -> Vec<f64> {
+    let raw = (high - low) / 5.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= raw)
+        .unwrap_or(raw);
+    let mut tick = (low / step).ceil() * step;
+    let mut ticks = Vec::new();
+    while tick <= high + step * 1e-9 {
+        ticks.push(tick);
+        tick += step;
+    }
+    ticks
+}
+
+fn label(value: f64) -> String {
+    if value.abs() >= 100.0 || value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+fn ms(value: f64) -> String {
+    format!("{:.2} ms", value)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
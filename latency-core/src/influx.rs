@@ -0,0 +1,63 @@
+// InfluxDB line protocol for raw measurements
+
+use crate::measurement::LatencyMeasurement;
+use std::fmt::Write;
+
+/// Formats measurements as points of the `latency` measurement, with a fixed
+/// set of tags (mode, symbol, region, ...) added to every point
+#[derive(Debug, Clone)]
+pub struct LineProtocol {
+    tags: String, // Pre-escaped ",key=value" pairs
+}
+
+impl LineProtocol {
+    pub fn new(tags: &[(&str, &str)]) -> Self {
+        let mut encoded = String::new();
+        // Influx prefers tags sorted by key
+        let mut tags = tags.to_vec();
+        tags.sort_by_key(|(key, _)| *key);
+        for (key, value) in tags.into_iter().filter(|(_, value)| !value.is_empty()) {
+            let _ = write!(encoded, ",{}={}", escape(key), escape(value));
+        }
+        Self { tags: encoded }
+    }
+
+    /// One point, timestamped with the receive time (nanoseconds)
+    pub fn point(&self, m: &LatencyMeasurement) -> String {
+        let mut line = format!("latency{}", self.tags);
+        if let Some(endpoint) = m.endpoint {
+            let _ = write!(line, ",endpoint={}", endpoint);
+        }
+        let _ = write!(
+            line,
+            " sequence_id={}i,end_to_end_latency_ms={},binance_event_time={}i,warmup={}",
+            m.sequence_id,
+            m.end_to_end_latency_ms(),
+            m.binance_event_time,
+            m.warmup
+        );
+        if let Some(backbone) = m.backbone_latency_ms() {
+            let _ = write!(line, ",backbone_latency_ms={}", backbone);
+        }
+        if let Some(delay) = m.exchange_delay_ms() {
+            let _ = write!(line, ",exchange_delay_ms={}", delay);
+        }
+        if let Some(delay) = m.kernel_to_user_ms() {
+            let _ = write!(line, ",kernel_to_user_ms={}", delay);
+        }
+        let _ = write!(line, " {}", m.frankfurt_receive_time);
+        line
+    }
+}
+
+/// Escape a tag key or value (commas, equals signs and spaces)
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,73 @@
+// Datagrams the kernel dropped before the receiver could read them
+//
+// A UDP datagram that arrives while the socket's receive buffer is full is
+// dropped by the kernel. It then shows up as a sequence gap, just like a loss
+// on the network. The kernel counts these drops per socket (/proc/net/udp)
+// and host-wide (/proc/net/snmp), so the two causes can be told apart.
+
+use serde::{Deserialize, Serialize};
+
+/// Kernel UDP drops over a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KernelDropStats {
+    pub recv_buffer_bytes: Option<u64>, // Effective SO_RCVBUF, as reported by the kernel
+    pub socket_drops: Option<u64>,      // Dropped for the receiver's socket
+    pub host_rcvbuf_errors: Option<u64>, // Host-wide: receive buffer full, any UDP socket
+    pub host_in_errors: Option<u64>,    // Host-wide: all UDP receive errors, including the above
+}
+
+/// One reading of the kernel's UDP counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UdpCounters {
+    pub socket_drops: Option<u64>,
+    pub rcvbuf_errors: Option<u64>,
+    pub in_errors: Option<u64>,
+}
+
+impl UdpCounters {
+    /// Read the counters from the contents of /proc/net/udp (for the socket
+    /// with `inode`) and /proc/net/snmp
+    pub fn parse(proc_net_udp: &str, snmp: &str, inode: u64) -> Self {
+        Self {
+            socket_drops: socket_drops(proc_net_udp, inode),
+            rcvbuf_errors: snmp_udp_counter(snmp, "RcvbufErrors"),
+            in_errors: snmp_udp_counter(snmp, "InErrors"),
+        }
+    }
+
+    /// Drops between `before` and this reading
+    pub fn since(&self, before: &UdpCounters, recv_buffer_bytes: Option<u64>) -> KernelDropStats {
+        let delta = |after: Option<u64>, before: Option<u64>| {
+            after.zip(before).map(|(a, b)| a.saturating_sub(b))
+        };
+        KernelDropStats {
+            recv_buffer_bytes,
+            socket_drops: delta(self.socket_drops, before.socket_drops),
+            host_rcvbuf_errors: delta(self.rcvbuf_errors, before.rcvbuf_errors),
+            host_in_errors: delta(self.in_errors, before.in_errors),
+        }
+    }
+}
+
+/// The `drops` column of the /proc/net/udp row for the socket with `inode`
+pub fn socket_drops(proc_net_udp: &str, inode: u64) -> Option<u64> {
+    // sl local rem st tx:rx tr:when retrnsmt uid timeout inode ref pointer drops
+    proc_net_udp.lines().skip(1).find_map(|line| {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.get(9)?.parse::<u64>().ok()? != inode {
+            return None;
+        }
+        columns.get(12)?.parse().ok()
+    })
+}
+
+/// A counter from the `Udp:` header and value lines of /proc/net/snmp
+pub fn snmp_udp_counter(snmp: &str, name: &str) -> Option<u64> {
+    let mut lines = snmp.lines().filter(|line| line.starts_with("Udp:"));
+    let names = lines.next()?.split_whitespace();
+    let values = lines.next()?.split_whitespace();
+    names
+        .zip(values)
+        .find(|(n, _)| *n == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
@@ -0,0 +1,115 @@
+// Latency collection and analysis for the Binance latency experiment
+//
+// This crate holds everything needed to turn raw timestamps into experiment
+// results, independent of how events are received, so that other tools can
+// reuse the same statistics as the forwarder and receiver binaries.
+
+mod alerts;
+mod arrivals;
+mod bootstrap;
+mod bursts;
+mod clock;
+mod collector;
+mod csv;
+mod delivery;
+mod digest;
+mod endpoints;
+mod exchange_address;
+mod fragments;
+mod framing;
+mod gaps;
+mod handover;
+mod heatmap;
+mod html;
+mod influx;
+mod kernel_drops;
+mod market;
+mod measurement;
+mod metadata;
+mod openmetrics;
+mod path_race;
+mod payload;
+mod ping;
+mod quantization;
+mod queue;
+mod quotes;
+mod rate;
+mod report;
+mod results;
+mod sampling;
+mod server_time;
+mod session;
+mod shards;
+mod size;
+mod sources;
+mod spikes;
+mod stages;
+mod stale;
+mod stats;
+mod subscriptions;
+mod symbols;
+mod timeseries;
+
+pub use alerts::{Alert, AlertKind, AlertMonitor, AlertThresholds, MIN_ALERT_SAMPLES};
+pub use arrivals::{
+    merge_arrivals, read_arrivals, ArrivalDeltaStats, ArrivalLog, ArrivalMerge, MatchedArrival,
+    UpdateArrival, ARRIVAL_LOG_HEADER,
+};
+pub use bootstrap::{
+    bootstrap_intervals, BootstrapIntervals, ConfidenceInterval, CONFIDENCE_LEVEL,
+};
+pub use bursts::{burst_stats, BurstStats, AUTOCORRELATION_LAGS};
+pub use clock::{
+    ClockEstimate, ClockSample, ClockSource, OneWayDelay, OneWayDelayTracker, PtpDataSet,
+};
+pub use collector::{Collector, SecondStats};
+pub use csv::{CsvWriter, CSV_HEADER};
+pub use delivery::{delivery_class_stats, DeliveryClass, DeliveryClassStats};
+pub use digest::{
+    StreamingPercentiles, StreamingStats, StreamingSummary, DEFAULT_RELATIVE_ACCURACY,
+};
+pub use endpoints::{rank_endpoints, EndpointStats};
+pub use exchange_address::{guess_aws_region, ExchangeAddress};
+pub use fragments::FragmentStats;
+pub use framing::FramingStats;
+pub use gaps::{losses_per_minute, MinuteLosses, SequenceGap};
+pub use handover::{ConnectionHandover, HandoverReason};
+pub use heatmap::{Heatmap, HEATMAP_BUCKET_BOUNDS_MS, HEATMAP_INTERVAL_SECS};
+pub use influx::LineProtocol;
+pub use kernel_drops::{snmp_udp_counter, socket_drops, KernelDropStats, UdpCounters};
+pub use market::{compare_markets, Market, MarketStats};
+pub use measurement::{event_time_nanos, event_time_unit_nanos, LatencyMeasurement, StreamNames};
+pub use metadata::{ChronyTracking, ForwarderRegistration, ForwarderSupervision, RunMetadata};
+pub use openmetrics::{
+    render_openmetrics, validate_bucket_bounds, LatencyHistogram, OPENMETRICS_BUCKET_BOUNDS_MS,
+};
+pub use path_race::{Arrival, PathRace, PathWinStats, PATH_RACE_WINDOW};
+pub use payload::{PayloadCheck, PayloadCheckStats};
+pub use ping::{PingRttStats, PingSample, PingTracker};
+pub use quantization::{quantized_latency, QuantizedLatency};
+pub use queue::{QueueMonitor, ReceiveQueueStats};
+pub use quotes::QuoteStats;
+pub use rate::{rate_buckets, RateBucket, RATE_BUCKET_BOUNDS};
+pub use report::Report;
+pub use results::{ExperimentResults, SCHEMA_VERSION};
+pub use sampling::{SampleRate, SamplingStats};
+pub use server_time::{ServerTimeCorrection, ServerTimeSample, ServerTimeStats};
+pub use session::{
+    session_stats, SessionSplit, SessionStats, SessionWindow, MARKET_SESSIONS,
+    MIN_HOURLY_SPAN_NANOS,
+};
+pub use shards::{shard_imbalance, ShardLoad};
+pub use size::{size_buckets, SizeBucket, SizeBuckets, SIZE_BUCKET_BOUNDS};
+pub use sources::SourceStats;
+pub use spikes::{Spike, SpikeContext, SpikeDetector, MAX_SPIKES};
+pub use stages::{
+    send_queue_delay, ForwardingOverhead, OverheadTracker, SendQueueDelay, StageBreakdown,
+    StageBudget,
+};
+pub use stale::{stale_stats, StaleStats};
+pub use stats::{
+    percentile, percentile_label, LatencySummary, StatsAggregator, DEFAULT_PERCENTILES,
+};
+pub use subscriptions::{SubscriptionChange, SubscriptionMethod};
+pub use symbols::{split_by_symbol, SymbolStats};
+pub use timeseries::TimeSeriesWriter;
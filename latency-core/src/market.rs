@@ -0,0 +1,89 @@
+// Spot vs futures: the same symbol measured on both Binance markets
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Binance market a stream belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Market {
+    Spot,
+    Futures, // USD-M (fstream) and COIN-M (dstream)
+}
+
+impl Market {
+    pub const ALL: [Market; 2] = [Market::Spot, Market::Futures];
+
+    /// Market of a Binance stream URL; `None` for hosts that are not Binance
+    pub fn from_url(url: &str) -> Option<Self> {
+        let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = host.split(['/', ':', '?']).next().unwrap_or_default();
+        if !host.contains("binance") {
+            None
+        } else if host.starts_with("fstream") || host.starts_with("dstream") {
+            Some(Market::Futures)
+        } else {
+            Some(Market::Spot)
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Market::Spot => "spot",
+            Market::Futures => "futures",
+        }
+    }
+}
+
+impl fmt::Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Latency of one market in a run that collected both
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketStats {
+    pub market: Market,
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub median_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub jitter_stddev_ms: f64,
+    pub exchange_delay_median_ms: Option<f64>, // Event time − transaction time, when the stream has both
+}
+
+/// Summarize measurements per market. Returns one entry per market with
+/// samples, spot first; warm-up and untagged measurements are left out.
+pub fn compare_markets(measurements: &[LatencyMeasurement]) -> Vec<MarketStats> {
+    Market::ALL
+        .iter()
+        .filter_map(|&market| {
+            let samples = || {
+                measurements
+                    .iter()
+                    .filter(move |m| !m.warmup && m.market == Some(market))
+            };
+            let latency: StatsAggregator = samples().map(|m| m.end_to_end_latency_ms()).collect();
+            if latency.is_empty() {
+                return None;
+            }
+            let exchange_delay: StatsAggregator =
+                samples().filter_map(|m| m.exchange_delay_ms()).collect();
+
+            let summary = latency.summary();
+            Some(MarketStats {
+                market,
+                sample_count: summary.count,
+                avg_latency_ms: summary.avg_ms,
+                median_latency_ms: summary.median_ms,
+                p99_latency_ms: summary.p99_ms,
+                jitter_stddev_ms: summary.stddev_ms,
+                exchange_delay_median_ms: (!exchange_delay.is_empty())
+                    .then(|| exchange_delay.summary().median_ms),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,251 @@
+// Per-event latency measurement
+
+use crate::csv::{read_csv, CsvWriter};
+use crate::delivery::DeliveryClass;
+use crate::market::Market;
+use std::sync::Arc;
+
+/// Latency measurement for a single event
+///
+/// Latencies are kept as integer nanoseconds so sub-millisecond backbone
+/// latencies survive intact; the `*_ms` methods convert for reporting.
+#[derive(Debug, Clone)]
+pub struct LatencyMeasurement {
+    pub sequence_id: u64,
+    pub binance_event_time: i64, // Exchange timestamp as published (ms, or µs with timeUnit=MICROSECOND)
+    pub tokyo_receive_time: Option<i64>, // Only for AWS backbone mode (epoch nanos)
+    pub frankfurt_receive_time: i64, // Frankfurt arrival (epoch nanos)
+    pub end_to_end_latency_ns: i64, // Binance to Frankfurt
+    pub backbone_latency_ns: Option<i64>, // Tokyo to Frankfurt (AWS backbone only)
+    pub kernel_receive_time: Option<i64>, // Kernel receive timestamp (epoch nanos, SO_TIMESTAMPING)
+    pub warmup: bool,            // Collected during warm-up; excluded from statistics
+    pub transaction_time: Option<i64>, // Exchange trade/match time (same unit as the event time)
+    pub endpoint: Option<u16>,   // Index of the exchange endpoint (multi-endpoint baseline)
+    pub market: Option<Market>,  // Binance spot or futures, when known from the stream URL
+    pub delivery_class: DeliveryClass, // Live, or replayed by the forwarder after an outage
+    pub stream: Option<Arc<str>>, // Binance combined-stream name (btcusdt@aggTrade), shared by its events
+    pub symbol: Option<Arc<str>>, // Venue symbol of the event (BTCUSDT), with --split-by-symbol
+    pub frame_bytes: Option<u32>, // Length of the raw exchange frame
+    pub packet_bytes: Option<u32>, // Length of the forwarded event as received (AWS backbone only)
+    pub send_queue_delay_ns: Option<i64>, // Time in the forwarder's send queue, part of the backbone latency
+    pub source: Option<Arc<str>>, // Forwarder the event came from when several send to one receiver
+    pub forwarding_overhead_ns: Option<i64>, // Forwarder frame received → handed to the socket (AWS backbone only)
+}
+
+/// Nanoseconds per unit of an exchange timestamp, inferred from its magnitude:
+/// 1_000_000 for milliseconds, 1_000 for microseconds and 1 for nanoseconds
+pub fn event_time_unit_nanos(event_time: i64) -> i64 {
+    match event_time.unsigned_abs() {
+        t if t < 100_000_000_000_000 => 1_000_000,
+        t if t < 100_000_000_000_000_000 => 1_000,
+        _ => 1,
+    }
+}
+
+/// Exchange timestamp as epoch nanoseconds, whatever unit it was published in
+pub fn event_time_nanos(event_time: i64) -> i64 {
+    event_time * event_time_unit_nanos(event_time)
+}
+
+/// Combined-stream names seen in a run, so every measurement of a stream
+/// shares one allocation of its name
+#[derive(Debug, Default)]
+pub struct StreamNames(Vec<Arc<str>>);
+
+impl StreamNames {
+    /// The shared copy of `name`, added the first time it is seen
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(known) = self.0.iter().find(|known| &***known == name) {
+            return known.clone();
+        }
+        let name: Arc<str> = Arc::from(name);
+        self.0.push(name.clone());
+        name
+    }
+}
+
+impl LatencyMeasurement {
+    /// Create a new latency measurement for baseline mode (direct Binance → Frankfurt)
+    pub fn new_baseline(
+        sequence_id: u64,
+        binance_event_time: i64,
+        frankfurt_receive_time: i64,
+    ) -> Self {
+        Self {
+            sequence_id,
+            binance_event_time,
+            tokyo_receive_time: None,
+            frankfurt_receive_time,
+            end_to_end_latency_ns: frankfurt_receive_time - event_time_nanos(binance_event_time),
+            backbone_latency_ns: None,
+            kernel_receive_time: None,
+            warmup: false,
+            transaction_time: None,
+            endpoint: None,
+            market: None,
+            delivery_class: DeliveryClass::Live,
+            stream: None,
+            symbol: None,
+            frame_bytes: None,
+            packet_bytes: None,
+            send_queue_delay_ns: None,
+            source: None,
+            forwarding_overhead_ns: None,
+        }
+    }
+
+    /// Create a new latency measurement for AWS backbone mode (Binance → Tokyo → Frankfurt)
+    pub fn new_aws_backbone(
+        sequence_id: u64,
+        binance_event_time: i64,
+        tokyo_receive_time: i64,
+        frankfurt_receive_time: i64,
+    ) -> Self {
+        Self {
+            sequence_id,
+            binance_event_time,
+            tokyo_receive_time: Some(tokyo_receive_time),
+            frankfurt_receive_time,
+            end_to_end_latency_ns: frankfurt_receive_time - event_time_nanos(binance_event_time),
+            backbone_latency_ns: Some(frankfurt_receive_time - tokyo_receive_time),
+            kernel_receive_time: None,
+            warmup: false,
+            transaction_time: None,
+            endpoint: None,
+            market: None,
+            delivery_class: DeliveryClass::Live,
+            stream: None,
+            symbol: None,
+            frame_bytes: None,
+            packet_bytes: None,
+            send_queue_delay_ns: None,
+            source: None,
+            forwarding_overhead_ns: None,
+        }
+    }
+
+    /// End-to-end latency in milliseconds
+    pub fn end_to_end_latency_ms(&self) -> f64 {
+        self.end_to_end_latency_ns as f64 / 1_000_000.0
+    }
+
+    /// Backbone latency in milliseconds (AWS backbone only)
+    pub fn backbone_latency_ms(&self) -> Option<f64> {
+        self.backbone_latency_ns.map(|ns| ns as f64 / 1_000_000.0)
+    }
+
+    /// Attach the kernel receive timestamp captured for this event
+    pub fn with_kernel_receive_time(mut self, kernel_receive_time: i64) -> Self {
+        self.kernel_receive_time = Some(kernel_receive_time);
+        self
+    }
+
+    /// Attach the exchange's transaction (trade/match) time
+    pub fn with_transaction_time(mut self, transaction_time: i64) -> Self {
+        self.transaction_time = Some(transaction_time);
+        self
+    }
+
+    /// Tag the measurement with the exchange endpoint it was received from
+    pub fn with_endpoint(mut self, endpoint: u16) -> Self {
+        self.endpoint = Some(endpoint);
+        self
+    }
+
+    /// Tag the measurement with the market its stream belongs to
+    pub fn with_market(mut self, market: Market) -> Self {
+        self.market = Some(market);
+        self
+    }
+
+    /// Tag the measurement with the combined-stream name its event arrived under
+    pub fn with_stream(mut self, stream: Arc<str>) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Tag the measurement with the symbol its event names
+    pub fn with_symbol(mut self, symbol: Arc<str>) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+
+    /// Attach the length of the exchange frame the event arrived in
+    pub fn with_frame_bytes(mut self, frame_bytes: u32) -> Self {
+        self.frame_bytes = Some(frame_bytes);
+        self
+    }
+
+    /// Attach the length of the forwarded event, reassembled if it arrived in fragments
+    pub fn with_packet_bytes(mut self, packet_bytes: u32) -> Self {
+        self.packet_bytes = Some(packet_bytes);
+        self
+    }
+
+    /// Attach how long the event waited in the forwarder's send queue
+    pub fn with_send_queue_delay(mut self, send_queue_delay_ns: i64) -> Self {
+        self.send_queue_delay_ns = Some(send_queue_delay_ns);
+        self
+    }
+
+    /// Send queue delay in milliseconds (forwarders with --send-queue only)
+    pub fn send_queue_delay_ms(&self) -> Option<f64> {
+        self.send_queue_delay_ns.map(|ns| ns as f64 / 1_000_000.0)
+    }
+
+    /// Attach how long the forwarder took from receiving the frame to handing
+    /// the event to the socket
+    pub fn with_forwarding_overhead(mut self, forwarding_overhead_ns: i64) -> Self {
+        self.forwarding_overhead_ns = Some(forwarding_overhead_ns);
+        self
+    }
+
+    /// Exchange → forwarder latency in milliseconds (AWS backbone only)
+    pub fn tokyo_latency_ms(&self) -> Option<f64> {
+        self.tokyo_receive_time
+            .map(|t| (t - event_time_nanos(self.binance_event_time)) as f64 / 1_000_000.0)
+    }
+
+    /// Tag the measurement with the forwarder it came from, so its sequence ID
+    /// is tracked apart from those of other forwarders
+    pub fn with_source(mut self, source: Arc<str>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Tag the measurement with how the forwarder delivered the event
+    pub fn with_delivery_class(mut self, delivery_class: DeliveryClass) -> Self {
+        self.delivery_class = delivery_class;
+        self
+    }
+
+    /// Exchange-internal delay between the transaction and the event being published (E − T)
+    pub fn exchange_delay_ms(&self) -> Option<f64> {
+        self.transaction_time.map(|t| {
+            (event_time_nanos(self.binance_event_time) - event_time_nanos(t)) as f64 / 1_000_000.0
+        })
+    }
+
+    /// Delay between the kernel receiving the packet and userspace timestamping it
+    pub fn kernel_to_user_ms(&self) -> Option<f64> {
+        self.kernel_receive_time
+            .map(|k| (self.frankfurt_receive_time - k) as f64 / 1_000_000.0)
+    }
+
+    /// Write measurements to CSV file
+    pub fn write_to_csv(
+        measurements: &[LatencyMeasurement],
+        filepath: &str,
+    ) -> Result<(), std::io::Error> {
+        let mut writer = CsvWriter::create(filepath)?;
+        for m in measurements {
+            writer.write(m)?;
+        }
+        writer.flush()
+    }
+
+    /// Read measurements written by `write_to_csv` or a continuous-mode CSV file
+    pub fn read_from_csv(filepath: &str) -> Result<Vec<LatencyMeasurement>, std::io::Error> {
+        read_csv(filepath)
+    }
+}
@@ -0,0 +1,105 @@
+// Description of the environment a run was recorded in
+
+use crate::clock::ClockSample;
+use serde::{Deserialize, Serialize};
+
+/// Where, when and how a run was recorded, so results stay self-describing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub hostname: Option<String>,
+    pub instance_type: Option<String>, // EC2 instance type (IMDS)
+    pub availability_zone: Option<String>, // EC2 availability zone (IMDS)
+    pub aws_region: Option<String>,    // EC2 region (IMDS)
+    pub kernel_version: Option<String>, // Kernel release; the OS version on Windows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>, // OS and architecture, e.g. linux-x86_64 or macos-aarch64
+    pub crate_version: String,
+    pub command_line: Vec<String>, // Secrets such as tokens are redacted
+    pub started_at: String,        // RFC 3339
+    pub ended_at: Option<String>,  // RFC 3339, set when results are written
+    pub clock_sync: Option<ChronyTracking>, // Clock state at startup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clock_error: Vec<ClockSample>, // Read every --clock-sync-interval during the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forwarders: Vec<ForwarderRegistration>, // Forwarders that announced themselves
+}
+
+/// A forwarder as announced in its handshake, once per forwarder and address
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwarderRegistration {
+    pub forwarder_id: Option<String>,
+    pub region: String,
+    pub streams: Vec<String>,
+    pub wire_format: u32,
+    pub version: String,       // Crate version of the forwarder
+    pub peer: Option<String>,  // IP address it connected from
+    pub path: String,          // udp, tcp, ws or wss
+    pub registered_at: String, // RFC 3339, first handshake
+    pub handshakes: u64,       // One per connection, so reconnects count
+    pub compatible: bool,      // False when its events are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervision: Option<ForwarderSupervision>, // From its latest heartbeat (--heartbeat-ms)
+    #[serde(default)]
+    pub restarts_during_run: u64, // Restarts its heartbeats reported while this run collected
+}
+
+/// A forwarder's restart history, as its heartbeats report it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwarderSupervision {
+    pub process_starts: u64, // Processes started on its --state-file, this one included
+    pub restarts: u64,       // After a failure within a process, summed over processes
+    pub panics: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub session_uptime_secs: f64, // Since it last started or restarted
+    pub events_forwarded: u64,    // Over every session and process
+}
+
+impl ForwarderSupervision {
+    /// Process starts and restarts between `earlier` and this report
+    pub fn restarts_since(&self, earlier: &Self) -> u64 {
+        (self.process_starts + self.restarts)
+            .saturating_sub(earlier.process_starts + earlier.restarts)
+    }
+}
+
+/// Clock synchronization state as reported by `chronyc tracking`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChronyTracking {
+    pub reference: String, // e.g. "A9FEA97B (169.254.169.123)"
+    pub stratum: u32,
+    pub system_time_offset_ms: f64, // Positive when the system clock is ahead of NTP time
+    pub rms_offset_ms: f64,
+    pub root_delay_ms: f64,
+    pub root_dispersion_ms: f64,
+    pub leap_status: String, // "Normal" when synchronized
+}
+
+impl ChronyTracking {
+    /// Parse the output of `chronyc tracking`; `None` if a field is missing
+    pub fn parse(output: &str) -> Option<Self> {
+        let field = |name: &str| {
+            output.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                (key.trim() == name).then(|| value.trim())
+            })
+        };
+        // "0.000012345 seconds fast of NTP time" → milliseconds
+        let seconds_ms = |name: &str| -> Option<f64> {
+            let value = field(name)?;
+            let seconds: f64 = value.split_whitespace().next()?.parse().ok()?;
+            let sign = if value.contains("slow of") { -1.0 } else { 1.0 };
+            Some(sign * seconds * 1000.0)
+        };
+
+        Some(Self {
+            reference: field("Reference ID")?.to_string(),
+            stratum: field("Stratum")?.parse().ok()?,
+            system_time_offset_ms: seconds_ms("System time")?,
+            rms_offset_ms: seconds_ms("RMS offset")?,
+            root_delay_ms: seconds_ms("Root delay")?,
+            root_dispersion_ms: seconds_ms("Root dispersion")?,
+            leap_status: field("Leap status")?.to_string(),
+        })
+    }
+}
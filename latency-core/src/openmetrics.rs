@@ -0,0 +1,169 @@
+// Final latency distribution as an OpenMetrics histogram (--openmetrics-output)
+//
+// Alerting and recording rules built for Prometheus work on cumulative
+// histogram buckets, not on raw samples or a results JSON. The export is the
+// text exposition format with one `latency_seconds` histogram per leg: end to
+// end, and on backbone runs the Tokyo → receiver leg. Latency is converted to
+// seconds, the base unit the format expects; bucket bounds are given in ms
+// like everywhere else in the tool.
+
+use crate::measurement::LatencyMeasurement;
+use crate::results::ExperimentResults;
+use std::fmt::Write;
+
+/// Default upper bounds of the histogram buckets (ms); +Inf is always added
+pub const OPENMETRICS_BUCKET_BOUNDS_MS: &[f64] = &[
+    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 150.0, 200.0, 250.0, 300.0, 400.0, 500.0, 1000.0,
+    2000.0, 5000.0,
+];
+
+const METRIC: &str = "latency_seconds";
+
+/// Latencies counted at or below each bucket bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    pub bucket_bounds_ms: Vec<f64>, // Ascending upper bounds, +Inf left implicit
+    pub cumulative_counts: Vec<u64>, // At or below each bound
+    pub count: u64,
+    pub sum_ms: f64,
+    pub negative: bool, // Any latency below zero (receiver clock behind the exchange)
+}
+
+impl LatencyHistogram {
+    /// Count `latencies_ms` into buckets with the given ascending upper bounds
+    pub fn from_latencies(latencies_ms: &[f64], bucket_bounds_ms: &[f64]) -> Self {
+        let mut sorted = latencies_ms.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self {
+            bucket_bounds_ms: bucket_bounds_ms.to_vec(),
+            cumulative_counts: bucket_bounds_ms
+                .iter()
+                .map(|&bound| sorted.partition_point(|&latency| latency <= bound) as u64)
+                .collect(),
+            count: sorted.len() as u64,
+            sum_ms: sorted.iter().sum(),
+            negative: sorted.first().is_some_and(|&min| min < 0.0),
+        }
+    }
+}
+
+/// Whether `bounds` can be histogram buckets: at least one, finite and
+/// strictly ascending
+pub fn validate_bucket_bounds(bounds: &[f64]) -> Result<(), String> {
+    if bounds.is_empty() {
+        return Err("at least one bucket bound is needed".to_string());
+    }
+    if let Some(bound) = bounds.iter().find(|bound| !bound.is_finite()) {
+        return Err(format!("bucket bound {} is not a finite number", bound));
+    }
+    if let Some(pair) = bounds.windows(2).find(|pair| pair[0] >= pair[1]) {
+        return Err(format!(
+            "bucket bounds must ascend: {} is followed by {}",
+            pair[0], pair[1]
+        ));
+    }
+    Ok(())
+}
+
+/// Render the run's latency histograms in the OpenMetrics text format. The
+/// setup type, region, exchange and run ID label every series; warm-up is
+/// left out as in the statistics.
+pub fn render_openmetrics(
+    results: &ExperimentResults,
+    measurements: &[LatencyMeasurement],
+    bucket_bounds_ms: &[f64],
+) -> String {
+    let measured = || measurements.iter().filter(|m| !m.warmup);
+    let end_to_end: Vec<f64> = measured().map(|m| m.end_to_end_latency_ms()).collect();
+    let backbone: Vec<f64> = measured().filter_map(|m| m.backbone_latency_ms()).collect();
+
+    let mut labels = vec![("setup", results.setup_type.as_str())];
+    labels.extend(results.region.as_deref().map(|region| ("region", region)));
+    labels.extend(
+        results
+            .exchange
+            .as_deref()
+            .map(|exchange| ("exchange", exchange)),
+    );
+    labels.extend(results.run_id.as_deref().map(|run_id| ("run_id", run_id)));
+
+    let mut text = String::new();
+    let _ = writeln!(text, "# TYPE {} histogram", METRIC);
+    let _ = writeln!(text, "# UNIT {} seconds", METRIC);
+    let _ = writeln!(
+        text,
+        "# HELP {} Event latency: end_to_end from the exchange event time to the receiver, backbone from the forwarder to the receiver",
+        METRIC
+    );
+    let mut leg = |leg: &str, latencies: &[f64]| {
+        let mut series_labels = labels.clone();
+        series_labels.push(("leg", leg));
+        let histogram = LatencyHistogram::from_latencies(latencies, bucket_bounds_ms);
+        write_histogram(&mut text, &label_set(&series_labels), &histogram);
+    };
+    leg("end_to_end", &end_to_end);
+    if !backbone.is_empty() {
+        leg("backbone", &backbone);
+    }
+    text.push_str("# EOF\n");
+    text
+}
+
+fn write_histogram(text: &mut String, labels: &str, histogram: &LatencyHistogram) {
+    for (bound, count) in histogram
+        .bucket_bounds_ms
+        .iter()
+        .zip(&histogram.cumulative_counts)
+    {
+        let _ = writeln!(
+            text,
+            "{}_bucket{{{},le=\"{}\"}} {}",
+            METRIC,
+            labels,
+            seconds(*bound),
+            count
+        );
+    }
+    let _ = writeln!(
+        text,
+        "{}_bucket{{{},le=\"+Inf\"}} {}",
+        METRIC, labels, histogram.count
+    );
+    let _ = writeln!(text, "{}_count{{{}}} {}", METRIC, labels, histogram.count);
+    // A sum is only allowed while it can only grow, i.e. without negative
+    // observations
+    if !histogram.negative {
+        let _ = writeln!(
+            text,
+            "{}_sum{{{}}} {}",
+            METRIC,
+            labels,
+            seconds(histogram.sum_ms)
+        );
+    }
+}
+
+/// `key="value"` pairs, comma-separated, with values escaped
+fn label_set(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Milliseconds as seconds, always with a decimal point (`5.0`, not `5`)
+fn seconds(ms: f64) -> String {
+    let seconds = ms / 1_000.0;
+    if seconds.fract() == 0.0 {
+        format!("{:.1}", seconds)
+    } else {
+        format!("{}", seconds)
+    }
+}
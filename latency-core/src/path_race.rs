@@ -0,0 +1,136 @@
+// Deduplication and win tracking for events delivered over redundant paths
+
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Events whose race is remembered. A copy arriving after this many newer
+/// events is taken for a new event; an older event only one path delivered
+/// is counted as that path's only delivery and forgotten.
+pub const PATH_RACE_WINDOW: usize = 100_000;
+
+/// Outcome of recording an arrival on one of the redundant paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// First copy of this event; it should be measured
+    First,
+    /// A copy of an event that has already been measured
+    Duplicate,
+}
+
+/// Per-path results of a redundant-path run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathWinStats {
+    pub path: String,
+    pub wins: u64,          // Arrived first while another path also delivered
+    pub only_delivery: u64, // No other path delivered this event
+    pub avg_win_margin_ms: f64,
+    pub median_win_margin_ms: f64,
+    pub max_win_margin_ms: f64,
+}
+
+/// Tracks which of several paths delivers each event first. Events are keyed
+/// by anything both paths agree on: a sequence ID, or an exchange update ID
+/// when the paths are separate exchange connections. The race for an event is
+/// decided when a second path delivers it.
+#[derive(Debug, Default)]
+pub struct PathRace {
+    // Some((path index, receive time)) while waiting for another copy, None once decided
+    arrivals: HashMap<u64, Option<(usize, i64)>>,
+    order: VecDeque<u64>, // Keys of `arrivals`, oldest first
+    tallies: Vec<PathTally>,
+}
+
+#[derive(Debug)]
+struct PathTally {
+    path: String,
+    wins: u64,
+    only_delivery: u64, // Among the events already forgotten
+    margins_ms: StatsAggregator,
+}
+
+impl PathRace {
+    pub fn new<S: AsRef<str>>(paths: &[S]) -> Self {
+        Self {
+            arrivals: HashMap::new(),
+            order: VecDeque::new(),
+            tallies: paths
+                .iter()
+                .map(|path| PathTally {
+                    path: path.as_ref().to_string(),
+                    wins: 0,
+                    only_delivery: 0,
+                    margins_ms: StatsAggregator::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Record that `sequence_id` arrived on `path` at `receive_time` (epoch
+    /// nanos). `path` must be one of the paths the race was created with.
+    pub fn record_arrival(&mut self, sequence_id: u64, path: &str, receive_time: i64) -> Arrival {
+        let path = self
+            .tallies
+            .iter()
+            .position(|t| t.path == path)
+            .expect("path not registered with PathRace::new");
+        match self.arrivals.get_mut(&sequence_id) {
+            None => {
+                self.arrivals
+                    .insert(sequence_id, Some((path, receive_time)));
+                self.order.push_back(sequence_id);
+                if self.order.len() > PATH_RACE_WINDOW {
+                    self.forget_oldest();
+                }
+                Arrival::First
+            }
+            Some(state) => {
+                // Repeats on the winning path (e.g. UDP duplicates) don't decide the race
+                if let Some((winner, first_time)) = *state {
+                    if winner != path {
+                        let margin_ms = (receive_time - first_time) as f64 / 1_000_000.0;
+                        let tally = &mut self.tallies[winner];
+                        tally.wins += 1;
+                        tally.margins_ms.push(margin_ms);
+                        *state = None;
+                    }
+                }
+                Arrival::Duplicate
+            }
+        }
+    }
+
+    fn forget_oldest(&mut self) {
+        let Some(oldest) = self.order.pop_front() else {
+            return;
+        };
+        if let Some(Some((path, _))) = self.arrivals.remove(&oldest) {
+            self.tallies[path].only_delivery += 1;
+        }
+    }
+
+    /// Summarize wins and margins per path
+    pub fn results(&self) -> Vec<PathWinStats> {
+        self.tallies
+            .iter()
+            .enumerate()
+            .map(|(index, tally)| {
+                let pending = self
+                    .arrivals
+                    .values()
+                    .filter(|state| matches!(state, Some((path, _)) if *path == index))
+                    .count() as u64;
+                let summary = tally.margins_ms.summary();
+
+                PathWinStats {
+                    path: tally.path.clone(),
+                    wins: tally.wins,
+                    only_delivery: tally.only_delivery + pending,
+                    avg_win_margin_ms: summary.avg_ms,
+                    median_win_margin_ms: summary.median_ms,
+                    max_win_margin_ms: summary.max_ms,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,43 @@
+// Integrity of forwarded events (--verify-payload)
+//
+// The forwarder copies the exchange event time into the envelope it sends, and
+// the latency is computed from that copy. Parsing the raw payload again on the
+// receiver and comparing catches serialization bugs in the forwarder and
+// events from a stream other than the one being measured.
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of checking one event's payload against its envelope
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadCheck {
+    Match,
+    Unparsable, // Not an event the exchange adapter understands
+    EventTimeMismatch { payload: Option<i64> }, // Payload event time differs from the envelope's
+    SymbolMismatch { payload: String }, // Event of another symbol
+}
+
+/// Payload checks over a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadCheckStats {
+    pub checked: u64,
+    pub unparsable: u64,
+    pub event_time_mismatches: u64,
+    pub symbol_mismatches: u64,
+}
+
+impl PayloadCheckStats {
+    pub fn record(&mut self, check: &PayloadCheck) {
+        self.checked += 1;
+        match check {
+            PayloadCheck::Match => {}
+            PayloadCheck::Unparsable => self.unparsable += 1,
+            PayloadCheck::EventTimeMismatch { .. } => self.event_time_mismatches += 1,
+            PayloadCheck::SymbolMismatch { .. } => self.symbol_mismatches += 1,
+        }
+    }
+
+    /// Events that failed any check
+    pub fn failed(&self) -> u64 {
+        self.unparsable + self.event_time_mismatches + self.symbol_mismatches
+    }
+}
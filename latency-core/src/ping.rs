@@ -0,0 +1,92 @@
+// Round trips measured alongside the experiment: WebSocket pings to the
+// exchange and path probes to the forwarder host
+//
+// A ping is answered by the exchange's WebSocket server without going through
+// its matching or market data pipeline, so the round trip measures how far
+// away the server is, separately from how late events are published.
+
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+
+/// One answered ping
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PingSample {
+    pub sent_at: i64, // Epoch nanos
+    pub rtt_ms: f64,
+}
+
+/// Ping round trips over a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingRttStats {
+    pub sent: u64,
+    pub answered: usize,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub median_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub series: Vec<PingSample>,
+}
+
+/// Builds ping payloads and matches pongs to them. The payload is the send
+/// time, which the server echoes back, so no pings need to be remembered.
+#[derive(Debug, Clone, Default)]
+pub struct PingTracker {
+    sent: u64,
+    samples: Vec<PingSample>,
+}
+
+impl PingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a ping sent at `now` (epoch nanos) and return its payload
+    pub fn payload(&mut self, now: i64) -> Vec<u8> {
+        self.sent += 1;
+        now.to_be_bytes().to_vec()
+    }
+
+    /// Record the pong for one of our pings received at `now`; returns the
+    /// round trip in milliseconds, or `None` for a pong we did not ask for
+    pub fn pong(&mut self, payload: &[u8], now: i64) -> Option<f64> {
+        let sent_at = i64::from_be_bytes(payload.try_into().ok()?);
+        self.record(sent_at, now)
+    }
+
+    /// Record a round trip timed by the caller, for probes that carry no
+    /// payload; `None` when the times are out of order
+    pub fn record(&mut self, sent_at: i64, now: i64) -> Option<f64> {
+        if sent_at > now {
+            return None;
+        }
+        let rtt_ms = (now - sent_at) as f64 / 1_000_000.0;
+        self.samples.push(PingSample { sent_at, rtt_ms });
+        Some(rtt_ms)
+    }
+
+    /// Round-trip statistics, with every sample when `series` is set; `None`
+    /// until a pong arrived
+    pub fn stats(&self, series: bool) -> Option<PingRttStats> {
+        let rtt: StatsAggregator = self.samples.iter().map(|s| s.rtt_ms).collect();
+        if rtt.is_empty() {
+            return None;
+        }
+        let summary = rtt.summary();
+        Some(PingRttStats {
+            sent: self.sent,
+            answered: summary.count,
+            min_ms: summary.min_ms,
+            avg_ms: summary.avg_ms,
+            median_ms: summary.median_ms,
+            p99_ms: summary.p99_ms,
+            max_ms: summary.max_ms,
+            series: if series {
+                self.samples.clone()
+            } else {
+                Vec::new()
+            },
+        })
+    }
+}
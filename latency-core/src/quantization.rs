@@ -0,0 +1,66 @@
+// Exchange timestamp quantization (--quantization)
+//
+// Binance truncates event times to whole milliseconds: an event stamped E was
+// published somewhere in [E, E + 1 ms), so latency measured against E reads
+// 0 to 1 ms high. Once backbone savings are down to 1-2 ms that error is as
+// large as the effect being measured. The quantization-aware view moves every
+// latency to the middle of its interval and states the ± half-resolution bound
+// that remains. Delays that never go through E keep their precision: the
+// backbone leg, and arrival deltas matched by update ID.
+
+use crate::arrivals::ArrivalDeltaStats;
+use crate::measurement::{event_time_nanos, event_time_unit_nanos, LatencyMeasurement};
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Latency corrected for the resolution of the exchange event time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedLatency {
+    pub resolution_ms: f64, // Coarsest event time resolution in the run: 1 for millisecond feeds
+    pub bound_ms: f64,      // Each corrected latency is within ± this of the true one
+    pub end_to_end: LatencySummary, // Exchange → receiver, at the middle of each interval
+    pub percentiles: BTreeMap<String, f64>, // Same, keyed "p50", "p99.9", ...
+    // AWS backbone runs: exchange → forwarder, corrected the same way
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokyo: Option<LatencySummary>,
+    // Forwarder and receiver arrival logs joined by update ID: sub-millisecond
+    // deltas without the event time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival_delta: Option<ArrivalDeltaStats>,
+}
+
+/// Correct end-to-end (and exchange → forwarder) latency for event time
+/// truncation. Warm-up is left out; returns nothing without samples.
+pub fn quantized_latency(
+    measurements: &[LatencyMeasurement],
+    percentiles: &[f64],
+) -> Option<QuantizedLatency> {
+    let measured = || measurements.iter().filter(|m| !m.warmup);
+    let unit_nanos = measured()
+        .map(|m| event_time_unit_nanos(m.binance_event_time))
+        .max()?;
+
+    // The true event time lies half a unit after E on average, give or take
+    // half a unit
+    let half_unit = |m: &LatencyMeasurement| event_time_unit_nanos(m.binance_event_time) / 2;
+    let end_to_end: StatsAggregator = measured()
+        .map(|m| (m.end_to_end_latency_ns - half_unit(m)) as f64 / 1_000_000.0)
+        .collect();
+    let tokyo: StatsAggregator = measured()
+        .filter_map(|m| {
+            let tokyo_ns = m.tokyo_receive_time? - event_time_nanos(m.binance_event_time);
+            Some((tokyo_ns - half_unit(m)) as f64 / 1_000_000.0)
+        })
+        .collect();
+
+    let resolution_ms = unit_nanos as f64 / 1_000_000.0;
+    Some(QuantizedLatency {
+        resolution_ms,
+        bound_ms: resolution_ms / 2.0,
+        end_to_end: end_to_end.summary(),
+        percentiles: end_to_end.percentiles(percentiles),
+        tokyo: (!tokyo.is_empty()).then(|| tokyo.summary()),
+        arrival_delta: None,
+    })
+}
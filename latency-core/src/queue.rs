@@ -0,0 +1,57 @@
+// Backpressure on the queue between a receive task and event processing
+//
+// Receive tasks only timestamp incoming data and push it onto a bounded
+// queue. If processing falls behind, the queue fills and the receive task has
+// to wait, which delays the next read and shows up as extra latency. These
+// counters make that visible in the results.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Receive queue usage over a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiveQueueStats {
+    pub capacity: usize,
+    pub frames: u64,      // Frames pushed by the receive tasks
+    pub max_depth: usize, // Most frames waiting for processing at once
+    pub full_pushes: u64, // Pushes that found the queue full and had to wait
+    pub blocked_ms: f64,  // Total time receive tasks spent waiting for space
+}
+
+/// Counters updated by receive tasks on every push; safe to share across tasks
+#[derive(Debug, Default)]
+pub struct QueueMonitor {
+    frames: AtomicU64,
+    max_depth: AtomicUsize,
+    full_pushes: AtomicU64,
+    blocked_nanos: AtomicU64,
+}
+
+impl QueueMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one push that left `depth` frames queued, having waited
+    /// `blocked` for space if the queue was full
+    pub fn record(&self, depth: usize, blocked: Option<Duration>) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.max_depth.fetch_max(depth, Ordering::Relaxed);
+        if let Some(blocked) = blocked {
+            self.full_pushes.fetch_add(1, Ordering::Relaxed);
+            self.blocked_nanos
+                .fetch_add(blocked.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn results(&self, capacity: usize) -> ReceiveQueueStats {
+        ReceiveQueueStats {
+            capacity,
+            frames: self.frames.load(Ordering::Relaxed),
+            max_depth: self.max_depth.load(Ordering::Relaxed),
+            full_pushes: self.full_pushes.load(Ordering::Relaxed),
+            blocked_ms: self.blocked_nanos.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
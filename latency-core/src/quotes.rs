@@ -0,0 +1,56 @@
+// Top-of-book tracking per reporting window (--market-stats)
+//
+// Book ticker updates give the best bid and ask; each reporting window
+// records the mid-price, the average spread and how often the top of the book
+// moved, so latency spikes can be lined up with volatility bursts.
+
+/// Market data of one reporting window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteStats {
+    pub mid_price: f64,      // At the end of the window
+    pub avg_spread_bps: f64, // Over the window's quotes, or the last one if none arrived
+    pub book_changes: u64,   // Quotes that moved the best bid or ask price
+}
+
+/// Accumulates quotes between window closes
+#[derive(Debug, Default)]
+pub(crate) struct QuoteTracker {
+    last: Option<(f64, f64)>, // Best bid and ask
+    spread_bps_sum: f64,
+    quotes: u64,
+    changes: u64,
+}
+
+impl QuoteTracker {
+    pub fn record(&mut self, bid_price: f64, ask_price: f64) {
+        if self.last != Some((bid_price, ask_price)) {
+            self.changes += 1;
+        }
+        self.last = Some((bid_price, ask_price));
+        self.spread_bps_sum += spread_bps(bid_price, ask_price);
+        self.quotes += 1;
+    }
+
+    /// Close the window; `None` until the first quote
+    pub fn close(&mut self) -> Option<QuoteStats> {
+        let (bid, ask) = self.last?;
+        let avg_spread_bps = if self.quotes > 0 {
+            self.spread_bps_sum / self.quotes as f64
+        } else {
+            spread_bps(bid, ask)
+        };
+        let stats = QuoteStats {
+            mid_price: (bid + ask) / 2.0,
+            avg_spread_bps,
+            book_changes: self.changes,
+        };
+        self.spread_bps_sum = 0.0;
+        self.quotes = 0;
+        self.changes = 0;
+        Some(stats)
+    }
+}
+
+fn spread_bps(bid: f64, ask: f64) -> f64 {
+    (ask - bid) / ((bid + ask) / 2.0) * 10_000.0
+}
@@ -0,0 +1,59 @@
+// Latency by event rate: does latency degrade during market bursts?
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Lower bounds of the rate buckets (events per second)
+pub const RATE_BUCKET_BOUNDS: &[u64] = &[0, 10, 50, 100, 250, 500, 1000, 2500, 5000];
+
+/// Latency of measurements received in seconds with a similar event rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateBucket {
+    pub min_events_per_sec: u64,
+    pub max_events_per_sec: Option<u64>, // Exclusive; `None` for the top bucket
+    pub seconds: usize,                  // Seconds whose rate fell in this bucket
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>,
+}
+
+/// Bucket measurements by the number of events received in the same wall-clock
+/// second and report end-to-end latency per bucket. Warm-up measurements count
+/// toward the rate but not the latency. The first and last second of a run are
+/// usually partial, so they understate the rate. Empty buckets are left out.
+pub fn rate_buckets(measurements: &[LatencyMeasurement], percentiles: &[f64]) -> Vec<RateBucket> {
+    let mut per_second: HashMap<i64, u64> = HashMap::new();
+    for m in measurements {
+        *per_second
+            .entry(m.frankfurt_receive_time.div_euclid(1_000_000_000))
+            .or_default() += 1;
+    }
+
+    let bucket_of = |rate: u64| RATE_BUCKET_BOUNDS.partition_point(|&bound| bound <= rate) - 1;
+    let mut seconds = vec![0usize; RATE_BUCKET_BOUNDS.len()];
+    for &rate in per_second.values() {
+        seconds[bucket_of(rate)] += 1;
+    }
+
+    let mut latencies = vec![StatsAggregator::new(); RATE_BUCKET_BOUNDS.len()];
+    for m in measurements.iter().filter(|m| !m.warmup) {
+        let rate = per_second[&m.frankfurt_receive_time.div_euclid(1_000_000_000)];
+        latencies[bucket_of(rate)].push(m.end_to_end_latency_ms());
+    }
+
+    latencies
+        .iter()
+        .enumerate()
+        .filter(|(_, stats)| !stats.is_empty())
+        .map(|(i, stats)| RateBucket {
+            min_events_per_sec: RATE_BUCKET_BOUNDS[i],
+            max_events_per_sec: RATE_BUCKET_BOUNDS.get(i + 1).copied(),
+            seconds: seconds[i],
+            sample_count: stats.len(),
+            avg_latency_ms: stats.mean(),
+            percentiles: stats.percentiles(percentiles),
+        })
+        .collect()
+}
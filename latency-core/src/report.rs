@@ -0,0 +1,812 @@
+// Final experiment report: results plus the raw measurements behind them
+
+use crate::exchange_address::ExchangeAddress;
+use crate::gaps::losses_per_minute;
+use crate::html::render_html;
+use crate::measurement::LatencyMeasurement;
+use crate::openmetrics::render_openmetrics;
+use crate::results::ExperimentResults;
+use crate::shards::shard_imbalance;
+
+/// Experiment results together with the measurements they were computed from
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub results: ExperimentResults,
+    pub measurements: Vec<LatencyMeasurement>,
+}
+
+impl Report {
+    pub fn new(results: ExperimentResults, measurements: Vec<LatencyMeasurement>) -> Self {
+        Self {
+            results,
+            measurements,
+        }
+    }
+
+    /// Write aggregate results as pretty-printed JSON
+    pub fn write_json(&self, filepath: &str) -> Result<(), std::io::Error> {
+        let results_json = serde_json::to_string_pretty(&self.results)?;
+        std::fs::write(filepath, results_json)
+    }
+
+    /// Write the raw measurements as CSV
+    pub fn write_csv(&self, filepath: &str) -> Result<(), std::io::Error> {
+        LatencyMeasurement::write_to_csv(&self.measurements, filepath)
+    }
+
+    /// Write a standalone HTML report with charts. The distribution and
+    /// time-series charts need the raw measurements and are left out without them.
+    pub fn write_html(&self, filepath: &str) -> Result<(), std::io::Error> {
+        std::fs::write(filepath, render_html(&self.results, &self.measurements))
+    }
+
+    /// Write the latency distribution as OpenMetrics histograms with the
+    /// given bucket upper bounds (ms)
+    pub fn write_openmetrics(
+        &self,
+        filepath: &str,
+        bucket_bounds_ms: &[f64],
+    ) -> Result<(), std::io::Error> {
+        std::fs::write(
+            filepath,
+            render_openmetrics(&self.results, &self.measurements, bucket_bounds_ms),
+        )
+    }
+
+    /// Print a human-readable summary to stdout
+    pub fn print_summary(&self) {
+        let results = &self.results;
+
+        println!("\n=== Experiment Results ===");
+        println!("Setup: {}", results.setup_type);
+        if let Some(region) = &results.region {
+            println!("Region: {}", region);
+        }
+        if let Some(exchange) = &results.exchange {
+            println!("Exchange: {}", exchange);
+        }
+        if let Some(run_id) = &results.run_id {
+            println!("Run ID: {}", run_id);
+        }
+        let platform = results
+            .metadata
+            .as_ref()
+            .and_then(|m| m.platform.as_deref());
+        if let Some(platform) = platform.filter(|platform| !platform.starts_with("linux-")) {
+            println!(
+                "Platform: {} (no kernel timestamps; compare with runs on the same platform only)",
+                platform
+            );
+        }
+        println!("Samples: {}", results.sample_count);
+        if let Some(sampling) = &results.sampling {
+            println!(
+                "Sampled: {} of {} events kept ({:.2}%, rate {}); counters and whole-run percentiles cover every event",
+                sampling.retained,
+                sampling.events,
+                sampling.retained_pct(),
+                sampling.sample_rate
+            );
+        }
+        if results.warmup_samples > 0 {
+            println!("Warm-up samples excluded: {}", results.warmup_samples);
+        }
+        println!("Events lost: {}", results.events_lost);
+        if results.duplicates > 0 || results.reordered > 0 {
+            println!(
+                "Duplicates: {}, reordered: {} (max distance {})",
+                results.duplicates, results.reordered, results.max_reorder_distance
+            );
+        }
+        if let Some(buffered) = results.buffered_events {
+            println!(
+                "Delivered from the forwarder's retry buffer: {} (latency includes the outage)",
+                buffered
+            );
+        }
+        if let Some(events) = results.foreign_run_events {
+            println!(
+                "Ignored from another forwarder run: {} (not measured)",
+                events
+            );
+        }
+        if let Some(events) = results.incompatible_events {
+            println!(
+                "Refused from incompatible forwarders: {} (not measured)",
+                events
+            );
+        }
+        if let Some(dscp) = results.dscp {
+            println!("DSCP marking: {} (set by the forwarder)", dscp);
+        }
+        if let Some(compressed) = results.ws_compression {
+            let setting = if compressed {
+                "permessage-deflate"
+            } else {
+                "off"
+            };
+            println!("Exchange WebSocket compression: {}", setting);
+        }
+        if let Some(check) = &results.payload_check {
+            println!(
+                "Payload check: {} of {} events failed ({} event time, {} symbol, {} unparsable)",
+                check.failed(),
+                check.checked,
+                check.event_time_mismatches,
+                check.symbol_mismatches,
+                check.unparsable
+            );
+        }
+        if let Some(ping) = &results.exchange_ping_rtt {
+            println!(
+                "Exchange ping RTT: median {:.2} ms, p99 {:.2} ms, min {:.2} ms ({} of {} pings answered)",
+                ping.median_ms, ping.p99_ms, ping.min_ms, ping.answered, ping.sent
+            );
+        }
+        if let Some(path) = &results.path_rtt {
+            println!(
+                "Path RTT to the forwarder: median {:.2} ms, p99 {:.2} ms, min {:.2} ms ({} of {} probes answered)",
+                path.median_ms, path.p99_ms, path.min_ms, path.answered, path.sent
+            );
+        }
+        if results.reconnects > 0 {
+            println!(
+                "Reconnects: {} (total outage {:.0} ms)",
+                results.reconnects, results.outage_ms
+            );
+        }
+        let forwarders = results.metadata.iter().flat_map(|m| &m.forwarders);
+        for forwarder in forwarders.filter(|f| f.restarts_during_run > 0) {
+            let name = forwarder
+                .forwarder_id
+                .as_deref()
+                .or(forwarder.peer.as_deref())
+                .unwrap_or(&forwarder.region);
+            let last_error = forwarder
+                .supervision
+                .as_ref()
+                .and_then(|s| s.last_error.as_deref());
+            println!(
+                "Forwarder {} restarted {} time(s) during the run (last error: {})",
+                name,
+                forwarder.restarts_during_run,
+                last_error.unwrap_or("none")
+            );
+        }
+        for handover in results.exchange_handovers.iter().flatten() {
+            println!(
+                "Exchange connection handover ({}): after {:.1} h, gap {:.1} ms, {} duplicates skipped",
+                handover.reason,
+                handover.connection_age_secs / 3600.0,
+                handover.gap_ms,
+                handover.duplicates_skipped
+            );
+        }
+        if let Some(addresses) = &results.exchange_addresses {
+            print_exchange_addresses(addresses);
+        }
+        for change in results.subscriptions.iter().flatten() {
+            let answer = match (&change.error, change.ack_ms) {
+                (Some(error), _) => format!("rejected: {}", error),
+                (None, Some(ack_ms)) => format!("acknowledged in {:.1} ms", ack_ms),
+                (None, None) => "not acknowledged".to_string(),
+            };
+            println!("{} {}: {}", change.method, change.streams.join(","), answer);
+        }
+        println!("Average latency: {:.2} ms", results.avg_latency_ms);
+        println!("Median latency: {:.2} ms", results.median_latency_ms);
+        // Labels sort as strings ("p10" < "p5"), so order them numerically
+        let mut percentiles: Vec<(&String, &f64)> = results.percentiles.iter().collect();
+        percentiles.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+        for (label, value) in percentiles {
+            println!("{} latency: {:.2} ms", label.to_uppercase(), value);
+        }
+        println!("Min latency: {:.2} ms", results.min_latency_ms);
+        println!("Max latency: {:.2} ms", results.max_latency_ms);
+        println!("Jitter (stddev): {:.2} ms", results.jitter_stddev_ms);
+        if let Some(intervals) = &results.confidence_intervals {
+            println!(
+                "{:.0}% confidence intervals ({} bootstrap resamples):",
+                intervals.confidence * 100.0,
+                intervals.iterations
+            );
+            for (label, interval) in [
+                ("Median", intervals.median),
+                ("P95", intervals.p95),
+                ("P99", intervals.p99),
+            ] {
+                println!(
+                    "  {}: {:.2} ms [{:.2}, {:.2}]",
+                    label, interval.estimate_ms, interval.lower_ms, interval.upper_ms
+                );
+            }
+        }
+
+        if let Some(backbone_avg) = results.backbone_avg_latency_ms {
+            println!("\n=== AWS Backbone Latency (Tokyo → receiver) ===");
+            println!("Average backbone latency: {:.2} ms", backbone_avg);
+            if let Some(backbone_median) = results.backbone_median_latency_ms {
+                println!("Median backbone latency: {:.2} ms", backbone_median);
+            }
+            if let Some(backbone_percentiles) = &results.backbone_percentiles_us {
+                let mut backbone_percentiles: Vec<(&String, &f64)> =
+                    backbone_percentiles.iter().collect();
+                backbone_percentiles.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+                for (label, value) in backbone_percentiles {
+                    println!("{} backbone latency: {:.1} µs", label.to_uppercase(), value);
+                }
+            }
+        }
+
+        if let Some(sources) = &results.sources {
+            println!("\n=== Per Forwarder ===");
+            for source in sources {
+                let backbone = source
+                    .backbone_avg_latency_ms
+                    .map(|ms| format!(" | backbone avg {:.2} ms", ms))
+                    .unwrap_or_default();
+                println!(
+                    "{}: {} samples | avg {:.2} ms | median {:.2} ms{} | {} lost, {} duplicates, {} reordered",
+                    source.source,
+                    source.sample_count,
+                    source.avg_latency_ms,
+                    source.median_latency_ms,
+                    backbone,
+                    source.events_lost,
+                    source.duplicates,
+                    source.reordered
+                );
+            }
+        }
+
+        if let Some(one_way) = &results.backbone_one_way {
+            let delay = &one_way.delay;
+            println!(
+                "\n=== One-Way Delay, Clock-Corrected ({} events) ===",
+                delay.count
+            );
+            println!(
+                "Average: {:.3} ms ± {:.3} ms, median: {:.3} ms, p99: {:.3} ms",
+                delay.avg_ms, one_way.avg_error_bound_ms, delay.median_ms, delay.p99_ms
+            );
+            println!(
+                "Measured − corrected: {:.3} ms on average; error bound up to {:.3} ms (forwarder {:.3} ms, receiver {:.3} ms)",
+                one_way.avg_correction_ms,
+                one_way.max_error_bound_ms,
+                one_way.forwarder_error_bound_ms,
+                one_way.receiver_error_bound_ms
+            );
+        }
+
+        if let Some(server_time) = &results.server_time {
+            println!(
+                "\n=== Exchange Server Time ({} of {} requests answered) ===",
+                server_time.samples.len(),
+                server_time.samples.len() as u64 + server_time.failures
+            );
+            println!(
+                "Exchange clock − ours: {:.3} ms ± {:.3} ms on average (min {:.3} ms, max {:.3} ms)",
+                server_time.avg_offset_ms,
+                server_time.avg_error_bound_ms,
+                server_time.min_offset_ms,
+                server_time.max_offset_ms
+            );
+            if let Some(corrected) = &server_time.corrected {
+                println!(
+                    "Corrected end-to-end: average {:.3} ms, median {:.3} ms, p99 {:.3} ms ({}; {} events uncorrected)",
+                    corrected.avg_ms,
+                    corrected.median_ms,
+                    corrected.p99_ms,
+                    if server_time.applied {
+                        "applied to the results above"
+                    } else {
+                        "not applied, see --correct-server-time"
+                    },
+                    server_time.uncorrected_events
+                );
+            }
+            if server_time.skewed() && !server_time.applied {
+                println!(
+                    "Clocks disagree beyond the error bound: measured end-to-end latencies are off by about {:.3} ms",
+                    server_time.avg_offset_ms
+                );
+            }
+            if server_time.negative_latencies > 0 {
+                println!(
+                    "Negative end-to-end latencies: {} measured, {} after correction",
+                    server_time.negative_latencies, server_time.negative_corrected
+                );
+            }
+        }
+
+        if let Some(quantized) = &results.quantization {
+            let e2e = &quantized.end_to_end;
+            println!(
+                "\n=== Quantization-Aware Latency (event time resolution {} ms) ===",
+                quantized.resolution_ms
+            );
+            println!(
+                "End-to-end: average {:.3} ms, median {:.3} ms, p99 {:.3} ms, each ± {:.3} ms",
+                e2e.avg_ms, e2e.median_ms, e2e.p99_ms, quantized.bound_ms
+            );
+            if let Some(tokyo) = &quantized.tokyo {
+                println!(
+                    "Exchange → forwarder: average {:.3} ms, median {:.3} ms, each ± {:.3} ms",
+                    tokyo.avg_ms, tokyo.median_ms, quantized.bound_ms
+                );
+            }
+            if let Some(arrivals) = &quantized.arrival_delta {
+                println!(
+                    "Receiver − forwarder arrival, matched by update ID ({} events): average {:.3} ms, median {:.3} ms, p99 {:.3} ms",
+                    arrivals.matched,
+                    arrivals.delta.avg_ms,
+                    arrivals.delta.median_ms,
+                    arrivals.delta.p99_ms
+                );
+            }
+        }
+
+        if let (Some(avg), Some(median)) = (
+            results.exchange_delay_avg_ms,
+            results.exchange_delay_median_ms,
+        ) {
+            println!("\n=== Exchange Delay (event time − transaction time) ===");
+            println!("Average: {:.2} ms, median: {:.2} ms", avg, median);
+            // End-to-end latency starts at the event time, so the two add up
+            println!(
+                "Median trade → receiver: {:.2} ms",
+                median + results.median_latency_ms
+            );
+        }
+
+        if let Some(delay) = &results.kernel_to_user_delay {
+            println!("\n=== Kernel → Userspace Receive Delay ===");
+            println!(
+                "Average: {:.3} ms, median: {:.3} ms, p99: {:.3} ms, max: {:.3} ms",
+                delay.avg_ms, delay.median_ms, delay.p99_ms, delay.max_ms
+            );
+        }
+
+        if !results.gaps.is_empty() {
+            let largest = results.gaps.iter().map(|g| g.size).max().unwrap_or(0);
+            println!("\n=== Sequence Gaps ===");
+            println!("Gaps: {} (largest {} events)", results.gaps.len(), largest);
+            println!("Losses per minute:");
+            for minute in losses_per_minute(&results.gaps) {
+                println!(
+                    "  {:>4} min | {:>6} events in {} gaps",
+                    minute.minute, minute.events_lost, minute.gaps
+                );
+            }
+        }
+
+        if let Some(spikes) = &results.spikes {
+            let with_gap = spikes.iter().filter(|s| s.seq_gap).count();
+            let count = results.spike_count.unwrap_or(spikes.len() as u64);
+            println!("\n=== Latency Spikes ===");
+            if count > spikes.len() as u64 {
+                println!(
+                    "Spikes: {} (kept the last {}, of which {} coincided with a sequence gap)",
+                    count,
+                    spikes.len(),
+                    with_gap
+                );
+            } else {
+                println!(
+                    "Spikes: {} ({} coincided with a sequence gap)",
+                    count, with_gap
+                );
+            }
+            if spikes.iter().any(|s| s.send_queue_delay_ms.is_some()) {
+                println!(
+                    "Explained by the forwarder's send queue: {}",
+                    spikes.iter().filter(|s| s.queued()).count()
+                );
+            }
+            if let Some(worst) = spikes
+                .iter()
+                .max_by(|a, b| a.latency_ms.partial_cmp(&b.latency_ms).unwrap())
+            {
+                println!(
+                    "Worst: {:.2} ms ({:.1}x median) at {:.1}s",
+                    worst.latency_ms, worst.magnitude, worst.elapsed_secs
+                );
+            }
+        }
+
+        if let Some(stale) = &results.stale {
+            println!(
+                "\n=== Stale Events (older than {} ms) ===",
+                stale.max_staleness_ms
+            );
+            println!(
+                "Stale: {} ({:.2}% of events, excluded from the statistics above)",
+                stale.stale_events, stale.share_pct
+            );
+            if stale.stale_events > 0 {
+                let mut levels: Vec<(&String, &f64)> = stale.percentiles.iter().collect();
+                levels.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+                let levels: Vec<String> = levels
+                    .iter()
+                    .map(|(label, ms)| format!("{} {:.2}", label, ms))
+                    .collect();
+                println!(
+                    "Staleness: avg {:.2} ms | max {:.2} ms | {}",
+                    stale.staleness.avg_ms,
+                    stale.staleness.max_ms,
+                    levels.join(" ")
+                );
+            }
+        }
+
+        if let Some(bursts) = &results.bursts {
+            println!("\n=== Latency Bursts ===");
+            let mut lags: Vec<(&String, &f64)> = bursts.autocorrelation.iter().collect();
+            lags.sort_by_key(|(label, _)| label[3..].parse::<usize>().unwrap_or(usize::MAX));
+            let lags: Vec<String> = lags
+                .iter()
+                .map(|(label, r)| format!("{} {:+.3}", label, r))
+                .collect();
+            println!("Autocorrelation: {}", lags.join(" | "));
+            println!(
+                "Runs above median: {} | avg {:.2} events | p99 {:.0} | max {}",
+                bursts.above_median_runs,
+                bursts.avg_run_length,
+                bursts.p99_run_length,
+                bursts.max_run_length
+            );
+        }
+
+        if let Some(alerts) = &results.alerts {
+            println!("\n=== Alerts ===");
+            if alerts.is_empty() {
+                println!("No threshold exceeded");
+            }
+            for alert in alerts {
+                println!("At {} s: {}", alert.elapsed_secs, alert);
+            }
+        }
+
+        if let Some(paths) = &results.path_race {
+            println!("\n=== Redundant Path Race ===");
+            for path in paths {
+                println!(
+                    "{}: {} wins (avg margin {:.2} ms, median {:.2} ms, max {:.2} ms), {} only-delivery",
+                    path.path,
+                    path.wins,
+                    path.avg_win_margin_ms,
+                    path.median_win_margin_ms,
+                    path.max_win_margin_ms,
+                    path.only_delivery
+                );
+            }
+        }
+
+        if let Some(endpoints) = &results.endpoints {
+            println!("\n=== Endpoint Ranking (by median latency) ===");
+            for endpoint in endpoints {
+                println!(
+                    "{:>2}. {:>8.2} ms median | {:>8.2} ms p99 | {:>7} samples | {}",
+                    endpoint.rank,
+                    endpoint.median_latency_ms,
+                    endpoint.p99_latency_ms,
+                    endpoint.sample_count,
+                    endpoint.endpoint
+                );
+            }
+        }
+
+        if let Some(markets) = &results.markets {
+            println!("\n=== Spot vs Futures ===");
+            for market in markets {
+                let exchange_delay = market
+                    .exchange_delay_median_ms
+                    .map(|ms| format!(" | E−T {:.2} ms", ms))
+                    .unwrap_or_default();
+                println!(
+                    "{:<7} | {:>8.2} ms median | {:>8.2} ms p99 | {:>7} samples{}",
+                    market.market,
+                    market.median_latency_ms,
+                    market.p99_latency_ms,
+                    market.sample_count,
+                    exchange_delay
+                );
+            }
+            if let [spot, futures] = markets.as_slice() {
+                println!(
+                    "Futures − spot median: {:+.2} ms",
+                    futures.median_latency_ms - spot.median_latency_ms
+                );
+            }
+        }
+
+        if let Some(symbols) = &results.symbols {
+            println!("\n=== Latency by Symbol ===");
+            for symbol in symbols {
+                let backbone = symbol
+                    .backbone_median_ms
+                    .map(|ms| format!(" | backbone {:.2} ms median", ms))
+                    .unwrap_or_default();
+                println!(
+                    "{:<12} | {:>8.2} ms median | {:>8.2} ms p99 | {:>7} samples{}",
+                    symbol.symbol,
+                    symbol.median_latency_ms,
+                    symbol.p99_latency_ms,
+                    symbol.sample_count,
+                    backbone
+                );
+            }
+        }
+
+        if let Some(sessions) = &results.sessions {
+            println!("\n=== Latency by Session (UTC) ===");
+            for session in sessions {
+                let level = |label: &str| session.percentiles.get(label).copied();
+                println!(
+                    "{:<8} {:02}-{:02} | {:>8} samples | {:>8.1} events/s | avg {:>8.2} ms | p50 {} | p99 {}",
+                    session.session,
+                    session.start_hour,
+                    session.end_hour,
+                    session.sample_count,
+                    session.events_per_sec,
+                    session.avg_latency_ms,
+                    level("p50").map_or("-".to_string(), |ms| format!("{:.2} ms", ms)),
+                    level("p99").map_or("-".to_string(), |ms| format!("{:.2} ms", ms)),
+                );
+            }
+        }
+
+        if let Some(classes) = &results.delivery_classes {
+            println!("\n=== Latency by Delivery Class ===");
+            for class in classes {
+                let mut levels: Vec<(&String, &f64)> = class.percentiles.iter().collect();
+                levels.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+                let levels: Vec<String> = levels
+                    .iter()
+                    .map(|(label, ms)| format!("{} {:.2}", label, ms))
+                    .collect();
+                println!(
+                    "{:<13} | {:>8} samples | avg {:>8.2} ms | max {:>9.2} ms | {}",
+                    class.class,
+                    class.sample_count,
+                    class.avg_latency_ms,
+                    class.max_latency_ms,
+                    levels.join(" ")
+                );
+            }
+        }
+
+        if let Some(stages) = &results.stage_budget {
+            println!("\n=== Latency Budget ({} events) ===", stages.events);
+            for (stage, summary) in [
+                ("Parse", &stages.parse),
+                ("Serialize", &stages.serialize),
+                ("Send", &stages.send),
+                ("Network", &stages.network),
+            ] {
+                println!(
+                    "{:<9} | median {:>8.3} ms | p99 {:>8.3} ms | max {:>8.3} ms",
+                    stage, summary.median_ms, summary.p99_ms, summary.max_ms
+                );
+            }
+        }
+
+        if let Some(forwarding) = &results.forwarding_overhead {
+            let overhead = &forwarding.overhead;
+            println!("\n=== Forwarding Overhead ({} events) ===", overhead.count);
+            println!(
+                "Forwarder processing: avg {:.3} ms | median {:.3} ms | p99 {:.3} ms | max {:.3} ms",
+                overhead.avg_ms, overhead.median_ms, overhead.p99_ms, overhead.max_ms
+            );
+            println!(
+                "Share of backbone latency: {:.1}% (backbone avg {:.2} ms)",
+                forwarding.share_pct, forwarding.backbone_avg_ms
+            );
+        }
+
+        if let Some(queue) = &results.send_queue {
+            let delay = &queue.delay;
+            println!("\n=== Forwarder Send Queue ({} events) ===", delay.count);
+            println!(
+                "Queue delay: avg {:.3} ms | median {:.3} ms | p99 {:.3} ms | max {:.3} ms",
+                delay.avg_ms, delay.median_ms, delay.p99_ms, delay.max_ms
+            );
+            println!(
+                "Share of backbone latency: {:.1}% (backbone avg {:.2} ms)",
+                queue.share_pct, queue.backbone_avg_ms
+            );
+        }
+
+        if let Some(buckets) = results.rate_buckets.as_ref().filter(|b| b.len() > 1) {
+            println!("\n=== Latency by Event Rate ===");
+            for bucket in buckets {
+                let range = match bucket.max_events_per_sec {
+                    Some(max) => format!("{}-{}/s", bucket.min_events_per_sec, max),
+                    None => format!("{}+/s", bucket.min_events_per_sec),
+                };
+                let mut levels: Vec<(&String, &f64)> = bucket.percentiles.iter().collect();
+                levels.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+                let levels: Vec<String> = levels
+                    .iter()
+                    .map(|(label, ms)| format!("{} {:.2}", label, ms))
+                    .collect();
+                println!(
+                    "{:>11} | {:>6} s | {:>8} samples | avg {:>8.2} ms | {}",
+                    range,
+                    bucket.seconds,
+                    bucket.sample_count,
+                    bucket.avg_latency_ms,
+                    levels.join(" ")
+                );
+            }
+        }
+
+        if let Some(sizes) = results
+            .size_buckets
+            .as_ref()
+            .filter(|sizes| sizes.frame.len() > 1 || sizes.packet.len() > 1)
+        {
+            println!("\n=== Latency by Message Size ===");
+            for (what, buckets) in [
+                ("Exchange frame (end-to-end)", &sizes.frame),
+                ("Forwarded packet (backbone)", &sizes.packet),
+            ] {
+                if buckets.is_empty() {
+                    continue;
+                }
+                println!("{}:", what);
+                for bucket in buckets {
+                    let range = match bucket.max_bytes {
+                        Some(max) => format!("{}-{} B", bucket.min_bytes, max - 1),
+                        None => format!("{}+ B", bucket.min_bytes),
+                    };
+                    let mut levels: Vec<(&String, &f64)> = bucket.percentiles.iter().collect();
+                    levels.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+                    let levels: Vec<String> = levels
+                        .iter()
+                        .map(|(label, ms)| format!("{} {:.3}", label, ms))
+                        .collect();
+                    println!(
+                        "{:>13} | {:>8} samples | avg {:>8.3} ms | {}",
+                        range,
+                        bucket.sample_count,
+                        bucket.avg_latency_ms,
+                        levels.join(" ")
+                    );
+                }
+            }
+        }
+
+        if let Some(queue) = &results.receive_queue {
+            println!("\n=== Receive Queue ===");
+            println!(
+                "Max depth: {} of {} | Full: {} times | Receive blocked: {:.2} ms",
+                queue.max_depth, queue.capacity, queue.full_pushes, queue.blocked_ms
+            );
+        }
+
+        if let Some(shards) = &results.shards {
+            println!("\n=== Receiver Shards ===");
+            for load in shards {
+                println!(
+                    "Shard {}: {} frames | {:.1}% CPU{} | Max queue depth: {} of {}",
+                    load.shard,
+                    load.frames,
+                    load.utilization_pct(),
+                    if load.cpu_ms.is_some() {
+                        ""
+                    } else {
+                        " (busy time)"
+                    },
+                    load.queue.max_depth,
+                    load.queue.capacity
+                );
+            }
+            println!(
+                "Busiest shard: {:.2}x an even share of frames",
+                shard_imbalance(shards)
+            );
+        }
+
+        if let Some(fragments) = &results.udp_fragments {
+            println!("\n=== UDP Fragmentation ===");
+            println!(
+                "Fragmented events: {} ({} reassembled, {} incomplete)",
+                fragments.frames, fragments.reassembled, fragments.incomplete
+            );
+            println!(
+                "Fragments: {} received, {} lost, {} duplicates",
+                fragments.fragments, fragments.lost_fragments, fragments.duplicate_fragments
+            );
+        }
+
+        if let Some(drops) = &results.kernel_udp_drops {
+            let count = |value: Option<u64>| value.map_or("n/a".to_string(), |v| v.to_string());
+            println!("\n=== Kernel UDP Drops ===");
+            println!(
+                "Receiver socket: {} dropped | Host-wide: {} receive buffer errors, {} receive errors",
+                count(drops.socket_drops),
+                count(drops.host_rcvbuf_errors),
+                count(drops.host_in_errors)
+            );
+            if let Some(bytes) = drops.recv_buffer_bytes {
+                println!("Receive buffer (SO_RCVBUF): {} bytes", bytes);
+            }
+        }
+
+        if let Some(framing) = &results.grpc_framing {
+            println!("\n=== gRPC Framing ===");
+            println!(
+                "Messages: {} | Payload: {} bytes | Framing: {} bytes ({:.1}% of payload)",
+                framing.messages,
+                framing.payload_bytes,
+                framing.framing_bytes,
+                framing.overhead_pct()
+            );
+            println!(
+                "Per message: {:.1} bytes average, {} min, {} max",
+                framing.avg_framing_bytes(),
+                framing.min_framing_bytes,
+                framing.max_framing_bytes
+            );
+        }
+
+        if let Some(streaming) = &results.streaming {
+            let run = &streaming.since_start;
+            println!(
+                "\n=== Whole Run (approximate, within {}%) ===",
+                streaming.relative_accuracy * 100.0
+            );
+            println!(
+                "Samples: {} | Average: {:.2} ms | Min: {:.2} ms | Max: {:.2} ms",
+                run.count, run.avg_ms, run.min_ms, run.max_ms
+            );
+            let mut percentiles: Vec<(&String, &f64)> = run.percentiles.iter().collect();
+            percentiles.sort_by(|a, b| label_value(a.0).total_cmp(&label_value(b.0)));
+            for (label, value) in percentiles {
+                println!("{} latency: {:.2} ms", label.to_uppercase(), value);
+            }
+        }
+    }
+}
+
+/// Numeric level of a percentile label such as "p99.9"
+pub(crate) fn label_value(label: &str) -> f64 {
+    label
+        .trim_start_matches('p')
+        .parse()
+        .unwrap_or(f64::INFINITY)
+}
+
+/// One line per exchange connection, noting where the address changed
+fn print_exchange_addresses(addresses: &[ExchangeAddress]) {
+    let Some(first) = addresses.first() else {
+        return;
+    };
+    for (i, address) in addresses.iter().enumerate() {
+        let mut line = format!(
+            "Exchange connection at +{:.1} s",
+            (address.connected_at - first.connected_at) as f64 / 1e9
+        );
+        if let Some(endpoint) = address.endpoint {
+            line += &format!(" (endpoint {})", endpoint);
+        }
+        line += &format!(": {}:{}", address.ip, address.port);
+        let names: Vec<&str> = [&address.reverse_dns, &address.aws_region]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if !names.is_empty() {
+            line += &format!(" ({})", names.join(", "));
+        }
+        let previous = addresses[..i]
+            .iter()
+            .rev()
+            .find(|previous| previous.endpoint == address.endpoint);
+        if previous.is_some_and(|previous| previous.ip != address.ip) {
+            line += " [address changed]";
+        }
+        println!("{}", line);
+    }
+}
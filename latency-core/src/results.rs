@@ -0,0 +1,501 @@
+// Aggregate experiment results
+
+use crate::alerts::Alert;
+use crate::bootstrap::BootstrapIntervals;
+use crate::bursts::{burst_stats, BurstStats};
+use crate::clock::OneWayDelay;
+use crate::delivery::{delivery_class_stats, DeliveryClass, DeliveryClassStats};
+use crate::digest::StreamingPercentiles;
+use crate::endpoints::EndpointStats;
+use crate::exchange_address::ExchangeAddress;
+use crate::fragments::FragmentStats;
+use crate::framing::FramingStats;
+use crate::gaps::SequenceGap;
+use crate::handover::ConnectionHandover;
+use crate::kernel_drops::KernelDropStats;
+use crate::market::{compare_markets, MarketStats};
+use crate::measurement::LatencyMeasurement;
+use crate::metadata::RunMetadata;
+use crate::path_race::PathWinStats;
+use crate::payload::PayloadCheckStats;
+use crate::ping::PingRttStats;
+use crate::quantization::QuantizedLatency;
+use crate::queue::ReceiveQueueStats;
+use crate::rate::{rate_buckets, RateBucket};
+use crate::sampling::SamplingStats;
+use crate::server_time::ServerTimeStats;
+use crate::session::SessionStats;
+use crate::shards::ShardLoad;
+use crate::size::{size_buckets, SizeBuckets};
+use crate::sources::SourceStats;
+use crate::spikes::Spike;
+use crate::stages::{send_queue_delay, ForwardingOverhead, SendQueueDelay, StageBreakdown};
+use crate::stale::StaleStats;
+use crate::stats::{LatencySummary, StatsAggregator, DEFAULT_PERCENTILES};
+use crate::subscriptions::SubscriptionChange;
+use crate::symbols::SymbolStats;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Version of the results JSON layout written by this build.
+///
+/// 1. Original layout, without a `schema_version` field
+/// 2. Adds `schema_version`, warm-up/reconnect/outage counters, the
+///    `percentiles` map, `gaps`, and the optional analysis sections
+/// 3. Adds `duplicates`, `reordered` and `max_reorder_distance`
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Results of a latency experiment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentResults {
+    pub schema_version: u32,
+    pub setup_type: String, // "baseline" or "aws-backbone"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>, // Receiver region label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<String>, // Venue measured directly (baseline mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>, // Forwarder run measured (aws-backbone), or the receiver's own
+    pub sample_count: usize,
+    pub warmup_samples: usize, // Collected during warm-up, excluded from statistics
+    pub events_lost: usize,    // Missing sequence IDs
+    pub duplicates: usize,     // Sequence IDs received more than once
+    pub reordered: usize,      // Arrivals with a lower sequence ID than one already received
+    pub max_reorder_distance: u64, // Largest gap between such an arrival and the highest ID
+    pub reconnects: usize,     // Upstream reconnections during the run
+    pub outage_ms: f64,        // Total time spent reconnecting
+
+    // End-to-end latency (Binance → receiver)
+    pub avg_latency_ms: f64,
+    pub median_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub min_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>, // Configured percentiles, keyed "p50", "p99.9", ...
+
+    // Jitter (variance in latency)
+    pub jitter_stddev_ms: f64,
+
+    // Runs with --bootstrap: 95% confidence intervals of the median, p95 and p99
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence_intervals: Option<BootstrapIntervals>,
+
+    // AWS backbone specific (Tokyo → receiver)
+    pub backbone_avg_latency_ms: Option<f64>,
+    pub backbone_median_latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backbone_percentiles_us: Option<BTreeMap<String, f64>>, // Microseconds, keyed like `percentiles`
+
+    // Exchange-internal delay (event time − transaction time), when the feed has both
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_delay_avg_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_delay_median_ms: Option<f64>,
+
+    // Kernel (SO_TIMESTAMPING) to userspace timestamp delay, when captured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel_to_user_delay: Option<LatencySummary>,
+
+    // Sequence gaps as they were detected; late arrivals that fill a gap are not subtracted
+    pub gaps: Vec<SequenceGap>,
+
+    // Latency spikes, when spike detection is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spikes: Option<Vec<Spike>>,
+
+    // Every spike detected; `spikes` keeps the most recent MAX_SPIKES
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spike_count: Option<u64>,
+
+    // Runs with --alert-p99-ms or --alert-loss-pct: every alarm that fired or resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<Vec<Alert>>,
+
+    // Redundant-path runs only: which path delivered each event first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_race: Option<Vec<PathWinStats>>,
+
+    // Forwarder stage timestamps, when the forwarder was run with --stage-timestamps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage_budget: Option<StageBreakdown>,
+
+    // Baseline runs only: exchange connections replaced around the 24-hour limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_handovers: Option<Vec<ConnectionHandover>>,
+
+    // Baseline runs only: the address every exchange connection landed on, in connection order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_addresses: Option<Vec<ExchangeAddress>>,
+
+    // Baseline runs with --subscribe: SUBSCRIBE/UNSUBSCRIBE requests and their acknowledgement times
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscriptions: Option<Vec<SubscriptionChange>>,
+
+    // Runs with --clock-sync on both hosts: backbone latency corrected for both clock offsets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backbone_one_way: Option<OneWayDelay>,
+
+    // Runs with --quantization: latency corrected for the event time's resolution, with its bound
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<QuantizedLatency>,
+
+    // Forwarder processing time, from forwarders that report it with every event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forwarding_overhead: Option<ForwardingOverhead>,
+
+    // Forwarders with --send-queue: time events waited to be sent, so backbone
+    // spikes can be told apart from queuing in Tokyo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub send_queue: Option<SendQueueDelay>,
+
+    // Multi-endpoint baseline runs only: endpoints ranked by median latency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Vec<EndpointStats>>,
+
+    // Backpressure between the receiver's receive tasks and event processing
+    // (with --shards, between the decode shards and event processing)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receive_queue: Option<ReceiveQueueStats>,
+
+    // Receivers run with --shards: frames, CPU time and queue use of each decode shard
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shards: Option<Vec<ShardLoad>>,
+
+    // UDP runs that received events split into fragments: reassembly and fragment loss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub udp_fragments: Option<FragmentStats>,
+
+    // UDP runs: datagrams the kernel dropped before the receiver read them (Linux)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel_udp_drops: Option<KernelDropStats>,
+
+    // gRPC runs: bytes of protobuf, gRPC and HTTP/2 framing around each event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grpc_framing: Option<FramingStats>,
+
+    // Events the forwarder held back during a receiver outage and sent after reconnecting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buffered_events: Option<usize>,
+
+    // Events from a forwarder of another run (a stale process), rejected rather than measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreign_run_events: Option<usize>,
+
+    // Events from a forwarder whose handshake announced a wire format this receiver cannot read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incompatible_events: Option<usize>,
+
+    // DSCP marking the forwarder set on its sockets (--dscp), as it reported it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dscp: Option<u8>,
+
+    // Whether permessage-deflate was negotiated with the exchange (--ws-compression):
+    // the receiver's own connection in baseline runs, the forwarder's in aws-backbone runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ws_compression: Option<bool>,
+
+    // Runs with --verify-payload: forwarded payloads that disagree with their envelope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_check: Option<PayloadCheckStats>,
+
+    // Runs with replayed events: latency per delivery class, so live latency
+    // can be read without the stale deliveries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delivery_classes: Option<Vec<DeliveryClassStats>>,
+
+    // Baseline runs: WebSocket ping round trips to the exchange server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_ping_rtt: Option<PingRttStats>,
+
+    // AWS backbone runs with --path-probe: network RTT to the forwarder host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_rtt: Option<PingRttStats>,
+
+    // Runs with --server-time: the exchange's clock against the receiver's, from its REST time endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_time: Option<ServerTimeStats>,
+
+    // Runs that collected both Binance spot and futures streams: latency per market
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub markets: Option<Vec<MarketStats>>,
+
+    // Runs with --split-by-symbol: latency per symbol, busiest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<Vec<SymbolStats>>,
+
+    // End-to-end latency by the event rate of the second each event arrived in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_buckets: Option<Vec<RateBucket>>,
+
+    // Latency by exchange frame size and forwarded packet size, for MTU and
+    // fragmentation effects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_buckets: Option<SizeBuckets>,
+
+    // Events older than --max-staleness-ms on arrival, left out of the
+    // latency statistics above
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale: Option<StaleStats>,
+
+    // Runs with --sample-rate: the statistics above cover only the events
+    // kept; counters, per-second stats and `streaming` cover them all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingStats>,
+
+    // Autocorrelation of consecutive end-to-end latencies and runs above the
+    // median: isolated spikes or sustained bursts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bursts: Option<BurstStats>,
+
+    // Several forwarders sending to one receiver (--multi-forwarder): latency
+    // and sequence accounting per forwarder; the rest of the results combine them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<SourceStats>>,
+
+    // Runs of an hour or more, or with --sessions windows: latency per UTC session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<SessionStats>>,
+
+    // Continuous runs: approximate end-to-end percentiles over the whole run,
+    // not only the sliding window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<StreamingPercentiles>,
+
+    // Host, instance and clock state the run was recorded with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+}
+
+impl ExperimentResults {
+    /// Calculate statistics from a set of latency measurements.
+    /// Measurements flagged as warm-up are counted but otherwise ignored.
+    pub fn from_measurements(
+        setup_type: String,
+        measurements: &[LatencyMeasurement],
+        events_lost: usize,
+    ) -> Self {
+        Self::with_percentiles(setup_type, measurements, events_lost, DEFAULT_PERCENTILES)
+    }
+
+    /// Like `from_measurements`, reporting end-to-end latency at `percentiles`
+    /// (in percent, e.g. 99.9)
+    pub fn with_percentiles(
+        setup_type: String,
+        measurements: &[LatencyMeasurement],
+        events_lost: usize,
+        percentiles: &[f64],
+    ) -> Self {
+        let warmup_samples = measurements.iter().filter(|m| m.warmup).count();
+        let rate_buckets =
+            (warmup_samples < measurements.len()).then(|| rate_buckets(measurements, percentiles));
+        let size_buckets = size_buckets(measurements, percentiles);
+        let send_queue = send_queue_delay(measurements);
+        let markets = Some(compare_markets(measurements)).filter(|markets| markets.len() > 1);
+        let delivery_classes = measurements
+            .iter()
+            .any(|m| !m.warmup && m.delivery_class != DeliveryClass::Live)
+            .then(|| delivery_class_stats(measurements, percentiles));
+        let measurements: Vec<&LatencyMeasurement> =
+            measurements.iter().filter(|m| !m.warmup).collect();
+
+        let end_to_end: StatsAggregator = measurements
+            .iter()
+            .map(|m| m.end_to_end_latency_ms())
+            .collect();
+        let summary = end_to_end.summary();
+        let bursts = burst_stats(
+            &measurements
+                .iter()
+                .map(|m| m.end_to_end_latency_ms())
+                .collect::<Vec<_>>(),
+        );
+        let levels = percentiles;
+        let percentiles = end_to_end.percentiles(levels);
+
+        // Calculate backbone statistics if available
+        let backbone: StatsAggregator = measurements
+            .iter()
+            .filter_map(|m| m.backbone_latency_ms())
+            .collect();
+        let backbone_percentiles_us = (!backbone.is_empty()).then(|| {
+            measurements
+                .iter()
+                .filter_map(|m| m.backbone_latency_ns)
+                .map(|ns| ns as f64 / 1_000.0)
+                .collect::<StatsAggregator>()
+                .percentiles(levels)
+        });
+
+        let (backbone_avg_latency_ms, backbone_median_latency_ms) = if !backbone.is_empty() {
+            let backbone_summary = backbone.summary();
+            (
+                Some(backbone_summary.avg_ms),
+                Some(backbone_summary.median_ms),
+            )
+        } else {
+            (None, None)
+        };
+
+        let exchange_delay: StatsAggregator = measurements
+            .iter()
+            .filter_map(|m| m.exchange_delay_ms())
+            .collect();
+        let (exchange_delay_avg_ms, exchange_delay_median_ms) = if !exchange_delay.is_empty() {
+            let exchange_delay_summary = exchange_delay.summary();
+            (
+                Some(exchange_delay_summary.avg_ms),
+                Some(exchange_delay_summary.median_ms),
+            )
+        } else {
+            (None, None)
+        };
+
+        let kernel_to_user: StatsAggregator = measurements
+            .iter()
+            .filter_map(|m| m.kernel_to_user_ms())
+            .collect();
+        let kernel_to_user_delay = (!kernel_to_user.is_empty()).then(|| kernel_to_user.summary());
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            setup_type,
+            region: None,
+            exchange: None,
+            run_id: None,
+            sample_count: summary.count,
+            warmup_samples,
+            events_lost,
+            duplicates: 0,
+            reordered: 0,
+            max_reorder_distance: 0,
+            reconnects: 0,
+            outage_ms: 0.0,
+            avg_latency_ms: summary.avg_ms,
+            median_latency_ms: summary.median_ms,
+            p95_latency_ms: summary.p95_ms,
+            p99_latency_ms: summary.p99_ms,
+            min_latency_ms: summary.min_ms,
+            max_latency_ms: summary.max_ms,
+            percentiles,
+            jitter_stddev_ms: summary.stddev_ms,
+            confidence_intervals: None,
+            backbone_avg_latency_ms,
+            backbone_median_latency_ms,
+            backbone_percentiles_us,
+            exchange_delay_avg_ms,
+            exchange_delay_median_ms,
+            kernel_to_user_delay,
+            gaps: Vec::new(),
+            spikes: None,
+            spike_count: None,
+            alerts: None,
+            path_race: None,
+            stage_budget: None,
+            exchange_handovers: None,
+            exchange_addresses: None,
+            subscriptions: None,
+            backbone_one_way: None,
+            quantization: None,
+            forwarding_overhead: None,
+            send_queue,
+            endpoints: None,
+            receive_queue: None,
+            shards: None,
+            udp_fragments: None,
+            kernel_udp_drops: None,
+            grpc_framing: None,
+            buffered_events: None,
+            foreign_run_events: None,
+            incompatible_events: None,
+            payload_check: None,
+            delivery_classes,
+            exchange_ping_rtt: None,
+            path_rtt: None,
+            server_time: None,
+            markets,
+            symbols: None,
+            rate_buckets,
+            size_buckets,
+            stale: None,
+            sampling: None,
+            bursts,
+            sources: None,
+            sessions: None,
+            streaming: None,
+            dscp: None,
+            ws_compression: None,
+            metadata: None,
+        }
+    }
+
+    /// Parse results JSON written by this or any earlier version, migrating
+    /// older layouts to the current one
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let mut value: Value = serde_json::from_str(json)?;
+        migrate(&mut value)?;
+        serde_json::from_value(value)
+    }
+
+    /// Read a results file written by this or any earlier version
+    pub fn load(filepath: &str) -> Result<Self, std::io::Error> {
+        let json = std::fs::read_to_string(filepath)?;
+        Ok(Self::from_json(&json)?)
+    }
+}
+
+/// Upgrade a results document in place to `SCHEMA_VERSION`
+fn migrate(value: &mut Value) -> Result<(), serde_json::Error> {
+    use serde::de::Error;
+
+    let Some(fields) = value.as_object_mut() else {
+        return Err(Error::custom("results must be a JSON object"));
+    };
+    let version = match fields.get("schema_version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| Error::custom("schema_version must be a positive integer"))?,
+    };
+    if version > u64::from(SCHEMA_VERSION) {
+        return Err(Error::custom(format!(
+            "results schema_version {} is newer than this build supports ({})",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    if version < 2 {
+        migrate_v1(fields);
+    }
+    if version < 3 {
+        // Duplicates and reordering were not tracked
+        for counter in ["duplicates", "reordered", "max_reorder_distance"] {
+            fields.entry(counter).or_insert(0.into());
+        }
+    }
+    fields.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+    Ok(())
+}
+
+/// Version 1 predates warm-up, reconnect and gap tracking and the percentile
+/// map; the map is rebuilt from the fixed median/p95/p99 fields
+fn migrate_v1(fields: &mut Map<String, Value>) {
+    for counter in ["warmup_samples", "reconnects"] {
+        fields.entry(counter).or_insert(0.into());
+    }
+    fields.entry("outage_ms").or_insert(0.0.into());
+    fields.entry("gaps").or_insert(Value::Array(Vec::new()));
+
+    if !fields.contains_key("percentiles") {
+        let percentiles: Map<String, Value> = [
+            ("p50", "median_latency_ms"),
+            ("p95", "p95_latency_ms"),
+            ("p99", "p99_latency_ms"),
+        ]
+        .into_iter()
+        .filter_map(|(label, field)| Some((label.to_string(), fields.get(field)?.clone())))
+        .collect();
+        fields.insert("percentiles".to_string(), Value::Object(percentiles));
+    }
+}
@@ -0,0 +1,86 @@
+// Event sampling for long runs (--sample-rate)
+//
+// A week of book ticker events is billions of measurements, more than the
+// receiver can hold or the raw CSV should grow to. With a sample rate, only a
+// fraction of the events is kept for the raw output and the exact statistics,
+// after timestamping so the kept ones are measured no differently. Sequence
+// tracking, the per-second stats, spikes, alerts and the whole-run sketches
+// still see every event. Which events are kept depends only on the sequence
+// ID, so the sample is independent of latency and every path or receiver
+// measuring the same events keeps the same ones.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Fraction of events kept, from 0 (exclusive) to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleRate(f64);
+
+impl SampleRate {
+    pub fn new(rate: f64) -> Result<Self, String> {
+        if rate > 0.0 && rate <= 1.0 {
+            Ok(Self(rate))
+        } else {
+            Err(format!(
+                "sample rate must be above 0 and at most 1: {}",
+                rate
+            ))
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.0
+    }
+
+    /// Whether the event numbered `sequence_id` is kept
+    pub fn keeps(&self, sequence_id: u64) -> bool {
+        // Sequence IDs are consecutive, so hash them rather than take every Nth
+        // and risk beating with a periodic pattern in the stream
+        self.0 >= 1.0 || (mix(sequence_id) as f64) < self.0 * u64::MAX as f64
+    }
+}
+
+impl FromStr for SampleRate {
+    type Err = String;
+
+    /// A fraction like `0.1`, or one in N like `1/10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid sample rate: {} (expected e.g. 0.1 or 1/10)", s);
+        let rate = match s.split_once('/') {
+            Some((one, n)) => {
+                let one: f64 = one.trim().parse().map_err(|_| invalid())?;
+                let n: f64 = n.trim().parse().map_err(|_| invalid())?;
+                one / n
+            }
+            None => s.trim().parse().map_err(|_| invalid())?,
+        };
+        Self::new(rate)
+    }
+}
+
+/// How many events a sampled run kept
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SamplingStats {
+    pub sample_rate: f64,
+    pub events: u64, // Recorded, warm-up included; stale events are counted separately
+    pub retained: u64, // Kept for the raw output and the exact statistics
+}
+
+impl SamplingStats {
+    /// Share of the events kept, which should be close to the sample rate
+    pub fn retained_pct(&self) -> f64 {
+        if self.events == 0 {
+            0.0
+        } else {
+            self.retained as f64 / self.events as f64 * 100.0
+        }
+    }
+}
+
+/// SplitMix64's finalizer
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,149 @@
+// The exchange's clock, read from its REST time endpoint (--server-time)
+//
+// End-to-end latency subtracts the exchange's event time (E) from the
+// receiver's clock, so it is off by however far the two clocks disagree.
+// Binance publishes its clock at /api/v3/time. Asking it periodically and
+// taking the answer as stamped halfway through the request gives the offset
+// serverTime − localTime − RTT/2, good to half the round trip plus the
+// millisecond serverTime is truncated to. The offset sanity-checks the
+// measured latencies and, with --correct-server-time, corrects them.
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+
+/// One answer from the time endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ServerTimeSample {
+    pub time: i64,      // Epoch nanos the request was sent
+    pub offset_ms: f64, // Exchange clock minus ours; positive when the exchange is ahead
+    pub rtt_ms: f64,
+}
+
+impl ServerTimeSample {
+    /// From the local times the request was sent and answered (epoch nanos)
+    /// and the exchange's serverTime (epoch millis)
+    pub fn new(sent: i64, server_time_ms: i64, received: i64) -> Self {
+        // serverTime is truncated, so the exchange's clock read up to 1 ms more
+        let server_time = server_time_ms * 1_000_000 + 500_000;
+        let rtt = received - sent;
+        Self {
+            time: sent,
+            offset_ms: nanos_to_ms(server_time - sent - rtt / 2),
+            rtt_ms: nanos_to_ms(rtt),
+        }
+    }
+
+    pub fn offset_ns(&self) -> i64 {
+        (self.offset_ms * 1_000_000.0).round() as i64
+    }
+
+    /// The true offset is within this of `offset_ms`
+    pub fn error_bound_ms(&self) -> f64 {
+        self.rtt_ms / 2.0 + 0.5
+    }
+}
+
+/// The exchange's clock against the receiver's over a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerTimeStats {
+    pub url: String,
+    pub samples: Vec<ServerTimeSample>,
+    pub failures: u64, // Requests that failed, timed out or had no serverTime
+    pub avg_offset_ms: f64,
+    pub min_offset_ms: f64,
+    pub max_offset_ms: f64,
+    pub avg_error_bound_ms: f64, // ± on each sample's offset, on average
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrected: Option<LatencySummary>, // End-to-end latency with E moved onto the receiver's clock
+    pub uncorrected_events: u64, // Received before the first answer, or while the endpoint failed
+    pub negative_latencies: u64, // Measured end-to-end latencies below zero
+    pub negative_corrected: u64, // The same after correction; the offset cannot explain these
+    pub applied: bool, // The headline latencies are the corrected ones (--correct-server-time)
+}
+
+impl ServerTimeStats {
+    /// Whether the clocks disagree by more than the offset's error bound, so
+    /// the measured end-to-end latencies are off by about `avg_offset_ms`
+    pub fn skewed(&self) -> bool {
+        self.avg_offset_ms.abs() > self.avg_error_bound_ms
+    }
+}
+
+/// Corrects end-to-end latencies by the latest offset and counts the ones no
+/// clock could have produced
+#[derive(Debug, Default)]
+pub struct ServerTimeCorrection {
+    apply: bool,
+    corrected: StatsAggregator,
+    uncorrected: u64,
+    negative: u64,
+    negative_corrected: u64,
+}
+
+impl ServerTimeCorrection {
+    /// With `apply`, measurements are corrected in place; otherwise the
+    /// corrected latency is only kept for the comparison
+    pub fn new(apply: bool) -> Self {
+        Self {
+            apply,
+            ..Self::default()
+        }
+    }
+
+    /// Record one measurement before it is collected, with the latest sample
+    pub fn record(
+        &mut self,
+        measurement: &mut LatencyMeasurement,
+        sample: Option<ServerTimeSample>,
+    ) {
+        let measured = measurement.end_to_end_latency_ns;
+        if measured < 0 {
+            self.negative += 1;
+        }
+        let Some(sample) = sample else {
+            self.uncorrected += 1;
+            return;
+        };
+        let corrected = measured + sample.offset_ns();
+        if corrected < 0 {
+            self.negative_corrected += 1;
+        }
+        self.corrected.push(nanos_to_ms(corrected));
+        if self.apply {
+            measurement.end_to_end_latency_ns = corrected;
+        }
+    }
+
+    /// `None` if the endpoint never answered
+    pub fn results(
+        &self,
+        url: &str,
+        samples: Vec<ServerTimeSample>,
+        failures: u64,
+    ) -> Option<ServerTimeStats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let count = samples.len() as f64;
+        let offsets = samples.iter().map(|sample| sample.offset_ms);
+        Some(ServerTimeStats {
+            url: url.to_string(),
+            failures,
+            avg_offset_ms: offsets.clone().sum::<f64>() / count,
+            min_offset_ms: offsets.clone().fold(f64::INFINITY, f64::min),
+            max_offset_ms: offsets.fold(f64::NEG_INFINITY, f64::max),
+            avg_error_bound_ms: samples.iter().map(|s| s.error_bound_ms()).sum::<f64>() / count,
+            corrected: (!self.corrected.is_empty()).then(|| self.corrected.summary()),
+            uncorrected_events: self.uncorrected,
+            negative_latencies: self.negative,
+            negative_corrected: self.negative_corrected,
+            applied: self.apply,
+            samples,
+        })
+    }
+}
+
+fn nanos_to_ms(nanos: i64) -> f64 {
+    nanos as f64 / 1_000_000.0
+}
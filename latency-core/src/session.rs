@@ -0,0 +1,213 @@
+// Latency by trading session: Asia, Europe and US hours behave differently
+//
+// Message rates and latency change with the hours the big markets are open,
+// so a multi-hour run summarized as one distribution hides the difference.
+// Measurements are bucketed by the UTC time they were received, either per
+// hour of the day or into named session windows, which may overlap.
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+const NANOS_PER_HOUR: i64 = 3_600_000_000_000;
+const NANOS_PER_DAY: i64 = 24 * NANOS_PER_HOUR;
+
+/// Hourly buckets are only reported for runs at least this long
+pub const MIN_HOURLY_SPAN_NANOS: i64 = NANOS_PER_HOUR;
+
+/// Named windows used by `--sessions markets`, approximating local trading
+/// hours in UTC (Tokyo/Hong Kong, London/Frankfurt, New York)
+pub const MARKET_SESSIONS: &[(&str, u32, u32)] =
+    &[("asia", 0, 9), ("europe", 7, 16), ("us", 13, 21)];
+
+/// A daily window of UTC hours, `start_hour` (0-23) inclusive to `end_hour`
+/// (1-24) exclusive. Windows with `end_hour` < `start_hour` wrap past midnight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionWindow {
+    pub name: String,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl SessionWindow {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+
+    /// The window as ranges of nanoseconds within a day
+    fn day_ranges(&self) -> Vec<(i64, i64)> {
+        let at = |hour: u32| hour as i64 * NANOS_PER_HOUR;
+        if self.start_hour < self.end_hour {
+            vec![(at(self.start_hour), at(self.end_hour))]
+        } else {
+            vec![(at(self.start_hour), NANOS_PER_DAY), (0, at(self.end_hour))]
+        }
+    }
+
+    /// Nanoseconds of [`start`, `end`] (epoch nanos) that fall in this window
+    fn overlap_nanos(&self, start: i64, end: i64) -> i64 {
+        let mut overlap = 0;
+        for day in start.div_euclid(NANOS_PER_DAY)..=end.div_euclid(NANOS_PER_DAY) {
+            let midnight = day * NANOS_PER_DAY;
+            for (from, to) in self.day_ranges() {
+                overlap += (end.min(midnight + to) - start.max(midnight + from)).max(0);
+            }
+        }
+        overlap
+    }
+}
+
+/// How measurements are split into sessions (--sessions)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionSplit {
+    Hourly,                      // One bucket per UTC hour of the day
+    Windows(Vec<SessionWindow>), // Named windows
+}
+
+impl SessionSplit {
+    /// The `markets` preset: Asia, Europe and US hours
+    pub fn markets() -> Self {
+        SessionSplit::Windows(
+            MARKET_SESSIONS
+                .iter()
+                .map(|&(name, start_hour, end_hour)| SessionWindow {
+                    name: name.to_string(),
+                    start_hour,
+                    end_hour,
+                })
+                .collect(),
+        )
+    }
+
+    fn windows(&self) -> Vec<SessionWindow> {
+        match self {
+            SessionSplit::Hourly => (0..24)
+                .map(|hour| SessionWindow {
+                    name: format!("{:02}:00", hour),
+                    start_hour: hour,
+                    end_hour: hour + 1,
+                })
+                .collect(),
+            SessionSplit::Windows(windows) => windows.clone(),
+        }
+    }
+}
+
+impl FromStr for SessionSplit {
+    type Err = String;
+
+    /// `hourly`, `markets`, or windows like `asia=0-9,europe=7-16,us=13-21`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hourly" => return Ok(SessionSplit::Hourly),
+            "markets" => return Ok(SessionSplit::markets()),
+            _ => {}
+        }
+
+        let mut windows = Vec::new();
+        for window in s.split(',') {
+            let invalid = || {
+                format!(
+                    "invalid session window: {} (expected NAME=START-END)",
+                    window
+                )
+            };
+            let (name, hours) = window.split_once('=').ok_or_else(invalid)?;
+            let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+            let hour =
+                |value: &str, max: u32| value.trim().parse::<u32>().ok().filter(|&h| h <= max);
+            let (Some(start_hour), Some(end_hour)) = (hour(start, 23), hour(end, 24)) else {
+                return Err(format!(
+                    "session hours must be between 0 and 24: {}",
+                    window
+                ));
+            };
+            let name = name.trim();
+            if name.is_empty() || start_hour == end_hour {
+                return Err(invalid());
+            }
+            if windows.iter().any(|w: &SessionWindow| w.name == name) {
+                return Err(format!("duplicate session name: {}", name));
+            }
+            windows.push(SessionWindow {
+                name: name.to_string(),
+                start_hour,
+                end_hour,
+            });
+        }
+        Ok(SessionSplit::Windows(windows))
+    }
+}
+
+/// Latency of the measurements received during one session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub session: String,
+    pub start_hour: u32,   // UTC, inclusive
+    pub end_hour: u32,     // UTC, exclusive; wraps past midnight when below `start_hour`
+    pub covered_secs: f64, // Time of the run that fell in this session
+    pub sample_count: usize,
+    pub events_per_sec: f64, // Over `covered_secs`
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>,
+    pub backbone_avg_latency_ms: Option<f64>,
+}
+
+/// Summarize end-to-end latency per session at `percentiles`, by receive time.
+/// Warm-up is left out, and so are sessions without samples. Returns nothing
+/// for hourly buckets when the run is shorter than an hour.
+pub fn session_stats(
+    measurements: &[LatencyMeasurement],
+    split: &SessionSplit,
+    percentiles: &[f64],
+) -> Vec<SessionStats> {
+    let measured = || measurements.iter().filter(|m| !m.warmup);
+    let (Some(start), Some(end)) = (
+        measured().map(|m| m.frankfurt_receive_time).min(),
+        measured().map(|m| m.frankfurt_receive_time).max(),
+    ) else {
+        return Vec::new();
+    };
+    if *split == SessionSplit::Hourly && end - start < MIN_HOURLY_SPAN_NANOS {
+        return Vec::new();
+    }
+
+    let hour_of = |m: &LatencyMeasurement| {
+        (m.frankfurt_receive_time.rem_euclid(NANOS_PER_DAY) / NANOS_PER_HOUR) as u32
+    };
+    split
+        .windows()
+        .into_iter()
+        .filter_map(|window| {
+            let samples = || measured().filter(|m| window.contains(hour_of(m)));
+            let latency: StatsAggregator = samples().map(|m| m.end_to_end_latency_ms()).collect();
+            if latency.is_empty() {
+                return None;
+            }
+            let backbone: StatsAggregator =
+                samples().filter_map(|m| m.backbone_latency_ms()).collect();
+            let covered_secs = window.overlap_nanos(start, end) as f64 / 1e9;
+
+            let summary = latency.summary();
+            Some(SessionStats {
+                session: window.name.clone(),
+                start_hour: window.start_hour,
+                end_hour: window.end_hour,
+                covered_secs,
+                sample_count: summary.count,
+                events_per_sec: summary.count as f64 / covered_secs.max(1.0),
+                avg_latency_ms: summary.avg_ms,
+                max_latency_ms: summary.max_ms,
+                percentiles: latency.percentiles(percentiles),
+                backbone_avg_latency_ms: (!backbone.is_empty()).then(|| backbone.mean()),
+            })
+        })
+        .collect()
+}
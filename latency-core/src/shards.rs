@@ -0,0 +1,42 @@
+// Load on each decode shard of a sharded receiver (--shards)
+//
+// With several shards, frames are reassembled and parsed on one thread per
+// shard, and the processing loop only records what they decoded. Each shard's
+// share of the frames shows how evenly the streams spread; its thread CPU time
+// over its lifetime shows how close it came to saturating a core.
+
+use crate::queue::ReceiveQueueStats;
+use serde::{Deserialize, Serialize};
+
+/// What one shard decoded and what it cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardLoad {
+    pub shard: usize,
+    pub frames: u64,              // Frames taken from the shard's queue
+    pub bytes: u64,               // Their size
+    pub busy_ms: f64,             // Wall time spent decoding them
+    pub cpu_ms: Option<f64>,      // Thread CPU time, where the OS reports it (Linux)
+    pub wall_ms: f64,             // Lifetime of the shard's thread
+    pub queue: ReceiveQueueStats, // Between the receive tasks and the shard
+}
+
+impl ShardLoad {
+    /// Share of the shard's lifetime spent on its core: CPU time where it is
+    /// known, else the time spent decoding
+    pub fn utilization_pct(&self) -> f64 {
+        if self.wall_ms <= 0.0 {
+            return 0.0;
+        }
+        self.cpu_ms.unwrap_or(self.busy_ms) / self.wall_ms * 100.0
+    }
+}
+
+/// Frames on the busiest shard relative to an even split (1.0 when balanced)
+pub fn shard_imbalance(loads: &[ShardLoad]) -> f64 {
+    let total: u64 = loads.iter().map(|load| load.frames).sum();
+    let busiest = loads.iter().map(|load| load.frames).max().unwrap_or(0);
+    if total == 0 {
+        return 1.0;
+    }
+    busiest as f64 * loads.len() as f64 / total as f64
+}
@@ -0,0 +1,78 @@
+// Latency by message size: do large frames or packets take longer?
+//
+// The exchange frame is bucketed against end-to-end latency, the forwarded
+// packet against backbone latency, so an MTU or fragmentation effect on the
+// backbone shows up separately from serialization cost at the exchange.
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Lower bounds of the size buckets (bytes). 1472 bytes is the largest UDP
+/// payload that fits a 1500-byte MTU, 8973 bytes the largest that fits the
+/// 9001-byte jumbo frames within a VPC.
+pub const SIZE_BUCKET_BOUNDS: &[u32] = &[0, 256, 512, 1024, 1473, 4096, 8974];
+
+/// Latency of measurements whose message size fell in one range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeBucket {
+    pub min_bytes: u32,
+    pub max_bytes: Option<u32>, // Exclusive; `None` for the top bucket
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>,
+}
+
+/// Latency by exchange frame size and by forwarded packet size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeBuckets {
+    pub frame: Vec<SizeBucket>, // End-to-end latency by raw exchange frame size
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packet: Vec<SizeBucket>, // Backbone latency by forwarded event size (AWS backbone only)
+}
+
+/// Bucket measurements by the size of their exchange frame and forwarded
+/// packet. Warm-up measurements and those without sizes are left out, as are
+/// empty buckets; `None` if no measurement has a size.
+pub fn size_buckets(
+    measurements: &[LatencyMeasurement],
+    percentiles: &[f64],
+) -> Option<SizeBuckets> {
+    let measurements: Vec<&LatencyMeasurement> =
+        measurements.iter().filter(|m| !m.warmup).collect();
+    let frame = buckets(
+        measurements
+            .iter()
+            .filter_map(|m| Some((m.frame_bytes?, m.end_to_end_latency_ms()))),
+        percentiles,
+    );
+    let packet = buckets(
+        measurements
+            .iter()
+            .filter_map(|m| Some((m.packet_bytes?, m.backbone_latency_ms()?))),
+        percentiles,
+    );
+    (!frame.is_empty() || !packet.is_empty()).then_some(SizeBuckets { frame, packet })
+}
+
+fn buckets(samples: impl Iterator<Item = (u32, f64)>, percentiles: &[f64]) -> Vec<SizeBucket> {
+    let mut latencies = vec![StatsAggregator::new(); SIZE_BUCKET_BOUNDS.len()];
+    for (bytes, latency_ms) in samples {
+        let bucket = SIZE_BUCKET_BOUNDS.partition_point(|&bound| bound <= bytes) - 1;
+        latencies[bucket].push(latency_ms);
+    }
+
+    latencies
+        .iter()
+        .enumerate()
+        .filter(|(_, stats)| !stats.is_empty())
+        .map(|(i, stats)| SizeBucket {
+            min_bytes: SIZE_BUCKET_BOUNDS[i],
+            max_bytes: SIZE_BUCKET_BOUNDS.get(i + 1).copied(),
+            sample_count: stats.len(),
+            avg_latency_ms: stats.mean(),
+            percentiles: stats.percentiles(percentiles),
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+// Statistics per forwarder when several send to one receiver
+//
+// Each forwarder numbers its events from zero, so sequence IDs only mean
+// something within one source: loss, duplicates and reordering are tracked
+// per source and summed for the combined results. Latency is reported for
+// every source as well, to compare the paths they took.
+
+use crate::collector::Sequences;
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Latency and sequence accounting of the events from one forwarder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceStats {
+    pub source: String, // The forwarder's --forwarder-id, or its IP address
+    pub sample_count: usize,
+    pub events_lost: usize,
+    pub duplicates: usize,
+    pub reordered: usize,
+    pub avg_latency_ms: f64,
+    pub median_latency_ms: f64,
+    pub percentiles: BTreeMap<String, f64>,
+    pub backbone_avg_latency_ms: Option<f64>,
+}
+
+/// Summarize every tagged source, in the order they were first seen. Warm-up
+/// measurements are left out of the latency, not of the sequence accounting.
+pub(crate) fn source_stats(
+    measurements: &[LatencyMeasurement],
+    sequences: &[(Option<Arc<str>>, Sequences)],
+    percentiles: &[f64],
+) -> Vec<SourceStats> {
+    sequences
+        .iter()
+        .filter_map(|(source, sequences)| {
+            let source = source.as_deref()?;
+            let samples = || {
+                measurements
+                    .iter()
+                    .filter(|m| !m.warmup && m.source.as_deref() == Some(source))
+            };
+            let latency: StatsAggregator = samples().map(|m| m.end_to_end_latency_ms()).collect();
+            let backbone: StatsAggregator =
+                samples().filter_map(|m| m.backbone_latency_ms()).collect();
+            let summary = latency.summary();
+            Some(SourceStats {
+                source: source.to_string(),
+                sample_count: summary.count,
+                events_lost: sequences.events_lost(),
+                duplicates: sequences.duplicates,
+                reordered: sequences.reordered,
+                avg_latency_ms: summary.avg_ms,
+                median_latency_ms: summary.median_ms,
+                percentiles: latency.percentiles(percentiles),
+                backbone_avg_latency_ms: (!backbone.is_empty()).then(|| backbone.mean()),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+// Real-time latency spike (outlier) detection
+
+use crate::stats::percentile;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of recent samples the baseline is computed from
+const WINDOW_SIZE: usize = 1000;
+/// Samples required before spikes are flagged at all
+const MIN_SAMPLES: usize = 100;
+/// Recompute the baseline every this many samples rather than on every event
+const REFRESH_INTERVAL: usize = 100;
+/// Smallest MAD the threshold is built from (ms). On a very steady path the
+/// MAD can be zero or a few microseconds, and every bit of clock jitter would
+/// be flagged.
+const MIN_MAD_MS: f64 = 0.5;
+/// Spikes kept with the results; older ones are only counted, so a long run
+/// on a bad path does not grow without bound
+pub const MAX_SPIKES: usize = 10_000;
+
+/// A measurement flagged as a latency spike
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spike {
+    pub sequence_id: u64,
+    pub receive_time: i64, // Epoch nanos
+    pub elapsed_secs: f64, // Seconds since the start of the run
+    pub latency_ms: f64,
+    pub threshold_ms: f64, // median + k × MAD (at least 0.5 ms) at detection time
+    pub magnitude: f64,    // latency / rolling median
+    pub events_per_sec: u64, // Event rate in the surrounding second
+    pub seq_gap: bool,     // A sequence gap opened at this event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_queue_delay_ms: Option<f64>, // Time the event waited in the forwarder's send queue
+}
+
+impl Spike {
+    /// Whether the forwarder's send queue accounts for at least half of the
+    /// latency above the median, rather than the path
+    pub fn queued(&self) -> bool {
+        let Some(delay_ms) = self.send_queue_delay_ms else {
+            return false;
+        };
+        let median_ms = if self.magnitude > 0.0 {
+            self.latency_ms / self.magnitude
+        } else {
+            0.0
+        };
+        delay_ms >= (self.latency_ms - median_ms) / 2.0
+    }
+}
+
+/// Context about the surrounding traffic, supplied by the collector
+#[derive(Debug, Clone, Copy)]
+pub struct SpikeContext {
+    pub elapsed_secs: f64,
+    pub events_per_sec: u64,
+    pub seq_gap: bool,
+    pub send_queue_delay_ms: Option<f64>,
+}
+
+/// Flags latencies more than `k` median absolute deviations above the rolling median
+#[derive(Debug)]
+pub struct SpikeDetector {
+    k: f64,
+    window: VecDeque<f64>,
+    since_refresh: usize,
+    median_ms: f64,
+    threshold_ms: f64,
+}
+
+impl SpikeDetector {
+    pub fn new(k: f64) -> Self {
+        Self {
+            k,
+            window: VecDeque::with_capacity(WINDOW_SIZE),
+            since_refresh: 0,
+            median_ms: 0.0,
+            threshold_ms: f64::INFINITY,
+        }
+    }
+
+    /// Check one latency sample. The sample is added to the rolling window
+    /// afterwards, so a spike is judged against the traffic before it.
+    pub fn check(
+        &mut self,
+        sequence_id: u64,
+        receive_time: i64,
+        latency_ms: f64,
+        context: SpikeContext,
+    ) -> Option<Spike> {
+        let spike =
+            (self.window.len() >= MIN_SAMPLES && latency_ms > self.threshold_ms).then(|| Spike {
+                sequence_id,
+                receive_time,
+                elapsed_secs: context.elapsed_secs,
+                latency_ms,
+                threshold_ms: self.threshold_ms,
+                magnitude: if self.median_ms > 0.0 {
+                    latency_ms / self.median_ms
+                } else {
+                    0.0
+                },
+                events_per_sec: context.events_per_sec,
+                seq_gap: context.seq_gap,
+                send_queue_delay_ms: context.send_queue_delay_ms,
+            });
+
+        if self.window.len() == WINDOW_SIZE {
+            self.window.pop_front();
+        }
+        self.window.push_back(latency_ms);
+
+        self.since_refresh += 1;
+        if self.since_refresh >= REFRESH_INTERVAL || self.window.len() == MIN_SAMPLES {
+            self.refresh();
+        }
+
+        spike
+    }
+
+    /// Recompute the rolling median and MAD-based threshold
+    fn refresh(&mut self) {
+        self.since_refresh = 0;
+
+        let mut sorted: Vec<f64> = self.window.iter().copied().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = percentile(&sorted, 0.5);
+
+        let mut deviations: Vec<f64> = sorted.iter().map(|l| (l - median).abs()).collect();
+        deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mad = percentile(&deviations, 0.5).max(MIN_MAD_MS);
+
+        self.median_ms = median;
+        self.threshold_ms = median + self.k * mad;
+    }
+}
@@ -0,0 +1,196 @@
+// Latency budget: splitting end-to-end latency into forwarder stages and network
+//
+// The forwarder cannot embed its own after-serialize and after-send times in
+// the message being serialized and sent, so those two timestamps for event N
+// travel with event N+1 (like a PTP two-step follow-up). An event's breakdown
+// is complete once the next sequence ID arrives.
+
+use crate::delivery::DeliveryClass;
+use crate::measurement::LatencyMeasurement;
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Events waiting longer than this many sequence IDs for a follow-up are dropped
+const MAX_PENDING: u64 = 1024;
+
+/// Per-stage latency distributions (milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageBreakdown {
+    pub events: usize,             // Events with a complete breakdown
+    pub parse: LatencySummary,     // Frame received → parsed
+    pub serialize: LatencySummary, // Parsed → serialized
+    pub send: LatencySummary,      // Serialized → send returned
+    pub network: LatencySummary,   // Send returned → receiver
+}
+
+/// Forwarder timestamps received for an event that awaits its follow-up
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    frame_received: i64,
+    parsed: i64,
+    receive_time: i64,
+}
+
+/// Matches forwarder stage timestamps with receive times (all epoch nanos)
+#[derive(Debug, Default)]
+pub struct StageBudget {
+    pending: BTreeMap<u64, Pending>,
+    parse: StatsAggregator,
+    serialize: StatsAggregator,
+    send: StatsAggregator,
+    network: StatsAggregator,
+}
+
+impl StageBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record event `sequence_id`, which carries its own frame-received and
+    /// after-parse times plus the after-serialize and after-send times of
+    /// `sequence_id - 1`, if the forwarder sent that event
+    pub fn record(
+        &mut self,
+        sequence_id: u64,
+        frame_received: i64,
+        parsed: i64,
+        previous: Option<(i64, i64)>,
+        receive_time: i64,
+    ) {
+        if let (Some((serialized, sent)), Some(previous_id)) =
+            (previous, sequence_id.checked_sub(1))
+        {
+            if let Some(p) = self.pending.remove(&previous_id) {
+                self.parse.push(nanos_to_ms(p.parsed - p.frame_received));
+                self.serialize.push(nanos_to_ms(serialized - p.parsed));
+                self.send.push(nanos_to_ms(sent - serialized));
+                self.network.push(nanos_to_ms(p.receive_time - sent));
+            }
+        }
+
+        self.pending.insert(
+            sequence_id,
+            Pending {
+                frame_received,
+                parsed,
+                receive_time,
+            },
+        );
+        // Follow-ups that were lost never arrive
+        let cutoff = sequence_id.saturating_sub(MAX_PENDING);
+        while let Some(oldest) = self.pending.first_entry() {
+            if *oldest.key() >= cutoff {
+                break;
+            }
+            oldest.remove();
+        }
+    }
+
+    /// Stage distributions, or `None` if no event has a complete breakdown
+    pub fn results(&self) -> Option<StageBreakdown> {
+        if self.network.is_empty() {
+            return None;
+        }
+        Some(StageBreakdown {
+            events: self.network.len(),
+            parse: self.parse.summary(),
+            serialize: self.serialize.summary(),
+            send: self.send.summary(),
+            network: self.network.summary(),
+        })
+    }
+}
+
+fn nanos_to_ms(nanos: i64) -> f64 {
+    nanos as f64 / 1_000_000.0
+}
+
+/// How much of the backbone latency is the forwarder's own processing, from
+/// the `forwarding_overhead_ns` every event carries (milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardingOverhead {
+    pub overhead: LatencySummary, // Frame received → handed to the socket
+    pub backbone_avg_ms: f64,     // Tokyo receive → Frankfurt receive, same events
+    pub share_pct: f64,           // Average overhead as a percentage of `backbone_avg_ms`
+}
+
+/// Collects forwarding overhead next to the backbone latency of the same events
+#[derive(Debug, Default)]
+pub struct OverheadTracker {
+    overhead: StatsAggregator,
+    backbone: StatsAggregator,
+}
+
+impl OverheadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one event's overhead and its backbone latency (nanoseconds)
+    pub fn record(&mut self, overhead_ns: i64, backbone_ns: i64) {
+        self.overhead.push(nanos_to_ms(overhead_ns));
+        self.backbone.push(nanos_to_ms(backbone_ns));
+    }
+
+    /// `None` if no event carried an overhead (older forwarders)
+    pub fn results(&self) -> Option<ForwardingOverhead> {
+        if self.overhead.is_empty() {
+            return None;
+        }
+        let overhead = self.overhead.summary();
+        let backbone_avg_ms = self.backbone.mean();
+        let share_pct = if backbone_avg_ms > 0.0 {
+            overhead.avg_ms / backbone_avg_ms * 100.0
+        } else {
+            0.0
+        };
+        Some(ForwardingOverhead {
+            overhead,
+            backbone_avg_ms,
+            share_pct,
+        })
+    }
+}
+
+/// Time events waited in the forwarder's send queue (--send-queue), next to
+/// the backbone latency it is part of (milliseconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendQueueDelay {
+    pub delay: LatencySummary, // Queued → dequeued for sending
+    pub backbone_avg_ms: f64,  // Tokyo receive → Frankfurt receive, same events
+    pub share_pct: f64,        // Average delay as a percentage of `backbone_avg_ms`
+}
+
+/// Summarize the send queue delay of live measurements that carry one;
+/// `None` if the forwarder ran without a send queue
+pub fn send_queue_delay(measurements: &[LatencyMeasurement]) -> Option<SendQueueDelay> {
+    let mut delay = StatsAggregator::new();
+    let mut backbone = StatsAggregator::new();
+    for m in measurements
+        .iter()
+        .filter(|m| !m.warmup && m.delivery_class == DeliveryClass::Live)
+    {
+        if let (Some(delay_ms), Some(backbone_ms)) =
+            (m.send_queue_delay_ms(), m.backbone_latency_ms())
+        {
+            delay.push(delay_ms);
+            backbone.push(backbone_ms);
+        }
+    }
+    if delay.is_empty() {
+        return None;
+    }
+    let delay = delay.summary();
+    let backbone_avg_ms = backbone.mean();
+    let share_pct = if backbone_avg_ms > 0.0 {
+        delay.avg_ms / backbone_avg_ms * 100.0
+    } else {
+        0.0
+    };
+    Some(SendQueueDelay {
+        delay,
+        backbone_avg_ms,
+        share_pct,
+    })
+}
@@ -0,0 +1,53 @@
+// Stale events (--max-staleness-ms)
+//
+// An event that reaches the receiver long after the exchange published it,
+// e.g. held up behind a stalled connection, is of no use to a strategy and
+// would only stretch the tail of the latency distribution. Events older than
+// the threshold, measured against the exchange event time, are still tracked
+// by sequence ID so they do not count as lost, but are kept out of the
+// latency statistics and summarized on their own.
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Events that arrived later than the staleness threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaleStats {
+    pub max_staleness_ms: f64,
+    pub stale_events: usize,
+    pub share_pct: f64,                     // Of the events past warm-up
+    pub staleness: LatencySummary,          // Age of the stale events on arrival
+    pub percentiles: BTreeMap<String, f64>, // Same, keyed "p50", "p99.9", ...
+}
+
+/// Summarize `stale` measurements against the `fresh` ones that stayed in the
+/// statistics. Warm-up in either is left out.
+pub fn stale_stats(
+    max_staleness_ms: f64,
+    stale: &[LatencyMeasurement],
+    fresh: &[LatencyMeasurement],
+    percentiles: &[f64],
+) -> StaleStats {
+    let staleness: StatsAggregator = stale
+        .iter()
+        .filter(|m| !m.warmup)
+        .map(|m| m.end_to_end_latency_ms())
+        .collect();
+    let stale_events = staleness.len();
+    let fresh_events = fresh.iter().filter(|m| !m.warmup).count();
+    let total = stale_events + fresh_events;
+
+    StaleStats {
+        max_staleness_ms,
+        stale_events,
+        share_pct: if total > 0 {
+            stale_events as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        },
+        staleness: staleness.summary(),
+        percentiles: staleness.percentiles(percentiles),
+    }
+}
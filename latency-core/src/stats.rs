@@ -0,0 +1,140 @@
+// Descriptive statistics over latency samples
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Percentiles reported when none are configured (in percent)
+pub const DEFAULT_PERCENTILES: &[f64] = &[50.0, 90.0, 95.0, 99.0, 99.9];
+
+/// Key used for a percentile in results, e.g. `p99` or `p99.9`
+pub fn percentile_label(level: f64) -> String {
+    format!("p{}", level)
+}
+
+/// Calculate percentile from sorted data using linear interpolation
+/// between the two closest ranks. `percentile` is a fraction (0.95 = p95).
+pub fn percentile(sorted_data: &[f64], percentile: f64) -> f64 {
+    let len = sorted_data.len();
+    if len == 0 {
+        return 0.0;
+    }
+    if len == 1 {
+        return sorted_data[0];
+    }
+
+    let index = percentile * (len - 1) as f64;
+    let lower = index.floor() as usize;
+    let upper = index.ceil() as usize;
+    let weight = index - lower as f64;
+
+    sorted_data[lower] * (1.0 - weight) + sorted_data[upper] * weight
+}
+
+/// Summary statistics for a set of latency samples (milliseconds)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub avg_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub stddev_ms: f64,
+}
+
+/// Accumulates latency samples and produces summary statistics
+#[derive(Debug, Clone, Default)]
+pub struct StatsAggregator {
+    samples: Vec<f64>,
+}
+
+impl StatsAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a single latency sample (milliseconds)
+    pub fn push(&mut self, latency_ms: f64) {
+        self.samples.push(latency_ms);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Drop all samples, keeping the allocation for reuse
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Arithmetic mean of the samples, 0.0 when empty
+    pub fn mean(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// Values at each of `levels` (in percent, e.g. 99.9), keyed by
+    /// `percentile_label`; empty when there are no samples
+    pub fn percentiles(&self, levels: &[f64]) -> BTreeMap<String, f64> {
+        if self.samples.is_empty() {
+            return BTreeMap::new();
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        levels
+            .iter()
+            .map(|&level| (percentile_label(level), percentile(&sorted, level / 100.0)))
+            .collect()
+    }
+
+    /// Calculate summary statistics; all fields are zero when empty
+    pub fn summary(&self) -> LatencySummary {
+        let count = self.samples.len();
+        if count == 0 {
+            return LatencySummary::default();
+        }
+
+        // Sort latencies for percentile calculations
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let avg_ms = self.mean();
+
+        // Jitter is reported as the population standard deviation
+        let variance = sorted
+            .iter()
+            .map(|l| {
+                let diff = l - avg_ms;
+                diff * diff
+            })
+            .sum::<f64>()
+            / count as f64;
+
+        LatencySummary {
+            count,
+            avg_ms,
+            median_ms: percentile(&sorted, 0.50),
+            p95_ms: percentile(&sorted, 0.95),
+            p99_ms: percentile(&sorted, 0.99),
+            min_ms: sorted[0],
+            max_ms: sorted[count - 1],
+            stddev_ms: variance.sqrt(),
+        }
+    }
+}
+
+impl FromIterator<f64> for StatsAggregator {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        Self {
+            samples: iter.into_iter().collect(),
+        }
+    }
+}
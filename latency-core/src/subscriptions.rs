@@ -0,0 +1,37 @@
+// Stream subscription changes on a live exchange connection
+//
+// Binance connections can add and drop streams in-band with SUBSCRIBE and
+// UNSUBSCRIBE requests instead of naming them in the URL. Each request is
+// kept with the results, together with how long the exchange took to
+// acknowledge it, so a change made during a run shows up next to the latencies.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What a subscription request asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscriptionMethod {
+    Subscribe,
+    Unsubscribe,
+}
+
+impl fmt::Display for SubscriptionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SubscriptionMethod::Subscribe => "SUBSCRIBE",
+            SubscriptionMethod::Unsubscribe => "UNSUBSCRIBE",
+        })
+    }
+}
+
+/// One subscription request and the exchange's answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionChange {
+    pub time: i64, // Epoch nanos the request was sent
+    pub method: SubscriptionMethod,
+    pub streams: Vec<String>,
+    pub ack_ms: Option<f64>, // Request sent → reply received; none if never answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // The exchange rejected the request
+}
@@ -0,0 +1,66 @@
+// Latency per symbol when several share a connection (--split-by-symbol)
+//
+// A connection carrying several symbols mixes them into one distribution,
+// though a busy symbol's bursts delay the quiet ones queued behind it. Split
+// by the symbol each event names, every symbol gets its own measurement set.
+
+use crate::measurement::LatencyMeasurement;
+use crate::stats::StatsAggregator;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Latency of one symbol in a run that split them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolStats {
+    pub symbol: String,
+    pub sample_count: usize,
+    pub avg_latency_ms: f64,
+    pub median_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub jitter_stddev_ms: f64,
+    pub backbone_median_ms: Option<f64>, // AWS backbone only
+}
+
+/// Summarize measurements per symbol, busiest first; warm-up and untagged
+/// measurements are left out
+pub fn split_by_symbol(measurements: &[LatencyMeasurement]) -> Vec<SymbolStats> {
+    let mut symbols: Vec<&Arc<str>> = Vec::new();
+    for symbol in measurements.iter().filter_map(|m| m.symbol.as_ref()) {
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    let mut stats: Vec<SymbolStats> = symbols
+        .into_iter()
+        .filter_map(|symbol| {
+            let samples = || {
+                measurements
+                    .iter()
+                    .filter(move |m| !m.warmup && m.symbol.as_ref() == Some(symbol))
+            };
+            let latency: StatsAggregator = samples().map(|m| m.end_to_end_latency_ms()).collect();
+            if latency.is_empty() {
+                return None;
+            }
+            let backbone: StatsAggregator =
+                samples().filter_map(|m| m.backbone_latency_ms()).collect();
+
+            let summary = latency.summary();
+            Some(SymbolStats {
+                symbol: symbol.to_string(),
+                sample_count: summary.count,
+                avg_latency_ms: summary.avg_ms,
+                median_latency_ms: summary.median_ms,
+                p99_latency_ms: summary.p99_ms,
+                jitter_stddev_ms: summary.stddev_ms,
+                backbone_median_ms: (!backbone.is_empty()).then(|| backbone.summary().median_ms),
+            })
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.sample_count
+            .cmp(&a.sample_count)
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+    stats
+}
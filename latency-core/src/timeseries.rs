@@ -0,0 +1,54 @@
+// Per-second time-series output
+
+use crate::collector::SecondStats;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Writes one CSV row per reporting window so runs can be plotted afterwards
+#[derive(Debug)]
+pub struct TimeSeriesWriter {
+    writer: BufWriter<File>,
+}
+
+impl TimeSeriesWriter {
+    /// Create the file and write the CSV header
+    pub fn create(filepath: &str) -> Result<Self, std::io::Error> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writeln!(
+            writer,
+            "elapsed_secs,events,avg_latency_ms,min_latency_ms,max_latency_ms,p95_latency_ms,backbone_avg_latency_ms,events_lost,mid_price,spread_bps,book_changes"
+        )?;
+        Ok(Self { writer })
+    }
+
+    /// Append one window. The market data columns stay empty without quotes;
+    /// the mid-price keeps nine decimals, one more than Binance prices have.
+    pub fn write(&mut self, second: &SecondStats) -> Result<(), std::io::Error> {
+        let quotes = second.quotes.map_or(",,".to_string(), |quotes| {
+            format!(
+                "{:.9},{:.4},{}",
+                quotes.mid_price, quotes.avg_spread_bps, quotes.book_changes
+            )
+        });
+        writeln!(
+            self.writer,
+            "{},{},{:.3},{:.3},{:.3},{:.3},{},{},{}",
+            second.elapsed_secs,
+            second.events,
+            second.avg_latency_ms,
+            second.min_latency_ms,
+            second.max_latency_ms,
+            second.p95_latency_ms,
+            second
+                .avg_backbone_latency_ms
+                .map_or(String::new(), |l| format!("{:.3}", l)),
+            second.events_lost,
+            quotes
+        )
+    }
+
+    /// Flush buffered rows to disk
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,100 @@
+use latency_core::{AlertKind, AlertMonitor, AlertThresholds, LatencyMeasurement, SecondStats};
+
+fn thresholds() -> AlertThresholds {
+    AlertThresholds {
+        p99_ms: Some(50.0),
+        loss_pct: Some(1.0),
+        window_secs: 2,
+    }
+}
+
+fn second(elapsed_secs: u64, events: u64, events_lost: u64) -> SecondStats {
+    SecondStats {
+        elapsed_secs,
+        events,
+        avg_latency_ms: 0.0,
+        min_latency_ms: 0.0,
+        max_latency_ms: 0.0,
+        p95_latency_ms: 0.0,
+        avg_backbone_latency_ms: None,
+        events_lost,
+        quotes: None,
+    }
+}
+
+/// `count` measurements of `latency_ms` each
+fn record(monitor: &mut AlertMonitor, count: u64, latency_ms: i64) {
+    for i in 0..count {
+        let measurement = LatencyMeasurement::new_baseline(i, 0, latency_ms * 1_000_000);
+        monitor.record(&measurement);
+    }
+}
+
+#[test]
+fn p99_alarm_fires_once_and_resolves() {
+    let mut monitor = AlertMonitor::new(thresholds());
+
+    record(&mut monitor, 200, 10);
+    assert!(monitor.second(&second(1, 200, 0)).is_empty());
+
+    // 10 of 200 samples in the window are slow, well past the top percent
+    record(&mut monitor, 190, 10);
+    record(&mut monitor, 10, 80);
+    let alerts = monitor.second(&second(2, 200, 0));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertKind::P99Latency);
+    assert!(alerts[0].firing);
+    assert_eq!(alerts[0].value, 80.0);
+    assert_eq!(alerts[0].elapsed_secs, 2);
+
+    // Still in the window: no repeat
+    record(&mut monitor, 200, 10);
+    assert!(monitor.second(&second(3, 200, 0)).is_empty());
+
+    // Slid out of the window
+    record(&mut monitor, 200, 10);
+    let alerts = monitor.second(&second(4, 200, 0));
+    assert_eq!(alerts.len(), 1);
+    assert!(!alerts[0].firing);
+    assert_eq!(monitor.history().len(), 2);
+}
+
+#[test]
+fn p99_needs_enough_samples() {
+    let mut monitor = AlertMonitor::new(thresholds());
+    record(&mut monitor, 10, 500);
+    assert!(monitor.second(&second(1, 10, 0)).is_empty());
+}
+
+#[test]
+fn warmup_measurements_are_ignored() {
+    let mut monitor = AlertMonitor::new(thresholds());
+    for i in 0..200 {
+        let mut measurement = LatencyMeasurement::new_baseline(i, 0, 500_000_000);
+        measurement.warmup = true;
+        monitor.record(&measurement);
+    }
+    assert!(monitor.second(&second(1, 200, 0)).is_empty());
+}
+
+#[test]
+fn loss_alarm_counts_the_whole_window() {
+    let mut monitor = AlertMonitor::new(AlertThresholds {
+        p99_ms: None,
+        ..thresholds()
+    });
+
+    // 1 lost of 100 sent is not above 1 %
+    assert!(monitor.second(&second(1, 99, 1)).is_empty());
+
+    // 3 lost of 200 sent over the window is
+    let alerts = monitor.second(&second(2, 98, 2));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertKind::Loss);
+    assert!(alerts[0].firing);
+    assert!((alerts[0].value - 1.5).abs() < 1e-9);
+    assert_eq!(
+        alerts[0].to_string(),
+        "loss rate 1.50% over the last 2 s exceeds the threshold of 1%"
+    );
+}
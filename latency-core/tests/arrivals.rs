@@ -0,0 +1,61 @@
+use latency_core::{merge_arrivals, read_arrivals, ArrivalLog, UpdateArrival};
+
+fn arrival(update_id: i64, receive_time: i64) -> UpdateArrival {
+    UpdateArrival {
+        update_id,
+        event_time: Some(1_700_000_000_000 + update_id),
+        receive_time,
+    }
+}
+
+#[test]
+fn merge_joins_by_update_id() {
+    let tokyo = [
+        arrival(1, 1_000),
+        arrival(2, 2_000),
+        arrival(3, 3_000),
+        arrival(3, 3_500),
+    ];
+    let frankfurt = [
+        arrival(2, 2_000_000),
+        arrival(1, 1_500_000),
+        arrival(4, 4_000_000),
+    ];
+    let merge = merge_arrivals(&tokyo, &frankfurt);
+
+    assert_eq!(merge.matched.len(), 2);
+    assert_eq!(merge.matched[0].update_id, 1);
+    assert_eq!(merge.matched[0].delta_ns(), 1_499_000);
+    assert_eq!(merge.tokyo_only, 1);
+    assert_eq!(merge.frankfurt_only, 1);
+    assert_eq!(merge.duplicates, 1);
+
+    let stats = merge.stats(&[50.0]);
+    assert_eq!(stats.matched, 2);
+    assert!((stats.delta.min_ms - 1.499).abs() < 1e-9);
+    assert!((stats.delta.max_ms - 1.998).abs() < 1e-9);
+    assert!(stats.percentiles.contains_key("p50"));
+}
+
+#[test]
+fn arrival_log_round_trips() {
+    let path = std::env::temp_dir().join(format!("arrivals-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+    let written = [
+        arrival(400900217, 1_700_000_000_150_000_000),
+        UpdateArrival {
+            update_id: 400900218,
+            event_time: None,
+            receive_time: 1_700_000_000_160_000_000,
+        },
+    ];
+    let mut log = ArrivalLog::create(path).unwrap();
+    for arrival in &written {
+        log.write(arrival).unwrap();
+    }
+    assert_eq!(log.rows(), 2);
+    log.finish().unwrap();
+
+    assert_eq!(read_arrivals(path).unwrap(), written);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,73 @@
+use latency_core::{bootstrap_intervals, Collector, LatencyMeasurement, CONFIDENCE_LEVEL};
+
+const EVENT_TIME_MS: i64 = 1_700_000_000_000;
+
+/// Measurements with latencies `base_ms` to `base_ms + spread_ms`, spread evenly
+fn measurements(count: u64, base_ms: f64, spread_ms: f64) -> Vec<LatencyMeasurement> {
+    (0..count)
+        .map(|id| {
+            let event_time = EVENT_TIME_MS + id as i64;
+            // Every 7th latency, so neighbouring events are not in order
+            let share = (id * 7 % count) as f64 / count as f64;
+            let latency_ns = ((base_ms + share * spread_ms) * 1_000_000.0) as i64;
+            LatencyMeasurement::new_baseline(id, event_time, event_time * 1_000_000 + latency_ns)
+        })
+        .collect()
+}
+
+#[test]
+fn intervals_surround_the_run_percentiles() {
+    let measurements = measurements(5_000, 10.0, 20.0);
+    let report = Collector::new()
+        .with_bootstrap(500)
+        .replay("baseline", measurements.clone());
+    let results = &report.results;
+    let intervals = results.confidence_intervals.unwrap();
+    assert_eq!(intervals.iterations, 500);
+    assert_eq!(intervals.confidence, CONFIDENCE_LEVEL);
+
+    for (interval, estimate) in [
+        (intervals.median, results.median_latency_ms),
+        (intervals.p95, results.p95_latency_ms),
+        (intervals.p99, results.p99_latency_ms),
+    ] {
+        assert!((interval.estimate_ms - estimate).abs() < 1e-9);
+        assert!(interval.lower_ms <= estimate && estimate <= interval.upper_ms);
+        assert!(interval.width_ms() > 0.0 && interval.width_ms() < 1.0);
+    }
+
+    // The same measurements give the same intervals
+    assert_eq!(bootstrap_intervals(&measurements, 500), Some(intervals));
+}
+
+#[test]
+fn more_samples_narrow_the_interval() {
+    let width = |count| {
+        bootstrap_intervals(&measurements(count, 10.0, 20.0), 300)
+            .unwrap()
+            .median
+            .width_ms()
+    };
+    assert!(width(20_000) < width(500) / 2.0);
+}
+
+#[test]
+fn separate_setups_do_not_overlap() {
+    let baseline = bootstrap_intervals(&measurements(2_000, 10.0, 20.0), 300).unwrap();
+    let faster = bootstrap_intervals(&measurements(2_000, 8.0, 20.0), 300).unwrap();
+    let same = bootstrap_intervals(&measurements(1_999, 10.0, 20.0), 300).unwrap();
+    assert!(!baseline.median.overlaps(&faster.median));
+    assert!(baseline.median.overlaps(&same.median));
+    assert!(baseline.p99.overlaps(&same.p99));
+}
+
+#[test]
+fn no_intervals_without_samples_or_the_option() {
+    assert!(bootstrap_intervals(&[], 1_000).is_none());
+    let report = Collector::new().replay("baseline", measurements(100, 10.0, 1.0));
+    assert!(report.results.confidence_intervals.is_none());
+
+    // Identical latencies leave nothing to vary
+    let constant = bootstrap_intervals(&measurements(100, 10.0, 0.0), 100).unwrap();
+    assert_eq!(constant.p99.lower_ms, constant.p99.upper_ms);
+}
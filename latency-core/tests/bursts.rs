@@ -0,0 +1,52 @@
+use latency_core::{burst_stats, ExperimentResults, LatencyMeasurement};
+
+#[test]
+fn alternating_latencies_have_no_bursts() {
+    let latencies: Vec<f64> = (0..100)
+        .map(|i| if i % 2 == 0 { 1.0 } else { 3.0 })
+        .collect();
+    let bursts = burst_stats(&latencies).unwrap();
+
+    assert!((bursts.autocorrelation["lag1"] + 0.99).abs() < 1e-9);
+    assert!((bursts.autocorrelation["lag2"] - 0.98).abs() < 1e-9);
+    assert_eq!(bursts.above_median_runs, 50);
+    assert_eq!(bursts.avg_run_length, 1.0);
+    assert_eq!(bursts.max_run_length, 1);
+}
+
+#[test]
+fn sustained_slow_stretch_is_one_long_run() {
+    let latencies: Vec<f64> = (0..100)
+        .map(|i| if (40..60).contains(&i) { 10.0 } else { 1.0 })
+        .collect();
+    let bursts = burst_stats(&latencies).unwrap();
+
+    assert!(bursts.autocorrelation["lag1"] > 0.9);
+    assert!(bursts.autocorrelation["lag10"] > 0.3);
+    assert!(!bursts.autocorrelation.contains_key("lag100"));
+    assert_eq!(bursts.above_median_runs, 1);
+    assert_eq!(bursts.avg_run_length, 20.0);
+    assert_eq!(bursts.max_run_length, 20);
+}
+
+#[test]
+fn constant_or_single_latencies_have_nothing_to_report() {
+    assert!(burst_stats(&[]).is_none());
+    assert!(burst_stats(&[5.0]).is_none());
+    assert!(burst_stats(&[2.0, 2.0, 2.0]).is_none());
+}
+
+#[test]
+fn results_measure_bursts_after_warmup() {
+    let mut measurements: Vec<LatencyMeasurement> = (0..10)
+        .map(|i| LatencyMeasurement::new_baseline(i, 1_000, 1_000_000_000 + i as i64 * 1_000_000))
+        .collect();
+    measurements[0].warmup = true;
+    let results = ExperimentResults::from_measurements("baseline".into(), &measurements, 0);
+
+    let bursts = results.bursts.unwrap();
+    assert_eq!(bursts.above_median_runs, 1);
+    assert_eq!(bursts.max_run_length, 4);
+    assert!(bursts.autocorrelation.contains_key("lag5"));
+    assert!(!bursts.autocorrelation.contains_key("lag10"));
+}
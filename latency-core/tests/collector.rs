@@ -0,0 +1,256 @@
+use latency_core::{losses_per_minute, Collector, LatencyMeasurement};
+use std::sync::Arc;
+
+fn measurement(sequence_id: u64) -> LatencyMeasurement {
+    LatencyMeasurement::new_aws_backbone(sequence_id, 1_000, 1_100_000_000, 1_150_000_000)
+}
+
+#[test]
+fn counts_sequence_gaps_as_lost() {
+    let mut collector = Collector::new();
+    for sequence_id in [10, 11, 14, 15, 17] {
+        collector.record(measurement(sequence_id));
+    }
+
+    assert_eq!(collector.len(), 5);
+    assert_eq!(collector.events_lost(), 3);
+}
+
+#[test]
+fn records_each_gap_with_its_size() {
+    let mut collector = Collector::new();
+    for sequence_id in [10, 11, 14, 15, 17] {
+        collector.record(measurement(sequence_id));
+    }
+
+    let gaps: Vec<(u64, u64)> = collector
+        .gaps()
+        .iter()
+        .map(|g| (g.first_missing, g.size))
+        .collect();
+    assert_eq!(gaps, vec![(12, 2), (16, 1)]);
+
+    let minutes = losses_per_minute(collector.gaps());
+    assert_eq!(minutes.len(), 1);
+    assert_eq!(minutes[0].events_lost, 3);
+    assert_eq!(minutes[0].gaps, 2);
+
+    let report = collector.finish("aws-backbone");
+    assert_eq!(report.results.gaps.len(), 2);
+}
+
+#[test]
+fn empty_collector_has_no_loss() {
+    let collector = Collector::new();
+    assert!(collector.is_empty());
+    assert_eq!(collector.events_lost(), 0);
+}
+
+#[test]
+fn finish_builds_report_from_measurements() {
+    let mut collector = Collector::new();
+    collector.record(measurement(0));
+    collector.record(measurement(2));
+
+    let report = collector.finish("aws-backbone");
+    assert_eq!(report.results.setup_type, "aws-backbone");
+    assert_eq!(report.results.sample_count, 2);
+    assert_eq!(report.results.events_lost, 1);
+    assert_eq!(report.measurements.len(), 2);
+    assert_eq!(report.results.backbone_avg_latency_ms, Some(50.0));
+}
+
+#[test]
+fn flush_second_reports_window_loss() {
+    let mut collector = Collector::new();
+    for sequence_id in [0, 1, 4] {
+        collector.record(measurement(sequence_id));
+    }
+
+    let second = collector.flush_second().expect("window has events");
+    assert_eq!(second.events, 3);
+    assert_eq!(second.events_lost, 2);
+    assert_eq!(second.avg_backbone_latency_ms, Some(50.0));
+    assert!(collector.flush_second().is_none());
+}
+
+#[test]
+fn flush_second_reports_top_of_book() {
+    let mut collector = Collector::new();
+    collector.record(measurement(0));
+    assert_eq!(collector.flush_second().unwrap().quotes, None);
+
+    collector.record_quote(99.0, 101.0);
+    collector.record_quote(99.0, 101.0); // Same top of book again
+    collector.record_quote(99.5, 100.5);
+    collector.record(measurement(1));
+    let quotes = collector.flush_second().unwrap().quotes.unwrap();
+    assert_eq!(quotes.mid_price, 100.0);
+    assert_eq!(quotes.book_changes, 2);
+    assert!((quotes.avg_spread_bps - 500.0 / 3.0).abs() < 1e-9);
+
+    // A quiet second keeps the last quote
+    collector.record(measurement(2));
+    let quotes = collector.flush_second().unwrap().quotes.unwrap();
+    assert_eq!(quotes.book_changes, 0);
+    assert!((quotes.avg_spread_bps - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn tracks_duplicates_and_reordering() {
+    let mut collector = Collector::new();
+    for sequence_id in [0, 1, 4, 2, 5, 3, 4] {
+        if !collector.check_duplicate(None, sequence_id) {
+            collector.record(measurement(sequence_id));
+        }
+    }
+
+    let report = collector.finish("aws-backbone");
+    assert_eq!(report.results.sample_count, 6);
+    assert_eq!(report.results.events_lost, 0);
+    assert_eq!(report.results.duplicates, 1);
+    assert_eq!(report.results.reordered, 2);
+    assert_eq!(report.results.max_reorder_distance, 2);
+}
+
+#[test]
+fn tracks_sequence_ids_per_source() {
+    let mut collector = Collector::new();
+    let tokyo: Arc<str> = Arc::from("tokyo");
+    let seoul: Arc<str> = Arc::from("seoul");
+    // Both forwarders number from zero; only seoul loses events
+    for (source, sequence_id) in [
+        (&tokyo, 0),
+        (&seoul, 0),
+        (&tokyo, 1),
+        (&seoul, 3),
+        (&tokyo, 1),
+    ] {
+        if !collector.check_duplicate(Some(source), sequence_id) {
+            collector.record(measurement(sequence_id).with_source(source.clone()));
+        }
+    }
+
+    let report = collector.finish("aws-backbone");
+    assert_eq!(report.results.sample_count, 4);
+    assert_eq!(report.results.events_lost, 2);
+    assert_eq!(report.results.duplicates, 1);
+    assert_eq!(report.results.reordered, 0);
+    assert_eq!(report.results.gaps.len(), 1);
+    assert_eq!(report.results.gaps[0].source.as_deref(), Some("seoul"));
+
+    let sources = report.results.sources.unwrap();
+    let counts: Vec<_> = sources
+        .iter()
+        .map(|s| {
+            (
+                s.source.as_str(),
+                s.sample_count,
+                s.events_lost,
+                s.duplicates,
+            )
+        })
+        .collect();
+    assert_eq!(counts, [("tokyo", 2, 0, 1), ("seoul", 2, 2, 0)]);
+}
+
+#[test]
+fn recent_keeps_only_the_window() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64;
+    let mut collector = Collector::new();
+    for (sequence_id, age_secs) in [(1, 120), (2, 90), (3, 30), (4, 1)] {
+        let receive_time = now - age_secs * 1_000_000_000;
+        let event_time = receive_time / 1_000_000 - 10;
+        collector.record(LatencyMeasurement::new_baseline(
+            sequence_id,
+            event_time,
+            receive_time,
+        ));
+    }
+
+    let recent = collector.recent(std::time::Duration::from_secs(60));
+    let ids: Vec<u64> = recent.iter().map(|m| m.sequence_id).collect();
+    assert_eq!(ids, [3, 4]);
+}
+
+#[test]
+fn streaming_percentiles_span_intervals() {
+    let mut collector = Collector::new().with_streaming_stats(0.01);
+    for seq in 0..10 {
+        collector.record(measurement(seq));
+    }
+    collector.start_streaming_interval();
+    for seq in 10..15 {
+        collector.record(measurement(seq));
+    }
+
+    let streaming = collector.snapshot("aws-backbone").streaming.unwrap();
+    assert_eq!(streaming.since_start.count, 15);
+    assert_eq!(streaming.since_last_report.count, 5);
+    assert!(Collector::new()
+        .snapshot("aws-backbone")
+        .streaming
+        .is_none());
+}
+
+#[test]
+fn replay_times_recorded_measurements_by_receive_time() {
+    // Two rotated files, given out of order; the first second is warm-up
+    let received = |sequence_id: u64, secs: i64| {
+        let frankfurt = 1_700_000_000_000_000_000 + secs * 1_000_000_000;
+        LatencyMeasurement::new_aws_backbone(
+            sequence_id,
+            frankfurt / 1_000_000 - 80,
+            frankfurt - 60_000_000,
+            frankfurt,
+        )
+    };
+    let mut measurements: Vec<_> = (4..8).map(|i| received(i, i as i64)).collect();
+    measurements.extend((0..3).map(|i| received(i, i as i64)));
+    measurements.push(received(2, 2));
+
+    let report = Collector::new()
+        .with_warmup(std::time::Duration::from_millis(1_500))
+        .replay("aws-backbone", measurements);
+
+    assert_eq!(report.measurements.len(), 7);
+    assert_eq!(report.measurements[0].sequence_id, 0);
+    assert_eq!(report.results.warmup_samples, 2);
+    assert_eq!(report.results.duplicates, 1);
+    assert_eq!(report.results.events_lost, 1);
+    assert_eq!(report.results.reordered, 0);
+    assert_eq!(report.results.gaps[0].first_missing, 3);
+    assert_eq!(report.results.gaps[0].elapsed_secs, 4.0);
+    assert_eq!(report.results.avg_latency_ms, 80.0);
+}
+
+#[test]
+fn sampled_window_counts_only_missing_ids_as_lost() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64;
+    let mut collector = Collector::new()
+        .with_window(std::time::Duration::from_secs(60))
+        .with_sample_rate(latency_core::SampleRate::new(0.1).unwrap());
+    // One event every 100 ms over the last two minutes; 1100 never arrives
+    for sequence_id in (0..1_200).filter(|&id| id != 1_100) {
+        let receive_time = now - (1_200 - sequence_id as i64) * 100_000_000;
+        let event_time = receive_time / 1_000_000 - 10;
+        collector.record(LatencyMeasurement::new_baseline(
+            sequence_id,
+            event_time,
+            receive_time,
+        ));
+    }
+    assert_eq!(collector.events_lost(), 1);
+
+    collector.flush_second();
+    assert!(collector.len() < 100, "{}", collector.len());
+    assert_eq!(collector.events_lost(), 1);
+    assert!(!collector.check_duplicate(None, 1_100));
+    assert!(collector.check_duplicate(None, 1_099));
+}
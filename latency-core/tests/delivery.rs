@@ -0,0 +1,77 @@
+use latency_core::{DeliveryClass, ExperimentResults, LatencyMeasurement};
+
+fn measurement(sequence_id: u64, latency_ms: i64, class: DeliveryClass) -> LatencyMeasurement {
+    LatencyMeasurement::new_aws_backbone(
+        sequence_id,
+        1_000,
+        1_000_000_000,
+        1_000_000_000 + latency_ms * 1_000_000,
+    )
+    .with_delivery_class(class)
+}
+
+#[test]
+fn replayed_events_get_their_own_statistics() {
+    let mut measurements: Vec<LatencyMeasurement> = (0..10)
+        .map(|i| measurement(i, 100, DeliveryClass::Live))
+        .collect();
+    measurements.push(measurement(10, 5_000, DeliveryClass::Buffered));
+    measurements.push(measurement(11, 400, DeliveryClass::Retransmitted));
+
+    let results =
+        ExperimentResults::from_measurements("aws-backbone".to_string(), &measurements, 0);
+    let classes = results.delivery_classes.unwrap();
+    assert_eq!(classes.len(), 3);
+    assert_eq!(classes[0].class, DeliveryClass::Live);
+    assert_eq!(classes[0].sample_count, 10);
+    assert_eq!(classes[0].max_latency_ms, 100.0);
+    assert_eq!(classes[1].class, DeliveryClass::Buffered);
+    assert_eq!(classes[1].avg_latency_ms, 5_000.0);
+    assert_eq!(classes[2].class, DeliveryClass::Retransmitted);
+    // The headline statistics still cover every event
+    assert_eq!(results.max_latency_ms, 5_000.0);
+
+    let live_only =
+        ExperimentResults::from_measurements("aws-backbone".to_string(), &measurements[..10], 0);
+    assert!(live_only.delivery_classes.is_none());
+}
+
+#[test]
+fn delivery_class_survives_csv() {
+    let path = std::env::temp_dir().join(format!("delivery-class-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+    let measurements = [
+        measurement(0, 100, DeliveryClass::Live),
+        measurement(1, 900, DeliveryClass::Buffered),
+        measurement(2, 300, DeliveryClass::Retransmitted),
+    ];
+    LatencyMeasurement::write_to_csv(&measurements, path).unwrap();
+    let read = LatencyMeasurement::read_from_csv(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let classes: Vec<DeliveryClass> = read.iter().map(|m| m.delivery_class).collect();
+    assert_eq!(
+        classes,
+        [
+            DeliveryClass::Live,
+            DeliveryClass::Buffered,
+            DeliveryClass::Retransmitted
+        ]
+    );
+}
+
+#[test]
+fn stream_name_survives_csv() {
+    let path = std::env::temp_dir().join(format!("stream-name-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+    let measurements = [
+        measurement(0, 100, DeliveryClass::Live).with_stream("btcusdt@aggTrade".into()),
+        measurement(1, 200, DeliveryClass::Live),
+    ];
+    LatencyMeasurement::write_to_csv(&measurements, path).unwrap();
+    let read = LatencyMeasurement::read_from_csv(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(read[0].stream.as_deref(), Some("btcusdt@aggTrade"));
+    assert_eq!(read[1].stream, None);
+}
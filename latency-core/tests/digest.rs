@@ -0,0 +1,88 @@
+use latency_core::{percentile, StreamingStats};
+
+/// Deterministic, skewed latencies between 1 and about 500 ms
+fn latencies(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| {
+            let x = ((i * 7919) % n) as f64 / n as f64;
+            1.0 + 499.0 * x.powi(4)
+        })
+        .collect()
+}
+
+#[test]
+fn quantiles_are_within_the_relative_accuracy() {
+    let samples = latencies(100_000);
+    let mut sketch = StreamingStats::new(0.01);
+    for &latency in &samples {
+        sketch.push(latency);
+    }
+    let mut sorted = samples.clone();
+    sorted.sort_by(f64::total_cmp);
+
+    for q in [0.01, 0.25, 0.5, 0.9, 0.99, 0.999] {
+        let exact = sorted[(q * (sorted.len() - 1) as f64).round() as usize];
+        let approx = sketch.quantile(q);
+        assert!(
+            (approx - exact).abs() <= 0.01 * exact + 1e-9,
+            "q{}: {} vs {}",
+            q,
+            approx,
+            exact
+        );
+    }
+    assert_eq!(sketch.quantile(0.0), sorted[0]);
+    assert_eq!(sketch.quantile(1.0), sorted[sorted.len() - 1]);
+    assert_eq!(sketch.len(), 100_000);
+    assert!((sketch.mean() - samples.iter().sum::<f64>() / 100_000.0).abs() < 1e-6);
+    // Memory depends on the value range, not the sample count
+    assert!(sketch.buckets() < 400);
+}
+
+#[test]
+fn merged_sketches_match_one_sketch_over_all_samples() {
+    let samples = latencies(10_000);
+    let mut whole = StreamingStats::default();
+    let mut merged = StreamingStats::default();
+    for window in samples.chunks(1_000) {
+        let mut part = StreamingStats::default();
+        for &latency in window {
+            whole.push(latency);
+            part.push(latency);
+        }
+        merged.merge(&part);
+    }
+
+    assert_eq!(merged.len(), whole.len());
+    for q in [0.5, 0.9, 0.99] {
+        assert_eq!(merged.quantile(q), whole.quantile(q));
+    }
+}
+
+#[test]
+fn negative_latencies_from_clock_skew_are_kept() {
+    let mut sketch = StreamingStats::default();
+    for latency in [-2.0, -1.0, 0.0, 1.0, 2.0] {
+        sketch.push(latency);
+    }
+    assert_eq!(sketch.quantile(0.0), -2.0);
+    assert!((sketch.quantile(0.25) + 1.0).abs() <= 0.01);
+    assert_eq!(sketch.quantile(0.5), 0.0);
+    assert!((sketch.quantile(0.75) - 1.0).abs() <= 0.01);
+    assert_eq!(sketch.mean(), 0.0);
+}
+
+#[test]
+fn summary_uses_percentile_labels() {
+    let mut sketch = StreamingStats::default();
+    assert_eq!(sketch.summary(&[50.0]).count, 0);
+    for latency in [10.0, 20.0, 30.0] {
+        sketch.push(latency);
+    }
+    let summary = sketch.summary(&[50.0, 99.9]);
+    assert_eq!(summary.count, 3);
+    assert_eq!(summary.min_ms, 10.0);
+    assert_eq!(summary.max_ms, 30.0);
+    assert!((summary.percentiles["p50"] - percentile(&[10.0, 20.0, 30.0], 0.5)).abs() <= 0.2);
+    assert!(summary.percentiles.contains_key("p99.9"));
+}
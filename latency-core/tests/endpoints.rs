@@ -0,0 +1,32 @@
+use latency_core::{rank_endpoints, LatencyMeasurement};
+
+fn tagged(sequence_id: u64, latency_ms: i64, endpoint: u16) -> LatencyMeasurement {
+    LatencyMeasurement::new_baseline(sequence_id, 1_000, (1_000 + latency_ms) * 1_000_000)
+        .with_endpoint(endpoint)
+}
+
+#[test]
+fn endpoints_are_ranked_by_median() {
+    let endpoints = vec![
+        "wss://stream.binance.com:9443".to_string(),
+        "wss://data-stream.binance.vision".to_string(),
+        "wss://stream1.binance.com:9443".to_string(),
+    ];
+    let measurements = vec![
+        tagged(0, 30, 0),
+        tagged(1, 10, 1),
+        tagged(2, 32, 0),
+        tagged(3, 12, 1),
+    ];
+
+    let ranked = rank_endpoints(&endpoints, &measurements);
+
+    assert_eq!(ranked[0].endpoint, endpoints[1]);
+    assert_eq!(ranked[0].rank, 1);
+    assert_eq!(ranked[0].median_latency_ms, 11.0);
+    assert_eq!(ranked[1].endpoint, endpoints[0]);
+    assert_eq!(ranked[1].sample_count, 2);
+    // Endpoints that never delivered rank last
+    assert_eq!(ranked[2].endpoint, endpoints[2]);
+    assert_eq!(ranked[2].sample_count, 0);
+}
@@ -0,0 +1,27 @@
+use latency_core::guess_aws_region;
+
+#[test]
+fn regions_are_read_from_ec2_names() {
+    assert_eq!(
+        guess_aws_region("ec2-52-68-1-2.ap-northeast-1.compute.amazonaws.com."),
+        Some("ap-northeast-1".to_string())
+    );
+    assert_eq!(
+        guess_aws_region("ec2-3-80-1-2.compute-1.amazonaws.com"),
+        Some("us-east-1".to_string())
+    );
+    assert_eq!(
+        guess_aws_region("ec2-1-2-3-4.us-gov-west-1.compute.amazonaws.com"),
+        Some("us-gov-west-1".to_string())
+    );
+}
+
+#[test]
+fn other_names_have_no_region() {
+    assert_eq!(
+        guess_aws_region("server-13-225-1-2.nrt57.r.cloudfront.net"),
+        None
+    );
+    assert_eq!(guess_aws_region("s3.amazonaws.com"), None);
+    assert_eq!(guess_aws_region("ap-northeast-1.example.com"), None);
+}
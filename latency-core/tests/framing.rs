@@ -0,0 +1,24 @@
+use latency_core::FramingStats;
+
+#[test]
+fn counts_payload_and_framing_per_message() {
+    let mut stats = FramingStats::default();
+    stats.record(400, 20);
+    stats.record(600, 22);
+    stats.record(20_000, 40);
+
+    assert_eq!(stats.messages, 3);
+    assert_eq!(stats.payload_bytes, 21_000);
+    assert_eq!(stats.framing_bytes, 82);
+    assert_eq!(stats.min_framing_bytes, 20);
+    assert_eq!(stats.max_framing_bytes, 40);
+    assert!((stats.avg_framing_bytes() - 82.0 / 3.0).abs() < 1e-9);
+    assert!((stats.overhead_pct() - 82.0 / 21_000.0 * 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn empty_stats_have_no_overhead() {
+    let stats = FramingStats::default();
+    assert_eq!(stats.avg_framing_bytes(), 0.0);
+    assert_eq!(stats.overhead_pct(), 0.0);
+}
@@ -0,0 +1,75 @@
+use latency_core::{Heatmap, LatencyMeasurement, HEATMAP_BUCKET_BOUNDS_MS};
+
+const SEC: i64 = 1_000_000_000;
+
+/// Received at `secs` after a 10-second boundary with the given latency
+fn received(secs: i64, latency_ms: i64) -> LatencyMeasurement {
+    let received = 1_700_000_000 * SEC + secs * SEC;
+    LatencyMeasurement::new_baseline(0, received / 1_000_000 - latency_ms, received)
+}
+
+#[test]
+fn counts_cells_and_keeps_empty_intervals() {
+    let mut measurements = vec![
+        received(1, 3),
+        received(9, 4),
+        received(9, 180),
+        // Nothing between 10 s and 20 s
+        received(25, 12_000),
+        received(26, -2),
+    ];
+    let mut warmup = received(2, 3);
+    warmup.warmup = true;
+    measurements.push(warmup);
+
+    let heatmap = Heatmap::from_measurements(&measurements, 10);
+    assert_eq!(
+        heatmap.interval_starts,
+        vec![1_700_000_000, 1_700_000_010, 1_700_000_020]
+    );
+    assert!(heatmap
+        .counts
+        .iter()
+        .all(|row| row.len() == HEATMAP_BUCKET_BOUNDS_MS.len()));
+    let bucket = |ms: f64| {
+        HEATMAP_BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&b| b == ms)
+            .unwrap()
+    };
+
+    assert_eq!(heatmap.counts[0][bucket(2.0)], 2);
+    assert_eq!(heatmap.counts[0][bucket(150.0)], 1);
+    assert_eq!(heatmap.counts[0].iter().sum::<u64>(), 3);
+    assert_eq!(heatmap.counts[1].iter().sum::<u64>(), 0);
+    // Beyond the last bound, and below zero
+    assert_eq!(heatmap.counts[2][HEATMAP_BUCKET_BOUNDS_MS.len() - 1], 1);
+    assert_eq!(heatmap.counts[2][0], 1);
+}
+
+#[test]
+fn writes_long_format_csv() {
+    let heatmap = Heatmap::from_measurements(&[received(1, 3), received(11, 3)], 10);
+    let path = std::env::temp_dir().join(format!("heatmap-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+    heatmap.write(path).unwrap();
+    let csv = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "interval_start,elapsed_secs,bucket_min_ms,bucket_max_ms,count"
+    );
+    assert_eq!(lines.len(), 1 + 2 * HEATMAP_BUCKET_BOUNDS_MS.len());
+    assert!(lines.contains(&"1700000000,0,2,5,1"));
+    assert!(lines.contains(&"1700000010,10,2,5,1"));
+    assert!(lines.contains(&"1700000010,10,5000,,0"));
+}
+
+#[test]
+fn empty_without_measurements() {
+    let heatmap = Heatmap::from_measurements(&[], 10);
+    assert!(heatmap.interval_starts.is_empty());
+    assert!(heatmap.counts.is_empty());
+}
@@ -0,0 +1,29 @@
+use latency_core::{LatencyMeasurement, LineProtocol};
+
+#[test]
+fn backbone_point_has_sorted_escaped_tags() {
+    let format = LineProtocol::new(&[
+        ("region", "eu central"),
+        ("mode", "aws-backbone"),
+        ("symbol", "BTC-USDT"),
+    ]);
+    let m =
+        LatencyMeasurement::new_aws_backbone(7, 1_000_000, 1_000_100_000_000, 1_000_250_000_000);
+
+    assert_eq!(
+        format.point(&m),
+        "latency,mode=aws-backbone,region=eu\\ central,symbol=BTC-USDT \
+         sequence_id=7i,end_to_end_latency_ms=250,binance_event_time=1000000i,warmup=false,\
+         backbone_latency_ms=150 1000250000000"
+    );
+}
+
+#[test]
+fn empty_tags_are_left_out() {
+    let format = LineProtocol::new(&[("mode", "baseline"), ("symbol", "")]);
+    let m = LatencyMeasurement::new_baseline(0, 1_000, 1_050_000_000).with_endpoint(2);
+
+    assert!(format
+        .point(&m)
+        .starts_with("latency,mode=baseline,endpoint=2 sequence_id=0i,end_to_end_latency_ms=50,"));
+}
@@ -0,0 +1,44 @@
+use latency_core::{snmp_udp_counter, socket_drops, UdpCounters};
+
+const PROC_NET_UDP: &str = "\
+   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  123: 00000000:1F90 00000000:0000 07 00000000:00034000 00:00000000 00000000  1000        0 48213 2 0000000000000000 17
+  456: 0100007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 1920 2 0000000000000000 0
+";
+
+const SNMP: &str = "\
+Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors
+Udp: 4201 1127 25 5331 21 0 0 0 0
+UdpLite: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors
+UdpLite: 0 0 0 0 0 0 0 0 0
+";
+
+#[test]
+fn socket_drops_are_read_by_inode() {
+    assert_eq!(socket_drops(PROC_NET_UDP, 48213), Some(17));
+    assert_eq!(socket_drops(PROC_NET_UDP, 1920), Some(0));
+    assert_eq!(socket_drops(PROC_NET_UDP, 99), None);
+}
+
+#[test]
+fn host_counters_come_from_the_udp_lines() {
+    assert_eq!(snmp_udp_counter(SNMP, "RcvbufErrors"), Some(21));
+    assert_eq!(snmp_udp_counter(SNMP, "InErrors"), Some(25));
+    assert_eq!(snmp_udp_counter(SNMP, "Missing"), None);
+    assert_eq!(snmp_udp_counter("", "InErrors"), None);
+}
+
+#[test]
+fn drops_are_counted_since_the_start() {
+    let before = UdpCounters {
+        socket_drops: Some(2),
+        rcvbuf_errors: Some(10),
+        in_errors: None,
+    };
+    let after = UdpCounters::parse(PROC_NET_UDP, SNMP, 48213);
+    let stats = after.since(&before, Some(425984));
+    assert_eq!(stats.socket_drops, Some(15));
+    assert_eq!(stats.host_rcvbuf_errors, Some(11));
+    assert_eq!(stats.host_in_errors, None);
+    assert_eq!(stats.recv_buffer_bytes, Some(425984));
+}
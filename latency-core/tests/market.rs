@@ -0,0 +1,44 @@
+use latency_core::{compare_markets, ExperimentResults, LatencyMeasurement, Market};
+
+#[test]
+fn market_is_taken_from_the_stream_host() {
+    assert_eq!(
+        Market::from_url("wss://fstream.binance.com/ws/btcusdt@bookTicker"),
+        Some(Market::Futures)
+    );
+    assert_eq!(
+        Market::from_url("wss://dstream.binance.com/ws/btcusd_perp@aggTrade"),
+        Some(Market::Futures)
+    );
+    assert_eq!(
+        Market::from_url("wss://stream.binance.com:9443/ws/btcusdt@aggTrade"),
+        Some(Market::Spot)
+    );
+    assert_eq!(
+        Market::from_url("wss://data-stream.binance.vision/ws/btcusdt@aggTrade"),
+        Some(Market::Spot)
+    );
+    assert_eq!(Market::from_url("ws://127.0.0.1:9101"), None);
+}
+
+#[test]
+fn results_compare_markets_only_when_both_are_present() {
+    let spot = LatencyMeasurement::new_baseline(0, 1_000, 1_010_000_000).with_market(Market::Spot);
+    let futures = LatencyMeasurement::new_baseline(1, 1_000, 1_004_000_000)
+        .with_transaction_time(999)
+        .with_market(Market::Futures);
+
+    let markets = compare_markets(&[futures.clone(), spot.clone()]);
+    assert_eq!(markets.len(), 2);
+    assert_eq!(markets[0].market, Market::Spot);
+    assert_eq!(markets[0].median_latency_ms, 10.0);
+    assert_eq!(markets[0].exchange_delay_median_ms, None);
+    assert_eq!(markets[1].median_latency_ms, 4.0);
+    assert_eq!(markets[1].exchange_delay_median_ms, Some(1.0));
+
+    let both =
+        ExperimentResults::from_measurements("baseline".to_string(), &[spot.clone(), futures], 0);
+    assert_eq!(both.markets.map(|m| m.len()), Some(2));
+    let spot_only = ExperimentResults::from_measurements("baseline".to_string(), &[spot], 0);
+    assert!(spot_only.markets.is_none());
+}
@@ -0,0 +1,60 @@
+use latency_core::{event_time_nanos, ExperimentResults, LatencyMeasurement};
+
+#[test]
+fn keeps_nanosecond_latencies() {
+    // 2024 epoch times, where f64 milliseconds lose sub-microsecond detail
+    let m = LatencyMeasurement::new_aws_backbone(
+        0,
+        1_700_000_000_000,
+        1_700_000_000_100_000_123,
+        1_700_000_000_100_412_456,
+    );
+    assert_eq!(m.backbone_latency_ns, Some(412_333));
+    assert_eq!(m.end_to_end_latency_ns, 100_412_456);
+    assert_eq!(m.backbone_latency_ms(), Some(0.412333));
+}
+
+#[test]
+fn accepts_microsecond_event_times() {
+    assert_eq!(
+        event_time_nanos(1_700_000_000_123),
+        1_700_000_000_123_000_000
+    );
+    assert_eq!(
+        event_time_nanos(1_700_000_000_123_456),
+        1_700_000_000_123_456_000
+    );
+
+    let m = LatencyMeasurement::new_baseline(0, 1_700_000_000_123_456, 1_700_000_000_125_000_000)
+        .with_transaction_time(1_700_000_000_122_956);
+    assert_eq!(m.end_to_end_latency_ns, 1_544_000);
+    assert_eq!(m.exchange_delay_ms(), Some(0.5));
+}
+
+#[test]
+fn reports_backbone_percentiles_in_microseconds() {
+    let measurements: Vec<LatencyMeasurement> = (0..=100)
+        .map(|i| {
+            LatencyMeasurement::new_aws_backbone(
+                i,
+                1_000,
+                1_000_000_000,
+                1_000_400_000 + i as i64 * 1_000,
+            )
+        })
+        .collect();
+    let results = ExperimentResults::with_percentiles(
+        "aws-backbone".to_string(),
+        &measurements,
+        0,
+        &[50.0, 99.0],
+    );
+
+    let percentiles = results.backbone_percentiles_us.unwrap();
+    assert_eq!(percentiles["p50"], 450.0);
+    assert_eq!(percentiles["p99"], 499.0);
+
+    let baseline = vec![LatencyMeasurement::new_baseline(0, 1_000, 1_010_000_000)];
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &baseline, 0);
+    assert!(results.backbone_percentiles_us.is_none());
+}
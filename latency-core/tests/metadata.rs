@@ -0,0 +1,108 @@
+use latency_core::{
+    ChronyTracking, ClockEstimate, ForwarderSupervision, OneWayDelayTracker, PtpDataSet,
+};
+
+const TRACKING: &str = "\
+Reference ID    : A9FEA97B (169.254.169.123)
+Stratum         : 4
+Ref time (UTC)  : Fri Oct 16 15:00:00 2026
+System time     : 0.000012000 seconds slow of NTP time
+Last offset     : -0.000001234 seconds
+RMS offset      : 0.000023000 seconds
+Frequency       : 1.234 ppm slow
+Residual freq   : +0.000 ppm
+Skew            : 0.012 ppm
+Root delay      : 0.000345000 seconds
+Root dispersion : 0.000123000 seconds
+Update interval : 16.0 seconds
+Leap status     : Normal
+";
+
+#[test]
+fn parses_chronyc_tracking() {
+    let tracking = ChronyTracking::parse(TRACKING).unwrap();
+
+    assert_eq!(tracking.reference, "A9FEA97B (169.254.169.123)");
+    assert_eq!(tracking.stratum, 4);
+    assert!((tracking.system_time_offset_ms + 0.012).abs() < 1e-9);
+    assert!((tracking.rms_offset_ms - 0.023).abs() < 1e-9);
+    assert!((tracking.root_delay_ms - 0.345).abs() < 1e-9);
+    assert!((tracking.root_dispersion_ms - 0.123).abs() < 1e-9);
+    assert_eq!(tracking.leap_status, "Normal");
+}
+
+#[test]
+fn incomplete_output_is_rejected() {
+    assert!(ChronyTracking::parse("506 Cannot talk to daemon\n").is_none());
+}
+
+#[test]
+fn chrony_estimate_bounds_the_corrected_offset() {
+    let estimate = ChronyTracking::parse(TRACKING).unwrap().estimate();
+
+    assert_eq!(estimate.offset_ns, -12_000);
+    // Root dispersion + root delay / 2
+    assert_eq!(estimate.error_bound_ns, 123_000 + 172_500);
+}
+
+#[test]
+fn parses_pmc_current_data_set() {
+    let output = "\
+sending: GET CURRENT_DATA_SET
+\t0a1b2c.fffe.3d4e5f-1 seq 0 RESPONSE MANAGEMENT CURRENT_DATA_SET
+\t\tstepsRemoved     1
+\t\toffsetFromMaster 25.0
+\t\tmeanPathDelay    3100.0
+";
+    let estimate = PtpDataSet::parse(output).unwrap().estimate();
+
+    assert_eq!(estimate.offset_ns, 25);
+    assert_eq!(estimate.error_bound_ns, 1_550);
+    assert!(PtpDataSet::parse("sending: GET CURRENT_DATA_SET\n").is_none());
+}
+
+#[test]
+fn one_way_delay_removes_both_offsets() {
+    let mut tracker = OneWayDelayTracker::new();
+    assert!(tracker.results().is_none());
+
+    // Forwarder 1 ms behind, receiver 0.5 ms ahead: 12 ms measured, 10.5 ms true
+    let forwarder = ClockEstimate {
+        offset_ns: -1_000_000,
+        error_bound_ns: 100_000,
+    };
+    let receiver = ClockEstimate {
+        offset_ns: 500_000,
+        error_bound_ns: 200_000,
+    };
+    tracker.record(1_000_000_000, 1_012_000_000, forwarder, receiver);
+    let one_way = tracker.results().unwrap();
+
+    assert!((one_way.delay.avg_ms - 10.5).abs() < 1e-9);
+    assert!((one_way.avg_correction_ms - 1.5).abs() < 1e-9);
+    assert!((one_way.max_error_bound_ms - 0.3).abs() < 1e-9);
+    assert!((one_way.forwarder_error_bound_ms - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn restarts_count_process_starts_and_in_process_restarts() {
+    let earlier = ForwarderSupervision {
+        process_starts: 1,
+        restarts: 2,
+        panics: 0,
+        last_error: None,
+        session_uptime_secs: 60.0,
+        events_forwarded: 1_000,
+    };
+    let later = ForwarderSupervision {
+        process_starts: 2,
+        restarts: 3,
+        panics: 1,
+        last_error: Some("panic: boom".to_string()),
+        session_uptime_secs: 5.0,
+        events_forwarded: 1_200,
+    };
+    assert_eq!(later.restarts_since(&earlier), 2);
+    assert_eq!(earlier.restarts_since(&later), 0);
+    assert_eq!(later.restarts_since(&later), 0);
+}
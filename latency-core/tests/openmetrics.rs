@@ -0,0 +1,98 @@
+use latency_core::{
+    render_openmetrics, validate_bucket_bounds, ExperimentResults, LatencyHistogram,
+    LatencyMeasurement,
+};
+
+const EVENT_TIME_MS: i64 = 1_700_000_000_000;
+
+/// Baseline measurement `latency_ms` after its event time
+fn baseline(sequence_id: u64, latency_ms: f64) -> LatencyMeasurement {
+    let event_time = EVENT_TIME_MS + sequence_id as i64;
+    let received = event_time * 1_000_000 + (latency_ms * 1_000_000.0) as i64;
+    LatencyMeasurement::new_baseline(sequence_id, event_time, received)
+}
+
+fn results(setup_type: &str, measurements: &[LatencyMeasurement]) -> ExperimentResults {
+    let mut results = ExperimentResults::from_measurements(setup_type.to_string(), measurements, 0);
+    results.region = Some("frankfurt".to_string());
+    results
+}
+
+#[test]
+fn buckets_are_cumulative_and_inclusive() {
+    let histogram =
+        LatencyHistogram::from_latencies(&[0.5, 1.0, 3.0, 7.0, 12.0], &[1.0, 5.0, 10.0]);
+    assert_eq!(histogram.cumulative_counts, vec![2, 3, 4]);
+    assert_eq!(histogram.count, 5);
+    assert!((histogram.sum_ms - 23.5).abs() < 1e-9);
+    assert!(!histogram.negative);
+}
+
+#[test]
+fn renders_an_openmetrics_histogram() {
+    let mut measurements = vec![baseline(1, 0.5), baseline(2, 4.0), baseline(3, 7.0)];
+    let mut warmup = baseline(4, 100.0);
+    warmup.warmup = true;
+    measurements.push(warmup);
+
+    let text = render_openmetrics(
+        &results("baseline", &measurements),
+        &measurements,
+        &[1.0, 5.0],
+    );
+    let labels = r#"setup="baseline",region="frankfurt",leg="end_to_end""#;
+    let expected = format!(
+        "# TYPE latency_seconds histogram\n\
+         # UNIT latency_seconds seconds\n\
+         # HELP latency_seconds Event latency: end_to_end from the exchange event time to the receiver, backbone from the forwarder to the receiver\n\
+         latency_seconds_bucket{{{labels},le=\"0.001\"}} 1\n\
+         latency_seconds_bucket{{{labels},le=\"0.005\"}} 2\n\
+         latency_seconds_bucket{{{labels},le=\"+Inf\"}} 3\n\
+         latency_seconds_count{{{labels}}} 3\n\
+         latency_seconds_sum{{{labels}}} 0.0115\n\
+         # EOF\n"
+    );
+    assert_eq!(text, expected);
+}
+
+#[test]
+fn backbone_runs_add_the_backbone_leg() {
+    let event_time = EVENT_TIME_MS;
+    let tokyo = event_time * 1_000_000 + 2_000_000;
+    let measurements = vec![LatencyMeasurement::new_aws_backbone(
+        1,
+        event_time,
+        tokyo,
+        tokyo + 200_000_000,
+    )];
+    let text = render_openmetrics(
+        &results("aws-backbone", &measurements),
+        &measurements,
+        &[100.0, 1000.0],
+    );
+    assert!(text.contains(
+        r#"latency_seconds_bucket{setup="aws-backbone",region="frankfurt",leg="backbone",le="1.0"} 1"#
+    ));
+    assert!(text.contains(
+        r#"latency_seconds_bucket{setup="aws-backbone",region="frankfurt",leg="end_to_end",le="0.1"} 0"#
+    ));
+    assert!(text.ends_with("# EOF\n"));
+}
+
+#[test]
+fn no_sum_with_negative_latencies() {
+    // A receiver clock behind the exchange's
+    let measurements = vec![baseline(1, -0.5), baseline(2, 4.0)];
+    let text = render_openmetrics(&results("baseline", &measurements), &measurements, &[1.0]);
+    assert!(text.contains("latency_seconds_count{"));
+    assert!(!text.contains("latency_seconds_sum{"));
+}
+
+#[test]
+fn bucket_bounds_must_ascend() {
+    assert!(validate_bucket_bounds(&[1.0, 5.0, 10.0]).is_ok());
+    assert!(validate_bucket_bounds(&[]).is_err());
+    assert!(validate_bucket_bounds(&[5.0, 1.0]).is_err());
+    assert!(validate_bucket_bounds(&[1.0, 1.0]).is_err());
+    assert!(validate_bucket_bounds(&[1.0, f64::INFINITY]).is_err());
+}
@@ -0,0 +1,91 @@
+use latency_core::{Arrival, PathRace, PATH_RACE_WINDOW};
+
+#[test]
+fn first_arrival_wins_and_margin_is_recorded() {
+    let mut race = PathRace::new(&["udp", "tcp"]);
+
+    assert_eq!(race.record_arrival(1, "udp", 1_000_000), Arrival::First);
+    assert_eq!(race.record_arrival(1, "tcp", 3_000_000), Arrival::Duplicate);
+    assert_eq!(race.record_arrival(2, "tcp", 5_000_000), Arrival::First);
+    assert_eq!(race.record_arrival(2, "udp", 5_500_000), Arrival::Duplicate);
+    assert_eq!(race.record_arrival(3, "udp", 9_000_000), Arrival::First);
+
+    let results = race.results();
+    assert_eq!(results[0].path, "udp");
+    assert_eq!(results[0].wins, 1);
+    assert_eq!(results[0].avg_win_margin_ms, 2.0);
+    assert_eq!(results[0].only_delivery, 1);
+    assert_eq!(results[1].path, "tcp");
+    assert_eq!(results[1].wins, 1);
+    assert_eq!(results[1].avg_win_margin_ms, 0.5);
+    assert_eq!(results[1].only_delivery, 0);
+}
+
+#[test]
+fn repeats_on_the_same_path_do_not_decide_the_race() {
+    let mut race = PathRace::new(&["udp", "tcp"]);
+
+    assert_eq!(race.record_arrival(7, "udp", 0), Arrival::First);
+    assert_eq!(race.record_arrival(7, "udp", 100), Arrival::Duplicate);
+    assert_eq!(race.record_arrival(7, "tcp", 2_000_000), Arrival::Duplicate);
+    assert_eq!(race.record_arrival(7, "tcp", 3_000_000), Arrival::Duplicate);
+
+    let results = race.results();
+    assert_eq!(results[0].wins, 1);
+    assert_eq!(results[0].max_win_margin_ms, 2.0);
+    assert_eq!(results[0].only_delivery, 0);
+}
+
+#[test]
+fn races_any_number_of_paths() {
+    let labels: Vec<String> = (0..3).map(|i| format!("ws{}", i)).collect();
+    let mut race = PathRace::new(&labels);
+
+    // Keyed by exchange update ID; the second connection to deliver decides the race
+    assert_eq!(race.record_arrival(500, "ws2", 1_000_000), Arrival::First);
+    assert_eq!(
+        race.record_arrival(500, "ws0", 1_400_000),
+        Arrival::Duplicate
+    );
+    assert_eq!(
+        race.record_arrival(500, "ws1", 9_000_000),
+        Arrival::Duplicate
+    );
+    assert_eq!(race.record_arrival(501, "ws1", 2_000_000), Arrival::First);
+
+    let results = race.results();
+    assert_eq!(results.len(), 3);
+    assert_eq!((results[2].wins, results[2].max_win_margin_ms), (1, 0.4));
+    assert_eq!((results[0].wins, results[1].wins), (0, 0));
+    assert_eq!(results[1].only_delivery, 1);
+}
+
+#[test]
+fn old_races_are_forgotten() {
+    let mut race = PathRace::new(&["udp", "tcp"]);
+    let events = PATH_RACE_WINDOW as u64 + 1_000;
+
+    // Every other event is lost on TCP
+    for id in 0..events {
+        assert_eq!(race.record_arrival(id, "udp", 0), Arrival::First);
+        if id % 2 == 0 {
+            assert_eq!(
+                race.record_arrival(id, "tcp", 1_000_000),
+                Arrival::Duplicate
+            );
+        }
+    }
+    let results = race.results();
+    assert_eq!(results[0].wins, events / 2);
+    assert_eq!(results[0].only_delivery, events / 2);
+
+    // Beyond the window a late copy is a new event; within it, a duplicate
+    assert_eq!(race.record_arrival(1, "tcp", 0), Arrival::First);
+    assert_eq!(
+        race.record_arrival(events - 1, "tcp", 0),
+        Arrival::Duplicate
+    );
+    let results = race.results();
+    assert_eq!(results[0].wins, events / 2 + 1);
+    assert_eq!(results[0].only_delivery, events / 2 - 1);
+}
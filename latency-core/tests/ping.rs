@@ -0,0 +1,24 @@
+use latency_core::PingTracker;
+
+#[test]
+fn pong_payload_gives_the_round_trip() {
+    let mut ping = PingTracker::new();
+    assert!(ping.stats(true).is_none());
+
+    let payload = ping.payload(1_000_000_000);
+    assert_eq!(ping.pong(&payload, 1_012_500_000), Some(12.5));
+    let payload = ping.payload(2_000_000_000);
+    assert_eq!(ping.pong(&payload, 2_007_500_000), Some(7.5));
+    ping.payload(3_000_000_000); // Never answered
+
+    // Pongs with a payload we did not send are ignored
+    assert_eq!(ping.pong(b"heartbeat", 3_000_000_000), None);
+
+    let stats = ping.stats(true).unwrap();
+    assert_eq!((stats.sent, stats.answered), (3, 2));
+    assert_eq!(stats.min_ms, 7.5);
+    assert_eq!(stats.max_ms, 12.5);
+    assert_eq!(stats.series.len(), 2);
+    assert_eq!(stats.series[0].sent_at, 1_000_000_000);
+    assert!(ping.stats(false).unwrap().series.is_empty());
+}
@@ -0,0 +1,68 @@
+use latency_core::{quantized_latency, Collector, LatencyMeasurement};
+
+const EVENT_TIME_MS: i64 = 1_700_000_000_000;
+
+#[test]
+fn millisecond_event_times_move_to_the_middle_of_the_interval() {
+    let receive_time = EVENT_TIME_MS * 1_000_000 + 150_300_000;
+    let measurements = vec![
+        LatencyMeasurement::new_aws_backbone(
+            1,
+            EVENT_TIME_MS,
+            EVENT_TIME_MS * 1_000_000 + 40_000_000,
+            receive_time,
+        ),
+        LatencyMeasurement::new_aws_backbone(
+            2,
+            EVENT_TIME_MS,
+            EVENT_TIME_MS * 1_000_000 + 40_000_000,
+            receive_time,
+        ),
+    ];
+    let quantized = quantized_latency(&measurements, &[50.0]).unwrap();
+
+    assert_eq!(quantized.resolution_ms, 1.0);
+    assert_eq!(quantized.bound_ms, 0.5);
+    assert!((quantized.end_to_end.avg_ms - 149.8).abs() < 1e-9);
+    assert!((quantized.percentiles["p50"] - 149.8).abs() < 1e-9);
+    assert!((quantized.tokyo.unwrap().avg_ms - 39.5).abs() < 1e-9);
+    assert!(quantized.arrival_delta.is_none());
+}
+
+#[test]
+fn microsecond_event_times_have_a_microsecond_bound() {
+    let event_time_us = EVENT_TIME_MS * 1_000;
+    let measurements = vec![LatencyMeasurement::new_baseline(
+        1,
+        event_time_us,
+        event_time_us * 1_000 + 2_000_000,
+    )];
+    let quantized = quantized_latency(&measurements, &[]).unwrap();
+
+    assert_eq!(quantized.resolution_ms, 0.001);
+    assert!((quantized.end_to_end.avg_ms - 1.9995).abs() < 1e-9);
+    assert!(quantized.tokyo.is_none());
+}
+
+#[test]
+fn collector_reports_quantization_only_when_asked() {
+    let measurement =
+        LatencyMeasurement::new_baseline(1, EVENT_TIME_MS, EVENT_TIME_MS * 1_000_000 + 1_000_000);
+
+    let report = Collector::new().replay("baseline", vec![measurement.clone()]);
+    assert!(report.results.quantization.is_none());
+
+    let report = Collector::new()
+        .with_quantization()
+        .replay("baseline", vec![measurement]);
+    let quantized = report.results.quantization.unwrap();
+    assert!((quantized.end_to_end.avg_ms - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn warmup_only_runs_have_nothing_to_report() {
+    let mut measurement =
+        LatencyMeasurement::new_baseline(1, EVENT_TIME_MS, EVENT_TIME_MS * 1_000_000);
+    measurement.warmup = true;
+    assert!(quantized_latency(&[measurement], &[50.0]).is_none());
+}
@@ -0,0 +1,17 @@
+use latency_core::QueueMonitor;
+use std::time::Duration;
+
+#[test]
+fn only_full_pushes_count_as_blocked() {
+    let monitor = QueueMonitor::new();
+    monitor.record(1, None);
+    monitor.record(8, Some(Duration::from_micros(1500)));
+    monitor.record(3, Some(Duration::from_micros(500)));
+
+    let stats = monitor.results(8);
+    assert_eq!(stats.capacity, 8);
+    assert_eq!(stats.frames, 3);
+    assert_eq!(stats.max_depth, 8);
+    assert_eq!(stats.full_pushes, 2);
+    assert_eq!(stats.blocked_ms, 2.0);
+}
@@ -0,0 +1,47 @@
+use latency_core::{rate_buckets, LatencyMeasurement};
+
+const SECOND: i64 = 1_000_000_000;
+
+/// `count` events spread over the second starting at `second`, each with `latency_ms`
+fn burst(second: i64, count: i64, latency_ms: i64) -> Vec<LatencyMeasurement> {
+    (0..count)
+        .map(|i| {
+            let receive_time = second * SECOND + i * (SECOND / count);
+            let event_time = receive_time / 1_000_000 - latency_ms;
+            LatencyMeasurement::new_baseline(0, event_time, receive_time)
+        })
+        .collect()
+}
+
+#[test]
+fn buckets_latency_by_events_in_the_same_second() {
+    let mut measurements = burst(1_700_000_000, 5, 2);
+    measurements.extend(burst(1_700_000_001, 5, 2));
+    measurements.extend(burst(1_700_000_002, 200, 8));
+
+    let buckets = rate_buckets(&measurements, &[50.0]);
+
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0].min_events_per_sec, 0);
+    assert_eq!(buckets[0].max_events_per_sec, Some(10));
+    assert_eq!(buckets[0].seconds, 2);
+    assert_eq!(buckets[0].sample_count, 10);
+    assert_eq!(buckets[0].percentiles["p50"], 2.0);
+    assert_eq!(buckets[1].min_events_per_sec, 100);
+    assert_eq!(buckets[1].sample_count, 200);
+    assert_eq!(buckets[1].avg_latency_ms, 8.0);
+}
+
+#[test]
+fn warmup_counts_toward_rate_only() {
+    let mut measurements = burst(1_700_000_000, 20, 3);
+    for m in &mut measurements[..15] {
+        m.warmup = true;
+    }
+
+    let buckets = rate_buckets(&measurements, &[99.0]);
+
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0].min_events_per_sec, 10);
+    assert_eq!(buckets[0].sample_count, 5);
+}
@@ -0,0 +1,81 @@
+use latency_core::{ExperimentResults, LatencyMeasurement, Market, Report};
+use std::sync::Arc;
+
+fn measurements() -> Vec<LatencyMeasurement> {
+    (0..120)
+        .map(|i| {
+            let received = 1_700_000_000_000_000_000 + i as i64 * 250_000_000;
+            let tokyo = received - 90_000_000 - i as i64 * 1_000;
+            LatencyMeasurement::new_aws_backbone(
+                i,
+                (tokyo - 5_000_000) / 1_000_000,
+                tokyo,
+                received,
+            )
+            .with_market(Market::Spot)
+            .with_frame_bytes(180 + i as u32)
+            .with_packet_bytes(420 + i as u32)
+            .with_send_queue_delay(i as i64 * 1_000)
+            .with_forwarding_overhead(20_000 + i as i64)
+            .with_source(Arc::from(if i % 2 == 0 { "tokyo-a" } else { "tokyo-b" }))
+        })
+        .collect()
+}
+
+#[test]
+fn csv_round_trip() {
+    let measurements = measurements();
+    let path = std::env::temp_dir().join(format!("report-round-trip-{}.csv", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    LatencyMeasurement::write_to_csv(&measurements, path).unwrap();
+    let csv = std::fs::read_to_string(path).unwrap();
+    let read = LatencyMeasurement::read_from_csv(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    // Schema comment, column names, then rows with the derived columns last
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().starts_with("# "));
+    assert!(lines
+        .next()
+        .unwrap()
+        .ends_with(",binance_tokyo_ms,tokyo_processing_ns"));
+    assert!(lines.next().unwrap().ends_with(",5.000,20000"));
+
+    assert_eq!(read.len(), measurements.len());
+    for (read, written) in read.iter().zip(&measurements) {
+        assert_eq!(read.sequence_id, written.sequence_id);
+        assert_eq!(read.end_to_end_latency_ns, written.end_to_end_latency_ns);
+        assert_eq!(read.backbone_latency_ns, written.backbone_latency_ns);
+        assert_eq!(read.market, Some(Market::Spot));
+        assert_eq!(read.frame_bytes, written.frame_bytes);
+        assert_eq!(read.packet_bytes, written.packet_bytes);
+        assert_eq!(read.send_queue_delay_ns, written.send_queue_delay_ns);
+        assert_eq!(read.source, written.source);
+        assert_eq!(read.forwarding_overhead_ns, written.forwarding_overhead_ns);
+    }
+}
+
+#[test]
+fn html_report_has_charts_and_percentiles() {
+    let measurements = measurements();
+    let results =
+        ExperimentResults::from_measurements("aws-backbone".to_string(), &measurements, 0);
+    let path = std::env::temp_dir().join(format!("report-{}.html", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    Report::new(results.clone(), measurements)
+        .write_html(path)
+        .unwrap();
+    let html = std::fs::read_to_string(path).unwrap();
+    assert_eq!(html.matches("<svg").count(), 2);
+    assert!(html.contains("<td>p99.9</td>"));
+    assert!(html.contains("No sequence gaps"));
+
+    // Without the CSV only the tables and loss timeline are left
+    Report::new(results, Vec::new()).write_html(path).unwrap();
+    let html = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(!html.contains("<svg"));
+    assert!(html.contains("<td>p50</td>"));
+}
@@ -0,0 +1,77 @@
+use latency_core::{ExperimentResults, LatencyMeasurement, SCHEMA_VERSION};
+
+// results.json as written before results were versioned
+const V1_RESULTS: &str = r#"{
+  "setup_type": "aws-backbone",
+  "sample_count": 3,
+  "events_lost": 1,
+  "avg_latency_ms": 151.0,
+  "median_latency_ms": 150.5,
+  "p95_latency_ms": 152.5,
+  "p99_latency_ms": 152.9,
+  "min_latency_ms": 150.0,
+  "max_latency_ms": 153.0,
+  "jitter_stddev_ms": 1.2,
+  "backbone_avg_latency_ms": 110.0,
+  "backbone_median_latency_ms": 109.5
+}"#;
+
+#[test]
+fn migrates_v1_results() {
+    let results = ExperimentResults::from_json(V1_RESULTS).unwrap();
+
+    assert_eq!(results.schema_version, SCHEMA_VERSION);
+    assert_eq!(results.setup_type, "aws-backbone");
+    assert_eq!(results.sample_count, 3);
+    assert_eq!(results.events_lost, 1);
+    assert_eq!(results.backbone_median_latency_ms, Some(109.5));
+    assert_eq!(results.warmup_samples, 0);
+    assert_eq!(results.reconnects, 0);
+    assert!(results.gaps.is_empty());
+    assert!(results.region.is_none());
+    assert_eq!(results.percentiles["p50"], 150.5);
+    assert_eq!(results.percentiles["p95"], 152.5);
+    assert_eq!(results.percentiles["p99"], 152.9);
+}
+
+#[test]
+fn migrates_v2_results() {
+    let mut v2: serde_json::Value = serde_json::from_str(V1_RESULTS).unwrap();
+    v2["schema_version"] = 2.into();
+    v2["warmup_samples"] = 0.into();
+    v2["reconnects"] = 2.into();
+    v2["outage_ms"] = 1500.0.into();
+    v2["percentiles"] = serde_json::json!({"p50": 150.5, "p99.9": 153.0});
+    v2["gaps"] = serde_json::json!([]);
+
+    let results = ExperimentResults::from_json(&v2.to_string()).unwrap();
+    assert_eq!(results.schema_version, SCHEMA_VERSION);
+    assert_eq!(results.reconnects, 2);
+    assert_eq!(results.percentiles["p99.9"], 153.0);
+    assert_eq!(results.duplicates, 0);
+    assert_eq!(results.reordered, 0);
+    assert_eq!(results.max_reorder_distance, 0);
+}
+
+#[test]
+fn current_results_round_trip() {
+    let measurements: Vec<_> = (0..10)
+        .map(|i| LatencyMeasurement::new_baseline(i, 1_000, 1_000_000_000 + i as i64 * 1_000_000))
+        .collect();
+    let mut results = ExperimentResults::from_measurements("baseline".into(), &measurements, 0);
+    results.region = Some("frankfurt".into());
+
+    let loaded = ExperimentResults::from_json(&serde_json::to_string(&results).unwrap()).unwrap();
+    assert_eq!(loaded.schema_version, SCHEMA_VERSION);
+    assert_eq!(loaded.region.as_deref(), Some("frankfurt"));
+    assert_eq!(loaded.sample_count, 10);
+    assert_eq!(loaded.percentiles, results.percentiles);
+    assert_eq!(loaded.median_latency_ms, results.median_latency_ms);
+}
+
+#[test]
+fn rejects_newer_schema() {
+    let json = format!(r#"{{"schema_version": {}}}"#, SCHEMA_VERSION + 1);
+    let error = ExperimentResults::from_json(&json).unwrap_err();
+    assert!(error.to_string().contains("newer than this build supports"));
+}
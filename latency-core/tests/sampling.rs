@@ -0,0 +1,123 @@
+use latency_core::{Collector, LatencyMeasurement, SampleRate};
+
+const EVENT_TIME_MS: i64 = 1_700_000_000_000;
+
+/// Measurement `sequence_id` received `latency_ms` after its event time
+fn measurement(sequence_id: u64, latency_ms: i64) -> LatencyMeasurement {
+    let event_time = EVENT_TIME_MS + sequence_id as i64;
+    LatencyMeasurement::new_baseline(
+        sequence_id,
+        event_time,
+        (event_time + latency_ms) * 1_000_000,
+    )
+}
+
+#[test]
+fn rate_as_a_fraction_or_one_in_n() {
+    assert_eq!("0.1".parse::<SampleRate>().unwrap().rate(), 0.1);
+    assert_eq!("1/10".parse::<SampleRate>().unwrap().rate(), 0.1);
+    assert_eq!("1".parse::<SampleRate>().unwrap().rate(), 1.0);
+    for invalid in ["0", "1.5", "-0.1", "1/0", "ten", "1/x"] {
+        assert!(invalid.parse::<SampleRate>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn the_same_events_are_kept_every_time() {
+    let rate = SampleRate::new(0.1).unwrap();
+    let kept: Vec<u64> = (0..100_000).filter(|&id| rate.keeps(id)).collect();
+    assert!((9_000..11_000).contains(&kept.len()), "{}", kept.len());
+    assert!(kept.iter().all(|&id| rate.keeps(id)));
+    let all = SampleRate::new(1.0).unwrap();
+    assert!((0..1_000).all(|id| all.keeps(id)));
+}
+
+#[test]
+fn counters_and_sketches_see_every_event() {
+    // Every event is 10 ms except every 100th, at 1 s; event 500 never arrives
+    let measurements: Vec<LatencyMeasurement> = (0..10_000)
+        .filter(|&id| id != 500)
+        .map(|id| measurement(id, if id % 100 == 99 { 1_000 } else { 10 }))
+        .collect();
+    let report = Collector::new()
+        .with_streaming_stats(0.01)
+        .with_sample_rate(SampleRate::new(0.25).unwrap())
+        .replay("baseline", measurements);
+    let results = &report.results;
+
+    let sampling = results.sampling.unwrap();
+    assert_eq!(sampling.sample_rate, 0.25);
+    assert_eq!(sampling.events, 9_999);
+    assert_eq!(sampling.retained as usize, results.sample_count);
+    assert_eq!(report.measurements.len(), results.sample_count);
+    assert!((20.0..30.0).contains(&sampling.retained_pct()));
+    assert_eq!(results.events_lost, 1);
+
+    let whole_run = &results.streaming.as_ref().unwrap().since_start;
+    assert_eq!(whole_run.count, 9_999);
+    assert!((whole_run.max_ms - 1_000.0).abs() < 10.0);
+    assert!((results.median_latency_ms - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn no_sampling_section_without_a_rate() {
+    let report = Collector::new().replay("baseline", vec![measurement(1, 10)]);
+    assert!(report.results.sampling.is_none());
+}
+
+#[test]
+fn last_measurement_only_when_kept() {
+    let rate = SampleRate::new(0.5).unwrap();
+    let dropped = (0..).find(|&id| !rate.keeps(id)).unwrap();
+    let kept = (0..).find(|&id| rate.keeps(id)).unwrap();
+
+    let mut collector = Collector::new().with_sample_rate(rate);
+    collector.record(measurement(kept, 10));
+    assert_eq!(collector.last_measurement().unwrap().sequence_id, kept);
+    collector.record(measurement(dropped, 10));
+    assert!(collector.last_measurement().is_none());
+    assert_eq!(collector.len(), 1);
+}
+
+#[test]
+fn sampled_windows_count_like_unsampled_ones() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64;
+    // Arrival order over the last two minutes: 901 before 900, 1000 after
+    // 1003, 950 twice and 1100 never
+    let mut arrivals: Vec<u64> = (0..1_200).filter(|&id| id != 1_100).collect();
+    arrivals.swap(900, 901);
+    let late = arrivals.remove(1_000);
+    arrivals.insert(1_003, late);
+    arrivals.insert(961, 950);
+    let measurements: Vec<LatencyMeasurement> = arrivals
+        .iter()
+        .enumerate()
+        .map(|(i, &sequence_id)| {
+            let receive_time = now - (1_200 - i as i64) * 100_000_000;
+            let event_time = receive_time / 1_000_000 - 10;
+            LatencyMeasurement::new_baseline(sequence_id, event_time, receive_time)
+        })
+        .collect();
+
+    let window = std::time::Duration::from_secs(60);
+    let unsampled = Collector::new()
+        .with_window(window)
+        .replay("baseline", measurements.clone())
+        .results;
+    let sampled = Collector::new()
+        .with_window(window)
+        .with_sample_rate(SampleRate::new(0.1).unwrap())
+        .replay("baseline", measurements)
+        .results;
+
+    assert!(sampled.sample_count < unsampled.sample_count);
+    assert_eq!(unsampled.events_lost, 1);
+    assert_eq!(unsampled.duplicates, 1);
+    assert_eq!(unsampled.reordered, 2);
+    assert_eq!(sampled.events_lost, unsampled.events_lost);
+    assert_eq!(sampled.duplicates, unsampled.duplicates);
+    assert_eq!(sampled.reordered, unsampled.reordered);
+}
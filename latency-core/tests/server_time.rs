@@ -0,0 +1,59 @@
+use latency_core::{LatencyMeasurement, ServerTimeCorrection, ServerTimeSample};
+
+const SENT: i64 = 1_700_000_000_000_000_000;
+
+#[test]
+fn offset_takes_the_answer_as_stamped_halfway() {
+    // 20 ms round trip; the exchange said 1 ms after we sent, so it read
+    // 1.5 ms on average against our 10 ms
+    let sample = ServerTimeSample::new(SENT, SENT / 1_000_000 + 1, SENT + 20_000_000);
+    assert!((sample.rtt_ms - 20.0).abs() < 1e-9);
+    assert!((sample.offset_ms + 8.5).abs() < 1e-9);
+    assert_eq!(sample.offset_ns(), -8_500_000);
+    assert!((sample.error_bound_ms() - 10.5).abs() < 1e-9);
+}
+
+#[test]
+fn correction_moves_latencies_onto_the_exchange_clock() {
+    // The exchange is 5.5 ms ahead, so events look 5.5 ms older than they are
+    let sample = ServerTimeSample::new(SENT, SENT / 1_000_000 + 5, SENT);
+    let event_ms = SENT / 1_000_000;
+    let mut applied = ServerTimeCorrection::new(true);
+    let mut kept = ServerTimeCorrection::new(false);
+    for correction in [&mut applied, &mut kept] {
+        let mut early = LatencyMeasurement::new_baseline(0, event_ms + 10, SENT + 7_000_000);
+        correction.record(&mut early, Some(sample));
+        let mut late = LatencyMeasurement::new_baseline(1, event_ms, SENT + 2_000_000);
+        correction.record(&mut late, Some(sample));
+        let mut unknown = LatencyMeasurement::new_baseline(2, event_ms, SENT + 2_000_000);
+        correction.record(&mut unknown, None);
+        assert_eq!(unknown.end_to_end_latency_ns, 2_000_000);
+        if correction.results("", vec![sample], 0).unwrap().applied {
+            assert_eq!(early.end_to_end_latency_ns, 2_500_000);
+            assert_eq!(late.end_to_end_latency_ns, 7_500_000);
+        } else {
+            assert_eq!(early.end_to_end_latency_ns, -3_000_000);
+            assert_eq!(late.end_to_end_latency_ns, 2_000_000);
+        }
+    }
+
+    let stats = applied
+        .results("http://exchange/api/v3/time", vec![sample], 2)
+        .unwrap();
+    let corrected = stats.corrected.unwrap();
+    assert_eq!(corrected.count, 2);
+    assert!((corrected.avg_ms - 5.0).abs() < 1e-9);
+    assert_eq!(stats.uncorrected_events, 1);
+    assert_eq!(stats.negative_latencies, 1);
+    assert_eq!(stats.negative_corrected, 0);
+    assert_eq!(stats.failures, 2);
+    assert!(stats.skewed());
+}
+
+#[test]
+fn no_results_without_an_answer() {
+    let correction = ServerTimeCorrection::new(true);
+    assert!(correction
+        .results("http://exchange", Vec::new(), 3)
+        .is_none());
+}
@@ -0,0 +1,68 @@
+use latency_core::{session_stats, Collector, LatencyMeasurement, SessionSplit};
+
+const HOUR_NS: i64 = 3_600_000_000_000;
+const MIDNIGHT: i64 = 1_700_006_400 * 1_000_000_000; // 2023-11-15T00:00:00Z
+
+/// A measurement received `hours` after midnight UTC with `latency_ms` end to end
+fn at(sequence_id: u64, hours: f64, latency_ms: i64) -> LatencyMeasurement {
+    let received = MIDNIGHT + (hours * HOUR_NS as f64) as i64;
+    let event_time_ms = (received - latency_ms * 1_000_000) / 1_000_000;
+    LatencyMeasurement::new_aws_backbone(sequence_id, event_time_ms, received - 1_000, received)
+}
+
+#[test]
+fn hourly_buckets_need_an_hour_of_run() {
+    let short = [at(0, 7.1, 100), at(1, 7.9, 100)];
+    assert!(session_stats(&short, &SessionSplit::Hourly, &[50.0]).is_empty());
+
+    let measurements = [at(0, 7.5, 100), at(1, 7.75, 120), at(2, 8.5, 300)];
+    let sessions = session_stats(&measurements, &SessionSplit::Hourly, &[50.0]);
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].session, "07:00");
+    assert_eq!(sessions[0].sample_count, 2);
+    assert_eq!(sessions[0].covered_secs, 1_800.0);
+    assert_eq!(sessions[1].session, "08:00");
+    assert_eq!(sessions[1].max_latency_ms, 300.0);
+    assert_eq!(sessions[1].covered_secs, 1_800.0);
+}
+
+#[test]
+fn named_windows_may_overlap_and_wrap() {
+    let split: SessionSplit = "late=22-2,europe=7-16".parse().unwrap();
+    let measurements = [at(0, 23.0, 50), at(1, 25.0, 70), at(2, 31.0, 90)];
+    let sessions = session_stats(&measurements, &split, &[50.0]);
+
+    assert_eq!(sessions[0].session, "late");
+    assert_eq!(sessions[0].sample_count, 2);
+    // 23:00 to 02:00 of the next day
+    assert_eq!(sessions[0].covered_secs, 3.0 * 3_600.0);
+    assert_eq!(sessions[1].session, "europe");
+    assert_eq!(sessions[1].sample_count, 1);
+
+    let markets = session_stats(&measurements, &SessionSplit::markets(), &[50.0]);
+    let names: Vec<&str> = markets.iter().map(|s| s.session.as_str()).collect();
+    assert_eq!(names, ["asia", "europe"]);
+}
+
+#[test]
+fn invalid_windows_are_rejected() {
+    for split in [
+        "asia",
+        "asia=0-25",
+        "asia=24-3",
+        "asia=3-3",
+        "=1-2",
+        "a=0-1,a=2-3",
+    ] {
+        assert!(split.parse::<SessionSplit>().is_err(), "{}", split);
+    }
+}
+
+#[test]
+fn collector_reports_sessions() {
+    let mut collector = Collector::new().with_sessions("day=0-24".parse().unwrap());
+    collector.record(at(0, 1.0, 100));
+    let report = collector.finish("aws-backbone");
+    let sessions = report.results.sessions.unwrap();
+    assert_eq!(sessions[0].sample_count, 1);
+}
@@ -0,0 +1,35 @@
+use latency_core::{shard_imbalance, ReceiveQueueStats, ShardLoad};
+
+fn load(shard: usize, frames: u64, cpu_ms: Option<f64>) -> ShardLoad {
+    ShardLoad {
+        shard,
+        frames,
+        bytes: frames * 300,
+        busy_ms: 250.0,
+        cpu_ms,
+        wall_ms: 1000.0,
+        queue: ReceiveQueueStats {
+            capacity: 1024,
+            frames,
+            max_depth: 3,
+            full_pushes: 0,
+            blocked_ms: 0.0,
+        },
+    }
+}
+
+#[test]
+fn utilization_prefers_cpu_time() {
+    assert!((load(0, 10, Some(400.0)).utilization_pct() - 40.0).abs() < 1e-9);
+    // Without a CPU clock, the time spent decoding stands in
+    assert!((load(0, 10, None).utilization_pct() - 25.0).abs() < 1e-9);
+}
+
+#[test]
+fn imbalance_compares_the_busiest_shard_with_an_even_split() {
+    let even = [load(0, 500, None), load(1, 500, None)];
+    assert!((shard_imbalance(&even) - 1.0).abs() < 1e-9);
+    let skewed = [load(0, 900, None), load(1, 100, None)];
+    assert!((shard_imbalance(&skewed) - 1.8).abs() < 1e-9);
+    assert_eq!(shard_imbalance(&[load(0, 0, None)]), 1.0);
+}
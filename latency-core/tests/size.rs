@@ -0,0 +1,58 @@
+use latency_core::{size_buckets, ExperimentResults, LatencyMeasurement};
+
+/// A backbone event with `backbone_ms` on the backbone and 5 ms before it
+fn forwarded(frame_bytes: u32, packet_bytes: u32, backbone_ms: i64) -> LatencyMeasurement {
+    let tokyo = 1_700_000_000_000_000_000;
+    LatencyMeasurement::new_aws_backbone(
+        0,
+        tokyo / 1_000_000 - 5,
+        tokyo,
+        tokyo + backbone_ms * 1_000_000,
+    )
+    .with_frame_bytes(frame_bytes)
+    .with_packet_bytes(packet_bytes)
+}
+
+#[test]
+fn buckets_frames_by_end_to_end_and_packets_by_backbone_latency() {
+    let mut measurements: Vec<_> = (0..10).map(|_| forwarded(200, 600, 90)).collect();
+    // Past one 1500-byte MTU datagram, fragmented on the backbone
+    measurements.extend((0..5).map(|_| forwarded(1400, 1800, 95)));
+
+    let sizes = size_buckets(&measurements, &[50.0]).unwrap();
+
+    assert_eq!(sizes.frame.len(), 2);
+    assert_eq!(sizes.frame[0].min_bytes, 0);
+    assert_eq!(sizes.frame[0].max_bytes, Some(256));
+    assert_eq!(sizes.frame[0].sample_count, 10);
+    assert_eq!(sizes.frame[0].avg_latency_ms, 95.0);
+    assert_eq!(sizes.frame[1].min_bytes, 1024);
+
+    assert_eq!(sizes.packet.len(), 2);
+    assert_eq!(sizes.packet[0].min_bytes, 512);
+    assert_eq!(sizes.packet[0].percentiles["p50"], 90.0);
+    assert_eq!(sizes.packet[1].min_bytes, 1473);
+    assert_eq!(sizes.packet[1].max_bytes, Some(4096));
+    assert_eq!(sizes.packet[1].avg_latency_ms, 95.0);
+}
+
+#[test]
+fn leaves_out_warmup_and_unsized_measurements() {
+    let mut measurements = vec![forwarded(200, 600, 90), forwarded(9000, 9400, 90)];
+    measurements[1].warmup = true;
+    measurements.push(LatencyMeasurement::new_baseline(
+        0,
+        1_700_000_000_000,
+        1_700_000_000_010_000_000,
+    ));
+
+    let sizes = size_buckets(&measurements, &[50.0]).unwrap();
+    assert_eq!(sizes.frame.len(), 1);
+    assert_eq!(sizes.frame[0].sample_count, 1);
+    assert_eq!(sizes.packet.len(), 1);
+
+    let baseline = &measurements[2..];
+    assert_eq!(size_buckets(baseline, &[50.0]), None);
+    let results = ExperimentResults::from_measurements("baseline".to_string(), baseline, 0);
+    assert!(results.size_buckets.is_none());
+}
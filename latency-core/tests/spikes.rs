@@ -0,0 +1,108 @@
+use latency_core::{Collector, LatencyMeasurement, SpikeContext, SpikeDetector, MAX_SPIKES};
+
+fn context() -> SpikeContext {
+    SpikeContext {
+        elapsed_secs: 1.0,
+        events_per_sec: 100,
+        seq_gap: true,
+        send_queue_delay_ms: None,
+    }
+}
+
+#[test]
+fn flags_large_outlier_after_enough_samples() {
+    let mut detector = SpikeDetector::new(5.0);
+
+    // Not enough history yet: nothing is flagged
+    assert!(detector.check(0, 0, 1_000.0, context()).is_none());
+
+    for i in 1..200u64 {
+        let latency = if i % 2 == 0 { 10.0 } else { 11.0 };
+        assert!(detector.check(i, 0, latency, context()).is_none());
+    }
+
+    let spike = detector
+        .check(200, 42, 100.0, context())
+        .expect("outlier should be flagged");
+    assert_eq!(spike.sequence_id, 200);
+    assert_eq!(spike.receive_time, 42);
+    assert!(spike.seq_gap);
+    assert!(spike.magnitude > 9.0);
+
+    assert!(detector.check(201, 0, 11.5, context()).is_none());
+}
+
+#[test]
+fn jitter_on_a_constant_latency_is_not_a_spike() {
+    let mut detector = SpikeDetector::new(5.0);
+    for i in 0..200u64 {
+        assert!(detector.check(i, 0, 10.0, context()).is_none());
+    }
+
+    // The MAD is zero here, but microsecond jitter must not be flagged
+    for (i, jitter) in [0.001, 0.01, 0.1, 0.5, 2.0].into_iter().enumerate() {
+        let sequence_id = 200 + i as u64;
+        assert!(detector
+            .check(sequence_id, 0, 10.0 + jitter, context())
+            .is_none());
+    }
+
+    let spike = detector.check(300, 0, 13.0, context()).unwrap();
+    assert_eq!(spike.threshold_ms, 12.5);
+}
+
+#[test]
+fn spike_is_attributed_to_the_send_queue() {
+    let mut detector = SpikeDetector::new(5.0);
+    for i in 0..200u64 {
+        detector.check(i, 0, 10.0 + (i % 2) as f64, context());
+    }
+
+    let queued = SpikeContext {
+        send_queue_delay_ms: Some(70.0),
+        ..context()
+    };
+    let spike = detector.check(200, 0, 100.0, queued).unwrap();
+    assert_eq!(spike.send_queue_delay_ms, Some(70.0));
+    assert!(spike.queued());
+
+    let path = SpikeContext {
+        send_queue_delay_ms: Some(2.0),
+        ..context()
+    };
+    assert!(!detector.check(201, 0, 100.0, path).unwrap().queued());
+    assert!(!detector.check(202, 0, 100.0, context()).unwrap().queued());
+}
+
+#[test]
+fn only_the_most_recent_spikes_are_kept() {
+    const SPIKES: u64 = MAX_SPIKES as u64 + 500;
+    let mut collector = Collector::new().with_spike_detection(5.0);
+    let mut logged = 0;
+    // Every tenth event after the first 200 is a spike
+    for sequence_id in 0..200 + 10 * SPIKES {
+        let event_time = 1_700_000_000_000 + sequence_id as i64;
+        let latency_ms = if sequence_id >= 200 && sequence_id % 10 == 0 {
+            1_000
+        } else {
+            10
+        };
+        collector.record(LatencyMeasurement::new_baseline(
+            sequence_id,
+            event_time,
+            (event_time + latency_ms) * 1_000_000,
+        ));
+        logged += collector.new_spikes().count();
+    }
+    assert_eq!(logged as u64, SPIKES);
+
+    let results = collector.finish("baseline").results;
+    assert_eq!(results.spike_count, Some(SPIKES));
+    let spikes = results.spikes.unwrap();
+    assert_eq!(spikes.len(), MAX_SPIKES);
+    assert_eq!(spikes.last().unwrap().sequence_id, 200 + 10 * (SPIKES - 1));
+    assert_eq!(
+        spikes[0].sequence_id,
+        200 + 10 * (SPIKES - MAX_SPIKES as u64)
+    );
+}
@@ -0,0 +1,72 @@
+use latency_core::{
+    send_queue_delay, DeliveryClass, LatencyMeasurement, OverheadTracker, StageBudget,
+};
+
+#[test]
+fn follow_up_completes_the_previous_event() {
+    let mut budget = StageBudget::new();
+
+    // Event 1: frame at 0, parsed at 20 µs, received 2 ms later
+    budget.record(1, 0, 20_000, None, 2_100_000);
+    assert!(budget.results().is_none());
+
+    // Event 2 reports event 1 serialized at 50 µs and sent at 100 µs
+    budget.record(2, 5_000_000, 5_010_000, Some((50_000, 100_000)), 7_000_000);
+
+    let stages = budget.results().unwrap();
+    assert_eq!(stages.events, 1);
+    assert_eq!(stages.parse.median_ms, 0.02);
+    assert_eq!(stages.serialize.median_ms, 0.03);
+    assert_eq!(stages.send.median_ms, 0.05);
+    assert_eq!(stages.network.median_ms, 2.0);
+}
+
+#[test]
+fn follow_up_for_a_lost_event_is_ignored() {
+    let mut budget = StageBudget::new();
+
+    budget.record(1, 0, 10_000, None, 1_000_000);
+    // Event 2 was lost, so event 3's follow-up refers to an unknown event
+    budget.record(3, 0, 10_000, Some((20_000, 30_000)), 1_000_000);
+
+    assert!(budget.results().is_none());
+}
+
+#[test]
+fn overhead_is_compared_with_the_backbone_latency() {
+    let mut overhead = OverheadTracker::new();
+    assert!(overhead.results().is_none());
+
+    overhead.record(100_000, 4_000_000);
+    overhead.record(300_000, 6_000_000);
+
+    let results = overhead.results().unwrap();
+    assert_eq!(results.overhead.count, 2);
+    assert_eq!(results.overhead.avg_ms, 0.2);
+    assert_eq!(results.backbone_avg_ms, 5.0);
+    assert_eq!(results.share_pct, 4.0);
+}
+
+#[test]
+fn send_queue_delay_is_compared_with_the_backbone_latency() {
+    let queued = |sequence_id: u64, backbone_ms: i64, delay_ms: i64| {
+        let tokyo = 1_700_000_000_000_000_000;
+        LatencyMeasurement::new_aws_backbone(
+            sequence_id,
+            tokyo / 1_000_000 - 5,
+            tokyo,
+            tokyo + backbone_ms * 1_000_000,
+        )
+        .with_send_queue_delay(delay_ms * 1_000_000)
+    };
+    let mut measurements = vec![queued(0, 4, 1), queued(1, 6, 3)];
+    assert!(send_queue_delay(&measurements[..0]).is_none());
+
+    // Replayed events waited out an outage, not the queue
+    measurements.push(queued(2, 900, 800).with_delivery_class(DeliveryClass::Buffered));
+    let results = send_queue_delay(&measurements).unwrap();
+    assert_eq!(results.delay.count, 2);
+    assert_eq!(results.delay.avg_ms, 2.0);
+    assert_eq!(results.backbone_avg_ms, 5.0);
+    assert_eq!(results.share_pct, 40.0);
+}
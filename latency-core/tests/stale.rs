@@ -0,0 +1,70 @@
+use latency_core::{stale_stats, Collector, LatencyMeasurement};
+use std::time::Duration;
+
+const EVENT_TIME_MS: i64 = 1_700_000_000_000;
+
+/// Measurement `sequence_id` received `latency_ms` after its event time
+fn measurement(sequence_id: u64, latency_ms: i64) -> LatencyMeasurement {
+    let event_time = EVENT_TIME_MS + sequence_id as i64;
+    LatencyMeasurement::new_baseline(
+        sequence_id,
+        event_time,
+        (event_time + latency_ms) * 1_000_000,
+    )
+}
+
+#[test]
+fn stale_events_leave_the_statistics_but_are_not_lost() {
+    let measurements = vec![
+        measurement(1, 10),
+        measurement(2, 12),
+        measurement(3, 5_000),
+        measurement(4, 11),
+    ];
+    let report = Collector::new()
+        .with_max_staleness(Duration::from_millis(1_000))
+        .replay("baseline", measurements);
+    let results = &report.results;
+
+    assert_eq!(results.sample_count, 3);
+    assert_eq!(results.events_lost, 0);
+    assert_eq!(results.max_latency_ms, 12.0);
+    assert_eq!(report.measurements.len(), 3);
+
+    let stale = results.stale.as_ref().unwrap();
+    assert_eq!(stale.max_staleness_ms, 1_000.0);
+    assert_eq!(stale.stale_events, 1);
+    assert_eq!(stale.share_pct, 25.0);
+    assert_eq!(stale.staleness.max_ms, 5_000.0);
+}
+
+#[test]
+fn stale_section_only_with_a_threshold() {
+    let report = Collector::new().replay("baseline", vec![measurement(1, 5_000)]);
+    assert!(report.results.stale.is_none());
+    assert_eq!(report.results.sample_count, 1);
+
+    let report = Collector::new()
+        .with_max_staleness(Duration::from_millis(1_000))
+        .replay("baseline", vec![measurement(1, 10)]);
+    let stale = report.results.stale.unwrap();
+    assert_eq!(stale.stale_events, 0);
+    assert_eq!(stale.share_pct, 0.0);
+    assert!(stale.percentiles.is_empty());
+}
+
+#[test]
+fn warmup_is_left_out_of_the_share() {
+    let mut warmup = measurement(1, 10);
+    warmup.warmup = true;
+    let stats = stale_stats(
+        100.0,
+        &[measurement(2, 500)],
+        &[warmup, measurement(3, 10)],
+        &[50.0],
+    );
+
+    assert_eq!(stats.stale_events, 1);
+    assert_eq!(stats.share_pct, 50.0);
+    assert_eq!(stats.percentiles["p50"], 500.0);
+}
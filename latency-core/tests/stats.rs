@@ -0,0 +1,105 @@
+use latency_core::{percentile, ExperimentResults, LatencyMeasurement, StatsAggregator};
+
+#[test]
+fn percentile_interpolates_between_ranks() {
+    let sorted = [10.0, 20.0, 30.0, 40.0, 50.0];
+
+    assert_eq!(percentile(&sorted, 0.0), 10.0);
+    assert_eq!(percentile(&sorted, 0.5), 30.0);
+    assert_eq!(percentile(&sorted, 1.0), 50.0);
+    assert!((percentile(&sorted, 0.95) - 48.0).abs() < 1e-9);
+}
+
+#[test]
+fn percentile_handles_empty_and_single() {
+    assert_eq!(percentile(&[], 0.5), 0.0);
+    assert_eq!(percentile(&[7.0], 0.99), 7.0);
+}
+
+#[test]
+fn summary_of_unsorted_samples() {
+    let stats: StatsAggregator = [4.0, 2.0, 8.0, 6.0].into_iter().collect();
+    let summary = stats.summary();
+
+    assert_eq!(summary.count, 4);
+    assert_eq!(summary.avg_ms, 5.0);
+    assert_eq!(summary.median_ms, 5.0);
+    assert_eq!(summary.min_ms, 2.0);
+    assert_eq!(summary.max_ms, 8.0);
+    assert!((summary.stddev_ms - 5.0_f64.sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn empty_summary_is_zeroed() {
+    let summary = StatsAggregator::new().summary();
+    assert_eq!(summary.count, 0);
+    assert_eq!(summary.avg_ms, 0.0);
+    assert_eq!(summary.max_ms, 0.0);
+}
+
+#[test]
+fn results_include_backbone_only_when_present() {
+    let baseline = vec![
+        LatencyMeasurement::new_baseline(0, 1_000, 1_010_000_000),
+        LatencyMeasurement::new_baseline(1, 1_000, 1_030_000_000),
+    ];
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &baseline, 0);
+    assert_eq!(results.sample_count, 2);
+    assert_eq!(results.avg_latency_ms, 20.0);
+    assert!(results.backbone_avg_latency_ms.is_none());
+
+    let backbone = vec![LatencyMeasurement::new_aws_backbone(
+        0,
+        1_000,
+        1_100_000_000,
+        1_250_000_000,
+    )];
+    let results = ExperimentResults::from_measurements("aws-backbone".to_string(), &backbone, 3);
+    assert_eq!(results.events_lost, 3);
+    assert_eq!(results.avg_latency_ms, 250.0);
+    assert_eq!(results.backbone_avg_latency_ms, Some(150.0));
+    assert_eq!(results.backbone_median_latency_ms, Some(150.0));
+}
+
+#[test]
+fn warmup_measurements_are_excluded_from_statistics() {
+    let mut warmup = LatencyMeasurement::new_baseline(0, 1_000, 1_500_000_000);
+    warmup.warmup = true;
+    let measurements = vec![
+        warmup,
+        LatencyMeasurement::new_baseline(1, 1_000, 1_010_000_000),
+    ];
+
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &measurements, 0);
+    assert_eq!(results.sample_count, 1);
+    assert_eq!(results.warmup_samples, 1);
+    assert_eq!(results.max_latency_ms, 10.0);
+}
+
+#[test]
+fn exchange_delay_uses_event_minus_transaction_time() {
+    let measurements = vec![
+        LatencyMeasurement::new_baseline(0, 1_000, 1_050_000_000).with_transaction_time(998),
+        LatencyMeasurement::new_baseline(1, 2_000, 2_050_000_000).with_transaction_time(1_994),
+        LatencyMeasurement::new_baseline(2, 3_000, 3_050_000_000),
+    ];
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &measurements, 0);
+
+    assert_eq!(results.exchange_delay_avg_ms, Some(4.0));
+    assert_eq!(results.exchange_delay_median_ms, Some(4.0));
+
+    let without: Vec<_> = measurements.into_iter().skip(2).collect();
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &without, 0);
+    assert_eq!(results.exchange_delay_avg_ms, None);
+}
+
+#[test]
+fn configured_percentiles_are_keyed_by_label() {
+    let stats: StatsAggregator = (1..=1000).map(f64::from).collect();
+    let percentiles = stats.percentiles(&[50.0, 99.9]);
+
+    assert_eq!(percentiles.len(), 2);
+    assert!((percentiles["p50"] - 500.5).abs() < 1e-9);
+    assert!((percentiles["p99.9"] - 999.001).abs() < 1e-9);
+    assert!(StatsAggregator::new().percentiles(&[99.0]).is_empty());
+}
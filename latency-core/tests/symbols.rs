@@ -0,0 +1,41 @@
+use latency_core::{split_by_symbol, LatencyMeasurement};
+use std::sync::Arc;
+
+fn measurement(id: u64, symbol: Option<&str>, latency_ms: i64) -> LatencyMeasurement {
+    let receive_time = 1_700_000_000_000_000_000 + latency_ms * 1_000_000;
+    let mut m = LatencyMeasurement::new_baseline(id, 1_700_000_000_000, receive_time);
+    if let Some(symbol) = symbol {
+        m = m.with_symbol(Arc::from(symbol));
+    }
+    m
+}
+
+#[test]
+fn splits_busiest_symbol_first() {
+    let mut measurements = vec![
+        measurement(0, Some("ETHUSDT"), 40),
+        measurement(1, Some("BTCUSDT"), 10),
+        measurement(2, Some("BTCUSDT"), 20),
+        measurement(3, Some("BTCUSDT"), 30),
+        measurement(4, None, 500),
+    ];
+    let mut warmup = measurement(5, Some("ETHUSDT"), 900);
+    warmup.warmup = true;
+    measurements.push(warmup);
+
+    let symbols = split_by_symbol(&measurements);
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[0].symbol, "BTCUSDT");
+    assert_eq!(symbols[0].sample_count, 3);
+    assert!((symbols[0].median_latency_ms - 20.0).abs() < 1e-9);
+    assert_eq!(symbols[0].backbone_median_ms, None);
+    // Warm-up and untagged events stay out of every set
+    assert_eq!(symbols[1].symbol, "ETHUSDT");
+    assert_eq!(symbols[1].sample_count, 1);
+    assert!((symbols[1].avg_latency_ms - 40.0).abs() < 1e-9);
+}
+
+#[test]
+fn nothing_to_split_without_symbols() {
+    assert!(split_by_symbol(&[measurement(0, None, 10)]).is_empty());
+}
@@ -0,0 +1,403 @@
+// Mock Binance WebSocket server for testing without internet access
+//
+// Serves a synthetic futures-style bookTicker stream over ws:// so the forwarder
+// and receiver can be run end to end on one host. One generator produces the
+// stream and every connection receives the same frames, like clients of the
+// real exchange; connections add their own send jitter and forced disconnects.
+// Clients connecting to a /stream path get every frame wrapped in the
+// combined-stream envelope, as from Binance's /stream?streams= URLs. Clients
+// of the bare /ws or /stream endpoint get nothing until they SUBSCRIBE to
+// streams (e.g. btcusdt@bookTicker); clients naming streams in the path get
+// every symbol, whatever the name. GET /api/v3/time on the same port answers
+// with the exchange's clock, which --clock-offset-ms moves along with the
+// event times.
+// All randomness comes from a seeded generator so runs are reproducible.
+
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use shared::{init_logging, ExperimentError, Shutdown};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+/// Frames a slow connection may fall behind before it starts skipping
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// One generated frame and the name of the stream it belongs to
+type Frame = (Arc<str>, Arc<str>);
+
+#[derive(Parser, Debug)]
+#[command(name = "mock-binance")]
+#[command(about = "Mock Binance WebSocket server serving a synthetic bookTicker stream")]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:9443")]
+    listen: String,
+
+    /// Symbols to stream, comma-separated; updates rotate through them
+    #[arg(long, value_delimiter = ',', default_value = "BTCUSDT")]
+    symbols: Vec<String>,
+
+    /// Updates per second across all symbols
+    #[arg(long, default_value = "100")]
+    rate: u32,
+
+    /// Delay each frame by a random 0 to this many milliseconds before sending
+    #[arg(long, value_name = "MS", default_value = "0")]
+    jitter_ms: u64,
+
+    /// Fraction of frames sent as truncated, unparseable JSON (0-1)
+    #[arg(long, default_value = "0")]
+    malformed_rate: f64,
+
+    /// Drop each connection without a close frame after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    disconnect_after: Option<u64>,
+
+    /// Send spot-format frames, which carry no event or transaction time
+    #[arg(long)]
+    spot: bool,
+
+    /// Run the exchange's clock this many milliseconds ahead of the host's (behind if negative), in event times and /api/v3/time
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    clock_offset_ms: i64,
+
+    /// Seed for prices, jitter and malformed frames
+    #[arg(long, default_value = "1")]
+    seed: u64,
+
+    /// Log level (error, warn, info, debug, trace) or a tracing filter directive; RUST_LOG overrides it
+    #[arg(long, value_name = "FILTER", default_value = "info")]
+    log_level: String,
+}
+
+/// xorshift64*: small, fast and deterministic for a given seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self((seed ^ 0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Top of book for one symbol, moved by a random walk
+struct Book {
+    symbol: String,
+    update_id: i64,
+    bid_ticks: i64, // Price in 0.01 ticks
+}
+
+/// Run the server on command-line arguments, the program name first, until
+/// interrupted
+pub async fn run<I, T>(args: I) -> Result<(), ExperimentError>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = match Args::try_parse_from(args) {
+        Ok(args) => args,
+        // --help and --version
+        Err(e) if !e.use_stderr() => {
+            print!("{}", e);
+            return Ok(());
+        }
+        Err(e) => return Err(ExperimentError::other(e.to_string().trim_end())),
+    };
+    init_logging(&args.log_level, false).map_err(ExperimentError::other)?;
+    if args.rate == 0 {
+        return Err(ExperimentError::other("--rate must be at least 1"));
+    }
+    if !(0.0..=1.0).contains(&args.malformed_rate) {
+        return Err(ExperimentError::other(
+            "--malformed-rate must be between 0 and 1",
+        ));
+    }
+    if args.symbols.iter().any(|s| s.is_empty()) {
+        return Err(ExperimentError::other(
+            "--symbols must not contain empty names",
+        ));
+    }
+
+    let listener = TcpListener::bind(&args.listen).await.map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("failed to listen on {}: {}", args.listen, e),
+        )
+    })?;
+    info!(
+        listen = %args.listen,
+        symbols = ?args.symbols,
+        rate = args.rate,
+        "mock Binance server ready; connect to ws://{}/ws/btcusdt@bookTicker",
+        args.listen
+    );
+
+    let args = Arc::new(args);
+    let (frames, _) = broadcast::channel(CHANNEL_CAPACITY);
+    tokio::spawn(generate(args.clone(), frames.clone()));
+
+    let mut shutdown = Shutdown::install();
+    let mut connections = 0u64;
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    connections += 1;
+                    info!(%peer, connection = connections, "client connected");
+                    let args = args.clone();
+                    let frames = frames.subscribe();
+                    tokio::spawn(async move {
+                        serve(stream, &args, frames, connections).await;
+                        info!(%peer, connection = connections, "client disconnected");
+                    });
+                }
+                Err(e) => warn!(error = %e, "accept failed"),
+            },
+            _ = shutdown.wait() => break,
+        }
+    }
+    info!(connections, "shutting down");
+    Ok(())
+}
+
+/// Produce the stream at the configured rate until the process exits
+async fn generate(args: Arc<Args>, frames: broadcast::Sender<Frame>) {
+    let mut rng = Rng::new(args.seed);
+    let mut books: Vec<Book> = args
+        .symbols
+        .iter()
+        .map(|symbol| Book {
+            symbol: symbol.to_uppercase(),
+            update_id: 1_000_000,
+            bid_ticks: 5_000_000, // 50000.00
+        })
+        .collect();
+
+    let mut ticker = interval(Duration::from_secs_f64(1.0 / f64::from(args.rate)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    for next in (0..books.len()).cycle() {
+        ticker.tick().await;
+        let book = &mut books[next];
+        book.update_id += 1;
+        book.bid_ticks = (book.bid_ticks + (rng.next_u64() % 5) as i64 - 2).max(1);
+        let mut frame = book_ticker(book, &mut rng, args.spot, exchange_now_ms(&args));
+        if rng.next_f64() < args.malformed_rate {
+            frame.truncate(frame.len() / 2);
+            debug!(update_id = book.update_id, "sending malformed frame");
+        }
+        // Sending only fails while nobody is connected
+        let stream = format!("{}@bookTicker", book.symbol.to_lowercase());
+        let _ = frames.send((stream.into(), frame.into()));
+    }
+}
+
+/// The exchange's clock in epoch milliseconds
+fn exchange_now_ms(args: &Args) -> i64 {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    now_ms + args.clock_offset_ms
+}
+
+/// One bookTicker frame as Binance USDⓈ-M futures sends it (or spot, without e/E/T)
+fn book_ticker(book: &Book, rng: &mut Rng, spot: bool, now_ms: i64) -> String {
+    let price = |ticks: i64| format!("{}.{:02}", ticks / 100, ticks % 100);
+    let qty = |rng: &mut Rng| format!("{:.3}", 0.001 + rng.next_f64() * 2.0);
+    let (bid, ask) = (price(book.bid_ticks), price(book.bid_ticks + 1));
+    let (bid_qty, ask_qty) = (qty(rng), qty(rng));
+    // Field order follows Binance's own frames
+    if spot {
+        format!(
+            r#"{{"u":{},"s":"{}","b":"{}","B":"{}","a":"{}","A":"{}"}}"#,
+            book.update_id, book.symbol, bid, bid_qty, ask, ask_qty
+        )
+    } else {
+        format!(
+            r#"{{"e":"bookTicker","u":{},"E":{},"T":{},"s":"{}","b":"{}","B":"{}","a":"{}","A":"{}"}}"#,
+            book.update_id,
+            now_ms,
+            now_ms - 1,
+            book.symbol,
+            bid,
+            bid_qty,
+            ask,
+            ask_qty
+        )
+    }
+}
+
+/// Answer GET /api/v3/time like Binance's REST API, then close the
+/// connection. The request line arrives in the first segment on loopback, so
+/// one peek tells it from a WebSocket handshake.
+async fn serve_time(stream: &mut TcpStream, args: &Args) -> bool {
+    const REQUEST: &[u8] = b"GET /api/v3/time";
+    let mut line = [0u8; REQUEST.len()];
+    match stream.peek(&mut line).await {
+        Ok(len) if line[..len] == *REQUEST => {}
+        _ => return false,
+    }
+    // The request has no body; read its head before answering
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return true,
+            Ok(len) => head.extend_from_slice(&buf[..len]),
+        }
+    }
+    let body = json!({ "serverTime": exchange_now_ms(args) }).to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!(error = %e, "server time answer failed");
+    }
+    true
+}
+
+/// Stream frames to one client until it leaves, falls away or is cut off
+async fn serve(
+    mut stream: TcpStream,
+    args: &Args,
+    mut frames: broadcast::Receiver<Frame>,
+    connection: u64,
+) {
+    // Small frames at a steady rate would otherwise sit in Nagle's buffer
+    if let Err(e) = stream.set_nodelay(true) {
+        warn!(error = %e, "failed to set TCP_NODELAY");
+    }
+    if serve_time(&mut stream, args).await {
+        return;
+    }
+    let mut combined = false;
+    let mut subscribed: Option<HashSet<String>> = None; // Streams to send; all if none
+                                                        // The error type is tungstenite's handshake callback signature
+    #[allow(clippy::result_large_err)]
+    let record_path = |request: &Request, response: Response| {
+        let path = request.uri().path();
+        combined = path.starts_with("/stream");
+        if request.uri().query().is_none()
+            && matches!(path.trim_end_matches('/'), "/ws" | "/stream")
+        {
+            subscribed = Some(HashSet::new());
+        }
+        Ok(response)
+    };
+    let mut ws = match tokio_tungstenite::accept_hdr_async(stream, record_path).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!(error = %e, "WebSocket handshake failed");
+            return;
+        }
+    };
+    let mut rng = Rng::new(args.seed.wrapping_add(connection));
+    let cutoff = sleep(
+        args.disconnect_after
+            .map_or(Duration::MAX, Duration::from_secs),
+    );
+    tokio::pin!(cutoff);
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => {
+                let (stream, frame) = match frame {
+                    Ok(frame) => frame,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "client too slow, skipping frames");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if subscribed.as_ref().is_some_and(|streams| !streams.contains(&*stream)) {
+                    continue;
+                }
+                if args.jitter_ms > 0 {
+                    let jitter = rng.next_f64() * args.jitter_ms as f64;
+                    sleep(Duration::from_secs_f64(jitter / 1000.0)).await;
+                }
+                let text = if combined {
+                    format!(r#"{{"stream":"{}","data":{}}}"#, stream, frame)
+                } else {
+                    frame.to_string()
+                };
+                if ws.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(reply) = subscription_reply(&text, &mut subscribed) {
+                        if ws.send(Message::Text(reply)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                Some(Ok(_)) => {} // Pings are answered by tungstenite
+            },
+            _ = &mut cutoff => {
+                info!(connection, "forcing disconnect");
+                return; // Dropping the socket closes it without a close frame
+            }
+        }
+    }
+}
+
+/// Binance answers SUBSCRIBE/UNSUBSCRIBE requests with `{"result":null,"id":..}`.
+/// Connections to the bare endpoint change their streams accordingly; the
+/// others keep receiving every symbol.
+fn subscription_reply(text: &str, subscribed: &mut Option<HashSet<String>>) -> Option<String> {
+    let request: Value = serde_json::from_str(text).ok()?;
+    let method = request.get("method")?.as_str().unwrap_or_default();
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let streams = request
+        .get("params")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    if let Some(subscribed) = subscribed {
+        match method {
+            "SUBSCRIBE" => subscribed.extend(streams.map(str::to_string)),
+            "UNSUBSCRIBE" => streams.for_each(|stream| {
+                subscribed.remove(stream);
+            }),
+            _ => {
+                let error = json!({ "code": 2, "msg": format!("Invalid request: unknown method {}", method) });
+                return Some(json!({ "error": error, "id": id }).to_string());
+            }
+        }
+    }
+    Some(json!({ "result": null, "id": id }).to_string())
+}
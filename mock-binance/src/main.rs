@@ -0,0 +1,7 @@
+#[tokio::main]
+async fn main() {
+    if let Err(e) = mock_binance::run(std::env::args_os()).await {
+        eprintln!("{}", e);
+        std::process::exit(e.exit_code());
+    }
+}
@@ -0,0 +1,704 @@
+// Runs a complete experiment on the deployed instances from one command
+//
+// For each phase the receiver (and, for aws-backbone, the forwarder) is started
+// over SSH with matching parameters, the run is waited out, the outputs are
+// copied into a local run directory and the phases are compared. Instance
+// addresses come from vpc-resources.txt, as written by the setup scripts, unless
+// given on the command line. Settings can also come from a named profile.
+
+mod profile;
+mod remote;
+
+use clap::{CommandFactory, FromArgMatches};
+use latency_core::{BootstrapIntervals, ConfidenceInterval, ExperimentResults, Report};
+use remote::{shell_quote, success, Remote};
+use shared::ErrorKind;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PHASES: &[&str] = &["baseline", "aws-backbone"];
+
+#[derive(clap::Parser, Debug)]
+#[command(name = "orchestrator")]
+#[command(
+    about = "Run the baseline and AWS backbone experiments on the EC2 instances and compare them"
+)]
+struct Args {
+    /// Phases to run in order, comma-separated: baseline, aws-backbone
+    #[arg(long, value_delimiter = ',', default_value = "baseline,aws-backbone")]
+    phases: Vec<String>,
+
+    /// Collection time per phase in seconds
+    #[arg(long, default_value = "300")]
+    duration: u64,
+
+    /// Exchange to measure: binance, okx, or bybit
+    #[arg(long, default_value = "binance")]
+    exchange: String,
+
+    /// Trading pairs as BASE-QUOTE, comma-separated; every phase runs once per symbol
+    #[arg(long, value_delimiter = ',', default_value = "BTC-USDT")]
+    symbol: Vec<String>,
+
+    /// Backbone transport: udp, tcp, dual, ws, wss or grpc (wss needs TLS flags in the extra arguments)
+    #[arg(long, default_value = "udp")]
+    transport: String,
+
+    /// Backbone port on the receiver
+    #[arg(long, default_value = "8080")]
+    port: u16,
+
+    /// Run the aws-backbone phase once per DSCP marking, comma-separated (e.g. 0,46)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    dscp: Vec<u8>,
+
+    /// End-to-end latency percentiles the receiver reports, comma-separated (default: the receiver's)
+    #[arg(long, value_delimiter = ',')]
+    percentiles: Vec<f64>,
+
+    /// Seconds between starting the receiver and the forwarder
+    #[arg(long, value_name = "SECONDS", default_value = "3")]
+    startup_delay: u64,
+
+    /// Extra receiver arguments, appended to its command line as given (shell syntax)
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+    receiver_args: Option<String>,
+
+    /// Extra forwarder arguments, appended to its command line as given (shell syntax)
+    #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+    forwarder_args: Option<String>,
+
+    /// Resource file with the instance addresses, written by scripts/setup-ec2.sh
+    #[arg(long, default_value = "vpc-resources.txt")]
+    resources: String,
+
+    /// Tokyo instance public address (default: TOKYO_PUBLIC_IP from the resource file)
+    #[arg(long)]
+    tokyo_host: Option<String>,
+
+    /// Frankfurt instance public address (default: FRANKFURT_PUBLIC_IP from the resource file)
+    #[arg(long)]
+    frankfurt_host: Option<String>,
+
+    /// Frankfurt private address the forwarder sends to (default: FRANKFURT_PRIVATE_IP from the resource file)
+    #[arg(long)]
+    frankfurt_private_ip: Option<String>,
+
+    /// SSH private key (default: ~/.ssh/$KEY_NAME.pem, KEY_NAME defaulting to binance-latency-key)
+    #[arg(long, value_name = "PEM")]
+    ssh_key: Option<String>,
+
+    /// SSH login user on both instances
+    #[arg(long, default_value = "ec2-user")]
+    ssh_user: String,
+
+    /// Local directory for the fetched outputs (default: runs/<UTC start time>)
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Print the commands that would be run without connecting to anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Take the settings of this profile, for any option not given on the command line
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// TOML file the profiles are read from
+    #[arg(long, value_name = "FILE", default_value = "experiments.toml")]
+    profiles: String,
+}
+
+/// One phase, run for one symbol
+struct Run {
+    name: String, // Names the output files, e.g. aws-backbone-dscp46-eth-usdt
+    backbone: bool,
+    symbol: String,
+    dscp: Option<u8>,
+}
+
+/// Remote file names for one phase
+struct PhaseFiles {
+    results: String,
+    measurements: String,
+    forwarder_status: String, // aws-backbone phases only
+}
+
+impl PhaseFiles {
+    fn new(phase: &str) -> Self {
+        let suffix = phase.strip_prefix("aws-backbone").unwrap_or_default();
+        Self {
+            results: format!("results-{}.json", phase),
+            measurements: format!("measurements-{}.csv", phase),
+            forwarder_status: format!("forwarder-status{}.json", suffix),
+        }
+    }
+}
+
+/// Name of an aws-backbone phase, e.g. `aws-backbone-dscp46` with a DSCP marking
+fn backbone_phase(dscp: Option<u8>) -> String {
+    match dscp {
+        Some(dscp) => format!("aws-backbone-dscp{}", dscp),
+        None => "aws-backbone".to_string(),
+    }
+}
+
+fn main() {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(name) = args.profile.clone() {
+        match profile::load(&args.profiles, &name) {
+            Ok(profile) => {
+                match profile.description() {
+                    Some(description) => println!("Profile: {} ({})", name, description),
+                    None => println!("Profile: {}", name),
+                }
+                profile.apply(&mut args, &matches);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(phase) = args.phases.iter().find(|p| !PHASES.contains(&p.as_str())) {
+        eprintln!(
+            "Invalid phase: {}. Must be one of {}",
+            phase,
+            PHASES.join(", ")
+        );
+        std::process::exit(1);
+    }
+    if args.duration == 0 {
+        eprintln!("--duration must be at least 1");
+        std::process::exit(1);
+    }
+    if args.symbol.is_empty() {
+        eprintln!("--symbol needs at least one symbol");
+        std::process::exit(1);
+    }
+    if let Some(p) = args
+        .percentiles
+        .iter()
+        .find(|&&p| !(0.0..=100.0).contains(&p))
+    {
+        eprintln!("Invalid percentile: {}. Must be between 0 and 100", p);
+        std::process::exit(1);
+    }
+    if !args.dscp.is_empty() {
+        if !args.phases.iter().any(|p| p == "aws-backbone") {
+            eprintln!("--dscp requires the aws-backbone phase");
+            std::process::exit(1);
+        }
+        if let Some(dscp) = args.dscp.iter().find(|&&dscp| dscp > 63) {
+            eprintln!("Invalid DSCP value: {}. Must be between 0 and 63", dscp);
+            std::process::exit(1);
+        }
+    }
+
+    let resources = read_resources(&args.resources);
+    let lookup = |value: &Option<String>, key: &str, flag: &str| {
+        value
+            .clone()
+            .or_else(|| resources.get(key).cloned())
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "Error: {} not found in {}; pass --{}",
+                    key, args.resources, flag
+                );
+                std::process::exit(1);
+            })
+    };
+    let key = args.ssh_key.clone().or_else(default_key);
+    let frankfurt = Remote {
+        name: "Frankfurt",
+        host: lookup(
+            &args.frankfurt_host,
+            "FRANKFURT_PUBLIC_IP",
+            "frankfurt-host",
+        ),
+        user: args.ssh_user.clone(),
+        key: key.clone(),
+        dry_run: args.dry_run,
+    };
+    let backbone = args.phases.iter().any(|p| p == "aws-backbone");
+    let (tokyo, frankfurt_private_ip) = if backbone {
+        let tokyo = Remote {
+            name: "Tokyo",
+            host: lookup(&args.tokyo_host, "TOKYO_PUBLIC_IP", "tokyo-host"),
+            user: args.ssh_user.clone(),
+            key,
+            dry_run: args.dry_run,
+        };
+        let private_ip = lookup(
+            &args.frankfurt_private_ip,
+            "FRANKFURT_PRIVATE_IP",
+            "frankfurt-private-ip",
+        );
+        (Some(tokyo), private_ip)
+    } else {
+        (None, String::new())
+    };
+
+    let output_dir = args.output_dir.clone().unwrap_or_else(|| {
+        let started = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        match &args.profile {
+            Some(profile) => PathBuf::from("runs").join(format!("{}-{}", started, profile)),
+            None => PathBuf::from("runs").join(started),
+        }
+    });
+    if !args.dry_run {
+        if let Err(e) = std::fs::create_dir_all(&output_dir) {
+            eprintln!("Error: failed to create {}: {}", output_dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+    println!("Run directory: {}", output_dir.display());
+
+    let runs = plan_runs(&args);
+    let mut failed = false;
+    for run in &runs {
+        println!("\n=== Phase: {} ({} s) ===", run.name, args.duration);
+        let result = match &tokyo {
+            Some(tokyo) if run.backbone => run_backbone(
+                &args,
+                run,
+                &frankfurt,
+                tokyo,
+                &frankfurt_private_ip,
+                &output_dir,
+            ),
+            _ => run_baseline(&args, run, &frankfurt, &output_dir),
+        };
+        if let Err(e) = result {
+            eprintln!("Phase {} failed: {}", run.name, e);
+            failed = true;
+        }
+    }
+
+    if !args.dry_run {
+        summarize(&runs, args.symbol.len() > 1, &output_dir);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Every phase for every symbol, symbol by symbol. With --dscp the
+/// aws-backbone phase is repeated for each marking.
+fn plan_runs(args: &Args) -> Vec<Run> {
+    let mut runs = Vec::new();
+    for symbol in &args.symbol {
+        // Output file names only tell symbols apart when there are several
+        let name = |phase: String| match args.symbol.len() {
+            1 => phase,
+            _ => format!("{}-{}", phase, symbol.to_ascii_lowercase()),
+        };
+        for phase in &args.phases {
+            if phase != "aws-backbone" {
+                runs.push(Run {
+                    name: name(phase.clone()),
+                    backbone: false,
+                    symbol: symbol.clone(),
+                    dscp: None,
+                });
+                continue;
+            }
+            let markings: Vec<Option<u8>> = if args.dscp.is_empty() {
+                vec![None]
+            } else {
+                args.dscp.iter().copied().map(Some).collect()
+            };
+            for dscp in markings {
+                runs.push(Run {
+                    name: name(backbone_phase(dscp)),
+                    backbone: true,
+                    symbol: symbol.clone(),
+                    dscp,
+                });
+            }
+        }
+    }
+    runs
+}
+
+/// Receiver only, measuring the exchange directly from Frankfurt
+fn run_baseline(
+    args: &Args,
+    run: &Run,
+    frankfurt: &Remote,
+    output_dir: &Path,
+) -> Result<(), String> {
+    let files = PhaseFiles::new(&run.name);
+    let command = receiver_command(args, run, &files, &run_id(&run.name));
+    println!("Starting receiver on {}", frankfurt.name);
+    check(frankfurt, frankfurt.run(&command), "receiver")?;
+    fetch_outputs(
+        frankfurt,
+        &[&files.results, &files.measurements],
+        output_dir,
+    )
+}
+
+/// Receiver on Frankfurt, forwarder on Tokyo. The receiver stops itself after
+/// the duration; the forwarder is then interrupted so it writes its final status.
+fn run_backbone(
+    args: &Args,
+    run: &Run,
+    frankfurt: &Remote,
+    tokyo: &Remote,
+    frankfurt_private_ip: &str,
+    output_dir: &Path,
+) -> Result<(), String> {
+    let files = PhaseFiles::new(&run.name);
+    // The receiver ignores events from any other forwarder run
+    let run_id = run_id(&run.name);
+    println!("Starting receiver on {} (run {})", frankfurt.name, run_id);
+    let receiver = frankfurt
+        .spawn(&receiver_command(args, run, &files, &run_id))
+        .map_err(|e| format!("failed to start ssh: {}", e))?;
+
+    if !args.dry_run {
+        std::thread::sleep(Duration::from_secs(args.startup_delay));
+    }
+    println!("Starting forwarder on {}", tokyo.name);
+    let forwarder = tokyo.spawn(&forwarder_command(
+        args,
+        run,
+        frankfurt_private_ip,
+        &files,
+        &run_id,
+    ));
+
+    let received = match receiver {
+        Some(mut receiver) => receiver.wait().map_err(|e| e.to_string()),
+        None => Ok(success()),
+    };
+    // Stop the forwarder whether or not the receiver succeeded
+    println!("Stopping forwarder on {}", tokyo.name);
+    let _ = tokyo.run("pkill -INT -x tokyo-forwarder");
+    if let Ok(Some(mut forwarder)) = forwarder {
+        let _ = forwarder.wait();
+    }
+
+    match received {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(format!(
+                "receiver on {} exited with {}",
+                frankfurt.name,
+                describe_exit(status)
+            ))
+        }
+        Err(e) => return Err(format!("receiver on {}: {}", frankfurt.name, e)),
+    }
+    fetch_outputs(
+        frankfurt,
+        &[&files.results, &files.measurements],
+        output_dir,
+    )?;
+    fetch_outputs(tokyo, &[&files.forwarder_status], output_dir)
+}
+
+/// Run ID for one phase: start time and phase, e.g. `20240108T120000Z-aws-backbone`
+fn run_id(phase: &str) -> String {
+    format!("{}-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"), phase)
+}
+
+fn receiver_command(args: &Args, run: &Run, files: &PhaseFiles, run_id: &str) -> String {
+    let mode = if run.backbone {
+        "aws-backbone"
+    } else {
+        "baseline"
+    };
+    let mut command = vec![
+        "./frankfurt-receiver".to_string(),
+        format!("--mode {}", mode),
+        format!("--duration {}", args.duration),
+        format!("--run-id {}", run_id),
+        format!("--output {}", shell_quote(&files.results)),
+        format!("--csv-output {}", shell_quote(&files.measurements)),
+    ];
+    if run.backbone {
+        command.push(format!("--port {}", args.port));
+        command.push(format!("--transport {}", shell_quote(&args.transport)));
+    } else {
+        command.push(format!("--exchange {}", shell_quote(&args.exchange)));
+        command.push(format!("--symbol {}", shell_quote(&run.symbol)));
+    }
+    if !args.percentiles.is_empty() {
+        let percentiles: Vec<String> = args.percentiles.iter().map(f64::to_string).collect();
+        command.push(format!("--percentiles {}", percentiles.join(",")));
+    }
+    command.extend(args.receiver_args.clone());
+    command.join(" ")
+}
+
+/// The forwarder has no duration of its own; `timeout` stops it should the
+/// orchestrator lose its connection before it can interrupt it
+fn forwarder_command(
+    args: &Args,
+    run: &Run,
+    frankfurt_private_ip: &str,
+    files: &PhaseFiles,
+    run_id: &str,
+) -> String {
+    let limit = args.duration + args.startup_delay + 60;
+    let mut command = vec![
+        format!("timeout --signal=INT {}", limit),
+        "./tokyo-forwarder".to_string(),
+        format!("--exchange {}", shell_quote(&args.exchange)),
+        format!("--symbol {}", shell_quote(&run.symbol)),
+        format!("--frankfurt-ip {}", shell_quote(frankfurt_private_ip)),
+        format!("--port {}", args.port),
+        format!("--transport {}", shell_quote(&args.transport)),
+        format!("--status-file {}", shell_quote(&files.forwarder_status)),
+        format!("--run-id {}", run_id),
+    ];
+    if let Some(dscp) = run.dscp {
+        command.push(format!("--dscp {}", dscp));
+    }
+    command.extend(args.forwarder_args.clone());
+    command.join(" ")
+}
+
+fn check(
+    remote: &Remote,
+    status: std::io::Result<std::process::ExitStatus>,
+    what: &str,
+) -> Result<(), String> {
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!(
+            "{} on {} exited with {}",
+            what,
+            remote.name,
+            describe_exit(status)
+        )),
+        Err(e) => Err(format!("failed to run ssh for the {}: {}", what, e)),
+    }
+}
+
+/// An exit status, with the kind of failure if the receiver or forwarder
+/// exited with one of the error codes
+fn describe_exit(status: std::process::ExitStatus) -> String {
+    match status.code().and_then(ErrorKind::from_exit_code) {
+        Some(kind) if kind.is_retryable() => format!("{} ({}, retryable)", status, kind),
+        Some(kind) => format!("{} ({})", status, kind),
+        None => status.to_string(),
+    }
+}
+
+fn fetch_outputs(remote: &Remote, files: &[&str], output_dir: &Path) -> Result<(), String> {
+    for file in files {
+        println!("Fetching {} from {}", file, remote.name);
+        match remote.fetch(file, output_dir) {
+            Ok(status) if status.success() => {}
+            Ok(status) => return Err(format!("scp of {} exited with {}", file, status)),
+            Err(e) => return Err(format!("failed to run scp: {}", e)),
+        }
+    }
+    Ok(())
+}
+
+/// Print each phase's summary and, when both ran, how they compare for
+/// each symbol
+fn summarize(runs: &[Run], several_symbols: bool, output_dir: &Path) {
+    let mut loaded = HashMap::new();
+    for run in runs {
+        let path = output_dir.join(PhaseFiles::new(&run.name).results);
+        match ExperimentResults::load(&path.to_string_lossy()) {
+            Ok(results) => {
+                Report::new(results.clone(), Vec::new()).print_summary();
+                loaded.insert(run.name.as_str(), results);
+            }
+            Err(e) => eprintln!("No results for {}: {}", run.name, e),
+        }
+    }
+    let mut symbols: Vec<&str> = runs.iter().map(|run| run.symbol.as_str()).collect();
+    symbols.dedup();
+    for symbol in symbols {
+        let label = several_symbols.then_some(symbol);
+        let result = |backbone: bool, dscp: Option<u8>| {
+            let run = runs
+                .iter()
+                .find(|run| run.symbol == symbol && run.backbone == backbone && run.dscp == dscp)?;
+            loaded.get(run.name.as_str())
+        };
+        if let (Some(baseline), Some(backbone)) = (result(false, None), result(true, None)) {
+            print_comparison(label, baseline, backbone);
+        }
+        let marked: Vec<(u8, &ExperimentResults)> = runs
+            .iter()
+            .filter(|run| run.symbol == symbol)
+            .filter_map(|run| Some((run.dscp?, result(true, run.dscp)?)))
+            .collect();
+        if !marked.is_empty() {
+            print_dscp_comparison(label, &marked);
+        }
+    }
+    println!("\nOutputs are in {}", output_dir.display());
+}
+
+/// Section heading, naming the symbol when a run measured several
+fn heading(title: &str, symbol: Option<&str>) -> String {
+    match symbol {
+        Some(symbol) => format!("\n=== {}, {} ===", title, symbol),
+        None => format!("\n=== {} ===", title),
+    }
+}
+
+/// The comparison from README "Comparison Analysis"
+fn print_comparison(
+    symbol: Option<&str>,
+    baseline: &ExperimentResults,
+    backbone: &ExperimentResults,
+) {
+    println!(
+        "{}",
+        heading("Comparison (AWS backbone − baseline)", symbol)
+    );
+    println!(
+        "{:<18} {:>12} {:>14} {:>12}",
+        "", "Baseline", "AWS backbone", "Difference"
+    );
+    let rows = [
+        (
+            "Average (ms)",
+            baseline.avg_latency_ms,
+            backbone.avg_latency_ms,
+        ),
+        (
+            "Median (ms)",
+            baseline.median_latency_ms,
+            backbone.median_latency_ms,
+        ),
+        ("P95 (ms)", baseline.p95_latency_ms, backbone.p95_latency_ms),
+        ("P99 (ms)", baseline.p99_latency_ms, backbone.p99_latency_ms),
+        (
+            "Jitter (ms)",
+            baseline.jitter_stddev_ms,
+            backbone.jitter_stddev_ms,
+        ),
+    ];
+    for (label, baseline, backbone) in rows {
+        println!(
+            "{:<18} {:>12.2} {:>14.2} {:>+12.2}",
+            label,
+            baseline,
+            backbone,
+            backbone - baseline
+        );
+    }
+    println!(
+        "{:<18} {:>12} {:>14}",
+        "Events lost", baseline.events_lost, backbone.events_lost
+    );
+    println!(
+        "{:<18} {:>12} {:>14}",
+        "Samples", baseline.sample_count, backbone.sample_count
+    );
+    if let (Some(baseline), Some(backbone)) = (
+        &baseline.confidence_intervals,
+        &backbone.confidence_intervals,
+    ) {
+        print_interval_comparison(baseline, backbone);
+    }
+
+    let difference = backbone.avg_latency_ms - baseline.avg_latency_ms;
+    if difference < 0.0 {
+        println!("AWS backbone is faster by {:.2} ms on average", -difference);
+    } else if difference > 0.0 {
+        println!("Baseline is faster by {:.2} ms on average", difference);
+    } else {
+        println!("Both setups have equal average latency");
+    }
+}
+
+/// Bootstrap confidence intervals of both phases (receiver --bootstrap).
+/// Intervals that do not overlap make the difference significant.
+fn print_interval_comparison(baseline: &BootstrapIntervals, backbone: &BootstrapIntervals) {
+    println!(
+        "\n{:.0}% confidence intervals (ms, bootstrap)",
+        baseline.confidence * 100.0
+    );
+    let interval = |ci: &ConfidenceInterval| format!("[{:.2}, {:.2}]", ci.lower_ms, ci.upper_ms);
+    for (label, baseline, backbone) in [
+        ("Median", baseline.median, backbone.median),
+        ("P95", baseline.p95, backbone.p95),
+        ("P99", baseline.p99, backbone.p99),
+    ] {
+        let verdict = if baseline.overlaps(&backbone) {
+            "overlap, not significant"
+        } else {
+            "significant"
+        };
+        println!(
+            "{:<18} {:>18} {:>18}  {}",
+            label,
+            interval(&baseline),
+            interval(&backbone),
+            verdict
+        );
+    }
+}
+
+/// One column per DSCP marking of the aws-backbone phase
+fn print_dscp_comparison(symbol: Option<&str>, marked: &[(u8, &ExperimentResults)]) {
+    println!("{}", heading("Comparison by DSCP marking", symbol));
+    print!("{:<18}", "");
+    for (dscp, _) in marked {
+        print!(" {:>12}", format!("DSCP {}", dscp));
+    }
+    println!();
+    let row = |label: &str, value: fn(&ExperimentResults) -> f64| {
+        print!("{:<18}", label);
+        for (_, results) in marked {
+            print!(" {:>12.2}", value(results));
+        }
+        println!();
+    };
+    row("Average (ms)", |r| r.avg_latency_ms);
+    row("Median (ms)", |r| r.median_latency_ms);
+    row("P95 (ms)", |r| r.p95_latency_ms);
+    row("P99 (ms)", |r| r.p99_latency_ms);
+    row("Jitter (ms)", |r| r.jitter_stddev_ms);
+    print!("{:<18}", "Backbone median");
+    for (_, results) in marked {
+        match results.backbone_median_latency_ms {
+            Some(median) => print!(" {:>12.2}", median),
+            None => print!(" {:>12}", "n/a"),
+        }
+    }
+    println!();
+    print!("{:<18}", "Events lost");
+    for (_, results) in marked {
+        print!(" {:>12}", results.events_lost);
+    }
+    println!();
+}
+
+/// KEY=VALUE lines of the resource file; missing file means no defaults
+fn read_resources(path: &str) -> HashMap<String, String> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches('"');
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// The key scripts/create-keypair.sh writes, if it exists
+fn default_key() -> Option<String> {
+    let home = std::env::var("HOME").ok()?;
+    let name = std::env::var("KEY_NAME").unwrap_or_else(|_| "binance-latency-key".to_string());
+    let path = Path::new(&home).join(".ssh").join(format!("{}.pem", name));
+    path.exists().then(|| path.to_string_lossy().into_owned())
+}
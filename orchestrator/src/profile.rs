@@ -0,0 +1,101 @@
+// Named experiment presets (--profile)
+//
+// Recurring configurations live in a TOML file, one table per profile, so a
+// run can be repeated exactly and its settings reviewed like code instead of
+// being dug out of shell history:
+//
+//     [nightly-30min-btc-eth]
+//     duration = 1800
+//     symbols = ["BTC-USDT", "ETH-USDT"]
+//     percentiles = [50, 99, 99.9]
+//
+// Every key is optional and named after the command-line option it stands
+// for; options given on the command line override the profile.
+
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::Args;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Profile {
+    description: Option<String>,
+    phases: Option<Vec<String>>,
+    duration: Option<u64>,
+    exchange: Option<String>,
+    symbols: Option<Vec<String>>,
+    transport: Option<String>,
+    port: Option<u16>,
+    dscp: Option<Vec<u8>>,
+    percentiles: Option<Vec<f64>>,
+    startup_delay: Option<u64>,
+    receiver_args: Option<String>,
+    forwarder_args: Option<String>,
+    output_dir: Option<PathBuf>,
+}
+
+/// Read profile `name` from the TOML file at `path`
+pub fn load(path: &str, name: &str) -> Result<Profile, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let mut profiles: BTreeMap<String, Profile> =
+        toml::from_str(&contents).map_err(|e| format!("invalid {}: {}", path, e))?;
+    let known = profiles.keys().cloned().collect::<Vec<_>>().join(", ");
+    profiles
+        .remove(name)
+        .ok_or_else(|| format!("no profile {} in {} (profiles: {})", name, path, known))
+}
+
+impl Profile {
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Fill in every option of `args` the command line left at its default
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        set(unset("phases"), &mut args.phases, self.phases);
+        set(unset("duration"), &mut args.duration, self.duration);
+        set(unset("exchange"), &mut args.exchange, self.exchange);
+        set(unset("symbol"), &mut args.symbol, self.symbols);
+        set(unset("transport"), &mut args.transport, self.transport);
+        set(unset("port"), &mut args.port, self.port);
+        set(unset("dscp"), &mut args.dscp, self.dscp);
+        set(
+            unset("percentiles"),
+            &mut args.percentiles,
+            self.percentiles,
+        );
+        set(
+            unset("startup_delay"),
+            &mut args.startup_delay,
+            self.startup_delay,
+        );
+        set(
+            unset("receiver_args"),
+            &mut args.receiver_args,
+            self.receiver_args.map(Some),
+        );
+        set(
+            unset("forwarder_args"),
+            &mut args.forwarder_args,
+            self.forwarder_args.map(Some),
+        );
+        set(
+            unset("output_dir"),
+            &mut args.output_dir,
+            self.output_dir.map(Some),
+        );
+    }
+}
+
+/// Take the profile's `value`, if any, unless the option was given
+fn set<T>(unset: bool, target: &mut T, value: Option<T>) {
+    if let (true, Some(value)) = (unset, value) {
+        *target = value;
+    }
+}
@@ -0,0 +1,118 @@
+// Commands on the experiment instances over ssh/scp
+//
+// The binaries and their outputs live in the login user's home directory, as
+// left by scripts/deploy.sh.
+
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+
+/// One EC2 instance reachable over SSH
+#[derive(Debug, Clone)]
+pub struct Remote {
+    pub name: &'static str, // "Tokyo" or "Frankfurt", for messages
+    pub host: String,
+    pub user: String,
+    pub key: Option<String>,
+    pub dry_run: bool, // Print commands instead of running them
+}
+
+impl Remote {
+    /// Run `command` remotely and wait for it to exit
+    pub fn run(&self, command: &str) -> io::Result<ExitStatus> {
+        let mut ssh = self.ssh(command);
+        if self.dry_run {
+            return Ok(dry_run(&ssh));
+        }
+        ssh.status()
+    }
+
+    /// Start `command` remotely without waiting; `None` in a dry run
+    pub fn spawn(&self, command: &str) -> io::Result<Option<Child>> {
+        let mut ssh = self.ssh(command);
+        if self.dry_run {
+            dry_run(&ssh);
+            return Ok(None);
+        }
+        ssh.spawn().map(Some)
+    }
+
+    /// Copy a file from the home directory into `local_dir`
+    pub fn fetch(&self, remote_file: &str, local_dir: &Path) -> io::Result<ExitStatus> {
+        let mut scp = Command::new("scp");
+        scp.args(self.options())
+            .arg(format!("{}@{}:{}", self.user, self.host, remote_file))
+            .arg(local_dir);
+        if self.dry_run {
+            return Ok(dry_run(&scp));
+        }
+        scp.status()
+    }
+
+    fn ssh(&self, command: &str) -> Command {
+        let mut ssh = Command::new("ssh");
+        ssh.args(self.options())
+            .arg(format!("{}@{}", self.user, self.host))
+            .arg(command);
+        ssh
+    }
+
+    /// Same options as the setup scripts, plus batch mode so a missing key
+    /// fails instead of prompting
+    fn options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(key) = &self.key {
+            options.extend(["-i".to_string(), key.clone()]);
+        }
+        for option in [
+            "StrictHostKeyChecking=no",
+            "UserKnownHostsFile=/dev/null",
+            "LogLevel=ERROR",
+            "BatchMode=yes",
+            "ServerAliveInterval=30",
+        ] {
+            options.extend(["-o".to_string(), option.to_string()]);
+        }
+        options
+    }
+}
+
+/// Print a command as it would be run; reports success
+fn dry_run(command: &Command) -> ExitStatus {
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect();
+    println!(
+        "  $ {} {}",
+        command.get_program().to_string_lossy(),
+        args.join(" ")
+    );
+    success()
+}
+
+/// Exit status of a command that was not run
+#[cfg(unix)]
+pub fn success() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
+
+#[cfg(windows)]
+pub fn success() -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
+
+/// Quote an argument for a POSIX shell unless it is plainly safe
+pub fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
@@ -98,6 +98,22 @@ aws ec2 authorize-security-group-egress \
     --cidr 10.1.0.0/16 \
     --region $TOKYO_REGION
 
+# Allow inbound UDP 9200 from Frankfurt VPC (path RTT probes, --echo-port)
+aws ec2 authorize-security-group-ingress \
+    --group-id $TOKYO_SG_ID \
+    --protocol udp \
+    --port 9200 \
+    --cidr 10.1.0.0/16 \
+    --region $TOKYO_REGION
+
+# Allow inbound UDP 9300 from Frankfurt VPC (event acknowledgements, --ack-port)
+aws ec2 authorize-security-group-ingress \
+    --group-id $TOKYO_SG_ID \
+    --protocol udp \
+    --port 9300 \
+    --cidr 10.1.0.0/16 \
+    --region $TOKYO_REGION
+
 echo -e "${GREEN}✓ Tokyo Security Group configured${NC}"
 
 # Create Frankfurt Security Group
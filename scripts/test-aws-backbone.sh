@@ -158,7 +158,7 @@ fi
 echo "✓ backbone_median_latency_ms present: $BACKBONE_MEDIAN ms"
 
 # Verify CSV has tokyo_time and backbone_latency_ms columns
-CSV_HEADER=$(head -n 1 /tmp/backbone-measurements.csv)
+CSV_HEADER=$(grep -v '^#' /tmp/backbone-measurements.csv | head -n 1)
 if [[ ! "$CSV_HEADER" =~ "tokyo_time" ]]; then
     echo "✗ FAILED: CSV missing tokyo_time column"
     exit 1
@@ -172,7 +172,7 @@ fi
 echo "✓ CSV has backbone_latency_ms column"
 
 # Verify CSV has data with tokyo_time populated
-SECOND_LINE=$(sed -n '2p' /tmp/backbone-measurements.csv)
+SECOND_LINE=$(grep -v '^#' /tmp/backbone-measurements.csv | sed -n '2p')
 TOKYO_TIME_VALUE=$(echo "$SECOND_LINE" | cut -d',' -f3)
 if [ -z "$TOKYO_TIME_VALUE" ] || [ "$TOKYO_TIME_VALUE" = "null" ]; then
     echo "✗ FAILED: tokyo_time not populated in CSV data"
@@ -183,7 +183,7 @@ echo "✓ tokyo_time populated in CSV data"
 # Check for sequence ID tracking
 echo ""
 echo "=== Sequence Tracking Verification ==="
-FIRST_SEQ=$(sed -n '2p' /tmp/backbone-measurements.csv | cut -d',' -f1)
+FIRST_SEQ=$(grep -v '^#' /tmp/backbone-measurements.csv | sed -n '2p' | cut -d',' -f1)
 LAST_SEQ=$(tail -n 1 /tmp/backbone-measurements.csv | cut -d',' -f1)
 echo "First sequence ID: $FIRST_SEQ"
 echo "Last sequence ID: $LAST_SEQ"
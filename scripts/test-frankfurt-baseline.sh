@@ -95,7 +95,7 @@ fi
 echo "✓ CSV file has data ($CSV_LINE_COUNT lines including header)"
 
 # Verify CSV header
-HEADER=$(head -n 1 /tmp/baseline-measurements.csv)
+HEADER=$(grep -v '^#' /tmp/baseline-measurements.csv | head -n 1)
 if [[ ! "$HEADER" =~ "sequence_id" ]] || [[ ! "$HEADER" =~ "latency_ms" ]]; then
     echo "✗ FAILED: CSV header missing expected columns"
     echo "Header: $HEADER"
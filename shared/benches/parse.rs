@@ -0,0 +1,36 @@
+// Parse cost per frame on the forwarder hot path: full serde parse vs --fast-parse
+//
+//   cargo bench -p shared --bench parse
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use shared::{extract_binance_fields, Binance, BinanceFastParse, ExchangeAdapter};
+
+const FRAMES: &[(&str, &str)] = &[
+    (
+        "aggTrade",
+        r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":3036573452,"p":"37000.10000000","q":"0.00150000","f":3957213640,"l":3957213641,"T":1700000000120,"m":false,"M":true}"#,
+    ),
+    (
+        "futures bookTicker",
+        r#"{"e":"bookTicker","u":400900217,"s":"BTCUSDT","b":"37000.10","B":"31.21","a":"37000.20","A":"40.66","T":1700000000121,"E":1700000000123}"#,
+    ),
+];
+
+fn parse(c: &mut Criterion) {
+    for (name, frame) in FRAMES {
+        let mut group = c.benchmark_group(*name);
+        group.bench_function("serde", |b| {
+            b.iter(|| Binance.parse(black_box(frame)).unwrap())
+        });
+        group.bench_function("fast-parse", |b| {
+            b.iter(|| BinanceFastParse.parse(black_box(frame)).unwrap())
+        });
+        group.bench_function("extract fields", |b| {
+            b.iter(|| extract_binance_fields(black_box(frame)).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
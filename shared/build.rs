@@ -0,0 +1,8 @@
+// Generates the gRPC backbone client and server from proto/backbone.proto,
+// with a bundled protoc so no system install is needed
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("proto/backbone.proto")?;
+    Ok(())
+}
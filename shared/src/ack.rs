@@ -0,0 +1,97 @@
+// Event acknowledgements for reverse-path RTT (--ack-every / --ack-port)
+//
+// The receiver answers every Nth event with a small UDP datagram carrying the
+// event's send time, when it arrived and when the answer left. The forwarder
+// adds the time the answer arrived, which gives the four timestamps of an NTP
+// exchange: the round trip without the receiver's turnaround, and the offset
+// between the two clocks, estimated continuously while events flow.
+
+use latency_core::{LatencySummary, StatsAggregator};
+use serde::{Deserialize, Serialize};
+
+/// What the receiver sends back for one event (epoch nanos, each on the clock
+/// of the host that took it)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventAck {
+    pub ack: u64,      // Sequence ID of the acknowledged event
+    pub sent: i64,     // Forwarder: event handed to the socket
+    pub received: i64, // Receiver: event arrived
+    pub acked: i64,    // Receiver: acknowledgement sent
+}
+
+impl EventAck {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("ack serializes")
+    }
+
+    pub fn parse(datagram: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(datagram)
+    }
+
+    /// Round trip and clock offset (receiver minus forwarder) in nanoseconds,
+    /// given when the acknowledgement reached the forwarder. The offset
+    /// assumes both directions take equally long.
+    pub fn round_trip(&self, arrived: i64) -> (i64, i64) {
+        let rtt = (arrived - self.sent) - (self.acked - self.received);
+        let offset = ((self.received - self.sent) + (self.acked - arrived)) / 2;
+        (rtt, offset)
+    }
+}
+
+/// Round trips and clock offsets from acknowledgements over a run (milliseconds)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AckStats {
+    pub acks: u64,
+    pub invalid: u64, // Datagrams that were not an acknowledgement
+    pub rtt: LatencySummary,
+    pub offset: LatencySummary, // Receiver clock minus forwarder clock
+    pub latest_rtt_ms: f64,
+    pub latest_offset_ms: f64,
+}
+
+/// Collects acknowledgements as they arrive
+#[derive(Debug, Clone, Default)]
+pub struct AckTracker {
+    acks: u64,
+    invalid: u64,
+    rtt: StatsAggregator,
+    offset: StatsAggregator,
+    latest: (f64, f64), // RTT and offset of the latest acknowledgement
+}
+
+impl AckTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one datagram received at `arrived`; returns the acknowledgement
+    /// it carried
+    pub fn record(&mut self, datagram: &[u8], arrived: i64) -> Option<EventAck> {
+        let Ok(ack) = EventAck::parse(datagram) else {
+            self.invalid += 1;
+            return None;
+        };
+        let (rtt, offset) = ack.round_trip(arrived);
+        let latest = (rtt as f64 / 1_000_000.0, offset as f64 / 1_000_000.0);
+        self.acks += 1;
+        self.rtt.push(latest.0);
+        self.offset.push(latest.1);
+        self.latest = latest;
+        Some(ack)
+    }
+
+    /// `None` until an acknowledgement arrived
+    pub fn stats(&self) -> Option<AckStats> {
+        if self.acks == 0 {
+            return None;
+        }
+        Some(AckStats {
+            acks: self.acks,
+            invalid: self.invalid,
+            rtt: self.rtt.summary(),
+            offset: self.offset.summary(),
+            latest_rtt_ms: self.latest.0,
+            latest_offset_ms: self.latest.1,
+        })
+    }
+}
@@ -0,0 +1,383 @@
+// Binance WebSocket event types and stream auto-detection
+
+use crate::quote::{BookTickerQuote, TradePrint};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+use std::num::ParseFloatError;
+
+/// Binance aggregate trade event structure
+/// Matches the JSON format from Binance WebSocket aggTrade stream
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceAggTradeEvent {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type ("aggTrade")
+
+    #[serde(rename = "E")]
+    pub event_time: i64, // Event time (milliseconds)
+
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol (BTCUSDT)
+
+    #[serde(rename = "a")]
+    pub agg_trade_id: i64, // Aggregate trade ID
+
+    #[serde(rename = "p")]
+    pub price: String, // Price
+
+    #[serde(rename = "q")]
+    pub quantity: String, // Quantity
+
+    #[serde(rename = "f")]
+    pub first_trade_id: i64, // First trade ID
+
+    #[serde(rename = "l")]
+    pub last_trade_id: i64, // Last trade ID
+
+    #[serde(rename = "T")]
+    pub trade_time: i64, // Trade time (milliseconds)
+
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool, // Is the buyer the market maker?
+}
+
+impl BinanceAggTradeEvent {
+    /// Price and quantity as numbers
+    pub fn trade(&self) -> Result<TradePrint, ParseFloatError> {
+        TradePrint::parse(&self.price, &self.quantity)
+    }
+}
+
+/// Binance raw trade event structure
+/// Matches the JSON format from Binance WebSocket trade stream
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceTradeEvent {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type ("trade")
+
+    #[serde(rename = "E")]
+    pub event_time: i64, // Event time (milliseconds)
+
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol (BTCUSDT)
+
+    #[serde(rename = "t")]
+    pub trade_id: i64, // Trade ID
+
+    #[serde(rename = "p")]
+    pub price: String, // Price
+
+    #[serde(rename = "q")]
+    pub quantity: String, // Quantity
+
+    #[serde(rename = "T")]
+    pub trade_time: i64, // Trade time (milliseconds)
+
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool, // Is the buyer the market maker?
+}
+
+impl BinanceTradeEvent {
+    /// Price and quantity as numbers
+    pub fn trade(&self) -> Result<TradePrint, ParseFloatError> {
+        TradePrint::parse(&self.price, &self.quantity)
+    }
+}
+
+/// Binance book ticker event structure
+/// Matches the JSON format from Binance WebSocket bookTicker stream.
+/// Spot streams omit `e`, `E` and `T`; futures streams include them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceBookTickerEvent {
+    #[serde(rename = "e", default)]
+    pub event_type: Option<String>, // Event type ("bookTicker", futures only)
+
+    #[serde(rename = "u")]
+    pub update_id: i64, // Order book update ID
+
+    #[serde(rename = "E", default)]
+    pub event_time: Option<i64>, // Event time (milliseconds, futures only)
+
+    #[serde(rename = "T", default)]
+    pub transaction_time: Option<i64>, // Transaction time (milliseconds, futures only)
+
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol (BTCUSDT)
+
+    #[serde(rename = "b")]
+    pub best_bid_price: String, // Best bid price
+
+    #[serde(rename = "B")]
+    pub best_bid_qty: String, // Best bid quantity
+
+    #[serde(rename = "a")]
+    pub best_ask_price: String, // Best ask price
+
+    #[serde(rename = "A")]
+    pub best_ask_qty: String, // Best ask quantity
+}
+
+impl BinanceBookTickerEvent {
+    /// Best bid and ask as numbers
+    pub fn quote(&self) -> Result<BookTickerQuote, ParseFloatError> {
+        BookTickerQuote::parse(
+            &self.best_bid_price,
+            &self.best_bid_qty,
+            &self.best_ask_price,
+            &self.best_ask_qty,
+        )
+    }
+}
+
+/// Kind of Binance stream an event came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinanceStreamKind {
+    AggTrade,
+    Trade,
+    BookTicker,
+}
+
+impl BinanceStreamKind {
+    /// Stream name as used in Binance stream URLs (e.g. `btcusdt@aggTrade`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinanceStreamKind::AggTrade => "aggTrade",
+            BinanceStreamKind::Trade => "trade",
+            BinanceStreamKind::BookTicker => "bookTicker",
+        }
+    }
+}
+
+/// Any supported Binance market data event
+#[derive(Debug, Clone)]
+pub enum BinanceMarketEvent {
+    AggTrade(BinanceAggTradeEvent),
+    Trade(BinanceTradeEvent),
+    BookTicker(BinanceBookTickerEvent),
+}
+
+/// Minimal view used to detect the stream type before full parsing
+#[derive(Deserialize)]
+struct EventTypeProbe {
+    #[serde(rename = "e", default)]
+    event_type: Option<String>,
+}
+
+impl BinanceMarketEvent {
+    /// Parse a raw Binance text frame, detecting the stream type from the `e` field.
+    /// Frames without an `e` field are treated as spot bookTicker updates.
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let probe: EventTypeProbe = serde_json::from_str(text)?;
+
+        match probe.event_type.as_deref() {
+            Some("aggTrade") => Ok(Self::AggTrade(serde_json::from_str(text)?)),
+            Some("trade") => Ok(Self::Trade(serde_json::from_str(text)?)),
+            Some("bookTicker") | None => Ok(Self::BookTicker(serde_json::from_str(text)?)),
+            Some(other) => Err(serde::de::Error::custom(format!(
+                "unsupported Binance event type: {}",
+                other
+            ))),
+        }
+    }
+
+    /// Stream type this event was parsed as
+    pub fn kind(&self) -> BinanceStreamKind {
+        match self {
+            Self::AggTrade(_) => BinanceStreamKind::AggTrade,
+            Self::Trade(_) => BinanceStreamKind::Trade,
+            Self::BookTicker(_) => BinanceStreamKind::BookTicker,
+        }
+    }
+
+    /// Binance event time (E field, milliseconds), if the stream provides one
+    pub fn event_time(&self) -> Option<i64> {
+        match self {
+            Self::AggTrade(e) => Some(e.event_time),
+            Self::Trade(e) => Some(e.event_time),
+            Self::BookTicker(e) => e.event_time,
+        }
+    }
+
+    /// Symbol the event refers to
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::AggTrade(e) => &e.symbol,
+            Self::Trade(e) => &e.symbol,
+            Self::BookTicker(e) => &e.symbol,
+        }
+    }
+}
+
+// Borrowed views: the same events parsed without allocating a String per field.
+// String fields point into the received frame where no unescaping is needed.
+
+/// Borrowed view of an aggTrade or trade event
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceTradeView<'a> {
+    #[serde(rename = "E")]
+    pub event_time: i64, // Event time (milliseconds)
+
+    #[serde(rename = "s", borrow)]
+    pub symbol: Cow<'a, str>, // Symbol (BTCUSDT)
+
+    #[serde(rename = "p", borrow)]
+    pub price: Cow<'a, str>, // Price
+
+    #[serde(rename = "q", borrow)]
+    pub quantity: Cow<'a, str>, // Quantity
+
+    #[serde(rename = "T")]
+    pub trade_time: i64, // Trade time (milliseconds)
+
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool, // Is the buyer the market maker?
+
+    #[serde(rename = "a", default)]
+    pub agg_trade_id: Option<i64>, // Aggregate trade ID (aggTrade only)
+
+    #[serde(rename = "t", default)]
+    pub trade_id: Option<i64>, // Trade ID (trade only)
+}
+
+impl BinanceTradeView<'_> {
+    /// Price and quantity as numbers
+    pub fn trade(&self) -> Result<TradePrint, ParseFloatError> {
+        TradePrint::parse(&self.price, &self.quantity)
+    }
+}
+
+/// Borrowed view of a bookTicker event
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceBookTickerView<'a> {
+    #[serde(rename = "u")]
+    pub update_id: i64, // Order book update ID
+
+    #[serde(rename = "E", default)]
+    pub event_time: Option<i64>, // Event time (milliseconds, futures only)
+
+    #[serde(rename = "T", default)]
+    pub transaction_time: Option<i64>, // Transaction time (milliseconds, futures only)
+
+    #[serde(rename = "s", borrow)]
+    pub symbol: Cow<'a, str>, // Symbol (BTCUSDT)
+
+    #[serde(rename = "b", borrow)]
+    pub best_bid_price: Cow<'a, str>, // Best bid price
+
+    #[serde(rename = "B", borrow)]
+    pub best_bid_qty: Cow<'a, str>, // Best bid quantity
+
+    #[serde(rename = "a", borrow)]
+    pub best_ask_price: Cow<'a, str>, // Best ask price
+
+    #[serde(rename = "A", borrow)]
+    pub best_ask_qty: Cow<'a, str>, // Best ask quantity
+}
+
+impl BinanceBookTickerView<'_> {
+    /// Best bid and ask as numbers
+    pub fn quote(&self) -> Result<BookTickerQuote, ParseFloatError> {
+        BookTickerQuote::parse(
+            &self.best_bid_price,
+            &self.best_bid_qty,
+            &self.best_ask_price,
+            &self.best_ask_qty,
+        )
+    }
+}
+
+/// Borrowed view of any supported Binance market data event
+#[derive(Debug, Clone)]
+pub enum BinanceMarketEventView<'a> {
+    AggTrade(BinanceTradeView<'a>),
+    Trade(BinanceTradeView<'a>),
+    BookTicker(BinanceBookTickerView<'a>),
+}
+
+/// Borrowing counterpart of `EventTypeProbe`
+#[derive(Deserialize)]
+struct EventTypeProbeView<'a> {
+    #[serde(rename = "e", default, borrow)]
+    event_type: Option<&'a str>,
+}
+
+impl<'a> BinanceMarketEventView<'a> {
+    /// Parse a raw frame in place, detecting the stream type like
+    /// `BinanceMarketEvent::parse`
+    pub fn parse(frame: &'a [u8]) -> Result<Self, serde_json::Error> {
+        let probe: EventTypeProbeView = serde_json::from_slice(frame)?;
+
+        match probe.event_type {
+            Some("aggTrade") => Ok(Self::AggTrade(serde_json::from_slice(frame)?)),
+            Some("trade") => Ok(Self::Trade(serde_json::from_slice(frame)?)),
+            Some("bookTicker") | None => Ok(Self::BookTicker(serde_json::from_slice(frame)?)),
+            Some(other) => Err(serde::de::Error::custom(format!(
+                "unsupported Binance event type: {}",
+                other
+            ))),
+        }
+    }
+
+    /// Stream type this event was parsed as
+    pub fn kind(&self) -> BinanceStreamKind {
+        match self {
+            Self::AggTrade(_) => BinanceStreamKind::AggTrade,
+            Self::Trade(_) => BinanceStreamKind::Trade,
+            Self::BookTicker(_) => BinanceStreamKind::BookTicker,
+        }
+    }
+
+    /// Binance event time (E field, milliseconds), if the stream provides one
+    pub fn event_time(&self) -> Option<i64> {
+        match self {
+            Self::AggTrade(e) | Self::Trade(e) => Some(e.event_time),
+            Self::BookTicker(e) => e.event_time,
+        }
+    }
+
+    /// Trade or transaction time (T field, milliseconds), if the stream provides one
+    pub fn transaction_time(&self) -> Option<i64> {
+        match self {
+            Self::AggTrade(e) | Self::Trade(e) => Some(e.trade_time),
+            Self::BookTicker(e) => e.transaction_time,
+        }
+    }
+
+    /// Symbol the event refers to
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::AggTrade(e) | Self::Trade(e) => &e.symbol,
+            Self::BookTicker(e) => &e.symbol,
+        }
+    }
+
+    /// ID Binance assigned to the event (aggregate trade, trade or book
+    /// update ID), the same on every connection that receives it
+    pub fn update_id(&self) -> Option<i64> {
+        match self {
+            Self::AggTrade(e) => e.agg_trade_id,
+            Self::Trade(e) => e.trade_id,
+            Self::BookTicker(e) => Some(e.update_id),
+        }
+    }
+}
+
+/// Envelope Binance wraps events in on combined streams (`/stream?streams=`)
+#[derive(Deserialize)]
+struct CombinedStreamFrame<'a> {
+    stream: &'a str,
+    #[serde(borrow)]
+    data: &'a RawValue,
+}
+
+/// Split a combined-stream frame into the stream name (`btcusdt@aggTrade`)
+/// and the event object inside it. Returns `None` for frames of a raw
+/// stream, which are the event object itself.
+pub fn split_combined_stream(text: &str) -> Option<(&str, &str)> {
+    // Binance puts `stream` first; raw stream events never start with it
+    if !text.trim_start().starts_with(r#"{"stream""#) {
+        return None;
+    }
+    let frame: CombinedStreamFrame = serde_json::from_str(text).ok()?;
+    Some((frame.stream, frame.data.get()))
+}
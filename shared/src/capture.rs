@@ -0,0 +1,115 @@
+// Recorded raw exchange frames (newline-delimited JSON, optionally gzipped)
+
+use crate::rotate::{RotatingFile, RotationPolicy};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+/// One raw WebSocket text frame as it was received
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    pub receive_time: i64, // Local receive time (epoch nanos)
+    pub text: String,      // Frame exactly as sent by the exchange
+}
+
+/// Borrowed form of `CapturedFrame` so writing does not copy the frame
+#[derive(Serialize)]
+struct CapturedFrameRef<'a> {
+    receive_time: i64,
+    text: &'a str,
+}
+
+fn is_gzip(path: &str) -> bool {
+    path.ends_with(".gz")
+}
+
+/// Read captured frames from a `.jsonl` file (or `.jsonl.gz`), one frame per
+/// line. Blank lines are skipped.
+pub fn read_capture(
+    path: &str,
+) -> Result<impl Iterator<Item = Result<CapturedFrame, std::io::Error>>, std::io::Error> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if is_gzip(path) {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    Ok(BufReader::new(reader)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?)))
+}
+
+enum CaptureOutput {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Rotating(RotatingFile),
+}
+
+/// Writes every received frame to a capture file for replay and debugging.
+/// Paths ending in `.gz` are gzip-compressed.
+pub struct CaptureWriter {
+    output: CaptureOutput,
+    frames: u64,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &str) -> Result<Self, std::io::Error> {
+        let file = BufWriter::new(File::create(path)?);
+        let output = if is_gzip(path) {
+            CaptureOutput::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            CaptureOutput::Plain(file)
+        };
+        Ok(Self { output, frames: 0 })
+    }
+
+    /// Like `create`, but split the capture into timestamped files following
+    /// `policy`. A `.gz` path turns on compression of the finished files.
+    pub fn create_rotating(path: &str, policy: RotationPolicy) -> Result<Self, std::io::Error> {
+        if !policy.rotates() {
+            return Self::create(path);
+        }
+        let (base, compress) = match path.strip_suffix(".gz") {
+            Some(base) => (base, true),
+            None => (path, policy.compress),
+        };
+        let policy = RotationPolicy { compress, ..policy };
+        let output = CaptureOutput::Rotating(RotatingFile::create(base, policy, b"")?);
+        Ok(Self { output, frames: 0 })
+    }
+
+    /// Append one frame
+    pub fn write(&mut self, receive_time: i64, text: &str) -> Result<(), std::io::Error> {
+        let line = serde_json::to_string(&CapturedFrameRef { receive_time, text })?;
+        let writer: &mut dyn Write = match &mut self.output {
+            CaptureOutput::Plain(w) => w,
+            CaptureOutput::Gzip(w) => w,
+            CaptureOutput::Rotating(w) => {
+                w.rotate_if_due()?;
+                w
+            }
+        };
+        writeln!(writer, "{}", line)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames written so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Flush buffered frames and, for gzip, write the trailer
+    pub fn finish(self) -> Result<(), std::io::Error> {
+        match self.output {
+            CaptureOutput::Plain(mut w) => w.flush(),
+            CaptureOutput::Gzip(w) => w.finish()?.flush(),
+            CaptureOutput::Rotating(w) => w.finish(),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// Local clock error, read from chrony or the PTP daemon (--clock-sync)
+//
+// Both binaries read it at startup and every --clock-sync-interval after that
+// in the background, as the commands may take a moment. The latest estimate
+// is what events are corrected with; every reading is kept for the results.
+// A failed reading clears the estimate rather than leaving a stale one.
+
+use chrono::Utc;
+use latency_core::{ChronyTracking, ClockEstimate, ClockSample, ClockSource, PtpDataSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::{interval, timeout, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Clock state from `chronyc tracking`, if chrony is installed and running
+pub async fn chrony_tracking() -> Option<ChronyTracking> {
+    let output = run("chronyc", &["tracking"]).await?;
+    ChronyTracking::parse(&output)
+}
+
+/// ptp4l's offset from its grandmaster, if linuxptp is installed and running.
+/// With hardware timestamping, phc2sys must keep the system clock on the PTP
+/// hardware clock for this to describe it.
+pub async fn ptp_data_set() -> Option<PtpDataSet> {
+    let output = run("pmc", &["-u", "-b", "0", "GET CURRENT_DATA_SET"]).await?;
+    PtpDataSet::parse(&output)
+}
+
+/// Read the clock error once
+pub async fn read_clock(source: ClockSource) -> Option<ClockEstimate> {
+    match source {
+        ClockSource::Chrony => chrony_tracking().await.map(|tracking| tracking.estimate()),
+        ClockSource::Ptp => ptp_data_set().await.map(|data_set| data_set.estimate()),
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = timeout(COMMAND_TIMEOUT, Command::new(program).args(args).output())
+        .await
+        .map_err(|_| debug!(program, "clock command timed out"))
+        .ok()?
+        .map_err(|e| debug!(program, error = %e, "clock command not available"))
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[derive(Debug, Default)]
+struct ClockState {
+    latest: Option<ClockEstimate>,
+    readings: u64,
+    samples: Vec<ClockSample>,
+}
+
+/// Reads the local clock error periodically in the background
+#[derive(Debug, Clone)]
+pub struct ClockMonitor {
+    source: ClockSource,
+    state: Arc<Mutex<ClockState>>,
+}
+
+impl ClockMonitor {
+    /// Read now and every `period` after that, for as long as the process runs
+    pub fn start(source: ClockSource, period: Duration) -> Self {
+        let monitor = Self {
+            source,
+            state: Arc::default(),
+        };
+        let task = monitor.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                task.read().await;
+            }
+        });
+        monitor
+    }
+
+    async fn read(&self) {
+        let estimate = read_clock(self.source).await;
+        let mut state = self.state.lock().unwrap();
+        let first = state.readings == 0;
+        state.readings += 1;
+        match estimate {
+            Some(estimate) => {
+                if state.latest.is_none() {
+                    info!(
+                        source = %self.source,
+                        offset_ns = estimate.offset_ns,
+                        error_bound_ns = estimate.error_bound_ns,
+                        "clock error estimate available"
+                    );
+                }
+                let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+                state
+                    .samples
+                    .push(ClockSample::new(now, self.source, estimate));
+            }
+            // Warned once per outage
+            None if first || state.latest.is_some() => warn!(
+                source = %self.source,
+                "cannot read the clock error, events are not corrected until it is back"
+            ),
+            None => {}
+        }
+        state.latest = estimate;
+    }
+
+    /// The latest estimate, if the last reading succeeded
+    pub fn latest(&self) -> Option<ClockEstimate> {
+        self.state.lock().unwrap().latest
+    }
+
+    /// Every successful reading so far
+    pub fn samples(&self) -> Vec<ClockSample> {
+        self.state.lock().unwrap().samples.clone()
+    }
+}
@@ -0,0 +1,156 @@
+// Errors of the forwarder and receiver, by kind of failure
+//
+// Callers match on the kind instead of a message: connection and transport
+// failures are usually worth retrying, parse and clock errors are not. The
+// binaries exit with the kind's code so the orchestrator, which only sees the
+// exit status over ssh, can tell them apart too.
+
+use std::fmt;
+use std::io;
+use std::time::SystemTimeError;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+/// Any underlying error, kept for its message
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = ExperimentError> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExperimentError {
+    #[error("connection failed: {0}")]
+    Connect(BoxError), // Exchange WebSocket, receiver socket or HTTP endpoint
+    #[error("parse error: {0}")]
+    Parse(BoxError), // Exchange frames, forwarded events, captures, URLs
+    #[error("transport error: {0}")]
+    Transport(BoxError), // An established connection failed
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error), // Local files and sockets
+    #[error("clock error: {0}")]
+    Clock(String), // System clock unusable for timestamps
+    #[error("{0}")]
+    Other(String), // Invalid arguments or input, a failed self-test
+}
+
+impl ExperimentError {
+    pub fn connect(error: impl Into<BoxError>) -> Self {
+        ExperimentError::Connect(error.into())
+    }
+
+    pub fn parse(error: impl Into<BoxError>) -> Self {
+        ExperimentError::Parse(error.into())
+    }
+
+    pub fn transport(error: impl Into<BoxError>) -> Self {
+        ExperimentError::Transport(error.into())
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        ExperimentError::Other(message.into())
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ExperimentError::Connect(_) => ErrorKind::Connect,
+            ExperimentError::Parse(_) => ErrorKind::Parse,
+            ExperimentError::Transport(_) => ErrorKind::Transport,
+            ExperimentError::Io(_) => ErrorKind::Io,
+            ExperimentError::Clock(_) => ErrorKind::Clock,
+            ExperimentError::Other(_) => ErrorKind::Other,
+        }
+    }
+
+    /// Whether trying again may succeed. I/O errors count when they come
+    /// from the network rather than local files.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ExperimentError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+            ),
+            _ => self.kind().is_retryable(),
+        }
+    }
+
+    /// Process exit status for this error
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+}
+
+impl From<WsError> for ExperimentError {
+    fn from(error: WsError) -> Self {
+        ExperimentError::Connect(error.into())
+    }
+}
+
+impl From<serde_json::Error> for ExperimentError {
+    fn from(error: serde_json::Error) -> Self {
+        ExperimentError::Parse(error.into())
+    }
+}
+
+impl From<SystemTimeError> for ExperimentError {
+    fn from(error: SystemTimeError) -> Self {
+        ExperimentError::Clock(error.to_string())
+    }
+}
+
+/// The kind of an [`ExperimentError`], also recoverable from an exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Connect,
+    Parse,
+    Transport,
+    Io,
+    Clock,
+    Other, // Exits with 1 like any unclassified error
+}
+
+/// Exit codes by kind; 1 remains a usage or unclassified error
+const EXIT_CODES: [(ErrorKind, i32); 5] = [
+    (ErrorKind::Connect, 3),
+    (ErrorKind::Parse, 4),
+    (ErrorKind::Transport, 5),
+    (ErrorKind::Io, 6),
+    (ErrorKind::Clock, 7),
+];
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        EXIT_CODES
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map_or(1, |&(_, code)| code)
+    }
+
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        EXIT_CODES
+            .iter()
+            .find(|&&(_, c)| c == code)
+            .map(|&(kind, _)| kind)
+    }
+
+    /// Connection and transport failures are usually transient. An I/O error
+    /// known only by its exit code may be either, so it is not retried.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, ErrorKind::Connect | ErrorKind::Transport)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::Connect => "connection error",
+            ErrorKind::Parse => "parse error",
+            ErrorKind::Transport => "transport error",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::Clock => "clock error",
+            ErrorKind::Other => "error",
+        })
+    }
+}
@@ -0,0 +1,303 @@
+// Venue-independent view of exchange market data feeds
+
+use crate::binance::{split_combined_stream, BinanceMarketEventView};
+use crate::quote::{mid_price, spread_bps};
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// Exchanges with a built-in adapter, as accepted by `exchange_adapter`
+pub const EXCHANGES: &[&str] = &["binance", "okx", "bybit"];
+
+/// Market data event normalized across exchanges. String fields borrow from
+/// the received frame wherever possible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickerEvent<'a> {
+    pub exchange: &'static str,
+    pub symbol: Cow<'a, str>,    // Venue-native symbol (BTCUSDT, BTC-USDT)
+    pub event_time: Option<i64>, // Exchange event time (ms; µs on Binance timeUnit=MICROSECOND streams), if the feed has one
+    pub transaction_time: Option<i64>, // Trade/match time (milliseconds), if distinct from event time
+    pub update_id: Option<i64>, // Venue event ID, identical on every connection (Binance a, t or u)
+    pub price: Option<Cow<'a, str>>, // Last trade price
+    pub best_bid_price: Option<Cow<'a, str>>, // Best bid (book ticker feeds)
+    pub best_ask_price: Option<Cow<'a, str>>, // Best ask (book ticker feeds)
+    pub stream: Option<&'a str>, // Binance combined-stream name (btcusdt@aggTrade), if the frame was wrapped in one
+}
+
+impl TickerEvent<'_> {
+    /// Last trade price as a number; `None` without one or if it does not parse
+    pub fn last_price(&self) -> Option<f64> {
+        parse_price(&self.price)
+    }
+
+    /// Best bid as a number (book ticker feeds)
+    pub fn best_bid(&self) -> Option<f64> {
+        parse_price(&self.best_bid_price)
+    }
+
+    /// Best ask as a number (book ticker feeds)
+    pub fn best_ask(&self) -> Option<f64> {
+        parse_price(&self.best_ask_price)
+    }
+
+    /// Halfway between best bid and best ask (book ticker feeds)
+    pub fn mid_price(&self) -> Option<f64> {
+        Some(mid_price(self.best_bid()?, self.best_ask()?))
+    }
+
+    /// Spread in basis points of the mid-price (book ticker feeds)
+    pub fn spread_bps(&self) -> Option<f64> {
+        Some(spread_bps(self.best_bid()?, self.best_ask()?))
+    }
+}
+
+fn parse_price(price: &Option<Cow<'_, str>>) -> Option<f64> {
+    price.as_deref()?.parse().ok()
+}
+
+/// Connects the latency experiment to one exchange's public WebSocket feed.
+///
+/// Symbols are given in `BASE-QUOTE` form (e.g. `BTC-USDT`) and translated
+/// to the venue's own format by each adapter.
+pub trait ExchangeAdapter: Send + Sync {
+    /// Short lowercase exchange name recorded in results
+    fn name(&self) -> &'static str;
+
+    /// WebSocket URL streaming trades for `symbol`
+    fn stream_url(&self, symbol: &str) -> String;
+
+    /// Subscription request to send after connecting, for venues that
+    /// subscribe in-band rather than through the URL
+    fn subscribe_message(&self, symbol: &str) -> Option<String>;
+
+    /// Parse a text frame. Returns `Ok(None)` for frames that carry no market
+    /// data, such as subscription acknowledgements.
+    fn parse<'a>(&self, text: &'a str) -> Result<Option<TickerEvent<'a>>, serde_json::Error>;
+}
+
+/// Look up the adapter for an exchange by name
+pub fn exchange_adapter(name: &str) -> Option<Box<dyn ExchangeAdapter>> {
+    match name.to_ascii_lowercase().as_str() {
+        "binance" => Some(Box::new(Binance)),
+        "okx" => Some(Box::new(Okx)),
+        "bybit" => Some(Box::new(Bybit)),
+        _ => None,
+    }
+}
+
+/// Split a `BASE-QUOTE` symbol, accepting `/` and `_` as separators too
+fn split_symbol(symbol: &str) -> (String, String) {
+    let upper = symbol.to_ascii_uppercase();
+    match upper.split_once(['-', '/', '_']) {
+        Some((base, quote)) => (base.to_string(), quote.to_string()),
+        None => (upper, String::new()),
+    }
+}
+
+/// Binance spot aggregate trades, subscribed through the stream URL
+#[derive(Debug, Clone, Copy)]
+pub struct Binance;
+
+impl ExchangeAdapter for Binance {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn stream_url(&self, symbol: &str) -> String {
+        let (base, quote) = split_symbol(symbol);
+        format!(
+            "wss://stream.binance.com:9443/ws/{}{}@aggTrade",
+            base.to_ascii_lowercase(),
+            quote.to_ascii_lowercase()
+        )
+    }
+
+    fn subscribe_message(&self, _symbol: &str) -> Option<String> {
+        None
+    }
+
+    fn parse<'a>(&self, text: &'a str) -> Result<Option<TickerEvent<'a>>, serde_json::Error> {
+        // Combined streams (/stream?streams=) wrap the event with its stream name
+        let (stream, text) = match split_combined_stream(text) {
+            Some((stream, data)) => (Some(stream), data),
+            None => (None, text),
+        };
+        let event = BinanceMarketEventView::parse(text.as_bytes())?;
+        let event_time = event.event_time();
+        let transaction_time = event.transaction_time();
+        let update_id = event.update_id();
+
+        let event = match event {
+            BinanceMarketEventView::AggTrade(e) | BinanceMarketEventView::Trade(e) => TickerEvent {
+                exchange: self.name(),
+                symbol: e.symbol,
+                event_time,
+                transaction_time,
+                update_id,
+                price: Some(e.price),
+                best_bid_price: None,
+                best_ask_price: None,
+                stream,
+            },
+            BinanceMarketEventView::BookTicker(e) => TickerEvent {
+                exchange: self.name(),
+                symbol: e.symbol,
+                event_time,
+                transaction_time,
+                update_id,
+                price: None,
+                best_bid_price: Some(e.best_bid_price),
+                best_ask_price: Some(e.best_ask_price),
+                stream,
+            },
+        };
+        Ok(Some(event))
+    }
+}
+
+/// OKX v5 public trades channel
+#[derive(Debug, Clone, Copy)]
+pub struct Okx;
+
+#[derive(Debug, Deserialize)]
+struct OkxMessage<'a> {
+    event: Option<String>, // "subscribe" or "error" for control frames
+    msg: Option<String>,   // Error description
+    #[serde(default, borrow)]
+    data: Vec<OkxTrade<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OkxTrade<'a> {
+    #[serde(rename = "instId", borrow)]
+    inst_id: Cow<'a, str>,
+    #[serde(borrow)]
+    px: Cow<'a, str>,
+    #[serde(borrow)]
+    ts: Cow<'a, str>, // Trade time (milliseconds, as a string)
+}
+
+impl ExchangeAdapter for Okx {
+    fn name(&self) -> &'static str {
+        "okx"
+    }
+
+    fn stream_url(&self, _symbol: &str) -> String {
+        "wss://ws.okx.com:8443/ws/v5/public".to_string()
+    }
+
+    fn subscribe_message(&self, symbol: &str) -> Option<String> {
+        let (base, quote) = split_symbol(symbol);
+        Some(
+            serde_json::json!({
+                "op": "subscribe",
+                "args": [{"channel": "trades", "instId": format!("{}-{}", base, quote)}],
+            })
+            .to_string(),
+        )
+    }
+
+    fn parse<'a>(&self, text: &'a str) -> Result<Option<TickerEvent<'a>>, serde_json::Error> {
+        // Keep-alive replies are plain text
+        if text == "pong" {
+            return Ok(None);
+        }
+
+        let mut message: OkxMessage = serde_json::from_str(text)?;
+        if message.event.as_deref() == Some("error") {
+            return Err(serde::de::Error::custom(format!(
+                "OKX error: {}",
+                message.msg.unwrap_or_default()
+            )));
+        }
+
+        // A frame may batch several trades; the newest is closest to the push time
+        let newest =
+            (0..message.data.len()).max_by_key(|&i| message.data[i].ts.parse::<i64>().ok());
+        let Some(newest) = newest else {
+            return Ok(None);
+        };
+        let latest = message.data.swap_remove(newest);
+        let event_time = latest
+            .ts
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid OKX ts: {}", latest.ts)))?;
+
+        Ok(Some(TickerEvent {
+            exchange: self.name(),
+            symbol: latest.inst_id,
+            event_time: Some(event_time),
+            transaction_time: None, // Only the trade time is published
+            update_id: None,
+            price: Some(latest.px),
+            best_bid_price: None,
+            best_ask_price: None,
+            stream: None,
+        }))
+    }
+}
+
+/// Bybit v5 spot public trades
+#[derive(Debug, Clone, Copy)]
+pub struct Bybit;
+
+#[derive(Debug, Deserialize)]
+struct BybitMessage<'a> {
+    #[serde(borrow)]
+    topic: Option<&'a str>, // Absent on control frames
+    ts: Option<i64>, // Message generation time (milliseconds)
+    #[serde(default, borrow)]
+    data: Vec<BybitTrade<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitTrade<'a> {
+    #[serde(rename = "T")]
+    trade_time: i64,
+    #[serde(rename = "s", borrow)]
+    symbol: Cow<'a, str>,
+    #[serde(rename = "p", borrow)]
+    price: Cow<'a, str>,
+}
+
+impl ExchangeAdapter for Bybit {
+    fn name(&self) -> &'static str {
+        "bybit"
+    }
+
+    fn stream_url(&self, _symbol: &str) -> String {
+        "wss://stream.bybit.com/v5/public/spot".to_string()
+    }
+
+    fn subscribe_message(&self, symbol: &str) -> Option<String> {
+        let (base, quote) = split_symbol(symbol);
+        Some(
+            serde_json::json!({
+                "op": "subscribe",
+                "args": [format!("publicTrade.{}{}", base, quote)],
+            })
+            .to_string(),
+        )
+    }
+
+    fn parse<'a>(&self, text: &'a str) -> Result<Option<TickerEvent<'a>>, serde_json::Error> {
+        let mut message: BybitMessage = serde_json::from_str(text)?;
+        if message.topic.is_none() {
+            return Ok(None);
+        }
+        let Some(last) = message.data.pop() else {
+            return Ok(None);
+        };
+
+        Ok(Some(TickerEvent {
+            exchange: self.name(),
+            symbol: last.symbol,
+            event_time: message.ts,
+            transaction_time: Some(last.trade_time),
+            update_id: None,
+            price: Some(last.price),
+            best_bid_price: None,
+            best_ask_price: None,
+            stream: None,
+        }))
+    }
+}
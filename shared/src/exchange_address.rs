@@ -0,0 +1,137 @@
+// The address every exchange connection lands on
+//
+// The peer address is read from the connected socket. Its reverse DNS name is
+// looked up in the background, so connecting is not delayed, and the AWS region
+// is guessed from that name.
+
+use crate::ws::ExchangeStream;
+use latency_core::{guess_aws_region, ExchangeAddress};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio_tungstenite::MaybeTlsStream;
+use tracing::info;
+
+/// Longest wait for a reverse DNS answer
+const REVERSE_DNS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Exchange addresses of a run in connection order; clones share the list
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeAddresses(Arc<Mutex<Vec<ExchangeAddress>>>);
+
+impl ExchangeAddresses {
+    /// Record the address `ws` is connected to, once its reverse DNS name is known
+    pub fn record(&self, ws: &ExchangeStream) {
+        if let Some(peer) = tcp_peer(ws.get_ref().get_ref()) {
+            self.record_peer(peer, None);
+        }
+    }
+
+    /// Record a connection to `peer` made just now, to one of several endpoints
+    pub fn record_peer(&self, peer: SocketAddr, endpoint: Option<u16>) {
+        let connected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as i64;
+        let addresses = self.0.clone();
+        tokio::spawn(async move {
+            let reverse_dns = reverse_dns(peer.ip()).await;
+            let address = ExchangeAddress {
+                connected_at,
+                ip: peer.ip().to_string(),
+                port: peer.port(),
+                aws_region: reverse_dns.as_deref().and_then(guess_aws_region),
+                reverse_dns,
+                endpoint,
+            };
+            info!(
+                endpoint,
+                ip = %address.ip,
+                port = address.port,
+                reverse_dns = address.reverse_dns.as_deref(),
+                aws_region = address.aws_region.as_deref(),
+                "exchange connection address"
+            );
+            // Lookups finish out of order
+            let mut addresses = addresses.lock().unwrap();
+            let at = addresses.partition_point(|known| known.connected_at <= connected_at);
+            addresses.insert(at, address);
+        });
+    }
+
+    /// The addresses resolved so far
+    pub fn snapshot(&self) -> Vec<ExchangeAddress> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Remote address of a WebSocket's TCP connection, under TLS if any
+pub fn tcp_peer(stream: &MaybeTlsStream<TcpStream>) -> Option<SocketAddr> {
+    match stream {
+        MaybeTlsStream::Plain(tcp) => tcp.peer_addr().ok(),
+        MaybeTlsStream::NativeTls(tls) => tls.get_ref().get_ref().get_ref().peer_addr().ok(),
+        _ => None,
+    }
+}
+
+async fn reverse_dns(ip: IpAddr) -> Option<String> {
+    let lookup = tokio::task::spawn_blocking(move || name_of(ip));
+    tokio::time::timeout(REVERSE_DNS_TIMEOUT, lookup)
+        .await
+        .ok()?
+        .ok()?
+}
+
+/// The host name of `ip` from getnameinfo; `None` without a PTR record
+#[cfg(target_os = "linux")]
+fn name_of(ip: IpAddr) -> Option<String> {
+    const NI_MAXHOST: usize = 1025;
+
+    // SAFETY: sockaddr_storage is plain old data
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let len = match ip {
+        IpAddr::V4(ip) => {
+            // SAFETY: sockaddr_storage is large and aligned enough for sockaddr_in
+            let addr = unsafe {
+                &mut *(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in)
+            };
+            addr.sin_family = libc::AF_INET as libc::sa_family_t;
+            addr.sin_addr.s_addr = u32::from_ne_bytes(ip.octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(ip) => {
+            // SAFETY: sockaddr_storage is large and aligned enough for sockaddr_in6
+            let addr = unsafe {
+                &mut *(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_in6)
+            };
+            addr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            addr.sin6_addr.s6_addr = ip.octets();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    let mut host = [0 as libc::c_char; NI_MAXHOST];
+    // SAFETY: storage holds a sockaddr of `len` bytes and host is NI_MAXHOST long
+    let ret = unsafe {
+        libc::getnameinfo(
+            &storage as *const libc::sockaddr_storage as *const libc::sockaddr,
+            len as libc::socklen_t,
+            host.as_mut_ptr(),
+            NI_MAXHOST as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+    // SAFETY: getnameinfo wrote a NUL-terminated name into host
+    let name = unsafe { std::ffi::CStr::from_ptr(host.as_ptr()) };
+    name.to_str().ok().map(str::to_string)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn name_of(_ip: IpAddr) -> Option<String> {
+    None
+}
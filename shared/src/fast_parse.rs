@@ -0,0 +1,156 @@
+// Field extractor for the forwarder hot path (--fast-parse)
+//
+// The forwarder forwards the raw frame and only needs a few fields from it, so
+// instead of deserializing every frame it scans the flat Binance event object
+// once for `E`, `T`, the event ID and `s`. Anything it does not understand
+// (nested objects, escaped strings) is handed back to the full serde parser.
+
+use crate::binance::split_combined_stream;
+use crate::exchange::{Binance, ExchangeAdapter, TickerEvent};
+use std::borrow::Cow;
+
+/// The fields of a Binance event the forwarder needs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BinanceFields<'a> {
+    pub event_time: Option<i64>,       // E
+    pub transaction_time: Option<i64>, // T
+    pub update_id: Option<i64>,        // a (aggTrade), t (trade) or u (bookTicker)
+    pub symbol: Option<&'a str>,       // s
+}
+
+/// Scan a flat JSON object for the fields in `BinanceFields`. Returns `None`
+/// if the frame is not a flat object of numbers, plain strings and literals,
+/// or is an event type other than aggTrade, trade or bookTicker.
+pub fn extract_binance_fields(text: &str) -> Option<BinanceFields<'_>> {
+    let bytes = text.as_bytes();
+    let mut fields = BinanceFields::default();
+    let mut i = skip_whitespace(bytes, 0);
+    if bytes.get(i) != Some(&b'{') {
+        return None;
+    }
+    i += 1;
+
+    loop {
+        i = skip_whitespace(bytes, i);
+        match bytes.get(i)? {
+            b'}' => return Some(fields),
+            b'"' => {}
+            _ => return None,
+        }
+        let (key, next) = string_at(bytes, i)?;
+        i = skip_whitespace(bytes, next);
+        if bytes.get(i) != Some(&b':') {
+            return None;
+        }
+        i = skip_whitespace(bytes, i + 1);
+
+        match bytes.get(i)? {
+            b'"' => {
+                let (value, next) = string_at(bytes, i)?;
+                match key {
+                    b"s" => fields.symbol = Some(&text[i + 1..next - 1]),
+                    // Other event types are left to the full parser to accept or reject
+                    b"e" if !matches!(value, b"aggTrade" | b"trade" | b"bookTicker") => {
+                        return None
+                    }
+                    _ => {}
+                }
+                i = next;
+            }
+            b'-' | b'0'..=b'9' => {
+                let start = i;
+                while matches!(
+                    bytes.get(i),
+                    Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                ) {
+                    i += 1;
+                }
+                let number = || text[start..i].parse::<i64>().ok();
+                match key {
+                    b"E" => fields.event_time = Some(number()?),
+                    b"T" => fields.transaction_time = Some(number()?),
+                    b"a" | b"t" | b"u" => fields.update_id = Some(number()?),
+                    _ => {}
+                }
+            }
+            b't' | b'f' | b'n' => {
+                while bytes.get(i).is_some_and(u8::is_ascii_alphabetic) {
+                    i += 1;
+                }
+            }
+            _ => return None,
+        }
+
+        i = skip_whitespace(bytes, i);
+        match bytes.get(i)? {
+            b',' => i += 1,
+            b'}' => return Some(fields),
+            _ => return None,
+        }
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+        i += 1;
+    }
+    i
+}
+
+/// Contents of the string starting at `bytes[start]` and the index after its
+/// closing quote; `None` for strings with escapes
+fn string_at(bytes: &[u8], start: usize) -> Option<(&[u8], usize)> {
+    let rest = bytes.get(start + 1..)?;
+    let end = rest.iter().position(|&b| b == b'"' || b == b'\\')?;
+    if rest[end] == b'\\' {
+        return None;
+    }
+    Some((&rest[..end], start + end + 2))
+}
+
+/// Binance adapter that reads only the fields the forwarder needs, falling
+/// back to the full parser for frames the extractor does not handle. Events
+/// carry no prices.
+#[derive(Debug, Clone, Copy)]
+pub struct BinanceFastParse;
+
+impl ExchangeAdapter for BinanceFastParse {
+    fn name(&self) -> &'static str {
+        Binance.name()
+    }
+
+    fn stream_url(&self, symbol: &str) -> String {
+        Binance.stream_url(symbol)
+    }
+
+    fn subscribe_message(&self, symbol: &str) -> Option<String> {
+        Binance.subscribe_message(symbol)
+    }
+
+    fn parse<'a>(&self, text: &'a str) -> Result<Option<TickerEvent<'a>>, serde_json::Error> {
+        let (stream, data) = match split_combined_stream(text) {
+            Some((stream, data)) => (Some(stream), data),
+            None => (None, text),
+        };
+        match extract_binance_fields(data) {
+            // Every market data event has a symbol; anything else goes to the full parser
+            Some(BinanceFields {
+                event_time,
+                transaction_time,
+                update_id,
+                symbol: Some(symbol),
+            }) => Ok(Some(TickerEvent {
+                exchange: self.name(),
+                symbol: Cow::Borrowed(symbol),
+                event_time,
+                transaction_time,
+                update_id,
+                price: None,
+                best_bid_price: None,
+                best_ask_price: None,
+                stream,
+            })),
+            _ => Binance.parse(text),
+        }
+    }
+}
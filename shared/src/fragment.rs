@@ -0,0 +1,190 @@
+// Application-level fragmentation for the UDP backbone path
+//
+// Events that fit in one datagram are sent as plain JSON. Larger ones are
+// split into fragments that each carry a small binary header:
+//
+//   magic (1) | version (1) | frame ID (8, BE) | index (2, BE) | count (2, BE) | payload
+//
+// The frame ID is the event's sequence ID. A JSON datagram always starts with
+// `{`, so the magic byte tells the two apart.
+
+use latency_core::FragmentStats;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+const MAGIC: u8 = 0xFA;
+const VERSION: u8 = 1;
+
+/// Bytes of header in front of every fragment's payload
+pub const FRAGMENT_HEADER_LEN: usize = 14;
+
+/// Completed or expired frame IDs remembered to recognize late fragments
+const RECENT_FRAMES: usize = 256;
+
+/// Split `payload` into datagrams of at most `max_datagram` bytes. Returns
+/// `None` when it fits in one datagram and can be sent unchanged.
+///
+/// # Panics
+/// If `max_datagram` leaves no room for payload after the header.
+pub fn fragment(frame_id: u64, payload: &[u8], max_datagram: usize) -> Option<Vec<Vec<u8>>> {
+    if payload.len() <= max_datagram {
+        return None;
+    }
+    assert!(
+        max_datagram > FRAGMENT_HEADER_LEN,
+        "max datagram size must exceed the fragment header"
+    );
+    let chunks = payload.chunks(max_datagram - FRAGMENT_HEADER_LEN);
+    let count = u16::try_from(chunks.len()).expect("frame too large to fragment");
+    Some(
+        chunks
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut datagram = Vec::with_capacity(FRAGMENT_HEADER_LEN + chunk.len());
+                datagram.extend_from_slice(&[MAGIC, VERSION]);
+                datagram.extend_from_slice(&frame_id.to_be_bytes());
+                datagram.extend_from_slice(&(index as u16).to_be_bytes());
+                datagram.extend_from_slice(&count.to_be_bytes());
+                datagram.extend_from_slice(chunk);
+                datagram
+            })
+            .collect(),
+    )
+}
+
+/// Fragment header fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    frame_id: u64,
+    index: u16,
+    count: u16,
+}
+
+/// Header of `datagram` if it is a well-formed fragment
+fn parse_header(datagram: &[u8]) -> Option<Header> {
+    let header = datagram.get(..FRAGMENT_HEADER_LEN)?;
+    if header[0] != MAGIC || header[1] != VERSION {
+        return None;
+    }
+    let header = Header {
+        frame_id: u64::from_be_bytes(header[2..10].try_into().ok()?),
+        index: u16::from_be_bytes(header[10..12].try_into().ok()?),
+        count: u16::from_be_bytes(header[12..14].try_into().ok()?),
+    };
+    (header.index < header.count).then_some(header)
+}
+
+/// What a received datagram amounted to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Datagram<'a> {
+    Whole(&'a [u8]),      // Not a fragment; handle as is
+    Reassembled(Vec<u8>), // The last missing fragment of a frame
+    Pending,              // A fragment of a frame that is not complete yet
+}
+
+/// A frame waiting for the rest of its fragments
+#[derive(Debug)]
+struct Partial {
+    first_seen: i64, // Epoch nanos
+    parts: Vec<Option<Vec<u8>>>,
+    received: u16,
+}
+
+/// Puts fragmented frames back together. Frames still missing fragments
+/// `timeout` after their first fragment arrived are dropped and counted.
+#[derive(Debug)]
+pub struct Reassembler {
+    timeout_ns: i64,
+    pending: HashMap<u64, Partial>,
+    recent: VecDeque<u64>,
+    stats: FragmentStats,
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout_ns: timeout.as_nanos() as i64,
+            pending: HashMap::new(),
+            recent: VecDeque::with_capacity(RECENT_FRAMES),
+            stats: FragmentStats::default(),
+        }
+    }
+
+    /// Handle one datagram received at `receive_time` (epoch nanos)
+    pub fn push<'a>(&mut self, datagram: &'a [u8], receive_time: i64) -> Datagram<'a> {
+        self.expire(receive_time);
+        let Some(header) = parse_header(datagram) else {
+            return Datagram::Whole(datagram);
+        };
+        self.stats.fragments += 1;
+
+        if self.recent.contains(&header.frame_id) {
+            self.stats.duplicate_fragments += 1;
+            return Datagram::Pending;
+        }
+        let partial = self.pending.entry(header.frame_id).or_insert_with(|| {
+            self.stats.frames += 1;
+            Partial {
+                first_seen: receive_time,
+                parts: vec![None; usize::from(header.count)],
+                received: 0,
+            }
+        });
+        // A count that disagrees with earlier fragments is treated like a duplicate;
+        // checking it first keeps the index within `parts`
+        let index = usize::from(header.index);
+        if partial.parts.len() != usize::from(header.count) || partial.parts[index].is_some() {
+            self.stats.duplicate_fragments += 1;
+            return Datagram::Pending;
+        }
+        partial.parts[index] = Some(datagram[FRAGMENT_HEADER_LEN..].to_vec());
+        partial.received += 1;
+        if usize::from(partial.received) < partial.parts.len() {
+            return Datagram::Pending;
+        }
+
+        let partial = self.pending.remove(&header.frame_id).unwrap();
+        self.remember(header.frame_id);
+        self.stats.reassembled += 1;
+        Datagram::Reassembled(partial.parts.into_iter().flatten().flatten().collect())
+    }
+
+    /// Counts so far; frames still pending are not included as incomplete
+    pub fn stats(&self) -> FragmentStats {
+        self.stats
+    }
+
+    /// Counts for the whole run, dropping frames that are still incomplete
+    pub fn finish(mut self) -> FragmentStats {
+        self.expire(i64::MAX);
+        self.stats
+    }
+
+    /// Drop frames whose first fragment arrived more than the timeout before `now`
+    fn expire(&mut self, now: i64) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let cutoff = now.saturating_sub(self.timeout_ns);
+        let expired: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, partial)| partial.first_seen < cutoff)
+            .map(|(&frame_id, _)| frame_id)
+            .collect();
+        for frame_id in expired {
+            let partial = self.pending.remove(&frame_id).unwrap();
+            self.stats.incomplete += 1;
+            self.stats.lost_fragments +=
+                (partial.parts.len() - usize::from(partial.received)) as u64;
+            self.remember(frame_id);
+        }
+    }
+
+    fn remember(&mut self, frame_id: u64) {
+        if self.recent.len() == RECENT_FRAMES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(frame_id);
+    }
+}
@@ -0,0 +1,47 @@
+// gRPC backbone between the forwarder and a receiver (--transport grpc)
+//
+// Some networks only allow gRPC between VPCs. Events go out as messages of
+// one long-lived bidirectional stream per connection, generated from
+// proto/backbone.proto. The message body is the same JSON the other
+// transports send, so what a grpc run adds over tcp is the wrapping around
+// it: the protobuf fields, gRPC's length prefix and the HTTP/2 DATA frame.
+
+use prost::Message;
+
+tonic::include_proto!("backbone");
+
+pub use backbone_client::BackboneClient;
+pub use backbone_server::{Backbone, BackboneServer};
+
+/// gRPC message prefix: compressed flag (1) and message length (4)
+pub const GRPC_PREFIX_LEN: usize = 5;
+
+/// Header in front of every HTTP/2 frame
+pub const HTTP2_FRAME_HEADER_LEN: usize = 9;
+
+/// Default HTTP/2 SETTINGS_MAX_FRAME_SIZE; larger messages span several DATA frames
+pub const HTTP2_MAX_FRAME_SIZE: usize = 16_384;
+
+impl ForwardedEvent {
+    /// Wrap one serialized event or handshake
+    pub fn wrap(sequence_id: u64, json: &str) -> Self {
+        Self {
+            sequence_id,
+            json: json.as_bytes().to_vec(),
+        }
+    }
+
+    /// Bytes this message occupies on the HTTP/2 connection: the encoded
+    /// message, its gRPC prefix and the header of every DATA frame it fills.
+    /// HPACK headers, sent once per stream, and TCP/TLS framing are not counted.
+    pub fn wire_bytes(&self) -> usize {
+        let prefixed = self.encoded_len() + GRPC_PREFIX_LEN;
+        let frames = prefixed.div_ceil(HTTP2_MAX_FRAME_SIZE);
+        prefixed + frames * HTTP2_FRAME_HEADER_LEN
+    }
+
+    /// Bytes around the JSON payload on the wire, for comparing with tcp
+    pub fn framing_bytes(&self) -> usize {
+        self.wire_bytes() - self.json.len()
+    }
+}
@@ -0,0 +1,59 @@
+// Forwarder registration: the first message on every receiver connection
+//
+// Before streaming, a forwarder announces who it is and which wire format its
+// events use. The receiver records every forwarder in the run metadata and
+// refuses events from one it cannot read, instead of silently misparsing
+// them. Forwarders from before the handshake send no hello; their events are
+// accepted as before.
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the `ForwardedEvent` wire format. Bumped when a change would be
+/// misread by an older receiver; added optional fields do not count.
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+/// What a forwarder announces about itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwarderHello {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarder_id: Option<String>, // --forwarder-id
+    pub region: String,       // --region-name of the forwarder
+    pub streams: Vec<String>, // Exchange streams it forwards
+    pub wire_format: u32,
+    pub version: String, // Crate version of the forwarder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+}
+
+/// On the wire a hello is wrapped as `{"hello":{...}}`, which no event starts with
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    hello: T,
+}
+
+const PREFIX: &[u8] = b"{\"hello\":";
+
+impl ForwarderHello {
+    /// One line of JSON, sent like an event
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Envelope { hello: self }).expect("hello serializes")
+    }
+
+    /// Parse `frame` if it is a hello; `None` for anything else, e.g. an event
+    pub fn parse(frame: &[u8]) -> Option<Result<Self, serde_json::Error>> {
+        frame
+            .starts_with(PREFIX)
+            .then(|| serde_json::from_slice::<Envelope<Self>>(frame).map(|envelope| envelope.hello))
+    }
+
+    /// Whether a receiver of this build can read the forwarder's events
+    pub fn check_compatible(&self) -> Result<(), String> {
+        if self.wire_format != WIRE_FORMAT_VERSION {
+            return Err(format!(
+                "forwarder wire format {} differs from the receiver's {}",
+                self.wire_format, WIRE_FORMAT_VERSION
+            ));
+        }
+        Ok(())
+    }
+}
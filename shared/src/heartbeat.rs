@@ -0,0 +1,53 @@
+// Dead-peer detection on the forwarder's TCP link
+//
+// A connection that silently dies, e.g. when a NAT entry times out, keeps
+// accepting writes until the socket buffer fills. With --heartbeat-ms the
+// forwarder sends numbered heartbeats between its events and the receiver
+// answers each on the same connection, so a peer that stopped answering is
+// noticed within one timeout instead of once the kernel gives up. Heartbeats
+// also carry the forwarder's restart history for the receiver's results.
+
+use latency_core::ForwarderSupervision;
+use serde::{Deserialize, Serialize};
+
+/// Heartbeat line, `{"heartbeat":N,...}`; no event or hello starts like this
+const HEARTBEAT: &[u8] = b"{\"heartbeat\":";
+
+/// Acknowledgement line, `{"heartbeat_ack":N}`
+const ACK: &[u8] = b"{\"heartbeat_ack\":";
+
+/// One heartbeat as sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    #[serde(rename = "heartbeat")]
+    pub id: u64, // Serialized first, so the line starts with HEARTBEAT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supervision: Option<ForwarderSupervision>,
+}
+
+/// Heartbeat `id`, sent like an event
+pub fn heartbeat_line(id: u64, supervision: Option<ForwarderSupervision>) -> String {
+    serde_json::to_string(&Heartbeat { id, supervision }).expect("heartbeat serializes")
+}
+
+/// The receiver's answer to heartbeat `id`, newline included
+pub fn heartbeat_ack_line(id: u64) -> String {
+    format!("{{\"heartbeat_ack\":{}}}\n", id)
+}
+
+/// A heartbeat line; `None` for anything else, e.g. an event
+pub fn parse_heartbeat(line: &[u8]) -> Option<Heartbeat> {
+    if !line.starts_with(HEARTBEAT) {
+        return None;
+    }
+    serde_json::from_slice(line).ok()
+}
+
+/// The id an acknowledgement line answers
+pub fn parse_heartbeat_ack(line: &[u8]) -> Option<u64> {
+    let id = line
+        .trim_ascii_end()
+        .strip_prefix(ACK)?
+        .strip_suffix(b"}")?;
+    std::str::from_utf8(id).ok()?.parse().ok()
+}
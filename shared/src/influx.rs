@@ -0,0 +1,178 @@
+// Stream raw measurements to InfluxDB (v2 write API)
+//
+// Points are queued without blocking the measuring loop and written in batches
+// by a background task. Amazon Timestream for InfluxDB exposes the same API, so
+// its endpoint works as the URL too.
+
+use crate::error::{ExperimentError, Result};
+use crate::sink::OutputSink;
+use futures_util::future::BoxFuture;
+use latency_core::{LatencyMeasurement, LineProtocol, Report};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+const QUEUE_POINTS: usize = 100_000;
+const MAX_BATCH_POINTS: u64 = 5_000;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and how to write points
+#[derive(Debug, Clone)]
+pub struct InfluxConfig {
+    pub url: String, // Server base URL, e.g. http://localhost:8086
+    pub token: Option<String>,
+    pub org: Option<String>,
+    pub bucket: String,
+}
+
+/// Points the writer task delivered or lost to write errors
+#[derive(Debug, Default)]
+struct Delivery {
+    written: u64,
+    failed: u64,
+}
+
+pub struct InfluxSink {
+    format: LineProtocol,
+    tx: mpsc::Sender<String>,
+    dropped: u64,
+    writer: JoinHandle<Delivery>,
+}
+
+impl InfluxSink {
+    pub fn start(config: InfluxConfig, tags: &[(&str, &str)]) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(ExperimentError::connect)?;
+        let mut url = reqwest::Url::parse(&format!(
+            "{}/api/v2/write",
+            config.url.trim_end_matches('/')
+        ))
+        .map_err(ExperimentError::parse)?;
+        url.query_pairs_mut()
+            .append_pair("bucket", &config.bucket)
+            .append_pair("precision", "ns");
+        if let Some(org) = &config.org {
+            url.query_pairs_mut().append_pair("org", org);
+        }
+
+        let (tx, rx) = mpsc::channel(QUEUE_POINTS);
+        let writer = tokio::spawn(write_batches(client, url, config.token, rx));
+        info!(url = %config.url, bucket = %config.bucket, "streaming measurements to InfluxDB");
+
+        Ok(Self {
+            format: LineProtocol::new(tags),
+            tx,
+            dropped: 0,
+            writer,
+        })
+    }
+}
+
+impl OutputSink for InfluxSink {
+    /// Queue one measurement; never waits for the server
+    fn write(&mut self, measurement: &LatencyMeasurement) {
+        match self.tx.try_send(self.format.point(measurement)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    warn!("InfluxDB writer is falling behind, dropping points");
+                }
+                self.dropped += 1;
+            }
+            Err(TrySendError::Closed(_)) => self.dropped += 1,
+        }
+    }
+
+    /// Write the remaining points and wait for the writer to finish
+    fn finish<'a>(self: Box<Self>, _report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let InfluxSink {
+                tx,
+                dropped,
+                writer,
+                ..
+            } = *self;
+            drop(tx);
+            let delivery = writer.await.unwrap_or_default();
+            info!(
+                written = delivery.written,
+                failed = delivery.failed,
+                dropped,
+                "InfluxDB sink finished"
+            );
+            Ok(())
+        })
+    }
+}
+
+async fn write_batches(
+    client: reqwest::Client,
+    url: reqwest::Url,
+    token: Option<String>,
+    mut rx: mpsc::Receiver<String>,
+) -> Delivery {
+    let mut delivery = Delivery::default();
+    let mut batch = String::new();
+    let mut points = 0u64;
+    let mut ticker = interval(FLUSH_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let closed = tokio::select! {
+            line = rx.recv() => match line {
+                Some(line) => {
+                    batch.push_str(&line);
+                    batch.push('\n');
+                    points += 1;
+                    if points < MAX_BATCH_POINTS {
+                        continue;
+                    }
+                    false
+                }
+                None => true,
+            },
+            _ = ticker.tick() => false,
+        };
+
+        if points > 0 {
+            match post(&client, &url, token.as_deref(), std::mem::take(&mut batch)).await {
+                Ok(()) => delivery.written += points,
+                Err(e) => {
+                    warn!(points, error = %e, "InfluxDB write failed");
+                    delivery.failed += points;
+                }
+            }
+            points = 0;
+        }
+        if closed {
+            return delivery;
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    url: &reqwest::Url,
+    token: Option<&str>,
+    body: String,
+) -> Result<(), String> {
+    let mut request = client
+        .post(url.clone())
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(body);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Token {}", token));
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let message = response.text().await.unwrap_or_default();
+    Err(format!("{}: {}", status, message.trim()))
+}
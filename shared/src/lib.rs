@@ -1,44 +1,90 @@
 // Shared data structures for the Binance latency experiment
 
 use serde::{Deserialize, Serialize};
-
-/// Binance aggregate trade event structure
-/// Matches the JSON format from Binance WebSocket aggTrade stream
-#[derive(Debug, Clone, Deserialize)]
-pub struct BinanceAggTradeEvent {
-    #[serde(rename = "e")]
-    pub event_type: String, // Event type ("aggTrade")
-
-    #[serde(rename = "E")]
-    pub event_time: i64, // Event time (milliseconds)
-
-    #[serde(rename = "s")]
-    pub symbol: String, // Symbol (BTCUSDT)
-
-    #[serde(rename = "a")]
-    pub agg_trade_id: i64, // Aggregate trade ID
-
-    #[serde(rename = "p")]
-    pub price: String, // Price
-
-    #[serde(rename = "q")]
-    pub quantity: String, // Quantity
-
-    #[serde(rename = "f")]
-    pub first_trade_id: i64, // First trade ID
-
-    #[serde(rename = "l")]
-    pub last_trade_id: i64, // Last trade ID
-
-    #[serde(rename = "T")]
-    pub trade_time: i64, // Trade time (milliseconds)
-
-    #[serde(rename = "m")]
-    pub is_buyer_maker: bool, // Is the buyer the market maker?
-}
-
-// Keep the old name as an alias for compatibility
-pub type BinanceBookTickerEvent = BinanceAggTradeEvent;
+use std::borrow::Cow;
+use std::io::Write;
+
+mod ack;
+mod binance;
+mod capture;
+mod clock_sync;
+mod error;
+mod exchange;
+mod exchange_address;
+mod fast_parse;
+mod fragment;
+pub mod grpc;
+mod handshake;
+mod heartbeat;
+mod influx;
+mod logging;
+mod parquet_sink;
+mod pool;
+mod quote;
+mod reconnect;
+mod rollover;
+mod rotate;
+mod run_id;
+mod s3;
+mod shutdown;
+mod sink;
+mod subscription;
+mod template;
+mod tls;
+mod verify;
+mod ws;
+
+pub use ack::{AckStats, AckTracker, EventAck};
+pub use clock_sync::{chrony_tracking, ptp_data_set, read_clock, ClockMonitor};
+pub use error::{BoxError, ErrorKind, ExperimentError, Result};
+pub use exchange_address::{tcp_peer, ExchangeAddresses};
+pub use handshake::{ForwarderHello, WIRE_FORMAT_VERSION};
+pub use heartbeat::{
+    heartbeat_ack_line, heartbeat_line, parse_heartbeat, parse_heartbeat_ack, Heartbeat,
+};
+pub use influx::{InfluxConfig, InfluxSink};
+pub use latency_core::{
+    event_time_nanos, event_time_unit_nanos, ArrivalLog, ExperimentResults, LatencyMeasurement,
+    LatencySummary, PayloadCheck, PayloadCheckStats, PingRttStats, PingTracker, StatsAggregator,
+    StreamingPercentiles, StreamingStats, StreamingSummary, UpdateArrival,
+};
+pub use latency_core::{
+    ClockEstimate, ClockSample, ClockSource, ConnectionHandover, ExchangeAddress,
+    ForwarderSupervision, HandoverReason, SubscriptionChange, SubscriptionMethod,
+};
+pub use logging::{init_logging, init_logging_to};
+pub use parquet_sink::{read_parquet, ParquetSink};
+pub use pool::{BufferPool, PooledBuffer};
+pub use quote::{BookTickerQuote, PayloadQuotes, TradePrint};
+pub use reconnect::{Backoff, ReconnectPolicy, ReconnectStats};
+pub use rollover::{
+    default_rollover, parse_rollover, CurrentFrame, Rollover, BINANCE_CONNECTION_LIMIT,
+    DEFAULT_ROLLOVER_LEAD,
+};
+pub use rotate::{parse_interval, parse_size, RotatingFile, RotationPolicy};
+pub use run_id::{new_run_id, validate_run_id, MAX_RUN_ID_LEN};
+pub use s3::{check_aws_cli, files_in, output_files, S3Destination};
+pub use shutdown::Shutdown;
+pub use sink::{
+    symbol_path, CsvSink, JsonSink, OutputSink, SinkSpec, Sinks, StdoutSink, SymbolCsvSink,
+};
+pub use subscription::{split_stream_url, Answer, Subscriptions};
+pub use template::expand_template;
+pub use tls::{tls_acceptor, TlsClient};
+pub use verify::PayloadVerifier;
+pub use ws::{connect_exchange, ExchangeSocket, ExchangeStream, InflateStream, WsCompression};
+
+pub use binance::{
+    split_combined_stream, BinanceAggTradeEvent, BinanceBookTickerEvent, BinanceBookTickerView,
+    BinanceMarketEvent, BinanceMarketEventView, BinanceStreamKind, BinanceTradeEvent,
+    BinanceTradeView,
+};
+pub use capture::{read_capture, CaptureWriter, CapturedFrame};
+pub use exchange::{
+    exchange_adapter, Binance, Bybit, ExchangeAdapter, Okx, TickerEvent, EXCHANGES,
+};
+pub use fast_parse::{extract_binance_fields, BinanceFastParse, BinanceFields};
+pub use fragment::{fragment, Datagram, Reassembler, FRAGMENT_HEADER_LEN};
 
 /// Event forwarded from Tokyo to Frankfurt
 /// Contains original Binance data plus Tokyo timestamps
@@ -46,222 +92,201 @@ pub type BinanceBookTickerEvent = BinanceAggTradeEvent;
 pub struct ForwardedEvent {
     pub sequence_id: u64,             // Incrementing event ID for tracking
     pub tokyo_receive_timestamp: i64, // When Tokyo received from Binance (epoch nanos)
-    pub binance_event_time: i64,      // Original Binance event time (E field)
-    pub event_data: String,           // Raw JSON from Binance
-}
-
-/// Latency measurement for a single event
-#[derive(Debug, Clone)]
-pub struct LatencyMeasurement {
-    pub sequence_id: u64,
-    pub binance_event_time: i64,          // Binance timestamp (ms)
-    pub tokyo_receive_time: Option<i64>,  // Only for AWS backbone mode (epoch nanos)
-    pub frankfurt_receive_time: i64,      // Frankfurt arrival (epoch nanos)
-    pub end_to_end_latency_ms: f64,       // Binance to Frankfurt
-    pub backbone_latency_ms: Option<f64>, // Tokyo to Frankfurt (AWS backbone only)
+    pub binance_event_time: i64,      // Exchange event time (milliseconds, Binance E field)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binance_transaction_time: Option<i64>, // Trade/match time (milliseconds, Binance T field)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<Cow<'static, str>>, // Forwarder transport setting (udp, tcp, dual, wss)
+    pub event_data: String,           // Raw JSON from the exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stages: Option<ForwarderStages>, // Forwarder instrumentation (--stage-timestamps)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub buffered: bool, // Held back while the receiver connection was down, sent after reconnecting
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retransmitted: bool, // Write failed on a broken connection, sent again after reconnecting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<Cow<'static, str>>, // Forwarder run (--run-id), so events of stale processes can be told apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dscp: Option<u8>, // DSCP marking the forwarder set on its sockets (--dscp)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ws_compressed: bool, // The forwarder's exchange connection negotiated permessage-deflate
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replayed: bool, // Forwarded from a capture (--replay); event times are shifted, the payload is not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarding_overhead_ns: Option<i64>, // Frame received → handed to the socket; appended after serializing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<Cow<'static, str>>, // Binance combined-stream name the event arrived on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<Cow<'static, str>>, // Venue symbol the event names (BTCUSDT), for --split-by-symbol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<ClockEstimate>, // The forwarder's clock error when it received the event (--clock-sync)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_bytes: Option<u32>, // Length of the exchange frame `event_data` was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_queue_delay_ns: Option<i64>, // Time spent in the forwarder's --send-queue; appended when dequeued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarder_id: Option<Cow<'static, str>>, // Names the forwarder (--forwarder-id) when several send to one receiver
 }
 
-impl LatencyMeasurement {
-    /// Create a new latency measurement for baseline mode (direct Binance → Frankfurt)
-    pub fn new_baseline(
+impl ForwardedEvent {
+    /// An event with only the fields every event has: optional fields are
+    /// unset and flags cleared. Set the rest with struct update syntax.
+    pub fn new(
         sequence_id: u64,
+        tokyo_receive_timestamp: i64,
         binance_event_time: i64,
-        frankfurt_receive_time: i64,
+        event_data: String,
     ) -> Self {
-        let end_to_end_latency_ms =
-            (frankfurt_receive_time as f64 / 1_000_000.0) - binance_event_time as f64;
-
         Self {
             sequence_id,
+            tokyo_receive_timestamp,
             binance_event_time,
-            tokyo_receive_time: None,
-            frankfurt_receive_time,
-            end_to_end_latency_ms,
-            backbone_latency_ms: None,
+            binance_transaction_time: None,
+            transport: None,
+            event_data,
+            stages: None,
+            buffered: false,
+            retransmitted: false,
+            run_id: None,
+            dscp: None,
+            ws_compressed: false,
+            replayed: false,
+            forwarding_overhead_ns: None,
+            stream: None,
+            symbol: None,
+            clock: None,
+            frame_bytes: None,
+            send_queue_delay_ns: None,
+            forwarder_id: None,
         }
     }
 
-    /// Create a new latency measurement for AWS backbone mode (Binance → Tokyo → Frankfurt)
-    pub fn new_aws_backbone(
-        sequence_id: u64,
-        binance_event_time: i64,
-        tokyo_receive_time: i64,
-        frankfurt_receive_time: i64,
-    ) -> Self {
-        let end_to_end_latency_ms =
-            (frankfurt_receive_time as f64 / 1_000_000.0) - binance_event_time as f64;
-        let backbone_latency_ms =
-            (frankfurt_receive_time - tokyo_receive_time) as f64 / 1_000_000.0;
-
-        Self {
-            sequence_id,
-            binance_event_time,
-            tokyo_receive_time: Some(tokyo_receive_time),
-            frankfurt_receive_time,
-            end_to_end_latency_ms,
-            backbone_latency_ms: Some(backbone_latency_ms),
-        }
+    /// Flag an event already serialized with `serde_json` as buffered, without
+    /// parsing it again. Anything that is not a JSON object is returned unchanged.
+    pub fn mark_buffered(json: &str) -> String {
+        set_flag(json, "buffered")
     }
 
-    /// Write measurements to CSV file
-    pub fn write_to_csv(
-        measurements: &[LatencyMeasurement],
-        filepath: &str,
-    ) -> Result<(), std::io::Error> {
-        use std::io::Write;
-
-        let mut file = std::fs::File::create(filepath)?;
-
-        // Write CSV header
-        writeln!(
-            file,
-            "sequence_id,binance_time,tokyo_time,frankfurt_time,latency_ms,backbone_latency_ms"
-        )?;
+    /// Flag an event already serialized with `serde_json` as retransmitted,
+    /// like `mark_buffered`
+    pub fn mark_retransmitted(json: &str) -> String {
+        set_flag(json, "retransmitted")
+    }
 
-        // Write each measurement
-        for m in measurements {
-            writeln!(
-                file,
-                "{},{},{},{},{:.3},{}",
-                m.sequence_id,
-                m.binance_event_time,
-                m.tokyo_receive_time
-                    .map_or(String::new(), |t| t.to_string()),
-                m.frankfurt_receive_time,
-                m.end_to_end_latency_ms,
-                m.backbone_latency_ms
-                    .map_or(String::new(), |l| format!("{:.3}", l))
-            )?;
-        }
+    /// Add `forwarding_overhead_ns` to an event just serialized with
+    /// `serde_json` (which left the field out), right before it is sent.
+    /// Anything that is not a JSON object is left unchanged.
+    pub fn append_overhead(json: &mut Vec<u8>, overhead_ns: i64) {
+        append_field(json, "forwarding_overhead_ns", overhead_ns);
+    }
 
-        Ok(())
+    /// Add `send_queue_delay_ns` to a serialized event as it leaves the send
+    /// queue, like `append_overhead`
+    pub fn append_send_queue_delay(json: &mut Vec<u8>, delay_ns: i64) {
+        append_field(json, "send_queue_delay_ns", delay_ns);
     }
 }
 
-/// Results of a latency experiment
-#[derive(Debug, Clone, Serialize)]
-pub struct ExperimentResults {
-    pub setup_type: String, // "baseline" or "aws-backbone"
-    pub sample_count: usize,
-    pub events_lost: usize, // Missing sequence IDs
-
-    // End-to-end latency (Binance → Frankfurt)
-    pub avg_latency_ms: f64,
-    pub median_latency_ms: f64,
-    pub p95_latency_ms: f64,
-    pub p99_latency_ms: f64,
-    pub min_latency_ms: f64,
-    pub max_latency_ms: f64,
-
-    // Jitter (variance in latency)
-    pub jitter_stddev_ms: f64,
-
-    // AWS backbone specific (Tokyo → Frankfurt)
-    pub backbone_avg_latency_ms: Option<f64>,
-    pub backbone_median_latency_ms: Option<f64>,
+/// Append `"name":value` to a serialized JSON object
+fn append_field(json: &mut Vec<u8>, name: &str, value: i64) {
+    if json.first() == Some(&b'{') && json.last() == Some(&b'}') {
+        json.pop();
+        // Writing to a Vec cannot fail
+        let _ = write!(json, ",\"{}\":{}}}", name, value);
+    }
 }
 
-impl ExperimentResults {
-    /// Calculate statistics from a vector of latency measurements
-    pub fn from_measurements(
-        setup_type: String,
-        measurements: Vec<LatencyMeasurement>,
-        events_lost: usize,
-    ) -> Self {
-        let sample_count = measurements.len();
-
-        if sample_count == 0 {
-            return Self {
-                setup_type,
-                sample_count: 0,
-                events_lost,
-                avg_latency_ms: 0.0,
-                median_latency_ms: 0.0,
-                p95_latency_ms: 0.0,
-                p99_latency_ms: 0.0,
-                min_latency_ms: 0.0,
-                max_latency_ms: 0.0,
-                jitter_stddev_ms: 0.0,
-                backbone_avg_latency_ms: None,
-                backbone_median_latency_ms: None,
-            };
-        }
-
-        // Sort latencies for percentile calculations
-        let mut latencies: Vec<f64> = measurements
-            .iter()
-            .map(|m| m.end_to_end_latency_ms)
-            .collect();
-        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-        // Calculate statistics
-        let avg_latency_ms = latencies.iter().sum::<f64>() / sample_count as f64;
-        let median_latency_ms = Self::percentile(&latencies, 0.50);
-        let p95_latency_ms = Self::percentile(&latencies, 0.95);
-        let p99_latency_ms = Self::percentile(&latencies, 0.99);
-        let min_latency_ms = latencies[0];
-        let max_latency_ms = latencies[sample_count - 1];
-
-        // Calculate jitter (standard deviation)
-        let variance = latencies
-            .iter()
-            .map(|l| {
-                let diff = l - avg_latency_ms;
-                diff * diff
-            })
-            .sum::<f64>()
-            / sample_count as f64;
-        let jitter_stddev_ms = variance.sqrt();
-
-        // Calculate backbone statistics if available
-        let backbone_latencies: Vec<f64> = measurements
-            .iter()
-            .filter_map(|m| m.backbone_latency_ms)
-            .collect();
-
-        let (backbone_avg_latency_ms, backbone_median_latency_ms) =
-            if !backbone_latencies.is_empty() {
-                let mut sorted_backbone = backbone_latencies.clone();
-                sorted_backbone.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// Append `"name":true` to a serialized JSON object
+fn set_flag(json: &str, name: &str) -> String {
+    match json.strip_suffix('}') {
+        Some(fields) if json.starts_with('{') => format!("{},\"{}\":true}}", fields, name),
+        _ => json.to_string(),
+    }
+}
 
-                let avg = sorted_backbone.iter().sum::<f64>() / sorted_backbone.len() as f64;
-                let median = Self::percentile(&sorted_backbone, 0.50);
-                (Some(avg), Some(median))
-            } else {
-                (None, None)
-            };
+/// Forwarder stage timestamps (epoch nanos). The frame-received time is
+/// `tokyo_receive_timestamp`. An event cannot contain the time it finished
+/// serializing or sending, so those are reported for the previous sequence ID.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ForwarderStages {
+    pub parsed: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_serialized: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_sent: Option<i64>,
+}
 
-        Self {
-            setup_type,
-            sample_count,
-            events_lost,
-            avg_latency_ms,
-            median_latency_ms,
-            p95_latency_ms,
-            p99_latency_ms,
-            min_latency_ms,
-            max_latency_ms,
-            jitter_stddev_ms,
-            backbone_avg_latency_ms,
-            backbone_median_latency_ms,
-        }
+impl ForwarderStages {
+    /// After-serialize and after-send times of the previous event, if both are known
+    pub fn previous(&self) -> Option<(i64, i64)> {
+        self.previous_serialized.zip(self.previous_sent)
     }
+}
 
-    /// Calculate percentile from sorted data
-    fn percentile(sorted_data: &[f64], percentile: f64) -> f64 {
-        let len = sorted_data.len();
-        if len == 0 {
-            return 0.0;
-        }
-        if len == 1 {
-            return sorted_data[0];
-        }
+/// Timing fields of a `ForwardedEvent` for the receive hot path. Parsed with
+/// `serde_json::from_slice` straight from the datagram or frame; the raw
+/// exchange payload is skipped rather than unescaped into a new String.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ForwardedEventView<'a> {
+    pub sequence_id: u64,
+    pub tokyo_receive_timestamp: i64,
+    pub binance_event_time: i64,
+    #[serde(default)]
+    pub binance_transaction_time: Option<i64>,
+    #[serde(default)]
+    pub stages: Option<ForwarderStages>,
+    #[serde(default)]
+    pub buffered: bool,
+    #[serde(default)]
+    pub retransmitted: bool,
+    #[serde(default, borrow)]
+    pub run_id: Option<&'a str>, // Run IDs contain no characters JSON escapes
+    #[serde(default)]
+    pub dscp: Option<u8>,
+    #[serde(default)]
+    pub ws_compressed: bool,
+    #[serde(default)]
+    pub replayed: bool,
+    #[serde(default)]
+    pub forwarding_overhead_ns: Option<i64>,
+    #[serde(default, borrow)]
+    pub stream: Option<&'a str>, // Binance stream names contain no characters JSON escapes
+    #[serde(default, borrow)]
+    pub symbol: Option<&'a str>, // Nor do exchange symbols
+    #[serde(default)]
+    pub clock: Option<ClockEstimate>,
+    #[serde(default)]
+    pub frame_bytes: Option<u32>,
+    #[serde(default)]
+    pub send_queue_delay_ns: Option<i64>,
+    #[serde(default, borrow)]
+    pub forwarder_id: Option<&'a str>, // Forwarder IDs contain no characters JSON escapes
+}
 
-        let index = percentile * (len - 1) as f64;
-        let lower = index.floor() as usize;
-        let upper = index.ceil() as usize;
-        let weight = index - lower as f64;
+impl<'a> ForwardedEventView<'a> {
+    pub fn parse(frame: &'a [u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(frame)
+    }
 
-        sorted_data[lower] * (1.0 - weight) + sorted_data[upper] * weight
+    /// Which of `shards` a serialized event belongs to, by a hash of its
+    /// stream name, found without parsing the event. The quotes inside
+    /// `event_data` are escaped, so only the envelope's field can match.
+    /// Events without a stream name, and anything that is not an event,
+    /// belong to the first shard.
+    pub fn stream_shard(frame: &[u8], shards: usize) -> usize {
+        const FIELD: &[u8] = b"\"stream\":\"";
+        let Some(start) = frame
+            .windows(FIELD.len())
+            .position(|window| window == FIELD)
+        else {
+            return 0;
+        };
+        let name = &frame[start + FIELD.len()..];
+        let name = &name[..name.iter().position(|&b| b == b'"').unwrap_or(name.len())];
+        // FNV-1a, so every process agrees on a stream's shard
+        let hash = name.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        (hash % shards.max(1) as u64) as usize
     }
 }
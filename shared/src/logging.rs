@@ -0,0 +1,49 @@
+// Log output shared by both binaries (tracing)
+//
+// Logs go to stderr so stdout keeps the per-second tables and summaries.
+
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Install the global subscriber. `filter` is a level (`info`, `debug`) or a
+/// full directive such as `info,frankfurt_receiver=debug`; `RUST_LOG`
+/// overrides it when set. With `json`, every event is one JSON object per line.
+/// When the binaries run in one process, as in the integration tests, the
+/// first subscriber installed stays.
+pub fn init_logging(filter: &str, json: bool) -> Result<(), String> {
+    install(filter, json, std::io::stderr, true)
+}
+
+/// Like `init_logging`, but send log lines to `writer` without terminal
+/// colours, e.g. into a panel of a full-screen display that owns the terminal
+pub fn init_logging_to<W>(filter: &str, json: bool, writer: W) -> Result<(), String>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    install(filter, json, writer, false)
+}
+
+fn install<W>(filter: &str, json: bool, writer: W, ansi: bool) -> Result<(), String>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    if tracing::dispatcher::has_been_set() {
+        return Ok(());
+    }
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(env) if !env.is_empty() => EnvFilter::try_new(env),
+        _ => EnvFilter::try_new(filter),
+    }
+    .map_err(|e| format!("invalid log filter: {}", e))?;
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+    let result = if json {
+        builder.json().with_current_span(true).try_init()
+    } else {
+        builder.try_init()
+    };
+    result.map_err(|e| e.to_string())
+}
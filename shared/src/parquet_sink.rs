@@ -0,0 +1,270 @@
+// Raw measurements as a Parquet file (--sink parquet:PATH)
+//
+// Same columns as the raw CSV, typed, so analysis tools load them without
+// parsing text. Rows are buffered and written one row group at a time, which
+// keeps memory flat over long runs; the file is only readable once closed.
+// `read_parquet` loads such a file back for re-analysis.
+
+use crate::error::{ExperimentError, Result};
+use crate::sink::OutputSink;
+use futures_util::future::BoxFuture;
+use latency_core::{DeliveryClass, LatencyMeasurement, Market, Report, StreamNames};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, DoubleType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::record::Field;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Rows per row group
+const ROW_GROUP_ROWS: usize = 65_536;
+
+/// Column names and types match `CSV_HEADER`
+const SCHEMA: &str = "
+message measurement {
+    required int64 sequence_id (INTEGER(64, false));
+    required int64 binance_time;
+    optional int64 tokyo_time;
+    required int64 frankfurt_time;
+    required double latency_ms;
+    optional double backbone_latency_ms;
+    optional int64 kernel_time;
+    required boolean warmup;
+    optional int64 transaction_time;
+    optional int64 endpoint;
+    optional binary market (STRING);
+    required binary delivery_class (STRING);
+    optional binary stream (STRING);
+    optional int64 frame_bytes;
+    optional int64 packet_bytes;
+    optional double send_queue_delay_ms;
+    optional binary source (STRING);
+    optional double binance_tokyo_ms;
+    optional int64 tokyo_processing_ns;
+}
+";
+
+pub struct ParquetSink {
+    path: String,
+    writer: SerializedFileWriter<File>,
+    rows: Vec<LatencyMeasurement>,
+    written: usize,
+    error: Option<ParquetError>,
+}
+
+impl ParquetSink {
+    pub fn create(path: &str) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(SCHEMA).map_err(ExperimentError::parse)?);
+        let properties = Arc::new(WriterProperties::builder().build());
+        let writer = SerializedFileWriter::new(File::create(path)?, schema, properties)
+            .map_err(parquet_error)?;
+        Ok(Self {
+            path: path.to_string(),
+            writer,
+            rows: Vec::with_capacity(ROW_GROUP_ROWS),
+            written: 0,
+            error: None,
+        })
+    }
+
+    /// Write the buffered rows as one row group
+    fn write_row_group(&mut self) -> Result<(), ParquetError> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        // Taken for the duration of the write, then handed back empty to reuse its allocation
+        let mut rows = std::mem::take(&mut self.rows);
+        let mut row_group = self.writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            write_column(&mut column, index, &rows)?;
+            column.close()?;
+            index += 1;
+        }
+        row_group.close()?;
+        self.written += rows.len();
+        rows.clear();
+        self.rows = rows;
+        Ok(())
+    }
+}
+
+impl OutputSink for ParquetSink {
+    fn write(&mut self, measurement: &LatencyMeasurement) {
+        if self.error.is_some() {
+            return;
+        }
+        self.rows.push(measurement.clone());
+        if self.rows.len() >= ROW_GROUP_ROWS {
+            if let Err(e) = self.write_row_group() {
+                warn!(path = %self.path, error = %e, "failed to write Parquet row group");
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish<'a>(mut self: Box<Self>, _report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Some(e) = self.error.take() {
+                return Err(parquet_error(e));
+            }
+            self.write_row_group().map_err(parquet_error)?;
+            let ParquetSink {
+                path,
+                writer,
+                written,
+                ..
+            } = *self;
+            writer.close().map_err(parquet_error)?;
+            info!(path = %path, rows = written, "raw measurements written as Parquet");
+            Ok(())
+        })
+    }
+}
+
+/// Write column `index` of the schema for `rows`
+fn write_column(
+    column: &mut SerializedColumnWriter<'_>,
+    index: usize,
+    rows: &[LatencyMeasurement],
+) -> Result<(), ParquetError> {
+    let int = |value: fn(&LatencyMeasurement) -> i64| rows.iter().map(value).collect::<Vec<_>>();
+    let text = |value: &str| ByteArray::from(value);
+    match index {
+        0 => required::<Int64Type>(column, int(|m| m.sequence_id as i64)),
+        1 => required::<Int64Type>(column, int(|m| m.binance_event_time)),
+        2 => optional::<Int64Type>(column, rows.iter().map(|m| m.tokyo_receive_time)),
+        3 => required::<Int64Type>(column, int(|m| m.frankfurt_receive_time)),
+        4 => required::<DoubleType>(
+            column,
+            rows.iter().map(|m| m.end_to_end_latency_ms()).collect(),
+        ),
+        5 => optional::<DoubleType>(column, rows.iter().map(|m| m.backbone_latency_ms())),
+        6 => optional::<Int64Type>(column, rows.iter().map(|m| m.kernel_receive_time)),
+        7 => required::<BoolType>(column, rows.iter().map(|m| m.warmup).collect()),
+        8 => optional::<Int64Type>(column, rows.iter().map(|m| m.transaction_time)),
+        9 => optional::<Int64Type>(column, rows.iter().map(|m| m.endpoint.map(i64::from))),
+        10 => optional::<ByteArrayType>(
+            column,
+            rows.iter()
+                .map(|m| m.market.map(|market| text(market.as_str()))),
+        ),
+        11 => required::<ByteArrayType>(
+            column,
+            rows.iter()
+                .map(|m| text(&m.delivery_class.to_string()))
+                .collect(),
+        ),
+        12 => optional::<ByteArrayType>(column, rows.iter().map(|m| m.stream.as_deref().map(text))),
+        13 => optional::<Int64Type>(column, rows.iter().map(|m| m.frame_bytes.map(i64::from))),
+        14 => optional::<Int64Type>(column, rows.iter().map(|m| m.packet_bytes.map(i64::from))),
+        15 => optional::<DoubleType>(column, rows.iter().map(|m| m.send_queue_delay_ms())),
+        16 => optional::<ByteArrayType>(column, rows.iter().map(|m| m.source.as_deref().map(text))),
+        17 => optional::<DoubleType>(column, rows.iter().map(|m| m.tokyo_latency_ms())),
+        18 => optional::<Int64Type>(column, rows.iter().map(|m| m.forwarding_overhead_ns)),
+        _ => Err(ParquetError::General(format!(
+            "unexpected column {}",
+            index
+        ))),
+    }
+}
+
+fn required<T: DataType>(
+    column: &mut SerializedColumnWriter<'_>,
+    values: Vec<T::T>,
+) -> Result<(), ParquetError> {
+    column.typed::<T>().write_batch(&values, None, None)?;
+    Ok(())
+}
+
+/// Nulls are left out of the values and marked by a definition level of 0
+fn optional<T: DataType>(
+    column: &mut SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = Option<T::T>>,
+) -> Result<(), ParquetError> {
+    let mut present = Vec::new();
+    let mut levels = Vec::new();
+    for value in values {
+        levels.push(value.is_some() as i16);
+        present.extend(value);
+    }
+    column
+        .typed::<T>()
+        .write_batch(&present, Some(&levels), None)?;
+    Ok(())
+}
+
+/// Read measurements written by `ParquetSink`. Columns are found by name, so
+/// files from before a column was added still load.
+pub fn read_parquet(path: &str) -> Result<Vec<LatencyMeasurement>> {
+    let reader = SerializedFileReader::new(File::open(path)?).map_err(parquet_error)?;
+    let mut streams = StreamNames::default();
+    let mut sources = StreamNames::default();
+    let mut measurements = Vec::new();
+    for row in reader.get_row_iter(None).map_err(parquet_error)? {
+        let row = row.map_err(parquet_error)?;
+        let mut m = LatencyMeasurement::new_baseline(0, 0, 0);
+        let mut end_to_end_ms = None;
+        for (name, field) in row.get_column_iter() {
+            match (name.as_str(), field) {
+                ("sequence_id", Field::ULong(id)) => m.sequence_id = *id,
+                ("sequence_id", Field::Long(id)) => m.sequence_id = *id as u64,
+                ("binance_time", Field::Long(time)) => m.binance_event_time = *time,
+                ("tokyo_time", Field::Long(time)) => m.tokyo_receive_time = Some(*time),
+                ("frankfurt_time", Field::Long(time)) => m.frankfurt_receive_time = *time,
+                ("latency_ms", Field::Double(ms)) => end_to_end_ms = Some(*ms),
+                ("backbone_latency_ms", Field::Double(ms)) => {
+                    m.backbone_latency_ns = Some((ms * 1_000_000.0).round() as i64)
+                }
+                ("kernel_time", Field::Long(time)) => m.kernel_receive_time = Some(*time),
+                ("warmup", Field::Bool(warmup)) => m.warmup = *warmup,
+                ("transaction_time", Field::Long(time)) => m.transaction_time = Some(*time),
+                ("endpoint", Field::Long(endpoint)) => m.endpoint = Some(*endpoint as u16),
+                ("market", Field::Str(market)) => {
+                    m.market = match market.as_str() {
+                        "spot" => Some(Market::Spot),
+                        "futures" => Some(Market::Futures),
+                        _ => None,
+                    }
+                }
+                ("delivery_class", Field::Str(class)) => {
+                    m.delivery_class = match class.as_str() {
+                        "buffered" => DeliveryClass::Buffered,
+                        "retransmitted" => DeliveryClass::Retransmitted,
+                        _ => DeliveryClass::Live,
+                    }
+                }
+                ("stream", Field::Str(stream)) => m.stream = Some(streams.intern(stream)),
+                ("frame_bytes", Field::Long(bytes)) => m.frame_bytes = Some(*bytes as u32),
+                ("packet_bytes", Field::Long(bytes)) => m.packet_bytes = Some(*bytes as u32),
+                ("source", Field::Str(source)) => m.source = Some(sources.intern(source)),
+                ("send_queue_delay_ms", Field::Double(ms)) => {
+                    m.send_queue_delay_ns = Some((ms * 1_000_000.0).round() as i64)
+                }
+                ("tokyo_processing_ns", Field::Long(ns)) => m.forwarding_overhead_ns = Some(*ns),
+                _ => {}
+            }
+        }
+        let Some(end_to_end_ms) = end_to_end_ms else {
+            return Err(ExperimentError::parse(format!(
+                "{}: not a raw measurements Parquet file",
+                path
+            )));
+        };
+        m.end_to_end_latency_ns = (end_to_end_ms * 1_000_000.0).round() as i64;
+        // Exact where both timestamps are there, like the CSV reader
+        if let Some(tokyo) = m.tokyo_receive_time {
+            m.backbone_latency_ns = Some(m.frankfurt_receive_time - tokyo);
+        }
+        measurements.push(m);
+    }
+    Ok(measurements)
+}
+
+fn parquet_error(error: ParquetError) -> ExperimentError {
+    ExperimentError::Io(std::io::Error::other(error))
+}
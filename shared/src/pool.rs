@@ -0,0 +1,107 @@
+// Reusable byte buffers for the hot paths
+//
+// Every received datagram or line would otherwise get a fresh Vec that is
+// freed again once the event is processed. Buffers taken from a pool go back
+// to it when dropped, so once the pool has warmed up the receive loops stop
+// allocating.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Buffers that grew beyond this are freed instead of pooled, so one huge
+/// event does not pin its memory for the rest of the run
+const MAX_RETAINED_BYTES: usize = 64 * 1024;
+
+/// A shared stack of free buffers; clones share the same pool
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    free: Arc<Mutex<Vec<Vec<u8>>>>,
+    buffer_capacity: usize, // Capacity of newly allocated buffers
+    max_pooled: usize,      // Free buffers kept; more are freed
+}
+
+impl BufferPool {
+    pub fn new(buffer_capacity: usize, max_pooled: usize) -> Self {
+        Self {
+            free: Arc::new(Mutex::new(Vec::with_capacity(max_pooled))),
+            buffer_capacity,
+            max_pooled,
+        }
+    }
+
+    /// An empty buffer, reused from the pool if one is free
+    pub fn get(&self) -> PooledBuffer {
+        let data = self
+            .free
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.buffer_capacity));
+        PooledBuffer {
+            data,
+            pool: Some(self.clone()),
+        }
+    }
+
+    /// Wrap a buffer allocated elsewhere (e.g. a frame owned by a library) so
+    /// its allocation joins the pool once dropped
+    pub fn adopt(&self, data: Vec<u8>) -> PooledBuffer {
+        PooledBuffer {
+            data,
+            pool: Some(self.clone()),
+        }
+    }
+
+    /// Free buffers currently in the pool
+    pub fn free(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    fn put(&self, mut data: Vec<u8>) {
+        if data.capacity() > MAX_RETAINED_BYTES.max(self.buffer_capacity) {
+            return;
+        }
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.max_pooled {
+            data.clear();
+            free.push(data);
+        }
+    }
+}
+
+/// A buffer that returns to its pool when dropped
+pub struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Option<BufferPool>, // Taken on drop
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.data
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.data
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuffer")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.put(std::mem::take(&mut self.data));
+        }
+    }
+}
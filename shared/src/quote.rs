@@ -0,0 +1,99 @@
+// Typed prices and quantities
+//
+// Exchanges publish prices and quantities as decimal strings, and the event
+// structs keep those strings exactly as received. These types carry the same
+// values parsed to f64 for analysis: a double holds 15 significant digits,
+// more than any spread or mid-price computed from them needs.
+
+use crate::exchange::ExchangeAdapter;
+use crate::verify::Payload;
+use std::num::ParseFloatError;
+
+/// Top of the order book from a book ticker update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookTickerQuote {
+    pub bid_price: f64,
+    pub bid_qty: f64,
+    pub ask_price: f64,
+    pub ask_qty: f64,
+}
+
+impl BookTickerQuote {
+    pub(crate) fn parse(
+        bid_price: &str,
+        bid_qty: &str,
+        ask_price: &str,
+        ask_qty: &str,
+    ) -> Result<Self, ParseFloatError> {
+        Ok(Self {
+            bid_price: bid_price.parse()?,
+            bid_qty: bid_qty.parse()?,
+            ask_price: ask_price.parse()?,
+            ask_qty: ask_qty.parse()?,
+        })
+    }
+
+    /// Halfway between best bid and best ask
+    pub fn mid_price(&self) -> f64 {
+        mid_price(self.bid_price, self.ask_price)
+    }
+
+    /// Best ask minus best bid, in price units
+    pub fn spread(&self) -> f64 {
+        self.ask_price - self.bid_price
+    }
+
+    /// Spread in basis points of the mid-price
+    pub fn spread_bps(&self) -> f64 {
+        spread_bps(self.bid_price, self.ask_price)
+    }
+}
+
+/// Price and size of a trade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradePrint {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+impl TradePrint {
+    pub(crate) fn parse(price: &str, quantity: &str) -> Result<Self, ParseFloatError> {
+        Ok(Self {
+            price: price.parse()?,
+            quantity: quantity.parse()?,
+        })
+    }
+
+    /// Price times quantity, in quote currency
+    pub fn notional(&self) -> f64 {
+        self.price * self.quantity
+    }
+}
+
+/// Reads the best bid and ask out of the exchange payload of forwarded
+/// events, which `ForwardedEventView` skips
+pub struct PayloadQuotes {
+    adapter: Box<dyn ExchangeAdapter>,
+}
+
+impl PayloadQuotes {
+    pub fn new(adapter: Box<dyn ExchangeAdapter>) -> Self {
+        Self { adapter }
+    }
+
+    /// Best bid and ask of the forwarded event in `frame`; `None` for trades
+    /// and payloads that do not parse
+    pub fn read(&self, frame: &[u8]) -> Option<(f64, f64)> {
+        let payload: Payload = serde_json::from_slice(frame).ok()?;
+        let event = self.adapter.parse(&payload.event_data).ok()??;
+        Some((event.best_bid()?, event.best_ask()?))
+    }
+}
+
+pub(crate) fn mid_price(bid: f64, ask: f64) -> f64 {
+    (bid + ask) / 2.0
+}
+
+pub(crate) fn spread_bps(bid: f64, ask: f64) -> f64 {
+    (ask - bid) / mid_price(bid, ask) * 10_000.0
+}
@@ -0,0 +1,159 @@
+// Reconnection policy shared by every connection that retries
+//
+// Delays grow exponentially and are jittered so that many forwarders restarted
+// together do not retry in lockstep. After a run of consecutive failures the
+// circuit breaker opens and retries pause for a cooldown, which keeps a
+// misbehaving client from hammering an exchange that is rejecting it.
+
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How a connection is retried
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: f64, // Fraction of each delay that is randomized (0 = none, 1 = full jitter)
+    pub max_attempts: Option<u32>, // Consecutive failures before giving up
+    pub breaker_after: u32, // Consecutive failures that open the breaker (0 = never)
+    pub breaker_cooldown: Duration, // Pause while the breaker is open
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            max_attempts: None,
+            breaker_after: 10,
+            breaker_cooldown: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Retry state for one connection
+#[derive(Debug)]
+pub struct Backoff {
+    policy: ReconnectPolicy,
+    consecutive_failures: u32,
+    rng: u64,
+    stats: ReconnectStats,
+}
+
+/// Reconnect activity over the life of a connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReconnectStats {
+    pub failures: u64,      // Failed attempts (including the loss of a live connection)
+    pub recoveries: u64,    // Successful connections after at least one failure
+    pub breaker_trips: u64, // Times the circuit breaker opened
+}
+
+impl ReconnectStats {
+    pub fn add(&mut self, other: ReconnectStats) {
+        self.failures += other.failures;
+        self.recoveries += other.recoveries;
+        self.breaker_trips += other.breaker_trips;
+    }
+}
+
+impl fmt::Display for ReconnectStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} failures, circuit breaker opened {} times)",
+            self.recoveries, self.failures, self.breaker_trips
+        )
+    }
+}
+
+// Distinguishes backoffs created in the same instant
+static SEED_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl Backoff {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        let seed = nanos
+            ^ u64::from(std::process::id()).rotate_left(32)
+            ^ SEED_COUNTER
+                .fetch_add(1, Ordering::Relaxed)
+                .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        Self {
+            policy,
+            consecutive_failures: 0,
+            rng: seed | 1,
+            stats: ReconnectStats::default(),
+        }
+    }
+
+    /// Record a failure and return how long to wait before the next attempt,
+    /// or `None` once `max_attempts` consecutive failures have been reached
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        self.consecutive_failures += 1;
+        self.stats.failures += 1;
+        if let Some(max) = self.policy.max_attempts {
+            if self.consecutive_failures > max {
+                return None;
+            }
+        }
+
+        if self.breaker_open() {
+            self.stats.breaker_trips += 1;
+            return Some(self.policy.breaker_cooldown);
+        }
+
+        let exponent = (self.consecutive_failures - 1).min(31);
+        let delay = self
+            .policy
+            .initial_delay
+            .saturating_mul(1 << exponent)
+            .min(self.policy.max_delay);
+        Some(self.jittered(delay))
+    }
+
+    /// Whether the last `next_delay` opened the circuit breaker
+    pub fn breaker_open(&self) -> bool {
+        let breaker = self.policy.breaker_after;
+        breaker > 0
+            && self.consecutive_failures > 0
+            && self.consecutive_failures.is_multiple_of(breaker)
+    }
+
+    /// Record a successful connection
+    pub fn succeeded(&mut self) {
+        if self.consecutive_failures > 0 {
+            self.stats.recoveries += 1;
+        }
+        self.consecutive_failures = 0;
+    }
+
+    /// Failures since the last successful connection
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn stats(&self) -> ReconnectStats {
+        self.stats
+    }
+
+    /// Keep `1 - jitter` of the delay and randomize the rest
+    fn jittered(&mut self, delay: Duration) -> Duration {
+        let jitter = self.policy.jitter.clamp(0.0, 1.0);
+        let fixed = delay.mul_f64(1.0 - jitter);
+        fixed + delay.mul_f64(jitter * self.random_unit())
+    }
+
+    /// Uniform in [0, 1) (xorshift64*)
+    fn random_unit(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let value = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
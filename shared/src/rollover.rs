@@ -0,0 +1,230 @@
+// Replacing the exchange connection before its 24-hour limit (--exchange-rollover)
+//
+// Binance closes every stream connection after 24 hours, which in a long run
+// shows up as a short outage. Instead, shortly before the limit a standby
+// connection is opened next to the current one. Both are read until they are
+// seen to overlap, that is one delivers a frame the other already has, and
+// from then on the standby is used alone. Frames that arrived on both are
+// passed on once, so the handover leaves no gap and no duplicates.
+//
+// Binance documents the limit but no close code for it, so a close from the
+// exchange close to the 24-hour mark is counted as the scheduled reset.
+
+use latency_core::{ConnectionHandover, HandoverReason};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long Binance keeps a stream connection open
+pub const BINANCE_CONNECTION_LIMIT: Duration = Duration::from_secs(24 * 3600);
+
+/// How long before the limit the standby connection takes over by default
+pub const DEFAULT_ROLLOVER_LEAD: Duration = Duration::from_secs(5 * 60);
+
+/// A close by the exchange this close to the limit is the scheduled reset
+const RESET_TOLERANCE: Duration = Duration::from_secs(10 * 60);
+
+/// Wait before opening another standby after one failed
+const STANDBY_RETRY: Duration = Duration::from_secs(30);
+
+/// Frames remembered from either connection while both are open
+const MAX_OVERLAP_FRAMES: usize = 10_000;
+
+/// A standby that never overlaps the current connection takes over after this
+const MAX_OVERLAP: Duration = Duration::from_secs(10);
+
+/// Default connection age for --exchange-rollover: shortly before the
+/// exchange's own limit, or never for exchanges without one
+pub fn default_rollover(exchange: &str) -> Option<Duration> {
+    exchange
+        .eq_ignore_ascii_case("binance")
+        .then(|| BINANCE_CONNECTION_LIMIT - DEFAULT_ROLLOVER_LEAD)
+}
+
+/// Parse --exchange-rollover: an interval such as `1435m`, or `off`
+pub fn parse_rollover(text: &str) -> Result<Option<Duration>, String> {
+    match text {
+        "off" => Ok(None),
+        _ => crate::parse_interval(text).map(Some),
+    }
+}
+
+/// What to do with a frame from the current connection
+#[derive(Debug, PartialEq, Eq)]
+pub enum CurrentFrame {
+    Pass, // As usual
+    Skip, // The standby that took over already delivered it
+    /// Pass it on, then switch to the standby. The frames it delivered ahead
+    /// of the current connection (with their receive times) follow, to be
+    /// handled like any frame of the new current connection.
+    PassThenSwitch(Vec<(String, i64)>),
+}
+
+/// Age, standby and handovers of one exchange feed across its connections
+#[derive(Debug)]
+pub struct Rollover {
+    after: Option<Duration>, // Connection age at which a standby takes over
+    reset_limit: Option<Duration>,
+    connected_at: Instant,
+    standby_at: Option<Instant>, // When to open the next standby
+    standby_opened: Option<Instant>,
+    current_seen: HashSet<String>, // Delivered by the current connection while the standby is open
+    standby_ahead: Vec<(String, i64)>, // Delivered by the standby first, in order
+    duplicates: HashSet<String>, // Delivered before the switch, still expected from the new connection
+    last_frame: Option<i64>,     // Receive time of the latest frame passed on (epoch nanos)
+    pending_reset: Option<f64>, // Age of a connection the exchange reset, until the first new frame
+    handovers: Vec<ConnectionHandover>,
+}
+
+impl Rollover {
+    /// `after` is the --exchange-rollover age; `exchange` decides whether
+    /// closes are checked against the 24-hour limit
+    pub fn new(exchange: &str, after: Option<Duration>) -> Self {
+        let now = Instant::now();
+        Self {
+            after,
+            reset_limit: exchange
+                .eq_ignore_ascii_case("binance")
+                .then_some(BINANCE_CONNECTION_LIMIT),
+            connected_at: now,
+            standby_at: after.map(|after| now + after),
+            standby_opened: None,
+            current_seen: HashSet::new(),
+            standby_ahead: Vec::new(),
+            duplicates: HashSet::new(),
+            last_frame: None,
+            pending_reset: None,
+            handovers: Vec::new(),
+        }
+    }
+
+    /// A new connection replaced one that was lost
+    pub fn reconnected(&mut self) {
+        let now = Instant::now();
+        self.connected_at = now;
+        self.standby_at = self.after.map(|after| now + after);
+        self.standby_opened = None;
+        self.current_seen.clear();
+        self.standby_ahead.clear();
+        self.duplicates.clear();
+    }
+
+    /// When a standby connection should be opened, unless one already is
+    pub fn standby_due(&self) -> Option<Instant> {
+        self.standby_at.filter(|_| self.standby_opened.is_none())
+    }
+
+    /// The standby is connected and is read alongside the current connection
+    pub fn standby_opened(&mut self) {
+        self.standby_opened = Some(Instant::now());
+        self.standby_at = None;
+        self.current_seen.clear();
+        self.standby_ahead.clear();
+    }
+
+    /// The standby could not connect or closed before taking over
+    pub fn standby_failed(&mut self) {
+        self.standby_opened = None;
+        self.standby_at = Some(Instant::now() + STANDBY_RETRY);
+        self.current_seen.clear();
+        self.standby_ahead.clear();
+    }
+
+    /// A frame from the current connection, received at `received`
+    pub fn current_frame(&mut self, text: &str, received: i64) -> CurrentFrame {
+        if self.standby_opened.is_some() {
+            // The standby delivered this one first: the current connection
+            // has caught up, and the standby covers everything after it
+            if let Some(i) = self.standby_ahead.iter().position(|(held, _)| held == text) {
+                let mut ahead = std::mem::take(&mut self.standby_ahead);
+                ahead.remove(i);
+                self.pass(received);
+                self.switch(received, 0);
+                return CurrentFrame::PassThenSwitch(ahead);
+            }
+            if self.current_seen.len() < MAX_OVERLAP_FRAMES {
+                self.current_seen.insert(text.to_string());
+            }
+        } else if !self.duplicates.is_empty() && self.duplicates.remove(text) {
+            if let Some(handover) = self.handovers.last_mut() {
+                handover.duplicates_skipped += 1;
+            }
+            return CurrentFrame::Skip;
+        }
+        self.pass(received);
+        CurrentFrame::Pass
+    }
+
+    /// A frame from the standby, received at `received`. Until the standby
+    /// takes over its frames are held. It does once it delivers a frame the
+    /// current connection already has, so nothing is missed in between; the
+    /// held frames are then returned, to be handled like any frame of the
+    /// new current connection.
+    pub fn standby_frame(&mut self, text: String, received: i64) -> Option<Vec<(String, i64)>> {
+        let opened = self.standby_opened?;
+        if self.current_seen.remove(&text) {
+            self.switch(received, 1);
+            return Some(std::mem::take(&mut self.standby_ahead));
+        }
+        self.standby_ahead.push((text, received));
+        // The two connections do not carry the same frames; switch anyway
+        if self.standby_ahead.len() >= MAX_OVERLAP_FRAMES || opened.elapsed() >= MAX_OVERLAP {
+            let ahead = std::mem::take(&mut self.standby_ahead);
+            self.switch(received, 0);
+            return Some(ahead);
+        }
+        None
+    }
+
+    /// The exchange closed the current connection. Returns whether this was
+    /// its scheduled reset, which is then recorded once a new connection
+    /// delivers its first frame.
+    pub fn closed(&mut self) -> bool {
+        let age = self.connected_at.elapsed();
+        let scheduled = self
+            .reset_limit
+            .is_some_and(|limit| age + RESET_TOLERANCE >= limit);
+        if scheduled {
+            self.pending_reset = Some(age.as_secs_f64());
+        }
+        scheduled
+    }
+
+    pub fn handovers(&self) -> &[ConnectionHandover] {
+        &self.handovers
+    }
+
+    /// A frame received at `received` is passed on
+    fn pass(&mut self, received: i64) {
+        if let Some(connection_age_secs) = self.pending_reset.take() {
+            self.record(HandoverReason::ExchangeReset, connection_age_secs, received);
+        }
+        self.last_frame = Some(received);
+    }
+
+    /// The standby becomes the current connection. Frames the old one
+    /// delivered may still arrive on the new one and are skipped then.
+    fn switch(&mut self, received: i64, duplicates_skipped: u64) {
+        let age = self.connected_at.elapsed().as_secs_f64();
+        let duplicates = std::mem::take(&mut self.current_seen);
+        self.reconnected();
+        self.duplicates = duplicates;
+        self.record(HandoverReason::Rollover, age, received);
+        if let Some(handover) = self.handovers.last_mut() {
+            handover.duplicates_skipped = duplicates_skipped;
+        }
+    }
+
+    fn record(&mut self, reason: HandoverReason, connection_age_secs: f64, received: i64) {
+        let gap_ms = self
+            .last_frame
+            .map_or(0.0, |last| (received - last).max(0) as f64 / 1e6);
+        self.handovers.push(ConnectionHandover {
+            time: received,
+            reason,
+            connection_age_secs,
+            gap_ms,
+            duplicates_skipped: 0,
+        });
+    }
+}
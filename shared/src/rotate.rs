@@ -0,0 +1,215 @@
+// Size- and time-based rotation for long-running output files (raw CSV, captures)
+
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// When a rotating file is closed and the next one started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RotationPolicy {
+    pub max_bytes: Option<u64>, // Rotate once a file reaches this size (uncompressed)
+    pub interval: Option<Duration>, // Rotate once a file has been open this long
+    pub compress: bool,         // Gzip each file once it is closed
+}
+
+impl RotationPolicy {
+    /// Whether files are rotated at all
+    pub fn rotates(&self) -> bool {
+        self.max_bytes.is_some() || self.interval.is_some()
+    }
+}
+
+/// Output split over files named `<stem>-<UTC timestamp>.<ext>` next to the
+/// base path, e.g. `measurements-20240108T120000Z.csv` for `measurements.csv`.
+/// Every file starts with `header`. Files are only rotated by `rotate_if_due`
+/// and `rotate`, so callers rotate between records and never split one.
+pub struct RotatingFile {
+    base: PathBuf,
+    policy: RotationPolicy,
+    header: Vec<u8>,
+    file: BufWriter<File>,
+    path: PathBuf,
+    written: u64,
+    opened: Instant,
+    compressing: Vec<JoinHandle<()>>,
+}
+
+impl RotatingFile {
+    /// Open the first file
+    pub fn create(
+        base: impl Into<PathBuf>,
+        policy: RotationPolicy,
+        header: &[u8],
+    ) -> io::Result<Self> {
+        let base = base.into();
+        let (file, path) = open_next(&base, header)?;
+        Ok(Self {
+            base,
+            policy,
+            header: header.to_vec(),
+            file,
+            path,
+            written: header.len() as u64,
+            opened: Instant::now(),
+            compressing: Vec::new(),
+        })
+    }
+
+    /// File currently written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the current file has reached the size or age limit
+    pub fn is_due(&self) -> bool {
+        self.policy.max_bytes.is_some_and(|max| self.written >= max)
+            || self
+                .policy
+                .interval
+                .is_some_and(|interval| self.opened.elapsed() >= interval)
+    }
+
+    /// Rotate if the current file is due; returns whether it was
+    pub fn rotate_if_due(&mut self) -> io::Result<bool> {
+        if !self.is_due() {
+            return Ok(false);
+        }
+        self.rotate()?;
+        Ok(true)
+    }
+
+    /// Close the current file and start the next one, returning its path
+    pub fn rotate(&mut self) -> io::Result<&Path> {
+        self.file.flush()?;
+        let (file, path) = open_next(&self.base, &self.header)?;
+        self.file = file;
+        let closed = std::mem::replace(&mut self.path, path);
+        self.written = self.header.len() as u64;
+        self.opened = Instant::now();
+        info!(closed = %closed.display(), path = %self.path.display(), "rotated output file");
+
+        if self.policy.compress {
+            self.compressing.retain(|handle| !handle.is_finished());
+            self.compressing
+                .push(std::thread::spawn(move || compress_logged(&closed)));
+        }
+        Ok(&self.path)
+    }
+
+    /// Flush the current file, compressing it too if the policy says so, and
+    /// wait for earlier files to finish compressing
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        drop(self.file);
+        if self.policy.compress {
+            compress(&self.path)?;
+        }
+        for handle in self.compressing {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Create the next file for `base`, adding a counter when a file for the same
+/// second already exists
+fn open_next(base: &Path, header: &[u8]) -> io::Result<(BufWriter<File>, PathBuf)> {
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = base
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+
+    let mut path = base.with_file_name(format!("{}-{}{}", stem, timestamp, extension));
+    let mut n = 1;
+    while path.exists() || gz_path(&path).exists() {
+        path = base.with_file_name(format!("{}-{}-{}{}", stem, timestamp, n, extension));
+        n += 1;
+    }
+
+    let mut file = BufWriter::new(File::create(&path)?);
+    file.write_all(header)?;
+    Ok((file, path))
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    PathBuf::from(gz)
+}
+
+/// Replace `path` with `path.gz`; the original is kept if anything fails
+fn compress(path: &Path) -> io::Result<()> {
+    let gz = gz_path(path);
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(&gz)?), Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    std::fs::remove_file(path)
+}
+
+fn compress_logged(path: &Path) {
+    if let Err(e) = compress(path) {
+        warn!(path = %path.display(), error = %e, "failed to compress rotated file");
+    }
+}
+
+/// Parse a size such as `500MB`, `2G` or `1048576` (bytes). Units are
+/// binary: K = 1024 bytes, M = 1024 K, G = 1024 M.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return Err(format!("invalid size: {} (e.g. 500MB)", text)),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&bytes| bytes > 0)
+        .ok_or_else(|| format!("invalid size: {} (e.g. 500MB)", text))
+}
+
+/// Parse an interval such as `1h`, `30m`, `90s`, `1d` or `3600` (seconds)
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, secs_per_unit) = match text.char_indices().last() {
+        Some((i, 's')) => (&text[..i], 1),
+        Some((i, 'm')) => (&text[..i], 60),
+        Some((i, 'h')) => (&text[..i], 3600),
+        Some((i, 'd')) => (&text[..i], 86_400),
+        _ => (text, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(secs_per_unit))
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("invalid interval: {} (e.g. 1h, 30m or 3600)", text))
+}
@@ -0,0 +1,69 @@
+// Run IDs (--run-id)
+//
+// The forwarder stamps every event with the ID of its run, so the receiver can
+// tell a stale forwarder process still sending to the same port apart from the
+// run it is measuring. IDs also name the run's folder for --s3-upload.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Longest accepted run ID
+pub const MAX_RUN_ID_LEN: usize = 64;
+
+/// A random UUID (version 4), e.g. `0f8fad5b-d9cb-469f-a165-70867728950e`
+pub fn new_run_id() -> String {
+    let mut bytes = [0u8; 16];
+    if !urandom(&mut bytes) {
+        // No /dev/urandom: std's randomly seeded hasher over the time and PID
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_u32(std::process::id());
+            hasher.write_usize(i);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn urandom(bytes: &mut [u8]) -> bool {
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(bytes))
+        .is_ok()
+}
+
+/// Check a run ID given on the command line. IDs are embedded in every event
+/// and used as an S3 key segment, so only letters, digits, `-`, `_` and `.`
+/// are accepted.
+pub fn validate_run_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || id.len() > MAX_RUN_ID_LEN {
+        return Err(format!(
+            "run ID must be 1 to {} characters long",
+            MAX_RUN_ID_LEN
+        ));
+    }
+    match id
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        Some(c) => Err(format!("invalid character {:?} in run ID {}", c, id)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,149 @@
+// Upload of run output to S3 (--s3-upload)
+//
+// The experiment instances are short-lived, so output files are copied to S3
+// when a run ends. Uploads go through the AWS CLI, which the instances already
+// have and which picks up the instance role or the usual credential chain.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Bucket and key prefix from `s3://bucket/prefix/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Destination {
+    pub bucket: String,
+    pub prefix: String, // Empty, or ends with '/'
+}
+
+impl FromStr for S3Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("expected s3://bucket/prefix/, got {}", s))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("missing bucket in {}", s));
+        }
+        let prefix = match prefix.trim_matches('/') {
+            "" => String::new(),
+            prefix => format!("{}/", prefix),
+        };
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for S3Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+impl S3Destination {
+    /// URL for a file of a run: `s3://bucket/prefix/<run id>/<file name>`
+    pub fn url(&self, run_id: &str, file: &Path) -> String {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        format!("{}{}/{}", self, run_id, name)
+    }
+
+    /// Copy each file that was written since `since` to the run's folder;
+    /// files left over from an earlier run are skipped. Returns how many were
+    /// uploaded. Failures are logged, not returned, so one missing file or a
+    /// throttled request does not stop the others.
+    pub async fn upload(&self, run_id: &str, files: &[PathBuf], since: SystemTime) -> usize {
+        let mut uploaded = 0;
+        for file in files {
+            let written = std::fs::metadata(file)
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified >= since);
+            if !written || !file.is_file() {
+                continue;
+            }
+            let url = self.url(run_id, file);
+            let status = Command::new("aws")
+                .args(["s3", "cp", "--only-show-errors"])
+                .arg(file)
+                .arg(&url)
+                .status()
+                .await;
+            match status {
+                Ok(status) if status.success() => {
+                    info!(file = %file.display(), url = %url, "uploaded");
+                    uploaded += 1;
+                }
+                Ok(status) => warn!(file = %file.display(), url = %url, %status, "upload failed"),
+                Err(e) => warn!(file = %file.display(), error = %e, "could not run the AWS CLI"),
+            }
+        }
+        uploaded
+    }
+}
+
+/// Check that the AWS CLI can be run, so a missing CLI is reported before the
+/// run rather than after it
+pub async fn check_aws_cli() -> Result<(), String> {
+    match Command::new("aws").arg("--version").output().await {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!("aws --version failed: {}", output.status)),
+        Err(e) => Err(format!("the AWS CLI is needed for --s3-upload: {}", e)),
+    }
+}
+
+/// Files written for `path` during this run: the path itself and rotated
+/// files named `<stem>-<timestamp>.<ext>[.gz]` that were modified since `since`
+pub fn output_files(path: &str, since: SystemTime) -> Vec<PathBuf> {
+    let base = Path::new(path.strip_suffix(".gz").unwrap_or(path));
+    let mut files = vec![PathBuf::from(path)];
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = base
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let dir = match base.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    let rotated_prefix = format!("{}-", stem);
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let name_stem = name.strip_suffix(".gz").unwrap_or(&name);
+        let rotated = name_stem.starts_with(&rotated_prefix) && name_stem.ends_with(&extension);
+        if rotated && modified_since(&entry, since) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Files directly in `dir` that were modified since `since`
+pub fn files_in(dir: &str, since: SystemTime) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_file() && modified_since(entry, since))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    files
+}
+
+fn modified_since(entry: &std::fs::DirEntry, since: SystemTime) -> bool {
+    entry
+        .metadata()
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified >= since)
+}
@@ -0,0 +1,63 @@
+// Graceful shutdown on SIGINT/SIGTERM
+
+use tokio::sync::watch;
+use tracing::info;
+
+/// Handle that resolves once the process has been asked to stop.
+/// Cloneable so every task can observe the same signal.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    rx: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    /// Install signal handlers for Ctrl-C (SIGINT) and, on Unix, SIGTERM.
+    /// Must be called from within a tokio runtime.
+    pub fn install() -> Self {
+        let (tx, rx) = watch::channel(false);
+
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            let _ = tx.send(true);
+        });
+
+        Self { rx }
+    }
+
+    /// Whether a shutdown signal has been received
+    pub fn is_triggered(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Wait until a shutdown signal is received
+    pub async fn wait(&mut self) {
+        // An error means the sender is gone, which only happens after it fired
+        let _ = self.rx.wait_for(|triggered| *triggered).await;
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    use tracing::warn;
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            warn!(error = %e, "failed to install SIGTERM handler");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("received SIGINT, shutting down"),
+        _ = sigterm.recv() => info!("received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+    info!("received Ctrl-C, shutting down");
+}
@@ -0,0 +1,325 @@
+// Outputs for a run's measurements and results (--sink)
+//
+// Every sink is handed each measurement as it is recorded and the final
+// report when the run ends, so any number of them can run side by side: the
+// raw CSV, the results JSON, a Parquet file, stdout and InfluxDB, and with
+// --split-by-symbol a CSV file per symbol. Writes must
+// not hold up the measuring loop; a sink that fails keeps the error and
+// returns it when finishing, after the other sinks had their turn.
+
+use crate::error::{ExperimentError, Result};
+use crate::influx::{InfluxConfig, InfluxSink};
+use crate::parquet_sink::ParquetSink;
+use futures_util::future::BoxFuture;
+use latency_core::{CsvWriter, LatencyMeasurement, Report, CSV_HEADER};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Where a run writes its measurements or results
+pub trait OutputSink: Send {
+    /// One measurement, as soon as it is recorded
+    fn write(&mut self, measurement: &LatencyMeasurement);
+
+    /// The run ended: write what waits for the results and flush the rest
+    fn finish<'a>(self: Box<Self>, report: &'a Report) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A --sink argument: `csv:PATH`, `json:PATH`, `parquet:PATH`, `stdout` or
+/// `influx:URL`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkSpec {
+    Csv(String),     // Raw measurements
+    Json(String),    // Results
+    Parquet(String), // Raw measurements
+    Stdout,          // Raw measurements as CSV
+    Influx(String),  // Raw measurements, as line protocol to this server
+}
+
+impl FromStr for SinkSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, target) = match s.split_once(':') {
+            Some((kind, target)) => (kind, Some(target).filter(|target| !target.is_empty())),
+            None => (s, None),
+        };
+        let required = || {
+            target
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} sink needs a target, e.g. {}:PATH", kind, kind))
+        };
+        match kind {
+            "csv" => required().map(SinkSpec::Csv),
+            "json" => required().map(SinkSpec::Json),
+            "parquet" => required().map(SinkSpec::Parquet),
+            "influx" => required().map(SinkSpec::Influx),
+            "stdout" if target.is_none() => Ok(SinkSpec::Stdout),
+            "stdout" => Err("stdout sink takes no target".to_string()),
+            _ => Err(format!(
+                "unknown sink {}: expected csv, json, parquet, stdout or influx",
+                kind
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkSpec::Csv(path) => write!(f, "csv:{}", path),
+            SinkSpec::Json(path) => write!(f, "json:{}", path),
+            SinkSpec::Parquet(path) => write!(f, "parquet:{}", path),
+            SinkSpec::Stdout => f.write_str("stdout"),
+            SinkSpec::Influx(url) => write!(f, "influx:{}", url),
+        }
+    }
+}
+
+impl SinkSpec {
+    /// Local file the sink writes, for uploads
+    pub fn path(&self) -> Option<PathBuf> {
+        match self {
+            SinkSpec::Csv(path) | SinkSpec::Json(path) | SinkSpec::Parquet(path) => {
+                Some(PathBuf::from(path))
+            }
+            SinkSpec::Stdout | SinkSpec::Influx(_) => None,
+        }
+    }
+
+    /// Open the sink. `influx` holds the settings besides the URL, and the
+    /// tags added to every point.
+    pub fn open(
+        &self,
+        influx: &InfluxConfig,
+        tags: &[(&str, &str)],
+    ) -> Result<Box<dyn OutputSink>> {
+        Ok(match self {
+            SinkSpec::Csv(path) => Box::new(CsvSink::create(path)?),
+            SinkSpec::Json(path) => Box::new(JsonSink::new(path)),
+            SinkSpec::Parquet(path) => Box::new(ParquetSink::create(path)?),
+            SinkSpec::Stdout => Box::new(StdoutSink::new()?),
+            SinkSpec::Influx(url) => {
+                let config = InfluxConfig {
+                    url: url.clone(),
+                    ..influx.clone()
+                };
+                Box::new(InfluxSink::start(config, tags)?)
+            }
+        })
+    }
+}
+
+/// All sinks of a run
+#[derive(Default)]
+pub struct Sinks(Vec<Box<dyn OutputSink>>);
+
+impl Sinks {
+    pub fn new(sinks: Vec<Box<dyn OutputSink>>) -> Self {
+        Self(sinks)
+    }
+
+    /// Hand a measurement to every sink
+    pub fn write(&mut self, measurement: &LatencyMeasurement) {
+        for sink in &mut self.0 {
+            sink.write(measurement);
+        }
+    }
+
+    /// Finish every sink, returning the first error
+    pub async fn finish(self, report: &Report) -> Result<()> {
+        let mut result = Ok(());
+        for sink in self.0 {
+            if let Err(e) = sink.finish(report).await {
+                warn!(error = %e, "output sink failed");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Raw measurements as CSV, written as they are recorded
+pub struct CsvSink {
+    path: String,
+    csv: CsvWriter,
+    error: Option<io::Error>,
+}
+
+impl CsvSink {
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            csv: CsvWriter::create(path)?,
+            error: None,
+        })
+    }
+}
+
+impl OutputSink for CsvSink {
+    fn write(&mut self, measurement: &LatencyMeasurement) {
+        if self.error.is_none() {
+            if let Err(e) = self.csv.write(measurement) {
+                warn!(path = %self.path, error = %e, "failed to write CSV row");
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish<'a>(mut self: Box<Self>, _report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Some(e) = self.error.take() {
+                return Err(e.into());
+            }
+            self.csv.flush()?;
+            info!(path = %self.path, "raw measurements written");
+            Ok(())
+        })
+    }
+}
+
+/// `path` with the symbol added to its name: `raw.csv` becomes
+/// `raw-BTCUSDT.csv`. Characters that do not belong in a file name become `_`.
+pub fn symbol_path(path: &str, symbol: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let symbol: String = symbol
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, symbol, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, symbol),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Raw measurements as CSV, a file per symbol next to `path`
+/// (--split-by-symbol). Files are created when their symbol is first seen;
+/// measurements without a symbol only go to the combined CSV.
+pub struct SymbolCsvSink {
+    path: String,
+    files: HashMap<Arc<str>, CsvWriter>,
+    error: Option<io::Error>,
+}
+
+impl SymbolCsvSink {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            files: HashMap::new(),
+            error: None,
+        }
+    }
+}
+
+impl OutputSink for SymbolCsvSink {
+    fn write(&mut self, measurement: &LatencyMeasurement) {
+        let Some(symbol) = &measurement.symbol else {
+            return;
+        };
+        if self.error.is_some() {
+            return;
+        }
+        let csv = match self.files.get_mut(symbol) {
+            Some(csv) => csv,
+            None => match CsvWriter::create(&symbol_path(&self.path, symbol)) {
+                Ok(csv) => self.files.entry(symbol.clone()).or_insert(csv),
+                Err(e) => {
+                    warn!(path = %symbol_path(&self.path, symbol), error = %e, "failed to create symbol CSV");
+                    self.error = Some(e);
+                    return;
+                }
+            },
+        };
+        if let Err(e) = csv.write(measurement) {
+            warn!(path = %symbol_path(&self.path, symbol), error = %e, "failed to write CSV row");
+            self.error = Some(e);
+        }
+    }
+
+    fn finish<'a>(mut self: Box<Self>, _report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if let Some(e) = self.error.take() {
+                return Err(e.into());
+            }
+            for csv in self.files.values_mut() {
+                csv.flush()?;
+            }
+            info!(path = %self.path, symbols = self.files.len(), "raw measurements written per symbol");
+            Ok(())
+        })
+    }
+}
+
+/// Results as pretty-printed JSON, written when the run ends
+pub struct JsonSink {
+    path: String,
+}
+
+impl JsonSink {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl OutputSink for JsonSink {
+    fn write(&mut self, _measurement: &LatencyMeasurement) {}
+
+    fn finish<'a>(self: Box<Self>, report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            report.write_json(&self.path)?;
+            info!(path = %self.path, "results written");
+            Ok(())
+        })
+    }
+}
+
+/// Raw measurements as CSV on stdout, for piping into other tools
+pub struct StdoutSink {
+    csv: CsvWriter<io::Stdout>,
+    error: Option<io::Error>,
+}
+
+impl StdoutSink {
+    pub fn new() -> Result<Self> {
+        let mut stdout = io::stdout();
+        stdout.write_all(CSV_HEADER.as_bytes())?;
+        Ok(Self {
+            csv: CsvWriter::from_writer(stdout),
+            error: None,
+        })
+    }
+}
+
+impl OutputSink for StdoutSink {
+    fn write(&mut self, measurement: &LatencyMeasurement) {
+        if self.error.is_none() {
+            if let Err(e) = self.csv.write(measurement) {
+                // Usually the reading end of a pipe went away
+                warn!(error = %e, "failed to write to stdout");
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish<'a>(mut self: Box<Self>, _report: &'a Report) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match self.error.take() {
+                Some(e) => Err(ExperimentError::Io(e)),
+                None => Ok(self.csv.flush()?),
+            }
+        })
+    }
+}
@@ -0,0 +1,188 @@
+// In-band stream subscriptions on a Binance connection (--subscribe)
+//
+// Rather than naming its streams in the URL, the connection goes to the bare
+// combined-stream endpoint and asks for them with
+// `{"method":"SUBSCRIBE","params":[...],"id":N}`. Binance answers each request
+// with `{"result":null,"id":N}`, or `{"error":{...},"id":N}` if it rejects it.
+// Streams can then be added and dropped while the connection stays open, and
+// the time to each answer is recorded. After a reconnect the streams are
+// requested again.
+
+use latency_core::{SubscriptionChange, SubscriptionMethod};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Split a single-stream URL such as `wss://host/ws/btcusdt@aggTrade` into
+/// the bare combined-stream endpoint (`wss://host/stream`) and the stream
+pub fn split_stream_url(url: &str) -> Option<(String, &str)> {
+    let (base, stream) = url.rsplit_once("/ws/")?;
+    (!stream.is_empty() && !stream.contains(['/', '?']))
+        .then(|| (format!("{}/stream", base), stream))
+}
+
+/// A reply to a subscription request
+#[derive(Debug, Deserialize)]
+struct Reply {
+    id: u64,
+    #[serde(default)]
+    error: Option<ReplyError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplyError {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    msg: String,
+}
+
+/// A frame that answered a subscription request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub id: u64,
+    /// The request it answered, now with its acknowledgement time; `None`
+    /// if the request was already answered (it went to two connections)
+    pub change: Option<SubscriptionChange>,
+}
+
+/// A request waiting for its reply
+#[derive(Debug)]
+struct Pending {
+    id: u64,
+    change: usize, // Index into `changes`
+}
+
+/// Subscribed streams of one feed, across its connections
+#[derive(Debug)]
+pub struct Subscriptions {
+    active: Vec<String>,
+    next_id: u64,
+    pending: Vec<Pending>,
+    changes: Vec<SubscriptionChange>,
+}
+
+impl Subscriptions {
+    /// `streams` are requested by `resubscribe` once connected
+    pub fn new(streams: Vec<String>) -> Self {
+        Self {
+            active: streams,
+            next_id: 1,
+            pending: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Streams the exchange confirmed, plus those asked for when connecting
+    pub fn active(&self) -> &[String] {
+        &self.active
+    }
+
+    /// Request for every active stream, to send on a new connection at
+    /// `now` (epoch nanos)
+    pub fn resubscribe(&mut self, now: i64) -> Option<String> {
+        if self.active.is_empty() {
+            return None;
+        }
+        let streams = self.active.clone();
+        Some(self.request(SubscriptionMethod::Subscribe, streams, now).1)
+    }
+
+    /// Request to add `streams`, sent at `now`; returns its ID and the
+    /// message. The streams count as active once the exchange acknowledges them.
+    pub fn subscribe(&mut self, streams: Vec<String>, now: i64) -> Result<(u64, String), String> {
+        check_streams(&streams)?;
+        Ok(self.request(SubscriptionMethod::Subscribe, streams, now))
+    }
+
+    /// Request to drop `streams`, sent at `now`, like `subscribe`
+    pub fn unsubscribe(&mut self, streams: Vec<String>, now: i64) -> Result<(u64, String), String> {
+        check_streams(&streams)?;
+        if let Some(stream) = streams.iter().find(|&stream| !self.active.contains(stream)) {
+            return Err(format!("not subscribed to {}", stream));
+        }
+        Ok(self.request(SubscriptionMethod::Unsubscribe, streams, now))
+    }
+
+    /// Check whether `text`, received at `received`, answers a subscription
+    /// request. Such frames carry no event.
+    pub fn reply(&mut self, text: &str, received: i64) -> Option<Answer> {
+        // Market data frames have no "id" key
+        if !text.contains("\"id\"") {
+            return None;
+        }
+        let reply: Reply = serde_json::from_str(text).ok()?;
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| pending.id == reply.id)
+        else {
+            return Some(Answer {
+                id: reply.id,
+                change: None,
+            });
+        };
+        let change = &mut self.changes[self.pending.remove(index).change];
+        change.ack_ms = Some((received - change.time).max(0) as f64 / 1e6);
+        match reply.error {
+            Some(error) => change.error = Some(format!("{} (code {})", error.msg, error.code)),
+            None => match change.method {
+                SubscriptionMethod::Subscribe => {
+                    for stream in &change.streams {
+                        if !self.active.contains(stream) {
+                            self.active.push(stream.clone());
+                        }
+                    }
+                }
+                SubscriptionMethod::Unsubscribe => self
+                    .active
+                    .retain(|stream| !change.streams.contains(stream)),
+            },
+        }
+        Some(Answer {
+            id: reply.id,
+            change: Some(change.clone()),
+        })
+    }
+
+    /// Every request sent, in order
+    pub fn changes(&self) -> &[SubscriptionChange] {
+        &self.changes
+    }
+
+    fn request(
+        &mut self,
+        method: SubscriptionMethod,
+        streams: Vec<String>,
+        now: i64,
+    ) -> (u64, String) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = json!({ "method": method.to_string(), "params": streams, "id": id });
+        self.pending.push(Pending {
+            id,
+            change: self.changes.len(),
+        });
+        self.changes.push(SubscriptionChange {
+            time: now,
+            method,
+            streams,
+            ack_ms: None,
+            error: None,
+        });
+        (id, message.to_string())
+    }
+}
+
+/// Binance stream names are lowercase, e.g. `btcusdt@aggTrade`
+fn check_streams(streams: &[String]) -> Result<(), String> {
+    if streams.is_empty() {
+        return Err("no streams given".to_string());
+    }
+    match streams.iter().find(|stream| !stream.contains('@')) {
+        Some(stream) => Err(format!(
+            "invalid stream {}: expected SYMBOL@TYPE, e.g. btcusdt@aggTrade",
+            stream
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,41 @@
+// Output file names from templates (--output-template)
+//
+// `{name}` placeholders are replaced with details of the run, so back-to-back
+// runs write to different files instead of overwriting results.json.
+
+/// Replace every `{name}` in `template` with its value in `values`. Values are
+/// made safe for file names: anything but letters, digits, `-`, `_` and `.`
+/// becomes `-`, so `BTC/USDT` cannot start a directory.
+pub fn expand_template(template: &str, values: &[(&str, &str)]) -> Result<String, String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("unclosed {{ in template {}", template));
+        };
+        let name = &rest[start + 1..start + end];
+        let Some((_, value)) = values.iter().find(|(known, _)| *known == name) else {
+            let known: Vec<String> = values
+                .iter()
+                .map(|(name, _)| format!("{{{}}}", name))
+                .collect();
+            return Err(format!(
+                "unknown placeholder {{{}}} in template {}; expected one of {}",
+                name,
+                template,
+                known.join(", ")
+            ));
+        };
+        expanded.extend(value.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        }));
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
@@ -0,0 +1,102 @@
+// TLS configuration for the Tokyo → receiver leg (rustls)
+
+use std::sync::Arc;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+fn invalid(what: &str, path: &str, e: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("{} {}: {}", what, path, e),
+    )
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, std::io::Error> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .map_err(|e| invalid("cannot read certificates from", path, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid("invalid certificate in", path, e))?;
+    if certs.is_empty() {
+        return Err(invalid("no certificates in", path, "empty PEM file"));
+    }
+    Ok(certs)
+}
+
+/// TLS client side: trusts the given CA bundle and verifies the receiver's name
+#[derive(Clone)]
+pub struct TlsClient {
+    connector: TlsConnector,
+    server_name: Option<ServerName<'static>>, // Overrides the name taken from the address
+}
+
+impl TlsClient {
+    /// Trust the certificates in `ca_path` (PEM). `server_name` overrides the
+    /// name checked against the receiver certificate; by default the host
+    /// part of the address is used, so IP addresses need an IP SAN.
+    pub fn new(ca_path: &str, server_name: Option<&str>) -> Result<Self, std::io::Error> {
+        let mut roots = RootCertStore::empty();
+        for cert in load_certs(ca_path)? {
+            roots
+                .add(cert)
+                .map_err(|e| invalid("unusable CA certificate in", ca_path, e))?;
+        }
+
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(std::io::Error::other)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let server_name = server_name
+            .map(|name| {
+                ServerName::try_from(name.to_string())
+                    .map_err(|e| invalid("invalid TLS server name", name, e))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(config)),
+            server_name,
+        })
+    }
+
+    /// Run the TLS handshake over an established connection to `addr` (host:port)
+    pub async fn connect<S>(
+        &self,
+        addr: &str,
+        stream: S,
+    ) -> Result<tokio_rustls::client::TlsStream<S>, std::io::Error>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let server_name = match &self.server_name {
+            Some(name) => name.clone(),
+            None => {
+                let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                ServerName::try_from(host.to_string())
+                    .map_err(|e| invalid("invalid TLS server name", host, e))?
+            }
+        };
+        self.connector.connect(server_name, stream).await
+    }
+}
+
+/// Build a TLS acceptor from a PEM certificate chain and private key
+pub fn tls_acceptor(cert_path: &str, key_path: &str) -> Result<TlsAcceptor, std::io::Error> {
+    let certs = load_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| invalid("cannot read private key from", key_path, e))?;
+
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(std::io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid("certificate and key do not match in", cert_path, e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
@@ -0,0 +1,80 @@
+// Receiver-side check of forwarded payloads (--verify-payload)
+
+use crate::exchange::ExchangeAdapter;
+use crate::ForwardedEventView;
+use latency_core::{PayloadCheck, PayloadCheckStats};
+use serde::Deserialize;
+
+/// The exchange payload of a forwarded event, which `ForwardedEventView` skips
+#[derive(Deserialize)]
+pub(crate) struct Payload {
+    pub event_data: String,
+}
+
+/// Parses the exchange payload of each forwarded event again and compares it
+/// with the envelope the latency is computed from
+pub struct PayloadVerifier {
+    adapter: Box<dyn ExchangeAdapter>,
+    symbol: String, // Expected symbol, normalized by `normalize_symbol`
+    stats: PayloadCheckStats,
+}
+
+impl PayloadVerifier {
+    /// Expect events of `symbol` (BASE-QUOTE) from the exchange `adapter` parses
+    pub fn new(adapter: Box<dyn ExchangeAdapter>, symbol: &str) -> Self {
+        Self {
+            adapter,
+            symbol: normalize_symbol(symbol),
+            stats: PayloadCheckStats::default(),
+        }
+    }
+
+    /// Check the payload of `frame`, already parsed as `event`. Replayed
+    /// events carry shifted event times, so only their symbol is compared.
+    pub fn check(&mut self, frame: &[u8], event: &ForwardedEventView) -> PayloadCheck {
+        let check = self.compare(frame, event);
+        self.stats.record(&check);
+        check
+    }
+
+    fn compare(&self, frame: &[u8], event: &ForwardedEventView) -> PayloadCheck {
+        let Ok(payload) = serde_json::from_slice::<Payload>(frame) else {
+            return PayloadCheck::Unparsable;
+        };
+        let Ok(Some(parsed)) = self.adapter.parse(&payload.event_data) else {
+            return PayloadCheck::Unparsable;
+        };
+        // A combined stream carries several symbols; each event must match the
+        // one in its stream name (btcusdt@aggTrade) instead
+        let symbol = normalize_symbol(&parsed.symbol);
+        let expected = match event.stream.and_then(|stream| stream.split_once('@')) {
+            Some((stream_symbol, _)) => symbol == normalize_symbol(stream_symbol),
+            None => symbol == self.symbol,
+        };
+        if !expected {
+            return PayloadCheck::SymbolMismatch {
+                payload: parsed.symbol.into_owned(),
+            };
+        }
+        if !event.replayed && parsed.event_time != Some(event.binance_event_time) {
+            return PayloadCheck::EventTimeMismatch {
+                payload: parsed.event_time,
+            };
+        }
+        PayloadCheck::Match
+    }
+
+    pub fn stats(&self) -> PayloadCheckStats {
+        self.stats
+    }
+}
+
+/// Symbols compared without separators or case: `BTC-USDT`, `BTCUSDT` and
+/// `btcusdt` are the same
+fn normalize_symbol(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
@@ -0,0 +1,403 @@
+// Exchange WebSocket connections with optional permessage-deflate (RFC 7692)
+//
+// tungstenite rejects frames with the RSV1 bit set, so compressed messages are
+// inflated below it: `InflateStream` sits between the TLS stream and the
+// WebSocket and rewrites each compressed message into a plain frame before
+// tungstenite reads it. Only the server → client direction is compressed; the
+// occasional subscribe or pong the client sends goes out uncompressed, which
+// the extension allows per message.
+
+use flate2::{Decompress, FlushDecompress, Status};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Error as WsError;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::warn;
+
+/// Socket under an exchange WebSocket
+pub type ExchangeSocket = InflateStream<MaybeTlsStream<TcpStream>>;
+
+/// WebSocket connection to an exchange
+pub type ExchangeStream = WebSocketStream<ExchangeSocket>;
+
+/// Trailer removed from every compressed message, restored before inflating
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Largest frame or inflated message accepted, matching tungstenite's default message limit
+const MAX_MESSAGE_BYTES: usize = 64 << 20;
+
+/// Whether to offer permessage-deflate to the exchange (--ws-compression)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsCompression {
+    On,
+    #[default]
+    Off,
+}
+
+impl WsCompression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WsCompression::On => "on",
+            WsCompression::Off => "off",
+        }
+    }
+}
+
+impl fmt::Display for WsCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WsCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => Ok(WsCompression::On),
+            "off" => Ok(WsCompression::Off),
+            other => Err(format!("expected on or off, got {}", other)),
+        }
+    }
+}
+
+/// Connect to `url` (ws:// or wss://), offering permessage-deflate with
+/// `compression` on. Whether the server accepted it is `compressed()` on the
+/// returned stream's socket.
+pub async fn connect_exchange(
+    url: &str,
+    compression: WsCompression,
+) -> Result<ExchangeStream, WsError> {
+    let mut request = url.into_client_request()?;
+    if compression == WsCompression::On {
+        request.headers_mut().insert(
+            "Sec-WebSocket-Extensions",
+            HeaderValue::from_static("permessage-deflate"),
+        );
+    }
+
+    let uri = request.uri().clone();
+    let host = uri
+        .host()
+        .ok_or(WsError::Url(
+            tokio_tungstenite::tungstenite::error::UrlError::NoHostName,
+        ))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let secure = uri.scheme_str() == Some("wss");
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    let tcp = TcpStream::connect((host.as_str(), port)).await?;
+    let stream = if secure {
+        let connector = tokio_native_tls::native_tls::TlsConnector::new()
+            .map_err(|e| WsError::Tls(e.into()))?;
+        let tls = tokio_native_tls::TlsConnector::from(connector)
+            .connect(&host, tcp)
+            .await
+            .map_err(|e| WsError::Tls(e.into()))?;
+        MaybeTlsStream::NativeTls(tls)
+    } else {
+        MaybeTlsStream::Plain(tcp)
+    };
+
+    let (mut ws, response) =
+        tokio_tungstenite::client_async(request, InflateStream::new(stream)).await?;
+    if compression == WsCompression::On {
+        let negotiated = response
+            .headers()
+            .get_all("Sec-WebSocket-Extensions")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find(|value| value.trim_start().starts_with("permessage-deflate"));
+        if let Some(params) = negotiated {
+            let no_context_takeover = params
+                .split(';')
+                .any(|param| param.trim() == "server_no_context_takeover");
+            ws.get_mut().enable_inflate(no_context_takeover);
+        } else {
+            warn!(
+                url,
+                "exchange declined permessage-deflate, receiving uncompressed"
+            );
+        }
+    }
+    Ok(ws)
+}
+
+/// Inflates permessage-deflate messages read from `inner` into plain frames.
+/// Passes everything through unchanged until `enable_inflate` is called.
+///
+/// Reads stop at the end of the HTTP handshake response, so frames that
+/// arrive right behind it are not taken into tungstenite's handshake buffer
+/// before inflating is enabled.
+pub struct InflateStream<S> {
+    inner: S,
+    handshake: bool, // Still reading the HTTP response
+    inflate: Option<Inflate>,
+    input: Vec<u8>,   // Bytes read from `inner`, not yet a complete frame
+    output: Vec<u8>,  // Rewritten frames for the reader
+    written: usize,   // Bytes of `output` already returned
+    scratch: Vec<u8>, // Read buffer for `inner`
+}
+
+struct Inflate {
+    decompress: Decompress,
+    no_context_takeover: bool, // The server resets its window for every message
+    message: Option<(u8, Vec<u8>)>, // Opcode and compressed payload of a fragmented message
+}
+
+impl<S> InflateStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            handshake: true,
+            inflate: None,
+            input: Vec::new(),
+            output: Vec::new(),
+            written: 0,
+            scratch: vec![0; 16 * 1024],
+        }
+    }
+
+    /// Inflate compressed messages from now on. With `no_context_takeover`,
+    /// each message is inflated with an empty window.
+    pub fn enable_inflate(&mut self, no_context_takeover: bool) {
+        self.inflate = Some(Inflate {
+            decompress: Decompress::new(false),
+            no_context_takeover,
+            message: None,
+        });
+    }
+
+    /// The stream being read
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Whether permessage-deflate was negotiated
+    pub fn compressed(&self) -> bool {
+        self.inflate.is_some()
+    }
+
+    /// Rewrite the next complete frame in `input` into `output`. Returns
+    /// false when more input is needed.
+    fn next_frame(&mut self) -> io::Result<bool> {
+        let Some(frame) = parse_frame(&self.input)? else {
+            return Ok(false);
+        };
+        let inflate = self.inflate.as_mut().expect("inflate enabled");
+        let payload = &self.input[frame.header_len..frame.len()];
+
+        let compressed = frame.rsv1 && matches!(frame.opcode, 0x1 | 0x2);
+        let continuation = frame.opcode == 0x0 && inflate.message.is_some();
+        if compressed || continuation {
+            if frame.masked {
+                return Err(invalid("masked compressed frame from server"));
+            }
+            let (_, message) = inflate
+                .message
+                .get_or_insert_with(|| (frame.opcode, Vec::new()));
+            if message.len() + payload.len() > MAX_MESSAGE_BYTES {
+                return Err(invalid("compressed message too large"));
+            }
+            message.extend_from_slice(payload);
+            if frame.fin {
+                let (opcode, mut message) = inflate.message.take().expect("message started");
+                message.extend_from_slice(&DEFLATE_TRAILER);
+                let inflated = inflate.inflate(&message)?;
+                write_frame_header(&mut self.output, opcode, inflated.len());
+                self.output.extend_from_slice(&inflated);
+            }
+        } else {
+            // Control frames, which may arrive between fragments, and uncompressed messages
+            self.output.extend_from_slice(&self.input[..frame.len()]);
+        }
+        self.input.drain(..frame.len());
+        Ok(true)
+    }
+}
+
+impl Inflate {
+    fn inflate(&mut self, message: &[u8]) -> io::Result<Vec<u8>> {
+        let mut inflated = Vec::with_capacity(message.len() * 4);
+        let mut consumed = 0;
+        loop {
+            let before = (self.decompress.total_in(), self.decompress.total_out());
+            let status = self
+                .decompress
+                .decompress_vec(&message[consumed..], &mut inflated, FlushDecompress::Sync)
+                .map_err(|e| invalid(&format!("inflate failed: {}", e)))?;
+            consumed += (self.decompress.total_in() - before.0) as usize;
+            let progressed = (self.decompress.total_in(), self.decompress.total_out()) != before;
+
+            if status == Status::StreamEnd
+                || (consumed == message.len() && inflated.len() < inflated.capacity())
+            {
+                break;
+            }
+            if inflated.len() > MAX_MESSAGE_BYTES {
+                return Err(invalid("inflated message too large"));
+            }
+            if !progressed && inflated.len() < inflated.capacity() {
+                return Err(invalid("truncated compressed message"));
+            }
+            inflated.reserve(inflated.capacity().max(4096));
+        }
+        if self.no_context_takeover {
+            self.decompress.reset(false);
+        }
+        Ok(inflated)
+    }
+}
+
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    masked: bool,
+    header_len: usize,
+    payload_len: usize,
+}
+
+impl FrameHeader {
+    fn len(&self) -> usize {
+        self.header_len + self.payload_len
+    }
+}
+
+/// Header of the frame at the start of `input`, once all of it has arrived
+fn parse_frame(input: &[u8]) -> io::Result<Option<FrameHeader>> {
+    let [first, second, ..] = *input else {
+        return Ok(None);
+    };
+    let masked = second & 0x80 != 0;
+    let (extended, payload_len) = match second & 0x7f {
+        126 if input.len() >= 4 => (2, u16::from_be_bytes([input[2], input[3]]) as usize),
+        127 if input.len() >= 10 => {
+            let len = u64::from_be_bytes(input[2..10].try_into().expect("8 bytes"));
+            (8, usize::try_from(len).unwrap_or(usize::MAX))
+        }
+        126 | 127 => return Ok(None),
+        len => (0, len as usize),
+    };
+    if payload_len > MAX_MESSAGE_BYTES {
+        return Err(invalid("frame too large"));
+    }
+    let header = FrameHeader {
+        fin: first & 0x80 != 0,
+        rsv1: first & 0x40 != 0,
+        opcode: first & 0x0f,
+        masked,
+        header_len: 2 + extended + if masked { 4 } else { 0 },
+        payload_len,
+    };
+    Ok((input.len() >= header.len()).then_some(header))
+}
+
+/// Header of an unmasked, final frame with no reserved bits set
+fn write_frame_header(output: &mut Vec<u8>, opcode: u8, payload_len: usize) {
+    output.push(0x80 | opcode);
+    match payload_len {
+        0..=125 => output.push(payload_len as u8),
+        126..=0xffff => {
+            output.push(126);
+            output.extend_from_slice(&(payload_len as u16).to_be_bytes());
+        }
+        _ => {
+            output.push(127);
+            output.extend_from_slice(&(payload_len as u64).to_be_bytes());
+        }
+    }
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("permessage-deflate: {}", what),
+    )
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for InflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.written < this.output.len() {
+                let pending = &this.output[this.written..];
+                let n = pending.len().min(buf.remaining());
+                buf.put_slice(&pending[..n]);
+                this.written += n;
+                if this.written == this.output.len() {
+                    this.output.clear();
+                    this.written = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.handshake {
+                if let Some(end) = find_header_end(&this.input) {
+                    this.output.extend(this.input.drain(..end));
+                    this.handshake = false;
+                    continue;
+                }
+            } else if this.inflate.is_none() {
+                if !this.input.is_empty() {
+                    this.output.append(&mut this.input);
+                    continue;
+                }
+                return Pin::new(&mut this.inner).poll_read(cx, buf);
+            } else if this.next_frame()? {
+                continue;
+            }
+
+            let mut read = ReadBuf::new(&mut this.scratch);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            if read.filled().is_empty() {
+                // End of stream; a partial frame is left to tungstenite to report
+                this.output.append(&mut this.input);
+                if this.output.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                continue;
+            }
+            this.input.extend_from_slice(read.filled());
+        }
+    }
+}
+
+/// Length of the HTTP header block at the start of `input`, including the blank line
+fn find_header_end(input: &[u8]) -> Option<usize> {
+    input
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|start| start + 4)
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for InflateStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,43 @@
+use shared::{AckTracker, EventAck};
+
+const MS: i64 = 1_000_000;
+
+#[test]
+fn round_trip_excludes_the_receiver_turnaround() {
+    // Receiver clock 5 ms ahead, 100 ms each way, 2 ms to answer
+    let ack = EventAck {
+        ack: 42,
+        sent: 1_000 * MS,
+        received: 1_105 * MS,
+        acked: 1_107 * MS,
+    };
+    let (rtt, offset) = ack.round_trip(1_202 * MS);
+    assert_eq!(rtt, 200 * MS);
+    assert_eq!(offset, 5 * MS);
+}
+
+#[test]
+fn tracker_summarizes_acks_and_counts_invalid_datagrams() {
+    let mut tracker = AckTracker::new();
+    assert!(tracker.stats().is_none());
+
+    for (i, one_way) in [100, 110, 120].into_iter().enumerate() {
+        let sent = i as i64 * 1_000 * MS;
+        let ack = EventAck {
+            ack: i as u64,
+            sent,
+            received: sent + one_way * MS,
+            acked: sent + one_way * MS,
+        };
+        let arrived = sent + 2 * one_way * MS;
+        assert_eq!(tracker.record(ack.to_json().as_bytes(), arrived), Some(ack));
+    }
+    assert_eq!(tracker.record(b"not an ack", 0), None);
+
+    let stats = tracker.stats().unwrap();
+    assert_eq!(stats.acks, 3);
+    assert_eq!(stats.invalid, 1);
+    assert!((stats.rtt.avg_ms - 220.0).abs() < 1e-9);
+    assert!((stats.offset.avg_ms).abs() < 1e-9);
+    assert!((stats.latest_rtt_ms - 240.0).abs() < 1e-9);
+}
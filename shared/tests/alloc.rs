@@ -0,0 +1,116 @@
+// Allocation regression tests for the hot paths. A counting allocator counts
+// allocations per thread, so tests running in parallel do not disturb each other.
+
+use latency_core::CsvWriter;
+use shared::{BufferPool, ForwardedEvent, LatencyMeasurement};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations made by `f` on this thread
+fn allocations(f: impl FnOnce()) -> u64 {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn pooled_buffers_are_reused() {
+    let pool = BufferPool::new(2048, 4);
+    let datagram = [b'x'; 1200];
+    // The first round allocates the buffers
+    drop((pool.get(), pool.get()));
+    assert_eq!(pool.free(), 2);
+
+    let count = allocations(|| {
+        for _ in 0..1000 {
+            let mut first = pool.get();
+            let mut second = pool.get();
+            first.extend_from_slice(&datagram);
+            second.extend_from_slice(&datagram);
+        }
+    });
+    assert_eq!(count, 0);
+    assert_eq!(pool.free(), 2);
+}
+
+#[test]
+fn oversized_buffers_are_not_pooled() {
+    let pool = BufferPool::new(2048, 4);
+    let mut buffer = pool.get();
+    buffer.resize(1 << 20, 0);
+    drop(buffer);
+    assert_eq!(pool.free(), 0);
+
+    drop(pool.adopt(vec![0; 100]));
+    assert_eq!(pool.free(), 1);
+    assert!(pool.get().is_empty());
+}
+
+#[test]
+fn serializing_into_a_reused_buffer_does_not_allocate() {
+    let event = ForwardedEvent {
+        binance_transaction_time: Some(1700000000122),
+        transport: Some(Cow::Borrowed("udp")),
+        run_id: Some(Cow::Borrowed("run-1")),
+        dscp: Some(46),
+        ..ForwardedEvent::new(
+            42,
+            1700000000150000000,
+            1700000000123,
+            r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","p":"64250.10"}"#.to_string(),
+        )
+    };
+    let mut serialized = Vec::new();
+    serde_json::to_writer(&mut serialized, &event).unwrap();
+
+    let count = allocations(|| {
+        for _ in 0..1000 {
+            serialized.clear();
+            serde_json::to_writer(&mut serialized, &event).unwrap();
+        }
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn csv_rows_do_not_allocate() {
+    let mut csv = CsvWriter::from_writer(std::io::sink());
+    let measurement = LatencyMeasurement::new_aws_backbone(
+        7,
+        1700000000123,
+        1700000000150000000,
+        1700000000250000000,
+    );
+    let count = allocations(|| {
+        for _ in 0..1000 {
+            csv.write(&measurement).unwrap();
+        }
+    });
+    assert_eq!(count, 0);
+}
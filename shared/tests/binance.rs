@@ -0,0 +1,160 @@
+use shared::{
+    BinanceMarketEvent, BinanceMarketEventView, BinanceStreamKind, ClockEstimate, ForwardedEvent,
+    ForwardedEventView,
+};
+use std::borrow::Cow;
+
+#[test]
+fn book_ticker_view_borrows_from_frame() {
+    let frame = br#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+    let event = BinanceMarketEventView::parse(frame).unwrap();
+
+    assert_eq!(event.kind(), BinanceStreamKind::BookTicker);
+    assert_eq!(event.event_time(), None);
+    let BinanceMarketEventView::BookTicker(ticker) = event else {
+        panic!("expected a book ticker");
+    };
+    assert!(matches!(ticker.symbol, Cow::Borrowed("BNBUSDT")));
+    assert!(matches!(
+        ticker.best_ask_price,
+        Cow::Borrowed("25.36520000")
+    ));
+}
+
+#[test]
+fn book_ticker_quote_is_typed() {
+    let frame = br#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+    let BinanceMarketEventView::BookTicker(ticker) = BinanceMarketEventView::parse(frame).unwrap()
+    else {
+        panic!("expected a book ticker");
+    };
+    let quote = ticker.quote().unwrap();
+
+    assert_eq!(quote.bid_price, 25.3519);
+    assert_eq!(quote.ask_qty, 40.66);
+    assert!((quote.mid_price() - 25.35855).abs() < 1e-9);
+    assert!((quote.spread() - 0.0133).abs() < 1e-9);
+    assert!((quote.spread_bps() - 5.2448).abs() < 1e-3);
+    // The raw strings are kept as received
+    assert_eq!(ticker.best_bid_price, "25.35190000");
+
+    let BinanceMarketEvent::BookTicker(owned) =
+        BinanceMarketEvent::parse(std::str::from_utf8(frame).unwrap()).unwrap()
+    else {
+        panic!("expected a book ticker");
+    };
+    assert_eq!(owned.quote().unwrap(), quote);
+}
+
+#[test]
+fn trade_price_is_typed() {
+    let text = r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":1,"p":"37000.10","q":"0.5","f":1,"l":1,"T":1700000000120,"m":false}"#;
+    let BinanceMarketEvent::AggTrade(event) = BinanceMarketEvent::parse(text).unwrap() else {
+        panic!("expected an aggTrade");
+    };
+    let trade = event.trade().unwrap();
+    assert_eq!(trade.price, 37000.1);
+    assert_eq!(trade.quantity, 0.5);
+    assert_eq!(trade.notional(), 18500.05);
+
+    let malformed = text.replace("37000.10", "n/a");
+    let BinanceMarketEventView::AggTrade(view) =
+        BinanceMarketEventView::parse(malformed.as_bytes()).unwrap()
+    else {
+        panic!("expected an aggTrade");
+    };
+    assert!(view.trade().is_err());
+}
+
+#[test]
+fn forwarded_view_skips_event_data() {
+    let frame = br#"{"sequence_id":7,"tokyo_receive_timestamp":1700000000150000000,"binance_event_time":1700000000123,"event_data":"{\"e\":\"aggTrade\"}"}"#;
+    let event = ForwardedEventView::parse(frame).unwrap();
+
+    assert_eq!(event.sequence_id, 7);
+    assert_eq!(event.binance_event_time, 1700000000123);
+    assert_eq!(event.binance_transaction_time, None);
+    assert!(!event.buffered);
+}
+
+#[test]
+fn buffered_flag_is_added_to_serialized_events() {
+    let event = ForwardedEvent {
+        transport: Some(Cow::Borrowed("tcp")),
+        run_id: Some(Cow::Borrowed("run-1")),
+        dscp: Some(46),
+        clock: Some(ClockEstimate {
+            offset_ns: -12_000,
+            error_bound_ns: 180_000,
+        }),
+        frame_bytes: Some(16),
+        ..ForwardedEvent::new(
+            7,
+            1700000000150000000,
+            1700000000123,
+            r#"{"e":"aggTrade"}"#.to_string(),
+        )
+    };
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(!json.contains("buffered"));
+
+    let marked = ForwardedEvent::mark_buffered(&json);
+    let view = ForwardedEventView::parse(marked.as_bytes()).unwrap();
+    assert!(view.buffered && !view.retransmitted);
+    assert_eq!(view.run_id, Some("run-1"));
+    assert_eq!(view.dscp, Some(46));
+    assert_eq!(view.clock, event.clock);
+    assert_eq!(view.frame_bytes, Some(16));
+    let parsed: ForwardedEvent = serde_json::from_str(&marked).unwrap();
+    assert!(parsed.buffered);
+    assert_eq!(parsed.event_data, event.event_data);
+
+    let retransmitted = ForwardedEvent::mark_retransmitted(&json);
+    let view = ForwardedEventView::parse(retransmitted.as_bytes()).unwrap();
+    assert!(view.retransmitted && !view.buffered);
+
+    assert_eq!(ForwardedEvent::mark_buffered("not json"), "not json");
+}
+
+#[test]
+fn overhead_is_appended_after_serializing() {
+    let frame = br#"{"sequence_id":7,"tokyo_receive_timestamp":1700000000150000000,"binance_event_time":1700000000123,"event_data":"{}"}"#;
+    let mut json = frame.to_vec();
+    ForwardedEvent::append_overhead(&mut json, 42_500);
+    let view = ForwardedEventView::parse(&json).unwrap();
+    assert_eq!(view.forwarding_overhead_ns, Some(42_500));
+    assert_eq!(view.sequence_id, 7);
+
+    // Appended later still, as the event leaves the send queue
+    ForwardedEvent::append_send_queue_delay(&mut json, 3_100_000);
+    let view = ForwardedEventView::parse(&json).unwrap();
+    assert_eq!(view.send_queue_delay_ns, Some(3_100_000));
+    assert_eq!(view.forwarding_overhead_ns, Some(42_500));
+
+    let view = ForwardedEventView::parse(frame).unwrap();
+    assert_eq!(view.forwarding_overhead_ns, None);
+}
+
+#[test]
+fn stream_shard_follows_the_envelope_stream_name() {
+    let frame = |stream: &str| {
+        format!(
+            r#"{{"sequence_id":7,"tokyo_receive_timestamp":1,"binance_event_time":1,"event_data":"{{\"stream\":\"ethusdt@trade\"}}","stream":"{}"}}"#,
+            stream
+        )
+    };
+    let shards: Vec<usize> = ["btcusdt@bookTicker", "ethusdt@bookTicker", "solusdt@trade"]
+        .iter()
+        .map(|stream| ForwardedEventView::stream_shard(frame(stream).as_bytes(), 4))
+        .collect();
+    assert!(shards.iter().all(|&shard| shard < 4));
+    // The same stream always lands on the same shard
+    assert_eq!(
+        ForwardedEventView::stream_shard(frame("btcusdt@bookTicker").as_bytes(), 4),
+        shards[0]
+    );
+    // Not the escaped name inside the payload
+    let payload_only = br#"{"sequence_id":7,"event_data":"{\"stream\":\"ethusdt@trade\"}"}"#;
+    assert_eq!(ForwardedEventView::stream_shard(payload_only, 4), 0);
+    assert_eq!(ForwardedEventView::stream_shard(b"{\"hello\":1}", 4), 0);
+}
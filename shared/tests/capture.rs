@@ -0,0 +1,85 @@
+use shared::{read_capture, CaptureWriter, CapturedFrame};
+use std::io::ErrorKind;
+
+fn temp_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("capture-{}-{}", std::process::id(), name));
+    path.to_str().unwrap().to_string()
+}
+
+/// Frames as the exchange sends them, including ones JSON has to escape
+fn frames() -> Vec<CapturedFrame> {
+    let texts = [
+        r#"{"e":"bookTicker","u":1,"E":1700000000123,"s":"BTCUSDT","b":"64250.10"}"#,
+        r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","m":true}}"#,
+        "{\"msg\":\"line\\nbreak, \\\"quotes\\\" and ünïcode\"}",
+        "",
+    ];
+    texts
+        .iter()
+        .enumerate()
+        .map(|(i, text)| CapturedFrame {
+            receive_time: 1_700_000_000_150_000_000 + i as i64 * 1_000,
+            text: text.to_string(),
+        })
+        .collect()
+}
+
+fn write(path: &str, frames: &[CapturedFrame]) {
+    let mut writer = CaptureWriter::create(path).unwrap();
+    for frame in frames {
+        writer.write(frame.receive_time, &frame.text).unwrap();
+    }
+    assert_eq!(writer.frames(), frames.len() as u64);
+    writer.finish().unwrap();
+}
+
+#[test]
+fn frames_round_trip() {
+    for name in ["plain.jsonl", "compressed.jsonl.gz"] {
+        let path = temp_path(name);
+        write(&path, &frames());
+        let read: Vec<CapturedFrame> = read_capture(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, frames(), "{}", name);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn truncated_final_record_is_an_error_after_the_complete_ones() {
+    let path = temp_path("truncated.jsonl");
+    write(&path, &frames());
+    // A recorder killed mid-write leaves part of its last line
+    let contents = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &contents[..contents.len() - 10]).unwrap();
+
+    let read: Vec<_> = read_capture(&path).unwrap().collect();
+    let complete = frames().len() - 1;
+    assert_eq!(read.len(), complete + 1);
+    for (read, frame) in read.iter().zip(frames()).take(complete) {
+        assert_eq!(read.as_ref().unwrap(), &frame);
+    }
+    assert_eq!(
+        read[complete].as_ref().unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn truncated_gzip_capture_is_an_error() {
+    let path = temp_path("truncated.jsonl.gz");
+    write(&path, &frames());
+    // Without its trailer the gzip stream ends early
+    let contents = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &contents[..contents.len() - 8]).unwrap();
+
+    let error = read_capture(&path)
+        .unwrap()
+        .find_map(Result::err)
+        .expect("a truncated gzip stream should fail");
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    std::fs::remove_file(&path).unwrap();
+}
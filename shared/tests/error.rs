@@ -0,0 +1,34 @@
+use shared::{ErrorKind, ExperimentError};
+use std::io;
+
+#[test]
+fn exit_codes_identify_the_kind() {
+    for kind in [
+        ErrorKind::Connect,
+        ErrorKind::Parse,
+        ErrorKind::Transport,
+        ErrorKind::Io,
+        ErrorKind::Clock,
+    ] {
+        assert_ne!(kind.exit_code(), 1);
+        assert_eq!(ErrorKind::from_exit_code(kind.exit_code()), Some(kind));
+    }
+    assert_eq!(ErrorKind::Other.exit_code(), 1);
+    assert_eq!(ErrorKind::from_exit_code(1), None);
+}
+
+#[test]
+fn network_failures_are_retryable() {
+    let parse = serde_json::from_str::<u64>("{").unwrap_err();
+    assert_eq!(ExperimentError::from(parse).kind(), ErrorKind::Parse);
+    assert!(!ExperimentError::parse("bad frame").is_retryable());
+
+    assert!(ExperimentError::connect("refused").is_retryable());
+    assert!(ExperimentError::transport("reset").is_retryable());
+
+    let reset = ExperimentError::from(io::Error::from(io::ErrorKind::ConnectionReset));
+    assert!(reset.is_retryable());
+    let missing = ExperimentError::from(io::Error::from(io::ErrorKind::NotFound));
+    assert!(!missing.is_retryable());
+    assert_eq!(missing.exit_code(), ErrorKind::Io.exit_code());
+}
@@ -0,0 +1,101 @@
+use shared::{
+    exchange_adapter, split_combined_stream, Binance, BinanceFastParse, Bybit, ExchangeAdapter, Okx,
+};
+
+#[test]
+fn binance_aggtrade_is_normalized() {
+    let text = r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":1,"p":"37000.10","q":"0.5","f":1,"l":1,"T":1700000000120,"m":false}"#;
+    let event = Binance.parse(text).unwrap().unwrap();
+
+    assert_eq!(event.exchange, "binance");
+    assert_eq!(event.symbol, "BTCUSDT");
+    assert_eq!(event.event_time, Some(1700000000123));
+    assert_eq!(event.update_id, Some(1));
+    assert_eq!(event.price.as_deref(), Some("37000.10"));
+    assert_eq!(event.last_price(), Some(37000.1));
+    assert_eq!(event.mid_price(), None);
+    assert_eq!(
+        Binance.stream_url("BTC-USDT"),
+        "wss://stream.binance.com:9443/ws/btcusdt@aggTrade"
+    );
+}
+
+#[test]
+fn binance_futures_book_ticker_is_normalized() {
+    // wss://fstream.binance.com/ws/btcusdt@bookTicker; spot frames lack e, E and T
+    let text = r#"{"e":"bookTicker","u":400900217,"E":1700000000123,"T":1700000000121,"s":"BTCUSDT","b":"37000.10","B":"31.21","a":"37000.20","A":"40.66"}"#;
+    let event = Binance.parse(text).unwrap().unwrap();
+
+    assert_eq!(event.event_time, Some(1700000000123));
+    assert_eq!(event.transaction_time, Some(1700000000121));
+    assert_eq!(event.update_id, Some(400900217));
+    assert_eq!(event.best_bid_price.as_deref(), Some("37000.10"));
+    assert_eq!(event.best_bid(), Some(37000.1));
+    assert_eq!(event.best_ask(), Some(37000.2));
+    assert!((event.mid_price().unwrap() - 37000.15).abs() < 1e-9);
+    assert!((event.spread_bps().unwrap() - 0.0270).abs() < 1e-4);
+    assert_eq!(event.last_price(), None);
+
+    let spot =
+        r#"{"u":400900217,"s":"BTCUSDT","b":"37000.10","B":"31.21","a":"37000.20","A":"40.66"}"#;
+    assert_eq!(Binance.parse(spot).unwrap().unwrap().event_time, None);
+}
+
+#[test]
+fn okx_uses_newest_trade_and_skips_acks() {
+    let ack =
+        r#"{"event":"subscribe","arg":{"channel":"trades","instId":"BTC-USDT"},"connId":"a1"}"#;
+    assert_eq!(Okx.parse(ack).unwrap(), None);
+    assert_eq!(Okx.parse("pong").unwrap(), None);
+
+    let text = r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[
+        {"instId":"BTC-USDT","tradeId":"1","px":"37000.1","sz":"0.1","side":"buy","ts":"1700000000100"},
+        {"instId":"BTC-USDT","tradeId":"2","px":"37000.2","sz":"0.1","side":"buy","ts":"1700000000200"}]}"#;
+    let event = Okx.parse(text).unwrap().unwrap();
+    assert_eq!(event.event_time, Some(1700000000200));
+    assert_eq!(event.price.as_deref(), Some("37000.2"));
+
+    let error = r#"{"event":"error","code":"60012","msg":"Invalid request"}"#;
+    assert!(Okx.parse(error).is_err());
+
+    let subscribe = Okx.subscribe_message("btc/usdt").unwrap();
+    assert!(subscribe.contains(r#""instId":"BTC-USDT""#));
+}
+
+#[test]
+fn bybit_uses_message_time() {
+    let ack = r#"{"success":true,"ret_msg":"subscribe","conn_id":"c1","op":"subscribe"}"#;
+    assert_eq!(Bybit.parse(ack).unwrap(), None);
+
+    let text = r#"{"topic":"publicTrade.BTCUSDT","ts":1700000000300,"type":"snapshot","data":[
+        {"T":1700000000298,"s":"BTCUSDT","S":"Buy","v":"0.001","p":"37000.3","L":"PlusTick","i":"x","BT":false}]}"#;
+    let event = Bybit.parse(text).unwrap().unwrap();
+    assert_eq!(event.exchange, "bybit");
+    assert_eq!(event.event_time, Some(1700000000300));
+    assert_eq!(
+        Bybit.subscribe_message("BTC-USDT").unwrap(),
+        r#"{"args":["publicTrade.BTCUSDT"],"op":"subscribe"}"#
+    );
+}
+
+#[test]
+fn adapters_are_looked_up_by_name() {
+    assert_eq!(exchange_adapter("OKX").unwrap().name(), "okx");
+    assert!(exchange_adapter("kraken").is_none());
+}
+
+#[test]
+fn binance_combined_stream_is_unwrapped() {
+    let text = r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":7,"p":"37000.10","q":"0.5","f":1,"l":1,"T":1700000000120,"m":false}}"#;
+    for adapter in [&Binance as &dyn ExchangeAdapter, &BinanceFastParse] {
+        let event = adapter.parse(text).unwrap().unwrap();
+        assert_eq!(event.stream, Some("btcusdt@aggTrade"));
+        assert_eq!(event.symbol, "BTCUSDT");
+        assert_eq!(event.event_time, Some(1700000000123));
+        assert_eq!(event.update_id, Some(7));
+    }
+
+    let raw = r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":7,"p":"37000.10","q":"0.5","f":1,"l":1,"T":1700000000120,"m":false}"#;
+    assert_eq!(split_combined_stream(raw), None);
+    assert_eq!(Binance.parse(raw).unwrap().unwrap().stream, None);
+}
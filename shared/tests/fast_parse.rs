@@ -0,0 +1,48 @@
+use shared::{extract_binance_fields, Binance, BinanceFastParse, BinanceFields, ExchangeAdapter};
+
+#[test]
+fn extracts_binance_fields() {
+    let trade = r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":1,"p":"37000.10","q":"0.5","f":1,"l":1,"T":1700000000120,"m":false,"M":true}"#;
+    assert_eq!(
+        extract_binance_fields(trade),
+        Some(BinanceFields {
+            event_time: Some(1700000000123),
+            transaction_time: Some(1700000000120),
+            update_id: Some(1),
+            symbol: Some("BTCUSDT"),
+        })
+    );
+
+    let book =
+        r#"{ "u" : 400900217, "s" : "BTCUSDT", "b" : "1.0", "B" : "2", "a" : "1.1", "A" : "3" }"#;
+    let fields = extract_binance_fields(book).unwrap();
+    assert_eq!(fields.update_id, Some(400900217));
+    assert_eq!(fields.event_time, None);
+}
+
+#[test]
+fn falls_back_to_the_full_parser() {
+    // Nested, escaped, other event types and malformed frames are not extracted
+    for frame in [
+        r#"{"stream":"btcusdt@aggTrade","data":{"e":"aggTrade","E":1}}"#,
+        r#"{"e":"aggTrade","E":1,"s":"BTC\"USDT"}"#,
+        r#"{"e":"24hrTicker","E":1,"s":"BTCUSDT"}"#,
+        r#"{"e":"aggTrade","E":1"#,
+        "not json",
+    ] {
+        assert_eq!(extract_binance_fields(frame), None, "{}", frame);
+    }
+
+    // Both paths agree on what the forwarder uses
+    let trade = r#"{"e":"trade","E":1700000000123,"s":"BTCUSDT","t":1,"p":"1","q":"1","T":1700000000120,"m":true,"M":true}"#;
+    let fast = BinanceFastParse.parse(trade).unwrap().unwrap();
+    let full = Binance.parse(trade).unwrap().unwrap();
+    assert_eq!(fast.event_time, full.event_time);
+    assert_eq!(fast.update_id, full.update_id);
+    assert_eq!(fast.transaction_time, full.transaction_time);
+    assert_eq!(fast.symbol, full.symbol);
+
+    assert!(BinanceFastParse
+        .parse(r#"{"e":"depthUpdate","E":1,"s":"BTCUSDT"}"#)
+        .is_err());
+}
@@ -0,0 +1,72 @@
+use shared::{fragment, Datagram, Reassembler, FRAGMENT_HEADER_LEN};
+use std::time::Duration;
+
+const MS: i64 = 1_000_000;
+
+#[test]
+fn small_events_are_not_fragmented() {
+    let json = br#"{"sequence_id":1}"#;
+    assert_eq!(fragment(1, json, 1400), None);
+
+    let mut reassembler = Reassembler::new(Duration::from_secs(1));
+    assert_eq!(reassembler.push(json, 0), Datagram::Whole(&json[..]));
+    assert_eq!(reassembler.finish().frames, 0);
+}
+
+#[test]
+fn fragments_are_reassembled_in_any_order() {
+    let payload: Vec<u8> = (0..1000u32).map(|i| b'a' + (i % 26) as u8).collect();
+    let fragments = fragment(42, &payload, 300).unwrap();
+    assert_eq!(fragments.len(), 4);
+    assert!(fragments.iter().all(|f| f.len() <= 300));
+    assert_eq!(fragments[0].len(), 300);
+    assert_eq!(fragments[3].len(), FRAGMENT_HEADER_LEN + 1000 - 3 * 286);
+
+    let mut reassembler = Reassembler::new(Duration::from_secs(1));
+    for (i, index) in [2, 0, 3].into_iter().enumerate() {
+        assert_eq!(
+            reassembler.push(&fragments[index], i as i64 * MS),
+            Datagram::Pending
+        );
+    }
+    // A repeat of a fragment already held does not complete the frame
+    assert_eq!(reassembler.push(&fragments[0], 3 * MS), Datagram::Pending);
+    assert_eq!(
+        reassembler.push(&fragments[1], 4 * MS),
+        Datagram::Reassembled(payload)
+    );
+    // Nor does one arriving after the frame was delivered
+    assert_eq!(reassembler.push(&fragments[1], 5 * MS), Datagram::Pending);
+
+    let stats = reassembler.finish();
+    assert_eq!(
+        (stats.frames, stats.reassembled, stats.incomplete),
+        (1, 1, 0)
+    );
+    assert_eq!((stats.fragments, stats.duplicate_fragments), (6, 2));
+}
+
+#[test]
+fn incomplete_frames_expire_and_count_lost_fragments() {
+    let payload = vec![b'x'; 900];
+    let first = fragment(1, &payload, 300).unwrap();
+    let second = fragment(2, &payload, 300).unwrap();
+
+    let mut reassembler = Reassembler::new(Duration::from_millis(100));
+    reassembler.push(&first[0], 0);
+    // Frame 1 is dropped once a datagram arrives after its timeout
+    reassembler.push(&second[0], 150 * MS);
+    assert_eq!(reassembler.stats().incomplete, 1);
+    assert_eq!(reassembler.stats().lost_fragments, 3);
+    // Its missing fragments arriving late are not a new frame
+    assert_eq!(reassembler.push(&first[1], 160 * MS), Datagram::Pending);
+
+    reassembler.push(&second[1], 170 * MS);
+    let stats = reassembler.finish();
+    assert_eq!(
+        (stats.frames, stats.reassembled, stats.incomplete),
+        (2, 0, 2)
+    );
+    assert_eq!(stats.lost_fragments, 3 + 2);
+    assert_eq!(stats.duplicate_fragments, 1);
+}
@@ -0,0 +1,28 @@
+use shared::grpc::{ForwardedEvent, GRPC_PREFIX_LEN, HTTP2_FRAME_HEADER_LEN, HTTP2_MAX_FRAME_SIZE};
+
+#[test]
+fn small_event_fits_one_data_frame() {
+    let json = r#"{"sequence_id":7}"#;
+    let message = ForwardedEvent::wrap(7, json);
+
+    // Two field tags, the sequence ID and the JSON length, one byte each
+    let protobuf = 4;
+    assert_eq!(
+        message.framing_bytes(),
+        protobuf + GRPC_PREFIX_LEN + HTTP2_FRAME_HEADER_LEN
+    );
+    assert_eq!(message.wire_bytes(), json.len() + message.framing_bytes());
+}
+
+#[test]
+fn large_event_spans_several_data_frames() {
+    let json = "x".repeat(2 * HTTP2_MAX_FRAME_SIZE);
+    let message = ForwardedEvent::wrap(1, &json);
+
+    // Tags, the sequence ID and a three-byte length: 3 frames' worth of headers
+    let protobuf = 1 + 1 + 1 + 3;
+    assert_eq!(
+        message.framing_bytes(),
+        protobuf + GRPC_PREFIX_LEN + 3 * HTTP2_FRAME_HEADER_LEN
+    );
+}
@@ -0,0 +1,40 @@
+use shared::{ForwardedEvent, ForwarderHello, WIRE_FORMAT_VERSION};
+
+fn hello() -> ForwarderHello {
+    ForwarderHello {
+        forwarder_id: Some("tokyo-a".to_string()),
+        region: "tokyo".to_string(),
+        streams: vec!["btcusdt@aggTrade".to_string()],
+        wire_format: WIRE_FORMAT_VERSION,
+        version: "0.1.0".to_string(),
+        run_id: Some("run-1".to_string()),
+    }
+}
+
+#[test]
+fn hello_round_trips_through_its_wire_form() {
+    let json = hello().to_json();
+    assert!(json.starts_with("{\"hello\":"));
+    assert_eq!(
+        ForwarderHello::parse(json.as_bytes()).unwrap().unwrap(),
+        hello()
+    );
+    assert!(hello().check_compatible().is_ok());
+}
+
+#[test]
+fn events_are_not_mistaken_for_a_hello() {
+    let event = ForwardedEvent::new(1, 2, 3, "{}".to_string());
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(ForwarderHello::parse(json.as_bytes()).is_none());
+    assert!(ForwarderHello::parse(br#"{"hello":42}"#).unwrap().is_err());
+}
+
+#[test]
+fn another_wire_format_is_incompatible() {
+    let hello = ForwarderHello {
+        wire_format: WIRE_FORMAT_VERSION + 1,
+        ..hello()
+    };
+    assert!(hello.check_compatible().is_err());
+}
@@ -0,0 +1,50 @@
+use shared::{
+    heartbeat_ack_line, heartbeat_line, parse_heartbeat, parse_heartbeat_ack, ForwarderSupervision,
+    Heartbeat,
+};
+
+#[test]
+fn heartbeats_and_acks_round_trip() {
+    assert_eq!(
+        parse_heartbeat(heartbeat_line(42, None).as_bytes()),
+        Some(Heartbeat {
+            id: 42,
+            supervision: None
+        })
+    );
+    assert_eq!(
+        parse_heartbeat_ack(heartbeat_ack_line(42).as_bytes()),
+        Some(42)
+    );
+}
+
+#[test]
+fn heartbeats_carry_the_restart_history() {
+    let supervision = ForwarderSupervision {
+        process_starts: 2,
+        restarts: 3,
+        panics: 1,
+        last_error: Some("exchange connection closed".to_string()),
+        session_uptime_secs: 12.5,
+        events_forwarded: 1_000,
+    };
+    let line = heartbeat_line(7, Some(supervision.clone()));
+    assert!(line.starts_with(r#"{"heartbeat":7,"#), "{}", line);
+    let heartbeat = parse_heartbeat(line.as_bytes()).unwrap();
+    assert_eq!(heartbeat.id, 7);
+    assert_eq!(heartbeat.supervision, Some(supervision));
+}
+
+#[test]
+fn events_are_not_heartbeats() {
+    let event = br#"{"sequence_id":1,"tokyo_receive_timestamp":1}"#;
+    assert_eq!(parse_heartbeat(event), None);
+    assert_eq!(parse_heartbeat(br#"{"hello":{}}"#), None);
+    assert_eq!(parse_heartbeat(br#"{"heartbeat":x}"#), None);
+    // An acknowledgement is not a heartbeat, nor the other way round
+    assert_eq!(parse_heartbeat(heartbeat_ack_line(1).as_bytes()), None);
+    assert_eq!(
+        parse_heartbeat_ack(heartbeat_line(1, None).as_bytes()),
+        None
+    );
+}
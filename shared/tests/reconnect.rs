@@ -0,0 +1,63 @@
+use shared::{Backoff, ReconnectPolicy, ReconnectStats};
+use std::time::Duration;
+
+fn policy() -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(8),
+        jitter: 0.0,
+        max_attempts: None,
+        breaker_after: 0,
+        breaker_cooldown: Duration::from_secs(60),
+    }
+}
+
+#[test]
+fn doubles_up_to_max_delay_and_resets() {
+    let mut backoff = Backoff::new(policy());
+    let delays: Vec<u64> = (0..5)
+        .map(|_| backoff.next_delay().unwrap().as_secs())
+        .collect();
+    assert_eq!(delays, [1, 2, 4, 8, 8]);
+
+    backoff.succeeded();
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(1)));
+}
+
+#[test]
+fn jitter_stays_within_the_randomized_fraction() {
+    let mut backoff = Backoff::new(ReconnectPolicy {
+        jitter: 0.5,
+        ..policy()
+    });
+    for _ in 0..100 {
+        let delay = backoff.next_delay().unwrap();
+        assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+        backoff.succeeded();
+    }
+}
+
+#[test]
+fn breaker_opens_and_attempts_run_out() {
+    let mut backoff = Backoff::new(ReconnectPolicy {
+        max_attempts: Some(4),
+        breaker_after: 3,
+        ..policy()
+    });
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(1)));
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(2)));
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(60)));
+    assert!(backoff.breaker_open());
+    assert_eq!(backoff.next_delay(), Some(Duration::from_secs(8)));
+    assert_eq!(backoff.next_delay(), None);
+
+    backoff.succeeded();
+    assert_eq!(
+        backoff.stats(),
+        ReconnectStats {
+            failures: 5,
+            recoveries: 1,
+            breaker_trips: 1,
+        }
+    );
+}
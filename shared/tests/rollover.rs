@@ -0,0 +1,59 @@
+use shared::{default_rollover, parse_rollover, CurrentFrame, HandoverReason, Rollover};
+use std::time::Duration;
+
+fn rollover() -> Rollover {
+    let mut rollover = Rollover::new("binance", Some(Duration::from_secs(3600)));
+    assert!(rollover.standby_due().is_some());
+    rollover.standby_opened();
+    assert!(rollover.standby_due().is_none());
+    rollover
+}
+
+#[test]
+fn standby_takes_over_once_it_catches_up() {
+    let mut rollover = rollover();
+    assert_eq!(rollover.current_frame("a", 100), CurrentFrame::Pass);
+    assert_eq!(rollover.current_frame("b", 200), CurrentFrame::Pass);
+
+    // The standby's copy of "a" shows it is live and behind by one frame
+    assert_eq!(
+        rollover.standby_frame("a".to_string(), 250),
+        Some(Vec::new())
+    );
+    assert_eq!(rollover.current_frame("b", 260), CurrentFrame::Skip);
+    assert_eq!(rollover.current_frame("c", 300), CurrentFrame::Pass);
+
+    let handover = rollover.handovers()[0];
+    assert_eq!(handover.reason, HandoverReason::Rollover);
+    assert_eq!(handover.duplicates_skipped, 2);
+    assert_eq!(handover.gap_ms, 50.0 / 1e6);
+    assert!(rollover.standby_due().is_some());
+}
+
+#[test]
+fn frames_the_standby_delivers_first_are_held() {
+    let mut rollover = rollover();
+    assert_eq!(rollover.standby_frame("b".to_string(), 150), None);
+    assert_eq!(rollover.standby_frame("c".to_string(), 160), None);
+    assert_eq!(rollover.current_frame("a", 100), CurrentFrame::Pass);
+    assert_eq!(
+        rollover.current_frame("b", 200),
+        CurrentFrame::PassThenSwitch(vec![("c".to_string(), 160)])
+    );
+    assert_eq!(rollover.current_frame("c", 160), CurrentFrame::Pass);
+    assert_eq!(rollover.current_frame("d", 300), CurrentFrame::Pass);
+    assert_eq!(rollover.handovers().len(), 1);
+}
+
+#[test]
+fn only_binance_resets_at_the_limit() {
+    assert!(default_rollover("binance").is_some());
+    assert!(default_rollover("okx").is_none());
+    assert_eq!(parse_rollover("off"), Ok(None));
+    assert_eq!(parse_rollover("90m"), Ok(Some(Duration::from_secs(5400))));
+
+    // A fresh connection closing is an ordinary disconnect
+    let mut rollover = Rollover::new("binance", None);
+    assert!(!rollover.closed());
+    assert!(rollover.standby_due().is_none());
+}
@@ -0,0 +1,89 @@
+use shared::{
+    parse_interval, parse_size, read_capture, CaptureWriter, RotatingFile, RotationPolicy,
+};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rotate-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn files(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn parses_sizes_and_intervals() {
+    assert_eq!(parse_size("500MB"), Ok(500 << 20));
+    assert_eq!(parse_size("2g"), Ok(2 << 30));
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert!(parse_size("0").is_err());
+    assert!(parse_size("5 parsecs").is_err());
+
+    assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+    assert_eq!(parse_interval("30m"), Ok(Duration::from_secs(1800)));
+    assert_eq!(parse_interval("3600"), Ok(Duration::from_secs(3600)));
+    assert!(parse_interval("soon").is_err());
+}
+
+#[test]
+fn rotates_by_size_with_a_header_per_file() {
+    let dir = temp_dir("size");
+    let policy = RotationPolicy {
+        max_bytes: Some(20),
+        ..RotationPolicy::default()
+    };
+    let mut file = RotatingFile::create(dir.join("rows.csv"), policy, b"a,b\n").unwrap();
+    for row in ["1,2\n", "3,4\n", "5,6\n", "7,8\n", "9,0\n"] {
+        file.rotate_if_due().unwrap();
+        file.write_all(row.as_bytes()).unwrap();
+    }
+    file.finish().unwrap();
+
+    let names = files(&dir);
+    assert_eq!(names.len(), 2, "{:?}", names);
+    assert!(names
+        .iter()
+        .all(|n| n.starts_with("rows-") && n.ends_with(".csv")));
+    let mut contents: Vec<String> = names
+        .iter()
+        .map(|n| std::fs::read_to_string(dir.join(n)).unwrap())
+        .collect();
+    contents.sort();
+    assert_eq!(contents, ["a,b\n1,2\n3,4\n5,6\n7,8\n", "a,b\n9,0\n"]);
+}
+
+#[test]
+fn rotated_captures_are_compressed_and_readable() {
+    let dir = temp_dir("capture");
+    let policy = RotationPolicy {
+        max_bytes: Some(1),
+        ..RotationPolicy::default()
+    };
+    let path = dir.join("raw.jsonl.gz");
+    let mut capture = CaptureWriter::create_rotating(path.to_str().unwrap(), policy).unwrap();
+    capture.write(1, r#"{"E":1}"#).unwrap();
+    capture.write(2, r#"{"E":2}"#).unwrap();
+    capture.finish().unwrap();
+
+    let names = files(&dir);
+    assert_eq!(names.len(), 2, "{:?}", names);
+    let mut times = Vec::new();
+    for name in &names {
+        assert!(name.starts_with("raw-") && name.ends_with(".jsonl.gz"));
+        for frame in read_capture(dir.join(name).to_str().unwrap()).unwrap() {
+            times.push(frame.unwrap().receive_time);
+        }
+    }
+    times.sort();
+    assert_eq!(times, [1, 2]);
+}
@@ -0,0 +1,28 @@
+use shared::{new_run_id, validate_run_id, MAX_RUN_ID_LEN};
+
+#[test]
+fn generated_run_ids_are_version_4_uuids() {
+    let id = new_run_id();
+    assert_eq!(id.len(), 36);
+    let groups: Vec<&str> = id.split('-').collect();
+    assert_eq!(
+        groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+        [8, 4, 4, 4, 12]
+    );
+    assert!(groups[2].starts_with('4'));
+    assert!(matches!(
+        groups[3].chars().next(),
+        Some('8' | '9' | 'a' | 'b')
+    ));
+    assert!(validate_run_id(&id).is_ok());
+    assert_ne!(id, new_run_id());
+}
+
+#[test]
+fn run_ids_are_restricted_to_key_safe_characters() {
+    assert!(validate_run_id("2024-01-08_tokyo.1").is_ok());
+    assert!(validate_run_id("").is_err());
+    assert!(validate_run_id("run/1").is_err());
+    assert!(validate_run_id("run \"1\"").is_err());
+    assert!(validate_run_id(&"x".repeat(MAX_RUN_ID_LEN + 1)).is_err());
+}
@@ -0,0 +1,55 @@
+use shared::{output_files, S3Destination};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn destination_keys_files_by_run() {
+    let s3: S3Destination = "s3://latency-results/experiments".parse().unwrap();
+    assert_eq!(s3.bucket, "latency-results");
+    assert_eq!(s3.prefix, "experiments/");
+    assert_eq!(
+        s3.url("run-1", Path::new("/home/ec2-user/results.json")),
+        "s3://latency-results/experiments/run-1/results.json"
+    );
+
+    let bare: S3Destination = "s3://latency-results/".parse().unwrap();
+    assert_eq!(
+        bare.url("run-1", Path::new("a.csv")),
+        "s3://latency-results/run-1/a.csv"
+    );
+
+    assert!("latency-results/experiments"
+        .parse::<S3Destination>()
+        .is_err());
+    assert!("s3:///experiments".parse::<S3Destination>().is_err());
+}
+
+#[test]
+fn rotated_output_files_are_found() {
+    let dir = std::env::temp_dir().join(format!("s3-output-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let before = SystemTime::now() - Duration::from_secs(5);
+    for name in [
+        "capture-20240108T120000Z.jsonl.gz",
+        "capture-20240108T130000Z.jsonl",
+        "other.jsonl",
+    ] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    let base = dir.join("capture.jsonl");
+    let files = output_files(base.to_str().unwrap(), before);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let names: Vec<String> = files
+        .iter()
+        .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "capture-20240108T120000Z.jsonl.gz",
+            "capture-20240108T130000Z.jsonl",
+            "capture.jsonl"
+        ]
+    );
+}
@@ -0,0 +1,305 @@
+// Deterministic simulation of forwarded event streams
+//
+// Events are generated with latencies drawn from known distributions, known
+// loss patterns and injected clock skew, sent through the same wire format as
+// the forwarder, and measured the way the receiver measures them. The results
+// must reproduce the statistics the simulation was built with.
+
+use latency_core::{Collector, ExperimentResults, LatencyMeasurement};
+use shared::{ForwardedEvent, ForwardedEventView};
+
+const START_MS: i64 = 1_700_000_000_000; // Exchange time of the first event
+const AGG_TRADE: &str =
+    r#"{"e":"aggTrade","E":1700000000000,"s":"BTCUSDT","p":"64250.10","q":"0.012"}"#;
+
+/// SplitMix64: small, fast and the same on every platform
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Distribution of one leg's latency, in ms
+#[derive(Clone, Copy)]
+enum Latency {
+    Fixed(f64),
+    Uniform { min: f64, max: f64 },
+    Exponential { offset: f64, mean: f64 }, // Offset plus an exponential tail
+}
+
+impl Latency {
+    fn sample(self, rng: &mut Rng) -> f64 {
+        match self {
+            Latency::Fixed(ms) => ms,
+            Latency::Uniform { min, max } => min + (max - min) * rng.uniform(),
+            Latency::Exponential { offset, mean } => offset - mean * (1.0 - rng.uniform()).ln(),
+        }
+    }
+}
+
+/// Which events never arrive
+#[derive(Clone, Copy)]
+enum Loss {
+    None,
+    EveryNth(u64),                    // Sequence IDs n-1, 2n-1, ...
+    Bursts { every: u64, size: u64 }, // `size` consecutive events from every `every`th
+    Tail(u64),                        // The last events of the run
+}
+
+impl Loss {
+    fn drops(self, sequence_id: u64, events: u64) -> bool {
+        match self {
+            Loss::None => false,
+            Loss::EveryNth(n) => sequence_id % n == n - 1,
+            Loss::Bursts { every, size } => sequence_id % every >= every - size,
+            Loss::Tail(count) => sequence_id >= events - count,
+        }
+    }
+}
+
+struct Simulation {
+    events: u64,
+    interval_ms: i64,      // Between exchange events
+    to_tokyo: Latency,     // Exchange → forwarder
+    backbone: Latency,     // Forwarder → receiver
+    tokyo_skew_ms: f64,    // Forwarder clock ahead of true time
+    receiver_skew_ms: f64, // Receiver clock ahead of true time
+    loss: Loss,
+    seed: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            events: 10_000,
+            interval_ms: 10,
+            to_tokyo: Latency::Fixed(3.0),
+            backbone: Latency::Fixed(110.0),
+            tokyo_skew_ms: 0.0,
+            receiver_skew_ms: 0.0,
+            loss: Loss::None,
+            seed: 1,
+        }
+    }
+}
+
+impl Simulation {
+    /// Measurements as the receiver records them, in arrival order
+    fn measurements(&self) -> Vec<LatencyMeasurement> {
+        let mut rng = Rng(self.seed);
+        let mut measurements = Vec::new();
+        for sequence_id in 0..self.events {
+            // Epoch nanos do not fit an f64 exactly; only the latencies are drawn as floats
+            let event_time = START_MS + sequence_id as i64 * self.interval_ms;
+            let at_tokyo = event_time * 1_000_000 + nanos(self.to_tokyo.sample(&mut rng));
+            let at_receiver = at_tokyo + nanos(self.backbone.sample(&mut rng));
+            if self.loss.drops(sequence_id, self.events) {
+                continue;
+            }
+
+            let event = ForwardedEvent::new(
+                sequence_id,
+                at_tokyo + nanos(self.tokyo_skew_ms),
+                event_time,
+                AGG_TRADE.to_string(),
+            );
+            let frame = serde_json::to_vec(&event).unwrap();
+            let view = ForwardedEventView::parse(&frame).unwrap();
+            measurements.push(LatencyMeasurement::new_aws_backbone(
+                view.sequence_id,
+                view.binance_event_time,
+                view.tokyo_receive_timestamp,
+                at_receiver + nanos(self.receiver_skew_ms),
+            ));
+        }
+        measurements
+    }
+
+    fn run(&self) -> ExperimentResults {
+        Collector::new()
+            .replay("aws-backbone", self.measurements())
+            .results
+    }
+}
+
+fn nanos(ms: f64) -> i64 {
+    (ms * 1_000_000.0).round() as i64
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn fixed_offsets_give_exact_statistics() {
+    let results = Simulation::default().run();
+
+    assert_eq!(results.sample_count, 10_000);
+    assert_eq!(results.events_lost, 0);
+    assert!(results.gaps.is_empty());
+    for p in ["p50", "p90", "p95", "p99", "p99.9"] {
+        assert_close(results.percentiles[p], 113.0, 1e-6);
+    }
+    assert_close(results.min_latency_ms, 113.0, 1e-6);
+    assert_close(results.max_latency_ms, 113.0, 1e-6);
+    assert_close(results.jitter_stddev_ms, 0.0, 1e-6);
+    assert_close(results.backbone_avg_latency_ms.unwrap(), 110.0, 1e-6);
+    assert_close(results.backbone_median_latency_ms.unwrap(), 110.0, 1e-6);
+    assert_close(
+        results.backbone_percentiles_us.unwrap()["p99"],
+        110_000.0,
+        1e-3,
+    );
+}
+
+#[test]
+fn uniform_latency_reproduces_percentiles() {
+    let results = Simulation {
+        events: 20_000,
+        backbone: Latency::Uniform {
+            min: 100.0,
+            max: 200.0,
+        },
+        ..Simulation::default()
+    }
+    .run();
+
+    // Latency spread is much wider than the event interval, so events arrive
+    // out of order; that is reordering, not loss
+    assert!(results.reordered > 0);
+    assert_eq!(results.events_lost, 0);
+
+    assert_close(results.avg_latency_ms, 153.0, 1.0);
+    assert_close(results.percentiles["p50"], 153.0, 1.5);
+    assert_close(results.percentiles["p90"], 193.0, 1.0);
+    assert_close(results.percentiles["p99"], 202.0, 0.5);
+    assert!(results.min_latency_ms >= 103.0 && results.max_latency_ms < 203.0);
+    assert_close(results.jitter_stddev_ms, 100.0 / 12f64.sqrt(), 0.5);
+    assert_close(results.backbone_median_latency_ms.unwrap(), 150.0, 1.5);
+}
+
+#[test]
+fn exponential_tail_reproduces_p99() {
+    let results = Simulation {
+        events: 50_000,
+        backbone: Latency::Exponential {
+            offset: 100.0,
+            mean: 10.0,
+        },
+        seed: 7,
+        ..Simulation::default()
+    }
+    .run();
+
+    // Quantiles of an exponential tail: offset − mean × ln(1 − q)
+    let quantile = |q: f64| 103.0 - 10.0 * (1.0 - q).ln();
+    assert_close(results.percentiles["p50"], quantile(0.50), 0.3);
+    assert_close(results.percentiles["p99"], quantile(0.99), 1.5);
+    assert_close(results.avg_latency_ms, 113.0, 0.3);
+}
+
+#[test]
+fn periodic_loss_is_counted_exactly() {
+    // One event past the last loss, which only shows once a later event arrives
+    let results = Simulation {
+        events: 10_001,
+        loss: Loss::EveryNth(100),
+        ..Simulation::default()
+    }
+    .run();
+
+    assert_eq!(results.sample_count, 9_901);
+    assert_eq!(results.events_lost, 100);
+    assert_eq!(results.gaps.len(), 100);
+    assert!(results.gaps.iter().all(|gap| gap.size == 1));
+    assert_eq!(results.gaps[0].first_missing, 99);
+}
+
+#[test]
+fn burst_loss_is_counted_per_gap() {
+    let results = Simulation {
+        events: 10_001,
+        loss: Loss::Bursts {
+            every: 1_000,
+            size: 5,
+        },
+        ..Simulation::default()
+    }
+    .run();
+
+    assert_eq!(results.events_lost, 50);
+    assert_eq!(results.gaps.len(), 10);
+    assert!(results.gaps.iter().all(|gap| gap.size == 5));
+    assert_eq!(results.gaps[0].first_missing, 995);
+    // Lost events leave the latency of the rest untouched
+    assert_close(results.percentiles["p99"], 113.0, 1e-6);
+}
+
+#[test]
+fn loss_after_the_last_arrival_is_not_visible() {
+    // Sequence gaps only show between received IDs
+    let results = Simulation {
+        loss: Loss::Tail(25),
+        ..Simulation::default()
+    }
+    .run();
+
+    assert_eq!(results.sample_count, 9_975);
+    assert_eq!(results.events_lost, 0);
+}
+
+#[test]
+fn forwarder_clock_skew_moves_only_the_backbone_latency() {
+    let results = Simulation {
+        tokyo_skew_ms: 5.0,
+        ..Simulation::default()
+    }
+    .run();
+
+    assert_close(results.percentiles["p50"], 113.0, 1e-6);
+    assert_close(results.backbone_median_latency_ms.unwrap(), 105.0, 1e-6);
+}
+
+#[test]
+fn receiver_clock_skew_moves_both_latencies() {
+    let results = Simulation {
+        receiver_skew_ms: -2.5,
+        ..Simulation::default()
+    }
+    .run();
+
+    assert_close(results.percentiles["p50"], 110.5, 1e-6);
+    assert_close(results.backbone_median_latency_ms.unwrap(), 107.5, 1e-6);
+}
+
+#[test]
+fn same_seed_gives_same_results() {
+    let simulation = Simulation {
+        backbone: Latency::Exponential {
+            offset: 100.0,
+            mean: 10.0,
+        },
+        ..Simulation::default()
+    };
+    let first = simulation.run();
+    let second = simulation.run();
+    assert_eq!(first.percentiles, second.percentiles);
+    assert_eq!(first.avg_latency_ms, second.avg_latency_ms);
+}
@@ -0,0 +1,103 @@
+use latency_core::{ExperimentResults, Report};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use shared::{read_parquet, symbol_path, InfluxConfig, LatencyMeasurement, SinkSpec, Sinks};
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sink-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn parses_sink_specs() {
+    assert_eq!(
+        "csv:raw.csv".parse::<SinkSpec>(),
+        Ok(SinkSpec::Csv("raw.csv".to_string()))
+    );
+    assert_eq!(
+        "influx:http://localhost:8086".parse::<SinkSpec>(),
+        Ok(SinkSpec::Influx("http://localhost:8086".to_string()))
+    );
+    assert_eq!("stdout".parse::<SinkSpec>(), Ok(SinkSpec::Stdout));
+    assert!("parquet".parse::<SinkSpec>().is_err());
+    assert!("stdout:file".parse::<SinkSpec>().is_err());
+    assert!("xml:raw.xml".parse::<SinkSpec>().is_err());
+    assert_eq!(SinkSpec::Stdout.path(), None);
+}
+
+#[tokio::test]
+async fn writes_to_every_sink() {
+    let dir = temp_dir("every");
+    let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let influx = InfluxConfig {
+        url: String::new(),
+        token: None,
+        org: None,
+        bucket: "latency".to_string(),
+    };
+    let specs = [
+        SinkSpec::Csv(path("raw.csv")),
+        SinkSpec::Json(path("results.json")),
+        SinkSpec::Parquet(path("raw.parquet")),
+    ];
+    let mut sinks = Sinks::new(
+        specs
+            .iter()
+            .map(|spec| spec.open(&influx, &[]).unwrap())
+            .collect(),
+    );
+
+    let measurements: Vec<_> = (0..3)
+        .map(|i| {
+            LatencyMeasurement::new_baseline(i, 1_000 + i as i64, 2_000_000_000)
+                .with_frame_bytes(200 + i as u32)
+        })
+        .collect();
+    for m in &measurements {
+        sinks.write(m);
+    }
+    let written = measurements[2].clone();
+    let results = ExperimentResults::from_measurements("baseline".to_string(), &measurements, 0);
+    sinks
+        .finish(&Report::new(results, measurements))
+        .await
+        .unwrap();
+
+    let csv = LatencyMeasurement::read_from_csv(&path("raw.csv")).unwrap();
+    assert_eq!(csv.len(), 3);
+    assert_eq!(csv[2].binance_event_time, 1_002);
+    let results = ExperimentResults::load(&path("results.json")).unwrap();
+    assert_eq!(results.sample_count, 3);
+    let parquet =
+        SerializedFileReader::new(std::fs::File::open(path("raw.parquet")).unwrap()).unwrap();
+    assert_eq!(parquet.metadata().file_metadata().num_rows(), 3);
+    assert_eq!(
+        parquet
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .column(12)
+            .name(),
+        "stream"
+    );
+    let read = read_parquet(&path("raw.parquet")).unwrap();
+    assert_eq!(read.len(), 3);
+    assert_eq!(read[2].sequence_id, written.sequence_id);
+    assert_eq!(read[2].end_to_end_latency_ns, written.end_to_end_latency_ns);
+    assert_eq!(read[2].frame_bytes, Some(202));
+    assert_eq!(read[2].packet_bytes, None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn symbol_files_sit_next_to_the_csv() {
+    assert_eq!(symbol_path("raw.csv", "BTCUSDT"), "raw-BTCUSDT.csv");
+    assert_eq!(
+        symbol_path("out/raw_run1.csv", "BTC-USDT"),
+        "out/raw_run1-BTC-USDT.csv"
+    );
+    assert_eq!(symbol_path("raw", "BTC/USDT"), "raw-BTC_USDT");
+}
@@ -0,0 +1,86 @@
+use latency_core::SubscriptionMethod;
+use shared::{split_stream_url, Subscriptions};
+
+#[test]
+fn splits_single_stream_urls() {
+    assert_eq!(
+        split_stream_url("wss://stream.binance.com:9443/ws/btcusdt@aggTrade"),
+        Some((
+            "wss://stream.binance.com:9443/stream".to_string(),
+            "btcusdt@aggTrade"
+        ))
+    );
+    assert_eq!(split_stream_url("wss://stream.binance.com:9443/ws/"), None);
+    assert_eq!(
+        split_stream_url("wss://stream.binance.com:9443/stream?streams=btcusdt@aggTrade"),
+        None
+    );
+}
+
+#[test]
+fn acknowledged_requests_change_the_active_streams() {
+    let mut subscriptions = Subscriptions::new(vec!["btcusdt@aggTrade".to_string()]);
+    let initial = subscriptions.resubscribe(0).unwrap();
+    let message: serde_json::Value = serde_json::from_str(&initial).unwrap();
+    assert_eq!(message["method"], "SUBSCRIBE");
+    assert_eq!(message["params"][0], "btcusdt@aggTrade");
+
+    let (id, _) = subscriptions
+        .subscribe(vec!["ethusdt@aggTrade".to_string()], 1_000_000_000)
+        .unwrap();
+    // Market data is not a reply
+    assert!(subscriptions
+        .reply(r#"{"e":"aggTrade","E":1,"s":"ETHUSDT"}"#, 1_002_000_000)
+        .is_none());
+    assert_eq!(subscriptions.active(), ["btcusdt@aggTrade"]);
+
+    let reply = format!(r#"{{"result":null,"id":{}}}"#, id);
+    let answer = subscriptions.reply(&reply, 1_002_500_000).unwrap();
+    let change = answer.change.unwrap();
+    assert_eq!(change.method, SubscriptionMethod::Subscribe);
+    assert_eq!(change.ack_ms, Some(2.5));
+    assert_eq!(
+        subscriptions.active(),
+        ["btcusdt@aggTrade", "ethusdt@aggTrade"]
+    );
+    // The same request answered on a second connection
+    assert_eq!(
+        subscriptions.reply(&reply, 1_003_000_000).unwrap().change,
+        None
+    );
+
+    let (id, _) = subscriptions
+        .unsubscribe(vec!["btcusdt@aggTrade".to_string()], 2_000_000_000)
+        .unwrap();
+    subscriptions.reply(&format!(r#"{{"result":null,"id":{}}}"#, id), 2_001_000_000);
+    assert_eq!(subscriptions.active(), ["ethusdt@aggTrade"]);
+    assert_eq!(subscriptions.changes().len(), 3);
+}
+
+#[test]
+fn rejected_requests_keep_the_active_streams() {
+    let mut subscriptions = Subscriptions::new(Vec::new());
+    assert_eq!(subscriptions.resubscribe(0), None);
+    assert!(subscriptions.subscribe(Vec::new(), 0).is_err());
+    assert!(subscriptions
+        .subscribe(vec!["btcusdt".to_string()], 0)
+        .is_err());
+    assert!(subscriptions
+        .unsubscribe(vec!["btcusdt@aggTrade".to_string()], 0)
+        .is_err());
+
+    let (id, _) = subscriptions
+        .subscribe(vec!["btcusdt@aggTrade".to_string()], 0)
+        .unwrap();
+    let reply = format!(
+        r#"{{"error":{{"code":2,"msg":"Invalid request"}},"id":{}}}"#,
+        id
+    );
+    let change = subscriptions
+        .reply(&reply, 1_000_000)
+        .unwrap()
+        .change
+        .unwrap();
+    assert_eq!(change.error.as_deref(), Some("Invalid request (code 2)"));
+    assert!(subscriptions.active().is_empty());
+}
@@ -0,0 +1,27 @@
+use shared::expand_template;
+
+const VALUES: &[(&str, &str)] = &[
+    ("mode", "aws-backbone"),
+    ("symbol", "BTC/USDT"),
+    ("start_ts", "20240101T120000Z"),
+];
+
+#[test]
+fn placeholders_are_replaced() {
+    assert_eq!(
+        expand_template("results_{mode}_{symbol}_{start_ts}.json", VALUES).unwrap(),
+        "results_aws-backbone_BTC-USDT_20240101T120000Z.json"
+    );
+    assert_eq!(
+        expand_template("out/results.json", VALUES).unwrap(),
+        "out/results.json"
+    );
+}
+
+#[test]
+fn bad_templates_are_rejected() {
+    let unknown = expand_template("results_{date}.json", VALUES).unwrap_err();
+    assert!(unknown.contains("{date}"), "{}", unknown);
+    assert!(unknown.contains("{start_ts}"), "{}", unknown);
+    assert!(expand_template("results_{mode.json", VALUES).is_err());
+}
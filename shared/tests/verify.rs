@@ -0,0 +1,65 @@
+use shared::{Binance, ForwardedEvent, ForwardedEventView, PayloadCheck, PayloadVerifier};
+
+const AGG_TRADE: &str = r#"{"e":"aggTrade","E":1700000000123,"s":"BTCUSDT","a":5,"p":"64250.10","q":"0.012","f":5,"l":5,"T":1700000000122,"m":false,"M":true}"#;
+
+fn forwarded(binance_event_time: i64, replayed: bool) -> String {
+    serde_json::to_string(&ForwardedEvent {
+        replayed,
+        ..ForwardedEvent::new(
+            1,
+            1700000000150000000,
+            binance_event_time,
+            AGG_TRADE.to_string(),
+        )
+    })
+    .unwrap()
+}
+
+fn check(verifier: &mut PayloadVerifier, frame: &str) -> PayloadCheck {
+    let event = ForwardedEventView::parse(frame.as_bytes()).unwrap();
+    verifier.check(frame.as_bytes(), &event)
+}
+
+#[test]
+fn payload_is_compared_with_the_envelope() {
+    let mut verifier = PayloadVerifier::new(Box::new(Binance), "BTC-USDT");
+    assert_eq!(
+        check(&mut verifier, &forwarded(1700000000123, false)),
+        PayloadCheck::Match
+    );
+    assert_eq!(
+        check(&mut verifier, &forwarded(1700000000999, false)),
+        PayloadCheck::EventTimeMismatch {
+            payload: Some(1700000000123)
+        }
+    );
+    // Replays shift the envelope's event time on purpose
+    assert_eq!(
+        check(&mut verifier, &forwarded(1800000000000, true)),
+        PayloadCheck::Match
+    );
+    assert_eq!(
+        check(
+            &mut verifier,
+            r#"{"sequence_id":2,"tokyo_receive_timestamp":0,"binance_event_time":0,"event_data":"{}"}"#
+        ),
+        PayloadCheck::Unparsable
+    );
+
+    let stats = verifier.stats();
+    assert_eq!((stats.checked, stats.failed()), (4, 2));
+    assert_eq!(stats.event_time_mismatches, 1);
+    assert_eq!(stats.unparsable, 1);
+}
+
+#[test]
+fn events_of_another_symbol_are_counted() {
+    let mut verifier = PayloadVerifier::new(Box::new(Binance), "eth-usdt");
+    assert_eq!(
+        check(&mut verifier, &forwarded(1700000000123, false)),
+        PayloadCheck::SymbolMismatch {
+            payload: "BTCUSDT".to_string()
+        }
+    );
+    assert_eq!(verifier.stats().symbol_mismatches, 1);
+}
@@ -0,0 +1,124 @@
+use flate2::{Compress, Compression, FlushCompress};
+use futures_util::{SinkExt, StreamExt};
+use shared::{connect_exchange, WsCompression};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Deflate `text` as one permessage-deflate message, without the trailer
+fn deflate(compress: &mut Compress, text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len() + 64);
+    compress
+        .compress_vec(text.as_bytes(), &mut out, FlushCompress::Sync)
+        .unwrap();
+    assert!(out.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+    out.truncate(out.len() - 4);
+    out
+}
+
+fn frame(payload: Vec<u8>, opcode: OpCode, fin: bool, rsv1: bool) -> Message {
+    let mut frame = Frame::message(payload, opcode, fin);
+    frame.header_mut().rsv1 = rsv1;
+    Message::Frame(frame)
+}
+
+/// Serve one connection, accepting permessage-deflate if `accept` is set, and
+/// send `frames` once the handshake is done
+async fn serve(accept: bool, frames: Vec<Message>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+        let request = String::from_utf8(request).unwrap();
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let extensions = if accept {
+            "Sec-WebSocket-Extensions: permessage-deflate\r\n"
+        } else {
+            ""
+        };
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Accept: {}\r\n{}\r\n",
+            derive_accept_key(key.trim().as_bytes()),
+            extensions
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+
+        let mut ws = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+        for frame in frames {
+            ws.send(frame).await.unwrap();
+        }
+        ws.close(None).await.ok();
+    });
+    url
+}
+
+async fn receive(url: &str, compression: WsCompression) -> (bool, Vec<String>) {
+    let mut ws = connect_exchange(url, compression).await.unwrap();
+    let compressed = ws.get_ref().compressed();
+    let mut texts = Vec::new();
+    while let Some(message) = ws.next().await {
+        match message.unwrap() {
+            Message::Text(text) => texts.push(text),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    (compressed, texts)
+}
+
+#[tokio::test]
+async fn compressed_messages_are_inflated() {
+    let first = r#"{"e":"trade","E":1700000000000,"s":"BTCUSDT","p":"37000.10"}"#;
+    let second = r#"{"e":"trade","E":1700000000001,"s":"BTCUSDT","p":"37000.20"}"#;
+    let fragmented = "x".repeat(300);
+
+    // The server keeps its window between messages (context takeover)
+    let mut compress = Compress::new(Compression::default(), false);
+    let first_deflated = deflate(&mut compress, first);
+    let second_deflated = deflate(&mut compress, second);
+    let mut fragments = deflate(&mut compress, &fragmented);
+    let tail = fragments.split_off(fragments.len() / 2);
+
+    let url = serve(
+        true,
+        vec![
+            frame(first_deflated, OpCode::Data(Data::Text), true, true),
+            frame(second_deflated, OpCode::Data(Data::Text), true, true),
+            Message::Text("uncompressed".to_string()),
+            frame(fragments, OpCode::Data(Data::Text), false, true),
+            Message::Ping(Vec::new()), // Control frames may arrive between fragments
+            frame(tail, OpCode::Data(Data::Continue), true, false),
+        ],
+    )
+    .await;
+
+    let (compressed, texts) = receive(&url, WsCompression::On).await;
+    assert!(compressed);
+    assert_eq!(texts, [first, second, "uncompressed", fragmented.as_str()]);
+}
+
+#[tokio::test]
+async fn declined_compression_passes_frames_through() {
+    let url = serve(false, vec![Message::Text("plain".to_string())]).await;
+    let (compressed, texts) = receive(&url, WsCompression::On).await;
+    assert!(!compressed);
+    assert_eq!(texts, ["plain"]);
+
+    let url = serve(true, vec![Message::Text("plain".to_string())]).await;
+    let (compressed, texts) = receive(&url, WsCompression::Off).await;
+    assert!(!compressed);
+    assert_eq!(texts, ["plain"]);
+}
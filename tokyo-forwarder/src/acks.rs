@@ -0,0 +1,41 @@
+// Receiver acknowledgements (--ack-port) for round trip and clock offset
+//
+// A receiver run with --ack-every answers every Nth event with a datagram to
+// this port. The arrival time completes its timestamps, and the estimates are
+// kept in the counters for the status file and the shutdown summary.
+
+use crate::{now_nanos, Counters};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tracing::{debug, info};
+
+/// Longest acknowledgement worth reading; anything larger is not one
+const MAX_ACK: usize = 512;
+
+/// Bind the acknowledgement port; fails like any other startup error
+pub async fn bind(port: u16) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).await?;
+    info!(port, "listening for receiver acknowledgements");
+    Ok(socket)
+}
+
+/// Record acknowledgements until the process exits
+pub async fn serve(socket: UdpSocket, counters: Arc<Counters>) {
+    let mut buf = [0u8; MAX_ACK];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((len, peer)) => {
+                let arrived = now_nanos();
+                let ack = counters
+                    .receiver_acks
+                    .lock()
+                    .unwrap()
+                    .record(&buf[..len], arrived);
+                if ack.is_none() {
+                    debug!(%peer, len, "ignoring datagram that is not an acknowledgement");
+                }
+            }
+            Err(e) => debug!(error = %e, "acknowledgement receive failed"),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+// Fault injection on the forwarder's send path (--chaos)
+//
+// To check the receiver's loss, duplicate and reorder accounting against known
+// ground truth, a fraction of outgoing events can be dropped, sent twice,
+// held back for a fixed delay, or swapped with the event after them. What was
+// injected is counted and reported in the forwarder summary and status file.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use tokio::time::{Duration, Instant};
+
+/// How long delayed events are held when no delay is configured
+pub const DEFAULT_CHAOS_DELAY: Duration = Duration::from_millis(100);
+
+/// Percentages of events to disturb, from the command line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChaosConfig {
+    pub drop: f64,      // Percent of events not sent
+    pub duplicate: f64, // Percent sent twice
+    pub delay: f64,     // Percent held back for `delay_by`
+    pub reorder: f64,   // Percent sent after the next event
+    pub delay_by: Duration,
+    pub seed: u64,
+}
+
+impl FromStr for ChaosConfig {
+    type Err = String;
+
+    /// Comma-separated `kind=percent`, e.g. `drop=1,duplicate=0.5,delay=2%,reorder=1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = ChaosConfig {
+            drop: 0.0,
+            duplicate: 0.0,
+            delay: 0.0,
+            reorder: 0.0,
+            delay_by: DEFAULT_CHAOS_DELAY,
+            seed: 0,
+        };
+        for part in s.split(',') {
+            let (kind, percent) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected KIND=PERCENT, got {}", part))?;
+            let percent: f64 = percent
+                .trim()
+                .trim_end_matches('%')
+                .parse()
+                .ok()
+                .filter(|p: &f64| (0.0..=100.0).contains(p))
+                .ok_or_else(|| format!("invalid percentage in {}", part))?;
+            match kind.trim() {
+                "drop" => config.drop = percent,
+                "duplicate" | "dup" => config.duplicate = percent,
+                "delay" => config.delay = percent,
+                "reorder" => config.reorder = percent,
+                other => {
+                    return Err(format!(
+                        "unknown chaos kind: {} (drop, duplicate, delay or reorder)",
+                        other
+                    ))
+                }
+            }
+        }
+        if config.total() > 100.0 {
+            return Err("chaos percentages add up to more than 100".to_string());
+        }
+        Ok(config)
+    }
+}
+
+impl ChaosConfig {
+    fn total(&self) -> f64 {
+        self.drop + self.duplicate + self.delay + self.reorder
+    }
+}
+
+impl fmt::Display for ChaosConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "drop {}%, duplicate {}%, delay {}% by {:?}, reorder {}%",
+            self.drop, self.duplicate, self.delay, self.delay_by, self.reorder
+        )
+    }
+}
+
+/// What to do with one outgoing event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaosAction {
+    Send,
+    Drop,
+    Duplicate,
+    Delay,
+    Reorder,
+}
+
+/// Events disturbed so far: the ground truth for the receiver's counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ChaosStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub delayed: u64,
+    pub reordered: u64,
+}
+
+impl ChaosStats {
+    pub fn record(&mut self, action: ChaosAction) {
+        match action {
+            ChaosAction::Send => {}
+            ChaosAction::Drop => self.dropped += 1,
+            ChaosAction::Duplicate => self.duplicated += 1,
+            ChaosAction::Delay => self.delayed += 1,
+            ChaosAction::Reorder => self.reordered += 1,
+        }
+    }
+}
+
+impl fmt::Display for ChaosStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} dropped, {} duplicated, {} delayed, {} reordered",
+            self.dropped, self.duplicated, self.delayed, self.reordered
+        )
+    }
+}
+
+/// Decides the fate of each event and holds the delayed and reordered ones
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: u64,
+    delayed: VecDeque<(Instant, u64, String)>, // Due time, sequence ID and event, in due order
+    reordered: Option<(u64, String)>,          // Sent after the next event
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        Self {
+            config,
+            rng: config.seed.max(1), // xorshift never leaves zero
+            delayed: VecDeque::new(),
+            reordered: None,
+        }
+    }
+
+    /// Pick the action for the next event. Only one event is held for
+    /// reordering at a time; while one is, reorder draws send normally.
+    pub fn decide(&mut self) -> ChaosAction {
+        let draw = self.random_unit() * 100.0;
+        let config = self.config;
+        let mut threshold = 0.0;
+        for (percent, action) in [
+            (config.drop, ChaosAction::Drop),
+            (config.duplicate, ChaosAction::Duplicate),
+            (config.delay, ChaosAction::Delay),
+            (config.reorder, ChaosAction::Reorder),
+        ] {
+            threshold += percent;
+            if draw < threshold {
+                if action == ChaosAction::Reorder && self.reordered.is_some() {
+                    break;
+                }
+                return action;
+            }
+        }
+        ChaosAction::Send
+    }
+
+    /// Hold an event until the configured delay has passed
+    pub fn delay(&mut self, sequence_id: u64, json: &str, now: Instant) {
+        let due = now + self.config.delay_by;
+        self.delayed.push_back((due, sequence_id, json.to_string()));
+    }
+
+    /// Hold an event until the next one has been sent
+    pub fn hold(&mut self, sequence_id: u64, json: &str) {
+        self.reordered = Some((sequence_id, json.to_string()));
+    }
+
+    /// The event held for reordering, to send now that another overtook it
+    pub fn take_reordered(&mut self) -> Option<(u64, String)> {
+        self.reordered.take()
+    }
+
+    /// A delayed event whose time has come
+    pub fn pop_due(&mut self, now: Instant) -> Option<(u64, String)> {
+        match self.delayed.front() {
+            Some((due, _, _)) if *due <= now => {
+                self.delayed.pop_front().map(|(_, id, json)| (id, json))
+            }
+            _ => None,
+        }
+    }
+
+    /// When the next delayed event is due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.delayed.front().map(|(due, _, _)| *due)
+    }
+
+    /// Everything still held back, delayed events first (on shutdown)
+    pub fn drain(&mut self) -> Vec<(u64, String)> {
+        let mut held: Vec<_> = self
+            .delayed
+            .drain(..)
+            .map(|(_, id, json)| (id, json))
+            .collect();
+        held.extend(self.reordered.take());
+        held
+    }
+
+    /// Uniform in [0, 1) (xorshift64*)
+    fn random_unit(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let value = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(spec: &str, seed: u64) -> Chaos {
+        let mut config: ChaosConfig = spec.parse().unwrap();
+        config.seed = seed;
+        Chaos::new(config)
+    }
+
+    /// Sequence IDs in the order the forwarder's dispatch sends them
+    fn dispatch(chaos: &mut Chaos, events: std::ops::Range<u64>) -> Vec<u64> {
+        let mut sent = Vec::new();
+        for id in events {
+            let action = chaos.decide();
+            match action {
+                ChaosAction::Send => sent.push(id),
+                ChaosAction::Duplicate => sent.extend([id, id]),
+                ChaosAction::Drop | ChaosAction::Delay => {}
+                ChaosAction::Reorder => chaos.hold(id, ""),
+            }
+            if matches!(action, ChaosAction::Send | ChaosAction::Duplicate) {
+                sent.extend(chaos.take_reordered().map(|(held, _)| held));
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn actions_follow_the_configured_percentages() {
+        const DRAWS: usize = 100_000;
+        let mut chaos = seeded("drop=10,duplicate=5,delay=2,reorder=3", 42);
+        let mut stats = ChaosStats::default();
+        for _ in 0..DRAWS {
+            stats.record(chaos.decide());
+        }
+        let percent = |count: u64| count as f64 * 100.0 / DRAWS as f64;
+        for (count, expected) in [
+            (stats.dropped, 10.0),
+            (stats.duplicated, 5.0),
+            (stats.delayed, 2.0),
+            (stats.reordered, 3.0),
+        ] {
+            assert!(
+                (percent(count) - expected).abs() < 0.5,
+                "{}% instead of {}%",
+                percent(count),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn the_same_seed_repeats_the_pattern() {
+        let decisions = |seed| {
+            let mut chaos = seeded("drop=20,duplicate=20,delay=20,reorder=20", seed);
+            (0..1_000).map(|_| chaos.decide()).collect::<Vec<_>>()
+        };
+        assert_eq!(decisions(7), decisions(7));
+        assert_ne!(decisions(7), decisions(8));
+        // Zero would stall xorshift
+        let zero = decisions(0);
+        assert!(zero.iter().any(|&action| action != zero[0]));
+    }
+
+    #[test]
+    fn held_events_follow_their_successor() {
+        // Only one event is held at a time, so the next one is sent
+        let mut chaos = seeded("reorder=100", 1);
+        assert_eq!(dispatch(&mut chaos, 0..6), [1, 0, 3, 2, 5, 4]);
+
+        let mut chaos = seeded("reorder=30,duplicate=10", 3);
+        let sent = dispatch(&mut chaos, 0..10_000);
+        let mut overtaken = 0;
+        for pair in sent.windows(2) {
+            if pair[1] < pair[0] {
+                assert_eq!(pair[0], pair[1] + 1);
+                overtaken += 1;
+            }
+        }
+        assert!(overtaken > 2_000, "{} reordered", overtaken);
+
+        // Nothing is lost: the last event may still be held
+        let mut ids = sent;
+        ids.extend(chaos.drain().into_iter().map(|(id, _)| id));
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn delayed_events_are_released_when_due() {
+        let mut chaos = seeded("delay=100", 1);
+        let start = Instant::now();
+        chaos.delay(1, "a", start);
+        chaos.delay(2, "b", start + Duration::from_millis(10));
+        assert_eq!(chaos.next_due(), Some(start + DEFAULT_CHAOS_DELAY));
+        assert_eq!(chaos.pop_due(start + Duration::from_millis(99)), None);
+        assert_eq!(
+            chaos.pop_due(start + DEFAULT_CHAOS_DELAY),
+            Some((1, "a".to_string()))
+        );
+        assert_eq!(chaos.pop_due(start + DEFAULT_CHAOS_DELAY), None);
+
+        chaos.hold(3, "c");
+        let held: Vec<u64> = chaos.drain().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(held, [2, 3]);
+        assert_eq!(chaos.next_due(), None);
+    }
+
+    #[test]
+    fn specs_are_validated() {
+        let config: ChaosConfig = "drop=1, dup=0.5%,delay=2,reorder=1".parse().unwrap();
+        assert_eq!(config.duplicate, 0.5);
+        assert_eq!(config.delay_by, DEFAULT_CHAOS_DELAY);
+        assert!("drop=101".parse::<ChaosConfig>().is_err());
+        assert!("drop=60,delay=50".parse::<ChaosConfig>().is_err());
+        assert!("corrupt=1".parse::<ChaosConfig>().is_err());
+        assert!("drop".parse::<ChaosConfig>().is_err());
+    }
+}
@@ -0,0 +1,33 @@
+// UDP echo responder (--echo-port) for the receiver's path RTT probes
+//
+// Datagrams are sent straight back to whoever sent them. The responder runs
+// next to the forwarding pipeline for the whole life of the process, so path
+// RTT can be sampled even while the exchange connection is down.
+
+use tokio::net::UdpSocket;
+use tracing::{debug, info};
+
+/// Longest probe worth echoing; anything larger is not one of ours
+const MAX_PROBE: usize = 512;
+
+/// Bind the echo port; fails like any other startup error
+pub async fn bind(port: u16) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).await?;
+    info!(port, "echoing path probes");
+    Ok(socket)
+}
+
+/// Echo datagrams until the process exits
+pub async fn serve(socket: UdpSocket) {
+    let mut buf = [0u8; MAX_PROBE];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((len, peer)) => {
+                if let Err(e) = socket.send_to(&buf[..len], peer).await {
+                    debug!(%peer, error = %e, "echo failed");
+                }
+            }
+            Err(e) => debug!(error = %e, "echo receive failed"),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// A second exchange connection kept ready to take over (--hot-spare)
+//
+// The forwarder reads a spare connection to the same stream alongside the
+// primary, forwarding only from the primary. Spare frames the primary has not
+// delivered yet are held, keyed by update ID. When the primary falls silent for
+// --stall-ms while the spare keeps delivering, or the primary disconnects, the
+// spare becomes the primary: the held frames are forwarded first and later
+// frames are deduplicated by update ID, so the cutover neither loses nor
+// repeats events.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use tokio::time::{Duration, Instant};
+
+/// Default for --stall-ms
+pub const DEFAULT_STALL_MS: u64 = 500;
+
+/// Wait before opening another spare after one failed or closed
+pub const SPARE_RETRY: Duration = Duration::from_secs(5);
+
+/// Spare frames held while the primary is behind; the oldest are dropped beyond this
+const MAX_HELD: usize = 10_000;
+
+/// Why the spare took over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CutoverReason {
+    Stall,      // The primary was silent while the spare delivered new events
+    Disconnect, // The primary connection was lost
+}
+
+impl fmt::Display for CutoverReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CutoverReason::Stall => "stall",
+            CutoverReason::Disconnect => "disconnect",
+        })
+    }
+}
+
+/// One switch from the primary to the spare connection
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Cutover {
+    pub time: i64, // Epoch nanos
+    pub reason: CutoverReason,
+    pub stall_ms: f64,    // Since the primary's last frame
+    pub recovered: usize, // Held spare frames forwarded at the cutover
+}
+
+/// Deduplication and stall detection across the primary and spare connections
+#[derive(Debug)]
+pub struct HotSpare {
+    stall_after: Duration,
+    forwarded: Option<i64>,             // Highest update ID forwarded
+    primary_seen: Instant,              // Last frame from the primary
+    held: VecDeque<(i64, String, i64)>, // Update ID, frame and receive time, in arrival order
+}
+
+impl HotSpare {
+    pub fn new(stall_after: Duration) -> Self {
+        Self {
+            stall_after,
+            forwarded: None,
+            primary_seen: Instant::now(),
+            held: VecDeque::new(),
+        }
+    }
+
+    /// A frame from the primary with `update_id` (none for control frames).
+    /// Returns whether to forward it.
+    pub fn primary(&mut self, update_id: Option<i64>) -> bool {
+        self.primary_seen = Instant::now();
+        let Some(id) = update_id else {
+            return true;
+        };
+        if self.forwarded.is_some_and(|forwarded| id <= forwarded) {
+            return false;
+        }
+        self.forwarded = Some(id);
+        while self.held.front().is_some_and(|(held, _, _)| *held <= id) {
+            self.held.pop_front();
+        }
+        true
+    }
+
+    /// A frame from the spare, received at `received`. Returns whether the
+    /// primary has stalled, so the spare should take over now.
+    pub fn spare(&mut self, update_id: Option<i64>, text: String, received: i64) -> bool {
+        let Some(id) = update_id else {
+            return false;
+        };
+        if self.forwarded.is_some_and(|forwarded| id <= forwarded) {
+            return false;
+        }
+        if self.held.len() >= MAX_HELD {
+            self.held.pop_front();
+        }
+        self.held.push_back((id, text, received));
+        self.primary_seen.elapsed() >= self.stall_after
+    }
+
+    /// The spare becomes the primary at `now` (epoch nanos). Returns the held
+    /// frames to forward, with their receive times, and the cutover to record.
+    pub fn cut_over(&mut self, reason: CutoverReason, now: i64) -> (Vec<(String, i64)>, Cutover) {
+        let stall_ms = self.primary_seen.elapsed().as_secs_f64() * 1000.0;
+        self.primary_seen = Instant::now();
+        if let Some((id, _, _)) = self.held.back() {
+            self.forwarded = Some(*id);
+        }
+        let frames: Vec<_> = self
+            .held
+            .drain(..)
+            .map(|(_, text, received)| (text, received))
+            .collect();
+        let cutover = Cutover {
+            time: now,
+            reason,
+            stall_ms,
+            recovered: frames.len(),
+        };
+        (frames, cutover)
+    }
+
+    /// Both connections were lost and the primary was reconnected
+    pub fn reconnected(&mut self) {
+        self.primary_seen = Instant::now();
+        self.held.clear();
+    }
+
+    /// The spare connection was lost; frames held from it will not be needed
+    pub fn spare_lost(&mut self) {
+        self.held.clear();
+    }
+}
@@ -0,0 +1,2372 @@
+mod acks;
+mod chaos;
+mod echo;
+mod hot_spare;
+mod pacing;
+mod send_queue;
+mod sockopt;
+mod status;
+mod supervision;
+mod synthetic;
+mod transport;
+
+use chaos::{Chaos, ChaosAction, ChaosConfig, ChaosStats};
+use futures_util::{SinkExt, StreamExt};
+use hot_spare::{Cutover, CutoverReason, HotSpare, DEFAULT_STALL_MS, SPARE_RETRY};
+use pacing::{Pacer, PacingConfig};
+use send_queue::{SendQueue, SendQueueConfig, SendQueueStats};
+use shared::{
+    check_aws_cli, connect_exchange, default_rollover, event_time_nanos, event_time_unit_nanos,
+    exchange_adapter, init_logging, new_run_id, output_files, parse_interval, parse_rollover,
+    parse_size, read_capture, split_stream_url, validate_run_id, AckTracker, ArrivalLog, Backoff,
+    BinanceFastParse, CaptureWriter, ClockMonitor, ClockSource, ConnectionHandover, CurrentFrame,
+    ExchangeAdapter, ExchangeAddresses, ExchangeStream, ExperimentError, ForwardedEvent,
+    ForwarderHello, ForwarderStages, ForwarderSupervision, PingTracker, ReconnectPolicy,
+    ReconnectStats, Rollover, RotationPolicy, S3Destination, Shutdown, TlsClient, UpdateArrival,
+    WsCompression, EXCHANGES, WIRE_FORMAT_VERSION,
+};
+use sockopt::{SocketOptions, TcpSocketInfo};
+use status::{ExchangeLatency, StatusReporter};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use supervision::Supervisor;
+use synthetic::{SyntheticConfig, SyntheticFeed};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use transport::{
+    BlackoutStats, Liveness, ReceiverSender, Recovery, RetryBufferStats, Target, Transport,
+    HEARTBEAT_ACK_INTERVALS,
+};
+
+/// Default for --retry-buffer
+const DEFAULT_RETRY_BUFFER: usize = 10_000;
+
+/// Default for --uds-path, the receiver's default
+const DEFAULT_UDS_PATH: &str = "/tmp/frankfurt-receiver.sock";
+
+/// Default for --ping-interval
+const DEFAULT_PING_INTERVAL_SECS: u64 = 5;
+
+/// Seconds between clock error readings with --clock-sync
+const DEFAULT_CLOCK_SYNC_INTERVAL_SECS: u64 = 30;
+
+/// Counters shared across forwarder restarts
+#[derive(Debug, Default)]
+struct Counters {
+    next_sequence_id: AtomicU64, // Also the number of events forwarded
+    send_failures: AtomicU64,
+    parse_failures: AtomicU64,
+    paced_events: AtomicU64, // Sent after waiting for a pacing token
+    pacing_drops: AtomicU64, // Dropped from a full pacing queue
+    max_pacing_delay_us: AtomicU64,
+    receiver_reconnects: Mutex<ReconnectStats>, // TCP and WSS paths of every receiver
+    udp_fragmented: AtomicU64,                  // Events sent as fragments, summed over receivers
+    retry_buffer: Mutex<RetryBufferStats>,      // Events held during receiver outages
+    receiver_blackouts: Mutex<BlackoutStats>,   // TCP path outages, until reconnected
+    receiver_acks: Mutex<AckTracker>,           // Round trips and clock offsets, with --ack-port
+    tcp_sockets: Mutex<BTreeMap<String, TcpSocketInfo>>, // Effective TCP options by receiver address
+    exchange_latency: Mutex<ExchangeLatency>,            // Tokyo receive time − exchange event time
+    exchange_ping: Mutex<PingTracker>, // WebSocket ping round trips to the exchange
+    chaos: Mutex<Option<ChaosStats>>,  // Events disturbed by --chaos, if enabled
+    exchange_handovers: Mutex<Vec<ConnectionHandover>>, // Copied from the exchange feed's Rollover
+    exchange_cutovers: Mutex<Vec<Cutover>>, // Switches to the --hot-spare connection
+    clock: OnceLock<ClockMonitor>,     // Clock error readings, with --clock-sync
+    send_queue: Mutex<SendQueueStats>, // Depth and watermark episodes, with --send-queue
+    exchange_addresses: ExchangeAddresses, // Where every exchange connection landed
+    supervisor: Supervisor,            // Restarts, panics and sessions, kept in --state-file
+}
+
+impl Counters {
+    /// The restart history, as heartbeats report it
+    fn supervision(&self) -> ForwarderSupervision {
+        self.supervisor
+            .report(self.next_sequence_id.load(Ordering::SeqCst))
+    }
+
+    /// Start a forwarder session; each after the first is a restart
+    fn start_session(&self) {
+        self.supervisor
+            .start_session(self.next_sequence_id.load(Ordering::SeqCst));
+    }
+
+    /// End the current session, with the error that ended it if it failed
+    fn end_session(&self, error: Option<impl std::fmt::Display>) {
+        self.supervisor.end_session(
+            self.next_sequence_id.load(Ordering::SeqCst),
+            error.map(|e| e.to_string()),
+        );
+    }
+
+    /// Keep the effective TCP options of `sender`'s latest connection for the status file
+    fn record_tcp_socket(&self, sender: &ReceiverSender) {
+        if let Some(socket) = sender.tcp_socket() {
+            let mut sockets = self.tcp_sockets.lock().unwrap();
+            sockets.insert(sender.addr().to_string(), socket);
+        }
+    }
+
+    /// Add a closed connection's reconnect, fragment and retry buffer counts
+    fn record_sender(&self, sender: &ReceiverSender) {
+        self.record_tcp_socket(sender);
+        self.receiver_reconnects
+            .lock()
+            .unwrap()
+            .add(sender.reconnect_stats());
+        self.retry_buffer
+            .lock()
+            .unwrap()
+            .add(sender.retry_buffer_stats());
+        self.receiver_blackouts
+            .lock()
+            .unwrap()
+            .add(sender.blackout_stats());
+        self.udp_fragmented
+            .fetch_add(sender.fragmented(), Ordering::SeqCst);
+    }
+
+    fn record_chaos(&self, action: ChaosAction) {
+        if let Some(stats) = self.chaos.lock().unwrap().as_mut() {
+            stats.record(action);
+        }
+    }
+
+    /// Log and keep the handovers `rollover` recorded since the last call
+    fn record_handovers(&self, rollover: &Rollover) {
+        let mut handovers = self.exchange_handovers.lock().unwrap();
+        for handover in &rollover.handovers()[handovers.len()..] {
+            info!(
+                reason = %handover.reason,
+                connection_age_secs = handover.connection_age_secs,
+                gap_ms = handover.gap_ms,
+                duplicates_skipped = handover.duplicates_skipped,
+                "exchange connection handed over"
+            );
+            handovers.push(*handover);
+        }
+    }
+
+    fn print_summary(&self, pacing: bool, exchange_reconnects: Option<ReconnectStats>) {
+        println!("\n=== Forwarder Summary ===");
+        println!(
+            "Events forwarded: {}",
+            self.next_sequence_id.load(Ordering::SeqCst)
+        );
+        println!(
+            "Send failures: {}",
+            self.send_failures.load(Ordering::SeqCst)
+        );
+        println!(
+            "Parse failures: {}",
+            self.parse_failures.load(Ordering::SeqCst)
+        );
+        let latency = self.exchange_latency.lock().unwrap().run();
+        if latency.count > 0 {
+            println!(
+                "Exchange latency: avg {:.2} ms, min {:.2} ms, max {:.2} ms",
+                latency.avg_ms, latency.min_ms, latency.max_ms
+            );
+        }
+        if let Some(ping) = self.exchange_ping.lock().unwrap().stats(false) {
+            println!(
+                "Exchange ping RTT: median {:.2} ms, p99 {:.2} ms, min {:.2} ms ({} of {} answered)",
+                ping.median_ms, ping.p99_ms, ping.min_ms, ping.answered, ping.sent
+            );
+        }
+        if pacing {
+            println!(
+                "Paced events: {} (max delay {:.3} ms)",
+                self.paced_events.load(Ordering::SeqCst),
+                self.max_pacing_delay_us.load(Ordering::SeqCst) as f64 / 1000.0
+            );
+            println!(
+                "Dropped by pacing: {}",
+                self.pacing_drops.load(Ordering::SeqCst)
+            );
+        }
+        if let Some(stats) = exchange_reconnects {
+            println!("Exchange reconnects: {}", stats);
+        }
+        let supervision = self.supervision();
+        if supervision.restarts > 0 || supervision.panics > 0 || supervision.process_starts > 1 {
+            println!(
+                "Restarts: {} (panics: {}, process starts: {}, events forwarded over all: {})",
+                supervision.restarts,
+                supervision.panics,
+                supervision.process_starts,
+                supervision.events_forwarded
+            );
+            if let Some(error) = &supervision.last_error {
+                println!("Last error: {}", error);
+            }
+        }
+        let send_queue = self.send_queue.lock().unwrap();
+        if send_queue.enqueued > 0 {
+            println!(
+                "Send queue: max depth {}, max delay {:.3} ms, full {} times, {} episodes above the high watermark",
+                send_queue.max_depth,
+                send_queue.max_delay_ms,
+                send_queue.full,
+                send_queue.episodes.len()
+            );
+        }
+        let fragmented = self.udp_fragmented.load(Ordering::SeqCst);
+        if fragmented > 0 {
+            println!("UDP events sent as fragments: {}", fragmented);
+        }
+        let receiver = *self.receiver_reconnects.lock().unwrap();
+        if receiver != ReconnectStats::default() {
+            println!("Receiver reconnects: {}", receiver);
+        }
+        let retry_buffer = *self.retry_buffer.lock().unwrap();
+        if retry_buffer.held > 0 {
+            println!("Retry buffer: {}", retry_buffer);
+        }
+        let blackouts = *self.receiver_blackouts.lock().unwrap();
+        if blackouts.blackouts > 0 {
+            println!("Receiver blackouts: {}", blackouts);
+        }
+        if let Some(acks) = self.receiver_acks.lock().unwrap().stats() {
+            println!(
+                "Receiver acknowledgements: {} (RTT avg {:.3} ms, p99 {:.3} ms; receiver clock offset {:+.3} ms)",
+                acks.acks, acks.rtt.avg_ms, acks.rtt.p99_ms, acks.offset.median_ms
+            );
+        }
+        if let Some(chaos) = *self.chaos.lock().unwrap() {
+            println!("Chaos injected: {}", chaos);
+        }
+        let cutovers = self.exchange_cutovers.lock().unwrap();
+        if !cutovers.is_empty() {
+            let recovered: usize = cutovers.iter().map(|c| c.recovered).sum();
+            let max_stall_ms = cutovers.iter().map(|c| c.stall_ms).fold(0.0, f64::max);
+            println!(
+                "Hot spare cutovers: {} (longest stall {:.1} ms, {} events recovered from the spare)",
+                cutovers.len(),
+                max_stall_ms,
+                recovered
+            );
+        }
+        let handovers = self.exchange_handovers.lock().unwrap();
+        if !handovers.is_empty() {
+            let duplicates: u64 = handovers.iter().map(|h| h.duplicates_skipped).sum();
+            let max_gap_ms = handovers.iter().map(|h| h.gap_ms).fold(0.0, f64::max);
+            println!(
+                "Exchange connection handovers: {} (max gap {:.3} ms, {} duplicates skipped)",
+                handovers.len(),
+                max_gap_ms,
+                duplicates
+            );
+        }
+        let addresses = self.exchange_addresses.snapshot();
+        let mut ips: Vec<&str> = Vec::new();
+        for address in &addresses {
+            if !ips.contains(&address.ip.as_str()) {
+                ips.push(&address.ip);
+            }
+        }
+        if !ips.is_empty() {
+            println!(
+                "Exchange addresses: {} ({} connections)",
+                ips.join(", "),
+                addresses.len()
+            );
+        }
+    }
+}
+
+/// Configuration for the Tokyo forwarder
+#[derive(Debug, Clone)]
+struct Config {
+    exchange: String,
+    symbol: String,         // BASE-QUOTE form, translated by the exchange adapter
+    ws_url: Option<String>, // Overrides the adapter's stream URL
+    frankfurt_ip: String,
+    frankfurt_port: u16,
+    reconnect: ReconnectPolicy, // Exchange connection and restarts; receivers ignore max_attempts
+    transport: Transport,
+    targets: Vec<Target>, // Overrides frankfurt_ip/frankfurt_port when non-empty
+    uds_path: Option<String>, // Receiver socket for --transport uds
+    replay: Option<String>, // Capture file to forward instead of the live feed
+    synthetic: Option<SyntheticConfig>, // Generate events instead of reading the exchange
+    capture: Option<String>, // Record every raw frame to this file
+    arrival_log: Option<String>, // Record the update ID and receive time of every event
+    rotation: RotationPolicy, // Split the capture into several files
+    tls: bool,            // Encrypt the TCP path
+    tls_ca: Option<String>, // PEM CA bundle trusted for receiver certificates
+    tls_server_name: Option<String>, // Name to verify instead of the target host
+    stage_timestamps: bool, // Ship per-stage timestamps with every event
+    fast_parse: bool,     // Extract only the needed fields instead of parsing frames (Binance)
+    sockets: SocketOptions,
+    dscp: Option<u8>, // Marking of every receiver socket, recorded in each event
+    udp_max_datagram: Option<usize>, // Fragment UDP events larger than this
+    retry_buffer: usize, // Events held per TCP/WSS connection while it is down
+    liveness: Liveness, // Heartbeats and a write deadline on the TCP path
+    pacing: Option<PacingConfig>, // Rate limit for forwarded events
+    send_queue: Option<SendQueueConfig>, // Queue in front of every receiver
+    chaos: Option<ChaosConfig>, // Deliberately disturb outgoing events
+    status_file: Option<String>, // Periodically rewritten local statistics
+    status_interval: Duration,
+    state_file: Option<String>, // Restart history kept across processes
+    ping_interval: Option<Duration>, // WebSocket pings to the exchange
+    ws_compression: WsCompression, // Offer permessage-deflate to the exchange
+    exchange_rollover: Option<Duration>, // Connection age at which a standby takes over
+    hot_spare: Option<Duration>, // Read a spare connection, cutting over after the primary stalls this long
+    echo_port: Option<u16>,      // Echo the receiver's UDP path probes
+    ack_port: Option<u16>,       // Receive the receiver's event acknowledgements
+    clock_sync: Option<ClockSource>, // Read the clock error and send it with every event
+    clock_sync_interval: Duration,
+    s3_upload: Option<S3Destination>, // Upload the status and capture files at exit
+    run_id: &'static str, // Embedded in every event; leaked once so events can borrow it
+    forwarder_id: Option<&'static str>, // Names this forwarder to a --multi-forwarder receiver
+    region_name: String,  // Announced to receivers in the handshake
+    log_level: String,    // Level or tracing filter directive
+    log_json: bool,       // One JSON object per log line
+}
+
+impl Config {
+    /// Configuration from the command line; nothing after printing --help
+    fn from_args(args: Vec<String>) -> Result<Option<Self>, ExperimentError> {
+        // Default configuration
+        let mut config = Config {
+            exchange: "binance".to_string(),
+            symbol: "BTC-USDT".to_string(),
+            ws_url: None,
+            frankfurt_ip: "10.1.1.10".to_string(),
+            frankfurt_port: 8080,
+            reconnect: ReconnectPolicy::default(),
+            transport: Transport::Udp,
+            targets: Vec::new(),
+            uds_path: None,
+            replay: None,
+            synthetic: None,
+            capture: None,
+            arrival_log: None,
+            rotation: RotationPolicy::default(),
+            tls: false,
+            tls_ca: None,
+            tls_server_name: None,
+            stage_timestamps: false,
+            fast_parse: false,
+            sockets: SocketOptions::default(),
+            dscp: None,
+            udp_max_datagram: None,
+            retry_buffer: DEFAULT_RETRY_BUFFER,
+            liveness: Liveness::default(),
+            pacing: None,
+            send_queue: None,
+            chaos: None,
+            status_file: None,
+            state_file: None,
+            status_interval: Duration::from_secs(1),
+            ping_interval: Some(Duration::from_secs(DEFAULT_PING_INTERVAL_SECS)),
+            ws_compression: WsCompression::Off,
+            exchange_rollover: None,
+            hot_spare: None,
+            echo_port: None,
+            ack_port: None,
+            clock_sync: None,
+            clock_sync_interval: Duration::from_secs(DEFAULT_CLOCK_SYNC_INTERVAL_SECS),
+            s3_upload: None,
+            run_id: "",
+            forwarder_id: None,
+            region_name: "tokyo".to_string(),
+            log_level: "info".to_string(),
+            log_json: false,
+        };
+
+        // Pacing flags are combined once all arguments are read
+        let mut max_rate: Option<f64> = None;
+        let mut synthetic_ramp: Option<f64> = None;
+        let mut burst: Option<u32> = None;
+        let mut pace_queue: Option<usize> = None;
+        let mut send_queue: Option<usize> = None;
+        let mut send_queue_high: Option<usize> = None;
+        let mut send_queue_low: Option<usize> = None;
+        let mut chaos_delay_ms: Option<u64> = None;
+        let mut chaos_seed: Option<u64> = None;
+        let mut status_interval: Option<u64> = None;
+        let mut clock_sync_interval: Option<u64> = None;
+        let mut retry_buffer: Option<usize> = None;
+        let mut run_id: Option<String> = None;
+        let mut exchange_rollover: Option<Option<Duration>> = None;
+        let mut hot_spare = false;
+        let mut stall_ms: Option<u64> = None;
+
+        // Parse command-line arguments
+        let mut i = 1;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--exchange" => {
+                    config.exchange = flag_value(&args, i)?.to_ascii_lowercase();
+                    if exchange_adapter(&config.exchange).is_none() {
+                        return Err(ExperimentError::other(format!(
+                            "Unknown exchange: {} (expected one of {})",
+                            config.exchange,
+                            EXCHANGES.join(", ")
+                        )));
+                    }
+                    i += 2;
+                }
+                "--symbol" => {
+                    config.symbol = flag_value(&args, i)?.to_string();
+                    i += 2;
+                }
+                "--binance-url" | "--ws-url" => {
+                    config.ws_url = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--frankfurt-ip" => {
+                    config.frankfurt_ip = flag_value(&args, i)?.to_string();
+                    i += 2;
+                }
+                "--frankfurt-port" | "--port" => {
+                    config.frankfurt_port = parse_flag(&args, i, "port number")?;
+                    i += 2;
+                }
+                "--max-delay" => {
+                    config.reconnect.max_delay =
+                        Duration::from_secs(parse_flag(&args, i, "max delay")?);
+                    i += 2;
+                }
+                "--reconnect-jitter" => {
+                    config.reconnect.jitter = parse_flag(&args, i, "reconnect jitter")?;
+                    if !(0.0..=1.0).contains(&config.reconnect.jitter) {
+                        return Err(ExperimentError::other(
+                            "--reconnect-jitter must be between 0 and 1",
+                        ));
+                    }
+                    i += 2;
+                }
+                "--max-reconnect-attempts" => {
+                    config.reconnect.max_attempts =
+                        Some(parse_flag(&args, i, "max reconnect attempts")?);
+                    i += 2;
+                }
+                "--breaker-after" => {
+                    config.reconnect.breaker_after = parse_flag(&args, i, "breaker threshold")?;
+                    i += 2;
+                }
+                "--breaker-cooldown" => {
+                    config.reconnect.breaker_cooldown =
+                        Duration::from_secs(parse_flag(&args, i, "breaker cooldown")?);
+                    i += 2;
+                }
+                "--targets" => {
+                    config.targets = flag_value(&args, i)?
+                        .split(',')
+                        .map(|target| {
+                            target.parse().map_err(|e| {
+                                ExperimentError::other(format!("Invalid target: {}", e))
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                    i += 2;
+                }
+                "--transport" => {
+                    config.transport = parse_flag(&args, i, "transport")?;
+                    i += 2;
+                }
+                "--uds-path" => {
+                    config.uds_path = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--replay" => {
+                    config.replay = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--capture" => {
+                    config.capture = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--arrival-log" => {
+                    config.arrival_log = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--rotate-size" => {
+                    config.rotation.max_bytes =
+                        Some(parse_size(flag_value(&args, i)?).map_err(ExperimentError::other)?);
+                    i += 2;
+                }
+                "--rotate-interval" => {
+                    config.rotation.interval = Some(
+                        parse_interval(flag_value(&args, i)?).map_err(ExperimentError::other)?,
+                    );
+                    i += 2;
+                }
+                "--rotate-compress" => {
+                    config.rotation.compress = true;
+                    i += 1;
+                }
+                "--tls" => {
+                    config.tls = true;
+                    i += 1;
+                }
+                "--tls-ca" => {
+                    config.tls_ca = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--tls-server-name" => {
+                    config.tls_server_name = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--udp-sndbuf" => {
+                    config.sockets.udp.send_buffer =
+                        Some(parse_flag(&args, i, "send buffer size")?);
+                    i += 2;
+                }
+                "--udp-tos" => {
+                    let tos = flag_value(&args, i)?;
+                    let parsed = match tos.strip_prefix("0x") {
+                        Some(hex) => u8::from_str_radix(hex, 16),
+                        None => tos.parse(),
+                    };
+                    config.sockets.udp.tos = Some(parsed.map_err(|_| {
+                        ExperimentError::other(format!("Invalid TOS byte: {}", tos))
+                    })?);
+                    i += 2;
+                }
+                "--dscp" => {
+                    config.dscp = Some(parse_flag(&args, i, "DSCP value")?);
+                    i += 2;
+                }
+                "--udp-max-datagram" => {
+                    config.udp_max_datagram = Some(parse_flag(&args, i, "datagram size")?);
+                    i += 2;
+                }
+                "--no-tcp-nodelay" => {
+                    config.sockets.tcp.nodelay = false;
+                    i += 1;
+                }
+                "--tcp-sndbuf" => {
+                    config.sockets.tcp.send_buffer =
+                        Some(parse_flag(&args, i, "send buffer size")?);
+                    i += 2;
+                }
+                "--tcp-keepalive" => {
+                    config.sockets.tcp.keepalive =
+                        Some(parse_flag(&args, i, "keepalive interval")?);
+                    i += 2;
+                }
+                "--retry-buffer" => {
+                    retry_buffer = Some(parse_flag(&args, i, "retry buffer size")?);
+                    i += 2;
+                }
+                "--heartbeat-ms" => {
+                    let ms: u64 = parse_flag(&args, i, "heartbeat interval")?;
+                    config.liveness.heartbeat = Some(Duration::from_millis(ms));
+                    i += 2;
+                }
+                "--write-timeout-ms" => {
+                    let ms: u64 = parse_flag(&args, i, "write timeout")?;
+                    config.liveness.write_timeout = Some(Duration::from_millis(ms));
+                    i += 2;
+                }
+                "--dont-fragment" => {
+                    config.sockets.udp.dont_fragment = true;
+                    i += 1;
+                }
+                "--stage-timestamps" => {
+                    config.stage_timestamps = true;
+                    i += 1;
+                }
+                "--fast-parse" => {
+                    config.fast_parse = true;
+                    i += 1;
+                }
+                "--max-rate" => {
+                    let rate = flag_value(&args, i)?;
+                    let rate = rate.strip_suffix("/s").unwrap_or(rate);
+                    match rate.parse::<f64>() {
+                        Ok(rate) if rate > 0.0 && rate.is_finite() => max_rate = Some(rate),
+                        _ => {
+                            return Err(ExperimentError::other(format!(
+                                "Invalid max rate: {}",
+                                flag_value(&args, i)?
+                            )));
+                        }
+                    }
+                    i += 2;
+                }
+                "--synthetic-rate" | "--synthetic-ramp" => {
+                    let rate = flag_value(&args, i)?;
+                    let rate = rate.strip_suffix("/s").unwrap_or(rate);
+                    let rate = match rate.parse::<f64>() {
+                        Ok(rate) if rate >= 0.0 && rate.is_finite() => rate,
+                        _ => {
+                            return Err(ExperimentError::other(format!(
+                                "Invalid {}: {}",
+                                args[i],
+                                flag_value(&args, i)?
+                            )));
+                        }
+                    };
+                    if args[i] == "--synthetic-ramp" {
+                        synthetic_ramp = Some(rate);
+                    } else if rate == 0.0 {
+                        return Err(ExperimentError::other("--synthetic-rate must be above 0"));
+                    } else {
+                        config.synthetic = Some(SyntheticConfig { rate, ramp: 0.0 });
+                    }
+                    i += 2;
+                }
+                "--burst" => {
+                    burst = Some(parse_flag(&args, i, "burst size")?);
+                    i += 2;
+                }
+                "--pace-queue" => {
+                    pace_queue = Some(parse_flag(&args, i, "pacing queue size")?);
+                    i += 2;
+                }
+                "--send-queue" => {
+                    send_queue = Some(parse_flag(&args, i, "send queue size")?);
+                    i += 2;
+                }
+                "--send-queue-high" => {
+                    send_queue_high = Some(parse_flag(&args, i, "high watermark")?);
+                    i += 2;
+                }
+                "--send-queue-low" => {
+                    send_queue_low = Some(parse_flag(&args, i, "low watermark")?);
+                    i += 2;
+                }
+                "--chaos" => {
+                    let spec = flag_value(&args, i)?;
+                    config.chaos =
+                        Some(spec.parse().map_err(|e| {
+                            ExperimentError::other(format!("Invalid --chaos: {}", e))
+                        })?);
+                    i += 2;
+                }
+                "--chaos-delay-ms" => {
+                    chaos_delay_ms = Some(parse_flag(&args, i, "chaos delay")?);
+                    i += 2;
+                }
+                "--chaos-seed" => {
+                    chaos_seed = Some(parse_flag(&args, i, "chaos seed")?);
+                    i += 2;
+                }
+                "--status-file" => {
+                    config.status_file = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--state-file" => {
+                    config.state_file = Some(flag_value(&args, i)?.to_string());
+                    i += 2;
+                }
+                "--status-interval" => {
+                    status_interval = Some(parse_flag(&args, i, "status interval")?);
+                    i += 2;
+                }
+                "--ping-interval" => {
+                    let secs: u64 = parse_flag(&args, i, "ping interval")?;
+                    config.ping_interval = (secs > 0).then(|| Duration::from_secs(secs));
+                    i += 2;
+                }
+                "--ws-compression" => {
+                    config.ws_compression = parse_flag(&args, i, "--ws-compression (on or off)")?;
+                    i += 2;
+                }
+                "--exchange-rollover" => {
+                    let value = flag_value(&args, i)?;
+                    exchange_rollover = Some(parse_rollover(value).map_err(|e| {
+                        ExperimentError::other(format!("Invalid --exchange-rollover: {}", e))
+                    })?);
+                    i += 2;
+                }
+                "--hot-spare" => {
+                    hot_spare = true;
+                    i += 1;
+                }
+                "--stall-ms" => {
+                    stall_ms = Some(parse_flag(&args, i, "stall time")?);
+                    i += 2;
+                }
+                "--s3-upload" => {
+                    let url = flag_value(&args, i)?;
+                    config.s3_upload = Some(url.parse().map_err(|e| {
+                        ExperimentError::other(format!("Invalid --s3-upload: {}", e))
+                    })?);
+                    i += 2;
+                }
+                "--echo-port" => {
+                    config.echo_port = Some(parse_flag(&args, i, "echo port")?);
+                    i += 2;
+                }
+                "--ack-port" => {
+                    config.ack_port = Some(parse_flag(&args, i, "ack port")?);
+                    i += 2;
+                }
+                "--clock-sync" => {
+                    config.clock_sync = Some(parse_flag(&args, i, "--clock-sync (chrony or ptp)")?);
+                    i += 2;
+                }
+                "--clock-sync-interval" => {
+                    clock_sync_interval = Some(parse_flag(&args, i, "clock sync interval")?);
+                    i += 2;
+                }
+                "--run-id" => {
+                    let id = flag_value(&args, i)?;
+                    if let Err(e) = validate_run_id(id) {
+                        return Err(ExperimentError::other(format!("Invalid --run-id: {}", e)));
+                    }
+                    run_id = Some(id.to_string());
+                    i += 2;
+                }
+                "--forwarder-id" => {
+                    let id = flag_value(&args, i)?;
+                    if let Err(e) = validate_run_id(id) {
+                        return Err(ExperimentError::other(format!(
+                            "Invalid --forwarder-id: {}",
+                            e
+                        )));
+                    }
+                    config.forwarder_id = Some(Box::leak(id.to_string().into_boxed_str()));
+                    i += 2;
+                }
+                "--region-name" => {
+                    config.region_name = flag_value(&args, i)?.to_string();
+                    i += 2;
+                }
+                "--log-level" => {
+                    config.log_level = flag_value(&args, i)?.to_string();
+                    i += 2;
+                }
+                "--log-json" => {
+                    config.log_json = true;
+                    i += 1;
+                }
+                "--help" | "-h" => {
+                    println!("Tokyo Forwarder - exchange WebSocket to receiver forwarder");
+                    println!("\nUsage: tokyo-forwarder [OPTIONS]");
+                    println!("\nOptions:");
+                    println!(
+                        "  --exchange <NAME>         binance, okx, or bybit (default: binance)"
+                    );
+                    println!("  --symbol <SYMBOL>         Trading pair as BASE-QUOTE (default: BTC-USDT)");
+                    println!("  --ws-url <URL>            Override the exchange WebSocket URL (alias: --binance-url)");
+                    println!(
+                        "  --frankfurt-ip <IP>       Frankfurt EC2 private IP (default: 10.1.1.10)"
+                    );
+                    println!("  --frankfurt-port <PORT>   Frankfurt receiver port (default: 8080)");
+                    println!("  --max-delay <SECONDS>     Max reconnection delay (default: 30)");
+                    println!("  --reconnect-jitter <F>    Fraction of each reconnect delay randomized, 0-1 (default: 0.5)");
+                    println!("  --max-reconnect-attempts <N>  Exit after N consecutive failed exchange connections (default: unlimited)");
+                    println!("  --breaker-after <N>       Consecutive failures before pausing for the cooldown, 0 disables (default: 10)");
+                    println!("  --breaker-cooldown <SECONDS>  Pause while the circuit breaker is open (default: 300)");
+                    println!("  --targets <LIST>          Fan out to receivers, e.g. fra:10.1.1.10:8080,lon:10.2.2.10:8080");
+                    println!("  --transport <KIND>        udp, tcp, dual (UDP + TCP, receiver dedups), ws, wss, uds or grpc (default: udp)");
+                    println!("  --uds-path <PATH>         Receiver socket for --transport uds, on the same host (default: {})", DEFAULT_UDS_PATH);
+                    println!("  --tls                     Encrypt the TCP path with TLS (requires --tls-ca)");
+                    println!("  --tls-ca <PEM>            CA certificate(s) trusted for receiver certificates");
+                    println!("  --tls-server-name <NAME>  Name to verify in the receiver certificate (default: target host)");
+                    println!("  --replay <FILE>           Forward frames from a capture file with their original timing");
+                    println!("  --capture <FILE>          Record raw exchange frames as JSON lines (.gz to compress)");
+                    println!("  --arrival-log <FILE>      Record each event's update ID and receive time, for the receiver's merge subcommand");
+                    println!("  --rotate-size <SIZE>      Start a new capture file at this size, e.g. 500MB");
+                    println!("  --rotate-interval <TIME>  Start a new capture file after this long, e.g. 1h");
+                    println!(
+                        "  --rotate-compress         Gzip capture files once they are rotated"
+                    );
+                    println!("  --udp-sndbuf <BYTES>      SO_SNDBUF for the UDP path");
+                    println!("  --udp-tos <BYTE>          IP TOS byte for the UDP path, e.g. 0xb8 (DSCP EF)");
+                    println!("  --dscp <0-63>             DSCP marking for the UDP and TCP paths, recorded in the receiver's results");
+                    println!("  --dont-fragment           Set DF on UDP datagrams instead of letting them fragment");
+                    println!("  --udp-max-datagram <BYTES>  Split larger events into fragments the receiver reassembles, e.g. 1400");
+                    println!("  --no-tcp-nodelay          Leave Nagle's algorithm on for the TCP and WSS paths");
+                    println!("  --tcp-sndbuf <BYTES>      SO_SNDBUF for the TCP and WSS paths");
+                    println!("  --tcp-keepalive <SECONDS>  Send TCP keepalive probes after this long idle, and as often");
+                    println!("  --retry-buffer <N>        Events kept per TCP/WSS/UDS receiver while it reconnects, 0 disables (default: 10000)");
+                    println!("  --heartbeat-ms <MS>       Send heartbeats on the TCP path this often; reconnect if {} go unacknowledged", HEARTBEAT_ACK_INTERVALS);
+                    println!("  --write-timeout-ms <MS>   Reconnect when a TCP write takes longer than this");
+                    println!("  --stage-timestamps        Send parse/serialize/send timestamps for a latency budget");
+                    println!("  --fast-parse              Read only the event/trade time and symbol from each frame (Binance)");
+                    println!("  --synthetic-rate <N/s>    Forward generated bookTicker events at this rate instead of the exchange feed");
+                    println!("  --synthetic-ramp <N/s>    Raise the synthetic rate by this much every second (default: 0)");
+                    println!("  --max-rate <N/s>          Pace forwarded events with a token bucket, e.g. 500/s");
+                    println!("  --burst <N>               Events --max-rate may send back to back (default: 1)");
+                    println!("  --pace-queue <N>          Events waiting for pacing before the oldest is dropped (default: 1000)");
+                    println!("  --send-queue <N>          Queue up to N events per receiver, sent by a separate task, and report queue delay");
+                    println!("  --send-queue-high <N>     Queue depth logged as a watermark episode (default: 3/4 of --send-queue)");
+                    println!("  --send-queue-low <N>      Queue depth that ends the episode (default: 1/4 of --send-queue)");
+                    println!("  --chaos <SPEC>            Drop, duplicate, delay or reorder a percentage of events, e.g. drop=1,duplicate=0.5,delay=2,reorder=1");
+                    println!(
+                        "  --chaos-delay-ms <MS>     How long --chaos delays events (default: 100)"
+                    );
+                    println!("  --chaos-seed <N>          Seed for --chaos, to repeat a run's pattern (default: from the clock, logged)");
+                    println!("  --status-file <FILE>      Write local event rate, exchange latency and failures as JSON");
+                    println!("  --status-interval <SECONDS>  How often --status-file is rewritten (default: 1)");
+                    println!("  --state-file <FILE>       Keep restarts, panics, session uptimes and events forwarded across processes");
+                    println!("  --ping-interval <SECONDS>  WebSocket ping to the exchange for a round-trip time, 0 disables (default: 5)");
+                    println!("  --ws-compression <on|off>  Offer permessage-deflate to the exchange (default: off)");
+                    println!("  --exchange-rollover <TIME|off>  Replace the exchange connection after this long using a standby connection (default: 1435m for Binance, off otherwise)");
+                    println!("  --hot-spare               Read a second exchange connection and cut over to it when the primary stalls (Binance)");
+                    println!("  --stall-ms <MS>           Primary silence, while the spare delivers, that counts as a stall (default: 500)");
+                    println!("  --s3-upload <URL>         Upload the status and capture files to s3://bucket/prefix/<run id>/ at exit");
+                    println!("  --echo-port <PORT>        Echo UDP datagrams for the receiver's --path-probe");
+                    println!("  --ack-port <PORT>         Receive the receiver's --ack-every acknowledgements; RTT and clock offset go to the status file");
+                    println!("  --clock-sync <chrony|ptp>  Read the clock error during the run and send it with every event, for the receiver's corrected one-way delay");
+                    println!("  --clock-sync-interval <SECONDS>  How often --clock-sync reads the clock error (default: 30)");
+                    println!("  --run-id <ID>             Run ID sent with every event and used for --s3-upload (default: random UUID)");
+                    println!("  --forwarder-id <NAME>     Name sent with every event, for a receiver run with --multi-forwarder");
+                    println!("  --region-name <LABEL>     Region announced to receivers when connecting (default: tokyo)");
+                    println!("  --log-level <FILTER>      error, warn, info, debug, trace or a tracing filter (default: info)");
+                    println!("  --log-json                Write logs to stderr as JSON lines");
+                    println!("  --help, -h                Show this help message");
+                    return Ok(None);
+                }
+                _ => {
+                    return Err(ExperimentError::other(format!(
+                        "Unknown argument: {}\nUse --help for usage information",
+                        args[i]
+                    )));
+                }
+            }
+        }
+
+        if !config.sockets.udp.is_default()
+            && !matches!(config.transport, Transport::Udp | Transport::Dual)
+        {
+            return Err(ExperimentError::other(
+                "UDP socket options require --transport udp or dual",
+            ));
+        }
+
+        if !config.sockets.tcp.is_default()
+            && !matches!(
+                config.transport,
+                Transport::Tcp | Transport::Dual | Transport::Ws | Transport::Wss
+            )
+        {
+            return Err(ExperimentError::other(
+                "TCP socket options require --transport tcp, dual, ws or wss",
+            ));
+        }
+        if config.sockets.tcp.keepalive == Some(0) {
+            return Err(ExperimentError::other("--tcp-keepalive must be at least 1"));
+        }
+
+        // Applied after the checks above, as it covers both paths
+        if let Some(dscp) = config.dscp {
+            if dscp > 63 {
+                return Err(ExperimentError::other("--dscp must be between 0 and 63"));
+            }
+            if config.sockets.udp.tos.is_some() {
+                return Err(ExperimentError::other(
+                    "--dscp cannot be combined with --udp-tos",
+                ));
+            }
+            let tos = Some(dscp << 2);
+            if matches!(config.transport, Transport::Udp | Transport::Dual) {
+                config.sockets.udp.tos = tos;
+            }
+            config.sockets.tcp.tos = tos;
+        }
+
+        if let Some(max) = config.udp_max_datagram {
+            if !matches!(config.transport, Transport::Udp | Transport::Dual) {
+                return Err(ExperimentError::other(
+                    "--udp-max-datagram requires --transport udp or dual",
+                ));
+            }
+            if !(256..=65507).contains(&max) {
+                return Err(ExperimentError::other(
+                    "--udp-max-datagram must be between 256 and 65507",
+                ));
+            }
+        }
+
+        if config.liveness.heartbeat.is_some() || config.liveness.write_timeout.is_some() {
+            if !matches!(
+                config.transport,
+                Transport::Tcp | Transport::Dual | Transport::Uds
+            ) {
+                return Err(ExperimentError::other(
+                    "--heartbeat-ms and --write-timeout-ms require --transport tcp, dual or uds",
+                ));
+            }
+            if config.liveness.heartbeat == Some(Duration::ZERO)
+                || config.liveness.write_timeout == Some(Duration::ZERO)
+            {
+                return Err(ExperimentError::other(
+                    "--heartbeat-ms and --write-timeout-ms must be at least 1",
+                ));
+            }
+        }
+
+        if let Some(events) = retry_buffer {
+            if config.transport == Transport::Udp {
+                return Err(ExperimentError::other(
+                    "--retry-buffer requires --transport tcp, dual, ws, wss, uds or grpc",
+                ));
+            }
+            config.retry_buffer = events;
+        }
+
+        match max_rate {
+            Some(max_rate) => {
+                let pacing = PacingConfig {
+                    max_rate,
+                    burst: burst.unwrap_or(1),
+                    queue: pace_queue.unwrap_or(1000),
+                };
+                if pacing.burst == 0 || pacing.queue == 0 {
+                    return Err(ExperimentError::other(
+                        "--burst and --pace-queue must be at least 1",
+                    ));
+                }
+                // Queued events would be sent after the next one is serialized
+                if config.stage_timestamps {
+                    return Err(ExperimentError::other(
+                        "--stage-timestamps cannot be combined with --max-rate",
+                    ));
+                }
+                config.pacing = Some(pacing);
+            }
+            None if burst.is_some() || pace_queue.is_some() => {
+                return Err(ExperimentError::other(
+                    "--burst and --pace-queue require --max-rate",
+                ));
+            }
+            None => {}
+        }
+
+        match send_queue {
+            Some(capacity) => {
+                let queue = SendQueueConfig::new(capacity, send_queue_high, send_queue_low)
+                    .map_err(ExperimentError::other)?;
+                // The send stage would end once the event is queued
+                if config.stage_timestamps {
+                    return Err(ExperimentError::other(
+                        "--stage-timestamps cannot be combined with --send-queue",
+                    ));
+                }
+                config.send_queue = Some(queue);
+            }
+            None if send_queue_high.is_some() || send_queue_low.is_some() => {
+                return Err(ExperimentError::other(
+                    "--send-queue-high and --send-queue-low require --send-queue",
+                ));
+            }
+            None => {}
+        }
+
+        match config.chaos.as_mut() {
+            Some(chaos) => {
+                if chaos_delay_ms == Some(0) {
+                    return Err(ExperimentError::other(
+                        "--chaos-delay-ms must be at least 1",
+                    ));
+                }
+                // Stage timestamps describe the event sent just before, which
+                // chaos may have dropped or held back
+                if config.stage_timestamps {
+                    return Err(ExperimentError::other(
+                        "--stage-timestamps cannot be combined with --chaos",
+                    ));
+                }
+                if let Some(ms) = chaos_delay_ms {
+                    chaos.delay_by = Duration::from_millis(ms);
+                }
+                chaos.seed = chaos_seed.unwrap_or_else(|| now_nanos() as u64);
+            }
+            None if chaos_delay_ms.is_some() || chaos_seed.is_some() => {
+                return Err(ExperimentError::other(
+                    "--chaos-delay-ms and --chaos-seed require --chaos",
+                ));
+            }
+            None => {}
+        }
+
+        match (&mut config.synthetic, synthetic_ramp) {
+            (Some(synthetic), ramp) => {
+                synthetic.ramp = ramp.unwrap_or(0.0);
+                // Frames are made up in Binance's format, and nothing is read from the exchange
+                if !config.exchange.eq_ignore_ascii_case("binance") {
+                    return Err(ExperimentError::other(
+                        "--synthetic-rate is only supported for Binance",
+                    ));
+                }
+                if config.replay.is_some()
+                    || config.capture.is_some()
+                    || config.arrival_log.is_some()
+                    || hot_spare
+                {
+                    return Err(ExperimentError::other("--synthetic-rate cannot be combined with --replay, --capture, --arrival-log or --hot-spare"));
+                }
+            }
+            (None, Some(_)) => {
+                return Err(ExperimentError::other(
+                    "--synthetic-ramp requires --synthetic-rate",
+                ));
+            }
+            (None, None) => {}
+        }
+
+        if config.arrival_log.is_some() && config.replay.is_some() {
+            return Err(ExperimentError::other(
+                "--arrival-log cannot be combined with --replay",
+            ));
+        }
+
+        if config.rotation != RotationPolicy::default() && config.capture.is_none() {
+            return Err(ExperimentError::other(
+                "--rotate-size, --rotate-interval and --rotate-compress require --capture",
+            ));
+        }
+
+        match status_interval {
+            Some(0) => {
+                return Err(ExperimentError::other(
+                    "--status-interval must be at least 1",
+                ));
+            }
+            Some(_) if config.status_file.is_none() => {
+                return Err(ExperimentError::other(
+                    "--status-interval requires --status-file",
+                ));
+            }
+            Some(secs) => config.status_interval = Duration::from_secs(secs),
+            None => {}
+        }
+
+        match clock_sync_interval {
+            Some(0) => {
+                return Err(ExperimentError::other(
+                    "--clock-sync-interval must be at least 1",
+                ));
+            }
+            Some(_) if config.clock_sync.is_none() => {
+                return Err(ExperimentError::other(
+                    "--clock-sync-interval requires --clock-sync",
+                ));
+            }
+            Some(secs) => config.clock_sync_interval = Duration::from_secs(secs),
+            None => {}
+        }
+
+        if config.fast_parse && !config.exchange.eq_ignore_ascii_case("binance") {
+            return Err(ExperimentError::other(
+                "--fast-parse is only supported for Binance",
+            ));
+        }
+
+        if config.transport == Transport::Uds {
+            // A local socket: no network path to encrypt, mark or fan out over
+            if config.tls {
+                return Err(ExperimentError::other(
+                    "--tls cannot be combined with --transport uds",
+                ));
+            }
+            if config.dscp.is_some() {
+                return Err(ExperimentError::other(
+                    "--dscp cannot be combined with --transport uds",
+                ));
+            }
+            if !config.targets.is_empty() {
+                return Err(ExperimentError::other(
+                    "--targets cannot be combined with --transport uds; use --uds-path",
+                ));
+            }
+        } else if config.uds_path.is_some() {
+            return Err(ExperimentError::other(
+                "--uds-path requires --transport uds",
+            ));
+        }
+
+        if config.transport == Transport::Grpc {
+            // tonic owns the connection, so there are no socket options to set
+            if config.tls {
+                return Err(ExperimentError::other(
+                    "--tls cannot be combined with --transport grpc",
+                ));
+            }
+            if config.dscp.is_some() {
+                return Err(ExperimentError::other(
+                    "--dscp cannot be combined with --transport grpc",
+                ));
+            }
+        }
+
+        if (config.tls || config.transport.requires_tls()) && config.tls_ca.is_none() {
+            return Err(ExperimentError::other("TLS requires --tls-ca"));
+        }
+
+        if hot_spare {
+            if !config.exchange.eq_ignore_ascii_case("binance") {
+                return Err(ExperimentError::other(
+                    "--hot-spare is only supported for Binance",
+                ));
+            }
+            if config.replay.is_some() {
+                return Err(ExperimentError::other(
+                    "--hot-spare cannot be combined with --replay",
+                ));
+            }
+            // Update IDs only increase within one stream
+            if config
+                .ws_url
+                .as_deref()
+                .is_some_and(|url| url.contains("/stream?"))
+            {
+                return Err(ExperimentError::other(
+                    "--hot-spare requires a single stream, not a combined stream URL",
+                ));
+            }
+            // The spare already covers the 24-hour reset of either connection
+            if exchange_rollover.is_some() {
+                return Err(ExperimentError::other(
+                    "--hot-spare cannot be combined with --exchange-rollover",
+                ));
+            }
+            if stall_ms == Some(0) {
+                return Err(ExperimentError::other("--stall-ms must be at least 1"));
+            }
+            let stall_ms = stall_ms.unwrap_or(DEFAULT_STALL_MS);
+            config.hot_spare = Some(Duration::from_millis(stall_ms));
+        } else if stall_ms.is_some() {
+            return Err(ExperimentError::other("--stall-ms requires --hot-spare"));
+        } else {
+            config.exchange_rollover =
+                exchange_rollover.unwrap_or_else(|| default_rollover(&config.exchange));
+        }
+
+        let run_id = run_id.unwrap_or_else(new_run_id);
+        config.run_id = Box::leak(run_id.into_boxed_str());
+
+        Ok(Some(config))
+    }
+
+    /// TLS client for the receiver leg, if TLS is in use
+    fn tls_client(&self) -> Result<Option<TlsClient>, std::io::Error> {
+        if !self.tls && !self.transport.requires_tls() {
+            return Ok(None);
+        }
+        let ca = self.tls_ca.as_deref().unwrap_or_default();
+        TlsClient::new(ca, self.tls_server_name.as_deref()).map(Some)
+    }
+
+    /// Adapter for the configured exchange (validated while parsing arguments)
+    fn adapter(&self) -> Box<dyn ExchangeAdapter> {
+        if self.fast_parse {
+            return Box::new(BinanceFastParse);
+        }
+        exchange_adapter(&self.exchange).expect("exchange validated in from_args")
+    }
+
+    /// WebSocket URL to connect to
+    fn ws_url(&self) -> String {
+        self.ws_url
+            .clone()
+            .unwrap_or_else(|| self.adapter().stream_url(&self.symbol))
+    }
+
+    /// Exchange streams announced to receivers
+    fn streams(&self) -> Vec<String> {
+        let url = self.ws_url();
+        match split_stream_url(&url) {
+            Some((_, stream)) => vec![stream.to_string()],
+            None => vec![format!("{} {}", self.exchange, self.symbol)],
+        }
+    }
+
+    /// The handshake that opens every receiver connection
+    fn hello(&self) -> String {
+        ForwarderHello {
+            forwarder_id: self.forwarder_id.map(str::to_string),
+            region: self.region_name.clone(),
+            streams: self.streams(),
+            wire_format: WIRE_FORMAT_VERSION,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            run_id: Some(self.run_id.to_string()),
+        }
+        .to_json()
+    }
+
+    /// Receivers to forward to; defaults to the single Frankfurt endpoint
+    fn targets(&self) -> Vec<Target> {
+        if !self.targets.is_empty() {
+            return self.targets.clone();
+        }
+        if self.transport == Transport::Uds {
+            return vec![Target {
+                region: "local".to_string(),
+                addr: self
+                    .uds_path
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_UDS_PATH.to_string()),
+            }];
+        }
+        vec![Target {
+            region: "frankfurt".to_string(),
+            addr: format!("{}:{}", self.frankfurt_ip, self.frankfurt_port),
+        }]
+    }
+}
+
+/// Value following the flag at `args[i]`, or an error if it is missing
+fn flag_value(args: &[String], i: usize) -> Result<&str, ExperimentError> {
+    args.get(i + 1)
+        .map(String::as_str)
+        .ok_or_else(|| ExperimentError::other(format!("{} requires a value", args[i])))
+}
+
+/// Parse the value following the flag at `args[i]`
+fn parse_flag<T: std::str::FromStr>(
+    args: &[String],
+    i: usize,
+    what: &str,
+) -> Result<T, ExperimentError> {
+    flag_value(args, i)?
+        .parse()
+        .map_err(|_| ExperimentError::other(format!("Invalid {}", what)))
+}
+
+/// Run the forwarder on command-line arguments, the program name first.
+/// Invalid arguments and a run that gave up are returned; only `main` exits.
+pub async fn run(args: Vec<String>) -> Result<(), ExperimentError> {
+    let Some(config) = Config::from_args(args)? else {
+        return Ok(()); // --help
+    };
+    init_logging(&config.log_level, config.log_json).map_err(ExperimentError::other)?;
+    let mut shutdown = Shutdown::install();
+
+    info!(
+        exchange = %config.exchange,
+        symbol = %config.symbol,
+        ws_url = %config.ws_url(),
+        run_id = config.run_id,
+        forwarder_id = config.forwarder_id,
+        "Tokyo forwarder starting"
+    );
+    for target in config.targets() {
+        info!(
+            region = %target.region,
+            addr = %target.addr,
+            transport = config.transport.as_str(),
+            "forwarding target"
+        );
+    }
+
+    if let Some(port) = config.echo_port {
+        match echo::bind(port).await {
+            Ok(socket) => {
+                tokio::spawn(echo::serve(socket));
+            }
+            Err(e) => {
+                error!(port, error = %e, "failed to bind echo port");
+                return Err(e.into());
+            }
+        }
+    }
+
+    if config.s3_upload.is_some() {
+        check_aws_cli().await.map_err(ExperimentError::other)?;
+    }
+    let started = SystemTime::now();
+
+    let counters = Arc::new(Counters {
+        supervisor: Supervisor::load(config.state_file.as_deref()),
+        ..Counters::default()
+    });
+    if config.state_file.is_some() {
+        supervision::install_panic_hook(counters.clone());
+    }
+    if let Some(port) = config.ack_port {
+        match acks::bind(port).await {
+            Ok(socket) => {
+                tokio::spawn(acks::serve(socket, counters.clone()));
+            }
+            Err(e) => {
+                error!(port, error = %e, "failed to bind ack port");
+                return Err(e.into());
+            }
+        }
+    }
+    if let Some(source) = config.clock_sync {
+        let _ = counters
+            .clock
+            .set(ClockMonitor::start(source, config.clock_sync_interval));
+    }
+    if let Some(chaos) = config.chaos {
+        warn!(seed = chaos.seed, chaos = %chaos, "chaos enabled, events will be disturbed");
+        *counters.chaos.lock().unwrap() = Some(ChaosStats::default());
+    }
+    let status = StatusReporter::new(
+        config.status_file.clone(),
+        config.run_id,
+        &config.exchange,
+        &config.symbol,
+    );
+    let status_task = status
+        .clone()
+        .spawn(counters.clone(), config.status_interval);
+
+    if let Some(synthetic) = config.synthetic {
+        counters.start_session();
+        let result = run_synthetic(&config, synthetic, counters.clone(), shutdown).await;
+        if let Err(e) = &result {
+            error!(error = %e, "synthetic run failed");
+        }
+        counters.end_session(result.err());
+        status_task.abort();
+        status.finish(&counters, None);
+        counters.print_summary(config.pacing.is_some(), None);
+        upload_outputs(&config, started).await;
+        return Ok(());
+    }
+
+    if let Some(path) = &config.replay {
+        counters.start_session();
+        let result = run_replay(&config, path, counters.clone(), shutdown).await;
+        if let Err(e) = &result {
+            error!(error = %e, "replay failed");
+        }
+        counters.end_session(result.err());
+        status_task.abort();
+        status.finish(&counters, None);
+        counters.print_summary(config.pacing.is_some(), None);
+        upload_outputs(&config, started).await;
+        return Ok(());
+    }
+
+    let mut capture = match config
+        .capture
+        .as_deref()
+        .map(|path| CaptureWriter::create_rotating(path, config.rotation))
+    {
+        Some(Ok(writer)) => Some(writer),
+        Some(Err(e)) => {
+            error!(error = %e, "failed to create capture file");
+            return Err(e.into());
+        }
+        None => None,
+    };
+    let mut arrivals = match config.arrival_log.as_deref().map(ArrivalLog::create) {
+        Some(Ok(log)) => Some(log),
+        Some(Err(e)) => {
+            error!(error = %e, "failed to create arrival log");
+            return Err(e.into());
+        }
+        None => None,
+    };
+
+    // Restarts share the exchange backoff, so a forwarder that keeps failing
+    // slows down, trips the breaker and eventually honours the attempt limit.
+    // Errors that cannot go away by themselves stop it straight away.
+    let mut backoff = Backoff::new(config.reconnect);
+    let mut rollover = Rollover::new(&config.exchange, config.exchange_rollover);
+    let mut gave_up = None;
+    while !shutdown.is_triggered() {
+        counters.start_session();
+        let result = run_forwarder(
+            config.clone(),
+            counters.clone(),
+            &mut capture,
+            &mut arrivals,
+            &mut backoff,
+            &mut rollover,
+            shutdown.clone(),
+        )
+        .await;
+        counters.end_session(result.as_ref().err());
+        if let Err(e) = result {
+            if !e.is_retryable() {
+                error!(error = %e, "forwarder failed, not retrying");
+                gave_up = Some(e);
+                break;
+            }
+            let Some(delay) = backoff.next_delay() else {
+                error!(
+                    error = %e,
+                    failures = backoff.consecutive_failures() - 1,
+                    "forwarder failed, giving up"
+                );
+                gave_up = Some(e);
+                break;
+            };
+            error!(
+                error = %e,
+                delay_ms = delay.as_millis() as u64,
+                breaker_open = backoff.breaker_open(),
+                "forwarder failed, restarting"
+            );
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = shutdown.wait() => {}
+            }
+        }
+    }
+
+    if let (Some(capture), Some(path)) = (capture, &config.capture) {
+        let frames = capture.frames();
+        match capture.finish() {
+            Ok(()) => info!(frames, path = %path, "capture written"),
+            Err(e) => error!(error = %e, "failed to finish capture file"),
+        }
+    }
+    if let (Some(arrivals), Some(path)) = (arrivals, &config.arrival_log) {
+        let rows = arrivals.rows();
+        match arrivals.finish() {
+            Ok(()) => info!(rows, path = %path, "arrival log written"),
+            Err(e) => error!(error = %e, "failed to finish arrival log"),
+        }
+    }
+
+    status_task.abort();
+    status.finish(&counters, Some(backoff.stats()));
+    counters.print_summary(config.pacing.is_some(), Some(backoff.stats()));
+    upload_outputs(&config, started).await;
+    match gave_up {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Copy the status and capture files to S3 if --s3-upload was given
+async fn upload_outputs(config: &Config, started: SystemTime) {
+    let Some(s3) = &config.s3_upload else {
+        return;
+    };
+    let mut files: Vec<PathBuf> = config.status_file.iter().map(PathBuf::from).collect();
+    if let Some(capture) = &config.capture {
+        files.extend(output_files(capture, started));
+    }
+    files.extend(config.arrival_log.iter().map(PathBuf::from));
+    let uploaded = s3.upload(config.run_id, &files, started).await;
+    info!(uploaded, destination = %s3.url(config.run_id, Path::new("")), "S3 upload finished");
+}
+
+async fn run_forwarder(
+    config: Config,
+    counters: Arc<Counters>,
+    capture: &mut Option<CaptureWriter>,
+    arrivals: &mut Option<ArrivalLog>,
+    backoff: &mut Backoff,
+    rollover: &mut Rollover,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    let mut pipeline = Pipeline::connect(&config, counters).await?;
+
+    // Connect to the exchange WebSocket
+    let mut ws_stream = tokio::select! {
+        stream = connect_to_exchange(&config, &pipeline.counters.exchange_addresses) => stream?,
+        _ = shutdown.wait() => return Ok(()),
+    };
+    info!(
+        exchange = pipeline.adapter.name(),
+        ws_compressed = ws_stream.get_ref().compressed(),
+        "connected to exchange WebSocket"
+    );
+    backoff.succeeded();
+    rollover.reconnected();
+
+    // One span per exchange connection so reconnects can be told apart in logs
+    let mut conn_id = 0u64;
+    loop {
+        let span = info_span!("exchange", conn_id, exchange = pipeline.adapter.name());
+        let ended = pump(
+            &mut ws_stream,
+            &mut pipeline,
+            capture,
+            arrivals,
+            rollover,
+            &config,
+            &mut shutdown,
+        )
+        .instrument(span.clone())
+        .await;
+        if ended == Ended::Shutdown {
+            break;
+        }
+        ws_stream = match reconnect_to_exchange(
+            &config,
+            &pipeline.counters.exchange_addresses,
+            backoff,
+            &mut shutdown,
+        )
+        .instrument(span)
+        .await?
+        {
+            Some(stream) => stream,
+            None => break,
+        };
+        rollover.reconnected();
+        conn_id += 1;
+    }
+
+    pipeline.close().await;
+    // Sockets to the receivers are closed when the senders go out of scope here
+    Ok(())
+}
+
+/// Why `pump` stopped reading from an exchange connection
+#[derive(Debug, PartialEq, Eq)]
+enum Ended {
+    Shutdown,
+    Disconnected,
+}
+
+/// Forward frames from one exchange connection until it ends or shutdown is
+/// requested. Once the connection reaches the --exchange-rollover age a
+/// standby is opened next to it, and takes its place without a gap.
+async fn pump(
+    ws_stream: &mut ExchangeStream,
+    pipeline: &mut Pipeline,
+    capture: &mut Option<CaptureWriter>,
+    arrivals: &mut Option<ArrivalLog>,
+    rollover: &mut Rollover,
+    config: &Config,
+    shutdown: &mut Shutdown,
+) -> Ended {
+    pipeline.ws_compressed = ws_stream.get_ref().compressed();
+    let ping_interval = config.ping_interval;
+    let mut next_ping = ping_interval.map(|period| Instant::now() + period);
+    let mut opening: Option<JoinHandle<Result<ExchangeStream, ExperimentError>>> = None;
+    let mut standby: Option<ExchangeStream> = None;
+    let mut spare_opening: Option<JoinHandle<Result<ExchangeStream, ExperimentError>>> = None;
+    let mut spare: Option<ExchangeStream> = None;
+    let mut spare_due = None;
+    if let Some(hot_spare) = pipeline.hot_spare.as_mut() {
+        hot_spare.reconnected();
+        spare_due = Some(Instant::now());
+    }
+    loop {
+        let due = pipeline.next_due();
+        let standby_due = rollover.standby_due().filter(|_| opening.is_none());
+        let msg_result = tokio::select! {
+            msg = ws_stream.next() => match msg {
+                Some(msg) => msg,
+                None => {
+                    warn!("WebSocket stream ended");
+                    if let Some(stream) = spare.take() {
+                        cut_over(ws_stream, stream, CutoverReason::Disconnect, pipeline, capture, arrivals).await;
+                        spare_due = Some(Instant::now());
+                        continue;
+                    }
+                    return Ended::Disconnected;
+                }
+            },
+            _ = until(due) => {
+                pipeline.send_due(Instant::now()).await;
+                continue;
+            }
+            _ = until(next_ping) => {
+                let payload = pipeline.counters.exchange_ping.lock().unwrap().payload(now_nanos());
+                if let Err(e) = ws_stream.send(Message::Ping(payload)).await {
+                    debug!(error = %e, "failed to send ping");
+                }
+                next_ping = ping_interval.map(|period| Instant::now() + period);
+                continue;
+            }
+            _ = until(standby_due) => {
+                info!("opening standby exchange connection");
+                let config = config.clone();
+                let addresses = pipeline.counters.exchange_addresses.clone();
+                opening = Some(tokio::spawn(async move { connect_to_exchange(&config, &addresses).await }));
+                continue;
+            }
+            opened = async { opening.as_mut().unwrap().await }, if opening.is_some() => {
+                opening = None;
+                match opened {
+                    Ok(Ok(stream)) => {
+                        info!("standby exchange connection open");
+                        rollover.standby_opened();
+                        standby = Some(stream);
+                    }
+                    Ok(Err(e)) => {
+                        warn!(error = %e, "standby exchange connection failed");
+                        rollover.standby_failed();
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "standby exchange connection task failed");
+                        rollover.standby_failed();
+                    }
+                }
+                continue;
+            }
+            msg = async { standby.as_mut().unwrap().next().await }, if standby.is_some() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let received = now_nanos();
+                        if let Some(ahead) = rollover.standby_frame(text, received) {
+                            take_over(ws_stream, standby.take().unwrap());
+                            pipeline.ws_compressed = ws_stream.get_ref().compressed();
+                            next_ping = ping_interval.map(|period| Instant::now() + period);
+                            for (text, received) in ahead {
+                                receive(pipeline, capture, arrivals, text, received).await;
+                            }
+                            pipeline.counters.record_handovers(rollover);
+                        }
+                    }
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => {
+                        warn!("standby exchange connection closed before taking over");
+                        standby = None;
+                        rollover.standby_failed();
+                    }
+                    Some(Ok(_)) => {}
+                }
+                continue;
+            }
+            _ = until(spare_due) => {
+                spare_due = None;
+                let config = config.clone();
+                let addresses = pipeline.counters.exchange_addresses.clone();
+                spare_opening = Some(tokio::spawn(async move { connect_to_exchange(&config, &addresses).await }));
+                continue;
+            }
+            opened = async { spare_opening.as_mut().unwrap().await }, if spare_opening.is_some() => {
+                spare_opening = None;
+                match opened {
+                    Ok(Ok(stream)) => {
+                        info!("spare exchange connection open");
+                        spare = Some(stream);
+                    }
+                    Ok(Err(e)) => {
+                        warn!(error = %e, "spare exchange connection failed");
+                        spare_due = Some(Instant::now() + SPARE_RETRY);
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "spare exchange connection task failed");
+                        spare_due = Some(Instant::now() + SPARE_RETRY);
+                    }
+                }
+                continue;
+            }
+            msg = async { spare.as_mut().unwrap().next().await }, if spare.is_some() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let received = now_nanos();
+                        let update_id = update_id(pipeline.adapter.as_ref(), &text);
+                        let hot_spare = pipeline.hot_spare.as_mut().expect("spare opened for --hot-spare");
+                        if hot_spare.spare(update_id, text, received) {
+                            cut_over(ws_stream, spare.take().unwrap(), CutoverReason::Stall, pipeline, capture, arrivals).await;
+                            spare_due = Some(Instant::now());
+                        }
+                    }
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => {
+                        warn!("spare exchange connection lost");
+                        spare = None;
+                        if let Some(hot_spare) = pipeline.hot_spare.as_mut() {
+                            hot_spare.spare_lost();
+                        }
+                        spare_due = Some(Instant::now() + SPARE_RETRY);
+                    }
+                    Some(Ok(_)) => {}
+                }
+                continue;
+            }
+            _ = shutdown.wait() => {
+                pipeline.discard_paced();
+                pipeline.release_chaos().await;
+                info!("closing exchange WebSocket");
+                if let Err(e) = ws_stream.close(None).await {
+                    warn!(error = %e, "failed to close WebSocket cleanly");
+                }
+                for mut extra in standby.into_iter().chain(spare) {
+                    let _ = extra.close(None).await;
+                }
+                return Ended::Shutdown;
+            }
+        };
+
+        match msg_result {
+            Ok(Message::Text(text)) => {
+                // Record timestamp immediately upon receiving message
+                let tokyo_receive_timestamp = now_nanos();
+                if let Some(hot_spare) = pipeline.hot_spare.as_mut() {
+                    if !hot_spare.primary(update_id(pipeline.adapter.as_ref(), &text)) {
+                        continue; // Already forwarded from the spare
+                    }
+                }
+                match rollover.current_frame(&text, tokyo_receive_timestamp) {
+                    CurrentFrame::Pass => {
+                        receive(pipeline, capture, arrivals, text, tokyo_receive_timestamp).await;
+                    }
+                    CurrentFrame::Skip => {}
+                    CurrentFrame::PassThenSwitch(ahead) => {
+                        receive(pipeline, capture, arrivals, text, tokyo_receive_timestamp).await;
+                        take_over(ws_stream, standby.take().unwrap());
+                        pipeline.ws_compressed = ws_stream.get_ref().compressed();
+                        next_ping = ping_interval.map(|period| Instant::now() + period);
+                        for (text, received) in ahead {
+                            receive(pipeline, capture, arrivals, text, received).await;
+                        }
+                    }
+                }
+                pipeline.counters.record_handovers(rollover);
+            }
+            Ok(Message::Pong(payload)) => {
+                let received = now_nanos();
+                let rtt_ms = pipeline
+                    .counters
+                    .exchange_ping
+                    .lock()
+                    .unwrap()
+                    .pong(&payload, received);
+                if let Some(rtt_ms) = rtt_ms {
+                    debug!(rtt_ms, "exchange pong");
+                }
+            }
+            Ok(Message::Close(_)) => {
+                if rollover.closed() {
+                    warn!("WebSocket closed by server at its 24-hour limit");
+                } else {
+                    warn!("WebSocket closed by server");
+                }
+                if let Some(stream) = spare.take() {
+                    cut_over(
+                        ws_stream,
+                        stream,
+                        CutoverReason::Disconnect,
+                        pipeline,
+                        capture,
+                        arrivals,
+                    )
+                    .await;
+                    spare_due = Some(Instant::now());
+                    continue;
+                }
+                return Ended::Disconnected;
+            }
+            Ok(_) => {
+                // Ignore other message types (Binary, Ping)
+            }
+            Err(e) => {
+                warn!(error = %e, "WebSocket error");
+                if let Some(stream) = spare.take() {
+                    cut_over(
+                        ws_stream,
+                        stream,
+                        CutoverReason::Disconnect,
+                        pipeline,
+                        capture,
+                        arrivals,
+                    )
+                    .await;
+                    spare_due = Some(Instant::now());
+                    continue;
+                }
+                return Ended::Disconnected;
+            }
+        }
+    }
+}
+
+/// Capture and forward one frame received at `tokyo_receive_timestamp`
+async fn receive(
+    pipeline: &mut Pipeline,
+    capture: &mut Option<CaptureWriter>,
+    arrivals: &mut Option<ArrivalLog>,
+    text: String,
+    tokyo_receive_timestamp: i64,
+) {
+    if let Some(capture) = capture {
+        if let Err(e) = capture.write(tokyo_receive_timestamp, &text) {
+            warn!(error = %e, "failed to capture frame");
+        }
+    }
+    pipeline
+        .forward(text, tokyo_receive_timestamp, 0, arrivals)
+        .await;
+}
+
+/// Make the --hot-spare connection the primary and forward what it delivered
+/// ahead of the old one
+async fn cut_over(
+    ws_stream: &mut ExchangeStream,
+    spare: ExchangeStream,
+    reason: CutoverReason,
+    pipeline: &mut Pipeline,
+    capture: &mut Option<CaptureWriter>,
+    arrivals: &mut Option<ArrivalLog>,
+) {
+    take_over(ws_stream, spare);
+    pipeline.ws_compressed = ws_stream.get_ref().compressed();
+    let hot_spare = pipeline
+        .hot_spare
+        .as_mut()
+        .expect("spare opened for --hot-spare");
+    let (frames, cutover) = hot_spare.cut_over(reason, now_nanos());
+    warn!(
+        %reason,
+        stall_ms = cutover.stall_ms,
+        recovered = cutover.recovered,
+        "cut over to the spare exchange connection"
+    );
+    pipeline
+        .counters
+        .exchange_cutovers
+        .lock()
+        .unwrap()
+        .push(cutover);
+    for (text, received) in frames {
+        receive(pipeline, capture, arrivals, text, received).await;
+    }
+}
+
+/// Make `standby` the current exchange connection, closing the old one in the background
+fn take_over(ws_stream: &mut ExchangeStream, standby: ExchangeStream) {
+    let mut old = std::mem::replace(ws_stream, standby);
+    tokio::spawn(async move {
+        if let Err(e) = old.close(None).await {
+            debug!(error = %e, "failed to close replaced exchange connection");
+        }
+    });
+}
+
+/// Forward frames from a capture file as if live, reproducing the original
+/// gaps between them
+async fn run_replay(
+    config: &Config,
+    path: &str,
+    counters: Arc<Counters>,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    let mut pipeline = Pipeline::connect(config, counters).await?;
+    info!(path, "replaying capture");
+
+    let mut start: Option<(i64, Instant)> = None;
+    let mut frames = 0u64;
+    'frames: for frame in read_capture(path)? {
+        let frame = frame?;
+
+        // Schedule each frame at its original offset from the first one
+        let (first_receive_time, started) =
+            *start.get_or_insert((frame.receive_time, Instant::now()));
+        let offset = Duration::from_nanos((frame.receive_time - first_receive_time).max(0) as u64);
+        loop {
+            let due = pipeline.next_due();
+            tokio::select! {
+                _ = sleep_until(started + offset) => break,
+                _ = until(due) => pipeline.send_due(Instant::now()).await,
+                _ = shutdown.wait() => {
+                    info!("stopping replay early");
+                    break 'frames;
+                }
+            }
+        }
+
+        // Move the event time forward so the original exchange → Tokyo delay is kept
+        let tokyo_receive_timestamp = now_nanos();
+        let event_time_shift_ns = tokyo_receive_timestamp - frame.receive_time;
+        pipeline
+            .forward(
+                frame.text,
+                tokyo_receive_timestamp,
+                event_time_shift_ns,
+                &mut None,
+            )
+            .await;
+        frames += 1;
+    }
+
+    // Let the pacer and chaos delays release whatever is still queued
+    while let Some(due) = pipeline.next_due() {
+        tokio::select! {
+            _ = sleep_until(due) => pipeline.send_due(Instant::now()).await,
+            _ = shutdown.wait() => break,
+        }
+    }
+    pipeline.discard_paced();
+    pipeline.release_chaos().await;
+    pipeline.close().await;
+
+    info!(frames, "replay complete");
+    Ok(())
+}
+
+/// Forward generated frames at the configured rate until shut down
+async fn run_synthetic(
+    config: &Config,
+    synthetic: SyntheticConfig,
+    counters: Arc<Counters>,
+    mut shutdown: Shutdown,
+) -> Result<(), ExperimentError> {
+    let mut pipeline = Pipeline::connect(config, counters).await?;
+    info!(
+        rate = synthetic.rate,
+        ramp = synthetic.ramp,
+        "forwarding synthetic events"
+    );
+
+    let mut feed = SyntheticFeed::new(synthetic, &config.symbol, Instant::now());
+    let mut frames = 0u64;
+    let mut logged_secs = 0;
+    'frames: loop {
+        loop {
+            let due = pipeline.next_due();
+            tokio::select! {
+                _ = sleep_until(feed.next_due()) => break,
+                _ = until(due) => pipeline.send_due(Instant::now()).await,
+                _ = shutdown.wait() => break 'frames,
+            }
+        }
+
+        let frame = feed.next_frame();
+        pipeline.forward(frame, now_nanos(), 0, &mut None).await;
+        frames += 1;
+
+        let elapsed = feed.elapsed();
+        if synthetic.ramp > 0.0 && elapsed.as_secs() > logged_secs {
+            logged_secs = elapsed.as_secs();
+            info!(rate = feed.rate_at(elapsed), "synthetic rate raised");
+        }
+    }
+
+    while let Some(due) = pipeline.next_due() {
+        tokio::select! {
+            _ = sleep_until(due) => pipeline.send_due(Instant::now()).await,
+            _ = shutdown.wait() => break,
+        }
+    }
+    pipeline.discard_paced();
+    pipeline.release_chaos().await;
+    pipeline.close().await;
+
+    info!(frames, "synthetic run complete");
+    Ok(())
+}
+
+/// Parses exchange frames and forwards them to every receiver
+struct Pipeline {
+    adapter: Box<dyn ExchangeAdapter>,
+    senders: Vec<ReceiverSender>,
+    queues: Vec<SendQueue>, // In place of `senders` with --send-queue
+    counters: Arc<Counters>,
+    events_without_time: u64,
+    transport: Transport,
+    run_id: &'static str,
+    forwarder_id: Option<&'static str>,
+    dscp: Option<u8>,
+    ws_compressed: bool, // Of the current exchange connection
+    replaying: bool,
+    stage_timestamps: bool,
+    previous_stages: Option<(i64, i64)>, // After-serialize and after-send of the last event
+    pacer: Option<Pacer>,
+    chaos: Option<Chaos>,
+    hot_spare: Option<HotSpare>,
+    names: HashSet<&'static str>, // Combined-stream names and symbols, leaked once so events can borrow them
+    serialized: Vec<u8>,          // Reused for every event
+}
+
+impl Drop for Pipeline {
+    /// Keep receiver reconnect, fragment and retry buffer counts across forwarder restarts
+    fn drop(&mut self) {
+        for sender in &self.senders {
+            self.counters.record_sender(sender);
+        }
+    }
+}
+
+impl Pipeline {
+    /// Set up the transport to every receiver
+    async fn connect(config: &Config, counters: Arc<Counters>) -> Result<Self, ExperimentError> {
+        let tls = config.tls_client()?;
+        // A receiver that is down is retried for as long as the forwarder runs
+        let recovery = Recovery {
+            reconnect: ReconnectPolicy {
+                max_attempts: None,
+                ..config.reconnect
+            },
+            retry_buffer: config.retry_buffer,
+            liveness: config.liveness,
+        };
+        let hello = config.hello();
+        let mut senders = Vec::new();
+        for target in config.targets() {
+            senders.push(
+                ReceiverSender::connect(
+                    config.transport,
+                    &target,
+                    tls.as_ref(),
+                    &config.sockets,
+                    config.udp_max_datagram,
+                    recovery,
+                    &hello,
+                )
+                .await
+                .map_err(ExperimentError::connect)?
+                .with_supervision(counters.clone()),
+            );
+        }
+        for sender in &senders {
+            counters.record_tcp_socket(sender);
+        }
+        let queues = match config.send_queue {
+            Some(queue) => senders
+                .drain(..)
+                .map(|sender| SendQueue::spawn(sender, queue, counters.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            adapter: config.adapter(),
+            senders,
+            queues,
+            counters,
+            events_without_time: 0,
+            transport: config.transport,
+            run_id: config.run_id,
+            forwarder_id: config.forwarder_id,
+            dscp: config.dscp,
+            ws_compressed: false,
+            replaying: config.replay.is_some(),
+            stage_timestamps: config.stage_timestamps,
+            previous_stages: None,
+            pacer: config.pacing.map(Pacer::new),
+            chaos: config.chaos.map(Chaos::new),
+            hot_spare: config.hot_spare.map(HotSpare::new),
+            names: HashSet::new(),
+            serialized: Vec::new(),
+        })
+    }
+
+    /// `name` with a static lifetime, leaked the first time it is seen; a
+    /// combined stream carries only a handful of stream names and symbols
+    fn static_name(&mut self, name: &str) -> &'static str {
+        if let Some(known) = self.names.get(name) {
+            return known;
+        }
+        let leaked: &'static str = Box::leak(name.into());
+        self.names.insert(leaked);
+        leaked
+    }
+
+    /// Parse one text frame and send it to every receiver. `event_time_shift_ns`
+    /// is added to the exchange event time, in its own unit (non-zero only when
+    /// replaying). Events with an update ID are recorded in `arrivals`.
+    async fn forward(
+        &mut self,
+        text: String,
+        tokyo_receive_timestamp: i64,
+        event_time_shift_ns: i64,
+        arrivals: &mut Option<ArrivalLog>,
+    ) {
+        // Parse the exchange event to get timestamp
+        let parsed = self.adapter.parse(&text);
+        let parsed_at = self.stage_timestamps.then(now_nanos);
+        let event = match parsed {
+            Ok(Some(event)) => event,
+            Ok(None) => {
+                // Subscription acknowledgements and other control frames
+                return;
+            }
+            Err(e) => {
+                self.counters.parse_failures.fetch_add(1, Ordering::SeqCst);
+                debug!(exchange = self.adapter.name(), error = %e, "failed to parse event");
+                return;
+            }
+        };
+
+        // Logged before the event time check: spot bookTicker frames have
+        // none but can still be matched by update ID
+        if let (Some(log), Some(update_id)) = (arrivals.as_mut(), event.update_id) {
+            let arrival = UpdateArrival {
+                update_id,
+                event_time: event.event_time,
+                receive_time: tokyo_receive_timestamp,
+            };
+            if let Err(e) = log.write(&arrival) {
+                warn!(error = %e, "failed to write arrival log");
+            }
+        }
+
+        // Spot bookTicker frames carry no event time and cannot be measured
+        let Some(binance_event_time) = event.event_time else {
+            if self.events_without_time == 0 {
+                warn!(
+                    exchange = event.exchange,
+                    "skipping events without event time"
+                );
+            }
+            self.events_without_time += 1;
+            return;
+        };
+
+        let binance_event_time = shift_event_time(binance_event_time, event_time_shift_ns);
+        let exchange_latency_ns = tokyo_receive_timestamp - event_time_nanos(binance_event_time);
+        self.counters
+            .exchange_latency
+            .lock()
+            .unwrap()
+            .push(exchange_latency_ns as f64 / 1_000_000.0);
+
+        // Assign sequence ID
+        let sequence_id = self
+            .counters
+            .next_sequence_id
+            .fetch_add(1, Ordering::SeqCst);
+
+        let stream = event.stream.map(|name| self.static_name(name));
+        let symbol = self.static_name(&event.symbol);
+
+        // Create forwarded event with the exchange's event time (as published)
+        let frame_bytes = text.len() as u32;
+        // forwarding_overhead_ns is appended once serialized, and
+        // send_queue_delay_ns when the event leaves the send queue
+        let forwarded_event = ForwardedEvent {
+            binance_transaction_time: event
+                .transaction_time
+                .map(|t| shift_event_time(t, event_time_shift_ns)),
+            transport: Some(self.transport.as_str().into()),
+            stages: parsed_at.map(|parsed| ForwarderStages {
+                parsed,
+                previous_serialized: self.previous_stages.map(|(serialized, _)| serialized),
+                previous_sent: self.previous_stages.map(|(_, sent)| sent),
+            }),
+            run_id: Some(self.run_id.into()),
+            dscp: self.dscp,
+            ws_compressed: self.ws_compressed,
+            replayed: self.replaying,
+            stream: stream.map(Cow::Borrowed),
+            symbol: Some(Cow::Borrowed(symbol)),
+            clock: self.counters.clock.get().and_then(ClockMonitor::latest),
+            frame_bytes: Some(frame_bytes),
+            forwarder_id: self.forwarder_id.map(Cow::Borrowed),
+            ..ForwardedEvent::new(
+                sequence_id,
+                tokyo_receive_timestamp,
+                binance_event_time,
+                text,
+            )
+        };
+
+        // Paced events wait in a queue, so each needs its own copy
+        if self.pacer.is_some() {
+            match serde_json::to_string(&forwarded_event) {
+                Ok(json) => {
+                    let now = Instant::now();
+                    let pacer = self.pacer.as_mut().unwrap();
+                    if let Some(dropped) =
+                        pacer.push(sequence_id, json, tokyo_receive_timestamp, now)
+                    {
+                        if self.counters.pacing_drops.fetch_add(1, Ordering::SeqCst) == 0 {
+                            warn!(
+                                sequence_id = dropped,
+                                "pacing queue full, dropping oldest events"
+                            );
+                        }
+                    }
+                    self.send_due(now).await;
+                }
+                Err(e) => error!(sequence_id, error = %e, "failed to serialize forwarded event"),
+            }
+            return;
+        }
+
+        // Serialize once, into a buffer reused for every event, and send to every receiver
+        let mut serialized = std::mem::take(&mut self.serialized);
+        serialized.clear();
+        match serde_json::to_writer(&mut serialized, &forwarded_event) {
+            Ok(()) => {
+                let serialized_at = self.stage_timestamps.then(now_nanos);
+                let overhead_ns = now_nanos() - tokyo_receive_timestamp;
+                ForwardedEvent::append_overhead(&mut serialized, overhead_ns);
+                let json = std::str::from_utf8(&serialized).expect("serde_json writes UTF-8");
+                self.dispatch(sequence_id, json).await;
+                // Reported with the next event, which follows this one in sequence
+                self.previous_stages = serialized_at.map(|serialized| (serialized, now_nanos()));
+            }
+            Err(e) => {
+                self.previous_stages = None;
+                error!(sequence_id, error = %e, "failed to serialize forwarded event");
+            }
+        }
+        self.serialized = serialized;
+    }
+
+    /// Send one event to every receiver, unless --chaos drops, duplicates,
+    /// delays or reorders it. An event held for reordering goes out right
+    /// after the next one that is sent.
+    async fn dispatch(&mut self, sequence_id: u64, json: &str) {
+        let Some(chaos) = self.chaos.as_mut() else {
+            self.send_all(sequence_id, json).await;
+            return;
+        };
+        let action = chaos.decide();
+        match action {
+            ChaosAction::Send => self.send_all(sequence_id, json).await,
+            ChaosAction::Drop => {}
+            ChaosAction::Duplicate => {
+                self.send_all(sequence_id, json).await;
+                self.send_all(sequence_id, json).await;
+            }
+            ChaosAction::Delay => chaos.delay(sequence_id, json, Instant::now()),
+            // Counted once another event has overtaken it
+            ChaosAction::Reorder => chaos.hold(sequence_id, json),
+        }
+        if action != ChaosAction::Reorder {
+            self.counters.record_chaos(action);
+        }
+        if matches!(action, ChaosAction::Send | ChaosAction::Duplicate) {
+            if let Some((held_id, held)) = self.chaos.as_mut().and_then(Chaos::take_reordered) {
+                self.send_all(held_id, &held).await;
+                self.counters.record_chaos(ChaosAction::Reorder);
+            }
+        }
+    }
+
+    async fn send_all(&mut self, sequence_id: u64, json: &str) {
+        for sender in &mut self.senders {
+            if let Err(e) = sender.send(sequence_id, json).await {
+                self.counters.send_failures.fetch_add(1, Ordering::SeqCst);
+                warn!(
+                    sequence_id,
+                    region = sender.region(),
+                    error = %e,
+                    "failed to send event"
+                );
+            }
+        }
+        for queue in &mut self.queues {
+            queue.push(sequence_id, json).await;
+        }
+    }
+
+    /// Send the paced events that have a token at `now`
+    async fn send_due(&mut self, now: Instant) {
+        while let Some((event, waited)) = self.pacer.as_mut().and_then(|p| p.pop_ready(now)) {
+            if !waited.is_zero() {
+                self.counters.paced_events.fetch_add(1, Ordering::SeqCst);
+                self.counters
+                    .max_pacing_delay_us
+                    .fetch_max(waited.as_micros() as u64, Ordering::SeqCst);
+            }
+            // Time spent waiting for a token counts as forwarder overhead
+            let mut json = event.json.into_bytes();
+            ForwardedEvent::append_overhead(&mut json, now_nanos() - event.received);
+            let json = String::from_utf8(json).expect("serde_json writes UTF-8");
+            self.dispatch(event.sequence_id, &json).await;
+        }
+        while let Some((sequence_id, json)) = self.chaos.as_mut().and_then(|c| c.pop_due(now)) {
+            self.send_all(sequence_id, &json).await;
+        }
+    }
+
+    /// When the next paced or chaos-delayed event is due, if any are queued
+    fn next_due(&self) -> Option<Instant> {
+        let paced = self.pacer.as_ref().and_then(Pacer::next_due);
+        let delayed = self.chaos.as_ref().and_then(Chaos::next_due);
+        paced.into_iter().chain(delayed).min()
+    }
+
+    /// Drop everything still waiting for pacing (on shutdown)
+    fn discard_paced(&mut self) {
+        let Some(pacer) = self.pacer.take() else {
+            return;
+        };
+        if pacer.len() > 0 {
+            info!(
+                events = pacer.len(),
+                "discarding events still queued for pacing"
+            );
+            self.counters
+                .pacing_drops
+                .fetch_add(pacer.len() as u64, Ordering::SeqCst);
+        }
+    }
+
+    /// Send the events chaos is still holding back without waiting (on
+    /// shutdown), so they are not mistaken for drops
+    async fn release_chaos(&mut self) {
+        let held = self.chaos.as_mut().map(Chaos::drain).unwrap_or_default();
+        for (sequence_id, json) in held {
+            self.send_all(sequence_id, &json).await;
+        }
+    }
+
+    /// Wait for the send queues to empty (on shutdown)
+    async fn close(&mut self) {
+        for queue in &mut self.queues {
+            queue.close().await;
+        }
+    }
+}
+
+/// Wait until `deadline`, or forever if there is none
+async fn until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Update ID of a frame, for matching it across exchange connections
+fn update_id(adapter: &dyn ExchangeAdapter, text: &str) -> Option<i64> {
+    adapter.parse(text).ok().flatten()?.update_id
+}
+
+/// Move an exchange timestamp by `shift_ns`, keeping the unit it was published in
+fn shift_event_time(event_time: i64, shift_ns: i64) -> i64 {
+    event_time + shift_ns / event_time_unit_nanos(event_time)
+}
+
+/// Current time in epoch nanoseconds
+fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as i64
+}
+
+/// Connect and, for venues that need it, send the subscription request.
+/// Where the connection landed is added to `addresses`.
+async fn connect_to_exchange(
+    config: &Config,
+    addresses: &ExchangeAddresses,
+) -> Result<ExchangeStream, ExperimentError> {
+    let adapter = config.adapter();
+    info!(
+        exchange = adapter.name(),
+        "connecting to exchange WebSocket"
+    );
+    let mut ws_stream = connect_exchange(&config.ws_url(), config.ws_compression).await?;
+    addresses.record(&ws_stream);
+    if let Some(subscribe) = adapter.subscribe_message(&config.symbol) {
+        ws_stream.send(Message::Text(subscribe)).await?;
+    }
+    Ok(ws_stream)
+}
+
+/// Reconnect following the backoff policy. Returns `None` if shutdown was
+/// requested before a connection could be established, and an error once
+/// the policy's attempt limit is reached.
+async fn reconnect_to_exchange(
+    config: &Config,
+    addresses: &ExchangeAddresses,
+    backoff: &mut Backoff,
+    shutdown: &mut Shutdown,
+) -> Result<Option<ExchangeStream>, ExperimentError> {
+    loop {
+        let delay = backoff.next_delay().ok_or_else(|| {
+            ExperimentError::connect(format!(
+                "no exchange connection after {} attempts",
+                backoff.consecutive_failures() - 1
+            ))
+        })?;
+        info!(
+            delay_ms = delay.as_millis() as u64,
+            breaker_open = backoff.breaker_open(),
+            "attempting to reconnect to exchange WebSocket"
+        );
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = shutdown.wait() => return Ok(None),
+        }
+
+        match connect_to_exchange(config, addresses).await {
+            Ok(stream) => {
+                info!("reconnected to exchange WebSocket");
+                backoff.succeeded();
+                return Ok(Some(stream));
+            }
+            Err(e) => warn!(error = %e, "reconnection failed"),
+        }
+    }
+}
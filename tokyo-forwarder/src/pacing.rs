@@ -0,0 +1,221 @@
+// Pacing of forwarded events (--max-rate)
+//
+// Exchange microbursts can overflow receiver socket buffers. With pacing on,
+// serialized events wait in a bounded queue and are released by a token bucket;
+// when the queue is full the oldest event is dropped, since it is the stalest.
+
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+/// Pacing settings from the command line
+#[derive(Debug, Clone, Copy)]
+pub struct PacingConfig {
+    pub max_rate: f64, // Events per second
+    pub burst: u32,    // Events that may be sent back to back
+    pub queue: usize,  // Events that may wait before the oldest is dropped
+}
+
+/// Token bucket refilled continuously at `rate` tokens per second
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: u32, now: Instant) -> Self {
+        Self {
+            rate,
+            burst: burst.into(),
+            tokens: burst.into(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.updated = now;
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// When the next token becomes available
+    fn next_token(&self) -> Instant {
+        let missing = (1.0 - self.tokens).max(0.0);
+        self.updated + Duration::from_secs_f64(missing / self.rate)
+    }
+}
+
+/// A serialized event waiting for a token
+#[derive(Debug)]
+pub struct Queued {
+    pub sequence_id: u64,
+    pub json: String,
+    pub received: i64, // Frame receive time (epoch nanos), for the forwarding overhead
+    queued_at: Instant,
+}
+
+/// Bounded drop-oldest queue drained by a token bucket
+#[derive(Debug)]
+pub struct Pacer {
+    bucket: TokenBucket,
+    queue: VecDeque<Queued>,
+    capacity: usize,
+}
+
+impl Pacer {
+    pub fn new(config: PacingConfig) -> Self {
+        Self {
+            bucket: TokenBucket::new(config.max_rate, config.burst, Instant::now()),
+            queue: VecDeque::with_capacity(config.queue),
+            capacity: config.queue,
+        }
+    }
+
+    /// Queue an event. Returns the sequence ID of the event dropped to make room, if any.
+    pub fn push(
+        &mut self,
+        sequence_id: u64,
+        json: String,
+        received: i64,
+        now: Instant,
+    ) -> Option<u64> {
+        let dropped = if self.queue.len() >= self.capacity {
+            self.queue.pop_front().map(|oldest| oldest.sequence_id)
+        } else {
+            None
+        };
+        self.queue.push_back(Queued {
+            sequence_id,
+            json,
+            received,
+            queued_at: now,
+        });
+        dropped
+    }
+
+    /// The oldest queued event and how long it waited, if a token is
+    /// available for it at `now`
+    pub fn pop_ready(&mut self, now: Instant) -> Option<(Queued, Duration)> {
+        if self.queue.is_empty() || !self.bucket.try_take(now) {
+            return None;
+        }
+        let event = self.queue.pop_front()?;
+        let waited = now.saturating_duration_since(event.queued_at);
+        Some((event, waited))
+    }
+
+    /// When the next queued event can be sent, or `None` if nothing is queued
+    pub fn next_due(&self) -> Option<Instant> {
+        (!self.queue.is_empty()).then(|| self.bucket.next_token())
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paced(max_rate: f64, burst: u32, queue: usize) -> (Pacer, Instant) {
+        let pacer = Pacer::new(PacingConfig {
+            max_rate,
+            burst,
+            queue,
+        });
+        let start = pacer.bucket.updated;
+        (pacer, start)
+    }
+
+    /// Queue events `ids` at `now`, returning the sequence IDs dropped
+    fn push_all(pacer: &mut Pacer, ids: std::ops::Range<u64>, now: Instant) -> Vec<u64> {
+        ids.filter_map(|id| pacer.push(id, id.to_string(), 0, now))
+            .collect()
+    }
+
+    fn pop_all(pacer: &mut Pacer, now: Instant) -> Vec<u64> {
+        std::iter::from_fn(|| pacer.pop_ready(now))
+            .map(|(event, _)| event.sequence_id)
+            .collect()
+    }
+
+    #[test]
+    fn tokens_refill_with_elapsed_time() {
+        let (mut pacer, start) = paced(10.0, 1, 100);
+        push_all(&mut pacer, 0..5, start);
+        assert_eq!(pop_all(&mut pacer, start), [0]);
+
+        // One token per 100 ms
+        assert_eq!(pacer.next_due(), Some(start + Duration::from_millis(100)));
+        assert!(pop_all(&mut pacer, start + Duration::from_millis(99)).is_empty());
+        assert_eq!(pop_all(&mut pacer, start + Duration::from_millis(100)), [1]);
+        let (event, waited) = pacer.pop_ready(start + Duration::from_millis(200)).unwrap();
+        assert_eq!(event.sequence_id, 2);
+        assert_eq!(waited, Duration::from_millis(200));
+    }
+
+    #[test]
+    fn tokens_accumulate_up_to_the_burst() {
+        let (mut pacer, start) = paced(10.0, 3, 100);
+        push_all(&mut pacer, 0..10, start);
+        assert_eq!(pop_all(&mut pacer, start), [0, 1, 2]);
+
+        // A long idle period still only allows `burst` events back to back
+        assert_eq!(
+            pop_all(&mut pacer, start + Duration::from_secs(60)),
+            [3, 4, 5]
+        );
+        assert_eq!(pacer.len(), 4);
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest() {
+        let (mut pacer, start) = paced(1.0, 1, 3);
+        let dropped = push_all(&mut pacer, 0..5, start);
+        assert_eq!(dropped, [0, 1]);
+        assert_eq!(pacer.len(), 3);
+        assert_eq!(pop_all(&mut pacer, start), [2]);
+
+        // Sending made room, so the next event is queued without a drop
+        assert!(push_all(&mut pacer, 5..6, start).is_empty());
+        assert_eq!(push_all(&mut pacer, 6..7, start), [3]);
+    }
+
+    #[test]
+    fn limits() {
+        // Nothing queued, nothing due, and no token spent
+        let (mut pacer, start) = paced(1.0, 1, 1);
+        assert_eq!(pacer.next_due(), None);
+        assert!(pacer.pop_ready(start).is_none());
+        push_all(&mut pacer, 0..1, start);
+        assert_eq!(pop_all(&mut pacer, start), [0]);
+
+        // A queue of one keeps only the newest event
+        assert_eq!(push_all(&mut pacer, 1..4, start), [1, 2]);
+        assert_eq!(pacer.len(), 1);
+
+        // Time going backwards refills nothing
+        assert!(pop_all(&mut pacer, start - Duration::from_secs(1)).is_empty());
+        assert_eq!(pop_all(&mut pacer, start + Duration::from_secs(1)), [3]);
+
+        // A very high rate is due right away after every event
+        let (mut pacer, start) = paced(1e9, 1, 10);
+        push_all(&mut pacer, 0..10, start);
+        assert_eq!(pop_all(&mut pacer, start), [0]);
+        assert!(pacer.next_due().unwrap() <= start + Duration::from_nanos(1));
+        assert_eq!(pop_all(&mut pacer, start + Duration::from_micros(1)), [1]);
+    }
+}
@@ -0,0 +1,258 @@
+// Per-receiver send queue with watermarks (--send-queue)
+//
+// Normally each event is written to every receiver before the next frame is
+// read, so a stalled TCP connection stalls the whole forwarder. With a send
+// queue each receiver is written to by its own task from a bounded queue; the
+// forwarder only waits when a queue is full. Every event carries how long it
+// waited in the queue (`send_queue_delay_ns`), so the receiver can tell queuing
+// in Tokyo from delay on the path. Crossing the high watermark opens an
+// episode, logged and kept until the depth is back at the low watermark.
+
+use crate::transport::ReceiverSender;
+use crate::Counters;
+use chrono::Utc;
+use serde::Serialize;
+use shared::ForwardedEvent;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Send queue settings from the command line
+#[derive(Debug, Clone, Copy)]
+pub struct SendQueueConfig {
+    pub capacity: usize, // Events per receiver before the forwarder waits
+    pub high: usize,     // Depth that opens a watermark episode
+    pub low: usize,      // Depth that closes it
+}
+
+impl SendQueueConfig {
+    /// Watermarks default to three quarters and a quarter of the capacity
+    pub fn new(capacity: usize, high: Option<usize>, low: Option<usize>) -> Result<Self, String> {
+        let high = high.unwrap_or((capacity * 3 / 4).max(1));
+        let low = low.unwrap_or(capacity / 4);
+        if capacity == 0 {
+            return Err("--send-queue must be at least 1".to_string());
+        }
+        if high > capacity || low >= high {
+            return Err(format!(
+                "send queue watermarks must satisfy low < high <= capacity (low {}, high {}, capacity {})",
+                low, high, capacity
+            ));
+        }
+        Ok(Self {
+            capacity,
+            high,
+            low,
+        })
+    }
+}
+
+/// Time a receiver's queue spent at or above the high watermark
+#[derive(Debug, Clone, Serialize)]
+pub struct WatermarkEpisode {
+    pub receiver: String, // ip:port
+    pub start: String,    // RFC 3339
+    pub duration_ms: f64,
+    pub max_depth: usize,
+}
+
+/// Send queue activity, summed over receivers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SendQueueStats {
+    pub depth: usize, // Queued right now
+    pub enqueued: u64,
+    pub full: u64, // Times the forwarder waited for room in a queue
+    pub max_depth: usize,
+    pub max_delay_ms: f64,
+    pub episodes: Vec<WatermarkEpisode>, // Closed episodes, oldest first
+}
+
+/// An episode still open
+#[derive(Debug)]
+struct Episode {
+    started: Instant,
+    start: String,
+    max_depth: usize,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    depth: usize, // Queued, plus the event being written
+    episode: Option<Episode>,
+}
+
+#[derive(Debug)]
+struct Queued {
+    sequence_id: u64,
+    json: String,
+    enqueued: Instant,
+}
+
+/// A bounded queue in front of one receiver, drained by its own task
+pub struct SendQueue {
+    addr: String,
+    config: SendQueueConfig,
+    tx: Option<mpsc::Sender<Queued>>,
+    state: Arc<Mutex<QueueState>>,
+    counters: Arc<Counters>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl SendQueue {
+    /// Hand `sender` to a task that writes whatever is queued for it. Its
+    /// reconnect and retry buffer counts are recorded when the queue closes.
+    pub fn spawn(sender: ReceiverSender, config: SendQueueConfig, counters: Arc<Counters>) -> Self {
+        let (tx, rx) = mpsc::channel(config.capacity);
+        let state = Arc::new(Mutex::new(QueueState {
+            depth: 0,
+            episode: None,
+        }));
+        let addr = sender.addr().to_string();
+        let task = tokio::spawn(drain(sender, rx, config, state.clone(), counters.clone()));
+        Self {
+            addr,
+            config,
+            tx: Some(tx),
+            state,
+            counters,
+            task: Some(task),
+        }
+    }
+
+    /// Queue one serialized event, waiting while the queue is full
+    pub async fn push(&mut self, sequence_id: u64, json: &str) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let queued = Queued {
+            sequence_id,
+            json: json.to_string(),
+            enqueued: Instant::now(),
+        };
+        // Counted before sending so the drain task never sees a depth below zero
+        self.enqueued();
+        let result = match tx.try_send(queued) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(queued)) => {
+                let full = {
+                    let mut stats = self.counters.send_queue.lock().unwrap();
+                    stats.full += 1;
+                    stats.full
+                };
+                if full == 1 {
+                    warn!(
+                        addr = %self.addr,
+                        capacity = self.config.capacity,
+                        "send queue full, waiting for the receiver"
+                    );
+                }
+                tx.send(queued).await.map_err(drop)
+            }
+            Err(TrySendError::Closed(_)) => Err(()),
+        };
+        if result.is_err() {
+            self.state.lock().unwrap().depth -= 1;
+            self.counters.send_queue.lock().unwrap().depth -= 1;
+            self.counters.send_failures.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn enqueued(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.depth += 1;
+        let depth = state.depth;
+        {
+            let mut stats = self.counters.send_queue.lock().unwrap();
+            stats.depth += 1;
+            stats.enqueued += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+        }
+        match &mut state.episode {
+            Some(episode) => episode.max_depth = episode.max_depth.max(depth),
+            None if depth >= self.config.high => {
+                warn!(
+                    addr = %self.addr,
+                    depth,
+                    high_watermark = self.config.high,
+                    "send queue above high watermark"
+                );
+                state.episode = Some(Episode {
+                    started: Instant::now(),
+                    start: Utc::now().to_rfc3339(),
+                    max_depth: depth,
+                });
+            }
+            None => {}
+        }
+    }
+
+    /// Stop accepting events and wait until the queued ones are written
+    pub async fn close(&mut self) {
+        self.tx = None;
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Write queued events to `sender` until the queue is closed and empty
+async fn drain(
+    mut sender: ReceiverSender,
+    mut rx: mpsc::Receiver<Queued>,
+    config: SendQueueConfig,
+    state: Arc<Mutex<QueueState>>,
+    counters: Arc<Counters>,
+) {
+    let mut json = Vec::new();
+    while let Some(queued) = rx.recv().await {
+        let delay = queued.enqueued.elapsed();
+        json.clear();
+        json.extend_from_slice(queued.json.as_bytes());
+        ForwardedEvent::append_send_queue_delay(&mut json, delay.as_nanos() as i64);
+        let text = std::str::from_utf8(&json).expect("serde_json writes UTF-8");
+        if let Err(e) = sender.send(queued.sequence_id, text).await {
+            counters.send_failures.fetch_add(1, Ordering::SeqCst);
+            warn!(
+                sequence_id = queued.sequence_id,
+                region = sender.region(),
+                error = %e,
+                "failed to send event"
+            );
+        }
+        {
+            let mut stats = counters.send_queue.lock().unwrap();
+            stats.depth -= 1;
+            stats.max_delay_ms = stats.max_delay_ms.max(delay.as_secs_f64() * 1000.0);
+        }
+
+        let mut state = state.lock().unwrap();
+        state.depth -= 1;
+        if state.depth <= config.low {
+            if let Some(episode) = state.episode.take() {
+                let duration_ms = episode.started.elapsed().as_secs_f64() * 1000.0;
+                info!(
+                    addr = sender.addr(),
+                    duration_ms,
+                    max_depth = episode.max_depth,
+                    "send queue back at low watermark"
+                );
+                counters
+                    .send_queue
+                    .lock()
+                    .unwrap()
+                    .episodes
+                    .push(WatermarkEpisode {
+                        receiver: sender.addr().to_string(),
+                        start: episode.start,
+                        duration_ms,
+                        max_depth: episode.max_depth,
+                    });
+            }
+        }
+    }
+    counters.record_sender(&sender);
+}
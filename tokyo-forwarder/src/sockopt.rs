@@ -0,0 +1,229 @@
+// Socket options for the UDP and TCP send paths (Linux only)
+
+use serde::Serialize;
+use std::fmt;
+use tokio::net::{TcpStream, UdpSocket};
+
+/// Options for every socket the forwarder sends to receivers from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SocketOptions {
+    pub udp: UdpOptions,
+    pub tcp: TcpOptions, // TCP and WSS connections
+}
+
+/// Options applied to every UDP socket the forwarder sends from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UdpOptions {
+    pub send_buffer: Option<usize>, // SO_SNDBUF in bytes
+    pub tos: Option<u8>,            // IP TOS byte (DSCP << 2 | ECN)
+    pub dont_fragment: bool,        // Set DF; oversized datagrams fail instead of fragmenting
+}
+
+impl UdpOptions {
+    pub fn is_default(&self) -> bool {
+        self.send_buffer.is_none() && self.tos.is_none() && !self.dont_fragment
+    }
+
+    /// Apply the options to `socket` and describe the effective settings
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, socket: &UdpSocket) -> std::io::Result<String> {
+        use std::os::fd::AsRawFd;
+
+        let fd = socket.as_raw_fd();
+        let mut applied = Vec::new();
+
+        if let Some(bytes) = self.send_buffer {
+            let requested = libc::c_int::try_from(bytes).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "send buffer too large")
+            })?;
+            set_int(fd, libc::SOL_SOCKET, libc::SO_SNDBUF, requested)?;
+            // The kernel doubles the value for bookkeeping overhead and caps it at wmem_max
+            let effective = get_int(fd, libc::SOL_SOCKET, libc::SO_SNDBUF)?;
+            applied.push(format!("SO_SNDBUF {} (effective {})", bytes, effective));
+        }
+        if let Some(tos) = self.tos {
+            set_int(fd, libc::IPPROTO_IP, libc::IP_TOS, tos.into())?;
+            applied.push(format!("TOS 0x{:02x} (DSCP {})", tos, tos >> 2));
+        }
+        if self.dont_fragment {
+            set_int(
+                fd,
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                libc::IP_PMTUDISC_DO,
+            )?;
+            applied.push("don't fragment".to_string());
+        }
+
+        Ok(applied.join(", "))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _socket: &UdpSocket) -> std::io::Result<String> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "UDP socket options require Linux",
+        ))
+    }
+}
+
+/// Options applied to every TCP connection to a receiver, on each (re)connect
+#[derive(Debug, Clone, Copy)]
+pub struct TcpOptions {
+    pub tos: Option<u8>,            // IP TOS byte (DSCP << 2)
+    pub nodelay: bool,              // TCP_NODELAY; Nagle holds small writes for up to 40 ms
+    pub send_buffer: Option<usize>, // SO_SNDBUF in bytes
+    pub keepalive: Option<u32>,     // Idle seconds before keepalive probes, also the probe interval
+}
+
+impl Default for TcpOptions {
+    fn default() -> Self {
+        Self {
+            tos: None,
+            nodelay: true,
+            send_buffer: None,
+            keepalive: None,
+        }
+    }
+}
+
+/// Socket options in effect on a TCP connection, read back after applying them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TcpSocketInfo {
+    pub nodelay: bool,
+    pub send_buffer_bytes: Option<i32>, // As reported by the kernel (twice the requested size)
+    pub keepalive_secs: Option<i32>,    // Idle time before the first probe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tos: Option<u8>,
+}
+
+impl fmt::Display for TcpSocketInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TCP_NODELAY {}", if self.nodelay { "on" } else { "off" })?;
+        if let Some(bytes) = self.send_buffer_bytes {
+            write!(f, ", SO_SNDBUF {}", bytes)?;
+        }
+        match self.keepalive_secs {
+            Some(secs) => write!(f, ", keepalive {}s", secs)?,
+            None => write!(f, ", keepalive off")?,
+        }
+        if let Some(tos) = self.tos {
+            write!(f, ", TOS 0x{:02x} (DSCP {})", tos, tos >> 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl TcpOptions {
+    pub fn is_default(&self) -> bool {
+        self.tos.is_none() && self.nodelay && self.send_buffer.is_none() && self.keepalive.is_none()
+    }
+
+    /// Apply the options to a connected `stream` and read back the effective settings
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, stream: &TcpStream) -> std::io::Result<TcpSocketInfo> {
+        use std::os::fd::AsRawFd;
+
+        let fd = stream.as_raw_fd();
+        stream.set_nodelay(self.nodelay)?;
+        if let Some(bytes) = self.send_buffer {
+            let requested = libc::c_int::try_from(bytes).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "send buffer too large")
+            })?;
+            set_int(fd, libc::SOL_SOCKET, libc::SO_SNDBUF, requested)?;
+        }
+        if let Some(secs) = self.keepalive {
+            let secs = libc::c_int::try_from(secs).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "keepalive too long")
+            })?;
+            set_int(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+            set_int(fd, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, secs)?;
+            set_int(fd, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, secs)?;
+        }
+        // Set after connecting, so the handshake itself is unmarked
+        if let Some(tos) = self.tos {
+            set_int(fd, libc::IPPROTO_IP, libc::IP_TOS, tos.into())?;
+        }
+
+        let keepalive = get_int(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE)? != 0;
+        Ok(TcpSocketInfo {
+            nodelay: stream.nodelay()?,
+            send_buffer_bytes: Some(get_int(fd, libc::SOL_SOCKET, libc::SO_SNDBUF)?),
+            keepalive_secs: if keepalive {
+                Some(get_int(fd, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE)?)
+            } else {
+                None
+            },
+            tos: self
+                .tos
+                .map(|_| get_int(fd, libc::IPPROTO_IP, libc::IP_TOS))
+                .transpose()?
+                .map(|tos| tos as u8),
+        })
+    }
+
+    /// Only TCP_NODELAY is portable; the other options require Linux
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, stream: &TcpStream) -> std::io::Result<TcpSocketInfo> {
+        if self.tos.is_some() || self.send_buffer.is_some() || self.keepalive.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TCP socket options other than TCP_NODELAY require Linux",
+            ));
+        }
+        stream.set_nodelay(self.nodelay)?;
+        Ok(TcpSocketInfo {
+            nodelay: stream.nodelay()?,
+            send_buffer_bytes: None,
+            keepalive_secs: None,
+            tos: None,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_int(
+    fd: std::os::fd::RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> std::io::Result<()> {
+    // SAFETY: `fd` is a valid socket and `value` outlives the call
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn get_int(
+    fd: std::os::fd::RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+) -> std::io::Result<libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    // SAFETY: `fd` is a valid socket and `value`/`len` describe a c_int buffer
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(value)
+}
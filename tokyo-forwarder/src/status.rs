@@ -0,0 +1,255 @@
+// What the forwarder itself observed (--status-file)
+//
+// The receivers only see what arrives, so the forwarder keeps its own view:
+// event rate, how far behind the exchange clock frames reach Tokyo, and send
+// failures. The status file is rewritten every interval and once more with the
+// run totals on shutdown.
+
+use crate::chaos::ChaosStats;
+use crate::hot_spare::Cutover;
+use crate::send_queue::SendQueueStats;
+use crate::sockopt::TcpSocketInfo;
+use crate::supervision::SupervisionState;
+use crate::transport::{BlackoutStats, RetryBufferStats};
+use crate::Counters;
+use chrono::Utc;
+use serde::Serialize;
+use shared::{
+    AckStats, ClockMonitor, ClockSample, ConnectionHandover, ExchangeAddress, LatencySummary,
+    PingRttStats, ReconnectStats, StatsAggregator,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tracing::{debug, warn};
+
+/// Exchange → Tokyo latency (local receive time − exchange event time)
+#[derive(Debug, Default)]
+pub struct ExchangeLatency {
+    interval: StatsAggregator, // Since the last status update
+    run: RunLatency,
+}
+
+impl ExchangeLatency {
+    pub fn push(&mut self, latency_ms: f64) {
+        self.interval.push(latency_ms);
+        self.run.push(latency_ms);
+    }
+
+    pub fn run(&self) -> RunLatency {
+        self.run
+    }
+
+    /// Summary of the current interval, starting the next one
+    fn take_interval(&mut self) -> Option<LatencySummary> {
+        let summary = (!self.interval.is_empty()).then(|| self.interval.summary());
+        self.interval.clear();
+        summary
+    }
+}
+
+/// Exchange latency over the whole run, kept in constant space
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RunLatency {
+    pub count: u64,
+    pub avg_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+impl RunLatency {
+    fn push(&mut self, latency_ms: f64) {
+        if self.count == 0 {
+            self.min_ms = latency_ms;
+            self.max_ms = latency_ms;
+        }
+        self.count += 1;
+        self.avg_ms += (latency_ms - self.avg_ms) / self.count as f64;
+        self.min_ms = self.min_ms.min(latency_ms);
+        self.max_ms = self.max_ms.max(latency_ms);
+    }
+}
+
+/// Contents of the status file
+#[derive(Debug, Serialize)]
+struct ForwarderStatus<'a> {
+    run_id: &'a str,
+    exchange: &'a str,
+    symbol: &'a str,
+    started_at: &'a str, // RFC 3339
+    updated_at: String,  // RFC 3339
+    uptime_secs: u64,
+    finished: bool, // Written on shutdown; rates and latency cover the whole run
+    events_forwarded: u64,
+    events_per_sec: f64, // Over the last interval, or the whole run once finished
+    send_failures: u64,
+    parse_failures: u64,
+    pacing_drops: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exchange_latency: Option<LatencySummary>, // Last interval only
+    exchange_latency_run: RunLatency,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exchange_ping_rtt: Option<PingRttStats>, // With every sample in the final status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exchange_reconnects: Option<ReconnectStats>, // Final status only
+    receiver_reconnects: ReconnectStats, // Updated when a pipeline is torn down
+    retry_buffer: RetryBufferStats,      // Updated when a pipeline is torn down
+    receiver_blackouts: BlackoutStats,   // Updated when a pipeline is torn down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    receiver_ack: Option<AckStats>, // Round trip and clock offset from --ack-port, over the run so far
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    tcp_socket_options: BTreeMap<String, TcpSocketInfo>, // By receiver address, as read back from the kernel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    send_queue: Option<SendQueueStats>, // Current and peak depth, watermark episodes (--send-queue)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chaos: Option<ChaosStats>, // What --chaos injected, to compare with the receiver's counts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exchange_handovers: Vec<ConnectionHandover>, // Rollovers and 24-hour resets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exchange_addresses: Vec<ExchangeAddress>, // Where every exchange connection landed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exchange_cutovers: Vec<Cutover>, // Switches to the --hot-spare connection and how long the primary stalled
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    clock_error: Vec<ClockSample>, // --clock-sync readings so far
+    supervision: SupervisionState, // Restarts, panics and session uptimes, across processes with --state-file
+}
+
+/// Periodically samples the counters and writes the status file
+#[derive(Debug, Clone)]
+pub struct StatusReporter {
+    path: Option<String>,
+    run_id: String,
+    exchange: String,
+    symbol: String,
+    started: Instant,
+    started_at: String,
+    last: (Instant, u64), // Time and events forwarded at the previous update
+}
+
+impl StatusReporter {
+    pub fn new(path: Option<String>, run_id: &str, exchange: &str, symbol: &str) -> Self {
+        let started = Instant::now();
+        Self {
+            path,
+            run_id: run_id.to_string(),
+            exchange: exchange.to_string(),
+            symbol: symbol.to_string(),
+            started,
+            started_at: Utc::now().to_rfc3339(),
+            last: (started, 0),
+        }
+    }
+
+    /// Update every `period` until aborted; the status file is only written
+    /// when a path was given, but the interval statistics are always reset
+    pub fn spawn(mut self, counters: Arc<Counters>, period: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.update(&counters);
+            }
+        })
+    }
+
+    fn update(&mut self, counters: &Counters) {
+        let now = Instant::now();
+        let forwarded = counters.next_sequence_id.load(Ordering::SeqCst);
+        let elapsed = now.duration_since(self.last.0).as_secs_f64();
+        let events_per_sec = (forwarded - self.last.1) as f64 / elapsed.max(f64::EPSILON);
+        self.last = (now, forwarded);
+
+        let latency = counters.exchange_latency.lock().unwrap().take_interval();
+        debug!(
+            events_per_sec,
+            exchange_latency_median_ms = latency.map(|l| l.median_ms),
+            "forwarder status"
+        );
+        let status = self.status(counters, events_per_sec, latency, None);
+        self.write(&status);
+        counters.supervisor.save(forwarded);
+    }
+
+    /// Write the run totals as the final status
+    pub fn finish(&self, counters: &Counters, exchange_reconnects: Option<ReconnectStats>) {
+        let uptime = self.started.elapsed().as_secs_f64();
+        let forwarded = counters.next_sequence_id.load(Ordering::SeqCst);
+        let events_per_sec = forwarded as f64 / uptime.max(f64::EPSILON);
+        let status = self.status(counters, events_per_sec, None, exchange_reconnects);
+        self.write(&ForwarderStatus {
+            finished: true,
+            exchange_ping_rtt: counters.exchange_ping.lock().unwrap().stats(true),
+            ..status
+        });
+        counters.supervisor.save(forwarded);
+    }
+
+    fn status(
+        &self,
+        counters: &Counters,
+        events_per_sec: f64,
+        exchange_latency: Option<LatencySummary>,
+        exchange_reconnects: Option<ReconnectStats>,
+    ) -> ForwarderStatus<'_> {
+        ForwarderStatus {
+            run_id: &self.run_id,
+            exchange: &self.exchange,
+            symbol: &self.symbol,
+            started_at: &self.started_at,
+            updated_at: Utc::now().to_rfc3339(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            finished: false,
+            events_forwarded: counters.next_sequence_id.load(Ordering::SeqCst),
+            events_per_sec,
+            send_failures: counters.send_failures.load(Ordering::SeqCst),
+            parse_failures: counters.parse_failures.load(Ordering::SeqCst),
+            pacing_drops: counters.pacing_drops.load(Ordering::SeqCst),
+            exchange_latency,
+            exchange_latency_run: counters.exchange_latency.lock().unwrap().run(),
+            exchange_ping_rtt: counters.exchange_ping.lock().unwrap().stats(false),
+            exchange_reconnects,
+            receiver_reconnects: *counters.receiver_reconnects.lock().unwrap(),
+            retry_buffer: *counters.retry_buffer.lock().unwrap(),
+            receiver_blackouts: *counters.receiver_blackouts.lock().unwrap(),
+            receiver_ack: counters.receiver_acks.lock().unwrap().stats(),
+            tcp_socket_options: counters.tcp_sockets.lock().unwrap().clone(),
+            send_queue: Some(counters.send_queue.lock().unwrap().clone())
+                .filter(|stats| stats.enqueued > 0),
+            chaos: *counters.chaos.lock().unwrap(),
+            exchange_handovers: counters.exchange_handovers.lock().unwrap().clone(),
+            exchange_addresses: counters.exchange_addresses.snapshot(),
+            exchange_cutovers: counters.exchange_cutovers.lock().unwrap().clone(),
+            clock_error: counters
+                .clock
+                .get()
+                .map(ClockMonitor::samples)
+                .unwrap_or_default(),
+            supervision: counters
+                .supervisor
+                .state(counters.next_sequence_id.load(Ordering::SeqCst)),
+        }
+    }
+
+    fn write(&self, status: &ForwarderStatus) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = write_atomic(path, status) {
+            warn!(path = %path, error = %e, "failed to write status file");
+        }
+    }
+}
+
+/// Write through a temporary file so readers never see a partial status
+fn write_atomic(path: &str, status: &ForwarderStatus) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(status)?;
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, Path::new(path))
+}
@@ -0,0 +1,238 @@
+// Restart history of the forwarder (--state-file)
+//
+// The outer loop restarts the forwarder after a failure it can recover from,
+// and a process manager restarts the process when it dies. The receivers only
+// see a gap either way, so the forwarder counts its restarts and panics,
+// keeps the uptime of every session between them and the events forwarded
+// over all of them. With --state-file that history is saved and picked up by
+// the next process. The status file and every heartbeat report it.
+
+use crate::Counters;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use shared::ForwarderSupervision;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Sessions kept in the state file, most recent last
+const MAX_SESSIONS: usize = 100;
+
+/// One run of the forwarder between (re)starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub started_at: String, // RFC 3339
+    pub uptime_secs: f64,
+    pub events_forwarded: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // What ended it, if it failed
+}
+
+/// Contents of the state file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SupervisionState {
+    pub process_starts: u64,
+    pub restarts: u64, // After a failure within a process
+    pub panics: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<String>, // RFC 3339
+    pub events_forwarded: u64, // Over every session
+    pub sessions: Vec<Session>,
+}
+
+#[derive(Debug)]
+struct Current {
+    state: SupervisionState,
+    session: Option<(Instant, u64)>, // Start and events forwarded by this process then
+    events_before: u64,              // `state.events_forwarded` when the current session started
+    sessions_started: u64,           // By this process
+}
+
+/// Keeps the restart history, and the state file with --state-file
+#[derive(Debug)]
+pub struct Supervisor {
+    path: Option<String>,
+    current: Mutex<Current>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::load(None)
+    }
+}
+
+impl Supervisor {
+    /// Continue the history saved at `path`, if there is one, and count this
+    /// process start
+    pub fn load(path: Option<&str>) -> Self {
+        let mut state = path.map(read_state).unwrap_or_default();
+        state.process_starts += 1;
+        if state.process_starts > 1 {
+            info!(
+                process_starts = state.process_starts,
+                restarts = state.restarts,
+                panics = state.panics,
+                last_error = state.last_error.as_deref(),
+                "continuing the forwarder's restart history"
+            );
+        }
+        let supervisor = Self {
+            path: path.map(str::to_string),
+            current: Mutex::new(Current {
+                events_before: state.events_forwarded,
+                state,
+                session: None,
+                sessions_started: 0,
+            }),
+        };
+        supervisor.save(0);
+        supervisor
+    }
+
+    /// Start a session; every one after the first in a process is a restart.
+    /// `forwarded` counts the events this process forwarded so far.
+    pub fn start_session(&self, forwarded: u64) {
+        let mut current = self.current.lock().unwrap();
+        if current.sessions_started > 0 {
+            current.state.restarts += 1;
+        }
+        current.sessions_started += 1;
+        current.session = Some((Instant::now(), forwarded));
+        current.events_before = current.state.events_forwarded;
+        let sessions = &mut current.state.sessions;
+        sessions.push(Session {
+            started_at: Utc::now().to_rfc3339(),
+            uptime_secs: 0.0,
+            events_forwarded: 0,
+            error: None,
+        });
+        if sessions.len() > MAX_SESSIONS {
+            sessions.drain(..sessions.len() - MAX_SESSIONS);
+        }
+        drop(current);
+        self.save(forwarded);
+    }
+
+    /// End the current session, with the error that ended it if it failed
+    pub fn end_session(&self, forwarded: u64, error: Option<String>) {
+        let mut current = self.current.lock().unwrap();
+        current.update(forwarded);
+        current.session = None;
+        if let Some(error) = error {
+            current.record_error(error.clone());
+            if let Some(session) = current.state.sessions.last_mut() {
+                session.error = Some(error);
+            }
+        }
+        drop(current);
+        self.save(forwarded);
+    }
+
+    /// Count a panic. Called from the panic hook, so a panic while the
+    /// history is locked is not counted rather than deadlocking.
+    pub fn record_panic(&self, forwarded: u64, message: String) {
+        let Ok(mut current) = self.current.try_lock() else {
+            return;
+        };
+        current.state.panics += 1;
+        current.record_error(format!("panic: {}", message));
+        drop(current);
+        self.save(forwarded);
+    }
+
+    /// The history as heartbeats report it
+    pub fn report(&self, forwarded: u64) -> ForwarderSupervision {
+        let mut current = self.current.lock().unwrap();
+        current.update(forwarded);
+        let state = &current.state;
+        ForwarderSupervision {
+            process_starts: state.process_starts,
+            restarts: state.restarts,
+            panics: state.panics,
+            last_error: state.last_error.clone(),
+            session_uptime_secs: current
+                .session
+                .map_or(0.0, |(started, _)| started.elapsed().as_secs_f64()),
+            events_forwarded: state.events_forwarded,
+        }
+    }
+
+    /// The full history, sessions included, for the status file
+    pub fn state(&self, forwarded: u64) -> SupervisionState {
+        let mut current = self.current.lock().unwrap();
+        current.update(forwarded);
+        current.state.clone()
+    }
+
+    /// Rewrite the state file, if there is one
+    pub fn save(&self, forwarded: u64) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let state = self.state(forwarded);
+        if let Err(e) = write_atomic(path, &state) {
+            warn!(path = %path, error = %e, "failed to write state file");
+        }
+    }
+}
+
+impl Current {
+    /// Bring the current session's uptime and event count up to date
+    fn update(&mut self, forwarded: u64) {
+        let Some((started, forwarded_at_start)) = self.session else {
+            return;
+        };
+        let events = forwarded.saturating_sub(forwarded_at_start);
+        self.state.events_forwarded = self.events_before + events;
+        if let Some(session) = self.state.sessions.last_mut() {
+            session.uptime_secs = started.elapsed().as_secs_f64();
+            session.events_forwarded = events;
+        }
+    }
+
+    fn record_error(&mut self, error: String) {
+        self.state.last_error = Some(error);
+        self.state.last_error_at = Some(Utc::now().to_rfc3339());
+    }
+}
+
+/// Count panics in the restart history, then panic as before. Only installed
+/// with --state-file, whose next process keeps the count of one that panicked.
+pub fn install_panic_hook(counters: Arc<Counters>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let forwarded = counters.next_sequence_id.load(Ordering::SeqCst);
+        counters
+            .supervisor
+            .record_panic(forwarded, info.to_string());
+        previous(info);
+    }));
+}
+
+/// The saved history, or a fresh one if there is none or it cannot be read
+fn read_state(path: &str) -> SupervisionState {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return SupervisionState::default(),
+        Err(e) => {
+            warn!(path = %path, error = %e, "cannot read state file, starting a new history");
+            return SupervisionState::default();
+        }
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        warn!(path = %path, error = %e, "invalid state file, starting a new history");
+        SupervisionState::default()
+    })
+}
+
+/// Write through a temporary file so a crash never leaves a partial state
+fn write_atomic(path: &str, state: &SupervisionState) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(state)?;
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, Path::new(path))
+}
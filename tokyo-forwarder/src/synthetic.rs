@@ -0,0 +1,69 @@
+// Synthetic events for transport stress tests (--synthetic-rate)
+//
+// Instead of connecting to the exchange, the forwarder makes up Binance
+// futures bookTicker frames stamped with the current time and forwards them at
+// a fixed rate, optionally raised every second, so the backbone and the
+// receiver can be pushed to saturation whatever the market is doing.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+/// Generator settings from the command line
+#[derive(Debug, Clone, Copy)]
+pub struct SyntheticConfig {
+    pub rate: f64, // Events per second at the start
+    pub ramp: f64, // Events per second added every second, 0 for a fixed rate
+}
+
+/// Schedules and builds the synthetic frames
+pub struct SyntheticFeed {
+    config: SyntheticConfig,
+    symbol: String, // Binance form, e.g. BTCUSDT
+    started: Instant,
+    next_due: Instant,
+    update_id: i64,
+}
+
+impl SyntheticFeed {
+    pub fn new(config: SyntheticConfig, symbol: &str, now: Instant) -> Self {
+        Self {
+            config,
+            symbol: symbol.replace('-', "").to_uppercase(),
+            started: now,
+            next_due: now,
+            update_id: 0,
+        }
+    }
+
+    /// Time since the first frame was due
+    pub fn elapsed(&self) -> Duration {
+        self.next_due - self.started
+    }
+
+    /// Events per second in the whole second `elapsed` falls into
+    pub fn rate_at(&self, elapsed: Duration) -> f64 {
+        self.config.rate + self.config.ramp * elapsed.as_secs() as f64
+    }
+
+    /// When the next frame should go out. Frames that fall behind are sent
+    /// back to back, so the schedule, not the sender, sets the rate.
+    pub fn next_due(&self) -> Instant {
+        self.next_due
+    }
+
+    /// The frame that was due, stamped with the current time
+    pub fn next_frame(&mut self) -> String {
+        let rate = self.rate_at(self.next_due - self.started);
+        self.next_due += Duration::from_secs_f64(1.0 / rate);
+        self.update_id += 1;
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        format!(
+            r#"{{"e":"bookTicker","u":{},"E":{},"T":{},"s":"{}","b":"50000.00","B":"1.000","a":"50000.01","A":"1.000"}}"#,
+            self.update_id, now_ms, now_ms, self.symbol
+        )
+    }
+}
@@ -0,0 +1,1060 @@
+// Delivery of forwarded events from Tokyo to the receivers
+
+use crate::sockopt::{SocketOptions, TcpOptions, TcpSocketInfo};
+use crate::Counters;
+use futures_util::SinkExt;
+use serde::Serialize;
+use shared::{
+    fragment, grpc, heartbeat_line, parse_heartbeat_ack, Backoff, ForwardedEvent,
+    ForwardedEventView, ReconnectPolicy, ReconnectStats, TlsClient,
+};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf,
+};
+use tokio::net::{TcpStream, UdpSocket, UnixStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tonic::transport::Endpoint;
+use tracing::{debug, info, warn};
+
+/// How forwarded events are delivered to receivers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    Dual, // Every event over both UDP and TCP; the receiver keeps the first arrival
+    Ws,   // One WebSocket text message per event, without TLS
+    Wss,  // One WebSocket text message per event, always over TLS
+    Uds,  // Newline-delimited JSON over a Unix domain socket, to a receiver on the same host
+    Grpc, // One message per event on a gRPC bidirectional stream, without TLS
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+            Transport::Dual => "dual",
+            Transport::Ws => "ws",
+            Transport::Wss => "wss",
+            Transport::Uds => "uds",
+            Transport::Grpc => "grpc",
+        }
+    }
+
+    fn uses_udp(&self) -> bool {
+        matches!(self, Transport::Udp | Transport::Dual)
+    }
+
+    fn uses_websocket(&self) -> bool {
+        matches!(self, Transport::Ws | Transport::Wss)
+    }
+
+    /// Whether events go out as lines over a stream: TCP, or a Unix socket
+    fn uses_lines(&self) -> bool {
+        matches!(self, Transport::Tcp | Transport::Dual | Transport::Uds)
+    }
+
+    /// Whether this transport cannot run without TLS
+    pub fn requires_tls(&self) -> bool {
+        matches!(self, Transport::Wss)
+    }
+}
+
+impl FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "udp" => Ok(Transport::Udp),
+            "tcp" => Ok(Transport::Tcp),
+            "dual" => Ok(Transport::Dual),
+            "ws" => Ok(Transport::Ws),
+            "wss" => Ok(Transport::Wss),
+            "uds" => Ok(Transport::Uds),
+            "grpc" => Ok(Transport::Grpc),
+            other => Err(format!("unknown transport: {}", other)),
+        }
+    }
+}
+
+/// A receiver endpoint, labelled with the region it runs in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub region: String,
+    pub addr: String, // ip:port
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parse `region:ip:port`, e.g. `fra:10.1.1.10:8080`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (region, addr) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected region:ip:port, got {}", s))?;
+        let (ip, port) = addr
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected region:ip:port, got {}", s))?;
+        if region.is_empty() || ip.is_empty() || port.parse::<u16>().is_err() {
+            return Err(format!("expected region:ip:port, got {}", s));
+        }
+
+        Ok(Target {
+            region: region.to_string(),
+            addr: addr.to_string(),
+        })
+    }
+}
+
+/// How dropped TCP, WebSocket and gRPC connections are recovered
+#[derive(Debug, Clone, Copy)]
+pub struct Recovery {
+    pub reconnect: ReconnectPolicy, // When to redial
+    pub retry_buffer: usize,        // Events held meanwhile
+    pub liveness: Liveness,         // How a dead TCP connection is noticed
+}
+
+/// Dead-peer detection on the TCP path, beyond the kernel's own timeouts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Liveness {
+    pub heartbeat: Option<Duration>, // Between heartbeats, each due an ack within HEARTBEAT_ACK_INTERVALS
+    pub write_timeout: Option<Duration>, // A write taking longer counts as a dead connection
+}
+
+/// Heartbeat intervals a receiver may take to acknowledge one
+pub const HEARTBEAT_ACK_INTERVALS: u32 = 3;
+
+/// Sends serialized events to one receiver over every path of the configured transport
+pub struct ReceiverSender {
+    region: String,
+    addr: String,
+    udp: Option<UdpSocket>,
+    max_datagram: Option<usize>, // Larger UDP events are sent as fragments
+    fragmented: u64,             // Events sent as fragments
+    tcp: Option<TcpSender>,
+    websocket: Option<WsSender>,
+    grpc: Option<GrpcSender>,
+}
+
+impl ReceiverSender {
+    /// Connect to one receiver. With `tls`, the TCP path is encrypted; the
+    /// wss transport requires it and the ws, uds and grpc transports never use it.
+    /// For uds, the target address is the socket path.
+    /// `max_datagram` applies to the UDP path only. Dropped TCP, WebSocket and
+    /// gRPC connections are redialed according to `recovery`. `hello` opens every
+    /// connection, and the UDP path once.
+    pub async fn connect(
+        transport: Transport,
+        target: &Target,
+        tls: Option<&TlsClient>,
+        sockets: &SocketOptions,
+        max_datagram: Option<usize>,
+        recovery: Recovery,
+        hello: &str,
+    ) -> Result<Self, std::io::Error> {
+        let addr = target.addr.clone();
+
+        let udp = if transport.uses_udp() {
+            let socket = UdpSocket::bind("0.0.0.0:0").await?;
+            info!(addr = %addr, region = %target.region, "UDP socket created");
+            if !sockets.udp.is_default() {
+                let applied = sockets.udp.apply(&socket)?;
+                info!(options = %applied, "UDP socket options applied");
+            }
+            socket.send_to(hello.as_bytes(), &addr).await?;
+            Some(socket)
+        } else {
+            None
+        };
+
+        let tcp = if transport.uses_lines() {
+            let unix = transport == Transport::Uds;
+            let mut sender = TcpSender {
+                addr: addr.clone(),
+                unix,
+                tls: tls.cloned().filter(|_| !unix),
+                options: sockets.tcp,
+                socket: None,
+                stream: None,
+                redial: Redial::new(recovery.reconnect),
+                backlog: RetryBuffer::new(recovery.retry_buffer),
+                line: Vec::new(),
+                hello: hello.to_string(),
+                liveness: recovery.liveness,
+                heartbeats: None,
+                last_alive: Instant::now(),
+                blackouts: BlackoutStats::default(),
+                counters: None,
+            };
+            sender.stream = Some(sender.open().await?);
+            if unix {
+                info!(path = %addr, "Unix socket connection established");
+            } else {
+                info!(
+                    addr = %addr,
+                    region = %target.region,
+                    tls = tls.is_some(),
+                    "TCP connection established"
+                );
+            }
+            Some(sender)
+        } else {
+            None
+        };
+
+        let websocket = if transport.uses_websocket() {
+            let tls = match transport {
+                Transport::Wss => Some(tls.cloned().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "wss transport requires a TLS CA (--tls-ca)",
+                    )
+                })?),
+                _ => None,
+            };
+            let mut sender = WsSender {
+                addr: addr.clone(),
+                tls,
+                options: sockets.tcp,
+                socket: None,
+                ws: None,
+                redial: Redial::new(recovery.reconnect),
+                backlog: RetryBuffer::new(recovery.retry_buffer),
+                hello: hello.to_string(),
+            };
+            sender.ws = Some(sender.open().await?);
+            info!(
+                addr = %addr,
+                region = %target.region,
+                transport = transport.as_str(),
+                "WebSocket connection established"
+            );
+            Some(sender)
+        } else {
+            None
+        };
+
+        let grpc = if transport == Transport::Grpc {
+            let mut sender = GrpcSender {
+                addr: addr.clone(),
+                nodelay: sockets.tcp.nodelay,
+                stream: None,
+                redial: Redial::new(recovery.reconnect),
+                backlog: RetryBuffer::new(recovery.retry_buffer),
+                hello: hello.to_string(),
+            };
+            sender.stream = Some(sender.open().await?);
+            info!(addr = %addr, region = %target.region, "gRPC stream established");
+            Some(sender)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            region: target.region.clone(),
+            addr,
+            udp,
+            max_datagram,
+            fragmented: 0,
+            tcp,
+            websocket,
+            grpc,
+        })
+    }
+
+    /// Report the forwarder's restart history from `counters` with every
+    /// heartbeat on the TCP path
+    pub fn with_supervision(mut self, counters: Arc<Counters>) -> Self {
+        if let Some(tcp) = &mut self.tcp {
+            tcp.counters = Some(counters);
+        }
+        self
+    }
+
+    /// Region label of the receiver this sender delivers to
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Address of the receiver this sender delivers to
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Reconnect activity of the TCP, WebSocket and gRPC paths
+    pub fn reconnect_stats(&self) -> ReconnectStats {
+        let mut stats = ReconnectStats::default();
+        if let Some(tcp) = &self.tcp {
+            stats.add(tcp.redial.backoff.stats());
+        }
+        if let Some(websocket) = &self.websocket {
+            stats.add(websocket.redial.backoff.stats());
+        }
+        if let Some(grpc) = &self.grpc {
+            stats.add(grpc.redial.backoff.stats());
+        }
+        stats
+    }
+
+    /// Effective socket options of the latest TCP or WebSocket connection
+    pub fn tcp_socket(&self) -> Option<TcpSocketInfo> {
+        match (&self.tcp, &self.websocket) {
+            (Some(tcp), _) => tcp.socket,
+            (None, Some(websocket)) => websocket.socket,
+            (None, None) => None,
+        }
+    }
+
+    /// Outages of the TCP path, from when it was last known alive until it was back
+    pub fn blackout_stats(&self) -> BlackoutStats {
+        self.tcp
+            .as_ref()
+            .map_or_else(BlackoutStats::default, |tcp| tcp.blackouts)
+    }
+
+    /// Events sent over UDP as fragments
+    pub fn fragmented(&self) -> u64 {
+        self.fragmented
+    }
+
+    /// Events held back while the TCP, WebSocket or gRPC connection was down
+    pub fn retry_buffer_stats(&self) -> RetryBufferStats {
+        let mut stats = RetryBufferStats::default();
+        if let Some(tcp) = &self.tcp {
+            stats.add(tcp.backlog.stats());
+        }
+        if let Some(websocket) = &self.websocket {
+            stats.add(websocket.backlog.stats());
+        }
+        if let Some(grpc) = &self.grpc {
+            stats.add(grpc.backlog.stats());
+        }
+        stats
+    }
+
+    /// Send one serialized event on every path. All paths are attempted even
+    /// if one fails; the first error is returned.
+    pub async fn send(&mut self, sequence_id: u64, json: &str) -> Result<(), std::io::Error> {
+        let mut result = Ok(());
+
+        if let Some(udp) = &self.udp {
+            let fragments = self
+                .max_datagram
+                .and_then(|max| fragment(sequence_id, json.as_bytes(), max));
+            let sent = match fragments {
+                Some(fragments) => {
+                    self.fragmented += 1;
+                    send_fragments(udp, &fragments, &self.addr).await
+                }
+                None => udp.send_to(json.as_bytes(), &self.addr).await.map(drop),
+            };
+            if let Err(e) = sent {
+                result = Err(e);
+            }
+        }
+
+        if let Some(tcp) = &mut self.tcp {
+            if let Err(e) = tcp.send_line(json).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        if let Some(websocket) = &mut self.websocket {
+            if let Err(e) = websocket.send(json).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        if let Some(grpc) = &mut self.grpc {
+            if let Err(e) = grpc.send(sequence_id, json).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Send every fragment of one event, stopping at the first failure
+async fn send_fragments(
+    udp: &UdpSocket,
+    fragments: &[Vec<u8>],
+    addr: &str,
+) -> Result<(), std::io::Error> {
+    for fragment in fragments {
+        udp.send_to(fragment, addr).await?;
+    }
+    Ok(())
+}
+
+/// When a dropped receiver connection may be dialed again. Until then sends
+/// on that path fail immediately instead of each event opening a connection.
+struct Redial {
+    backoff: Backoff,
+    retry_at: Option<Instant>,
+    gave_up: bool, // The policy's attempt limit was reached
+}
+
+impl Redial {
+    fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            backoff: Backoff::new(policy),
+            retry_at: None,
+            gave_up: false,
+        }
+    }
+
+    fn ready(&self, addr: &str) -> Result<(), std::io::Error> {
+        if self.gave_up {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!("gave up reconnecting to {}", addr),
+            ));
+        }
+        match self.retry_at {
+            Some(at) if Instant::now() < at => Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!("waiting to reconnect to {}", addr),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn failed(&mut self, addr: &str, error: &std::io::Error) {
+        match self.backoff.next_delay() {
+            Some(delay) => {
+                warn!(
+                    addr = %addr,
+                    error = %error,
+                    delay_ms = delay.as_millis() as u64,
+                    breaker_open = self.backoff.breaker_open(),
+                    "receiver connection failed, retrying later"
+                );
+                self.retry_at = Some(Instant::now() + delay);
+            }
+            None => {
+                warn!(addr = %addr, error = %error, "receiver connection failed, giving up");
+                self.gave_up = true;
+            }
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.backoff.succeeded();
+        self.retry_at = None;
+    }
+}
+
+/// Retry buffer activity, summed over connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RetryBufferStats {
+    pub held: u64,       // Events put in the buffer
+    pub flushed: u64,    // Sent after the connection came back
+    pub overflowed: u64, // Oldest events dropped because the buffer was full
+    pub unsent: u64,     // Still in the buffer when the sender was dropped
+}
+
+impl RetryBufferStats {
+    pub fn add(&mut self, other: RetryBufferStats) {
+        self.held += other.held;
+        self.flushed += other.flushed;
+        self.overflowed += other.overflowed;
+        self.unsent += other.unsent;
+    }
+}
+
+impl std::fmt::Display for RetryBufferStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} held, {} flushed, {} dropped when full, {} unsent",
+            self.held, self.flushed, self.overflowed, self.unsent
+        )
+    }
+}
+
+/// Receiver connection outages that ended in a reconnect, summed over connections
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct BlackoutStats {
+    pub blackouts: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+impl BlackoutStats {
+    fn record(&mut self, blackout: Duration) {
+        let ms = blackout.as_secs_f64() * 1000.0;
+        self.blackouts += 1;
+        self.total_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn add(&mut self, other: BlackoutStats) {
+        self.blackouts += other.blackouts;
+        self.total_ms += other.total_ms;
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+}
+
+impl std::fmt::Display for BlackoutStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} blackouts, {:.0} ms in total, longest {:.0} ms",
+            self.blackouts, self.total_ms, self.max_ms
+        )
+    }
+}
+
+/// Events that could not be written while a connection was down, oldest
+/// first, already flagged as buffered. Bounded: when full the oldest event is
+/// dropped. A capacity of zero disables buffering, so failed sends are errors.
+struct RetryBuffer {
+    events: VecDeque<String>,
+    capacity: usize,
+    stats: RetryBufferStats,
+}
+
+impl RetryBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+            stats: RetryBufferStats::default(),
+        }
+    }
+
+    /// Keep `json`, which could not be sent because of `error`, for later.
+    /// Returns the error when buffering is disabled.
+    fn hold(
+        &mut self,
+        addr: &str,
+        json: &str,
+        error: std::io::Error,
+    ) -> Result<(), std::io::Error> {
+        if self.capacity == 0 {
+            return Err(error);
+        }
+        if self.events.is_empty() {
+            info!(addr = %addr, "receiver connection down, buffering events");
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            if self.stats.overflowed == 0 {
+                warn!(addr = %addr, capacity = self.capacity, "retry buffer full, dropping oldest events");
+            }
+            self.stats.overflowed += 1;
+        }
+        self.events.push_back(ForwardedEvent::mark_buffered(json));
+        self.stats.held += 1;
+        Ok(())
+    }
+
+    fn front(&self) -> Option<&str> {
+        self.events.front().map(String::as_str)
+    }
+
+    /// The front event was written
+    fn flushed(&mut self) {
+        self.events.pop_front();
+        self.stats.flushed += 1;
+    }
+
+    fn stats(&self) -> RetryBufferStats {
+        RetryBufferStats {
+            unsent: self.events.len() as u64,
+            ..self.stats
+        }
+    }
+}
+
+/// A TCP connection, a TLS session over one, or a Unix socket
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+type TcpWriter = WriteHalf<Box<dyn Connection>>;
+
+/// Newline-delimited JSON over a TCP connection (optionally TLS) or a Unix
+/// socket that reconnects after write failures, buffering events until it is back
+struct TcpSender {
+    addr: String, // ip:port, or the socket path
+    unix: bool,
+    tls: Option<TlsClient>,
+    options: TcpOptions,
+    socket: Option<TcpSocketInfo>, // Effective options of the latest connection
+    stream: Option<TcpWriter>,
+    redial: Redial,
+    backlog: RetryBuffer,
+    line: Vec<u8>, // Reused for every line written
+    hello: String, // First line on every connection
+    liveness: Liveness,
+    heartbeats: Option<Heartbeats>, // Of the current connection, with --heartbeat-ms
+    last_alive: Instant,            // Latest acknowledged heartbeat, else successful write
+    blackouts: BlackoutStats,
+    counters: Option<Arc<Counters>>, // Restart history sent with every heartbeat
+}
+
+impl TcpSender {
+    async fn open(&mut self) -> Result<TcpWriter, std::io::Error> {
+        let stream: Box<dyn Connection> = if self.unix {
+            Box::new(UnixStream::connect(&self.addr).await?)
+        } else {
+            let (stream, socket) = connect_tcp(&self.addr, &self.options).await?;
+            self.socket = Some(socket);
+            match &self.tls {
+                Some(tls) => Box::new(tls.connect(&self.addr, stream).await?),
+                None => Box::new(stream),
+            }
+        };
+        let (reader, mut writer) = tokio::io::split(stream);
+        self.write(&mut writer, &self.hello.clone()).await?;
+        self.heartbeats = self
+            .liveness
+            .heartbeat
+            .map(|interval| Heartbeats::start(reader, interval));
+        Ok(writer)
+    }
+
+    /// Write one line, within the write deadline if there is one
+    async fn write(&mut self, stream: &mut TcpWriter, json: &str) -> Result<(), std::io::Error> {
+        let written = match self.liveness.write_timeout {
+            Some(deadline) => timeout(deadline, write_line(stream, &mut self.line, json))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "write deadline exceeded",
+                    ))
+                }),
+            None => write_line(stream, &mut self.line, json).await,
+        };
+        if written.is_ok() && self.heartbeats.is_none() {
+            self.last_alive = Instant::now();
+        }
+        written
+    }
+
+    /// Send a heartbeat if one is due. Fails when the receiver has not
+    /// acknowledged the previous one in time.
+    async fn heartbeat(&mut self, stream: &mut TcpWriter) -> Result<(), std::io::Error> {
+        let Some(heartbeats) = &mut self.heartbeats else {
+            return Ok(());
+        };
+        if let Some(acked) = heartbeats.acknowledged() {
+            self.last_alive = acked;
+        }
+        let due = heartbeats.due()?;
+        if let Some(id) = due {
+            let supervision = self.counters.as_ref().map(|c| c.supervision());
+            self.write(stream, &heartbeat_line(id, supervision)).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_line(&mut self, json: &str) -> Result<(), std::io::Error> {
+        // Events are only buffered while there is no connection. An event
+        // whose write failed is sent again on the new one, flagged so the
+        // receiver can tell it apart from live traffic.
+        let mut retransmit = false;
+        if let Some(mut stream) = self.stream.take() {
+            let sent = match self.heartbeat(&mut stream).await {
+                // The connection is dead already; this event was never written
+                Err(e) => Err((e, false)),
+                Ok(()) => self.write(&mut stream, json).await.map_err(|e| (e, true)),
+            };
+            match sent {
+                Ok(()) => {
+                    self.stream = Some(stream);
+                    return Ok(());
+                }
+                Err((e, written)) => {
+                    warn!(addr = %self.addr, error = %e, "TCP write failed, reconnecting");
+                    self.heartbeats = None;
+                    retransmit = written;
+                }
+            }
+        }
+
+        // (Re)connect if the backoff allows, then send the buffered events
+        // in order before this one
+        if let Err(e) = self.redial.ready(&self.addr) {
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        let mut stream = match self.open().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+        };
+        let mut flushed = 0u64;
+        while let Some(buffered) = self.backlog.front() {
+            let buffered = buffered.to_string();
+            if let Err(e) = self.write(&mut stream, &buffered).await {
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+            self.backlog.flushed();
+            flushed += 1;
+        }
+        let sent = if retransmit {
+            Cow::Owned(ForwardedEvent::mark_retransmitted(json))
+        } else {
+            Cow::Borrowed(json)
+        };
+        if let Err(e) = self.write(&mut stream, &sent).await {
+            self.redial.failed(&self.addr, &e);
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        let blackout = self.last_alive.elapsed();
+        self.blackouts.record(blackout);
+        info!(
+            addr = %self.addr,
+            flushed,
+            blackout_ms = blackout.as_millis() as u64,
+            "TCP connection re-established"
+        );
+        self.last_alive = Instant::now();
+        self.redial.succeeded();
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+/// Heartbeats on one TCP connection: at most one is outstanding, and a task
+/// reads the receiver's acknowledgements from the other half of the socket
+struct Heartbeats {
+    interval: Duration,
+    next_id: u64,
+    last_sent: Instant,
+    outstanding: Option<(u64, Instant)>, // Sent, not yet acknowledged
+    acked: Arc<AtomicU64>,               // Highest id acknowledged
+    reader: JoinHandle<()>,
+}
+
+impl Heartbeats {
+    fn start(reader: ReadHalf<Box<dyn Connection>>, interval: Duration) -> Self {
+        let acked = Arc::new(AtomicU64::new(0));
+        let reader = tokio::spawn(read_acks(reader, acked.clone()));
+        Self {
+            interval,
+            next_id: 1,
+            last_sent: Instant::now(),
+            outstanding: None,
+            acked,
+            reader,
+        }
+    }
+
+    /// When the outstanding heartbeat was sent, if it has been acknowledged since
+    fn acknowledged(&mut self) -> Option<Instant> {
+        let (id, sent) = self.outstanding?;
+        if self.acked.load(Ordering::Relaxed) < id {
+            return None;
+        }
+        self.outstanding = None;
+        Some(sent)
+    }
+
+    /// The id of the heartbeat to send now, if one is due
+    fn due(&mut self) -> Result<Option<u64>, std::io::Error> {
+        match self.outstanding {
+            Some((_, sent)) if sent.elapsed() > self.interval * HEARTBEAT_ACK_INTERVALS => {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "receiver stopped acknowledging heartbeats",
+                ))
+            }
+            Some(_) => Ok(None),
+            None if self.last_sent.elapsed() < self.interval => Ok(None),
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.last_sent = Instant::now();
+                self.outstanding = Some((id, self.last_sent));
+                Ok(Some(id))
+            }
+        }
+    }
+}
+
+impl Drop for Heartbeats {
+    fn drop(&mut self) {
+        // Closes the read half along with the connection
+        self.reader.abort();
+    }
+}
+
+/// Record every heartbeat acknowledgement the receiver sends
+async fn read_acks(reader: ReadHalf<Box<dyn Connection>>, acked: Arc<AtomicU64>) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(id) = parse_heartbeat_ack(line.as_bytes()) {
+            acked.fetch_max(id, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Connect to a receiver and apply the TCP socket options
+async fn connect_tcp(
+    addr: &str,
+    options: &TcpOptions,
+) -> Result<(TcpStream, TcpSocketInfo), std::io::Error> {
+    let stream = TcpStream::connect(addr).await?;
+    let socket = options.apply(&stream)?;
+    debug!(addr, options = %socket, "TCP socket options applied");
+    Ok((stream, socket))
+}
+
+/// Write `json` and its newline with one write, assembled in `line`
+async fn write_line(
+    stream: &mut TcpWriter,
+    line: &mut Vec<u8>,
+    json: &str,
+) -> Result<(), std::io::Error> {
+    line.clear();
+    line.extend_from_slice(json.as_bytes());
+    line.push(b'\n');
+    stream.write_all(line).await
+}
+
+/// WebSocket, optionally over TLS, one text message per event, reconnecting
+/// after failures and buffering events until it is back
+struct WsSender {
+    addr: String,
+    tls: Option<TlsClient>, // wss with, ws without
+    options: TcpOptions,
+    socket: Option<TcpSocketInfo>, // Effective options of the latest connection
+    ws: Option<WsStream>,
+    redial: Redial,
+    backlog: RetryBuffer,
+    hello: String, // First message on every connection
+}
+
+type WsStream = WebSocketStream<Box<dyn Connection>>;
+
+impl WsSender {
+    async fn open(&mut self) -> Result<WsStream, std::io::Error> {
+        let (stream, socket) = connect_tcp(&self.addr, &self.options).await?;
+        self.socket = Some(socket);
+        let (stream, url): (Box<dyn Connection>, _) = match &self.tls {
+            Some(tls) => (
+                Box::new(tls.connect(&self.addr, stream).await?),
+                format!("wss://{}/", self.addr),
+            ),
+            None => (Box::new(stream), format!("ws://{}/", self.addr)),
+        };
+        let (mut ws, _) = tokio_tungstenite::client_async(url, stream)
+            .await
+            .map_err(std::io::Error::other)?;
+        send_text(&mut ws, &self.hello).await?;
+        Ok(ws)
+    }
+
+    fn scheme(&self) -> &'static str {
+        if self.tls.is_some() {
+            "wss"
+        } else {
+            "ws"
+        }
+    }
+
+    async fn send(&mut self, json: &str) -> Result<(), std::io::Error> {
+        let mut retransmit = false;
+        if let Some(ws) = &mut self.ws {
+            match send_text(ws, json).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!(addr = %self.addr, transport = self.scheme(), error = %e, "WebSocket send failed, reconnecting");
+                    self.ws = None;
+                    retransmit = true;
+                }
+            }
+        }
+
+        // (Re)connect if the backoff allows, then send the buffered events
+        // in order before this one
+        if let Err(e) = self.redial.ready(&self.addr) {
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        let mut ws = match self.open().await {
+            Ok(ws) => ws,
+            Err(e) => {
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+        };
+        let mut flushed = 0u64;
+        while let Some(buffered) = self.backlog.front() {
+            if let Err(e) = send_text(&mut ws, buffered).await {
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+            self.backlog.flushed();
+            flushed += 1;
+        }
+        let sent = if retransmit {
+            Cow::Owned(ForwardedEvent::mark_retransmitted(json))
+        } else {
+            Cow::Borrowed(json)
+        };
+        if let Err(e) = send_text(&mut ws, &sent).await {
+            self.redial.failed(&self.addr, &e);
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        info!(addr = %self.addr, transport = self.scheme(), flushed, "WebSocket connection re-established");
+        self.redial.succeeded();
+        self.ws = Some(ws);
+        Ok(())
+    }
+}
+
+async fn send_text(ws: &mut WsStream, json: &str) -> Result<(), std::io::Error> {
+    ws.send(Message::Text(json.to_string()))
+        .await
+        .map_err(std::io::Error::other)
+}
+
+/// Messages waiting to go out on a gRPC stream; a send only fails once the
+/// stream has ended, so a small queue keeps writes close to immediate
+const GRPC_STREAM_QUEUE: usize = 64;
+
+/// The sending half of a gRPC stream: messages are queued here and written by tonic
+type GrpcStream = mpsc::Sender<grpc::ForwardedEvent>;
+
+/// A gRPC bidirectional stream, one message per event, reopened after it
+/// ends and buffering events until it is back
+struct GrpcSender {
+    addr: String,
+    nodelay: bool,
+    stream: Option<GrpcStream>,
+    redial: Redial,
+    backlog: RetryBuffer,
+    hello: String, // First message on every stream
+}
+
+impl GrpcSender {
+    async fn open(&mut self) -> Result<GrpcStream, std::io::Error> {
+        let endpoint = Endpoint::from_shared(format!("http://{}", self.addr))
+            .map_err(std::io::Error::other)?
+            .tcp_nodelay(self.nodelay);
+        let mut client = grpc::BackboneClient::connect(endpoint)
+            .await
+            .map_err(std::io::Error::other)?;
+
+        let (tx, rx) = mpsc::channel(GRPC_STREAM_QUEUE);
+        tx.send(grpc::ForwardedEvent::wrap(0, &self.hello))
+            .await
+            .map_err(|_| closed_stream())?;
+        let outbound = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|message| (message, rx))
+        });
+        let mut summaries = client
+            .forward(outbound)
+            .await
+            .map_err(std::io::Error::other)?
+            .into_inner();
+
+        // The receiver only answers once the stream closes; a failure ends
+        // the request stream, which the next send notices
+        let addr = self.addr.clone();
+        tokio::spawn(async move {
+            loop {
+                match summaries.message().await {
+                    Ok(Some(summary)) => {
+                        debug!(addr = %addr, events = summary.events, "gRPC stream summary")
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!(addr = %addr, error = %e, "gRPC stream failed");
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(tx)
+    }
+
+    async fn send(&mut self, sequence_id: u64, json: &str) -> Result<(), std::io::Error> {
+        let mut retransmit = false;
+        if let Some(stream) = &self.stream {
+            match stream
+                .send(grpc::ForwardedEvent::wrap(sequence_id, json))
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(_) => {
+                    warn!(addr = %self.addr, "gRPC stream closed, reconnecting");
+                    self.stream = None;
+                    retransmit = true;
+                }
+            }
+        }
+
+        // (Re)connect if the backoff allows, then send the buffered events
+        // in order before this one
+        if let Err(e) = self.redial.ready(&self.addr) {
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        let stream = match self.open().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+        };
+        let mut flushed = 0u64;
+        while let Some(buffered) = self.backlog.front() {
+            // Buffered events only keep their JSON
+            let buffered_id =
+                ForwardedEventView::parse(buffered.as_bytes()).map_or(0, |event| event.sequence_id);
+            let message = grpc::ForwardedEvent::wrap(buffered_id, buffered);
+            if stream.send(message).await.is_err() {
+                let e = closed_stream();
+                self.redial.failed(&self.addr, &e);
+                return self.backlog.hold(&self.addr, json, e);
+            }
+            self.backlog.flushed();
+            flushed += 1;
+        }
+        let sent = if retransmit {
+            Cow::Owned(ForwardedEvent::mark_retransmitted(json))
+        } else {
+            Cow::Borrowed(json)
+        };
+        if stream
+            .send(grpc::ForwardedEvent::wrap(sequence_id, &sent))
+            .await
+            .is_err()
+        {
+            let e = closed_stream();
+            self.redial.failed(&self.addr, &e);
+            return self.backlog.hold(&self.addr, json, e);
+        }
+        info!(addr = %self.addr, flushed, "gRPC stream re-established");
+        self.redial.succeeded();
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+fn closed_stream() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gRPC stream closed")
+}